@@ -1,6 +1,6 @@
 use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash};
 
-use crate::{Partition, State, BOOT_MAGIC, SWAP_MAGIC};
+use crate::{Partition, State, BOOT_MAGIC, DECOMPRESS_MAGIC, DFU_DETACH_MAGIC, SWAP_MAGIC};
 
 /// Errors returned by bootloader
 #[derive(PartialEq, Eq, Debug)]
@@ -9,6 +9,8 @@ pub enum BootError {
     Flash(NorFlashErrorKind),
     /// Invalid bootloader magic
     BadMagic,
+    /// The compressed image in the DFU partition is corrupt or does not fit in the active partition
+    Decompress,
 }
 
 #[cfg(feature = "defmt")]
@@ -17,6 +19,7 @@ impl defmt::Format for BootError {
         match self {
             BootError::Flash(_) => defmt::write!(fmt, "BootError::Flash(_)"),
             BootError::BadMagic => defmt::write!(fmt, "BootError::BadMagic"),
+            BootError::Decompress => defmt::write!(fmt, "BootError::Decompress"),
         }
     }
 }
@@ -49,6 +52,23 @@ pub trait FlashConfig {
     fn state(&mut self) -> &mut Self::STATE;
 }
 
+/// Streaming decompressor used to install compressed updates, see [`BootLoader::prepare_boot_decompress`].
+///
+/// Implement it for the decompression algorithm used to compress the firmware, e.g. heatshrink or LZ4.
+pub trait Decompressor {
+    /// Error returned for corrupt input.
+    type Error;
+
+    /// Prepares the decompression of a new image.
+    fn reset(&mut self);
+
+    /// Decompresses bytes of `input` into `output`, returning the number of input bytes consumed
+    /// and the number of output bytes produced.
+    ///
+    /// Once the whole image was fed, it is called with an empty `input` until it produces no more output.
+    fn decompress(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), Self::Error>;
+}
+
 trait FlashConfigEx {
     fn page_size() -> u32;
 }
@@ -66,6 +86,7 @@ pub struct BootLoader {
     // All ranges are in multiples of WRITE_SIZE bytes.
     // | Range    | Description                                                                      |
     // | 0..1     | Magic indicating bootloader state. BOOT_MAGIC means boot, SWAP_MAGIC means swap. |
+    // |          | DFU_DETACH_MAGIC means the application requested DFU mode.                       |
     // | 1..2     | Progress validity. ERASE_VALUE means valid, !ERASE_VALUE means invalid.          |
     // | 2..2 + N | Progress index used while swapping or reverting                                  |
     state: Partition,
@@ -221,6 +242,144 @@ impl BootLoader {
         Ok(state)
     }
 
+    /// Perform necessary boot preparations, installing compressed images written with
+    /// [`FirmwareUpdater::mark_updated_compressed`](crate::FirmwareUpdater::mark_updated_compressed).
+    ///
+    /// The DFU partition holds the length of the compressed image as a little endian u32, followed by
+    /// the image. As only the compressed image is stored, the DFU partition can be smaller than the active
+    /// partition. The image is decompressed into the active partition, overwriting the current firmware, so
+    /// there is no rollback if the new firmware fails to mark the boot successful.
+    ///
+    /// The installation is power-fail safe: the DFU partition is left untouched, and an interrupted installation
+    /// decompresses the image again on the next boot, only writing the pages which were not written yet.
+    ///
+    /// Updates marked with [`FirmwareUpdater::mark_updated`](crate::FirmwareUpdater::mark_updated) are
+    /// handled as by [`BootLoader::prepare_boot`], which requires the partition sizes of the swap algorithm.
+    ///
+    /// The provided aligned_buf argument must satisfy any alignment requirements
+    /// given by the partition flashes. All flash operations will use this buffer.
+    pub fn prepare_boot_decompress<P: FlashConfig, D: Decompressor>(
+        &mut self,
+        p: &mut P,
+        aligned_buf: &mut [u8],
+        decompressor: &mut D,
+    ) -> Result<State, BootError> {
+        assert_eq!(0, P::page_size() % aligned_buf.len() as u32);
+        assert_eq!(0, P::page_size() % P::ACTIVE::WRITE_SIZE as u32);
+        assert_eq!(0, P::page_size() % P::ACTIVE::ERASE_SIZE as u32);
+        assert!(aligned_buf.len() >= P::STATE::WRITE_SIZE);
+        assert_eq!(0, aligned_buf.len() % P::ACTIVE::WRITE_SIZE);
+        assert_eq!(0, DECOMPRESS_READ_SIZE % P::DFU::READ_SIZE);
+        assert_eq!(self.active.size() % P::page_size(), 0);
+        assert!(2 + self.active.size() / P::page_size() <= self.state.size() / P::STATE::WRITE_SIZE as u32);
+
+        let state_word = &mut aligned_buf[..P::STATE::WRITE_SIZE];
+        self.state.read_blocking(p.state(), 0, state_word)?;
+        if state_word.iter().any(|&b| b != DECOMPRESS_MAGIC) {
+            return match self.read_state(p, aligned_buf)? {
+                State::Swap => self.prepare_boot(p, aligned_buf),
+                state => Ok(state),
+            };
+        }
+
+        trace!("Decompressing");
+        self.decompress(p, aligned_buf, decompressor)?;
+        trace!("Decompressing done");
+
+        let state_flash = p.state();
+        let state_word = &mut aligned_buf[..P::STATE::WRITE_SIZE];
+
+        // Invalidate progress
+        state_word.fill(!P::STATE_ERASE_VALUE);
+        self.state
+            .write_blocking(state_flash, P::STATE::WRITE_SIZE as u32, state_word)?;
+
+        // Clear magic and progress
+        self.state.wipe_blocking(state_flash)?;
+
+        // Set magic
+        state_word.fill(BOOT_MAGIC);
+        self.state.write_blocking(state_flash, 0, state_word)?;
+
+        Ok(State::Swap)
+    }
+
+    fn decompress<P: FlashConfig, D: Decompressor>(
+        &mut self,
+        p: &mut P,
+        aligned_buf: &mut [u8],
+        decompressor: &mut D,
+    ) -> Result<(), BootError> {
+        let page_size = P::page_size();
+        // Pages written by a previous, interrupted installation
+        let written_pages = self.current_progress(p, aligned_buf)? as u32;
+
+        let mut header = [0; 4];
+        self.dfu.read_blocking(p.dfu(), 0, &mut header)?;
+        let image_end = u32::from_le_bytes(header)
+            .checked_add(4)
+            .filter(|&end| end <= self.dfu.size())
+            .ok_or(BootError::Decompress)?;
+
+        decompressor.reset();
+
+        let mut input = [0; DECOMPRESS_READ_SIZE];
+        let (mut input_start, mut input_end) = (0, 0);
+        let mut read_offset = 4;
+        let mut write_offset = 0;
+        let mut filled = 0;
+        loop {
+            if input_start == input_end && read_offset < image_end {
+                input_end = core::cmp::min(DECOMPRESS_READ_SIZE as u32, image_end - read_offset) as usize;
+                input_start = 0;
+                // The read is extended to a multiple of the read size, the extra bytes are never used
+                let read_len = (input_end + P::DFU::READ_SIZE - 1) / P::DFU::READ_SIZE * P::DFU::READ_SIZE;
+                self.dfu.read_blocking(p.dfu(), read_offset, &mut input[..read_len])?;
+                read_offset += input_end as u32;
+            }
+
+            let (consumed, produced) = decompressor
+                .decompress(&input[input_start..input_end], &mut aligned_buf[filled..])
+                .map_err(|_| BootError::Decompress)?;
+            input_start += consumed;
+            filled += produced;
+
+            let input_done = read_offset == image_end && input_start == input_end;
+            if !input_done && consumed == 0 && produced == 0 {
+                return Err(BootError::Decompress);
+            }
+
+            let done = input_done && produced == 0;
+            if filled == aligned_buf.len() || (done && filled > 0) {
+                if write_offset + aligned_buf.len() as u32 > self.active.size() {
+                    return Err(BootError::Decompress);
+                }
+
+                let page = write_offset / page_size;
+                if page >= written_pages {
+                    if write_offset % page_size == 0 {
+                        self.active
+                            .erase_blocking(p.active(), write_offset, write_offset + page_size)?;
+                    }
+                    // The padding of the last write is never read
+                    aligned_buf[filled..].fill(0xFF);
+                    self.active.write_blocking(p.active(), write_offset, aligned_buf)?;
+                }
+
+                write_offset += aligned_buf.len() as u32;
+                filled = 0;
+
+                if write_offset % page_size == 0 && page >= written_pages {
+                    self.update_progress(page as usize, p, aligned_buf)?;
+                }
+            }
+
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
     fn is_swapped<P: FlashConfig>(&mut self, p: &mut P, aligned_buf: &mut [u8]) -> Result<bool, BootError> {
         let page_count = (self.active.size() / P::page_size()) as usize;
         let progress = self.current_progress(p, aligned_buf)?;
@@ -367,12 +526,17 @@ impl BootLoader {
 
         if !state_word.iter().any(|&b| b != SWAP_MAGIC) {
             Ok(State::Swap)
+        } else if !state_word.iter().any(|&b| b != DFU_DETACH_MAGIC) {
+            Ok(State::DfuDetach)
         } else {
             Ok(State::Boot)
         }
     }
 }
 
+/// Size of the chunks the compressed image is read in
+const DECOMPRESS_READ_SIZE: usize = 32;
+
 fn assert_partitions(active: Partition, dfu: Partition, state: Partition, page_size: u32, state_write_size: usize) {
     assert_eq!(active.size() % page_size, 0);
     assert_eq!(dfu.size() % page_size, 0);
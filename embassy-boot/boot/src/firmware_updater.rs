@@ -3,7 +3,7 @@ use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind};
 #[cfg(feature = "nightly")]
 use embedded_storage_async::nor_flash::NorFlash as AsyncNorFlash;
 
-use crate::{Partition, State, BOOT_MAGIC, SWAP_MAGIC};
+use crate::{Partition, State, BOOT_MAGIC, DFU_DETACH_MAGIC, SWAP_MAGIC};
 
 /// Errors returned by FirmwareUpdater
 #[derive(Debug)]
@@ -75,6 +75,11 @@ impl FirmwareUpdater {
         Self { dfu, state }
     }
 
+    /// The partition the new firmware is written to.
+    pub const fn dfu_partition(&self) -> Partition {
+        self.dfu
+    }
+
     /// Obtain the current state.
     ///
     /// This is useful to check if the bootloader has just done a swap, in order
@@ -175,6 +180,10 @@ impl FirmwareUpdater {
     }
 
     /// Verify the update in DFU with any digest.
+    ///
+    /// The first `update_len` bytes of the DFU partition are read in chunks of `chunk_buf.len()` bytes and
+    /// streamed through the digest, so the image never has to fit in RAM. `output` must have the output size
+    /// of the digest.
     #[cfg(feature = "nightly")]
     pub async fn hash<F: AsyncNorFlash, D: Digest>(
         &mut self,
@@ -208,6 +217,25 @@ impl FirmwareUpdater {
         self.set_magic(aligned, SWAP_MAGIC, state_flash).await
     }
 
+    /// Mark to trigger the installation of a compressed firmware on next boot.
+    ///
+    /// The DFU partition must hold the length of the compressed image as a little endian u32, followed by
+    /// the image. It is installed by [`BootLoader::prepare_boot_decompress`](crate::BootLoader::prepare_boot_decompress),
+    /// without the possibility of a rollback.
+    ///
+    /// # Safety
+    ///
+    /// The `aligned` buffer must have a size of F::WRITE_SIZE, and follow the alignment rules for the flash being written to.
+    #[cfg(all(feature = "nightly", not(feature = "_verify")))]
+    pub async fn mark_updated_compressed<F: AsyncNorFlash>(
+        &mut self,
+        state_flash: &mut F,
+        aligned: &mut [u8],
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert_eq!(aligned.len(), F::WRITE_SIZE);
+        self.set_magic(aligned, crate::DECOMPRESS_MAGIC, state_flash).await
+    }
+
     /// Mark firmware boot successful and stop rollback on reset.
     ///
     /// # Safety
@@ -223,6 +251,23 @@ impl FirmwareUpdater {
         self.set_magic(aligned, BOOT_MAGIC, state_flash).await
     }
 
+    /// Mark to request the bootloader to enter DFU mode on next boot.
+    ///
+    /// The bootloader then returns [`State::DfuDetach`] until a new firmware is marked as updated.
+    ///
+    /// # Safety
+    ///
+    /// The `aligned` buffer must have a size of F::WRITE_SIZE, and follow the alignment rules for the flash being written to.
+    #[cfg(feature = "nightly")]
+    pub async fn mark_dfu<F: AsyncNorFlash>(
+        &mut self,
+        state_flash: &mut F,
+        aligned: &mut [u8],
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert_eq!(aligned.len(), F::WRITE_SIZE);
+        self.set_magic(aligned, DFU_DETACH_MAGIC, state_flash).await
+    }
+
     #[cfg(feature = "nightly")]
     async fn set_magic<F: AsyncNorFlash>(
         &mut self,
@@ -398,6 +443,10 @@ impl FirmwareUpdater {
     }
 
     /// Verify the update in DFU with any digest.
+    ///
+    /// The first `update_len` bytes of the DFU partition are read in chunks of `chunk_buf.len()` bytes and
+    /// streamed through the digest, so the image never has to fit in RAM. `output` must have the output size
+    /// of the digest.
     pub fn hash_blocking<F: NorFlash, D: Digest>(
         &mut self,
         dfu_flash: &mut F,
@@ -430,6 +479,25 @@ impl FirmwareUpdater {
         self.set_magic_blocking(aligned, SWAP_MAGIC, state_flash)
     }
 
+    /// Mark to trigger the installation of a compressed firmware on next boot.
+    ///
+    /// The DFU partition must hold the length of the compressed image as a little endian u32, followed by
+    /// the image. It is installed by [`BootLoader::prepare_boot_decompress`](crate::BootLoader::prepare_boot_decompress),
+    /// without the possibility of a rollback.
+    ///
+    /// # Safety
+    ///
+    /// The `aligned` buffer must have a size of F::WRITE_SIZE, and follow the alignment rules for the flash being written to.
+    #[cfg(not(feature = "_verify"))]
+    pub fn mark_updated_compressed_blocking<F: NorFlash>(
+        &mut self,
+        state_flash: &mut F,
+        aligned: &mut [u8],
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert_eq!(aligned.len(), F::WRITE_SIZE);
+        self.set_magic_blocking(aligned, crate::DECOMPRESS_MAGIC, state_flash)
+    }
+
     /// Mark firmware boot successful and stop rollback on reset.
     ///
     /// # Safety
@@ -444,6 +512,22 @@ impl FirmwareUpdater {
         self.set_magic_blocking(aligned, BOOT_MAGIC, state_flash)
     }
 
+    /// Mark to request the bootloader to enter DFU mode on next boot.
+    ///
+    /// The bootloader then returns [`State::DfuDetach`] until a new firmware is marked as updated.
+    ///
+    /// # Safety
+    ///
+    /// The `aligned` buffer must have a size of F::WRITE_SIZE, and follow the alignment rules for the flash being written to.
+    pub fn mark_dfu_blocking<F: NorFlash>(
+        &mut self,
+        state_flash: &mut F,
+        aligned: &mut [u8],
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert_eq!(aligned.len(), F::WRITE_SIZE);
+        self.set_magic_blocking(aligned, DFU_DETACH_MAGIC, state_flash)
+    }
+
     fn set_magic_blocking<F: NorFlash>(
         &mut self,
         aligned: &mut [u8],
@@ -540,4 +624,28 @@ mod tests {
 
         assert_eq!(Sha1::digest(update).as_slice(), hash);
     }
+
+    #[test]
+    fn can_verify_sha1_blocking() {
+        const STATE: Partition = Partition::new(0, 4096);
+        const DFU: Partition = Partition::new(65536, 131072);
+
+        let mut flash = MemFlash::<131072, 4096, 8>::default();
+
+        let update = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66];
+        let mut to_write = [0; 4096];
+        to_write[..7].copy_from_slice(update.as_slice());
+
+        let mut updater = FirmwareUpdater::new(DFU, STATE);
+        updater
+            .write_firmware_blocking(0, to_write.as_slice(), &mut flash)
+            .unwrap();
+        let mut chunk_buf = [0; 4];
+        let mut hash = [0; 20];
+        updater
+            .hash_blocking::<_, Sha1>(&mut flash, update.len() as u32, &mut chunk_buf, &mut hash)
+            .unwrap();
+
+        assert_eq!(Sha1::digest(update).as_slice(), hash);
+    }
 }
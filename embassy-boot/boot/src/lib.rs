@@ -10,12 +10,16 @@ mod firmware_updater;
 mod mem_flash;
 mod partition;
 
-pub use boot_loader::{BootError, BootFlash, BootLoader, FlashConfig, MultiFlashConfig, SingleFlashConfig};
+pub use boot_loader::{
+    BootError, BootFlash, BootLoader, Decompressor, FlashConfig, MultiFlashConfig, SingleFlashConfig,
+};
 pub use firmware_updater::{FirmwareUpdater, FirmwareUpdaterError};
 pub use partition::Partition;
 
 pub(crate) const BOOT_MAGIC: u8 = 0xD0;
 pub(crate) const SWAP_MAGIC: u8 = 0xF0;
+pub(crate) const DECOMPRESS_MAGIC: u8 = 0xE0;
+pub(crate) const DFU_DETACH_MAGIC: u8 = 0xE1;
 
 /// The state of the bootloader after running prepare.
 #[derive(PartialEq, Eq, Debug)]
@@ -25,6 +29,8 @@ pub enum State {
     Boot,
     /// Bootloader has swapped the active partition with the dfu partition and will attempt boot.
     Swap,
+    /// The application has requested to enter DFU mode, to receive a new firmware from the bootloader.
+    DfuDetach,
 }
 
 /// Buffer aligned to 32 byte boundary, largest known alignment requirement for embassy-boot.
@@ -81,6 +87,33 @@ mod tests {
         assert_eq!(State::Boot, bootloader.prepare_boot(&mut flash, &mut page).unwrap());
     }
 
+    #[test]
+    fn test_dfu_detach_state() {
+        const STATE: Partition = Partition::new(0, 4096);
+        const ACTIVE: Partition = Partition::new(4096, 61440);
+        const DFU: Partition = Partition::new(61440, 122880);
+
+        let mut flash = MemFlash::<131072, 4096, 4>::random();
+        flash.mem[0..4].copy_from_slice(&[BOOT_MAGIC; 4]);
+        let original = flash.mem;
+
+        let mut updater = FirmwareUpdater::new(DFU, STATE);
+        let mut aligned = [0; 4];
+        updater.mark_dfu_blocking(&mut flash, &mut aligned).unwrap();
+
+        let mut bootloader: BootLoader = BootLoader::new(ACTIVE, DFU, STATE);
+        let mut page = [0; 4096];
+        assert_eq!(
+            State::DfuDetach,
+            bootloader
+                .prepare_boot(&mut SingleFlashConfig::new(&mut flash), &mut page)
+                .unwrap()
+        );
+
+        // Neither the active nor the DFU partition are touched
+        assert_eq!(flash.mem[4096..], original[4096..]);
+    }
+
     #[test]
     #[cfg(all(feature = "nightly", not(feature = "_verify")))]
     fn test_swap_state() {
@@ -259,4 +292,138 @@ mod tests {
         ))
         .is_ok());
     }
+
+    #[test]
+    #[cfg(all(feature = "nightly", feature = "_verify"))]
+    fn test_verify_rejects_tampered_firmware() {
+        use ed25519_dalek::{Digest, Keypair, Sha512, Signature, Signer};
+        use rand::rngs::OsRng;
+
+        let mut csprng = OsRng {};
+        let keypair: Keypair = Keypair::generate(&mut csprng);
+
+        let firmware: &[u8] = b"This are bytes that would otherwise be firmware bytes for DFU.";
+        let mut digest = Sha512::new();
+        digest.update(&firmware);
+        let signature: Signature = keypair.sign(&digest.finalize());
+
+        // Setup flash, with one byte of the firmware flipped
+
+        const STATE: Partition = Partition::new(0, 4096);
+        const DFU: Partition = Partition::new(4096, 8192);
+        let mut flash = MemFlash::<8192, 4096, 4>::default();
+
+        let firmware_len = firmware.len();
+
+        let mut write_buf = [0; 4096];
+        write_buf[0..firmware_len].copy_from_slice(firmware);
+        write_buf[0] ^= 0x01;
+        DFU.write_blocking(&mut flash, 0, &write_buf).unwrap();
+
+        // On with the test
+
+        let mut updater = FirmwareUpdater::new(DFU, STATE);
+
+        let mut aligned = [0; 4];
+
+        assert!(matches!(
+            block_on(updater.verify_and_mark_updated(
+                &mut flash,
+                &keypair.public.to_bytes(),
+                &signature.to_bytes(),
+                firmware_len as u32,
+                &mut aligned,
+            )),
+            Err(FirmwareUpdaterError::Signature(_))
+        ));
+        assert_eq!(
+            State::Boot,
+            block_on(updater.get_state(&mut flash, &mut aligned)).unwrap()
+        );
+    }
+
+    /// Run length decoder of (count, byte) pairs
+    struct RunLengthDecoder {
+        run: Option<(u8, u8)>,
+    }
+
+    impl Decompressor for RunLengthDecoder {
+        type Error = ();
+
+        fn reset(&mut self) {
+            self.run = None;
+        }
+
+        fn decompress(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ()> {
+            let mut consumed = 0;
+            let mut produced = 0;
+            loop {
+                match self.run {
+                    Some((0, _)) => self.run = None,
+                    Some((count, byte)) if produced < output.len() => {
+                        output[produced] = byte;
+                        produced += 1;
+                        self.run = Some((count - 1, byte));
+                    }
+                    None if input.len() - consumed >= 2 => {
+                        self.run = Some((input[consumed], input[consumed + 1]));
+                        consumed += 2;
+                    }
+                    _ => return Ok((consumed, produced)),
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "_verify"))]
+    fn test_decompress() {
+        const STATE: Partition = Partition::new(0, 4096);
+        const ACTIVE: Partition = Partition::new(4096, 16384);
+        const DFU: Partition = Partition::new(16384, 20480);
+        let mut flash = MemFlash::<20480, 4096, 4>::random();
+
+        // 5000 bytes of 0xAA followed by 3000 bytes of 0x55, in runs of 200 bytes
+        let mut compressed = [0; 4096];
+        compressed[..4].copy_from_slice(&80u32.to_le_bytes());
+        for (i, run) in compressed[4..84].chunks_mut(2).enumerate() {
+            run.copy_from_slice(&[200, if i < 25 { 0xAA } else { 0x55 }]);
+        }
+
+        let mut updater = FirmwareUpdater::new(DFU, STATE);
+        updater.write_firmware_blocking(0, &compressed, &mut flash).unwrap();
+        let mut aligned = [0; 4];
+        updater
+            .mark_updated_compressed_blocking(&mut flash, &mut aligned)
+            .unwrap();
+
+        let mut bootloader: BootLoader = BootLoader::new(ACTIVE, DFU, STATE);
+        let mut decoder = RunLengthDecoder { run: None };
+        let mut page = [0; 1024];
+
+        // Interrupt the installation in the second page
+        flash.pending_write_successes = Some(6);
+        assert!(bootloader
+            .prepare_boot_decompress(&mut SingleFlashConfig::new(&mut flash), &mut page, &mut decoder)
+            .is_err());
+        flash.pending_write_successes = None;
+
+        assert_eq!(
+            State::Swap,
+            bootloader
+                .prepare_boot_decompress(&mut SingleFlashConfig::new(&mut flash), &mut page, &mut decoder)
+                .unwrap()
+        );
+        flash.assert_eq(ACTIVE.from, &[0xAA; 5000]);
+        flash.assert_eq(ACTIVE.from + 5000, &[0x55; 3000]);
+
+        // There is no rollback, the installed image is booted from now on
+        assert_eq!(
+            State::Boot,
+            bootloader
+                .prepare_boot_decompress(&mut SingleFlashConfig::new(&mut flash), &mut page, &mut decoder)
+                .unwrap()
+        );
+        flash.assert_eq(ACTIVE.from, &[0xAA; 5000]);
+    }
 }
@@ -0,0 +1,75 @@
+use embassy_stm32::flash::{Error, Flash, FLASH_SIZE};
+
+/// Firmware updater for chips with dual-bank flash, which installs a new firmware by swapping the flash banks
+/// instead of copying it.
+///
+/// The new firmware is written to the bank mapped at the end of the flash, then the option bytes are
+/// programmed to boot from it. The banks are exchanged in the address space, so both firmwares are linked to
+/// run from the start of the flash. Installing an update does not take time or wear the flash, and the
+/// previous firmware is kept in the other bank to swap back to.
+///
+/// This does not use the embassy-boot bootloader nor its partitions, each bank holds a complete firmware.
+pub struct BankSwapUpdater {
+    _private: (),
+}
+
+impl BankSwapUpdater {
+    /// Size of a bank, and offset of the inactive bank from the start of the flash
+    pub const BANK_SIZE: u32 = FLASH_SIZE as u32 / 2;
+
+    /// Create a new updater.
+    pub const fn new() -> Self {
+        Self { _private: () }
+    }
+
+    /// Returns true if the running firmware was booted from bank 2.
+    pub fn is_bank_swapped<MODE>(&self, flash: &Flash<'_, MODE>) -> bool {
+        flash.is_bank_swapped()
+    }
+
+    /// Erases the inactive bank to prepare it for [`BankSwapUpdater::write_firmware_blocking`].
+    pub fn prepare_update_blocking<MODE>(&mut self, flash: &mut Flash<'_, MODE>) -> Result<(), Error> {
+        flash.blocking_erase(Self::BANK_SIZE, 2 * Self::BANK_SIZE)
+    }
+
+    /// Writes `data` at `offset` into the inactive bank, which must have been erased with
+    /// [`BankSwapUpdater::prepare_update_blocking`].
+    pub fn write_firmware_blocking<MODE>(
+        &mut self,
+        offset: u32,
+        data: &[u8],
+        flash: &mut Flash<'_, MODE>,
+    ) -> Result<(), Error> {
+        if offset + data.len() as u32 > Self::BANK_SIZE {
+            return Err(Error::Size);
+        }
+        flash.blocking_write(Self::BANK_SIZE + offset, data)
+    }
+
+    /// Reads `data` from `offset` in the inactive bank, e.g. to verify the new firmware before swapping.
+    pub fn read_firmware<MODE>(
+        &mut self,
+        offset: u32,
+        data: &mut [u8],
+        flash: &mut Flash<'_, MODE>,
+    ) -> Result<(), Error> {
+        if offset + data.len() as u32 > Self::BANK_SIZE {
+            return Err(Error::Size);
+        }
+        flash.read(Self::BANK_SIZE + offset, data)
+    }
+
+    /// Boots the firmware in the inactive bank by swapping the banks. Resets the chip, and only returns on error.
+    ///
+    /// There is no trial boot: the new firmware is booted until the banks are swapped again, which also
+    /// reverts to the previous firmware as long as the inactive bank was not erased.
+    pub fn swap_banks_blocking<MODE>(&mut self, flash: &mut Flash<'_, MODE>) -> Result<(), Error> {
+        flash.blocking_swap_banks()
+    }
+}
+
+impl Default for BankSwapUpdater {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -3,6 +3,11 @@
 #![doc = include_str!("../README.md")]
 mod fmt;
 
+#[cfg(feature = "bank-swap")]
+mod bank_swap;
+
+#[cfg(feature = "bank-swap")]
+pub use bank_swap::BankSwapUpdater;
 pub use embassy_boot::{AlignedBuffer, BootFlash, FirmwareUpdater, FlashConfig, Partition, SingleFlashConfig, State};
 
 /// A bootloader for STM32 devices.
@@ -0,0 +1,200 @@
+use embedded_storage::nor_flash::MultiwriteNorFlash;
+
+/// Size of the scratch buffer used for reads and writes, which limits the supported read and write sizes
+const BUF_SIZE: usize = 32;
+/// Size of the sector header: the base value followed by its complement
+const HEADER_SIZE: usize = 16;
+
+/// A monotonic counter stored in flash, which survives resets and power loss.
+///
+/// The counter uses the first two erase sectors of the flash, which would typically be a
+/// [`BlockingPartition`](super::partition::BlockingPartition). Each sector starts with a header holding
+/// the base value of the sector, followed by a bit area. Every increment clears the next bit of the bit area,
+/// so the value of the counter is the base value plus the number of cleared bits. Only once the bit area is
+/// exhausted, the other sector is erased and started with the new base value, so a sector of 4 KiB is
+/// erased once per about 32000 increments.
+///
+/// The header is written with its complement, so a header that was only partially written or erased is
+/// detected and ignored. If both sectors hold a valid header, the one with the higher base value is used.
+/// An interrupted increment therefore either has no effect or is fully applied.
+pub struct FlashCounter<F: MultiwriteNorFlash> {
+    flash: F,
+    state: Option<State>,
+}
+
+#[derive(Clone, Copy)]
+struct State {
+    sector: u32,
+    base: u64,
+    used: u32,
+}
+
+impl<F: MultiwriteNorFlash> FlashCounter<F> {
+    /// Create a counter using the first two erase sectors of `flash`
+    pub fn new(flash: F) -> Self {
+        assert!(F::READ_SIZE <= BUF_SIZE && F::WRITE_SIZE <= BUF_SIZE);
+        assert!(flash.capacity() >= 2 * F::ERASE_SIZE);
+        assert!(F::ERASE_SIZE > Self::header_len());
+
+        Self { flash, state: None }
+    }
+
+    /// Return the underlying flash
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    /// Read the current value of the counter.
+    ///
+    /// The counter is zero if the flash does not hold a valid counter.
+    pub fn read(&mut self) -> Result<u64, F::Error> {
+        Ok(self.state()?.map_or(0, |s| s.base + s.used as u64))
+    }
+
+    /// Increment the counter and return the new value
+    pub fn increment(&mut self) -> Result<u64, F::Error> {
+        let state = match self.state()? {
+            Some(s) if s.used < Self::capacity_bits() => {
+                let unit = Self::unit();
+                let byte = s.used as usize / 8;
+                let chunk_start = byte - byte % unit;
+
+                // all bits of the chunk up to and including the next one are cleared
+                let mut buf = [0xFF; BUF_SIZE];
+                let cleared = s.used as usize - chunk_start * 8 + 1;
+                buf[..cleared / 8].fill(0);
+                if cleared % 8 != 0 {
+                    buf[cleared / 8] = 0xFF << (cleared % 8);
+                }
+
+                let offset = Self::sector_offset(s.sector) + (Self::header_len() + chunk_start) as u32;
+                self.flash.write(offset, &buf[..unit])?;
+
+                State { used: s.used + 1, ..s }
+            }
+            Some(s) => self.start_sector(1 - s.sector, s.base + s.used as u64 + 1)?,
+            None => self.start_sector(0, 1)?,
+        };
+
+        self.state = Some(state);
+        Ok(state.base + state.used as u64)
+    }
+
+    fn start_sector(&mut self, sector: u32, base: u64) -> Result<State, F::Error> {
+        let offset = Self::sector_offset(sector);
+        self.flash.erase(offset, offset + F::ERASE_SIZE as u32)?;
+
+        let mut buf = [0xFF; BUF_SIZE];
+        buf[..8].copy_from_slice(&base.to_le_bytes());
+        buf[8..16].copy_from_slice(&(!base).to_le_bytes());
+        self.flash.write(offset, &buf[..Self::header_len()])?;
+
+        Ok(State { sector, base, used: 0 })
+    }
+
+    fn state(&mut self) -> Result<Option<State>, F::Error> {
+        if self.state.is_none() {
+            self.state = self.load()?;
+        }
+        Ok(self.state)
+    }
+
+    fn load(&mut self) -> Result<Option<State>, F::Error> {
+        let mut current: Option<(u32, u64)> = None;
+        for sector in 0..2 {
+            let mut buf = [0; BUF_SIZE];
+            self.flash
+                .read(Self::sector_offset(sector), &mut buf[..Self::header_len()])?;
+
+            let base = u64::from_le_bytes(buf[..8].try_into().unwrap());
+            let complement = u64::from_le_bytes(buf[8..16].try_into().unwrap());
+            if base == !complement && current.map_or(true, |(_, b)| base > b) {
+                current = Some((sector, base));
+            }
+        }
+
+        let Some((sector, base)) = current else {
+            return Ok(None);
+        };
+
+        // bits are consumed in order, so the scan stops at the first chunk without cleared bits
+        let mut used = 0;
+        let mut offset = Self::sector_offset(sector) + Self::header_len() as u32;
+        let end = Self::sector_offset(sector) + F::ERASE_SIZE as u32;
+        while offset < end {
+            let mut buf = [0; BUF_SIZE];
+            let len = BUF_SIZE.min((end - offset) as usize);
+            self.flash.read(offset, &mut buf[..len])?;
+
+            let zeros: u32 = buf[..len].iter().map(|b| b.count_zeros()).sum();
+            if zeros == 0 {
+                break;
+            }
+            used += zeros;
+            offset += len as u32;
+        }
+
+        Ok(Some(State { sector, base, used }))
+    }
+
+    /// Read and write unit, which is a multiple of both the read and the write size
+    fn unit() -> usize {
+        F::READ_SIZE.max(F::WRITE_SIZE)
+    }
+
+    fn header_len() -> usize {
+        let unit = Self::unit();
+        (HEADER_SIZE + unit - 1) / unit * unit
+    }
+
+    fn capacity_bits() -> u32 {
+        ((F::ERASE_SIZE - Self::header_len()) * 8) as u32
+    }
+
+    fn sector_offset(sector: u32) -> u32 {
+        sector * F::ERASE_SIZE as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flash::mem_flash::MemFlash;
+
+    type Flash = MemFlash<512, 128, 4>;
+
+    #[test]
+    fn can_increment_across_sectors() {
+        let mut counter = FlashCounter::new(Flash::default());
+        assert_eq!(0, counter.read().unwrap());
+
+        // 896 bits per sector, 1 increment to start the first sector
+        for i in 1..=2000 {
+            assert_eq!(i, counter.increment().unwrap());
+        }
+
+        let flash = counter.into_inner();
+        assert_eq!(3, flash.erases.len());
+        assert_eq!(flash.erases[1], (128, 256));
+
+        let mut counter = FlashCounter::new(flash);
+        assert_eq!(2000, counter.read().unwrap());
+        assert_eq!(2001, counter.increment().unwrap());
+    }
+
+    #[test]
+    fn ignores_torn_header() {
+        let mut counter = FlashCounter::new(Flash::default());
+        for _ in 0..10 {
+            counter.increment().unwrap();
+        }
+
+        // header of the other sector with a bit of the complement not cleared
+        let mut flash = counter.into_inner();
+        flash.mem[128..136].copy_from_slice(&11u64.to_le_bytes());
+        flash.mem[136..144].copy_from_slice(&(!11u64 | 1).to_le_bytes());
+
+        let mut counter = FlashCounter::new(flash);
+        assert_eq!(10, counter.read().unwrap());
+    }
+}
@@ -1,8 +1,10 @@
 //! Utilities related to flash.
 
 mod concat_flash;
+mod counter;
 #[cfg(test)]
 pub(crate) mod mem_flash;
 pub mod partition;
 
 pub use concat_flash::ConcatFlash;
+pub use counter::FlashCounter;
@@ -0,0 +1,280 @@
+use embassy_net_driver_channel as ch;
+use embassy_net_driver_channel::driver::LinkState;
+
+use crate::ioctl::Shared;
+use crate::proto::{ProtoError, Reader, Value, Writer};
+
+// Fields of `CtrlMsg`
+const CTRL_MSG_TYPE: u32 = 1;
+const CTRL_MSG_ID: u32 = 2;
+
+const MSG_TYPE_REQ: i32 = 1;
+
+// Message IDs, also used as the field numbers of the payloads. Responses are at +100, and
+// events start at 300.
+const REQ_GET_MAC_ADDRESS: u32 = 101;
+const REQ_SET_WIFI_MODE: u32 = 104;
+const REQ_GET_AP_SCAN_LIST: u32 = 105;
+const REQ_CONNECT_AP: u32 = 107;
+const REQ_DISCONNECT_AP: u32 = 108;
+const RESP_OFFSET: u32 = 100;
+pub(crate) const EVENT_ESP_INIT: u32 = 301;
+pub(crate) const EVENT_STATION_DISCONNECT_FROM_AP: u32 = 303;
+
+const WIFI_MODE_STA: i32 = 1;
+
+/// Error returned by the [`Control`] requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The ESP failed to process the request, with this error code.
+    Failed(i32),
+    /// The request was too long, or the response malformed.
+    Internal,
+}
+
+impl From<ProtoError> for Error {
+    fn from(_: ProtoError) -> Self {
+        Error::Internal
+    }
+}
+
+/// Security protocol of an access point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Security {
+    /// Open network.
+    Open,
+    /// WEP.
+    Wep,
+    /// WPA-PSK.
+    WpaPsk,
+    /// WPA2-PSK.
+    Wpa2Psk,
+    /// WPA-PSK or WPA2-PSK.
+    WpaWpa2Psk,
+    /// WPA2-Enterprise.
+    Wpa2Enterprise,
+    /// WPA3-PSK.
+    Wpa3Psk,
+    /// WPA2-PSK or WPA3-PSK.
+    Wpa2Wpa3Psk,
+    /// Protocol unknown to this driver.
+    Unknown(u32),
+}
+
+impl From<u32> for Security {
+    fn from(val: u32) -> Self {
+        match val {
+            0 => Security::Open,
+            1 => Security::Wep,
+            2 => Security::WpaPsk,
+            3 => Security::Wpa2Psk,
+            4 => Security::WpaWpa2Psk,
+            5 => Security::Wpa2Enterprise,
+            6 => Security::Wpa3Psk,
+            7 => Security::Wpa2Wpa3Psk,
+            val => Security::Unknown(val),
+        }
+    }
+}
+
+/// Access point found by [`Control::scan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AccessPoint<'a> {
+    /// SSID, which is usually, but not necessarily, UTF-8.
+    pub ssid: &'a [u8],
+    /// BSSID, the MAC address of the access point.
+    pub bssid: [u8; 6],
+    /// Channel.
+    pub channel: u32,
+    /// Signal strength, in dBm.
+    pub rssi: i32,
+    /// Security protocol.
+    pub security: Security,
+}
+
+/// Control driver, to configure the WiFi of the ESP.
+pub struct Control<'a> {
+    state_ch: ch::StateRunner<'a>,
+    shared: &'a Shared,
+}
+
+impl<'a> Control<'a> {
+    pub(crate) fn new(state_ch: ch::StateRunner<'a>, shared: &'a Shared) -> Self {
+        Self { state_ch, shared }
+    }
+
+    /// Wait for the ESP to boot, and put it in station mode.
+    ///
+    /// This must be called before the other requests, while the [`Runner`](crate::Runner) is
+    /// running.
+    pub async fn init(&mut self) -> Result<(), Error> {
+        debug!("wait for init event...");
+        self.shared.init_wait().await;
+
+        self.request(REQ_SET_WIFI_MODE, |w| w.int32(1, WIFI_MODE_STA), |r| check(r.find(2)?))
+            .await?;
+
+        let mac_addr = self
+            .request(
+                REQ_GET_MAC_ADDRESS,
+                |w| w.int32(1, WIFI_MODE_STA),
+                |r| {
+                    let mut mac = None;
+                    let mut resp = None;
+                    for res in r {
+                        match res? {
+                            (1, val) => mac = Some(val.as_bytes()?),
+                            (2, val) => resp = Some(val),
+                            _ => {}
+                        }
+                    }
+                    check(resp)?;
+                    Ok(parse_mac(mac.ok_or(Error::Internal)?)?)
+                },
+            )
+            .await?;
+        debug!("mac addr: {:?}", mac_addr);
+        self.state_ch.set_ethernet_address(mac_addr);
+
+        Ok(())
+    }
+
+    /// Join the network `ssid`, with the passphrase `password`, or an empty one for an open
+    /// network.
+    pub async fn join(&mut self, ssid: &str, password: &str) -> Result<(), Error> {
+        self.request(
+            REQ_CONNECT_AP,
+            |w| {
+                w.bytes(1, ssid.as_bytes())?;
+                w.bytes(2, password.as_bytes())?;
+                w.bool(4, true)
+            },
+            |r| check(r.find(1)?),
+        )
+        .await?;
+
+        self.state_ch.set_link_state(LinkState::Up);
+        Ok(())
+    }
+
+    /// Leave the network joined with [`join`](Self::join).
+    pub async fn leave(&mut self) -> Result<(), Error> {
+        self.request(REQ_DISCONNECT_AP, |_| Ok(()), |r| check(r.find(1)?))
+            .await?;
+
+        self.state_ch.set_link_state(LinkState::Down);
+        Ok(())
+    }
+
+    /// Scan for access points, and call `f` with each one found.
+    pub async fn scan(&mut self, mut f: impl FnMut(&AccessPoint)) -> Result<(), Error> {
+        self.request(
+            REQ_GET_AP_SCAN_LIST,
+            |_| Ok(()),
+            |r| {
+                check(r.clone().find(3)?)?;
+                for res in r {
+                    if let (2, entry) = res? {
+                        f(&parse_access_point(entry.as_bytes()?)?);
+                    }
+                }
+                Ok(())
+            },
+        )
+        .await
+    }
+
+    /// Send the request `id`, with the payload encoded by `encode`, and decode the payload of
+    /// its response with `decode`.
+    async fn request<R>(
+        &mut self,
+        id: u32,
+        encode: impl FnOnce(&mut Writer) -> Result<(), ProtoError>,
+        decode: impl FnOnce(Reader) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        self.shared
+            .ioctl(
+                |buf| {
+                    let mut w = Writer::new(buf);
+                    w.int32(CTRL_MSG_TYPE, MSG_TYPE_REQ)?;
+                    w.int32(CTRL_MSG_ID, id as i32)?;
+                    w.message(id, encode)?;
+                    Ok(w.len())
+                },
+                |buf| {
+                    let payload = Reader::new(buf).find(id + RESP_OFFSET)?.ok_or(Error::Internal)?;
+                    decode(Reader::new(payload.as_bytes()?))
+                },
+            )
+            .await
+    }
+}
+
+/// Returns the ID of the control message `msg`, and its payload.
+pub(crate) fn parse_ctrl_msg(msg: &[u8]) -> Result<(u32, &[u8]), ProtoError> {
+    let id = Reader::new(msg).find(CTRL_MSG_ID)?.ok_or(ProtoError)?.as_u32()?;
+    let payload = match Reader::new(msg).find(id)? {
+        Some(val) => val.as_bytes()?,
+        None => &[],
+    };
+    Ok((id, payload))
+}
+
+/// Check the `resp` field of a response, which is absent when it's 0.
+fn check(resp: Option<Value>) -> Result<(), Error> {
+    match resp {
+        None => Ok(()),
+        Some(val) => match val.as_i32()? {
+            0 => Ok(()),
+            code => Err(Error::Failed(code)),
+        },
+    }
+}
+
+fn parse_access_point(entry: &[u8]) -> Result<AccessPoint, Error> {
+    let mut ap = AccessPoint {
+        ssid: &[],
+        bssid: [0; 6],
+        channel: 0,
+        rssi: 0,
+        security: Security::Open,
+    };
+    for res in Reader::new(entry) {
+        match res? {
+            (1, val) => ap.ssid = val.as_bytes()?,
+            (2, val) => ap.channel = val.as_u32()?,
+            (3, val) => ap.rssi = val.as_i32()?,
+            (4, val) => ap.bssid = parse_mac(val.as_bytes()?)?,
+            (5, val) => ap.security = val.as_u32()?.into(),
+            _ => {}
+        }
+    }
+    Ok(ap)
+}
+
+/// Parse a MAC address, which the ESP formats as text, like `12:34:56:78:9a:bc`.
+fn parse_mac(text: &[u8]) -> Result<[u8; 6], ProtoError> {
+    fn nibble(c: u8) -> Result<u8, ProtoError> {
+        match c {
+            b'0'..=b'9' => Ok(c - b'0'),
+            b'a'..=b'f' => Ok(c - b'a' + 10),
+            b'A'..=b'F' => Ok(c - b'A' + 10),
+            _ => Err(ProtoError),
+        }
+    }
+
+    // Some versions of the firmware terminate the text with NUL.
+    let text = text.strip_suffix(&[0]).unwrap_or(text);
+    if text.len() != 17 {
+        return Err(ProtoError);
+    }
+    let mut mac = [0; 6];
+    for (i, byte) in mac.iter_mut().enumerate() {
+        let hex = &text[i * 3..i * 3 + 2];
+        *byte = (nibble(hex[0])? << 4) | nibble(hex[1])?;
+    }
+    Ok(mac)
+}
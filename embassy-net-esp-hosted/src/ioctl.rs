@@ -0,0 +1,135 @@
+//! State shared by [`Control`](crate::Control) and [`Runner`](crate::Runner), to pass control
+//! requests and their responses.
+
+use core::cell::RefCell;
+use core::future::poll_fn;
+use core::task::Poll;
+
+use embassy_sync::waitqueue::WakerRegistration;
+
+/// Maximum length of an encoded control request or response.
+pub(crate) const CONTROL_BUF_LEN: usize = 1600;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum IoctlState {
+    Idle,
+    /// A request of this length is in `buf`, waiting to be sent by the runner.
+    Pending(usize),
+    /// The request was sent, waiting for the response.
+    Sent,
+    /// A response of this length is in `buf`.
+    Done(usize),
+}
+
+pub(crate) struct Shared(RefCell<SharedInner>);
+
+struct SharedInner {
+    buf: [u8; CONTROL_BUF_LEN],
+    ioctl: IoctlState,
+    is_init: bool,
+    control_waker: WakerRegistration,
+    runner_waker: WakerRegistration,
+}
+
+impl Shared {
+    pub const fn new() -> Self {
+        Self(RefCell::new(SharedInner {
+            buf: [0; CONTROL_BUF_LEN],
+            ioctl: IoctlState::Idle,
+            is_init: false,
+            control_waker: WakerRegistration::new(),
+            runner_waker: WakerRegistration::new(),
+        }))
+    }
+
+    /// Send a request encoded by `encode`, which returns its length, then wait for the response
+    /// and decode it with `decode`.
+    pub async fn ioctl<R, E>(
+        &self,
+        encode: impl FnOnce(&mut [u8]) -> Result<usize, E>,
+        decode: impl FnOnce(&[u8]) -> Result<R, E>,
+    ) -> Result<R, E> {
+        {
+            let mut this = self.0.borrow_mut();
+            let req_len = encode(&mut this.buf)?;
+            this.ioctl = IoctlState::Pending(req_len);
+            this.runner_waker.wake();
+        }
+
+        let resp_len = poll_fn(|cx| {
+            let mut this = self.0.borrow_mut();
+            if let IoctlState::Done(resp_len) = this.ioctl {
+                Poll::Ready(resp_len)
+            } else {
+                this.control_waker.register(cx.waker());
+                Poll::Pending
+            }
+        })
+        .await;
+
+        let mut this = self.0.borrow_mut();
+        this.ioctl = IoctlState::Idle;
+        decode(&this.buf[..resp_len])
+    }
+
+    /// Wait for a request to be pending.
+    pub async fn ioctl_wait_pending(&self) {
+        poll_fn(|cx| {
+            let mut this = self.0.borrow_mut();
+            if let IoctlState::Pending(_) = this.ioctl {
+                Poll::Ready(())
+            } else {
+                this.runner_waker.register(cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Pass the pending request to `f`, which returns how many bytes it wrote to the SPI buffer.
+    pub fn take_request(&self, f: impl FnOnce(&[u8]) -> usize) -> usize {
+        let mut this = self.0.borrow_mut();
+        match this.ioctl {
+            IoctlState::Pending(req_len) => {
+                let n = f(&this.buf[..req_len]);
+                this.ioctl = IoctlState::Sent;
+                n
+            }
+            _ => 0,
+        }
+    }
+
+    /// Complete the request sent with the response `resp`.
+    pub fn ioctl_done(&self, resp: &[u8]) {
+        let mut this = self.0.borrow_mut();
+        if this.ioctl != IoctlState::Sent {
+            warn!("esp-hosted: unexpected control response");
+            return;
+        }
+        let resp_len = resp.len().min(CONTROL_BUF_LEN);
+        this.buf[..resp_len].copy_from_slice(&resp[..resp_len]);
+        this.ioctl = IoctlState::Done(resp_len);
+        this.control_waker.wake();
+    }
+
+    /// Record that the ESP has booted, and is ready for control requests.
+    pub fn init_done(&self) {
+        let mut this = self.0.borrow_mut();
+        this.is_init = true;
+        this.control_waker.wake();
+    }
+
+    /// Wait for the ESP to have booted.
+    pub async fn init_wait(&self) {
+        poll_fn(|cx| {
+            let mut this = self.0.borrow_mut();
+            if this.is_init {
+                Poll::Ready(())
+            } else {
+                this.control_waker.register(cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
@@ -0,0 +1,291 @@
+#![no_std]
+#![warn(missing_docs)]
+#![doc = include_str!("../README.md")]
+
+// This mod MUST go first, so that the others see its macros.
+pub(crate) mod fmt;
+
+mod control;
+mod ioctl;
+mod proto;
+
+use embassy_futures::select::{select3, Either3};
+use embassy_net_driver_channel as ch;
+use embassy_net_driver_channel::driver::LinkState;
+use embassy_time::{Duration, Timer};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::spi::SpiDevice;
+
+pub use crate::control::{AccessPoint, Control, Error, Security};
+use crate::ioctl::{Shared, CONTROL_BUF_LEN};
+
+const MTU: usize = 1514;
+
+/// Length of the SPI transactions, which always transfer a full buffer.
+const BUF_LEN: usize = 1600;
+
+/// Length of the header of each buffer, `struct esp_payload_header`.
+const HEADER_LEN: usize = 12;
+
+// Interfaces, in the low nibble of the first byte of the header.
+const IF_TYPE_STA: u8 = 0;
+const IF_TYPE_SERIAL: u8 = 2;
+const IF_TYPE_PRIV: u8 = 4;
+
+/// The payload is continued in the next buffer.
+const FLAG_MORE_FRAGMENT: u8 = 1 << 0;
+
+/// Event sent on the private interface once the ESP has booted.
+const PRIV_EVENT_INIT: u8 = 0x22;
+
+// Control messages are wrapped in TLVs, on the serial interface.
+const TLV_ENDPOINT: u8 = 1;
+const TLV_DATA: u8 = 2;
+const ENDPOINT_RESP: &[u8] = b"ctrlResp";
+const ENDPOINT_EVENT: &[u8] = b"ctrlEvnt";
+
+/// Internal state for the ESP.
+pub struct State {
+    ch_state: ch::State<MTU, 4, 4>,
+    shared: Shared,
+    serial_buf: [u8; CONTROL_BUF_LEN],
+}
+
+impl State {
+    /// Create a new `State`.
+    pub const fn new() -> Self {
+        Self {
+            ch_state: ch::State::new(),
+            shared: Shared::new(),
+            serial_buf: [0; CONTROL_BUF_LEN],
+        }
+    }
+}
+
+/// ESP device for use with embassy-net.
+pub type NetDriver<'a> = ch::Device<'a, MTU>;
+
+/// Background runner for the ESP.
+///
+/// You must call `.run()` in a background task for the ESP to operate.
+pub struct Runner<'a, SPI, IN> {
+    ch: ch::Runner<'a, MTU>,
+    shared: &'a Shared,
+    serial_buf: &'a mut [u8; CONTROL_BUF_LEN],
+    spi: SPI,
+    handshake: IN,
+    ready: IN,
+}
+
+/// Reset an ESP running the esp-hosted firmware, connected over SPI.
+///
+/// `handshake` is the pin asserted by the ESP when it's ready for a SPI transaction, `ready` the
+/// pin asserted when it has data to send, and `reset` its EN pin. The SPI bus must be configured
+/// in the mode expected by the firmware: mode 2 for the ESP32, and mode 3 for the other chips.
+///
+/// Once the [`Runner`] is running, [`Control::init`] must be called before using the network.
+pub async fn new<SPI: SpiDevice, IN: Wait, OUT: OutputPin>(
+    state: &mut State,
+    spi: SPI,
+    handshake: IN,
+    ready: IN,
+    mut reset: OUT,
+) -> (NetDriver<'_>, Control<'_>, Runner<'_, SPI, IN>) {
+    reset.set_low().ok();
+    Timer::after(Duration::from_millis(100)).await;
+    reset.set_high().ok();
+
+    let (ch_runner, device) = ch::new(&mut state.ch_state, [0; 6]);
+    let state_ch = ch_runner.state_runner();
+
+    let runner = Runner {
+        ch: ch_runner,
+        shared: &state.shared,
+        serial_buf: &mut state.serial_buf,
+        spi,
+        handshake,
+        ready,
+    };
+
+    (device, Control::new(state_ch, &state.shared), runner)
+}
+
+impl<'a, SPI: SpiDevice, IN: Wait> Runner<'a, SPI, IN> {
+    /// Run the ESP: exchange frames and control messages with it.
+    pub async fn run(self) -> ! {
+        let Self {
+            ch,
+            shared,
+            serial_buf,
+            mut spi,
+            mut handshake,
+            mut ready,
+        } = self;
+        let (state_chan, mut rx_chan, mut tx_chan) = ch.split();
+
+        let mut tx_buf = [0; BUF_LEN];
+        let mut rx_buf = [0; BUF_LEN];
+        let mut seq_num = 0u16;
+        let mut serial_len = 0;
+
+        loop {
+            // Wait for something to send, or for the ESP to have something to send.
+            let tx = match select3(shared.ioctl_wait_pending(), tx_chan.tx_buf(), ready.wait_for_high()).await {
+                Either3::First(()) => {
+                    let len = shared.take_request(|req| serial_packet(&mut tx_buf[HEADER_LEN..], req));
+                    Some((IF_TYPE_SERIAL, len))
+                }
+                Either3::Second(frame) => {
+                    tx_buf[HEADER_LEN..][..frame.len()].copy_from_slice(frame);
+                    let len = frame.len();
+                    tx_chan.tx_done();
+                    Some((IF_TYPE_STA, len))
+                }
+                Either3::Third(_) => None,
+            };
+
+            match tx {
+                Some((if_type, len)) => {
+                    write_header(&mut tx_buf, if_type, len, seq_num);
+                    seq_num = seq_num.wrapping_add(1);
+                }
+                None => tx_buf[..HEADER_LEN].fill(0),
+            }
+
+            handshake.wait_for_high().await.ok();
+            if spi.transfer(&mut rx_buf, &tx_buf).await.is_err() {
+                warn!("esp-hosted: SPI transfer failed");
+                continue;
+            }
+
+            let Some((if_type, flags, payload)) = read_header(&rx_buf) else {
+                continue;
+            };
+            match if_type {
+                IF_TYPE_STA => match rx_chan.try_rx_buf() {
+                    Some(buf) => {
+                        let len = payload.len().min(buf.len());
+                        buf[..len].copy_from_slice(&payload[..len]);
+                        rx_chan.rx_done(len);
+                    }
+                    None => warn!("esp-hosted: no rx buffer available, dropping frame"),
+                },
+                IF_TYPE_SERIAL => {
+                    // Control messages may be split in several buffers.
+                    let Some(dst) = serial_buf.get_mut(serial_len..serial_len + payload.len()) else {
+                        warn!("esp-hosted: control message too long, dropping it");
+                        serial_len = 0;
+                        continue;
+                    };
+                    dst.copy_from_slice(payload);
+                    serial_len += payload.len();
+
+                    if flags & FLAG_MORE_FRAGMENT == 0 {
+                        handle_serial(shared, &state_chan, &serial_buf[..serial_len]);
+                        serial_len = 0;
+                    }
+                }
+                IF_TYPE_PRIV => {
+                    if payload.first() == Some(&PRIV_EVENT_INIT) {
+                        debug!("esp-hosted: init event");
+                        shared.init_done();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Handle a control message, a response to a request or an event.
+fn handle_serial(shared: &Shared, state_chan: &ch::StateRunner, msg: &[u8]) {
+    let Some((endpoint, data)) = parse_serial_packet(msg) else {
+        warn!("esp-hosted: malformed control message");
+        return;
+    };
+
+    if endpoint == ENDPOINT_RESP {
+        shared.ioctl_done(data);
+    } else if endpoint == ENDPOINT_EVENT {
+        match control::parse_ctrl_msg(data) {
+            Ok((control::EVENT_ESP_INIT, _)) => shared.init_done(),
+            Ok((control::EVENT_STATION_DISCONNECT_FROM_AP, _)) => {
+                debug!("esp-hosted: disconnected from AP");
+                state_chan.set_link_state(LinkState::Down);
+            }
+            Ok(_) => {}
+            Err(_) => warn!("esp-hosted: malformed event"),
+        }
+    }
+}
+
+/// Wrap the control request `req` in TLVs, in `buf`. Returns the length written.
+fn serial_packet(buf: &mut [u8], req: &[u8]) -> usize {
+    let mut pos = 0;
+    for (t, v) in [(TLV_ENDPOINT, ENDPOINT_RESP), (TLV_DATA, req)] {
+        buf[pos] = t;
+        buf[pos + 1..pos + 3].copy_from_slice(&(v.len() as u16).to_le_bytes());
+        buf[pos + 3..pos + 3 + v.len()].copy_from_slice(v);
+        pos += 3 + v.len();
+    }
+    pos
+}
+
+/// Returns the endpoint and data of a control message wrapped in TLVs.
+fn parse_serial_packet(mut buf: &[u8]) -> Option<(&[u8], &[u8])> {
+    let mut endpoint = None;
+    let mut data = None;
+    while buf.len() >= 3 {
+        let len = u16::from_le_bytes([buf[1], buf[2]]) as usize;
+        let v = buf.get(3..3 + len)?;
+        match buf[0] {
+            TLV_ENDPOINT => endpoint = Some(v),
+            TLV_DATA => data = Some(v),
+            _ => {}
+        }
+        buf = &buf[3 + len..];
+    }
+    Some((endpoint?, data?))
+}
+
+fn write_header(buf: &mut [u8; BUF_LEN], if_type: u8, len: usize, seq_num: u16) {
+    buf[0] = if_type;
+    buf[1] = 0;
+    buf[2..4].copy_from_slice(&(len as u16).to_le_bytes());
+    buf[4..6].copy_from_slice(&(HEADER_LEN as u16).to_le_bytes());
+    buf[6..8].fill(0);
+    buf[8..10].copy_from_slice(&seq_num.to_le_bytes());
+    buf[10..12].fill(0);
+
+    let checksum = checksum(&buf[..HEADER_LEN + len]);
+    buf[6..8].copy_from_slice(&checksum.to_le_bytes());
+}
+
+/// Returns the interface type, flags and payload of the buffer received, or `None` if it's
+/// empty or invalid.
+fn read_header(buf: &[u8; BUF_LEN]) -> Option<(u8, u8, &[u8])> {
+    let len = u16::from_le_bytes([buf[2], buf[3]]) as usize;
+    let offset = u16::from_le_bytes([buf[4], buf[5]]) as usize;
+    if len == 0 {
+        return None;
+    }
+    let Some(packet) = buf.get(..offset + len).filter(|_| offset >= HEADER_LEN) else {
+        warn!("esp-hosted: invalid header, len={} offset={}", len, offset);
+        return None;
+    };
+
+    // The checksum is computed with its own field zeroed.
+    let expected = u16::from_le_bytes([buf[6], buf[7]]);
+    let actual = checksum(packet).wrapping_sub(buf[6] as u16 + buf[7] as u16);
+    if actual != expected {
+        warn!("esp-hosted: bad checksum, dropping buffer");
+        return None;
+    }
+
+    Some((buf[0] & 0x0f, buf[1], &packet[offset..]))
+}
+
+fn checksum(buf: &[u8]) -> u16 {
+    buf.iter().fold(0u16, |sum, &b| sum.wrapping_add(b as u16))
+}
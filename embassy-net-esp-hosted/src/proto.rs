@@ -0,0 +1,205 @@
+//! Minimal protobuf encoding and decoding, for the control messages of esp-hosted.
+//!
+//! Only the wire types used by `esp_hosted_config.proto` are supported: varints and
+//! length-delimited fields.
+
+const WIRE_VARINT: u32 = 0;
+const WIRE_FIXED64: u32 = 1;
+const WIRE_LEN: u32 = 2;
+const WIRE_FIXED32: u32 = 5;
+
+/// The message doesn't fit in the buffer, or is malformed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) struct ProtoError;
+
+pub(crate) struct Writer<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pos
+    }
+
+    fn put(&mut self, data: &[u8]) -> Result<(), ProtoError> {
+        let dst = self.buf.get_mut(self.pos..self.pos + data.len()).ok_or(ProtoError)?;
+        dst.copy_from_slice(data);
+        self.pos += data.len();
+        Ok(())
+    }
+
+    fn varint(&mut self, mut val: u64) -> Result<(), ProtoError> {
+        loop {
+            let byte = (val & 0x7f) as u8;
+            val >>= 7;
+            if val == 0 {
+                return self.put(&[byte]);
+            }
+            self.put(&[byte | 0x80])?;
+        }
+    }
+
+    fn key(&mut self, field: u32, wire: u32) -> Result<(), ProtoError> {
+        self.varint(((field << 3) | wire) as u64)
+    }
+
+    pub fn int32(&mut self, field: u32, val: i32) -> Result<(), ProtoError> {
+        self.key(field, WIRE_VARINT)?;
+        // Negative values are sign-extended to 64 bits.
+        self.varint(val as i64 as u64)
+    }
+
+    pub fn bool(&mut self, field: u32, val: bool) -> Result<(), ProtoError> {
+        self.key(field, WIRE_VARINT)?;
+        self.varint(val as u64)
+    }
+
+    pub fn bytes(&mut self, field: u32, val: &[u8]) -> Result<(), ProtoError> {
+        self.key(field, WIRE_LEN)?;
+        self.varint(val.len() as u64)?;
+        self.put(val)
+    }
+
+    /// Write the embedded message of field `field`, encoded by `f`.
+    pub fn message(
+        &mut self,
+        field: u32,
+        f: impl FnOnce(&mut Writer) -> Result<(), ProtoError>,
+    ) -> Result<(), ProtoError> {
+        // The messages written are small, so their length is always encoded in a single byte.
+        self.key(field, WIRE_LEN)?;
+        let len_pos = self.pos;
+        self.put(&[0])?;
+        let mut inner = Writer::new(&mut self.buf[self.pos..]);
+        f(&mut inner)?;
+        let len = inner.len();
+        if len > 0x7f {
+            return Err(ProtoError);
+        }
+        self.buf[len_pos] = len as u8;
+        self.pos += len;
+        Ok(())
+    }
+}
+
+/// Value of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    /// A fixed size value, which isn't used by esp-hosted, and is ignored.
+    Fixed,
+}
+
+impl<'a> Value<'a> {
+    pub fn as_i32(self) -> Result<i32, ProtoError> {
+        match self {
+            Value::Varint(v) => Ok(v as i32),
+            _ => Err(ProtoError),
+        }
+    }
+
+    pub fn as_u32(self) -> Result<u32, ProtoError> {
+        match self {
+            Value::Varint(v) => Ok(v as u32),
+            _ => Err(ProtoError),
+        }
+    }
+
+    pub fn as_bytes(self) -> Result<&'a [u8], ProtoError> {
+        match self {
+            Value::Bytes(b) => Ok(b),
+            _ => Err(ProtoError),
+        }
+    }
+}
+
+/// Iterator over the fields of a message, as `(field number, value)`.
+#[derive(Clone)]
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    /// Returns the value of the last occurrence of `field`, like protobuf does for non-repeated
+    /// fields.
+    pub fn find(self, field: u32) -> Result<Option<Value<'a>>, ProtoError> {
+        let mut found = None;
+        for res in self {
+            let (f, val) = res?;
+            if f == field {
+                found = Some(val);
+            }
+        }
+        Ok(found)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ProtoError> {
+        if len > self.buf.len() {
+            return Err(ProtoError);
+        }
+        let (data, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(data)
+    }
+
+    fn varint(&mut self) -> Result<u64, ProtoError> {
+        let mut val = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            val |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(val);
+            }
+        }
+        Err(ProtoError)
+    }
+
+    fn field(&mut self) -> Result<(u32, Value<'a>), ProtoError> {
+        let key = self.varint()?;
+        let field = (key >> 3) as u32;
+        let val = match key as u32 & 0x7 {
+            WIRE_VARINT => Value::Varint(self.varint()?),
+            WIRE_LEN => {
+                let len = self.varint()? as usize;
+                Value::Bytes(self.take(len)?)
+            }
+            WIRE_FIXED64 => {
+                self.take(8)?;
+                Value::Fixed
+            }
+            WIRE_FIXED32 => {
+                self.take(4)?;
+                Value::Fixed
+            }
+            _ => return Err(ProtoError),
+        };
+        Ok((field, val))
+    }
+}
+
+impl<'a> Iterator for Reader<'a> {
+    type Item = Result<(u32, Value<'a>), ProtoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        let res = self.field();
+        if res.is_err() {
+            // Stop at the first error.
+            self.buf = &[];
+        }
+        Some(res)
+    }
+}
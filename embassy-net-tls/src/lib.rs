@@ -0,0 +1,160 @@
+#![no_std]
+#![feature(async_fn_in_trait, impl_trait_projections)]
+#![warn(missing_docs)]
+#![doc = include_str!("../README.md")]
+
+use embassy_net::tcp::TcpSocket;
+pub use embedded_tls;
+use embedded_tls::TlsConnection;
+pub use embedded_tls::{
+    Aes128GcmSha256, Aes256GcmSha384, Certificate, NoVerify, TlsCipherSuite, TlsConfig, TlsContext, TlsError,
+    TlsVerifier, TLS_RECORD_OVERHEAD,
+};
+use rand_core::{CryptoRng, RngCore};
+
+/// A pre-shared key, used to resume a session with a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Psk<'a> {
+    /// Identity of the key, sent to the server.
+    pub identity: &'a [u8],
+    /// Key.
+    pub key: &'a [u8],
+}
+
+/// Storage of the keys used to resume sessions.
+///
+/// `embedded-tls` doesn't process the session tickets sent by servers, so the keys must be
+/// obtained by other means, for example provisioned along with the server address, or derived
+/// from an earlier exchange at the application level.
+pub trait SessionStore {
+    /// Returns the key to use with the server named `server_name`, if any.
+    fn load(&self, server_name: &str) -> Option<Psk<'_>>;
+
+    /// Called when a handshake using the key of `server_name` failed, the key should be discarded.
+    fn invalidate(&mut self, server_name: &str) {
+        let _ = server_name;
+    }
+}
+
+/// A TLS connection over a TCP socket.
+pub struct TlsSocket<'a, CipherSuite>
+where
+    CipherSuite: TlsCipherSuite + 'static,
+{
+    conn: TlsConnection<'a, TcpSocket<'a>, CipherSuite>,
+}
+
+impl<'a, CipherSuite> TlsSocket<'a, CipherSuite>
+where
+    CipherSuite: TlsCipherSuite + 'static,
+{
+    /// Create a new TLS connection over a connected TCP socket.
+    ///
+    /// The read buffer must hold an encrypted TLS record, which can be up to 16 kB, unless the
+    /// maximum fragment length is negotiated. The write buffer must be larger than
+    /// [`TLS_RECORD_OVERHEAD`], and the largest of both buffers must hold the handshake.
+    pub fn new(socket: TcpSocket<'a>, read_buffer: &'a mut [u8], write_buffer: &'a mut [u8]) -> Self {
+        Self {
+            conn: TlsConnection::new(socket, read_buffer, write_buffer),
+        }
+    }
+
+    /// Perform the TLS handshake.
+    ///
+    /// The `Verifier` checks the server certificate, use [`NoVerify`] to skip this check. If the
+    /// handshake fails, the connection must be recreated.
+    pub async fn open<Verifier, Rng>(
+        &mut self,
+        config: &TlsConfig<'_, CipherSuite>,
+        rng: &mut Rng,
+    ) -> Result<(), TlsError>
+    where
+        Verifier: for<'v> TlsVerifier<'v, CipherSuite>,
+        Rng: CryptoRng + RngCore,
+    {
+        self.conn.open::<Rng, Verifier>(TlsContext::new(config, rng)).await
+    }
+
+    /// Perform the TLS handshake with the server named `server_name`, resuming the session with
+    /// the key provided by `store` if there is one.
+    ///
+    /// `config` must not have a pre-shared key nor a server name. If the handshake with a stored
+    /// key fails, the key is invalidated.
+    pub async fn open_with_store<Verifier, Rng, Store>(
+        &mut self,
+        server_name: &str,
+        config: TlsConfig<'_, CipherSuite>,
+        store: &mut Store,
+        rng: &mut Rng,
+    ) -> Result<(), TlsError>
+    where
+        Verifier: for<'v> TlsVerifier<'v, CipherSuite>,
+        Rng: CryptoRng + RngCore,
+        Store: SessionStore,
+    {
+        let psk = store.load(server_name);
+        let resumed = psk.is_some();
+
+        let mut config = config.with_server_name(server_name);
+        if let Some(psk) = psk {
+            config = config.with_psk(psk.key, &[psk.identity]);
+        }
+        let res = self.open::<Verifier, Rng>(&config, rng).await;
+        drop(config);
+
+        if res.is_err() && resumed {
+            store.invalidate(server_name);
+        }
+        res
+    }
+
+    /// Close the TLS connection, returning the TCP socket.
+    ///
+    /// The TCP socket is not closed.
+    pub async fn close(self) -> Result<TcpSocket<'a>, (TcpSocket<'a>, TlsError)> {
+        self.conn.close().await
+    }
+}
+
+impl<'a, CipherSuite> embedded_io::Io for TlsSocket<'a, CipherSuite>
+where
+    CipherSuite: TlsCipherSuite + 'static,
+{
+    type Error = TlsError;
+}
+
+impl<'a, CipherSuite> embedded_io::asynch::Read for TlsSocket<'a, CipherSuite>
+where
+    CipherSuite: TlsCipherSuite + 'static,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.conn.read(buf).await
+    }
+}
+
+impl<'a, CipherSuite> embedded_io::asynch::BufRead for TlsSocket<'a, CipherSuite>
+where
+    CipherSuite: TlsCipherSuite + 'static,
+{
+    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        embedded_io::asynch::BufRead::fill_buf(&mut self.conn).await
+    }
+
+    fn consume(&mut self, amt: usize) {
+        embedded_io::asynch::BufRead::consume(&mut self.conn, amt)
+    }
+}
+
+impl<'a, CipherSuite> embedded_io::asynch::Write for TlsSocket<'a, CipherSuite>
+where
+    CipherSuite: TlsCipherSuite + 'static,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.conn.write(buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.conn.flush().await
+    }
+}
@@ -0,0 +1,213 @@
+//! Registers of the W5500, and its socket 0 opened in MACRAW mode to send and receive raw
+//! Ethernet frames.
+
+use embedded_hal_async::spi::{Operation, SpiDevice};
+
+use crate::InitError;
+
+// Blocks selected by the BSB bits of the control phase.
+const BLOCK_COMMON: u8 = 0x00;
+const fn block_socket(n: u8) -> u8 {
+    n * 4 + 1
+}
+const BLOCK_SOCKET0: u8 = block_socket(0);
+const BLOCK_TX0: u8 = 0x02;
+const BLOCK_RX0: u8 = 0x03;
+
+const CONTROL_WRITE: u8 = 1 << 2;
+
+// Common registers
+const MR: u16 = 0x0000;
+const SHAR: u16 = 0x0009;
+const SIMR: u16 = 0x0018;
+const PHYCFGR: u16 = 0x002E;
+const VERSIONR: u16 = 0x0039;
+
+const MR_RST: u8 = 1 << 7;
+const PHYCFGR_LNK: u8 = 1 << 0;
+const VERSION: u8 = 0x04;
+
+// Socket registers
+const SN_MR: u16 = 0x0000;
+const SN_CR: u16 = 0x0001;
+const SN_IR: u16 = 0x0002;
+const SN_SR: u16 = 0x0003;
+const SN_RXBUF_SIZE: u16 = 0x001E;
+const SN_TXBUF_SIZE: u16 = 0x001F;
+const SN_TX_FSR: u16 = 0x0020;
+const SN_TX_WR: u16 = 0x0024;
+const SN_RX_RSR: u16 = 0x0026;
+const SN_RX_RD: u16 = 0x0028;
+const SN_IMR: u16 = 0x002C;
+
+const SN_MR_MACRAW: u8 = 0x04;
+const SN_CR_OPEN: u8 = 0x01;
+const SN_CR_SEND: u8 = 0x20;
+const SN_CR_RECV: u8 = 0x40;
+const SN_IR_RECV: u8 = 1 << 2;
+const SN_SR_MACRAW: u8 = 0x42;
+
+/// Number of sockets of the W5500, which share 16 KiB of TX and RX buffers.
+const SOCKETS: u8 = 8;
+/// Size of the buffers of socket 0, in KiB. MACRAW can only be used on socket 0, which gets all
+/// the memory.
+const BUF_SIZE_KB: u8 = 16;
+
+/// Length of the header prepended by the W5500 to each frame in the RX buffer.
+const RX_HEADER_LEN: usize = 2;
+
+pub(crate) struct W5500<SPI> {
+    spi: SPI,
+}
+
+impl<SPI: SpiDevice> W5500<SPI> {
+    /// Reset the W5500, and open socket 0 in MACRAW mode with the MAC address `mac_addr`.
+    pub async fn new(spi: SPI, mac_addr: [u8; 6]) -> Result<Self, InitError<SPI::Error>> {
+        let mut this = Self { spi };
+
+        let version = this.read_u8(BLOCK_COMMON, VERSIONR).await?;
+        if version != VERSION {
+            return Err(InitError::UnknownChip(version));
+        }
+
+        this.write_u8(BLOCK_COMMON, MR, MR_RST).await?;
+        while this.read_u8(BLOCK_COMMON, MR).await? & MR_RST != 0 {}
+
+        this.write(BLOCK_COMMON, SHAR, &mac_addr).await?;
+
+        for n in 1..SOCKETS {
+            this.write_u8(block_socket(n), SN_RXBUF_SIZE, 0).await?;
+            this.write_u8(block_socket(n), SN_TXBUF_SIZE, 0).await?;
+        }
+        this.write_u8(BLOCK_SOCKET0, SN_RXBUF_SIZE, BUF_SIZE_KB).await?;
+        this.write_u8(BLOCK_SOCKET0, SN_TXBUF_SIZE, BUF_SIZE_KB).await?;
+
+        // Assert INTn when a frame is received on socket 0.
+        this.write_u8(BLOCK_COMMON, SIMR, 1 << 0).await?;
+        this.write_u8(BLOCK_SOCKET0, SN_IMR, SN_IR_RECV).await?;
+
+        // The MAC filter (MFEN) only lets the frames sent to our MAC address or broadcast through,
+        // and can't be given multicast groups, so it would drop mDNS and IPv6 neighbor discovery.
+        // Receive all the frames instead, and let the stack filter them.
+        this.write_u8(BLOCK_SOCKET0, SN_MR, SN_MR_MACRAW).await?;
+        this.command(SN_CR_OPEN).await?;
+        while this.read_u8(BLOCK_SOCKET0, SN_SR).await? != SN_SR_MACRAW {}
+
+        Ok(this)
+    }
+
+    /// Returns whether the PHY of the W5500 has a link.
+    pub async fn is_link_up(&mut self) -> Result<bool, SPI::Error> {
+        Ok(self.read_u8(BLOCK_COMMON, PHYCFGR).await? & PHYCFGR_LNK != 0)
+    }
+
+    /// Read the next received frame into `frame`, truncating it if it doesn't fit.
+    ///
+    /// Returns the length of the frame, or 0 if none is available, in which case the interrupt is
+    /// cleared.
+    pub async fn read_frame(&mut self, frame: &mut [u8]) -> Result<usize, SPI::Error> {
+        if self.rx_size().await? == 0 {
+            // Clear the interrupt, then check again for a frame received meanwhile, as it may
+            // not have raised the interrupt again.
+            self.write_u8(BLOCK_SOCKET0, SN_IR, SN_IR_RECV).await?;
+            if self.rx_size().await? == 0 {
+                return Ok(0);
+            }
+        }
+
+        let read_ptr = self.read_u16(BLOCK_SOCKET0, SN_RX_RD).await?;
+
+        let mut header = [0; RX_HEADER_LEN];
+        self.read(BLOCK_RX0, read_ptr, &mut header).await?;
+        // The length includes the header itself.
+        let len = (u16::from_be_bytes(header) as usize).saturating_sub(RX_HEADER_LEN);
+        let n = len.min(frame.len());
+        self.read(BLOCK_RX0, read_ptr.wrapping_add(RX_HEADER_LEN as u16), &mut frame[..n])
+            .await?;
+
+        self.write_u16(
+            BLOCK_SOCKET0,
+            SN_RX_RD,
+            read_ptr.wrapping_add((RX_HEADER_LEN + len) as u16),
+        )
+        .await?;
+        self.command(SN_CR_RECV).await?;
+
+        Ok(n)
+    }
+
+    /// Send `frame`, once there's room for it in the TX buffer.
+    pub async fn write_frame(&mut self, frame: &[u8]) -> Result<(), SPI::Error> {
+        while (self.read_u16_stable(SN_TX_FSR).await? as usize) < frame.len() {}
+
+        let write_ptr = self.read_u16(BLOCK_SOCKET0, SN_TX_WR).await?;
+        self.write(BLOCK_TX0, write_ptr, frame).await?;
+        self.write_u16(BLOCK_SOCKET0, SN_TX_WR, write_ptr.wrapping_add(frame.len() as u16))
+            .await?;
+        self.command(SN_CR_SEND).await
+    }
+
+    async fn rx_size(&mut self) -> Result<u16, SPI::Error> {
+        self.read_u16_stable(SN_RX_RSR).await
+    }
+
+    /// Run a command on socket 0, and wait for it to be accepted.
+    async fn command(&mut self, command: u8) -> Result<(), SPI::Error> {
+        self.write_u8(BLOCK_SOCKET0, SN_CR, command).await?;
+        while self.read_u8(BLOCK_SOCKET0, SN_CR).await? != 0 {}
+        Ok(())
+    }
+
+    /// Read a 16-bit register of socket 0 updated by the W5500, until two reads agree.
+    async fn read_u16_stable(&mut self, addr: u16) -> Result<u16, SPI::Error> {
+        let mut val = self.read_u16(BLOCK_SOCKET0, addr).await?;
+        loop {
+            let again = self.read_u16(BLOCK_SOCKET0, addr).await?;
+            if again == val {
+                return Ok(val);
+            }
+            val = again;
+        }
+    }
+
+    async fn read_u8(&mut self, block: u8, addr: u16) -> Result<u8, SPI::Error> {
+        let mut buf = [0];
+        self.read(block, addr, &mut buf).await?;
+        Ok(buf[0])
+    }
+
+    async fn read_u16(&mut self, block: u8, addr: u16) -> Result<u16, SPI::Error> {
+        let mut buf = [0; 2];
+        self.read(block, addr, &mut buf).await?;
+        Ok(u16::from_be_bytes(buf))
+    }
+
+    async fn write_u8(&mut self, block: u8, addr: u16, val: u8) -> Result<(), SPI::Error> {
+        self.write(block, addr, &[val]).await
+    }
+
+    async fn write_u16(&mut self, block: u8, addr: u16, val: u16) -> Result<(), SPI::Error> {
+        self.write(block, addr, &val.to_be_bytes()).await
+    }
+
+    async fn read(&mut self, block: u8, addr: u16, buf: &mut [u8]) -> Result<(), SPI::Error> {
+        let header = header(block, addr, false);
+        self.spi
+            .transaction(&mut [Operation::Write(&header), Operation::Read(buf)])
+            .await
+    }
+
+    async fn write(&mut self, block: u8, addr: u16, data: &[u8]) -> Result<(), SPI::Error> {
+        let header = header(block, addr, true);
+        self.spi
+            .transaction(&mut [Operation::Write(&header), Operation::Write(data)])
+            .await
+    }
+}
+
+/// Address and control phases of a frame in variable length data mode.
+fn header(block: u8, addr: u16, write: bool) -> [u8; 3] {
+    let [hi, lo] = addr.to_be_bytes();
+    let control = (block << 3) | if write { CONTROL_WRITE } else { 0 };
+    [hi, lo, control]
+}
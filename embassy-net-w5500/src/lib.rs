@@ -0,0 +1,129 @@
+#![no_std]
+#![warn(missing_docs)]
+#![doc = include_str!("../README.md")]
+
+// This mod MUST go first, so that the others see its macros.
+pub(crate) mod fmt;
+
+mod device;
+
+use embassy_futures::select::{select3, Either3};
+use embassy_net_driver_channel as ch;
+use embassy_net_driver_channel::driver::LinkState;
+use embassy_time::{Duration, Ticker, Timer};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::device::W5500;
+
+const MTU: usize = 1514;
+
+/// Interval at which the link of the PHY is checked.
+const LINK_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Internal state for the embassy-net integration.
+pub struct State<const N_RX: usize, const N_TX: usize> {
+    ch_state: ch::State<MTU, N_RX, N_TX>,
+}
+
+impl<const N_RX: usize, const N_TX: usize> State<N_RX, N_TX> {
+    /// Create a new `State`.
+    pub const fn new() -> Self {
+        Self {
+            ch_state: ch::State::new(),
+        }
+    }
+}
+
+/// Error returned by [`new`] when the W5500 can't be initialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InitError<SE> {
+    /// An error occurred on the SPI bus.
+    Spi(SE),
+    /// The version register didn't hold the version of the W5500, the chip is likely not
+    /// connected properly.
+    UnknownChip(u8),
+}
+
+impl<SE> From<SE> for InitError<SE> {
+    fn from(e: SE) -> Self {
+        InitError::Spi(e)
+    }
+}
+
+/// W5500 device for use with embassy-net.
+pub type Device<'d> = embassy_net_driver_channel::Device<'d, MTU>;
+
+/// Background runner for the W5500.
+///
+/// You must call `.run()` in a background task for the W5500 to operate.
+pub struct Runner<'d, SPI, INT> {
+    mac: W5500<SPI>,
+    int: INT,
+    ch: ch::Runner<'d, MTU>,
+}
+
+impl<'d, SPI: SpiDevice, INT: Wait> Runner<'d, SPI, INT> {
+    /// Run the W5500: receive frames when its interrupt is asserted, send the frames queued by
+    /// the stack, and keep the link state up to date.
+    pub async fn run(self) -> ! {
+        let Self { mut mac, mut int, ch } = self;
+        let (state_chan, mut rx_chan, mut tx_chan) = ch.split();
+        let mut link_poll = Ticker::every(LINK_POLL_INTERVAL);
+
+        loop {
+            match select3(
+                async {
+                    int.wait_for_low().await.ok();
+                    rx_chan.rx_buf().await
+                },
+                tx_chan.tx_buf(),
+                link_poll.next(),
+            )
+            .await
+            {
+                Either3::First(p) => match mac.read_frame(p).await {
+                    Ok(0) => {}
+                    Ok(n) => rx_chan.rx_done(n),
+                    Err(_) => warn!("w5500: failed to read frame"),
+                },
+                Either3::Second(p) => {
+                    if mac.write_frame(p).await.is_err() {
+                        warn!("w5500: failed to write frame");
+                    }
+                    tx_chan.tx_done();
+                }
+                Either3::Third(()) => match mac.is_link_up().await {
+                    Ok(true) => state_chan.set_link_state(LinkState::Up),
+                    Ok(false) => state_chan.set_link_state(LinkState::Down),
+                    Err(_) => warn!("w5500: failed to read link state"),
+                },
+            }
+        }
+    }
+}
+
+/// Reset and initialize a W5500, with the MAC address `mac_addr`.
+///
+/// `int` is the INTn pin of the W5500, and `reset` its RSTn pin. Socket 0 is opened in MACRAW
+/// mode, with all the buffer memory of the chip, so the other sockets are unusable.
+pub async fn new<const N_RX: usize, const N_TX: usize, SPI: SpiDevice, INT: Wait, RST: OutputPin>(
+    mac_addr: [u8; 6],
+    state: &mut State<N_RX, N_TX>,
+    spi: SPI,
+    int: INT,
+    mut reset: RST,
+) -> Result<(Device<'_>, Runner<'_, SPI, INT>), InitError<SPI::Error>> {
+    // Hold RSTn low for at least 500us, then wait for the PLL to lock.
+    reset.set_low().ok();
+    Timer::after(Duration::from_millis(1)).await;
+    reset.set_high().ok();
+    Timer::after(Duration::from_millis(2)).await;
+
+    let mac = W5500::new(spi, mac_addr).await?;
+
+    let (runner, device) = ch::new(&mut state.ch_state, mac_addr);
+    Ok((device, Runner { mac, int, ch: runner }))
+}
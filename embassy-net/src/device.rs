@@ -1,9 +1,12 @@
+use core::cell::Cell;
 use core::task::Context;
 
 use embassy_net_driver::{Capabilities, Checksum, Driver, Medium, RxToken, TxToken};
 use smoltcp::phy;
 use smoltcp::time::Instant;
 
+use crate::Stats;
+
 pub(crate) struct DriverAdapter<'d, 'c, T>
 where
     T: Driver,
@@ -11,24 +14,33 @@ where
     // must be Some when actually using this to rx/tx
     pub cx: Option<&'d mut Context<'c>>,
     pub inner: &'d mut T,
+    pub stats: &'d Cell<Stats>,
 }
 
 impl<'d, 'c, T> phy::Device for DriverAdapter<'d, 'c, T>
 where
     T: Driver,
 {
-    type RxToken<'a> = RxTokenAdapter<T::RxToken<'a>> where Self: 'a;
-    type TxToken<'a> = TxTokenAdapter<T::TxToken<'a>> where Self: 'a;
+    type RxToken<'a> = RxTokenAdapter<'a, T::RxToken<'a>> where Self: 'a;
+    type TxToken<'a> = TxTokenAdapter<'a, T::TxToken<'a>> where Self: 'a;
 
     fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let stats = self.stats;
         self.inner
             .receive(self.cx.as_deref_mut().unwrap())
-            .map(|(rx, tx)| (RxTokenAdapter(rx), TxTokenAdapter(tx)))
+            .map(|(rx, tx)| (RxTokenAdapter(rx, stats), TxTokenAdapter(tx, stats)))
     }
 
     /// Construct a transmit token.
     fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
-        self.inner.transmit(self.cx.as_deref_mut().unwrap()).map(TxTokenAdapter)
+        let stats = self.stats;
+        match self.inner.transmit(self.cx.as_deref_mut().unwrap()) {
+            Some(tx) => Some(TxTokenAdapter(tx, stats)),
+            None => {
+                update(stats, |s| s.tx_exhausted = s.tx_exhausted.wrapping_add(1));
+                None
+            }
+        }
     }
 
     /// Get a description of device capabilities.
@@ -69,11 +81,17 @@ where
     }
 }
 
-pub(crate) struct RxTokenAdapter<T>(T)
+fn update(stats: &Cell<Stats>, f: impl FnOnce(&mut Stats)) {
+    let mut s = stats.get();
+    f(&mut s);
+    stats.set(s);
+}
+
+pub(crate) struct RxTokenAdapter<'a, T>(T, &'a Cell<Stats>)
 where
     T: RxToken;
 
-impl<T> phy::RxToken for RxTokenAdapter<T>
+impl<'a, T> phy::RxToken for RxTokenAdapter<'a, T>
 where
     T: RxToken,
 {
@@ -81,15 +99,22 @@ where
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        self.0.consume(|buf| f(buf))
+        let stats = self.1;
+        self.0.consume(|buf| {
+            update(stats, |s| {
+                s.rx_packets = s.rx_packets.wrapping_add(1);
+                s.rx_bytes = s.rx_bytes.wrapping_add(buf.len() as u64);
+            });
+            f(buf)
+        })
     }
 }
 
-pub(crate) struct TxTokenAdapter<T>(T)
+pub(crate) struct TxTokenAdapter<'a, T>(T, &'a Cell<Stats>)
 where
     T: TxToken;
 
-impl<T> phy::TxToken for TxTokenAdapter<T>
+impl<'a, T> phy::TxToken for TxTokenAdapter<'a, T>
 where
     T: TxToken,
 {
@@ -97,6 +122,10 @@ where
     where
         F: FnOnce(&mut [u8]) -> R,
     {
+        update(self.1, |s| {
+            s.tx_packets = s.tx_packets.wrapping_add(1);
+            s.tx_bytes = s.tx_bytes.wrapping_add(len as u64);
+        });
         self.0.consume(len, |buf| f(buf))
     }
 }
@@ -0,0 +1,385 @@
+//! DHCPv4 server.
+//!
+//! The server assigns addresses from a pool to the hosts on the link, for example the hosts
+//! connecting to a SoftAP, or the host a USB Ethernet gadget is plugged into. It runs in the
+//! network stack task, see [`Stack::start_dhcp_server`](crate::Stack::start_dhcp_server).
+//!
+//! The stack must have a static IPv4 configuration: its address is used as server identifier,
+//! and its subnet mask is given to the clients.
+
+use embassy_time::{Duration, Instant};
+use heapless::Vec;
+use smoltcp::iface::SocketHandle;
+use smoltcp::socket::udp::{self, PacketMetadata};
+use smoltcp::wire::{
+    DhcpMessageType, DhcpPacket, DhcpRepr, EthernetAddress, IpEndpoint, Ipv4Address, DHCP_CLIENT_PORT, DHCP_SERVER_PORT,
+};
+
+use crate::{SocketStack, StaticConfigV4};
+
+/// Maximum size of the DHCP messages received and sent by the server.
+const MAX_MESSAGE_SIZE: usize = 576;
+/// Number of messages the socket buffers can hold.
+const MESSAGE_COUNT: usize = 2;
+
+/// How long an offered address is kept for the client, waiting for its request.
+const OFFER_DURATION: Duration = Duration::from_secs(60);
+
+/// DHCP server configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DhcpServerConfig {
+    /// First address of the pool of addresses assigned to clients.
+    ///
+    /// The pool must be in the subnet of the stack address. The stack address is never assigned,
+    /// even if it is in the pool.
+    pub pool_start: Ipv4Address,
+    /// Number of addresses in the pool.
+    pub pool_size: u16,
+    /// Duration of the leases.
+    pub lease_duration: Duration,
+    /// Default gateway given to clients, usually the stack address if it routes their traffic.
+    pub router: Option<Ipv4Address>,
+    /// DNS servers given to clients.
+    pub dns_servers: Vec<Ipv4Address, 3>,
+}
+
+/// Memory resources needed by the DHCP server.
+///
+/// `LEASES` is the maximum number of clients the server keeps an address for at the same time.
+pub struct DhcpServerResources<const LEASES: usize> {
+    leases: [Option<Lease>; LEASES],
+    rx_meta: [PacketMetadata; MESSAGE_COUNT],
+    rx_buffer: [u8; MAX_MESSAGE_SIZE * MESSAGE_COUNT],
+    tx_meta: [PacketMetadata; MESSAGE_COUNT],
+    tx_buffer: [u8; MAX_MESSAGE_SIZE * MESSAGE_COUNT],
+}
+
+impl<const LEASES: usize> DhcpServerResources<LEASES> {
+    /// Create a new set of DHCP server resources.
+    pub fn new() -> Self {
+        const INIT: Option<Lease> = None;
+        Self {
+            leases: [INIT; LEASES],
+            rx_meta: [PacketMetadata::EMPTY; MESSAGE_COUNT],
+            rx_buffer: [0; MAX_MESSAGE_SIZE * MESSAGE_COUNT],
+            tx_meta: [PacketMetadata::EMPTY; MESSAGE_COUNT],
+            tx_buffer: [0; MAX_MESSAGE_SIZE * MESSAGE_COUNT],
+        }
+    }
+}
+
+impl<const LEASES: usize> Default for DhcpServerResources<LEASES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Lease {
+    hardware_address: EthernetAddress,
+    address: Ipv4Address,
+    expires: Instant,
+}
+
+/// The fields of a client message the server needs.
+struct Request {
+    message_type: DhcpMessageType,
+    transaction_id: u32,
+    hardware_address: EthernetAddress,
+    client_ip: Ipv4Address,
+    requested_ip: Option<Ipv4Address>,
+    server_identifier: Option<Ipv4Address>,
+    broadcast: bool,
+}
+
+impl Request {
+    fn parse(payload: &[u8]) -> Option<Self> {
+        let packet = DhcpPacket::new_checked(payload).ok()?;
+        let repr = DhcpRepr::parse(&packet).ok()?;
+        Some(Self {
+            message_type: repr.message_type,
+            transaction_id: repr.transaction_id,
+            hardware_address: repr.client_hardware_address,
+            client_ip: repr.client_ip,
+            requested_ip: repr.requested_ip,
+            server_identifier: repr.server_identifier,
+            broadcast: repr.broadcast,
+        })
+    }
+}
+
+pub(crate) struct Server {
+    socket: SocketHandle,
+    config: DhcpServerConfig,
+    leases: &'static mut [Option<Lease>],
+}
+
+impl Server {
+    pub(crate) fn new<const LEASES: usize>(
+        s: &mut SocketStack,
+        config: DhcpServerConfig,
+        resources: &'static mut DhcpServerResources<LEASES>,
+    ) -> Self {
+        let DhcpServerResources {
+            leases,
+            rx_meta,
+            rx_buffer,
+            tx_meta,
+            tx_buffer,
+        } = resources;
+
+        let mut socket = udp::Socket::new(
+            udp::PacketBuffer::new(&mut rx_meta[..], &mut rx_buffer[..]),
+            udp::PacketBuffer::new(&mut tx_meta[..], &mut tx_buffer[..]),
+        );
+        unwrap!(socket.bind(DHCP_SERVER_PORT));
+
+        leases.fill(None);
+
+        Self {
+            socket: s.sockets.add(socket),
+            config,
+            leases: &mut leases[..],
+        }
+    }
+
+    pub(crate) fn socket(&self) -> SocketHandle {
+        self.socket
+    }
+
+    /// Process the received client messages, and queue the replies.
+    ///
+    /// Returns whether replies were queued, in which case the interface must be polled again.
+    pub(crate) fn poll(&mut self, s: &mut SocketStack, config: Option<&StaticConfigV4>) -> bool {
+        let socket = s.sockets.get_mut::<udp::Socket>(self.socket);
+        let mut replied = false;
+
+        while let Ok((payload, _)) = socket.recv() {
+            let Some(request) = Request::parse(payload) else {
+                debug!("dhcp server: ignoring invalid message");
+                continue;
+            };
+            // Without an address, the server can't identify itself.
+            let Some(config) = config else {
+                continue;
+            };
+
+            let now = Instant::now();
+            let reply = match request.message_type {
+                DhcpMessageType::Discover => self.discover(&request, config, now),
+                DhcpMessageType::Request => self.request(&request, config, now),
+                DhcpMessageType::Release => {
+                    self.release(&request, config);
+                    None
+                }
+                DhcpMessageType::Decline => {
+                    self.decline(&request, now);
+                    None
+                }
+                _ => None,
+            };
+
+            if let Some((message_type, address)) = reply {
+                match self.send(socket, &request, config, message_type, address) {
+                    Ok(()) => replied = true,
+                    Err(()) => warn!("dhcp server: failed to send reply"),
+                }
+            }
+        }
+
+        replied
+    }
+
+    fn discover(
+        &mut self,
+        request: &Request,
+        config: &StaticConfigV4,
+        now: Instant,
+    ) -> Option<(DhcpMessageType, Ipv4Address)> {
+        let address = self.offer_address(request, config, now)?;
+        let lease = self.lease_slot(request.hardware_address, now)?;
+
+        // Keep the address for the client until its request, unless it already has a longer lease.
+        let expires = match lease {
+            Some(lease) if lease.address == address => lease.expires.max(now + OFFER_DURATION),
+            _ => now + OFFER_DURATION,
+        };
+        *lease = Some(Lease {
+            hardware_address: request.hardware_address,
+            address,
+            expires,
+        });
+
+        debug!("dhcp server: offering {} to {}", address, request.hardware_address);
+        Some((DhcpMessageType::Offer, address))
+    }
+
+    fn request(
+        &mut self,
+        request: &Request,
+        config: &StaticConfigV4,
+        now: Instant,
+    ) -> Option<(DhcpMessageType, Ipv4Address)> {
+        // The client selected another server.
+        if matches!(request.server_identifier, Some(id) if id != config.address.address()) {
+            return None;
+        }
+
+        let address = match request.requested_ip {
+            Some(address) => address,
+            None if !request.client_ip.is_unspecified() => request.client_ip,
+            None => return None,
+        };
+
+        if !self.is_available(address, request.hardware_address, config, now) {
+            debug!("dhcp server: refusing {} to {}", address, request.hardware_address);
+            return Some((DhcpMessageType::Nak, Ipv4Address::UNSPECIFIED));
+        }
+
+        let expires = now + self.config.lease_duration;
+        let Some(lease) = self.lease_slot(request.hardware_address, now) else {
+            warn!("dhcp server: no free lease for {}", request.hardware_address);
+            return Some((DhcpMessageType::Nak, Ipv4Address::UNSPECIFIED));
+        };
+        *lease = Some(Lease {
+            hardware_address: request.hardware_address,
+            address,
+            expires,
+        });
+
+        debug!("dhcp server: leasing {} to {}", address, request.hardware_address);
+        Some((DhcpMessageType::Ack, address))
+    }
+
+    fn release(&mut self, request: &Request, config: &StaticConfigV4) {
+        if request.server_identifier != Some(config.address.address()) {
+            return;
+        }
+
+        for lease in self.leases.iter_mut() {
+            if matches!(lease, Some(l) if l.hardware_address == request.hardware_address && l.address == request.client_ip)
+            {
+                debug!(
+                    "dhcp server: {} released by {}",
+                    request.client_ip, request.hardware_address
+                );
+                *lease = None;
+            }
+        }
+    }
+
+    fn decline(&mut self, request: &Request, now: Instant) {
+        let Some(address) = request.requested_ip else {
+            return;
+        };
+
+        // The address is used by another host, don't assign it for a while.
+        for lease in self.leases.iter_mut().flatten() {
+            if lease.hardware_address == request.hardware_address && lease.address == address {
+                warn!("dhcp server: {} declined by {}", address, request.hardware_address);
+                lease.hardware_address = EthernetAddress([0; 6]);
+                lease.expires = now + self.config.lease_duration;
+            }
+        }
+    }
+
+    /// Choose the address offered to a client: the one it already has, then the one it asked
+    /// for, then the first free one.
+    fn offer_address(&self, request: &Request, config: &StaticConfigV4, now: Instant) -> Option<Ipv4Address> {
+        let current = self
+            .active_leases(now)
+            .find(|l| l.hardware_address == request.hardware_address)
+            .map(|l| l.address);
+        let requested = request
+            .requested_ip
+            .filter(|&a| self.is_available(a, request.hardware_address, config, now));
+
+        current.or(requested).or_else(|| {
+            (0..self.config.pool_size)
+                .map(|i| pool_address(self.config.pool_start, i))
+                .find(|&a| self.is_available(a, request.hardware_address, config, now))
+        })
+    }
+
+    /// Whether `address` can be assigned to the client with the given hardware address.
+    fn is_available(
+        &self,
+        address: Ipv4Address,
+        hardware_address: EthernetAddress,
+        config: &StaticConfigV4,
+        now: Instant,
+    ) -> bool {
+        let start = u32::from_be_bytes(self.config.pool_start.0);
+        let in_pool = u32::from_be_bytes(address.0).wrapping_sub(start) < self.config.pool_size as u32;
+
+        in_pool
+            && address != config.address.address()
+            && self
+                .active_leases(now)
+                .all(|l| l.address != address || l.hardware_address == hardware_address)
+    }
+
+    /// Find the lease of a client, or a free lease for it.
+    fn lease_slot(&mut self, hardware_address: EthernetAddress, now: Instant) -> Option<&mut Option<Lease>> {
+        let index = self
+            .leases
+            .iter()
+            .position(|l| matches!(l, Some(l) if l.hardware_address == hardware_address))
+            .or_else(|| {
+                self.leases
+                    .iter()
+                    .position(|l| !matches!(l, Some(l) if l.expires > now))
+            })?;
+        Some(&mut self.leases[index])
+    }
+
+    fn active_leases(&self, now: Instant) -> impl Iterator<Item = &Lease> {
+        self.leases.iter().flatten().filter(move |l| l.expires > now)
+    }
+
+    fn send(
+        &self,
+        socket: &mut udp::Socket<'static>,
+        request: &Request,
+        config: &StaticConfigV4,
+        message_type: DhcpMessageType,
+        address: Ipv4Address,
+    ) -> Result<(), ()> {
+        let nak = message_type == DhcpMessageType::Nak;
+        let dns_servers = (!nak && !self.config.dns_servers.is_empty()).then(|| self.config.dns_servers.clone());
+
+        let repr = DhcpRepr {
+            message_type,
+            transaction_id: request.transaction_id,
+            secs: 0,
+            client_hardware_address: request.hardware_address,
+            client_ip: Ipv4Address::UNSPECIFIED,
+            your_ip: address,
+            server_ip: Ipv4Address::UNSPECIFIED,
+            router: self.config.router.filter(|_| !nak),
+            subnet_mask: (!nak).then(|| config.address.netmask()),
+            relay_agent_ip: Ipv4Address::UNSPECIFIED,
+            broadcast: request.broadcast,
+            requested_ip: None,
+            client_identifier: None,
+            server_identifier: Some(config.address.address()),
+            parameter_request_list: None,
+            dns_servers,
+            max_size: None,
+            lease_duration: (!nak).then(|| self.config.lease_duration.as_secs() as u32),
+            renew_duration: None,
+            rebind_duration: None,
+            additional_options: &[],
+        };
+
+        // The client has no address yet, so replies are always broadcast.
+        let endpoint = IpEndpoint::new(Ipv4Address::BROADCAST.into(), DHCP_CLIENT_PORT);
+        let buf = socket.send(repr.buffer_len(), endpoint).map_err(|_| ())?;
+        buf.fill(0);
+        repr.emit(&mut DhcpPacket::new_unchecked(buf)).map_err(|_| ())
+    }
+}
+
+fn pool_address(start: Ipv4Address, index: u16) -> Ipv4Address {
+    Ipv4Address::from_bytes(&(u32::from_be_bytes(start.0).wrapping_add(index as u32)).to_be_bytes())
+}
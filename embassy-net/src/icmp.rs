@@ -0,0 +1,277 @@
+//! ICMP sockets.
+
+use core::cell::RefCell;
+use core::future::poll_fn;
+use core::mem;
+use core::task::Poll;
+
+use embassy_net_driver::Driver;
+use embassy_time::{with_timeout, Duration, Instant};
+use smoltcp::iface::{Interface, SocketHandle};
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::socket::icmp;
+pub use smoltcp::socket::icmp::{Endpoint as IcmpEndpoint, PacketMetadata};
+use smoltcp::wire::{Icmpv4Packet, Icmpv4Repr, IpAddress};
+#[cfg(feature = "proto-ipv6")]
+use smoltcp::wire::{Icmpv6Packet, Icmpv6Repr};
+
+use crate::{SocketStack, Stack};
+
+/// Size of the data sent in the echo requests of [`Stack::ping`].
+const PING_DATA_SIZE: usize = 16;
+/// Largest echo message of [`Stack::ping`], the ICMP header is 8 bytes long.
+const PING_MESSAGE_SIZE: usize = 8 + PING_DATA_SIZE;
+
+/// Error returned by [`IcmpSocket::bind`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BindError {
+    /// The socket was already open.
+    InvalidState,
+    /// The endpoint is unspecified, or its port is 0.
+    InvalidEndpoint,
+}
+
+/// Error returned by [`IcmpSocket::recv_from`] and [`IcmpSocket::send_to`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// No route to host.
+    NoRoute,
+}
+
+/// Error returned by [`Stack::ping`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PingError {
+    /// No route to host.
+    NoRoute,
+    /// The host didn't reply in time.
+    TimedOut,
+}
+
+/// An ICMP socket.
+///
+/// The socket sends and receives whole ICMP messages, header included. Once bound, it receives
+/// the echo replies with its identifier, or the errors caused by the datagrams of a UDP port.
+/// The messages sent must have a valid type and code, the checksum is computed by the stack.
+pub struct IcmpSocket<'a> {
+    stack: &'a RefCell<SocketStack>,
+    handle: SocketHandle,
+}
+
+impl<'a> IcmpSocket<'a> {
+    /// Create a new ICMP socket using the provided stack and buffers.
+    pub fn new<D: Driver>(
+        stack: &'a Stack<D>,
+        rx_meta: &'a mut [PacketMetadata],
+        rx_buffer: &'a mut [u8],
+        tx_meta: &'a mut [PacketMetadata],
+        tx_buffer: &'a mut [u8],
+    ) -> Self {
+        Self::new_inner(&stack.socket, rx_meta, rx_buffer, tx_meta, tx_buffer)
+    }
+
+    fn new_inner(
+        stack: &'a RefCell<SocketStack>,
+        rx_meta: &'a mut [PacketMetadata],
+        rx_buffer: &'a mut [u8],
+        tx_meta: &'a mut [PacketMetadata],
+        tx_buffer: &'a mut [u8],
+    ) -> Self {
+        let s = &mut *stack.borrow_mut();
+
+        let rx_meta: &'static mut [PacketMetadata] = unsafe { mem::transmute(rx_meta) };
+        let rx_buffer: &'static mut [u8] = unsafe { mem::transmute(rx_buffer) };
+        let tx_meta: &'static mut [PacketMetadata] = unsafe { mem::transmute(tx_meta) };
+        let tx_buffer: &'static mut [u8] = unsafe { mem::transmute(tx_buffer) };
+        let handle = s.sockets.add(icmp::Socket::new(
+            icmp::PacketBuffer::new(rx_meta, rx_buffer),
+            icmp::PacketBuffer::new(tx_meta, tx_buffer),
+        ));
+
+        Self { stack, handle }
+    }
+
+    /// Bind the socket to an echo identifier, or to a UDP port to receive its errors.
+    pub fn bind<T>(&mut self, endpoint: T) -> Result<(), BindError>
+    where
+        T: Into<IcmpEndpoint>,
+    {
+        match self.with_mut(|s, _| s.bind(endpoint)) {
+            Ok(()) => Ok(()),
+            Err(icmp::BindError::InvalidState) => Err(BindError::InvalidState),
+            Err(icmp::BindError::Unaddressable) => Err(BindError::InvalidEndpoint),
+        }
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&icmp::Socket, &Interface) -> R) -> R {
+        let s = &*self.stack.borrow();
+        let socket = s.sockets.get::<icmp::Socket>(self.handle);
+        f(socket, &s.iface)
+    }
+
+    fn with_mut<R>(&self, f: impl FnOnce(&mut icmp::Socket, &mut Interface) -> R) -> R {
+        let s = &mut *self.stack.borrow_mut();
+        let socket = s.sockets.get_mut::<icmp::Socket>(self.handle);
+        let res = f(socket, &mut s.iface);
+        s.waker.wake();
+        res
+    }
+
+    /// Set the hop limit (IPv4 TTL) of the messages sent by the socket.
+    ///
+    /// If `None`, the default of 64 is used.
+    pub fn set_hop_limit(&mut self, hop_limit: Option<u8>) {
+        self.with_mut(|s, _| s.set_hop_limit(hop_limit))
+    }
+
+    /// Receive a message.
+    ///
+    /// This method will wait until a message is received. If `buf` is too small, the message
+    /// is truncated.
+    ///
+    /// Returns the number of bytes received and the address of the sender.
+    pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, IpAddress), Error> {
+        poll_fn(move |cx| {
+            self.with_mut(|s, _| match s.recv_slice(buf) {
+                Ok(x) => Poll::Ready(Ok(x)),
+                // No data ready
+                Err(icmp::RecvError::Exhausted) => {
+                    s.register_recv_waker(cx.waker());
+                    Poll::Pending
+                }
+            })
+        })
+        .await
+    }
+
+    /// Send a message to the specified remote address.
+    pub async fn send_to<T>(&self, buf: &[u8], remote_addr: T) -> Result<(), Error>
+    where
+        T: Into<IpAddress>,
+    {
+        let remote_addr = remote_addr.into();
+        poll_fn(move |cx| {
+            self.with_mut(|s, _| match s.send_slice(buf, remote_addr) {
+                // Entire message has been sent
+                Ok(()) => Poll::Ready(Ok(())),
+                Err(icmp::SendError::BufferFull) => {
+                    s.register_send_waker(cx.waker());
+                    Poll::Pending
+                }
+                Err(icmp::SendError::Unaddressable) => Poll::Ready(Err(Error::NoRoute)),
+            })
+        })
+        .await
+    }
+
+    /// Returns whether the socket is open.
+    pub fn is_open(&self) -> bool {
+        self.with(|s, _| s.is_open())
+    }
+
+    /// Returns whether the socket is ready to send data, i.e. it has enough buffer space to hold a message.
+    pub fn may_send(&self) -> bool {
+        self.with(|s, _| s.can_send())
+    }
+
+    /// Returns whether the socket is ready to receive data, i.e. it has received a message that's now in the buffer.
+    pub fn may_recv(&self) -> bool {
+        self.with(|s, _| s.can_recv())
+    }
+}
+
+impl Drop for IcmpSocket<'_> {
+    fn drop(&mut self) {
+        self.stack.borrow_mut().sockets.remove(self.handle);
+    }
+}
+
+/// Send an echo request to `addr`, and wait for the reply. See [`Stack::ping`].
+pub(crate) async fn ping(
+    stack: &RefCell<SocketStack>,
+    addr: IpAddress,
+    timeout: Duration,
+) -> Result<Duration, PingError> {
+    let mut rx_meta = [PacketMetadata::EMPTY; 1];
+    let mut rx_buffer = [0; PING_MESSAGE_SIZE];
+    let mut tx_meta = [PacketMetadata::EMPTY; 1];
+    let mut tx_buffer = [0; PING_MESSAGE_SIZE];
+    let mut socket = IcmpSocket::new_inner(stack, &mut rx_meta, &mut rx_buffer, &mut tx_meta, &mut tx_buffer);
+
+    // Identify our replies with a value unlikely to be used by another socket.
+    let ident = stack.borrow_mut().get_local_port();
+    socket.bind(IcmpEndpoint::Ident(ident)).unwrap();
+
+    let seq_no = 0;
+    let data = [0xa5; PING_DATA_SIZE];
+    let mut request = [0; PING_MESSAGE_SIZE];
+    match addr {
+        IpAddress::Ipv4(_) => {
+            let repr = Icmpv4Repr::EchoRequest {
+                ident,
+                seq_no,
+                data: &data,
+            };
+            repr.emit(
+                &mut Icmpv4Packet::new_unchecked(&mut request[..]),
+                &ChecksumCapabilities::default(),
+            );
+        }
+        #[cfg(feature = "proto-ipv6")]
+        IpAddress::Ipv6(_) => {
+            let repr = Icmpv6Repr::EchoRequest {
+                ident,
+                seq_no,
+                data: &data,
+            };
+            // The checksum depends on the source address, it is computed again by the stack.
+            repr.emit(
+                &IpAddress::Ipv6(Default::default()),
+                &addr,
+                &mut Icmpv6Packet::new_unchecked(&mut request[..]),
+                &ChecksumCapabilities::ignored(),
+            );
+        }
+    }
+
+    let start = Instant::now();
+    socket.send_to(&request, addr).await.map_err(|_| PingError::NoRoute)?;
+
+    let wait_reply = async {
+        let mut reply = [0; PING_MESSAGE_SIZE];
+        loop {
+            if let Ok((n, from)) = socket.recv_from(&mut reply).await {
+                if from == addr && is_echo_reply(&reply[..n], addr, ident, seq_no) {
+                    return;
+                }
+            }
+        }
+    };
+    with_timeout(timeout, wait_reply)
+        .await
+        .map_err(|_| PingError::TimedOut)?;
+
+    Ok(Instant::now() - start)
+}
+
+/// Returns whether `message`, received from `addr`, is the echo reply with `ident` and `seq_no`.
+fn is_echo_reply(message: &[u8], addr: IpAddress, ident: u16, seq_no: u16) -> bool {
+    // The socket only receives messages with a valid checksum.
+    let caps = ChecksumCapabilities::ignored();
+
+    match addr {
+        IpAddress::Ipv4(_) => {
+            let repr = Icmpv4Packet::new_checked(message).and_then(|p| Icmpv4Repr::parse(&p, &caps));
+            matches!(repr, Ok(Icmpv4Repr::EchoReply { ident: i, seq_no: s, .. }) if i == ident && s == seq_no)
+        }
+        #[cfg(feature = "proto-ipv6")]
+        IpAddress::Ipv6(_) => {
+            let unspecified = IpAddress::Ipv6(Default::default());
+            let repr = Icmpv6Packet::new_checked(message)
+                .and_then(|p| Icmpv6Repr::parse(&unspecified, &unspecified, &p, &caps));
+            matches!(repr, Ok(Icmpv6Repr::EchoReply { ident: i, seq_no: s, .. }) if i == ident && s == seq_no)
+        }
+    }
+}
@@ -7,15 +7,25 @@
 pub(crate) mod fmt;
 
 mod device;
+#[cfg(feature = "dhcp-server")]
+pub mod dhcp_server;
 #[cfg(feature = "dns")]
 pub mod dns;
+#[cfg(feature = "icmp")]
+pub mod icmp;
+#[cfg(feature = "mdns")]
+pub mod mdns;
+#[cfg(feature = "raw")]
+pub mod raw;
+#[cfg(feature = "slaac")]
+mod slaac;
 #[cfg(feature = "tcp")]
 pub mod tcp;
 mod time;
 #[cfg(feature = "udp")]
 pub mod udp;
 
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 use core::future::{poll_fn, Future};
 use core::task::{Context, Poll};
 
@@ -51,6 +61,8 @@ pub struct StackResources<const SOCK: usize> {
     sockets: [SocketStorage<'static>; SOCK],
     #[cfg(feature = "dns")]
     queries: [Option<dns::DnsQuery>; MAX_QUERIES],
+    #[cfg(feature = "slaac")]
+    slaac: slaac::SlaacResources,
 }
 
 impl<const SOCK: usize> StackResources<SOCK> {
@@ -62,13 +74,15 @@ impl<const SOCK: usize> StackResources<SOCK> {
             sockets: [SocketStorage::EMPTY; SOCK],
             #[cfg(feature = "dns")]
             queries: [INIT; MAX_QUERIES],
+            #[cfg(feature = "slaac")]
+            slaac: slaac::SlaacResources::new(),
         }
     }
 }
 
-/// Static IP address configuration.
+/// Static IPv4 address configuration.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct StaticConfig {
+pub struct StaticConfigV4 {
     /// IP address and subnet mask.
     pub address: Ipv4Cidr,
     /// Default gateway.
@@ -77,6 +91,18 @@ pub struct StaticConfig {
     pub dns_servers: Vec<Ipv4Address, 3>,
 }
 
+/// Static IPv6 address configuration.
+#[cfg(feature = "proto-ipv6")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticConfigV6 {
+    /// IP address and subnet mask.
+    pub address: Ipv6Cidr,
+    /// Default gateway.
+    pub gateway: Option<Ipv6Address>,
+    /// DNS servers.
+    pub dns_servers: Vec<Ipv6Address, 3>,
+}
+
 /// DHCP configuration.
 #[cfg(feature = "dhcpv4")]
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -112,14 +138,114 @@ impl Default for DhcpConfig {
 }
 
 /// Network stack configuration.
-pub enum Config {
-    /// Use a static IP address configuration.
-    Static(StaticConfig),
-    /// Use DHCP to obtain an IP address configuration.
+///
+/// IPv4 and IPv6 are configured independently. When both are enabled, sockets bound without
+/// an address accept the traffic of both families.
+///
+/// smoltcp holds 2 addresses by default, while a dual-stack configuration using SLAAC needs 3
+/// (IPv4, IPv6 link-local and IPv6 global). Set `SMOLTCP_IFACE_MAX_ADDR_COUNT` accordingly.
+#[derive(Default)]
+pub struct Config {
+    /// IPv4 configuration.
+    pub ipv4: ConfigV4,
+    /// IPv6 configuration.
+    #[cfg(feature = "proto-ipv6")]
+    pub ipv6: ConfigV6,
+}
+
+impl Config {
+    /// IPv4 configuration with a static address.
+    pub fn ipv4_static(config: StaticConfigV4) -> Self {
+        Self {
+            ipv4: ConfigV4::Static(config),
+            #[cfg(feature = "proto-ipv6")]
+            ipv6: ConfigV6::None,
+        }
+    }
+
+    /// IPv6 configuration with a static address.
+    #[cfg(feature = "proto-ipv6")]
+    pub fn ipv6_static(config: StaticConfigV6) -> Self {
+        Self {
+            ipv4: ConfigV4::None,
+            ipv6: ConfigV6::Static(config),
+        }
+    }
+
+    /// IPv4 configuration using DHCP.
+    #[cfg(feature = "dhcpv4")]
+    pub fn dhcpv4(config: DhcpConfig) -> Self {
+        Self {
+            ipv4: ConfigV4::Dhcp(config),
+            #[cfg(feature = "proto-ipv6")]
+            ipv6: ConfigV6::None,
+        }
+    }
+
+    /// IPv6 configuration using SLAAC.
+    #[cfg(feature = "slaac")]
+    pub fn slaac() -> Self {
+        Self {
+            ipv4: ConfigV4::None,
+            ipv6: ConfigV6::Slaac,
+        }
+    }
+}
+
+/// Network stack IPv4 configuration.
+#[derive(Default)]
+pub enum ConfigV4 {
+    /// Do not use IPv4.
+    #[default]
+    None,
+    /// Use a static IPv4 address configuration.
+    Static(StaticConfigV4),
+    /// Use DHCP to obtain an IPv4 address configuration.
     #[cfg(feature = "dhcpv4")]
     Dhcp(DhcpConfig),
 }
 
+/// Network stack IPv6 configuration.
+#[cfg(feature = "proto-ipv6")]
+#[derive(Default)]
+pub enum ConfigV6 {
+    /// Do not use IPv6.
+    #[default]
+    None,
+    /// Use a static IPv6 address configuration.
+    Static(StaticConfigV6),
+    /// Use stateless address autoconfiguration (SLAAC).
+    ///
+    /// The stack uses a link-local address derived from its MAC address, and a global address
+    /// built from the prefix advertised by the routers on the link. Duplicate address detection
+    /// is not performed, and the DNS servers advertised by the routers are ignored.
+    #[cfg(feature = "slaac")]
+    Slaac,
+}
+
+/// Statistics of the network interface, returned by [`Stack::stats`].
+///
+/// The counters start at zero when the stack is created, and wrap around on overflow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Stats {
+    /// Packets received from the driver.
+    pub rx_packets: u64,
+    /// Bytes received from the driver, including the link layer headers.
+    pub rx_bytes: u64,
+    /// Packets passed to the driver for transmission.
+    pub tx_packets: u64,
+    /// Bytes passed to the driver for transmission, including the link layer headers.
+    pub tx_bytes: u64,
+    /// Times a packet couldn't be sent because the driver had no transmit buffer available.
+    ///
+    /// The packet is not lost, it stays queued and is sent on a later poll. A steadily growing
+    /// count means the driver can't keep up with the traffic.
+    pub tx_exhausted: u64,
+    /// Sockets currently in the stack, including the ones used internally for DHCP and DNS.
+    pub sockets: usize,
+}
+
 /// A network stack.
 ///
 /// This is the main entry point for the network stack.
@@ -130,10 +256,17 @@ pub struct Stack<D: Driver> {
 
 struct Inner<D: Driver> {
     device: D,
+    stats: Cell<Stats>,
     link_up: bool,
-    config: Option<StaticConfig>,
+    static_v4: Option<StaticConfigV4>,
+    #[cfg(feature = "proto-ipv6")]
+    static_v6: Option<StaticConfigV6>,
     #[cfg(feature = "dhcpv4")]
     dhcp_socket: Option<SocketHandle>,
+    #[cfg(feature = "slaac")]
+    slaac: Option<slaac::Slaac>,
+    #[cfg(feature = "dhcp-server")]
+    dhcp_server: Option<dhcp_server::Server>,
     #[cfg(feature = "dns")]
     dns_socket: SocketHandle,
     #[cfg(feature = "dns")]
@@ -144,6 +277,10 @@ pub(crate) struct SocketStack {
     pub(crate) sockets: SocketSet<'static>,
     pub(crate) iface: Interface,
     pub(crate) waker: WakerRegistration,
+    /// Woken after packets were sent or received, for the changes smoltcp doesn't wake the
+    /// sockets for.
+    #[cfg(feature = "tcp")]
+    pub(crate) dispatch_waker: WakerRegistration,
     next_local_port: u16,
 }
 
@@ -165,11 +302,13 @@ impl<D: Driver + 'static> Stack<D> {
             iface_cfg.hardware_addr = Some(HardwareAddress::Ethernet(EthernetAddress(device.ethernet_address())));
         }
 
+        let stats = Cell::new(Stats::default());
         let iface = Interface::new(
             iface_cfg,
             &mut DriverAdapter {
                 inner: &mut device,
                 cx: None,
+                stats: &stats,
             },
         );
 
@@ -181,15 +320,24 @@ impl<D: Driver + 'static> Stack<D> {
             sockets,
             iface,
             waker: WakerRegistration::new(),
+            #[cfg(feature = "tcp")]
+            dispatch_waker: WakerRegistration::new(),
             next_local_port,
         };
 
         let mut inner = Inner {
             device,
+            stats,
             link_up: false,
-            config: None,
+            static_v4: None,
+            #[cfg(feature = "proto-ipv6")]
+            static_v6: None,
             #[cfg(feature = "dhcpv4")]
             dhcp_socket: None,
+            #[cfg(feature = "slaac")]
+            slaac: None,
+            #[cfg(feature = "dhcp-server")]
+            dhcp_server: None,
             #[cfg(feature = "dns")]
             dns_socket: socket.sockets.add(dns::Socket::new(
                 &[],
@@ -199,18 +347,32 @@ impl<D: Driver + 'static> Stack<D> {
             dns_waker: WakerRegistration::new(),
         };
 
-        match config {
-            Config::Static(config) => {
-                inner.apply_config(&mut socket, config);
+        match config.ipv4 {
+            ConfigV4::None => {}
+            ConfigV4::Static(config) => {
+                inner.apply_config_v4(&mut socket, config);
             }
             #[cfg(feature = "dhcpv4")]
-            Config::Dhcp(config) => {
+            ConfigV4::Dhcp(config) => {
                 let mut dhcp_socket = smoltcp::socket::dhcpv4::Socket::new();
                 inner.apply_dhcp_config(&mut dhcp_socket, config);
                 let handle = socket.sockets.add(dhcp_socket);
                 inner.dhcp_socket = Some(handle);
             }
         }
+        #[cfg(feature = "proto-ipv6")]
+        match config.ipv6 {
+            ConfigV6::None => {}
+            ConfigV6::Static(config) => {
+                inner.apply_config_v6(&mut socket, config);
+            }
+            #[cfg(feature = "slaac")]
+            ConfigV6::Slaac => {
+                let mac = inner.device.ethernet_address();
+                inner.slaac = Some(slaac::Slaac::new(&mut socket, &mut resources.slaac, mac));
+                inner.apply_ip_addrs(&mut socket);
+            }
+        }
 
         Self {
             socket: RefCell::new(socket),
@@ -237,14 +399,40 @@ impl<D: Driver + 'static> Stack<D> {
     }
 
     /// Get whether the network stack has a valid IP configuration.
-    /// This is true if the network stack has a static IP configuration or if DHCP has completed
+    /// This is true if the network stack has a static IP configuration, or if DHCP or SLAAC has
+    /// completed, for either IPv4 or IPv6.
     pub fn is_config_up(&self) -> bool {
-        self.with(|_s, i| i.config.is_some())
+        let v4_up = self.with(|_s, i| i.static_v4.is_some());
+
+        #[cfg(feature = "proto-ipv6")]
+        let v6_up = self.with(|_s, i| i.static_v6.is_some());
+        #[cfg(not(feature = "proto-ipv6"))]
+        let v6_up = false;
+
+        v4_up || v6_up
     }
 
-    /// Get the current IP configuration.
-    pub fn config(&self) -> Option<StaticConfig> {
-        self.with(|_s, i| i.config.clone())
+    /// Get the current IPv4 configuration.
+    pub fn config_v4(&self) -> Option<StaticConfigV4> {
+        self.with(|_s, i| i.static_v4.clone())
+    }
+
+    /// Get the current IPv6 configuration.
+    #[cfg(feature = "proto-ipv6")]
+    pub fn config_v6(&self) -> Option<StaticConfigV6> {
+        self.with(|_s, i| i.static_v6.clone())
+    }
+
+    /// Get the statistics of the network interface.
+    ///
+    /// The per-socket counters are returned by the `stats` method of the sockets. smoltcp doesn't
+    /// expose its TCP retransmissions nor the contents of its neighbor cache, so they aren't
+    /// reported.
+    pub fn stats(&self) -> Stats {
+        self.with(|s, i| Stats {
+            sockets: s.sockets.iter().count(),
+            ..i.stats.get()
+        })
     }
 
     /// Run the network stack.
@@ -259,6 +447,30 @@ impl<D: Driver + 'static> Stack<D> {
         unreachable!()
     }
 
+    /// Start a DHCP server, assigning addresses to the other hosts on the link.
+    ///
+    /// The server runs in [`run`](Self::run). It only answers while the stack has a static IPv4
+    /// configuration, see the [`dhcp_server`] module. If a server is already running, it is
+    /// replaced, and its leases are forgotten.
+    #[cfg(feature = "dhcp-server")]
+    pub fn start_dhcp_server<const LEASES: usize>(
+        &self,
+        config: dhcp_server::DhcpServerConfig,
+        resources: &'static mut dhcp_server::DhcpServerResources<LEASES>,
+    ) {
+        self.with_mut(|s, i| {
+            i.stop_dhcp_server(s);
+            i.dhcp_server = Some(dhcp_server::Server::new(s, config, resources));
+            s.waker.wake();
+        })
+    }
+
+    /// Stop the DHCP server, if one is running.
+    #[cfg(feature = "dhcp-server")]
+    pub fn stop_dhcp_server(&self) {
+        self.with_mut(|s, i| i.stop_dhcp_server(s))
+    }
+
     /// Make a query for a given name and return the corresponding IP addresses.
     #[cfg(feature = "dns")]
     pub async fn dns_query(&self, name: &str, qtype: dns::DnsQueryType) -> Result<Vec<IpAddress, 1>, dns::Error> {
@@ -328,33 +540,123 @@ impl<D: Driver + 'static> Stack<D> {
 
         res
     }
+
+    /// Send an ICMP echo request to `addr`, and wait for the reply.
+    ///
+    /// Returns the round-trip time, or [`TimedOut`](icmp::PingError::TimedOut) if no reply was
+    /// received within `timeout`. This uses a socket of the stack while waiting, so one must be
+    /// free in the [`StackResources`].
+    #[cfg(feature = "icmp")]
+    pub async fn ping<T>(
+        &self,
+        addr: T,
+        timeout: embassy_time::Duration,
+    ) -> Result<embassy_time::Duration, icmp::PingError>
+    where
+        T: Into<IpAddress>,
+    {
+        icmp::ping(&self.socket, addr.into(), timeout).await
+    }
 }
 
 #[cfg(feature = "igmp")]
-impl<D: Driver + smoltcp::phy::Device + 'static> Stack<D> {
+impl<D: Driver + 'static> Stack<D> {
     /// Join a multicast group.
-    pub fn join_multicast_group<T>(&self, addr: T) -> Result<bool, MulticastError>
+    ///
+    /// Once joined, the datagrams sent to the group are received by the UDP sockets bound to
+    /// their port. An IGMP membership report is sent for IPv4 groups, this waits for the driver
+    /// to be able to transmit it. IPv6 groups are not supported, but IPv6 multicast datagrams are
+    /// received without joining, if the driver passes them.
+    ///
+    /// Returns `Ok(true)` if the group was joined and the report was sent, and `Ok(false)` if
+    /// the group was already joined.
+    pub async fn join_multicast_group<T>(&self, addr: T) -> Result<bool, MulticastError>
+    where
+        T: Into<IpAddress>,
+    {
+        let addr = addr.into();
+        poll_fn(|cx| self.poll_join_multicast_group(addr, cx)).await
+    }
+
+    /// Join a multicast group, registering a waker if the membership report can't be sent yet.
+    ///
+    /// See [`join_multicast_group`](Self::join_multicast_group).
+    pub fn poll_join_multicast_group<T>(&self, addr: T, cx: &mut Context<'_>) -> Poll<Result<bool, MulticastError>>
     where
         T: Into<IpAddress>,
     {
         let addr = addr.into();
 
         self.with_mut(|s, i| {
-            s.iface
-                .join_multicast_group(&mut i.device, addr, instant_to_smoltcp(Instant::now()))
+            let mut smoldev = DriverAdapter {
+                cx: Some(cx),
+                inner: &mut i.device,
+                stats: &i.stats,
+            };
+            let timestamp = instant_to_smoltcp(Instant::now());
+            match s.iface.join_multicast_group(&mut smoldev, addr, timestamp) {
+                Err(MulticastError::Exhausted) => {
+                    // smoltcp added the group before failing to send the report. Remove it, so
+                    // the report is sent when retrying. The driver has no transmit buffer, so
+                    // no leave message is sent either.
+                    let _ = s.iface.leave_multicast_group(&mut smoldev, addr, timestamp);
+                    Poll::Pending
+                }
+                res => {
+                    s.waker.wake();
+                    Poll::Ready(res)
+                }
+            }
         })
     }
 
     /// Leave a multicast group.
-    pub fn leave_multicast_group<T>(&self, addr: T) -> Result<bool, MulticastError>
+    ///
+    /// For IPv4 groups, this waits for the driver to be able to transmit an IGMP leave message.
+    ///
+    /// Returns `Ok(true)` if the group was left and the message was sent, and `Ok(false)` if the
+    /// group wasn't joined.
+    pub async fn leave_multicast_group<T>(&self, addr: T) -> Result<bool, MulticastError>
+    where
+        T: Into<IpAddress>,
+    {
+        let addr = addr.into();
+        poll_fn(|cx| self.poll_leave_multicast_group(addr, cx)).await
+    }
+
+    /// Leave a multicast group, registering a waker if the leave message can't be sent yet.
+    ///
+    /// See [`leave_multicast_group`](Self::leave_multicast_group).
+    pub fn poll_leave_multicast_group<T>(&self, addr: T, cx: &mut Context<'_>) -> Poll<Result<bool, MulticastError>>
     where
         T: Into<IpAddress>,
     {
         let addr = addr.into();
 
         self.with_mut(|s, i| {
-            s.iface
-                .leave_multicast_group(&mut i.device, addr, instant_to_smoltcp(Instant::now()))
+            if !s.iface.has_multicast_group(addr) {
+                return Poll::Ready(Ok(false));
+            }
+
+            let mut smoldev = DriverAdapter {
+                cx: Some(cx),
+                inner: &mut i.device,
+                stats: &i.stats,
+            };
+            let timestamp = instant_to_smoltcp(Instant::now());
+            match s.iface.leave_multicast_group(&mut smoldev, addr, timestamp) {
+                Err(MulticastError::Exhausted) => {
+                    // smoltcp removed the group before failing to send the leave message. Add it
+                    // back, so the message is sent when retrying. The driver has no transmit
+                    // buffer, so no report is sent either.
+                    let _ = s.iface.join_multicast_group(&mut smoldev, addr, timestamp);
+                    Poll::Pending
+                }
+                res => {
+                    s.waker.wake();
+                    Poll::Ready(res)
+                }
+            }
         })
     }
 
@@ -374,20 +676,13 @@ impl SocketStack {
 }
 
 impl<D: Driver + 'static> Inner<D> {
-    fn apply_config(&mut self, s: &mut SocketStack, config: StaticConfig) {
+    fn apply_config_v4(&mut self, s: &mut SocketStack, config: StaticConfigV4) {
         #[cfg(feature = "medium-ethernet")]
         let medium = self.device.capabilities().medium;
 
-        debug!("Acquired IP configuration:");
+        debug!("Acquired IPv4 configuration:");
 
         debug!("   IP address:      {}", config.address);
-        s.iface.update_ip_addrs(|addrs| {
-            if addrs.is_empty() {
-                addrs.push(IpCidr::Ipv4(config.address)).unwrap();
-            } else {
-                addrs[0] = IpCidr::Ipv4(config.address);
-            }
-        });
 
         #[cfg(feature = "medium-ethernet")]
         if medium == Medium::Ethernet {
@@ -403,14 +698,82 @@ impl<D: Driver + 'static> Inner<D> {
             debug!("   DNS server {}:    {}", i, s);
         }
 
+        self.static_v4 = Some(config);
+        self.apply_ip_addrs(s);
         #[cfg(feature = "dns")]
-        {
-            let socket = s.sockets.get_mut::<smoltcp::socket::dns::Socket>(self.dns_socket);
-            let servers: Vec<IpAddress, 3> = config.dns_servers.iter().map(|c| IpAddress::Ipv4(*c)).collect();
-            socket.update_servers(&servers[..]);
+        self.update_dns_servers(s);
+    }
+
+    #[cfg(feature = "proto-ipv6")]
+    fn apply_config_v6(&mut self, s: &mut SocketStack, config: StaticConfigV6) {
+        #[cfg(feature = "medium-ethernet")]
+        let medium = self.device.capabilities().medium;
+
+        debug!("Acquired IPv6 configuration:");
+
+        debug!("   IP address:      {}", config.address);
+
+        #[cfg(feature = "medium-ethernet")]
+        if medium == Medium::Ethernet {
+            if let Some(gateway) = config.gateway {
+                debug!("   Default gateway: {}", gateway);
+                s.iface.routes_mut().add_default_ipv6_route(gateway).unwrap();
+            } else {
+                debug!("   Default gateway: None");
+                s.iface.routes_mut().remove_default_ipv6_route();
+            }
+        }
+        for (i, s) in config.dns_servers.iter().enumerate() {
+            debug!("   DNS server {}:    {}", i, s);
         }
 
-        self.config = Some(config)
+        self.static_v6 = Some(config);
+        self.apply_ip_addrs(s);
+        #[cfg(feature = "dns")]
+        self.update_dns_servers(s);
+    }
+
+    /// Set the interface addresses from the current configurations.
+    fn apply_ip_addrs(&mut self, s: &mut SocketStack) {
+        let v4 = self.static_v4.as_ref().map(|c| IpCidr::Ipv4(c.address));
+        #[cfg(feature = "proto-ipv6")]
+        let v6 = self.static_v6.as_ref().map(|c| IpCidr::Ipv6(c.address));
+        #[cfg(not(feature = "proto-ipv6"))]
+        let v6 = None;
+        #[cfg(feature = "slaac")]
+        let link_local = self.slaac.as_ref().map(|slaac| IpCidr::Ipv6(slaac.link_local()));
+        #[cfg(not(feature = "slaac"))]
+        let link_local = None;
+
+        s.iface.update_ip_addrs(|addrs| {
+            addrs.clear();
+            for addr in [v4, v6, link_local].into_iter().flatten() {
+                if addrs.push(addr).is_err() {
+                    warn!("Too many IP addresses, {} is not used", addr);
+                }
+            }
+        });
+    }
+
+    /// Give the DNS servers of all the configurations to the DNS socket.
+    #[cfg(feature = "dns")]
+    fn update_dns_servers(&mut self, s: &mut SocketStack) {
+        let socket = s.sockets.get_mut::<smoltcp::socket::dns::Socket>(self.dns_socket);
+
+        let servers_v4 = self
+            .static_v4
+            .iter()
+            .flat_map(|c| c.dns_servers.iter().map(|a| IpAddress::Ipv4(*a)));
+        #[cfg(feature = "proto-ipv6")]
+        let servers_v6 = self
+            .static_v6
+            .iter()
+            .flat_map(|c| c.dns_servers.iter().map(|a| IpAddress::Ipv6(*a)));
+        #[cfg(not(feature = "proto-ipv6"))]
+        let servers_v6 = core::iter::empty();
+
+        let servers: Vec<IpAddress, 6> = servers_v4.chain(servers_v6).collect();
+        socket.update_servers(&servers[..]);
     }
 
     #[cfg(feature = "dhcpv4")]
@@ -421,18 +784,43 @@ impl<D: Driver + 'static> Inner<D> {
         socket.set_retry_config(config.retry_config);
     }
 
+    #[cfg(feature = "dhcp-server")]
+    fn stop_dhcp_server(&mut self, s: &mut SocketStack) {
+        if let Some(server) = self.dhcp_server.take() {
+            s.sockets.remove(server.socket());
+        }
+    }
+
     #[allow(unused)] // used only with dhcp
-    fn unapply_config(&mut self, s: &mut SocketStack) {
+    fn unapply_config_v4(&mut self, s: &mut SocketStack) {
         #[cfg(feature = "medium-ethernet")]
         let medium = self.device.capabilities().medium;
 
-        debug!("Lost IP configuration");
-        s.iface.update_ip_addrs(|ip_addrs| ip_addrs.clear());
+        debug!("Lost IPv4 configuration");
         #[cfg(feature = "medium-ethernet")]
         if medium == Medium::Ethernet {
             s.iface.routes_mut().remove_default_ipv4_route();
         }
-        self.config = None
+        self.static_v4 = None;
+        self.apply_ip_addrs(s);
+        #[cfg(feature = "dns")]
+        self.update_dns_servers(s);
+    }
+
+    #[cfg(feature = "slaac")]
+    fn unapply_config_v6(&mut self, s: &mut SocketStack) {
+        #[cfg(feature = "medium-ethernet")]
+        let medium = self.device.capabilities().medium;
+
+        debug!("Lost IPv6 configuration");
+        #[cfg(feature = "medium-ethernet")]
+        if medium == Medium::Ethernet {
+            s.iface.routes_mut().remove_default_ipv6_route();
+        }
+        self.static_v6 = None;
+        self.apply_ip_addrs(s);
+        #[cfg(feature = "dns")]
+        self.update_dns_servers(s);
     }
 
     fn poll(&mut self, cx: &mut Context<'_>, s: &mut SocketStack) {
@@ -449,8 +837,15 @@ impl<D: Driver + 'static> Inner<D> {
         let mut smoldev = DriverAdapter {
             cx: Some(cx),
             inner: &mut self.device,
+            stats: &self.stats,
         };
-        s.iface.poll(timestamp, &mut smoldev, &mut s.sockets);
+        let processed = s.iface.poll(timestamp, &mut smoldev, &mut s.sockets);
+        #[cfg(feature = "tcp")]
+        if processed {
+            s.dispatch_waker.wake();
+        }
+        #[cfg(not(feature = "tcp"))]
+        let _ = processed;
 
         // Update link up
         let old_link_up = self.link_up;
@@ -468,28 +863,56 @@ impl<D: Driver + 'static> Inner<D> {
             if self.link_up {
                 match socket.poll() {
                     None => {}
-                    Some(dhcpv4::Event::Deconfigured) => self.unapply_config(s),
+                    Some(dhcpv4::Event::Deconfigured) => self.unapply_config_v4(s),
                     Some(dhcpv4::Event::Configured(config)) => {
-                        let config = StaticConfig {
+                        let config = StaticConfigV4 {
                             address: config.address,
                             gateway: config.router,
                             dns_servers: config.dns_servers,
                         };
-                        self.apply_config(s, config)
+                        self.apply_config_v4(s, config)
                     }
                 }
             } else if old_link_up {
                 socket.reset();
-                self.unapply_config(s);
+                self.unapply_config_v4(s);
+            }
+        }
+        #[cfg(feature = "slaac")]
+        if let Some(slaac) = &mut self.slaac {
+            if self.link_up {
+                match slaac.poll(s, self.device.ethernet_address()) {
+                    None => {}
+                    Some(slaac::Event::Deconfigured) => self.unapply_config_v6(s),
+                    Some(slaac::Event::Configured(config)) => self.apply_config_v6(s, config),
+                }
+            } else if old_link_up {
+                slaac.reset();
+                self.unapply_config_v6(s);
             }
         }
+        #[cfg(feature = "dhcp-server")]
+        if let Some(server) = &mut self.dhcp_server {
+            if server.poll(s, self.static_v4.as_ref()) {
+                // Send the replies.
+                cx.waker().wake_by_ref();
+            }
+        }
+
         //if old_link_up || self.link_up {
         //    self.poll_configurator(timestamp)
         //}
         //
 
-        if let Some(poll_at) = s.iface.poll_at(timestamp, &mut s.sockets) {
-            let t = Timer::at(instant_from_smoltcp(poll_at));
+        let poll_at = s.iface.poll_at(timestamp, &s.sockets).map(instant_from_smoltcp);
+        #[cfg(feature = "slaac")]
+        let poll_at = match (poll_at, self.slaac.as_ref().and_then(|slaac| slaac.poll_at())) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+
+        if let Some(poll_at) = poll_at {
+            let t = Timer::at(poll_at);
             pin_mut!(t);
             if t.poll(cx).is_ready() {
                 cx.waker().wake_by_ref();
@@ -0,0 +1,621 @@
+//! mDNS / DNS-SD responder.
+//!
+//! The responder answers the Multicast DNS queries for the address of `<hostname>.local`, and
+//! advertises services with DNS Service Discovery, so that the device and its services can be
+//! found on the local link without any configuration.
+//!
+//! Only the IPv4 address of the stack is announced. Name conflicts are not probed for, so the
+//! hostname and the service instance names must be unique on the link.
+//!
+//! The driver must receive the frames sent to the mDNS multicast group.
+//!
+//! See [RFC 6762](https://www.rfc-editor.org/rfc/rfc6762) and
+//! [RFC 6763](https://www.rfc-editor.org/rfc/rfc6763).
+
+use core::iter;
+
+use embassy_net_driver::Driver;
+use embassy_time::{Duration, Instant, Timer};
+use futures::future::{select, Either};
+use futures::pin_mut;
+use smoltcp::wire::{IpEndpoint, Ipv4Address};
+
+use crate::udp::{PacketMetadata, UdpSocket};
+use crate::Stack;
+
+const MDNS_PORT: u16 = 5353;
+const MDNS_GROUP: Ipv4Address = Ipv4Address([224, 0, 0, 251]);
+
+/// Maximum size of the received queries.
+const MAX_QUERY_SIZE: usize = 1500;
+/// Maximum size of the sent responses.
+const MAX_RESPONSE_SIZE: usize = 1024;
+/// Number of messages the socket buffers can hold.
+const MESSAGE_COUNT: usize = 2;
+
+/// Maximum number of services of a responder.
+pub const MAX_SERVICES: usize = 32;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+
+const CLASS_IN: u16 = 1;
+const CLASS_ANY: u16 = 255;
+/// Bit of the record class asking the other hosts to replace the records they have cached.
+const CLASS_CACHE_FLUSH: u16 = 0x8000;
+/// Bit of the question class asking for a unicast response.
+const CLASS_UNICAST_RESPONSE: u16 = 0x8000;
+
+const FLAGS_RESPONSE: u16 = 0x8000;
+const FLAGS_AUTHORITATIVE: u16 = 0x0400;
+const FLAGS_OPCODE_MASK: u16 = 0x7800;
+
+/// TTL of the records containing a hostname or an address.
+const HOST_TTL: u32 = 120;
+/// TTL of the other records.
+const OTHER_TTL: u32 = 4500;
+/// Maximum TTL of the records sent in legacy unicast responses, see RFC 6762 section 6.7.
+const LEGACY_UNICAST_TTL: u32 = 10;
+
+/// Number of unsolicited responses sent when the address changes.
+const ANNOUNCE_COUNT: u8 = 2;
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the stack configuration is checked for address changes.
+const CONFIG_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A service advertised with DNS-SD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MdnsService<'a> {
+    /// Instance name, displayed to users, for example `Living room lights`.
+    pub instance: &'a str,
+    /// Service type and transport protocol, for example `_http._tcp`.
+    pub service_type: &'a str,
+    /// Port the service listens on.
+    pub port: u16,
+    /// Entries of the TXT record, for example `path=/`.
+    pub txt: &'a [&'a str],
+}
+
+/// mDNS responder configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MdnsConfig<'a> {
+    /// Hostname, without the `.local` suffix.
+    pub hostname: &'a str,
+    /// Advertised services, at most [`MAX_SERVICES`].
+    pub services: &'a [MdnsService<'a>],
+}
+
+/// Memory resources needed by the mDNS responder.
+pub struct MdnsResources {
+    rx_meta: [PacketMetadata; MESSAGE_COUNT],
+    rx_buffer: [u8; MAX_QUERY_SIZE * MESSAGE_COUNT],
+    tx_meta: [PacketMetadata; MESSAGE_COUNT],
+    tx_buffer: [u8; MAX_RESPONSE_SIZE * MESSAGE_COUNT],
+    message: [u8; MAX_QUERY_SIZE],
+}
+
+impl MdnsResources {
+    /// Create a new set of mDNS responder resources.
+    pub const fn new() -> Self {
+        Self {
+            rx_meta: [PacketMetadata::EMPTY; MESSAGE_COUNT],
+            rx_buffer: [0; MAX_QUERY_SIZE * MESSAGE_COUNT],
+            tx_meta: [PacketMetadata::EMPTY; MESSAGE_COUNT],
+            tx_buffer: [0; MAX_RESPONSE_SIZE * MESSAGE_COUNT],
+            message: [0; MAX_QUERY_SIZE],
+        }
+    }
+}
+
+impl Default for MdnsResources {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// mDNS responder.
+pub struct MdnsResponder<'a, D: Driver> {
+    stack: &'a Stack<D>,
+    socket: UdpSocket<'a>,
+    config: MdnsConfig<'a>,
+    message: &'a mut [u8],
+    address: Option<Ipv4Address>,
+}
+
+impl<'a, D: Driver + 'static> MdnsResponder<'a, D> {
+    /// Create a new mDNS responder.
+    ///
+    /// Panics if the mDNS port is already in use.
+    pub fn new(stack: &'a Stack<D>, config: MdnsConfig<'a>, resources: &'a mut MdnsResources) -> Self {
+        assert!(config.services.len() <= MAX_SERVICES, "too many mDNS services");
+
+        let MdnsResources {
+            rx_meta,
+            rx_buffer,
+            tx_meta,
+            tx_buffer,
+            message,
+        } = resources;
+
+        let mut socket = UdpSocket::new(stack, rx_meta, rx_buffer, tx_meta, tx_buffer);
+        unwrap!(socket.bind(MDNS_PORT));
+
+        Self {
+            stack,
+            socket,
+            config,
+            message,
+            address: None,
+        }
+    }
+
+    /// Run the responder.
+    ///
+    /// The records are announced each time the stack gets a new IPv4 address, then the queries
+    /// are answered.
+    pub async fn run(&mut self) -> ! {
+        if let Err(e) = self.stack.join_multicast_group(MDNS_GROUP).await {
+            warn!("mdns: failed to join the multicast group: {:?}", e);
+        }
+
+        let mut announcements = 0;
+        let mut next_announcement = Instant::now();
+
+        loop {
+            let address = self.stack.config_v4().map(|c| c.address.address());
+            if address != self.address {
+                self.address = address;
+                announcements = if address.is_some() { ANNOUNCE_COUNT } else { 0 };
+                next_announcement = Instant::now();
+            }
+
+            if announcements > 0 && Instant::now() >= next_announcement {
+                debug!("mdns: announcing");
+                self.respond(&Answers::all(&self.config), None, mdns_endpoint()).await;
+                announcements -= 1;
+                next_announcement = Instant::now() + ANNOUNCE_INTERVAL;
+            }
+
+            let timeout = if announcements > 0 {
+                next_announcement
+            } else {
+                Instant::now() + CONFIG_CHECK_INTERVAL
+            };
+
+            let query = {
+                let recv = self.socket.recv_from(self.message);
+                let timer = Timer::at(timeout);
+                pin_mut!(recv);
+                pin_mut!(timer);
+                match select(recv, timer).await {
+                    Either::Left((Ok(query), _)) => Some(query),
+                    _ => None,
+                }
+            };
+
+            if let Some((len, endpoint)) = query {
+                self.answer(len, endpoint).await;
+            }
+        }
+    }
+
+    /// Answer the query of `len` bytes in the message buffer.
+    async fn answer(&mut self, len: usize, endpoint: IpEndpoint) {
+        let Some(query) = Query::parse(&self.message[..len], &self.config) else {
+            return;
+        };
+        if query.answers.is_empty() {
+            return;
+        }
+
+        // Legacy unicast queries don't come from the mDNS port, and expect a regular DNS response.
+        if endpoint.port != MDNS_PORT {
+            let legacy = LegacyUnicast {
+                id: query.id,
+                question_count: query.question_count,
+                questions_end: query.questions_end,
+            };
+            self.respond(&query.answers, Some(legacy), endpoint).await;
+        } else if query.unicast {
+            self.respond(&query.answers, None, endpoint).await;
+        } else {
+            self.respond(&query.answers, None, mdns_endpoint()).await;
+        }
+    }
+
+    async fn respond(&mut self, answers: &Answers, legacy: Option<LegacyUnicast>, endpoint: IpEndpoint) {
+        let Some(len) = Response::new(&self.config, self.address).write(answers, legacy, self.message) else {
+            return;
+        };
+        if let Err(e) = self.socket.send_to(&self.message[..len], endpoint).await {
+            warn!("mdns: failed to send response: {:?}", e);
+        }
+    }
+}
+
+fn mdns_endpoint() -> IpEndpoint {
+    IpEndpoint::new(MDNS_GROUP.into(), MDNS_PORT)
+}
+
+/// A domain name: an optional instance label, followed by the dot-separated labels of `domain`,
+/// and `local`.
+#[derive(Clone, Copy)]
+struct Name<'a> {
+    instance: Option<&'a str>,
+    domain: &'a str,
+}
+
+impl<'a> Name<'a> {
+    const SERVICES: Name<'static> = Name {
+        instance: None,
+        domain: "_services._dns-sd._udp",
+    };
+
+    fn host(config: &MdnsConfig<'a>) -> Self {
+        Self {
+            instance: None,
+            domain: config.hostname,
+        }
+    }
+
+    fn service_type(service: &MdnsService<'a>) -> Self {
+        Self {
+            instance: None,
+            domain: service.service_type,
+        }
+    }
+
+    fn service_instance(service: &MdnsService<'a>) -> Self {
+        Self {
+            instance: Some(service.instance),
+            domain: service.service_type,
+        }
+    }
+
+    fn labels(&self) -> impl Iterator<Item = &'a str> {
+        self.instance
+            .into_iter()
+            .chain(self.domain.split('.'))
+            .chain(iter::once("local"))
+    }
+
+    /// Compare with an uncompressed name in wire format, ignoring ASCII case.
+    fn matches(&self, mut encoded: &[u8]) -> bool {
+        for label in self.labels() {
+            let Some((&len, rest)) = encoded.split_first() else {
+                return false;
+            };
+            let len = len as usize;
+            if len != label.len() || rest.len() < len || !rest[..len].eq_ignore_ascii_case(label.as_bytes()) {
+                return false;
+            }
+            encoded = &rest[len..];
+        }
+        encoded == [0]
+    }
+}
+
+/// The records to send. Services are identified by their index in the configuration.
+#[derive(Default)]
+struct Answers {
+    host: bool,
+    services: bool,
+    ptr: u32,
+    srv: u32,
+    txt: u32,
+}
+
+impl Answers {
+    fn all(config: &MdnsConfig) -> Self {
+        let mask = service_mask(config);
+        Self {
+            host: true,
+            services: !config.services.is_empty(),
+            ptr: mask,
+            srv: mask,
+            txt: mask,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.host && !self.services && self.ptr == 0 && self.srv == 0 && self.txt == 0
+    }
+
+    /// The records a client will likely query next, which are sent as additional records.
+    fn additional(&self) -> Self {
+        Self {
+            host: !self.host && (self.ptr | self.srv) != 0,
+            services: false,
+            ptr: 0,
+            srv: self.ptr & !self.srv,
+            txt: self.ptr & !self.txt,
+        }
+    }
+}
+
+fn service_mask(config: &MdnsConfig) -> u32 {
+    if config.services.len() >= 32 {
+        u32::MAX
+    } else {
+        (1 << config.services.len()) - 1
+    }
+}
+
+struct Query {
+    id: u16,
+    unicast: bool,
+    answers: Answers,
+    question_count: u16,
+    /// Position following the question section.
+    questions_end: usize,
+}
+
+/// A response to a legacy unicast query, which echoes the query ID and questions.
+struct LegacyUnicast {
+    id: u16,
+    question_count: u16,
+    /// Position following the question section, which is left in place at the start of the
+    /// message buffer.
+    questions_end: usize,
+}
+
+impl Query {
+    fn parse(message: &[u8], config: &MdnsConfig) -> Option<Self> {
+        if message.len() < 12 {
+            return None;
+        }
+        let id = u16::from_be_bytes([message[0], message[1]]);
+        let flags = u16::from_be_bytes([message[2], message[3]]);
+        let questions = u16::from_be_bytes([message[4], message[5]]);
+        if flags & (FLAGS_RESPONSE | FLAGS_OPCODE_MASK) != 0 {
+            return None;
+        }
+
+        let mut query = Self {
+            id,
+            unicast: false,
+            answers: Answers::default(),
+            question_count: questions,
+            questions_end: 12,
+        };
+
+        let mut pos = 12;
+        for _ in 0..questions {
+            let mut name = [0; 256];
+            let (name_len, next) = read_name(message, pos, &mut name)?;
+            let name = &name[..name_len];
+            let fields = message.get(next..next + 4)?;
+            let qtype = u16::from_be_bytes([fields[0], fields[1]]);
+            let qclass = u16::from_be_bytes([fields[2], fields[3]]);
+            pos = next + 4;
+
+            if !matches!(qclass & !CLASS_UNICAST_RESPONSE, CLASS_IN | CLASS_ANY) {
+                continue;
+            }
+            let answers = &mut query.answers;
+            let matches_type = |t| qtype == t || qtype == TYPE_ANY;
+
+            if matches_type(TYPE_A) && Name::host(config).matches(name) {
+                answers.host = true;
+            }
+            if matches_type(TYPE_PTR) && !config.services.is_empty() && Name::SERVICES.matches(name) {
+                answers.services = true;
+            }
+            for (i, service) in config.services.iter().enumerate() {
+                let bit = 1 << i;
+                if matches_type(TYPE_PTR) && Name::service_type(service).matches(name) {
+                    answers.ptr |= bit;
+                }
+                if Name::service_instance(service).matches(name) {
+                    if matches_type(TYPE_SRV) {
+                        answers.srv |= bit;
+                    }
+                    if matches_type(TYPE_TXT) {
+                        answers.txt |= bit;
+                    }
+                }
+            }
+
+            query.unicast |= qclass & CLASS_UNICAST_RESPONSE != 0;
+        }
+
+        query.questions_end = pos;
+        Some(query)
+    }
+}
+
+/// Read a possibly compressed name at `pos` into `out`, uncompressed.
+///
+/// Returns the length of the uncompressed name, and the position following the name.
+fn read_name(message: &[u8], mut pos: usize, out: &mut [u8; 256]) -> Option<(usize, usize)> {
+    let mut len = 0;
+    let mut next = None;
+
+    // Bound the number of labels, to not loop forever on malformed compression pointers.
+    for _ in 0..128 {
+        let label_len = *message.get(pos)? as usize;
+        match label_len {
+            0 => {
+                *out.get_mut(len)? = 0;
+                return Some((len + 1, next.unwrap_or(pos + 1)));
+            }
+            0xc0..=0xff => {
+                let pointer = u16::from_be_bytes([*message.get(pos)?, *message.get(pos + 1)?]) & 0x3fff;
+                next.get_or_insert(pos + 2);
+                pos = pointer as usize;
+            }
+            1..=63 => {
+                let label = message.get(pos..pos + 1 + label_len)?;
+                out.get_mut(len..len + 1 + label_len)?.copy_from_slice(label);
+                len += 1 + label_len;
+                pos += 1 + label_len;
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+struct Response<'c, 'a> {
+    config: &'c MdnsConfig<'a>,
+    address: Option<Ipv4Address>,
+}
+
+impl<'c, 'a> Response<'c, 'a> {
+    fn new(config: &'c MdnsConfig<'a>, address: Option<Ipv4Address>) -> Self {
+        Self { config, address }
+    }
+
+    /// Write the response to `buf`, returning its length, or `None` if the echoed questions
+    /// don't fit.
+    ///
+    /// Records which don't fit are left out.
+    fn write(&self, answers: &Answers, legacy: Option<LegacyUnicast>, buf: &mut [u8]) -> Option<usize> {
+        let buf_len = buf.len().min(MAX_RESPONSE_SIZE);
+        let (id, question_count, questions_end) = match &legacy {
+            Some(legacy) => (legacy.id, legacy.question_count, legacy.questions_end),
+            None => (0, 0, 12),
+        };
+        if questions_end > buf_len {
+            return None;
+        }
+        let mut w = Writer {
+            buf: &mut buf[..buf_len],
+            len: questions_end,
+        };
+
+        let legacy = legacy.is_some();
+        let answer_count = self.write_records(&mut w, answers, legacy);
+        let additional_count = self.write_records(&mut w, &answers.additional(), legacy);
+
+        let header = &mut w.buf[..12];
+        header.fill(0);
+        header[0..2].copy_from_slice(&id.to_be_bytes());
+        header[2..4].copy_from_slice(&(FLAGS_RESPONSE | FLAGS_AUTHORITATIVE).to_be_bytes());
+        header[4..6].copy_from_slice(&question_count.to_be_bytes());
+        header[6..8].copy_from_slice(&answer_count.to_be_bytes());
+        header[10..12].copy_from_slice(&additional_count.to_be_bytes());
+        Some(w.len)
+    }
+
+    /// Write the records, returning how many were written.
+    ///
+    /// The records of legacy unicast responses have a short TTL and no cache-flush bit, as they
+    /// are cached by regular DNS resolvers.
+    fn write_records(&self, w: &mut Writer, answers: &Answers, legacy: bool) -> u16 {
+        let mut count = 0;
+        let mut record =
+            |w: &mut Writer, name: Name, rtype, class: u16, ttl: u32, rdata: &dyn Fn(&mut Writer) -> Option<()>| {
+                let (class, ttl) = if legacy {
+                    (class & !CLASS_CACHE_FLUSH, ttl.min(LEGACY_UNICAST_TTL))
+                } else {
+                    (class, ttl)
+                };
+                let start = w.len;
+                if w.record(name, rtype, class, ttl, rdata).is_some() {
+                    count += 1;
+                } else {
+                    w.len = start;
+                }
+            };
+        let host = Name::host(self.config);
+
+        if let (true, Some(address)) = (answers.host, self.address) {
+            record(w, host, TYPE_A, CLASS_IN | CLASS_CACHE_FLUSH, HOST_TTL, &|w| {
+                w.put(&address.0)
+            });
+        }
+
+        for (i, service) in self.config.services.iter().enumerate() {
+            let bit = 1 << i;
+            let service_type = Name::service_type(service);
+            let instance = Name::service_instance(service);
+
+            let first_of_type = !self.config.services[..i]
+                .iter()
+                .any(|s| s.service_type == service.service_type);
+            if answers.services && first_of_type {
+                record(w, Name::SERVICES, TYPE_PTR, CLASS_IN, OTHER_TTL, &|w| {
+                    w.name(service_type)
+                });
+            }
+            if answers.ptr & bit != 0 {
+                record(w, service_type, TYPE_PTR, CLASS_IN, OTHER_TTL, &|w| w.name(instance));
+            }
+            if answers.srv & bit != 0 {
+                record(w, instance, TYPE_SRV, CLASS_IN | CLASS_CACHE_FLUSH, HOST_TTL, &|w| {
+                    // Priority and weight.
+                    w.put(&[0; 4])?;
+                    w.put(&service.port.to_be_bytes())?;
+                    w.name(host)
+                });
+            }
+            if answers.txt & bit != 0 {
+                record(w, instance, TYPE_TXT, CLASS_IN | CLASS_CACHE_FLUSH, OTHER_TTL, &|w| {
+                    // A TXT record contains at least one string, which may be empty.
+                    if service.txt.is_empty() {
+                        return w.put(&[0]);
+                    }
+                    for entry in service.txt {
+                        w.put(&[u8::try_from(entry.len()).ok()?])?;
+                        w.put(entry.as_bytes())?;
+                    }
+                    Some(())
+                });
+            }
+        }
+
+        count
+    }
+}
+
+struct Writer<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl<'b> Writer<'b> {
+    fn put(&mut self, data: &[u8]) -> Option<()> {
+        self.buf.get_mut(self.len..self.len + data.len())?.copy_from_slice(data);
+        self.len += data.len();
+        Some(())
+    }
+
+    fn name(&mut self, name: Name) -> Option<()> {
+        for label in name.labels() {
+            if label.is_empty() || label.len() > 63 {
+                return None;
+            }
+            self.put(&[label.len() as u8])?;
+            self.put(label.as_bytes())?;
+        }
+        self.put(&[0])
+    }
+
+    fn record(
+        &mut self,
+        name: Name,
+        rtype: u16,
+        class: u16,
+        ttl: u32,
+        rdata: &dyn Fn(&mut Writer) -> Option<()>,
+    ) -> Option<()> {
+        self.name(name)?;
+        self.put(&rtype.to_be_bytes())?;
+        self.put(&class.to_be_bytes())?;
+        self.put(&ttl.to_be_bytes())?;
+
+        let rdata_len_pos = self.len;
+        self.put(&[0; 2])?;
+        rdata(self)?;
+
+        let rdata_len = (self.len - rdata_len_pos - 2) as u16;
+        self.buf[rdata_len_pos..rdata_len_pos + 2].copy_from_slice(&rdata_len.to_be_bytes());
+        Some(())
+    }
+}
@@ -0,0 +1,232 @@
+//! IPv6 stateless address autoconfiguration (SLAAC, RFC 4862).
+//!
+//! The stack assigns itself a link-local address derived from its MAC address, solicits the
+//! routers on the link, and forms a global address from the first autonomous /64 prefix
+//! they advertise. The router sending the advertisement becomes the default gateway.
+//!
+//! Duplicate address detection is not performed, and the DNS servers advertised by routers are
+//! ignored.
+
+use embassy_time::{Duration, Instant};
+use heapless::Vec;
+use smoltcp::iface::SocketHandle;
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::socket::raw::{self, PacketMetadata};
+use smoltcp::wire::{
+    EthernetAddress, Icmpv6Packet, Icmpv6Repr, IpProtocol, IpVersion, Ipv6Address, Ipv6Cidr, Ipv6Packet, Ipv6Repr,
+    NdiscPrefixInfoFlags, NdiscRepr,
+};
+
+use crate::time::duration_from_smoltcp;
+use crate::{SocketStack, StaticConfigV6};
+
+/// Number of router solicitations sent when the link goes up.
+const MAX_SOLICITATIONS: u8 = 3;
+/// Delay between router solicitations.
+const SOLICITATION_INTERVAL: Duration = Duration::from_secs(4);
+/// Hop limit of the neighbor discovery messages, which must not be forwarded.
+const NDISC_HOP_LIMIT: u8 = 255;
+/// Lifetime advertised by routers for prefixes that never expire.
+const INFINITE_LIFETIME: Duration = Duration::from_secs(0xffff_ffff);
+const LINK_LOCAL_PREFIX: Ipv6Address = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 0);
+
+const RX_SIZE: usize = 512;
+const TX_SIZE: usize = 64;
+
+/// Memory resources of the ICMPv6 socket receiving the router advertisements.
+pub(crate) struct SlaacResources {
+    rx_meta: [PacketMetadata; 2],
+    rx_buffer: [u8; RX_SIZE],
+    tx_meta: [PacketMetadata; 1],
+    tx_buffer: [u8; TX_SIZE],
+}
+
+impl SlaacResources {
+    pub(crate) const fn new() -> Self {
+        Self {
+            rx_meta: [PacketMetadata::EMPTY; 2],
+            rx_buffer: [0; RX_SIZE],
+            tx_meta: [PacketMetadata::EMPTY; 1],
+            tx_buffer: [0; TX_SIZE],
+        }
+    }
+}
+
+pub(crate) enum Event {
+    Configured(StaticConfigV6),
+    Deconfigured,
+}
+
+pub(crate) struct Slaac {
+    socket: SocketHandle,
+    link_local: Ipv6Cidr,
+    config: Option<StaticConfigV6>,
+    expires_at: Option<Instant>,
+    solicitations: u8,
+    next_solicitation: Instant,
+}
+
+impl Slaac {
+    pub(crate) fn new(s: &mut SocketStack, resources: &'static mut SlaacResources, mac: [u8; 6]) -> Self {
+        let socket = raw::Socket::new(
+            IpVersion::Ipv6,
+            IpProtocol::Icmpv6,
+            raw::PacketBuffer::new(&mut resources.rx_meta[..], &mut resources.rx_buffer[..]),
+            raw::PacketBuffer::new(&mut resources.tx_meta[..], &mut resources.tx_buffer[..]),
+        );
+
+        Self {
+            socket: s.sockets.add(socket),
+            link_local: Ipv6Cidr::new(with_interface_id(LINK_LOCAL_PREFIX, mac), 64),
+            config: None,
+            expires_at: None,
+            solicitations: 0,
+            next_solicitation: Instant::now(),
+        }
+    }
+
+    /// Link-local address of the stack.
+    pub(crate) fn link_local(&self) -> Ipv6Cidr {
+        self.link_local
+    }
+
+    /// Forget the configuration, and solicit the routers again.
+    pub(crate) fn reset(&mut self) {
+        self.config = None;
+        self.expires_at = None;
+        self.solicitations = 0;
+        self.next_solicitation = Instant::now();
+    }
+
+    /// Instant at which `poll` must be called again.
+    pub(crate) fn poll_at(&self) -> Option<Instant> {
+        let solicit = self.config.is_none() && self.solicitations < MAX_SOLICITATIONS;
+        let solicit_at = solicit.then_some(self.next_solicitation);
+        match (solicit_at, self.expires_at) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    pub(crate) fn poll(&mut self, s: &mut SocketStack, mac: [u8; 6]) -> Option<Event> {
+        let now = Instant::now();
+        let socket = s.sockets.get_mut::<raw::Socket>(self.socket);
+
+        let mut event = None;
+        while let Ok(packet) = socket.recv() {
+            if let Some(e) = self.process(packet, mac, now) {
+                event = Some(e);
+            }
+        }
+
+        if let Some(expires_at) = self.expires_at {
+            if now >= expires_at {
+                debug!("SLAAC: prefix expired");
+                self.reset();
+                event = Some(Event::Deconfigured);
+            }
+        }
+
+        let solicit = self.config.is_none() && self.solicitations < MAX_SOLICITATIONS;
+        if solicit && now >= self.next_solicitation && self.solicit(socket, mac) {
+            self.solicitations += 1;
+            self.next_solicitation = now + SOLICITATION_INTERVAL;
+        }
+
+        event
+    }
+
+    fn solicit(&self, socket: &mut raw::Socket, mac: [u8; 6]) -> bool {
+        let icmp_repr = Icmpv6Repr::Ndisc(NdiscRepr::RouterSolicit {
+            lladdr: Some(EthernetAddress(mac).into()),
+        });
+        let ip_repr = Ipv6Repr {
+            src_addr: self.link_local.address(),
+            dst_addr: Ipv6Address::LINK_LOCAL_ALL_ROUTERS,
+            next_header: IpProtocol::Icmpv6,
+            payload_len: icmp_repr.buffer_len(),
+            hop_limit: NDISC_HOP_LIMIT,
+        };
+
+        let Ok(buf) = socket.send(ip_repr.buffer_len() + icmp_repr.buffer_len()) else {
+            return false;
+        };
+        let mut ip_packet = Ipv6Packet::new_unchecked(buf);
+        ip_repr.emit(&mut ip_packet);
+        icmp_repr.emit(
+            &ip_repr.src_addr.into(),
+            &ip_repr.dst_addr.into(),
+            &mut Icmpv6Packet::new_unchecked(ip_packet.payload_mut()),
+            &ChecksumCapabilities::default(),
+        );
+        debug!("SLAAC: sent router solicitation");
+        true
+    }
+
+    fn process(&mut self, packet: &[u8], mac: [u8; 6], now: Instant) -> Option<Event> {
+        let ip_packet = Ipv6Packet::new_checked(packet).ok()?;
+        let ip_repr = Ipv6Repr::parse(&ip_packet).ok()?;
+        // Router advertisements must come from a link-local address, and must not have been forwarded.
+        if ip_repr.hop_limit != NDISC_HOP_LIMIT || !ip_repr.src_addr.is_link_local() {
+            return None;
+        }
+        let icmp_packet = Icmpv6Packet::new_checked(ip_packet.payload()).ok()?;
+        let icmp_repr = Icmpv6Repr::parse(
+            &ip_repr.src_addr.into(),
+            &ip_repr.dst_addr.into(),
+            &icmp_packet,
+            &ChecksumCapabilities::default(),
+        )
+        .ok()?;
+
+        let Icmpv6Repr::Ndisc(NdiscRepr::RouterAdvert {
+            router_lifetime,
+            prefix_info: Some(prefix_info),
+            ..
+        }) = icmp_repr else {
+            return None;
+        };
+        if prefix_info.prefix_len != 64
+            || !prefix_info.flags.contains(NdiscPrefixInfoFlags::ADDRCONF)
+            || prefix_info.prefix.is_link_local()
+        {
+            return None;
+        }
+
+        let address = with_interface_id(prefix_info.prefix, mac);
+        let valid_lifetime = duration_from_smoltcp(prefix_info.valid_lifetime);
+        if valid_lifetime == Duration::from_secs(0) {
+            // The router withdraws the prefix.
+            if self.config.as_ref().map(|c| c.address.address()) == Some(address) {
+                self.reset();
+                return Some(Event::Deconfigured);
+            }
+            return None;
+        }
+
+        let config = StaticConfigV6 {
+            address: Ipv6Cidr::new(address, 64),
+            gateway: (router_lifetime != smoltcp::time::Duration::ZERO).then_some(ip_repr.src_addr),
+            dns_servers: Vec::new(),
+        };
+        self.expires_at = (valid_lifetime != INFINITE_LIFETIME).then(|| now + valid_lifetime);
+
+        if self.config.as_ref() == Some(&config) {
+            return None;
+        }
+        self.config = Some(config.clone());
+        Some(Event::Configured(config))
+    }
+}
+
+/// Returns the /64 `prefix` with the modified EUI-64 interface identifier of `mac`.
+fn with_interface_id(prefix: Ipv6Address, mac: [u8; 6]) -> Ipv6Address {
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&prefix.as_bytes()[..8]);
+    bytes[8..11].copy_from_slice(&mac[..3]);
+    bytes[8] ^= 0x02;
+    bytes[11] = 0xff;
+    bytes[12] = 0xfe;
+    bytes[13..].copy_from_slice(&mac[3..]);
+    Ipv6Address(bytes)
+}
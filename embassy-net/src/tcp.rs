@@ -2,18 +2,19 @@
 //!
 //! # Listening
 //!
-//! `embassy-net` does not have a `TcpListener`. Instead, individual `TcpSocket`s can be put into
-//! listening mode by calling [`TcpSocket::accept`].
+//! Individual `TcpSocket`s can be put into listening mode by calling [`TcpSocket::accept`].
 //!
 //! Incoming connections when no socket is listening are rejected. To accept many incoming
-//! connections, create many sockets and put them all into listening mode.
+//! connections, use a [`TcpListener`], which keeps a pool of sockets in listening mode, and
+//! yields them as they accept connections.
 
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell, UnsafeCell};
 use core::future::poll_fn;
 use core::mem;
 use core::task::Poll;
 
 use embassy_net_driver::Driver;
+use embassy_sync::waitqueue::WakerRegistration;
 use embassy_time::Duration;
 use smoltcp::iface::{Interface, SocketHandle};
 use smoltcp::socket::tcp;
@@ -62,16 +63,36 @@ pub enum AcceptError {
 /// A TCP socket.
 pub struct TcpSocket<'a> {
     io: TcpIo<'a>,
+    rx_bytes: Cell<u64>,
+    tx_bytes: Cell<u64>,
+    /// Slot of the [`TcpListenerState`] holding the buffers, for the sockets of a listener.
+    listener_slot: Option<ListenerSlot<'a>>,
 }
 
 /// The reader half of a TCP socket.
 pub struct TcpReader<'a> {
     io: TcpIo<'a>,
+    rx_bytes: &'a Cell<u64>,
 }
 
 /// The writer half of a TCP socket.
 pub struct TcpWriter<'a> {
     io: TcpIo<'a>,
+    tx_bytes: &'a Cell<u64>,
+}
+
+/// Statistics of a [`TcpSocket`], returned by [`TcpSocket::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TcpStats {
+    /// Bytes read from the socket, by it or its [`TcpReader`].
+    pub rx_bytes: u64,
+    /// Bytes written to the socket, by it or its [`TcpWriter`]. They may not be sent yet.
+    pub tx_bytes: u64,
+    /// Bytes received and waiting to be read, in the receive buffer.
+    pub recv_queue: usize,
+    /// Bytes written and not sent or not ACKed yet, in the send buffer.
+    pub send_queue: usize,
 }
 
 impl<'a> TcpReader<'a> {
@@ -80,7 +101,7 @@ impl<'a> TcpReader<'a> {
     /// Returns how many bytes were read, or an error. If no data is available, it waits
     /// until there is at least one byte available.
     pub async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
-        self.io.read(buf).await
+        self.io.read(buf, self.rx_bytes).await
     }
 }
 
@@ -90,13 +111,12 @@ impl<'a> TcpWriter<'a> {
     /// Returns how many bytes were written, or an error. If the socket is not ready to
     /// accept data, it waits until it is.
     pub async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        self.io.write(buf).await
+        self.io.write(buf, self.tx_bytes).await
     }
 
     /// Flushes the written data to the socket.
     ///
-    /// This waits until all data has been sent, and ACKed by the remote host. For a connection
-    /// closed with [`abort()`](TcpSocket::abort) it will wait for the TCP RST packet to be sent.
+    /// See [`TcpSocket::flush`].
     pub async fn flush(&mut self) -> Result<(), Error> {
         self.io.flush().await
     }
@@ -105,7 +125,16 @@ impl<'a> TcpWriter<'a> {
 impl<'a> TcpSocket<'a> {
     /// Create a new TCP socket on the given stack, with the given buffers.
     pub fn new<D: Driver>(stack: &'a Stack<D>, rx_buffer: &'a mut [u8], tx_buffer: &'a mut [u8]) -> Self {
-        let s = &mut *stack.socket.borrow_mut();
+        Self::new_inner(&stack.socket, rx_buffer, tx_buffer, None)
+    }
+
+    fn new_inner(
+        stack: &'a RefCell<SocketStack>,
+        rx_buffer: &'a mut [u8],
+        tx_buffer: &'a mut [u8],
+        listener_slot: Option<ListenerSlot<'a>>,
+    ) -> Self {
+        let s = &mut *stack.borrow_mut();
         let rx_buffer: &'static mut [u8] = unsafe { mem::transmute(rx_buffer) };
         let tx_buffer: &'static mut [u8] = unsafe { mem::transmute(tx_buffer) };
         let handle = s.sockets.add(tcp::Socket::new(
@@ -114,16 +143,25 @@ impl<'a> TcpSocket<'a> {
         ));
 
         Self {
-            io: TcpIo {
-                stack: &stack.socket,
-                handle,
-            },
+            io: TcpIo { stack, handle },
+            rx_bytes: Cell::new(0),
+            tx_bytes: Cell::new(0),
+            listener_slot,
         }
     }
 
     /// Split the socket into reader and a writer halves.
     pub fn split(&mut self) -> (TcpReader<'_>, TcpWriter<'_>) {
-        (TcpReader { io: self.io }, TcpWriter { io: self.io })
+        (
+            TcpReader {
+                io: self.io,
+                rx_bytes: &self.rx_bytes,
+            },
+            TcpWriter {
+                io: self.io,
+                tx_bytes: &self.tx_bytes,
+            },
+        )
     }
 
     /// Connect to a remote host.
@@ -186,7 +224,7 @@ impl<'a> TcpSocket<'a> {
     /// Returns how many bytes were read, or an error. If no data is available, it waits
     /// until there is at least one byte available.
     pub async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
-        self.io.read(buf).await
+        self.io.read(buf, &self.rx_bytes).await
     }
 
     /// Write data to the socket.
@@ -194,21 +232,27 @@ impl<'a> TcpSocket<'a> {
     /// Returns how many bytes were written, or an error. If the socket is not ready to
     /// accept data, it waits until it is.
     pub async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        self.io.write(buf).await
+        self.io.write(buf, &self.tx_bytes).await
     }
 
     /// Flushes the written data to the socket.
     ///
     /// This waits until all data has been sent, and ACKed by the remote host. For a connection
-    /// closed with [`abort()`](TcpSocket::abort) it will wait for the TCP RST packet to be sent.
+    /// closed with [`close()`](TcpSocket::close), it also waits for the FIN to be ACKed.
+    ///
+    /// If the connection is reset, by the remote host or by a [timeout](TcpSocket::set_timeout),
+    /// the data not sent yet is discarded and this returns immediately.
     pub async fn flush(&mut self) -> Result<(), Error> {
         self.io.flush().await
     }
 
     /// Set the timeout for the socket.
     ///
-    /// If the timeout is set, the socket will be closed if no data is received for the
-    /// specified duration.
+    /// If the timeout is set, the connection is reset if the remote host doesn't acknowledge
+    /// the sent data, or doesn't answer the keep-alive packets, for the specified duration.
+    /// Reads and writes then return [`Error::ConnectionReset`].
+    ///
+    /// If not set, the socket waits forever.
     pub fn set_timeout(&mut self, duration: Option<Duration>) {
         self.io
             .with_mut(|s, _| s.set_timeout(duration.map(duration_to_smoltcp)))
@@ -220,6 +264,9 @@ impl<'a> TcpSocket<'a> {
     /// the specified duration of inactivity.
     ///
     /// If not set, the socket will not send keep-alive packets.
+    ///
+    /// Combined with a [timeout](Self::set_timeout) longer than the interval, this detects the
+    /// remote hosts which disappeared without closing the connection.
     pub fn set_keep_alive(&mut self, interval: Option<Duration>) {
         self.io
             .with_mut(|s, _| s.set_keep_alive(interval.map(duration_to_smoltcp)))
@@ -230,6 +277,24 @@ impl<'a> TcpSocket<'a> {
         self.io.with_mut(|s, _| s.set_hop_limit(hop_limit))
     }
 
+    /// Enable or disable Nagle's algorithm.
+    ///
+    /// When enabled, which is the default, small writes are delayed until the data sent
+    /// previously is ACKed, so they can be sent in a single segment. Disable it to send the
+    /// small writes of latency sensitive protocols immediately.
+    pub fn set_nagle_enabled(&mut self, enabled: bool) {
+        self.io.with_mut(|s, _| s.set_nagle_enabled(enabled))
+    }
+
+    /// Set the delay before sending the ACK of received data.
+    ///
+    /// Delaying the ACKs allows sending them along with response data. If `None`, the ACKs are
+    /// sent immediately. The default is 10 ms.
+    pub fn set_ack_delay(&mut self, duration: Option<Duration>) {
+        self.io
+            .with_mut(|s, _| s.set_ack_delay(duration.map(duration_to_smoltcp)))
+    }
+
     /// Get the local endpoint of the socket.
     ///
     /// Returns `None` if the socket is not bound (listening) or not connected.
@@ -249,6 +314,16 @@ impl<'a> TcpSocket<'a> {
         self.io.with(|s, _| s.state())
     }
 
+    /// Get the statistics of the socket.
+    pub fn stats(&self) -> TcpStats {
+        self.io.with(|s, _| TcpStats {
+            rx_bytes: self.rx_bytes.get(),
+            tx_bytes: self.tx_bytes.get(),
+            recv_queue: s.recv_queue(),
+            send_queue: s.send_queue(),
+        })
+    }
+
     /// Close the write half of the socket.
     ///
     /// This closes only the write half of the socket. The read half side remains open, the
@@ -265,12 +340,12 @@ impl<'a> TcpSocket<'a> {
     /// This instantly closes both the read and write halves of the socket. Any pending data
     /// that has not been sent will be lost.
     ///
-    /// Note that the TCP RST packet is not sent immediately - if the `TcpSocket` is dropped too soon
-    /// the remote host may not know the connection has been closed.
-    /// `abort()` callers should wait for a [`flush()`](TcpSocket::flush) call to complete before
-    /// dropping or reusing the socket.
-    pub fn abort(&mut self) {
-        self.io.with_mut(|s, _| s.abort())
+    /// This then waits for the TCP RST packet to be sent, so the remote host knows the
+    /// connection has been closed. If this is cancelled, the RST is still sent as long as the
+    /// `TcpSocket` is not dropped or reused.
+    pub async fn abort(&mut self) {
+        self.io.with_mut(|s, _| s.abort());
+        self.io.wait_rst_sent().await;
     }
 
     /// Get whether the socket is ready to send data, i.e. whether there is space in the send buffer.
@@ -287,9 +362,138 @@ impl<'a> TcpSocket<'a> {
 impl<'a> Drop for TcpSocket<'a> {
     fn drop(&mut self) {
         self.io.stack.borrow_mut().sockets.remove(self.io.handle);
+        if let Some(slot) = self.listener_slot {
+            // The buffers aren't used anymore, they can be reused by the listener.
+            slot.used.set(false);
+            slot.waker.borrow_mut().wake();
+        }
+    }
+}
+
+// =======================
+
+/// A TCP listener, which accepts connections on a local endpoint.
+///
+/// The listener keeps a socket in listening mode in each free slot of its [`TcpListenerState`],
+/// and [`accept`](Self::accept) yields them as they accept connections. The sockets accepted
+/// keep their slot until they are dropped, so up to `N` connections can be accepted at once,
+/// with the remaining slots acting as the backlog of the listener. When all the slots are used,
+/// incoming connections are rejected.
+pub struct TcpListener<'d, const N: usize, const TX_SZ: usize = 1024, const RX_SZ: usize = 1024> {
+    stack: &'d RefCell<SocketStack>,
+    state: &'d TcpListenerState<N, TX_SZ, RX_SZ>,
+    local_endpoint: IpListenEndpoint,
+    sockets: [Option<TcpSocket<'d>>; N],
+}
+
+impl<'d, const N: usize, const TX_SZ: usize, const RX_SZ: usize> TcpListener<'d, N, TX_SZ, RX_SZ> {
+    /// Create a new `TcpListener`, listening on `local_endpoint`.
+    pub fn new<D: Driver, T>(
+        stack: &'d Stack<D>,
+        state: &'d TcpListenerState<N, TX_SZ, RX_SZ>,
+        local_endpoint: T,
+    ) -> Result<Self, AcceptError>
+    where
+        T: Into<IpListenEndpoint>,
+    {
+        let mut this = Self {
+            stack: &stack.socket,
+            state,
+            local_endpoint: local_endpoint.into(),
+            sockets: [(); N].map(|_| None),
+        };
+        this.listen()?;
+        Ok(this)
+    }
+
+    /// Wait for a connection, and return the socket that accepted it.
+    ///
+    /// Another socket is put in listening mode in its place, once a slot of the state is free.
+    pub async fn accept(&mut self) -> Result<TcpSocket<'d>, AcceptError> {
+        poll_fn(|cx| {
+            if let Err(e) = self.listen() {
+                return Poll::Ready(Err(e));
+            }
+            // Woken up when a slot is released.
+            self.state.waker.borrow_mut().register(cx.waker());
+
+            for socket in &mut self.sockets {
+                let Some(s) = socket else { continue };
+                let accepted = s.io.with_mut(|s, _| match s.state() {
+                    tcp::State::Listen | tcp::State::SynSent | tcp::State::SynReceived => {
+                        s.register_send_waker(cx.waker());
+                        false
+                    }
+                    _ => true,
+                });
+                if accepted {
+                    return Poll::Ready(Ok(socket.take().unwrap()));
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Put a socket in listening mode in each free slot.
+    fn listen(&mut self) -> Result<(), AcceptError> {
+        for (socket, slot) in self.sockets.iter_mut().zip(&self.state.slots) {
+            if socket.is_some() || slot.used.get() {
+                continue;
+            }
+
+            slot.used.set(true);
+            // safety: the buffers are only used by this socket, until it's dropped and the slot is
+            // released.
+            let (tx_buffer, rx_buffer) = unsafe { &mut *slot.bufs.get() };
+            let listener_slot = ListenerSlot {
+                used: &slot.used,
+                waker: &self.state.waker,
+            };
+            let mut s = TcpSocket::new_inner(self.stack, rx_buffer, tx_buffer, Some(listener_slot));
+            match s.io.with_mut(|s, _| s.listen(self.local_endpoint)) {
+                Ok(()) => *socket = Some(s),
+                Err(tcp::ListenError::InvalidState) => return Err(AcceptError::InvalidState),
+                Err(tcp::ListenError::Unaddressable) => return Err(AcceptError::InvalidPort),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// State for [`TcpListener`]: the buffers of its `N` sockets.
+pub struct TcpListenerState<const N: usize, const TX_SZ: usize, const RX_SZ: usize> {
+    slots: [ListenerBuffers<TX_SZ, RX_SZ>; N],
+    waker: RefCell<WakerRegistration>,
+}
+
+impl<const N: usize, const TX_SZ: usize, const RX_SZ: usize> TcpListenerState<N, TX_SZ, RX_SZ> {
+    const SLOT: ListenerBuffers<TX_SZ, RX_SZ> = ListenerBuffers {
+        used: Cell::new(false),
+        bufs: UnsafeCell::new(([0; TX_SZ], [0; RX_SZ])),
+    };
+
+    /// Create a new `TcpListenerState`.
+    pub const fn new() -> Self {
+        Self {
+            slots: [Self::SLOT; N],
+            waker: RefCell::new(WakerRegistration::new()),
+        }
     }
 }
 
+struct ListenerBuffers<const TX_SZ: usize, const RX_SZ: usize> {
+    used: Cell<bool>,
+    bufs: UnsafeCell<([u8; TX_SZ], [u8; RX_SZ])>,
+}
+
+/// Slot of a [`TcpListenerState`], released when its socket is dropped.
+#[derive(Copy, Clone)]
+struct ListenerSlot<'a> {
+    used: &'a Cell<bool>,
+    waker: &'a RefCell<WakerRegistration>,
+}
+
 // =======================
 
 #[derive(Copy, Clone)]
@@ -313,7 +517,8 @@ impl<'d> TcpIo<'d> {
         res
     }
 
-    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+    /// Read data from the socket, adding the number of bytes read to `count`.
+    async fn read(&mut self, buf: &mut [u8], count: &Cell<u64>) -> Result<usize, Error> {
         poll_fn(move |cx| {
             // CAUTION: smoltcp semantics around EOF are different to what you'd expect
             // from posix-like IO, so we have to tweak things here.
@@ -324,7 +529,10 @@ impl<'d> TcpIo<'d> {
                     Poll::Pending
                 }
                 // Data ready!
-                Ok(n) => Poll::Ready(Ok(n)),
+                Ok(n) => {
+                    count.set(count.get().wrapping_add(n as u64));
+                    Poll::Ready(Ok(n))
+                }
                 // EOF
                 Err(tcp::RecvError::Finished) => Poll::Ready(Ok(0)),
                 // Connection reset. TODO: this can also be timeouts etc, investigate.
@@ -334,7 +542,8 @@ impl<'d> TcpIo<'d> {
         .await
     }
 
-    async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+    /// Write data to the socket, adding the number of bytes written to `count`.
+    async fn write(&mut self, buf: &[u8], count: &Cell<u64>) -> Result<usize, Error> {
         poll_fn(move |cx| {
             self.with_mut(|s, _| match s.send_slice(buf) {
                 // Not ready to send (no space in the tx buffer)
@@ -343,7 +552,10 @@ impl<'d> TcpIo<'d> {
                     Poll::Pending
                 }
                 // Some data sent
-                Ok(n) => Poll::Ready(Ok(n)),
+                Ok(n) => {
+                    count.set(count.get().wrapping_add(n as u64));
+                    Poll::Ready(Ok(n))
+                }
                 // Connection reset. TODO: this can also be timeouts etc, investigate.
                 Err(tcp::SendError::InvalidState) => Poll::Ready(Err(Error::ConnectionReset)),
             })
@@ -353,18 +565,41 @@ impl<'d> TcpIo<'d> {
 
     async fn flush(&mut self) -> Result<(), Error> {
         poll_fn(move |cx| {
-            self.with_mut(|s, _| {
-                let waiting_close = s.state() == tcp::State::Closed && s.remote_endpoint().is_some();
-                // If there are outstanding send operations, register for wake up and wait
-                // smoltcp issues wake-ups when octets are dequeued from the send buffer
-                if s.send_queue() > 0 || waiting_close {
-                    s.register_send_waker(cx.waker());
-                    Poll::Pending
-                // No outstanding sends, socket is flushed
-                } else {
-                    Poll::Ready(Ok(()))
-                }
-            })
+            let stack = &mut *self.stack.borrow_mut();
+            let s = stack.sockets.get_mut::<tcp::Socket>(self.handle);
+
+            let state = s.state();
+            // The send buffer is cleared when the connection is reset.
+            let data_pending = s.send_queue() > 0 && state != tcp::State::Closed;
+            let fin_pending = matches!(state, tcp::State::FinWait1 | tcp::State::Closing | tcp::State::LastAck);
+
+            // If there are outstanding send operations, register for wake up and wait
+            // smoltcp issues wake-ups when octets are dequeued from the send buffer
+            if data_pending || fin_pending {
+                s.register_send_waker(cx.waker());
+                Poll::Pending
+            // No outstanding sends, socket is flushed
+            } else {
+                Poll::Ready(Ok(()))
+            }
+        })
+        .await
+    }
+
+    /// Wait until the RST of an aborted connection is sent.
+    async fn wait_rst_sent(&mut self) {
+        poll_fn(move |cx| {
+            let stack = &mut *self.stack.borrow_mut();
+            let s = stack.sockets.get_mut::<tcp::Socket>(self.handle);
+
+            // After an abort, the socket keeps the remote endpoint until the RST is sent.
+            if s.state() == tcp::State::Closed && s.remote_endpoint().is_some() {
+                // smoltcp doesn't issue a wake-up when the RST is sent
+                stack.dispatch_waker.register(cx.waker());
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
         })
         .await
     }
@@ -392,13 +627,13 @@ mod embedded_io_impls {
 
     impl<'d> embedded_io::asynch::Read for TcpSocket<'d> {
         async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
-            self.io.read(buf).await
+            self.io.read(buf, &self.rx_bytes).await
         }
     }
 
     impl<'d> embedded_io::asynch::Write for TcpSocket<'d> {
         async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
-            self.io.write(buf).await
+            self.io.write(buf, &self.tx_bytes).await
         }
 
         async fn flush(&mut self) -> Result<(), Self::Error> {
@@ -412,7 +647,7 @@ mod embedded_io_impls {
 
     impl<'d> embedded_io::asynch::Read for TcpReader<'d> {
         async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
-            self.io.read(buf).await
+            self.io.read(buf, self.rx_bytes).await
         }
     }
 
@@ -422,7 +657,7 @@ mod embedded_io_impls {
 
     impl<'d> embedded_io::asynch::Write for TcpWriter<'d> {
         async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
-            self.io.write(buf).await
+            self.io.write(buf, self.tx_bytes).await
         }
 
         async fn flush(&mut self) -> Result<(), Self::Error> {
@@ -1,6 +1,6 @@
 //! UDP sockets.
 
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 use core::future::poll_fn;
 use core::mem;
 use core::task::Poll;
@@ -35,6 +35,21 @@ pub enum Error {
 pub struct UdpSocket<'a> {
     stack: &'a RefCell<SocketStack>,
     handle: SocketHandle,
+    stats: Cell<UdpStats>,
+}
+
+/// Statistics of an [`UdpSocket`], returned by [`UdpSocket::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UdpStats {
+    /// Datagrams received.
+    pub rx_datagrams: u64,
+    /// Bytes received, in the payload of the datagrams.
+    pub rx_bytes: u64,
+    /// Datagrams sent. They may still be waiting in the send buffer.
+    pub tx_datagrams: u64,
+    /// Bytes sent, in the payload of the datagrams.
+    pub tx_bytes: u64,
 }
 
 impl<'a> UdpSocket<'a> {
@@ -60,10 +75,14 @@ impl<'a> UdpSocket<'a> {
         Self {
             stack: &stack.socket,
             handle,
+            stats: Cell::new(UdpStats::default()),
         }
     }
 
     /// Bind the socket to a local endpoint.
+    ///
+    /// To receive multicast datagrams, bind to the port only, or to the group address and the
+    /// port, and join the group with [`Stack::join_multicast_group`](crate::Stack::join_multicast_group).
     pub fn bind<T>(&mut self, endpoint: T) -> Result<(), BindError>
     where
         T: Into<IpListenEndpoint>,
@@ -96,6 +115,13 @@ impl<'a> UdpSocket<'a> {
         res
     }
 
+    /// Set the hop limit (IPv4 TTL) of the datagrams sent by the socket.
+    ///
+    /// Multicast protocols often require a specific value. If `None`, the default of 64 is used.
+    pub fn set_hop_limit(&mut self, hop_limit: Option<u8>) {
+        self.with_mut(|s, _| s.set_hop_limit(hop_limit))
+    }
+
     /// Receive a datagram.
     ///
     /// This method will wait until a datagram is received.
@@ -104,7 +130,13 @@ impl<'a> UdpSocket<'a> {
     pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, IpEndpoint), Error> {
         poll_fn(move |cx| {
             self.with_mut(|s, _| match s.recv_slice(buf) {
-                Ok(x) => Poll::Ready(Ok(x)),
+                Ok((n, ep)) => {
+                    self.update_stats(|st| {
+                        st.rx_datagrams = st.rx_datagrams.wrapping_add(1);
+                        st.rx_bytes = st.rx_bytes.wrapping_add(n as u64);
+                    });
+                    Poll::Ready(Ok((n, ep)))
+                }
                 // No data ready
                 Err(udp::RecvError::Exhausted) => {
                     s.register_recv_waker(cx.waker());
@@ -124,7 +156,13 @@ impl<'a> UdpSocket<'a> {
         poll_fn(move |cx| {
             self.with_mut(|s, _| match s.send_slice(buf, remote_endpoint) {
                 // Entire datagram has been sent
-                Ok(()) => Poll::Ready(Ok(())),
+                Ok(()) => {
+                    self.update_stats(|st| {
+                        st.tx_datagrams = st.tx_datagrams.wrapping_add(1);
+                        st.tx_bytes = st.tx_bytes.wrapping_add(buf.len() as u64);
+                    });
+                    Poll::Ready(Ok(()))
+                }
                 Err(udp::SendError::BufferFull) => {
                     s.register_send_waker(cx.waker());
                     Poll::Pending
@@ -160,6 +198,17 @@ impl<'a> UdpSocket<'a> {
     pub fn may_recv(&self) -> bool {
         self.with(|s, _| s.can_recv())
     }
+
+    /// Returns the statistics of the socket.
+    pub fn stats(&self) -> UdpStats {
+        self.stats.get()
+    }
+
+    fn update_stats(&self, f: impl FnOnce(&mut UdpStats)) {
+        let mut stats = self.stats.get();
+        f(&mut stats);
+        self.stats.set(stats);
+    }
 }
 
 impl Drop for UdpSocket<'_> {
@@ -4,20 +4,76 @@ use core::sync::atomic::{compiler_fence, Ordering};
 use core::task::Poll;
 
 use embassy_cortex_m::interrupt::{Binding, Interrupt};
+use embassy_hal_common::drop::OnDrop;
 use embassy_sync::waitqueue::AtomicWaker;
 use embedded_hal_02::adc::{Channel, OneShot};
 
 use crate::gpio::Pin;
 use crate::interrupt::{self, InterruptExt, ADC_IRQ_FIFO};
 use crate::peripherals::ADC;
-use crate::{pac, peripherals, Peripheral};
+use crate::{dma, pac, peripherals, Peripheral};
 static WAKER: AtomicWaker = AtomicWaker::new();
 
+/// DREQ of the ADC FIFO
+const DREQ: u8 = 36;
+
+/// Channel of the temperature sensor
+const TEMPERATURE_CHANNEL: u8 = 4;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
 pub enum Error {
-    // No errors for now
+    /// The FIFO overflowed, samples were lost because the DMA could not keep up
+    Overrun,
+}
+
+/// Set of channels converted in turn by [`Adc::read_many`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Channels {
+    mask: u8,
+}
+
+impl Channels {
+    pub fn new() -> Self {
+        Self { mask: 0 }
+    }
+
+    /// Add the channel of `pin`
+    pub fn pin<PIN: Channel<Adc<'static>, ID = u8> + Pin>(mut self, pin: &mut PIN) -> Self {
+        configure_pin(pin);
+        self.mask |= 1 << PIN::channel();
+        self
+    }
+
+    /// Add the temperature sensor
+    pub fn temperature(mut self) -> Self {
+        self.mask |= 1 << TEMPERATURE_CHANNEL;
+        self
+    }
+
+    /// Number of channels in the set
+    pub fn len(&self) -> usize {
+        self.mask.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0
+    }
+}
+
+fn configure_pin(pin: &mut impl Pin) {
+    unsafe {
+        // disable pull-down and pull-up resistors
+        // pull-down resistors are enabled by default
+        pin.pad_ctrl().modify(|w| {
+            w.set_ie(true);
+            let (pu, pd) = (false, false);
+            w.set_pue(pu);
+            w.set_pde(pd);
+        });
+    }
 }
 
 #[non_exhaustive]
@@ -88,15 +144,8 @@ impl<'d> Adc<'d> {
 
     pub async fn read<PIN: Channel<Adc<'d>, ID = u8> + Pin>(&mut self, pin: &mut PIN) -> u16 {
         let r = Self::regs();
+        configure_pin(pin);
         unsafe {
-            // disable pull-down and pull-up resistors
-            // pull-down resistors are enabled by default
-            pin.pad_ctrl().modify(|w| {
-                w.set_ie(true);
-                let (pu, pd) = (false, false);
-                w.set_pue(pu);
-                w.set_pde(pd);
-            });
             r.cs().modify(|w| {
                 w.set_ainsel(PIN::channel());
                 w.set_start_once(true)
@@ -122,6 +171,87 @@ impl<'d> Adc<'d> {
         }
     }
 
+    /// Fill `buf` with free-running conversions of `channels`, transferred from the FIFO by `dma`.
+    ///
+    /// The channels are converted in turn, in increasing order of their channel number, so with several
+    /// channels `buf` holds interleaved samples starting with the lowest channel. A conversion is started
+    /// every `div + 1` cycles of the 48 MHz ADC clock, or every 96 cycles (500 ksps) if this is shorter.
+    ///
+    /// The conversions are stopped when `buf` is filled.
+    pub async fn read_many<C: dma::Channel>(
+        &mut self,
+        channels: Channels,
+        buf: &mut [u16],
+        div: u16,
+        dma: impl Peripheral<P = C>,
+    ) -> Result<(), Error> {
+        assert!(!channels.is_empty() && !buf.is_empty());
+
+        let r = Self::regs();
+        // The one-shot conversions don't use the FIFO, its configuration is restored on exit
+        let fcs = unsafe { r.fcs().read() };
+        unsafe {
+            if channels.mask & (1 << TEMPERATURE_CHANNEL) != 0 {
+                r.cs().modify(|w| w.set_ts_en(true));
+            }
+            while !r.cs().read().ready() {}
+
+            Self::drain_fifo();
+            r.fcs().write(|w| {
+                w.set_en(true);
+                w.set_dreq_en(true);
+                w.set_thresh(1);
+                // Clear the sticky flags
+                w.set_over(true);
+                w.set_under(true);
+            });
+            r.div().write(|w| w.set_int(div));
+        }
+
+        // Stop the conversions when done, or when cancelled. The DMA transfer is aborted before, when it is
+        // dropped.
+        let on_drop = OnDrop::new(|| unsafe {
+            r.cs().modify(|w| {
+                w.set_start_many(false);
+                w.set_rrobin(0);
+            });
+            while !r.cs().read().ready() {}
+
+            Self::drain_fifo();
+            let mut fcs = fcs;
+            fcs.set_over(true);
+            fcs.set_under(true);
+            r.fcs().write_value(fcs);
+        });
+
+        // Start the DMA before the conversions, so the FIFO does not overflow
+        let transfer = unsafe { dma::read(dma, r.fifo().ptr() as *const u16, buf, DREQ) };
+        unsafe {
+            r.cs().modify(|w| {
+                w.set_ainsel(channels.mask.trailing_zeros() as u8);
+                w.set_rrobin(if channels.len() > 1 { channels.mask } else { 0 });
+                w.set_start_many(true);
+            });
+        }
+        transfer.await;
+
+        let overrun = unsafe { r.fcs().read().over() };
+        drop(on_drop);
+
+        if overrun {
+            return Err(Error::Overrun);
+        }
+
+        Ok(())
+    }
+
+    unsafe fn drain_fifo() {
+        let r = Self::regs();
+        while !r.fcs().read().empty() {
+            r.fifo().read();
+        }
+    }
+
     pub fn blocking_read<PIN: Channel<Adc<'d>, ID = u8>>(&mut self, _pin: &mut PIN) -> u16 {
         let r = Self::regs();
         unsafe {
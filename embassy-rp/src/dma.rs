@@ -51,9 +51,33 @@ pub unsafe fn read<'a, C: Channel, W: Word>(
         to_ptr as *mut u32,
         len,
         W::size(),
-        false,
-        true,
         dreq,
+        TransferOptions {
+            incr_read: false,
+            ..Default::default()
+        },
+    )
+}
+
+pub(crate) unsafe fn read_repeated<'a, C: Channel, W: Word>(
+    ch: impl Peripheral<P = C> + 'a,
+    from: *const W,
+    len: usize,
+    dreq: u8,
+) -> Transfer<'a, C> {
+    static mut DUMMY: u32 = 0;
+    copy_inner(
+        ch,
+        from as *const u32,
+        &mut DUMMY as *mut u32,
+        len,
+        W::size(),
+        dreq,
+        TransferOptions {
+            incr_read: false,
+            incr_write: false,
+            ..Default::default()
+        },
     )
 }
 
@@ -70,9 +94,11 @@ pub unsafe fn write<'a, C: Channel, W: Word>(
         to as *mut u32,
         len,
         W::size(),
-        true,
-        false,
         dreq,
+        TransferOptions {
+            incr_write: false,
+            ..Default::default()
+        },
     )
 }
 
@@ -89,9 +115,12 @@ pub unsafe fn write_repeated<'a, C: Channel, W: Word>(
         to as *mut u32,
         len,
         W::size(),
-        false,
-        false,
         dreq,
+        TransferOptions {
+            incr_read: false,
+            incr_write: false,
+            ..Default::default()
+        },
     )
 }
 
@@ -109,21 +138,81 @@ pub unsafe fn copy<'a, C: Channel, W: Word>(
         to_ptr as *mut u32,
         from_len,
         W::size(),
-        true,
-        true,
         vals::TreqSel::PERMANENT.0,
+        Default::default(),
     )
 }
 
+/// Address wrapping of a transfer, to read from or write to a circular buffer.
+///
+/// The buffer is `1 << n` bytes long, and must be aligned to its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Ring {
+    /// Wrap the read address on a buffer of `1 << n` bytes, `n` in `1..16`
+    Read(u8),
+    /// Wrap the write address on a buffer of `1 << n` bytes, `n` in `1..16`
+    Write(u8),
+}
+
+/// Options of a [`transfer`]
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TransferOptions {
+    /// Increment the read address after each transfer
+    pub incr_read: bool,
+    /// Increment the write address after each transfer
+    pub incr_write: bool,
+    /// Wrap the read or write address
+    pub ring: Option<Ring>,
+    /// Number of the channel triggered when this transfer completes
+    pub chain_to: Option<u8>,
+    /// Start the transfer immediately. Otherwise it is started by [`Transfer::start`], or by the
+    /// completion of a channel chained to it.
+    pub start: bool,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        Self {
+            incr_read: true,
+            incr_write: true,
+            ring: None,
+            chain_to: None,
+            start: true,
+        }
+    }
+}
+
+/// Transfer `len` words from `from` to `to`, paced by `dreq`.
+///
+/// This is the general form of [`read`], [`write`] and [`copy`], for transfers that need address
+/// wrapping or chaining. Use `TreqSel::PERMANENT` as `dreq` for transfers between memories.
+///
+/// # Safety
+///
+/// `from` and `to` must be valid for the whole transfer, taking the increments and the wrapping of the
+/// addresses into account, until the returned [`Transfer`] completes or is dropped.
+pub unsafe fn transfer<'a, C: Channel, W: Word>(
+    ch: impl Peripheral<P = C> + 'a,
+    from: *const W,
+    to: *mut W,
+    len: usize,
+    dreq: u8,
+    options: TransferOptions,
+) -> Transfer<'a, C> {
+    copy_inner(ch, from as *const u32, to as *mut u32, len, W::size(), dreq, options)
+}
+
 fn copy_inner<'a, C: Channel>(
     ch: impl Peripheral<P = C> + 'a,
     from: *const u32,
     to: *mut u32,
     len: usize,
     data_size: DataSize,
-    incr_read: bool,
-    incr_write: bool,
     dreq: u8,
+    options: TransferOptions,
 ) -> Transfer<'a, C> {
     into_ref!(ch);
 
@@ -136,16 +225,31 @@ fn copy_inner<'a, C: Channel>(
 
         compiler_fence(Ordering::SeqCst);
 
-        p.ctrl_trig().write(|w| {
-            // TODO: Add all DREQ options to pac vals::TreqSel, and use
-            // `set_treq:sel`
-            w.0 = ((dreq as u32) & 0x3f) << 15usize;
-            w.set_data_size(data_size);
-            w.set_incr_read(incr_read);
-            w.set_incr_write(incr_write);
-            w.set_chain_to(ch.number());
-            w.set_en(true);
-        });
+        let mut ctrl = pac::dma::regs::CtrlTrig(0);
+        // TODO: Add all DREQ options to pac vals::TreqSel, and use
+        // `set_treq:sel`
+        ctrl.0 = ((dreq as u32) & 0x3f) << 15usize;
+        ctrl.set_data_size(data_size);
+        ctrl.set_incr_read(options.incr_read);
+        ctrl.set_incr_write(options.incr_write);
+        if let Some(ring) = options.ring {
+            let (size, write) = match ring {
+                Ring::Read(n) => (n, false),
+                Ring::Write(n) => (n, true),
+            };
+            assert!((1..16).contains(&size));
+            ctrl.set_ring_size(size);
+            ctrl.set_ring_sel(write);
+        }
+        // Chaining to the channel itself disables chaining
+        ctrl.set_chain_to(options.chain_to.unwrap_or(ch.number()));
+        ctrl.set_en(true);
+
+        if options.start {
+            p.ctrl_trig().write_value(ctrl);
+        } else {
+            p.al1_ctrl().write_value(ctrl.0);
+        }
 
         compiler_fence(Ordering::SeqCst);
     }
@@ -163,6 +267,22 @@ impl<'a, C: Channel> Transfer<'a, C> {
 
         Self { channel }
     }
+
+    /// Start a transfer configured without [`TransferOptions::start`], or restart a completed transfer
+    /// from its current addresses.
+    pub fn start(&mut self) {
+        unsafe {
+            pac::DMA
+                .multi_chan_trigger()
+                .write(|w| w.set_multi_chan_trigger(1 << self.channel.number()));
+        }
+    }
+
+    /// Whether the transfer is still running, or waiting to be started
+    pub fn is_running(&self) -> bool {
+        let p = self.channel.regs();
+        unsafe { p.ctrl_trig().read().busy() || p.trans_count().read() != 0 }
+    }
 }
 
 impl<'a, C: Channel> Drop for Transfer<'a, C> {
@@ -185,7 +305,7 @@ impl<'a, C: Channel> Future for Transfer<'a, C> {
         // calls to wake will deregister the waker.
         CHANNEL_WAKERS[self.channel.number() as usize].register(cx.waker());
 
-        if unsafe { self.channel.regs().ctrl_trig().read().busy() } {
+        if self.is_running() {
             Poll::Pending
         } else {
             Poll::Ready(())
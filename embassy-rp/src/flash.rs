@@ -1,5 +1,6 @@
 use core::marker::PhantomData;
 
+use embassy_futures::yield_now;
 use embassy_hal_common::Peripheral;
 use embedded_storage::nor_flash::{
     check_erase, check_read, check_write, ErrorType, MultiwriteNorFlash, NorFlash, NorFlashError, NorFlashErrorKind,
@@ -21,6 +22,11 @@ pub const WRITE_SIZE: usize = 1;
 pub const READ_SIZE: usize = 1;
 pub const ERASE_SIZE: usize = 4096;
 
+/// Size of a line of the XIP cache.
+pub const XIP_CACHE_LINE_SIZE: usize = 8;
+/// Total size of the XIP cache.
+pub const XIP_CACHE_SIZE: usize = 16 * 1024;
+
 /// Error type for NVMC operations.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -158,10 +164,96 @@ impl<'d, T: Instance, const FLASH_SIZE: usize> Flash<'d, T, FLASH_SIZE> {
         Ok(())
     }
 
-    /// Make sure to uphold the contract points with rp2040-flash.
-    /// - interrupts must be disabled
-    /// - DMA must not access flash memory
-    unsafe fn in_ram(&mut self, operation: impl FnOnce()) -> Result<(), Error> {
+    /// Write `bytes` to an arbitrary `offset`, preserving all other flash contents.
+    ///
+    /// Unlike [`Flash::write`], this does not require the target range to be erased.
+    /// Every sector touched by the write is read into a RAM buffer, patched with `bytes`,
+    /// erased and reprogrammed. This costs an `ERASE_SIZE` stack buffer and one erase per
+    /// affected sector.
+    ///
+    /// Writes spanning a sector boundary are split per sector, so the bytes before `offset` in
+    /// the first sector and after the end of `bytes` in the last sector are preserved.
+    pub fn write_unaligned(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Error> {
+        check_read(self, offset, bytes.len())?;
+
+        trace!(
+            "Writing {:?} unaligned bytes to 0x{:x}",
+            bytes.len(),
+            FLASH_BASE as u32 + offset
+        );
+
+        let mut sector_buf = [0xFF_u8; ERASE_SIZE];
+        let mut offset = offset;
+        let mut bytes = bytes;
+
+        while !bytes.is_empty() {
+            let sector_offset = offset - offset % ERASE_SIZE as u32;
+            let start = (offset - sector_offset) as usize;
+            let len = core::cmp::min(ERASE_SIZE - start, bytes.len());
+
+            self.read(sector_offset, &mut sector_buf)?;
+            sector_buf[start..start + len].copy_from_slice(&bytes[..len]);
+
+            unsafe { self.in_ram(|| ram_helpers::flash_range_erase_and_program(sector_offset, &sector_buf, true))? };
+
+            offset += len as u32;
+            bytes = &bytes[len..];
+        }
+
+        Ok(())
+    }
+
+    /// Erase the sectors from `from` to `to`, yielding to other tasks between sectors.
+    ///
+    /// Interrupts are still disabled and core1 is paused while a sector is erased, but only for one sector
+    /// at a time instead of the whole range.
+    pub async fn erase_async(&mut self, from: u32, to: u32) -> Result<(), Error> {
+        check_erase(self, from, to)?;
+
+        for sector in (from..to).step_by(ERASE_SIZE) {
+            self.erase(sector, sector + ERASE_SIZE as u32)?;
+            yield_now().await;
+        }
+
+        Ok(())
+    }
+
+    /// Write `bytes` to `offset`, yielding to other tasks between pages.
+    ///
+    /// Like [`Flash::erase_async`], interrupts are disabled and core1 is paused while a page is programmed.
+    pub async fn write_async(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Error> {
+        check_write(self, offset, bytes.len())?;
+
+        let mut offset = offset;
+        let mut bytes = bytes;
+
+        while !bytes.is_empty() {
+            let len = core::cmp::min(PAGE_SIZE - offset as usize % PAGE_SIZE, bytes.len());
+            self.write(offset, &bytes[..len])?;
+
+            offset += len as u32;
+            bytes = &bytes[len..];
+            yield_now().await;
+        }
+
+        Ok(())
+    }
+
+    /// Run `operation` while nothing else accesses flash, as required to take the flash out of XIP mode.
+    ///
+    /// Core1 is parked in RAM, interrupts are disabled and DMA transfers reading from flash are completed
+    /// before `operation` is called. The flash operations of this driver are built on this, it can be used to
+    /// issue other commands to the flash chip.
+    ///
+    /// # Safety
+    ///
+    /// All code executed while XIP is disabled must be located in RAM, e.g. with
+    /// `#[link_section = ".data.ram_func"]`, and XIP must be enabled again before `operation` returns.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidCore`] if not called from core0.
+    pub unsafe fn in_ram(&mut self, operation: impl FnOnce()) -> Result<(), Error> {
         // Make sure we're running on CORE0
         let core_id: u32 = unsafe { pac::SIO.cpuid().read() };
         if core_id != 0 {
@@ -188,13 +280,32 @@ impl<'d, T: Instance, const FLASH_SIZE: usize> Flash<'d, T, FLASH_SIZE> {
         Ok(())
     }
 
+    /// Invalidate the XIP cache lines covering `len` bytes at `offset`.
+    ///
+    /// [`Flash::erase`] and the write methods already do this for the range they modify, so the
+    /// instruction cache stays warm for the rest of the flash. Ranges larger than the cache
+    /// result in a full flush.
+    pub fn flush_cache_range(&mut self, offset: u32, len: u32) {
+        ram_helpers::flush_cache_range(offset, len);
+    }
+
+    /// Flush the entire XIP cache.
+    pub fn flush_cache(&mut self) {
+        ram_helpers::flush_cache();
+    }
+
     /// Read SPI flash unique ID
+    ///
+    /// This issues SPI commands that take the flash out of XIP mode, so like erase and write it
+    /// must be called from core0 and pauses core1 for the duration of the read.
     pub fn unique_id(&mut self, uid: &mut [u8]) -> Result<(), Error> {
         unsafe { self.in_ram(|| ram_helpers::flash_unique_id(uid, true))? };
         Ok(())
     }
 
     /// Read SPI flash JEDEC ID
+    ///
+    /// Like [`Flash::unique_id`], this must be called from core0 and pauses core1.
     pub fn jedec_id(&mut self) -> Result<u32, Error> {
         let mut jedec = None;
         unsafe {
@@ -204,6 +315,86 @@ impl<'d, T: Instance, const FLASH_SIZE: usize> Flash<'d, T, FLASH_SIZE> {
         };
         Ok(jedec.unwrap())
     }
+
+    /// Read the block protection bits of the SPI flash status registers
+    ///
+    /// Like [`Flash::unique_id`], this must be called from core0 and pauses core1.
+    pub fn block_protection(&mut self) -> Result<BlockProtection, Error> {
+        let mut status = None;
+        unsafe {
+            self.in_ram(|| {
+                status.replace(ram_helpers::flash_read_status(true));
+            })?;
+        };
+        Ok(BlockProtection::from_status(status.unwrap()))
+    }
+
+    /// Write the block protection bits of the SPI flash status registers
+    ///
+    /// The other bits of the status registers are left unchanged. The setting is stored in the
+    /// non-volatile status registers, so it persists across resets and power cycles.
+    ///
+    /// Erases and writes of protected blocks are silently ignored by the flash, the operations
+    /// of this driver still return `Ok`.
+    ///
+    /// Like [`Flash::unique_id`], this must be called from core0 and pauses core1.
+    pub fn set_block_protection(&mut self, protection: BlockProtection) -> Result<(), Error> {
+        if protection.bp > 7 {
+            return Err(Error::Other);
+        }
+
+        unsafe {
+            self.in_ram(|| {
+                let status = ram_helpers::flash_read_status(true);
+                ram_helpers::flash_write_status(protection.to_status(status), true);
+            })?;
+        };
+        Ok(())
+    }
+}
+
+/// Block protection bits of the SPI flash status registers
+///
+/// The range protected by a setting depends on the flash part. For the W25Q16JV on the Pico,
+/// with `sector` and `complement` cleared, `bp` 1 to 5 protect 64 KB << (bp - 1) at the top of
+/// the flash, or at the bottom if `bottom` is set, and `bp` 6 and 7 protect the whole flash.
+/// Refer to the datasheet of the flash part for the other combinations.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BlockProtection {
+    /// Block protect bits BP0-BP2, 0 to 7
+    pub bp: u8,
+    /// Top/bottom bit TB, protect from the bottom instead of the top of the flash
+    pub bottom: bool,
+    /// Sector bit SEC, protect 4 KB sectors instead of 64 KB blocks
+    pub sector: bool,
+    /// Complement bit CMP, invert the protected range
+    pub complement: bool,
+}
+
+impl BlockProtection {
+    /// Nothing protected
+    pub const NONE: Self = Self {
+        bp: 0,
+        bottom: false,
+        sector: false,
+        complement: false,
+    };
+
+    fn from_status(status: [u8; 2]) -> Self {
+        Self {
+            bp: (status[0] >> 2) & 0x7,
+            bottom: status[0] & (1 << 5) != 0,
+            sector: status[0] & (1 << 6) != 0,
+            complement: status[1] & (1 << 6) != 0,
+        }
+    }
+
+    fn to_status(self, status: [u8; 2]) -> [u8; 2] {
+        let sr1 = (status[0] & !0x7c) | (self.bp & 0x7) << 2 | (self.bottom as u8) << 5 | (self.sector as u8) << 6;
+        let sr2 = (status[1] & !(1 << 6)) | (self.complement as u8) << 6;
+        [sr1, sr2]
+    }
 }
 
 impl<'d, T: Instance, const FLASH_SIZE: usize> ErrorType for Flash<'d, T, FLASH_SIZE> {
@@ -238,6 +429,38 @@ impl<'d, T: Instance, const FLASH_SIZE: usize> NorFlash for Flash<'d, T, FLASH_S
     }
 }
 
+#[cfg(feature = "nightly")]
+impl<'d, T: Instance, const FLASH_SIZE: usize> embedded_storage_async::nor_flash::ReadNorFlash
+    for Flash<'d, T, FLASH_SIZE>
+{
+    const READ_SIZE: usize = READ_SIZE;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.read(offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity()
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<'d, T: Instance, const FLASH_SIZE: usize> embedded_storage_async::nor_flash::NorFlash
+    for Flash<'d, T, FLASH_SIZE>
+{
+    const WRITE_SIZE: usize = WRITE_SIZE;
+
+    const ERASE_SIZE: usize = ERASE_SIZE;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.erase_async(from, to).await
+    }
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.write_async(offset, bytes).await
+    }
+}
+
 #[allow(dead_code)]
 mod ram_helpers {
     use core::marker::PhantomData;
@@ -302,6 +525,48 @@ mod ram_helpers {
         }
     }
 
+    /// Release the QSPI chip select, which the boot-rom functions leave forced high.
+    ///
+    /// This is what the boot-rom `flash_flush_cache` does besides flushing the whole XIP
+    /// cache. It is used in its place so only the modified range needs to be invalidated
+    /// afterwards.
+    #[inline(never)]
+    #[link_section = ".data.ram_func"]
+    unsafe extern "C" fn flash_release_cs() {
+        // Clear GPIO_QSPI_SS_CTRL.OUTOVER through the atomic clear alias
+        #[cfg(target_arch = "arm")]
+        core::arch::asm!(
+            "str {outover}, [{ss_ctrl}]",
+            ss_ctrl = in(reg) 0x4001_800c + 0x3000,
+            outover = in(reg) 0x300,
+        );
+    }
+
+    /// Flush the entire XIP cache.
+    pub fn flush_cache() {
+        unsafe {
+            crate::pac::XIP_CTRL.flush().write(|w| w.set_flush(true));
+            // Reading blocks until the flush is complete
+            crate::pac::XIP_CTRL.flush().read();
+        }
+    }
+
+    /// Invalidate the XIP cache lines covering `len` bytes starting at flash offset `addr`.
+    pub fn flush_cache_range(addr: u32, len: u32) {
+        if len as usize >= super::XIP_CACHE_SIZE {
+            return flush_cache();
+        }
+
+        let line_mask = !(super::XIP_CACHE_LINE_SIZE as u32 - 1);
+        let start = addr & line_mask;
+        let end = addr + len;
+
+        // A write to the cached XIP alias invalidates the cache line on a tag match.
+        for line in (start..end).step_by(super::XIP_CACHE_LINE_SIZE) {
+            unsafe { core::ptr::write_volatile((super::FLASH_BASE as u32 + line) as *mut u32, 0) };
+        }
+    }
+
     /// Erase a flash range starting at `addr` with length `len`.
     ///
     /// `addr` and `len` must be multiples of 4096
@@ -320,16 +585,20 @@ mod ram_helpers {
     /// `addr` and `len` parameters must be valid and are not checked.
     pub unsafe fn flash_range_erase(addr: u32, len: u32, use_boot2: bool) {
         let mut boot2 = [0u32; 256 / 4];
-        let ptrs = if use_boot2 {
+        let mut ptrs = if use_boot2 {
             rom_data::memcpy44(&mut boot2 as *mut _, super::FLASH_BASE as *const _, 256);
             flash_function_pointers_with_boot2(true, false, &boot2)
         } else {
             flash_function_pointers(true, false)
         };
 
+        ptrs.flash_flush_cache = flash_release_cs;
+
         core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
 
         write_flash_inner(addr, len, None, &ptrs as *const FlashFunctionPointers);
+
+        flush_cache_range(addr, len);
     }
 
     /// Erase and rewrite a flash range starting at `addr` with data `data`.
@@ -350,13 +619,15 @@ mod ram_helpers {
     /// `addr` and `len` parameters must be valid and are not checked.
     pub unsafe fn flash_range_erase_and_program(addr: u32, data: &[u8], use_boot2: bool) {
         let mut boot2 = [0u32; 256 / 4];
-        let ptrs = if use_boot2 {
+        let mut ptrs = if use_boot2 {
             rom_data::memcpy44(&mut boot2 as *mut _, super::FLASH_BASE as *const _, 256);
             flash_function_pointers_with_boot2(true, true, &boot2)
         } else {
             flash_function_pointers(true, true)
         };
 
+        ptrs.flash_flush_cache = flash_release_cs;
+
         core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
 
         write_flash_inner(
@@ -365,6 +636,8 @@ mod ram_helpers {
             Some(data),
             &ptrs as *const FlashFunctionPointers,
         );
+
+        flush_cache_range(addr, data.len() as u32);
     }
 
     /// Write a flash range starting at `addr` with data `data`.
@@ -385,13 +658,15 @@ mod ram_helpers {
     /// `addr` and `len` parameters must be valid and are not checked.
     pub unsafe fn flash_range_program(addr: u32, data: &[u8], use_boot2: bool) {
         let mut boot2 = [0u32; 256 / 4];
-        let ptrs = if use_boot2 {
+        let mut ptrs = if use_boot2 {
             rom_data::memcpy44(&mut boot2 as *mut _, super::FLASH_BASE as *const _, 256);
             flash_function_pointers_with_boot2(false, true, &boot2)
         } else {
             flash_function_pointers(false, true)
         };
 
+        ptrs.flash_flush_cache = flash_release_cs;
+
         core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
 
         write_flash_inner(
@@ -400,6 +675,8 @@ mod ram_helpers {
             Some(data),
             &ptrs as *const FlashFunctionPointers,
         );
+
+        flush_cache_range(addr, data.len() as u32);
     }
 
     /// # Safety
@@ -551,6 +828,68 @@ mod ram_helpers {
         u32::from_be_bytes(id)
     }
 
+    /// Return the status registers 1 and 2 of the SPI flash
+    ///
+    /// # Safety
+    ///
+    /// Nothing must access flash while this is running.
+    /// Usually this means:
+    ///   - interrupts must be disabled
+    ///   - 2nd core must be running code from RAM or ROM with interrupts disabled
+    ///   - DMA must not access flash memory
+    pub unsafe fn flash_read_status(use_boot2: bool) -> [u8; 2] {
+        let mut boot2 = [0u32; 256 / 4];
+        let ptrs = if use_boot2 {
+            rom_data::memcpy44(&mut boot2 as *mut _, 0x10000000 as *const _, 256);
+            flash_function_pointers_with_boot2(false, false, &boot2)
+        } else {
+            flash_function_pointers(false, false)
+        };
+        let mut status = [0u8; 2];
+        // 05 - read status register 1, 35 - read status register 2
+        read_flash(&[0x05], 0, &mut status[0..1], &ptrs as *const FlashFunctionPointers);
+        read_flash(&[0x35], 0, &mut status[1..2], &ptrs as *const FlashFunctionPointers);
+        status
+    }
+
+    /// Write the status registers 1 and 2 of the SPI flash, and wait for the write to complete
+    ///
+    /// # Safety
+    ///
+    /// Nothing must access flash while this is running.
+    /// Usually this means:
+    ///   - interrupts must be disabled
+    ///   - 2nd core must be running code from RAM or ROM with interrupts disabled
+    ///   - DMA must not access flash memory
+    pub unsafe fn flash_write_status(status: [u8; 2], use_boot2: bool) {
+        let mut boot2 = [0u32; 256 / 4];
+        let ptrs = if use_boot2 {
+            rom_data::memcpy44(&mut boot2 as *mut _, 0x10000000 as *const _, 256);
+            flash_function_pointers_with_boot2(false, false, &boot2)
+        } else {
+            flash_function_pointers(false, false)
+        };
+        // 06 - write enable, 01 - write status registers
+        let mut write_enable = [0x06];
+        let mut write_status = [0x01, status[0], status[1]];
+        let transfers = [
+            FlashTransfer {
+                buf: write_enable.as_mut_ptr(),
+                len: write_enable.len() as u32,
+            },
+            FlashTransfer {
+                buf: write_status.as_mut_ptr(),
+                len: write_status.len() as u32,
+            },
+        ];
+        transfer_flash_inner(
+            transfers.as_ptr(),
+            transfers.len() as u32,
+            true,
+            &ptrs as *const FlashFunctionPointers,
+        );
+    }
+
     unsafe fn read_flash(cmd_addr: &[u8], dummy_len: u32, out: &mut [u8], ptrs: *const FlashFunctionPointers) {
         read_flash_inner(
             FlashCommand {
@@ -688,6 +1027,160 @@ mod ram_helpers {
             clobber_abi("C"),
         );
     }
+
+    #[repr(C)]
+    struct FlashTransfer {
+        buf: *mut u8,
+        len: u32,
+    }
+
+    /// Issue a sequence of SPI flash commands, each with the chip select asserted for its duration
+    ///
+    /// The bytes of each transfer are sent and replaced with the bytes received. If `wait` is
+    /// set, the status register is polled until the write in progress bit is cleared.
+    ///
+    /// # Arguments
+    ///
+    /// * `transfers` - array of `count` transfers, each with a length of at least 1
+    /// * `ptrs` - Flash function pointers as per `write_flash_inner`
+    #[inline(never)]
+    #[link_section = ".data.ram_func"]
+    unsafe fn transfer_flash_inner(
+        transfers: *const FlashTransfer,
+        count: u32,
+        wait: bool,
+        ptrs: *const FlashFunctionPointers,
+    ) {
+        #[cfg(target_arch = "arm")]
+        core::arch::asm!(
+            "mov r8, r0", // transfers
+            "mov r9, r1", // count
+            "mov r10, r2", // wait
+            "mov r5, r3", // ptrs
+
+            "ldr r4, [r5, #0]",
+            "blx r4", // connect_internal_flash()
+
+            "ldr r4, [r5, #4]",
+            "blx r4", // flash_exit_xip()
+
+            // 0x4001800c, GPIO_QSPI_SS_CTRL, RP2040 datasheet 2.19.6.2
+            "movs r0, #0x40",
+            "lsls r0, r0, #8",
+            "adds r0, #0x01",
+            "lsls r0, r0, #8",
+            "adds r0, #0x80",
+            "lsls r0, r0, #8",
+            "adds r0, #0x0c",
+            "mov r11, r0",
+
+            "movs r4, #0x18",
+            "lsls r4, r4, #24", // 0x18000000, SSI, RP2040 datasheet 4.10.13
+
+            // Disable, write 0 to SSIENR
+            "movs r0, #0",
+            "str r0, [r4, #8]", // SSIENR
+
+            // Clear TMOD in ctrlr0, transmit and receive
+            "movs r0, #0x3",
+            "lsls r0, r0, #8",
+            "ldr r1, [r4, #0]", // CTRLR0
+            "bics r1, r0",
+            "str r1, [r4, #0]",
+
+            // Enable, write 1 to ssienr
+            "movs r0, #1",
+            "str r0, [r4, #8]", // SSIENR
+
+            // Issue the transfers
+            "1:",
+            "mov r0, r9",
+            "cmp r0, #0",
+            "beq 3f",
+            "mov r2, r8",
+            "ldr r0, [r2, #0]", // buf
+            "ldr r1, [r2, #4]", // len
+            "bl 5f",
+            "mov r0, r8",
+            "adds r0, #8",
+            "mov r8, r0",
+            "mov r0, r9",
+            "subs r0, #1",
+            "mov r9, r0",
+            "b 1b",
+
+            // Poll status register 1 until WIP is cleared
+            "3:",
+            "mov r0, r10",
+            "cmp r0, #0",
+            "beq 4f",
+            "sub sp, #8",
+            "6:",
+            "movs r0, #0x05",
+            "str r0, [sp, #0]",
+            "mov r0, sp",
+            "movs r1, #2",
+            "bl 5f",
+            "ldr r0, [sp, #0]",
+            "lsrs r0, r0, #9", // WIP of the second byte to carry
+            "bcs 6b",
+            "add sp, #8",
+
+            // Release the chip select
+            "4:",
+            "movs r0, #0",
+            "mov r2, r11",
+            "str r0, [r2]", // GPIO_QSPI_SS_CTRL
+
+            // Disable, write 0 to ssienr
+            "str r0, [r4, #8]", // SSIENR
+
+            "ldr r4, [r5, #20]",
+            "blx r4", // flash_enter_cmd_xip();
+            "b 9f",
+
+            // Transfer r1 bytes at r0 in place, with the chip select forced low
+            "5:",
+            "mov r2, r11",
+            "movs r3, #0x2",
+            "lsls r3, r3, #8",
+            "str r3, [r2]", // GPIO_QSPI_SS_CTRL, OUTOVER low
+            "mov r2, r4",
+            "adds r2, 0x60", // &DR
+            "7:",
+            "ldrb r3, [r0]",
+            "str r3, [r2]", // DR
+            "8:",
+            "ldr r3, [r4, #0x28]", // SR
+            "lsrs r3, r3, #4", // SR.RFNE to carry
+            "bcc 8b",
+            "ldr r3, [r2]", // DR
+            "strb r3, [r0]",
+            "adds r0, #1",
+            "subs r1, #1",
+            "bne 7b",
+            "mov r2, r11",
+            "movs r3, #0x3",
+            "lsls r3, r3, #8",
+            "str r3, [r2]", // GPIO_QSPI_SS_CTRL, OUTOVER high
+            "bx lr",
+
+            "9:",
+            in("r0") transfers,
+            in("r1") count,
+            in("r2") wait as u32,
+            in("r3") ptrs,
+            out("r4") _,
+            out("r5") _,
+            // Registers r8-r11 are used to store values
+            // which must survive function calls.
+            out("r8") _,
+            out("r9") _,
+            out("r10") _,
+            out("r11") _,
+            clobber_abi("C"),
+        );
+    }
 }
 
 mod sealed {
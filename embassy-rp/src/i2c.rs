@@ -362,23 +362,8 @@ impl<'d, T: Instance + 'd, M: Mode> I2c<'d, T, M> {
             p.ic_rx_tl().write(|w| w.set_rx_tl(0));
 
             // Configure SCL & SDA pins
-            scl.io().ctrl().write(|w| w.set_funcsel(3));
-            sda.io().ctrl().write(|w| w.set_funcsel(3));
-
-            scl.pad_ctrl().write(|w| {
-                w.set_schmitt(true);
-                w.set_ie(true);
-                w.set_od(false);
-                w.set_pue(true);
-                w.set_pde(false);
-            });
-            sda.pad_ctrl().write(|w| {
-                w.set_schmitt(true);
-                w.set_ie(true);
-                w.set_od(false);
-                w.set_pue(true);
-                w.set_pde(false);
-            });
+            set_up_i2c_pin(&scl);
+            set_up_i2c_pin(&sda);
 
             // Configure baudrate
 
@@ -757,7 +742,18 @@ mod nightly {
     }
 }
 
-fn i2c_reserved_addr(addr: u16) -> bool {
+pub(crate) unsafe fn set_up_i2c_pin(pin: &PeripheralRef<'_, AnyPin>) {
+    pin.io().ctrl().write(|w| w.set_funcsel(3));
+    pin.pad_ctrl().write(|w| {
+        w.set_schmitt(true);
+        w.set_ie(true);
+        w.set_od(false);
+        w.set_pue(true);
+        w.set_pde(false);
+    });
+}
+
+pub(crate) fn i2c_reserved_addr(addr: u16) -> bool {
     (addr & 0x78) == 0 || (addr & 0x78) == 0x78
 }
 
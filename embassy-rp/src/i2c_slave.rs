@@ -0,0 +1,301 @@
+//! I2C slave (target) mode
+//!
+//! The controller is addressed by a master on the bus, [`I2cSlave::listen`] returns the commands it
+//! sends, and [`I2cSlave::respond_to_read`] answers its reads.
+
+use core::future;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use embassy_cortex_m::interrupt::{Binding, Interrupt, InterruptExt};
+use embassy_hal_common::into_ref;
+use pac::i2c;
+
+use crate::i2c::{i2c_reserved_addr, set_up_i2c_pin, AbortReason, Instance, InterruptHandler, SclPin, SdaPin};
+use crate::{pac, Peripheral};
+
+/// I2C slave error
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Error {
+    /// I2C abort with error
+    Abort(AbortReason),
+    /// User passed in a response buffer that was 0 length
+    InvalidResponseBufferLength,
+    /// The master wrote more bytes than fit in the buffer, the first `usize` were received
+    PartialWrite(usize),
+    /// The master sent a general call with more bytes than fit in the buffer, the first `usize` were
+    /// received
+    PartialGeneralCall(usize),
+}
+
+/// Command received from the master
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Command {
+    /// The master sent `usize` bytes to the general call address
+    GeneralCall(usize),
+    /// The master reads from us, answer with [`I2cSlave::respond_to_read`]
+    Read,
+    /// The master wrote `usize` bytes to us
+    Write(usize),
+    /// The master wrote `usize` bytes to us, then reads from us after a repeated start. Answer with
+    /// [`I2cSlave::respond_to_read`]
+    WriteRead(usize),
+}
+
+/// Outcome of [`I2cSlave::respond_to_read`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReadStatus {
+    /// All the bytes of the response were read by the master
+    Done,
+    /// The master wants more bytes than the response, call [`I2cSlave::respond_to_read`] again
+    NeedMoreBytes,
+    /// The master ended the read before the end of the response, `u16` bytes were not read
+    LeftoverBytes(u16),
+}
+
+/// I2C slave configuration
+#[non_exhaustive]
+#[derive(Copy, Clone)]
+pub struct Config {
+    /// 7-bit address of the slave, which must not be one of the reserved addresses (0x00 to 0x07 and
+    /// 0x78 to 0x7F). Defaults to 0x55.
+    pub addr: u16,
+    /// Respond to the general call address, and report its writes as [`Command::GeneralCall`].
+    /// Defaults to `true`.
+    pub general_call: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            addr: 0x55,
+            general_call: true,
+        }
+    }
+}
+
+const FIFO_SIZE: usize = 16;
+
+/// I2C slave driver
+pub struct I2cSlave<'d, T: Instance> {
+    phantom: PhantomData<&'d mut T>,
+}
+
+impl<'d, T: Instance> I2cSlave<'d, T> {
+    /// Create a new I2C slave on the `scl` and `sda` pins, with the addresses of `config`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config.addr` is not a 7-bit address, or is reserved.
+    pub fn new(
+        _peri: impl Peripheral<P = T> + 'd,
+        scl: impl Peripheral<P = impl SclPin<T>> + 'd,
+        sda: impl Peripheral<P = impl SdaPin<T>> + 'd,
+        _irq: impl Binding<T::Interrupt, InterruptHandler<T>>,
+        config: Config,
+    ) -> Self {
+        into_ref!(_peri, scl, sda);
+
+        assert!(config.addr < 0x80 && !i2c_reserved_addr(config.addr));
+
+        let p = T::regs();
+
+        unsafe {
+            let reset = T::reset();
+            crate::reset::reset(reset);
+            crate::reset::unreset_wait(reset);
+
+            p.ic_enable().write(|w| w.set_enable(false));
+
+            p.ic_sar().write(|w| w.set_ic_sar(config.addr));
+            p.ic_con().modify(|w| {
+                w.set_master_mode(false);
+                w.set_ic_slave_disable(false);
+                w.set_speed(i2c::vals::Speed::FAST);
+                w.set_tx_empty_ctrl(true);
+                // Stretch the clock instead of dropping bytes when the rx fifo is full
+                w.set_rx_fifo_full_hld_ctrl(true);
+                // Only signal the stops of transfers addressed to us
+                w.set_stop_det_ifaddressed(true);
+            });
+            p.ic_ack_general_call()
+                .write(|w| w.set_ack_gen_call(config.general_call));
+
+            // Set FIFO watermarks to 1 to make things simpler. This is encoded
+            // by a register value of 0.
+            p.ic_tx_tl().write(|w| w.set_tx_tl(0));
+            p.ic_rx_tl().write(|w| w.set_rx_tl(0));
+
+            // Configure SCL & SDA pins
+            set_up_i2c_pin(&scl.map_into());
+            set_up_i2c_pin(&sda.map_into());
+
+            // Clear the stale interrupts and mask everything initially
+            p.ic_clr_intr().read();
+            p.ic_intr_mask().write_value(i2c::regs::IcIntrMask(0));
+            T::Interrupt::steal().unpend();
+            T::Interrupt::steal().enable();
+
+            // Enable I2C block
+            p.ic_enable().write(|w| w.set_enable(true));
+        }
+
+        Self { phantom: PhantomData }
+    }
+
+    /// Calls `f` to check if we are ready or not.
+    /// If not, `g` is called once the waker is set (to eg enable the required interrupts).
+    async fn wait_on<F, U, G>(&mut self, mut f: F, mut g: G) -> U
+    where
+        F: FnMut(&mut Self) -> Poll<U>,
+        G: FnMut(&mut Self),
+    {
+        future::poll_fn(|cx| {
+            let r = f(self);
+
+            if r.is_pending() {
+                T::waker().register(cx.waker());
+                g(self);
+            }
+            r
+        })
+        .await
+    }
+
+    /// Wait for the next command of the master.
+    ///
+    /// The bytes written by the master are stored in `buffer`. If it writes more bytes than fit, the
+    /// remaining ones are discarded and [`Error::PartialWrite`] or [`Error::PartialGeneralCall`] is
+    /// returned at the end of the transfer.
+    pub async fn listen(&mut self, buffer: &mut [u8]) -> Result<Command, Error> {
+        let p = T::regs();
+
+        let mut len = 0;
+        let mut general_call = false;
+        let mut overflow = false;
+
+        self.wait_on(
+            |_me| unsafe {
+                let stat = p.ic_raw_intr_stat().read();
+                if stat.gen_call() {
+                    p.ic_clr_gen_call().read();
+                    general_call = true;
+                }
+
+                while p.ic_status().read().rfne() {
+                    let byte = p.ic_data_cmd().read().dat();
+                    if len < buffer.len() {
+                        buffer[len] = byte;
+                        len += 1;
+                    } else {
+                        overflow = true;
+                    }
+                }
+
+                if stat.rd_req() {
+                    // The request is cleared by `respond_to_read`
+                    return match len {
+                        0 => Poll::Ready(Ok(Command::Read)),
+                        n => Poll::Ready(Ok(Command::WriteRead(n))),
+                    };
+                }
+
+                if stat.stop_det() {
+                    p.ic_clr_stop_det().read();
+
+                    let res = match (general_call, overflow) {
+                        (true, true) => Err(Error::PartialGeneralCall(len)),
+                        (true, false) => Ok(Command::GeneralCall(len)),
+                        (false, true) => Err(Error::PartialWrite(len)),
+                        (false, false) if len > 0 => Ok(Command::Write(len)),
+                        // Transfer without data, e.g. the end of a previous read
+                        (false, false) => return Poll::Pending,
+                    };
+                    return Poll::Ready(res);
+                }
+
+                Poll::Pending
+            },
+            |_me| unsafe {
+                p.ic_intr_mask().modify(|w| {
+                    w.set_m_rx_full(true);
+                    w.set_m_rd_req(true);
+                    w.set_m_stop_det(true);
+                    w.set_m_gen_call(true);
+                });
+            },
+        )
+        .await
+    }
+
+    /// Answer a read of the master with `buffer`.
+    ///
+    /// Call this after [`listen`](Self::listen) returned [`Command::Read`] or [`Command::WriteRead`].
+    pub async fn respond_to_read(&mut self, buffer: &[u8]) -> Result<ReadStatus, Error> {
+        if buffer.is_empty() {
+            return Err(Error::InvalidResponseBufferLength);
+        }
+
+        let p = T::regs();
+        let mut sent = 0;
+
+        self.wait_on(
+            |_me| unsafe {
+                let stat = p.ic_raw_intr_stat().read();
+
+                if stat.tx_abrt() {
+                    // The master ended the read early, the bytes left in the tx fifo are flushed
+                    let flushed = p.ic_tx_abrt_source().read().tx_flush_cnt() as usize;
+                    p.ic_clr_tx_abrt().read();
+                    return Poll::Ready(Ok(ReadStatus::LeftoverBytes((buffer.len() - sent + flushed) as u16)));
+                }
+
+                if stat.rd_req() {
+                    if sent == buffer.len() {
+                        // The request is left for the next call
+                        return Poll::Ready(Ok(ReadStatus::NeedMoreBytes));
+                    }
+
+                    let free = FIFO_SIZE - p.ic_txflr().read().txflr() as usize;
+                    for &byte in buffer[sent..].iter().take(free) {
+                        p.ic_data_cmd().write(|w| w.set_dat(byte));
+                        sent += 1;
+                    }
+                    p.ic_clr_rd_req().read();
+                }
+
+                if stat.stop_det() {
+                    p.ic_clr_stop_det().read();
+
+                    let in_fifo = p.ic_txflr().read().txflr() as usize;
+                    if in_fifo > 0 {
+                        // Flush the bytes not read by the master, so they are not sent on the next read
+                        p.ic_enable().write(|w| w.set_enable(false));
+                        while p.ic_enable_status().read().ic_en() {}
+                        p.ic_enable().write(|w| w.set_enable(true));
+                    }
+
+                    let leftover = buffer.len() - sent + in_fifo;
+                    return Poll::Ready(Ok(match leftover {
+                        0 => ReadStatus::Done,
+                        n => ReadStatus::LeftoverBytes(n as u16),
+                    }));
+                }
+
+                Poll::Pending
+            },
+            |_me| unsafe {
+                p.ic_intr_mask().modify(|w| {
+                    w.set_m_rd_req(true);
+                    w.set_m_tx_abrt(true);
+                    w.set_m_stop_det(true);
+                });
+            },
+        )
+        .await
+    }
+}
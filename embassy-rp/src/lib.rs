@@ -16,6 +16,7 @@ pub mod flash;
 mod float;
 pub mod gpio;
 pub mod i2c;
+pub mod i2c_slave;
 pub mod interrupt;
 pub mod multicore;
 pub mod pwm;
@@ -23,6 +24,7 @@ mod reset;
 pub mod rom_data;
 pub mod rtc;
 pub mod spi;
+pub mod spi_slave;
 #[cfg(feature = "time-driver")]
 pub mod timer;
 pub mod uart;
@@ -34,6 +36,8 @@ pub mod watchdog;
 // TODO: move `pio_instr_util` and `relocate` to inside `pio`
 pub mod pio;
 pub mod pio_instr_util;
+#[cfg(feature = "ws2812")]
+pub mod pio_ws2812;
 pub mod relocate;
 
 // Reexports
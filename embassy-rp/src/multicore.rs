@@ -27,18 +27,142 @@
 //!     executor0.run(|spawner| unwrap!(spawner.spawn(core0_task())));
 //! }
 //! ```
-
-use core::mem::ManuallyDrop;
-use core::sync::atomic::{compiler_fence, AtomicBool, Ordering};
-
-use crate::interrupt::{Interrupt, InterruptExt};
+//!
+//! # Interrupts
+//!
+//! Each core has its own NVIC: an interrupt is handled by the core(s) on which it is enabled, which for
+//! the drivers is the core that created them. To handle the interrupts of a peripheral on core1, create
+//! its driver from core1.
+//!
+//! The interrupt mode executor of `embassy-executor` pends its interrupt on the NVIC of the core waking
+//! one of its tasks, so it only works if all its tasks are woken from the core it runs on. Use
+//! [`InterruptExecutor`] instead to run an interrupt mode executor on either core, with tasks woken from
+//! both cores.
+
+use core::cell::UnsafeCell;
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use atomic_polyfill::{AtomicBool, AtomicU32};
+use cortex_m::interrupt::InterruptNumber;
+use cortex_m::peripheral::NVIC;
+use embassy_executor::raw::{self, Pender};
+use embassy_executor::SendSpawner;
+
+use crate::interrupt::{Binding, Interrupt, InterruptExt};
 use crate::peripherals::CORE1;
 use crate::{gpio, interrupt, pac};
 
 const PAUSE_TOKEN: u32 = 0xDEADBEEF;
 const RESUME_TOKEN: u32 = !0xDEADBEEF;
+/// Signals the other core that interrupts were added to its `PENDING_IRQS`
+const DOORBELL_TOKEN: u32 = 0x5E0D_0B11;
 static IS_CORE1_INIT: AtomicBool = AtomicBool::new(false);
 
+/// Handler of the SIO interrupt of core0, which pends the interrupts requested by core1 with
+/// [`pend_interrupt`]. Bind it to `SIO_IRQ_PROC0` and call [`enable_core0_doorbell`] to use it.
+pub struct SioInterruptHandler {
+    _empty: (),
+}
+
+impl interrupt::Handler<interrupt::SIO_IRQ_PROC0> for SioInterruptHandler {
+    unsafe fn on_interrupt() {
+        let sio = pac::SIO;
+        // Clear IRQ
+        sio.fifo().st().write(|w| w.set_wof(false));
+
+        // Only doorbells are sent by CORE1 outside of `spawn_core1`, `pause_core1` and `resume_core1`,
+        // which mask this interrupt
+        fifo_drain();
+
+        pend_requested_interrupts(CoreId::Core0);
+    }
+}
+
+/// Interrupts to pend on each core, requested from the other core
+static PENDING_IRQS: [AtomicU32; 2] = [AtomicU32::new(0), AtomicU32::new(0)];
+
+/// Core of the RP2040
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CoreId {
+    Core0 = 0,
+    Core1 = 1,
+}
+
+/// Core executing the caller
+pub fn current_core() -> CoreId {
+    match unsafe { pac::SIO.cpuid().read() } {
+        0 => CoreId::Core0,
+        _ => CoreId::Core1,
+    }
+}
+
+/// Pend `irq` on the NVIC of `core`.
+///
+/// When `core` is the other core, it is signaled through the inter-core FIFO, and pends the interrupt
+/// from its SIO interrupt. On core0, this requires [`enable_core0_doorbell`] to be called first.
+pub fn pend_interrupt(core: CoreId, irq: impl InterruptNumber) {
+    if core == current_core() {
+        NVIC::pend(irq);
+    } else {
+        let prev = PENDING_IRQS[core as usize].fetch_or(1 << irq.number(), Ordering::AcqRel);
+        // The other core has not handled the previous doorbell yet, it will pend this interrupt as well
+        if prev == 0 {
+            fifo_write(DOORBELL_TOKEN);
+        }
+    }
+}
+
+/// Let core1 pend interrupts on core0 with [`pend_interrupt`], e.g. to wake the tasks of an
+/// [`InterruptExecutor`] started on core0 from core1. Must be called from core0.
+pub fn enable_core0_doorbell(_irq: impl Binding<interrupt::SIO_IRQ_PROC0, SioInterruptHandler>) {
+    assert!(current_core() == CoreId::Core0);
+
+    let irq = unsafe { interrupt::SIO_IRQ_PROC0::steal() };
+    if !irq.is_enabled() {
+        irq.unpend();
+        irq.enable();
+    }
+}
+
+/// Pend the interrupts requested from the other core.
+#[inline(always)]
+fn pend_requested_interrupts(core: CoreId) {
+    let irqs = PENDING_IRQS[core as usize].swap(0, Ordering::AcqRel);
+    for n in 0..32 {
+        if irqs & (1 << n) != 0 {
+            NVIC::pend(IrqNumber(n));
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct IrqNumber(u16);
+
+unsafe impl InterruptNumber for IrqNumber {
+    fn number(self) -> u16 {
+        self.0
+    }
+}
+
+/// Run `f` with the SIO interrupt of core0 masked, so the words read from the FIFO by `f` are not
+/// consumed by the [`SioInterruptHandler`].
+fn with_fifo_irq_masked<R>(f: impl FnOnce() -> R) -> R {
+    let irq = unsafe { interrupt::SIO_IRQ_PROC0::steal() };
+    let enabled = current_core() == CoreId::Core0 && irq.is_enabled();
+    if enabled {
+        irq.disable();
+    }
+    let res = f();
+    if enabled {
+        // Doorbells may have been consumed by `f`
+        pend_requested_interrupts(CoreId::Core0);
+        irq.enable();
+    }
+    res
+}
+
 #[inline(always)]
 fn install_stack_guard(stack_bottom: *mut usize) {
     let core = unsafe { cortex_m::Peripherals::steal() };
@@ -97,18 +221,63 @@ unsafe fn SIO_IRQ_PROC1() {
     while sio.fifo().st().read().vld() {
         // Pause CORE1 execution and disable interrupts
         if fifo_read_wfe() == PAUSE_TOKEN {
-            cortex_m::interrupt::disable();
-            // Signal to CORE0 that execution is paused
-            fifo_write(PAUSE_TOKEN);
-            // Wait for `resume` signal from CORE0
-            while fifo_read_wfe() != RESUME_TOKEN {
-                cortex_m::asm::nop();
-            }
-            cortex_m::interrupt::enable();
-            // Signal to CORE0 that execution is resumed
-            fifo_write(RESUME_TOKEN);
+            park_core1();
         }
     }
+
+    pend_requested_interrupts(CoreId::Core1);
+}
+
+/// Signal CORE0 that CORE1 is paused, and wait with interrupts disabled until CORE0 resumes it.
+///
+/// CORE0 may disable XIP while CORE1 is parked, e.g. to erase flash, so this must not execute any
+/// code from flash. Everything is done in assembly, because the register accessors and the `cortex_m`
+/// helpers are not guaranteed to be inlined, e.g. in debug builds.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn park_core1() {
+    #[cfg(target_arch = "arm")]
+    core::arch::asm!(
+        "cpsid i",
+
+        // fifo_write(PAUSE_TOKEN)
+        "1:",
+        "ldr {tmp}, [{sio}, #0x50]", // FIFO_ST
+        "movs {mask}, #2", // RDY
+        "tst {tmp}, {mask}",
+        "beq 1b",
+        "str {pause}, [{sio}, #0x54]", // FIFO_WR
+        "sev",
+
+        // wait for RESUME_TOKEN
+        "2:",
+        "ldr {tmp}, [{sio}, #0x50]", // FIFO_ST
+        "movs {mask}, #1", // VLD
+        "tst {tmp}, {mask}",
+        "bne 3f",
+        "wfe",
+        "b 2b",
+        "3:",
+        "ldr {tmp}, [{sio}, #0x58]", // FIFO_RD
+        "cmp {tmp}, {resume}",
+        "bne 2b",
+
+        "cpsie i",
+
+        // fifo_write(RESUME_TOKEN)
+        "4:",
+        "ldr {tmp}, [{sio}, #0x50]", // FIFO_ST
+        "movs {mask}, #2", // RDY
+        "tst {tmp}, {mask}",
+        "beq 4b",
+        "str {resume}, [{sio}, #0x54]", // FIFO_WR
+        "sev",
+        sio = in(reg) 0xd000_0000u32,
+        pause = in(reg) PAUSE_TOKEN,
+        resume = in(reg) RESUME_TOKEN,
+        tmp = out(reg) _,
+        mask = out(reg) _,
+    );
 }
 
 /// Spawn a function on this core
@@ -200,51 +369,60 @@ where
         core1_startup::<F> as usize,
     ];
 
-    let mut seq = 0;
-    let mut fails = 0;
-    loop {
-        let cmd = cmd_seq[seq] as u32;
-        if cmd == 0 {
-            fifo_drain();
-            cortex_m::asm::sev();
-        }
-        fifo_write(cmd);
-
-        let response = fifo_read();
-        if cmd == response {
-            seq += 1;
-        } else {
-            seq = 0;
-            fails += 1;
-            if fails > 16 {
-                // The second core isn't responding, and isn't going to take the entrypoint
-                panic!("CORE1 not responding");
+    with_fifo_irq_masked(|| {
+        let mut seq = 0;
+        let mut fails = 0;
+        loop {
+            let cmd = cmd_seq[seq] as u32;
+            if cmd == 0 {
+                fifo_drain();
+                cortex_m::asm::sev();
+            }
+            fifo_write(cmd);
+
+            let response = fifo_read();
+            if cmd == response {
+                seq += 1;
+            } else {
+                seq = 0;
+                fails += 1;
+                if fails > 16 {
+                    // The second core isn't responding, and isn't going to take the entrypoint
+                    panic!("CORE1 not responding");
+                }
+            }
+            if seq >= cmd_seq.len() {
+                break;
             }
         }
-        if seq >= cmd_seq.len() {
-            break;
-        }
-    }
 
-    // Wait until the other core has copied `entry` before returning.
-    fifo_read();
+        // Wait until the other core has copied `entry` before returning.
+        fifo_read();
+    });
 }
 
 /// Pause execution on CORE1.
+///
+/// CORE1 waits in RAM with interrupts disabled until [`resume_core1`] is called, so CORE0 may disable
+/// XIP in the meantime, e.g. to write to flash.
 pub fn pause_core1() {
     if IS_CORE1_INIT.load(Ordering::Acquire) {
-        fifo_write(PAUSE_TOKEN);
-        // Wait for CORE1 to signal it has paused execution.
-        while fifo_read() != PAUSE_TOKEN {}
+        with_fifo_irq_masked(|| {
+            fifo_write(PAUSE_TOKEN);
+            // Wait for CORE1 to signal it has paused execution.
+            while fifo_read() != PAUSE_TOKEN {}
+        });
     }
 }
 
 /// Resume CORE1 execution.
 pub fn resume_core1() {
     if IS_CORE1_INIT.load(Ordering::Acquire) {
-        fifo_write(RESUME_TOKEN);
-        // Wait for CORE1 to signal it has resumed execution.
-        while fifo_read() != RESUME_TOKEN {}
+        with_fifo_irq_masked(|| {
+            fifo_write(RESUME_TOKEN);
+            // Wait for CORE1 to signal it has resumed execution.
+            while fifo_read() != RESUME_TOKEN {}
+        });
     }
 }
 
@@ -301,6 +479,82 @@ fn fifo_drain() {
     }
 }
 
+/// Interrupt mode executor, which can be woken from both cores.
+///
+/// This works like the `InterruptExecutor` of `embassy-executor`, but pends its interrupt on the core
+/// it was started on, also when a task is woken from the other core. It can run on either core, e.g. to
+/// run high priority tasks on core1 alongside a thread mode executor on each core.
+///
+/// Use one of the `SWI_IRQ_n` software interrupts, and call [`on_interrupt()`](Self::on_interrupt) from
+/// its handler. Waking a task of an executor running on core0 from core1 uses the SIO interrupt of
+/// core0, which has to be enabled with [`enable_core0_doorbell`].
+pub struct InterruptExecutor {
+    started: AtomicBool,
+    executor: UnsafeCell<MaybeUninit<raw::Executor>>,
+}
+
+unsafe impl Send for InterruptExecutor {}
+unsafe impl Sync for InterruptExecutor {}
+
+impl InterruptExecutor {
+    /// Create a new, not started `InterruptExecutor`.
+    pub const fn new() -> Self {
+        Self {
+            started: AtomicBool::new(false),
+            executor: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Executor interrupt callback.
+    ///
+    /// # Safety
+    ///
+    /// You MUST call this from the interrupt handler, and from nowhere else.
+    pub unsafe fn on_interrupt(&'static self) {
+        let executor = (*self.executor.get()).assume_init_ref();
+        executor.poll();
+    }
+
+    /// Start the executor on the current core.
+    ///
+    /// This initializes the executor and enables `irq` on the NVIC of the current core. The priority of
+    /// `irq` must be set before calling this method.
+    pub fn start(&'static self, irq: impl InterruptNumber) -> SendSpawner {
+        if self
+            .started
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            panic!("InterruptExecutor::start() called multiple times on the same executor.");
+        }
+
+        let core = current_core();
+        // The target of the pender is encoded in its context
+        let context = ((core as usize) << 16 | irq.number() as usize) as *mut ();
+        unsafe {
+            (*self.executor.get())
+                .as_mut_ptr()
+                .write(raw::Executor::new(Pender::new_from_callback(pend_executor, context)))
+        }
+
+        let executor = unsafe { (*self.executor.get()).assume_init_ref() };
+
+        unsafe { NVIC::unmask(irq) }
+
+        executor.spawner().make_send()
+    }
+}
+
+fn pend_executor(context: *mut ()) {
+    let context = context as usize;
+    let core = if context >> 16 == 0 {
+        CoreId::Core0
+    } else {
+        CoreId::Core1
+    };
+    pend_interrupt(core, IrqNumber(context as u16));
+}
+
 // https://github.com/nvzqz/bad-rs/blob/master/src/never.rs
 mod bad {
     pub(crate) type Never = <F as HasOutput>::Output;
@@ -0,0 +1,108 @@
+//! WS2812 (NeoPixel) LED strip driver, using a PIO state machine and DMA.
+
+use embassy_hal_common::{into_ref, Peripheral, PeripheralRef};
+use embassy_time::{Duration, Timer};
+use fixed::types::U24F8;
+pub use smart_leds::RGB8;
+
+use crate::clocks;
+use crate::dma::{AnyChannel, Channel};
+use crate::pio::{Common, Config, FifoJoin, Instance, PioPin, ShiftConfig, ShiftDirection, StateMachine};
+use crate::relocate::RelocatedProgram;
+
+const T1: u8 = 2; // start bit
+const T2: u8 = 5; // data bit
+const T3: u8 = 3; // stop bit
+const CYCLES_PER_BIT: u32 = (T1 + T2 + T3) as u32;
+
+/// Bit rate of the WS2812, in kHz
+const BIT_FREQ_KHZ: u32 = 800;
+
+/// Time the data line is held low after a frame, for the LEDs to latch their colors
+const LATCH_TIME: Duration = Duration::from_micros(60);
+
+/// Driver for a strip of up to `N` WS2812 LEDs.
+///
+/// The colors are sent by DMA, `write` only returns once the LEDs have latched them.
+pub struct PioWs2812<'d, P: Instance, const S: usize, const N: usize> {
+    dma: PeripheralRef<'d, AnyChannel>,
+    sm: StateMachine<'d, P, S>,
+}
+
+impl<'d, P: Instance, const S: usize, const N: usize> PioWs2812<'d, P, S, N> {
+    /// Load the WS2812 program into `pio` and run it on `sm`, driving `pin`.
+    pub fn new(
+        pio: &mut Common<'d, P>,
+        mut sm: StateMachine<'d, P, S>,
+        dma: impl Peripheral<P = impl Channel> + 'd,
+        pin: impl PioPin,
+    ) -> Self {
+        into_ref!(dma);
+
+        let side_set = pio::SideSet::new(false, 1, false);
+        let mut a: pio::Assembler<32> = pio::Assembler::new_with_side_set(side_set);
+
+        let mut wrap_target = a.label();
+        let mut wrap_source = a.label();
+        let mut do_zero = a.label();
+        a.set_with_side_set(pio::SetDestination::PINDIRS, 1, 0);
+        a.bind(&mut wrap_target);
+        // Do stop bit
+        a.out_with_delay_and_side_set(pio::OutDestination::X, 1, T3 - 1, 0);
+        // Do start bit
+        a.jmp_with_delay_and_side_set(pio::JmpCondition::XIsZero, &mut do_zero, T1 - 1, 1);
+        // Do data bit = 1
+        a.jmp_with_delay_and_side_set(pio::JmpCondition::Always, &mut wrap_target, T2 - 1, 1);
+        a.bind(&mut do_zero);
+        // Do data bit = 0
+        a.nop_with_delay_and_side_set(T2 - 1, 0);
+        a.bind(&mut wrap_source);
+
+        let prg = a.assemble_with_wrap(wrap_source, wrap_target);
+        let mut cfg = Config::default();
+
+        let out_pin = pio.make_pio_pin(pin);
+        cfg.set_out_pins(&[&out_pin]);
+        cfg.set_set_pins(&[&out_pin]);
+
+        let relocated = RelocatedProgram::new(&prg);
+        cfg.use_program(&pio.load_program(&relocated), &[&out_pin]);
+
+        // Clock config, measured in kHz to avoid overflows
+        let clock_freq = U24F8::from_num(clocks::clk_sys_freq() / 1000);
+        let bit_freq = U24F8::from_num(BIT_FREQ_KHZ * CYCLES_PER_BIT);
+        cfg.clock_divider = clock_freq / bit_freq;
+
+        cfg.fifo_join = FifoJoin::TxOnly;
+        cfg.shift_out = ShiftConfig {
+            auto_fill: true,
+            threshold: 24,
+            direction: ShiftDirection::Left,
+        };
+
+        sm.set_config(&cfg);
+        sm.set_enable(true);
+
+        Self {
+            dma: dma.map_into(),
+            sm,
+        }
+    }
+
+    /// Send the colors of the first `colors.len()` LEDs of the strip.
+    pub async fn write(&mut self, colors: &[RGB8]) {
+        assert!(colors.len() <= N);
+
+        // The LEDs expect the colors in GRB order, MSB first
+        let mut words = [0u32; N];
+        for (word, color) in words.iter_mut().zip(colors) {
+            *word = (u32::from(color.g) << 24) | (u32::from(color.r) << 16) | (u32::from(color.b) << 8);
+        }
+
+        self.sm.tx().dma_push(self.dma.reborrow(), &words[..colors.len()]).await;
+
+        // Wait for the last bits to be shifted out, then for the LEDs to latch
+        while !self.sm.tx().empty() {}
+        Timer::after(LATCH_TIME).await;
+    }
+}
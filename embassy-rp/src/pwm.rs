@@ -1,6 +1,7 @@
 //! Pulse Width Modulation (PWM)
 
 use embassy_hal_common::{into_ref, Peripheral, PeripheralRef};
+use embassy_time::{Duration, Instant, Timer};
 use fixed::traits::ToFixed;
 use fixed::FixedU16;
 use pac::pwm::regs::{ChDiv, Intr};
@@ -38,9 +39,13 @@ impl Default for Config {
     }
 }
 
+/// Source of the counter in input mode, on pin B
 pub enum InputMode {
+    /// Count at the PWM clock rate while pin B is high
     Level,
+    /// Count the rising edges of pin B
     RisingEdge,
+    /// Count the falling edges of pin B
     FallingEdge,
 }
 
@@ -219,6 +224,38 @@ impl<'d, T: Channel> Pwm<'d, T> {
         }
     }
 
+    /// Measure the frequency in Hz of the input on pin B, by counting its edges during `duration`.
+    ///
+    /// The PWM must have been created with [`InputMode::RisingEdge`] or [`InputMode::FallingEdge`], the
+    /// divider and top of its configuration are taken into account. The wraps of the counter are
+    /// checked every millisecond, so `top` must not be reached more than once per millisecond.
+    pub async fn measure_frequency(&mut self, duration: Duration) -> u32 {
+        let p = self.inner.regs();
+        let (top, div) = unsafe { (p.top().read().top() as u64 + 1, p.div().read().0 as u64) };
+
+        self.set_counter(0);
+        self.clear_wrapped();
+
+        let end = Instant::now() + duration;
+        let mut wraps = 0u64;
+        loop {
+            let now = Instant::now();
+            if now >= end {
+                break;
+            }
+            Timer::at(end.min(now + Duration::from_millis(1))).await;
+
+            if self.wrapped() {
+                self.clear_wrapped();
+                wraps += 1;
+            }
+        }
+
+        // The divider has 4 fractional bits
+        let edges = (wraps * top + self.counter() as u64) * div / 16;
+        (edges * 1_000_000 / duration.as_micros().max(1)) as u32
+    }
+
     #[inline]
     fn bit(&self) -> u32 {
         1 << self.inner.number() as usize
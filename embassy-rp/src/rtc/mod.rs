@@ -1,6 +1,11 @@
 mod filter;
 
+use core::future::poll_fn;
+use core::task::Poll;
+
+use embassy_cortex_m::interrupt::{Binding, Interrupt, InterruptExt};
 use embassy_hal_common::{into_ref, Peripheral, PeripheralRef};
+use embassy_sync::waitqueue::AtomicWaker;
 
 pub use self::filter::DateTimeFilter;
 
@@ -10,6 +15,9 @@ mod datetime;
 
 pub use self::datetime::{DateTime, DayOfWeek, Error as DateTimeError};
 use crate::clocks::clk_rtc_freq;
+use crate::interrupt::{self, RTC_IRQ};
+
+static WAKER: AtomicWaker = AtomicWaker::new();
 
 /// A reference to the real time clock of the system
 pub struct RealTimeClock<'d, T: Instance> {
@@ -22,12 +30,21 @@ impl<'d, T: Instance> RealTimeClock<'d, T> {
     /// # Errors
     ///
     /// Will return `RtcError::InvalidDateTime` if the datetime is not a valid range.
-    pub fn new(inner: impl Peripheral<P = T> + 'd, initial_date: DateTime) -> Result<Self, RtcError> {
+    pub fn new(
+        inner: impl Peripheral<P = T> + 'd,
+        _irq: impl Binding<RTC_IRQ, InterruptHandler>,
+        initial_date: DateTime,
+    ) -> Result<Self, RtcError> {
         into_ref!(inner);
 
         // Set the RTC divider
         unsafe { inner.regs().clkdiv_m1().write(|w| w.set_clkdiv_m1(clk_rtc_freq() - 1)) };
 
+        unsafe {
+            RTC_IRQ::steal().unpend();
+            RTC_IRQ::steal().enable();
+        }
+
         let mut result = Self { inner };
         result.set_leap_year_check(true); // should be on by default, make sure this is the case.
         result.set_datetime(initial_date)?;
@@ -150,13 +167,48 @@ impl<'d, T: Instance> RealTimeClock<'d, T> {
         }
     }
 
-    /// Clear the interrupt. This should be called every time the `RTC_IRQ` interrupt is triggered,
-    /// or the next [`schedule_alarm`] will never fire.
+    /// Clear the interrupt. This should be called every time the alarm fires, or the next
+    /// [`schedule_alarm`] will never fire.
     ///
     /// [`schedule_alarm`]: #method.schedule_alarm
     pub fn clear_interrupt(&mut self) {
         self.disable_alarm();
     }
+
+    /// Schedule an alarm with `filter` and wait until it fires.
+    ///
+    /// See [`schedule_alarm`] for the behavior of the filter. The alarm is cleared when this returns.
+    ///
+    /// [`schedule_alarm`]: #method.schedule_alarm
+    pub async fn wait_for_alarm(&mut self, filter: DateTimeFilter) {
+        self.schedule_alarm(filter);
+
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            // The interrupt handler disables the interrupt when the alarm fires
+            if unsafe { self.inner.regs().inte().read().rtc() } {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await;
+
+        self.clear_interrupt();
+    }
+}
+
+/// RTC interrupt handler, wakes the task waiting for the alarm.
+pub struct InterruptHandler {
+    _empty: (),
+}
+
+impl interrupt::Handler<RTC_IRQ> for InterruptHandler {
+    unsafe fn on_interrupt() {
+        let r = crate::pac::RTC;
+        r.inte().modify(|w| w.set_rtc(false));
+        WAKER.wake();
+    }
 }
 
 /// Errors that can occur on methods on [RealTimeClock]
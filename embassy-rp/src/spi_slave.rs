@@ -0,0 +1,167 @@
+//! Serial Peripheral Interface in slave mode
+//!
+//! The transfers are clocked by the master on the bus, [`SpiSlave::transfer`] exchanges data with it
+//! by DMA while it selects us.
+
+use embassy_futures::join::join;
+use embassy_hal_common::{into_ref, PeripheralRef};
+
+use crate::dma::{AnyChannel, Channel};
+use crate::spi::{ClkPin, CsPin, Error, Instance, MisoPin, MosiPin, Phase, Polarity};
+use crate::Peripheral;
+
+/// Frame format of the transfers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrameFormat {
+    /// Motorola SPI, with the clock polarity and phase of the [`Config`]
+    Motorola,
+    /// Texas Instruments synchronous serial, with a frame pulse before each frame instead of a chip select
+    TexasInstruments,
+}
+
+#[non_exhaustive]
+#[derive(Clone)]
+pub struct Config {
+    pub phase: Phase,
+    pub polarity: Polarity,
+    pub frame_format: FrameFormat,
+    /// Number of bits per frame, from 4 to 8
+    pub data_bits: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            phase: Phase::CaptureOnFirstTransition,
+            polarity: Polarity::IdleLow,
+            frame_format: FrameFormat::Motorola,
+            data_bits: 8,
+        }
+    }
+}
+
+/// SPI slave driver.
+///
+/// The SPI clock of the master must not exceed 1/12 of `clk_peri`. In Motorola mode with
+/// [`Phase::CaptureOnFirstTransition`], the master must deassert the chip select between each frame.
+pub struct SpiSlave<'d, T: Instance> {
+    inner: PeripheralRef<'d, T>,
+    tx_dma: PeripheralRef<'d, AnyChannel>,
+    rx_dma: PeripheralRef<'d, AnyChannel>,
+}
+
+impl<'d, T: Instance> SpiSlave<'d, T> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        inner: impl Peripheral<P = T> + 'd,
+        clk: impl Peripheral<P = impl ClkPin<T> + 'd> + 'd,
+        mosi: impl Peripheral<P = impl MosiPin<T> + 'd> + 'd,
+        miso: impl Peripheral<P = impl MisoPin<T> + 'd> + 'd,
+        cs: impl Peripheral<P = impl CsPin<T> + 'd> + 'd,
+        tx_dma: impl Peripheral<P = impl Channel> + 'd,
+        rx_dma: impl Peripheral<P = impl Channel> + 'd,
+        config: Config,
+    ) -> Self {
+        into_ref!(inner, clk, mosi, miso, cs, tx_dma, rx_dma);
+
+        assert!((4..=8).contains(&config.data_bits));
+
+        unsafe {
+            let p = inner.regs();
+
+            p.cr1().write(|w| w.set_sse(false));
+
+            // The prescaler is unused in slave mode, but must have a valid value
+            p.cpsr().write(|w| w.set_cpsdvsr(2));
+            p.cr0().write(|w| {
+                w.set_dss(config.data_bits - 1);
+                w.set_frf(match config.frame_format {
+                    FrameFormat::Motorola => 0,
+                    FrameFormat::TexasInstruments => 1,
+                });
+                w.set_spo(config.polarity == Polarity::IdleHigh);
+                w.set_sph(config.phase == Phase::CaptureOnSecondTransition);
+            });
+            p.dmacr().write(|w| {
+                w.set_rxdmae(true);
+                w.set_txdmae(true);
+            });
+            p.cr1().write(|w| {
+                w.set_ms(true); // slave
+                w.set_sse(true); // enable
+            });
+
+            clk.io().ctrl().write(|w| w.set_funcsel(1));
+            mosi.io().ctrl().write(|w| w.set_funcsel(1));
+            miso.io().ctrl().write(|w| w.set_funcsel(1));
+            cs.io().ctrl().write(|w| w.set_funcsel(1));
+        }
+
+        Self {
+            inner,
+            tx_dma: tx_dma.map_into(),
+            rx_dma: rx_dma.map_into(),
+        }
+    }
+
+    /// Exchange data with the master.
+    ///
+    /// `tx_buffer` is sent while `rx_buffer` is received, this returns once the master clocked the
+    /// longest of both. Zeros are sent after the end of `tx_buffer`, and the data received after the end
+    /// of `rx_buffer` is discarded. The data the master sent between two transfers is discarded too.
+    pub async fn transfer(&mut self, rx_buffer: &mut [u8], tx_buffer: &[u8]) -> Result<(), Error> {
+        let p = self.inner.regs();
+        let (rx_len, tx_len) = (rx_buffer.len(), tx_buffer.len());
+
+        unsafe {
+            // discard the frames the master clocked since the last transfer, which would be read first
+            while p.sr().read().rne() {
+                let _: u16 = p.dr().read().data();
+            }
+            // clear RX overrun interrupt
+            p.icr().write(|w| w.set_roric(true));
+        }
+
+        let tx_ch = &mut self.tx_dma;
+        // If we don't assign future to a variable, the data register pointer
+        // is held across an await and makes the future non-Send.
+        let tx_transfer = async {
+            unsafe {
+                if tx_len > 0 {
+                    crate::dma::write(&mut *tx_ch, tx_buffer, p.dr().ptr() as *mut _, T::TX_DREQ).await;
+                }
+                if rx_len > tx_len {
+                    // write dummy data
+                    crate::dma::write_repeated(&mut *tx_ch, p.dr().ptr() as *mut u8, rx_len - tx_len, T::TX_DREQ).await
+                }
+            }
+        };
+
+        let rx_ch = &mut self.rx_dma;
+        let rx_transfer = async {
+            unsafe {
+                if rx_len > 0 {
+                    crate::dma::read(&mut *rx_ch, p.dr().ptr() as *const _, rx_buffer, T::RX_DREQ).await;
+                }
+                if tx_len > rx_len {
+                    // discard the data received while the rest of `tx_buffer` is sent
+                    crate::dma::read_repeated(&mut *rx_ch, p.dr().ptr() as *const u8, tx_len - rx_len, T::RX_DREQ).await
+                }
+            }
+        };
+        join(tx_transfer, rx_transfer).await;
+
+        Ok(())
+    }
+
+    /// Receive data from the master, sending zeros.
+    pub async fn read(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        self.transfer(buffer, &[]).await
+    }
+
+    /// Send data to the master, discarding the received data.
+    pub async fn write(&mut self, buffer: &[u8]) -> Result<(), Error> {
+        self.transfer(&mut [], buffer).await
+    }
+}
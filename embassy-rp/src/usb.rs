@@ -79,6 +79,31 @@ impl<T: Instance> EndpointBuffer<T> {
     }
 }
 
+/// Offset of the second buffer of a double buffered isochronous endpoint, in bytes.
+fn iso_offset(len: u16) -> (u16, pac::usb_dpram::vals::EpBufferControlDoubleBufferIsoOffset) {
+    use pac::usb_dpram::vals::EpBufferControlDoubleBufferIsoOffset as Offset;
+    match len {
+        0..=128 => (128, Offset::_128),
+        129..=256 => (256, Offset::_256),
+        257..=512 => (512, Offset::_512),
+        _ => (1024, Offset::_1024),
+    }
+}
+
+/// Write the half of a buffer control register that controls buffer `n`, leaving the other
+/// buffer, which the controller may be using at the same time, untouched.
+unsafe fn write_buffer_control_half(
+    reg: pac::common::Reg<pac::usb_dpram::regs::EpBufferControl, pac::common::RW>,
+    n: usize,
+    f: impl FnOnce(&mut pac::usb_dpram::regs::EpBufferControl),
+) {
+    let mut val = pac::usb_dpram::regs::EpBufferControl(0);
+    f(&mut val);
+    (reg.ptr() as *mut u16)
+        .add(n)
+        .write_volatile((val.0 >> (n * 16)) as u16);
+}
+
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 struct EndpointData {
@@ -189,12 +214,17 @@ impl<'d, T: Instance> Driver<'d, T> {
         // to allocate smaller chunks to save memory.
         let len = (max_packet_size + 63) / 64 * 64;
 
+        // isochronous endpoints are double buffered, the second buffer starting at
+        // a fixed offset from the first one.
+        let double_buffered = ep_type == EndpointType::Isochronous;
+        let size = if double_buffered { iso_offset(len).0 + len } else { len };
+
         let addr = self.ep_mem_free;
-        if addr + len > EP_MEMORY_SIZE as u16 {
+        if addr + size > EP_MEMORY_SIZE as u16 {
             warn!("Endpoint memory full");
             return Err(EndpointAllocError);
         }
-        self.ep_mem_free += len;
+        self.ep_mem_free += size;
 
         let buf = EndpointBuffer {
             addr,
@@ -221,6 +251,7 @@ impl<'d, T: Instance> Driver<'d, T> {
                     w.set_enable(false);
                     w.set_buffer_address(addr);
                     w.set_interrupt_per_buff(true);
+                    w.set_double_buffered(double_buffered);
                     w.set_endpoint_type(ep_type_reg);
                 })
             },
@@ -229,6 +260,7 @@ impl<'d, T: Instance> Driver<'d, T> {
                     w.set_enable(false);
                     w.set_buffer_address(addr);
                     w.set_interrupt_per_buff(true);
+                    w.set_double_buffered(double_buffered);
                     w.set_endpoint_type(ep_type_reg);
                 })
             },
@@ -243,6 +275,7 @@ impl<'d, T: Instance> Driver<'d, T> {
                 interval_ms,
             },
             buf,
+            next_buf: 0,
         })
     }
 }
@@ -340,6 +373,7 @@ impl<'d, T: Instance> driver::Driver<'d> for Driver<'d, T> {
             Bus {
                 phantom: PhantomData,
                 inited: false,
+                ep_in: self.ep_in,
                 ep_out: self.ep_out,
             },
             ControlPipe {
@@ -352,6 +386,7 @@ impl<'d, T: Instance> driver::Driver<'d> for Driver<'d, T> {
 
 pub struct Bus<'d, T: Instance> {
     phantom: PhantomData<&'d mut T>,
+    ep_in: [EndpointData; EP_COUNT],
     ep_out: [EndpointData; EP_COUNT],
     inited: bool,
 }
@@ -430,11 +465,40 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
         match ep_addr.direction() {
             Direction::In => unsafe {
                 T::dpram().ep_in_control(n - 1).modify(|w| w.set_enable(enabled));
-                T::dpram().ep_in_buffer_control(ep_addr.index()).write(|w| {
-                    w.set_pid(0, true); // first packet is DATA0, but PID is flipped before
-                });
+                if self.ep_in[n].ep_type == EndpointType::Isochronous {
+                    // both buffers empty, isochronous packets are always DATA0
+                    let (_, offset) = iso_offset(self.ep_in[n].max_packet_size);
+                    T::dpram()
+                        .ep_in_buffer_control(ep_addr.index())
+                        .write(|w| w.set_double_buffer_iso_offset(offset));
+                } else {
+                    T::dpram().ep_in_buffer_control(ep_addr.index()).write(|w| {
+                        w.set_pid(0, true); // first packet is DATA0, but PID is flipped before
+                    });
+                }
                 EP_IN_WAKERS[n].wake();
             },
+            Direction::Out if self.ep_out[n].ep_type == EndpointType::Isochronous => unsafe {
+                T::dpram().ep_out_control(n - 1).modify(|w| w.set_enable(enabled));
+
+                // make both buffers available, so that a packet can be received in every frame
+                let max_packet_size = self.ep_out[n].max_packet_size;
+                let (_, offset) = iso_offset(max_packet_size);
+                T::dpram().ep_out_buffer_control(ep_addr.index()).write(|w| {
+                    w.set_length(0, max_packet_size);
+                    w.set_length(1, max_packet_size);
+                    w.set_double_buffer_iso_offset(offset);
+                });
+                cortex_m::asm::delay(12);
+                T::dpram().ep_out_buffer_control(ep_addr.index()).write(|w| {
+                    w.set_length(0, max_packet_size);
+                    w.set_length(1, max_packet_size);
+                    w.set_double_buffer_iso_offset(offset);
+                    w.set_available(0, true);
+                    w.set_available(1, true);
+                });
+                EP_OUT_WAKERS[n].wake();
+            },
             Direction::Out => unsafe {
                 T::dpram().ep_out_control(n - 1).modify(|w| w.set_enable(enabled));
 
@@ -458,7 +522,10 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
     async fn disable(&mut self) {}
 
     async fn remote_wakeup(&mut self) -> Result<(), Unsupported> {
-        Err(Unsupported)
+        // The SIE drives the resume signaling, the bit is cleared by hardware.
+        let regs = T::regs();
+        unsafe { regs.sie_ctrl().write_set(|w| w.set_resume(true)) };
+        Ok(())
     }
 }
 
@@ -495,6 +562,106 @@ pub struct Endpoint<'d, T: Instance, D> {
     _phantom: PhantomData<(&'d mut T, D)>,
     info: EndpointInfo,
     buf: EndpointBuffer<T>,
+    next_buf: usize, // buffer used by the next packet, for double buffered endpoints
+}
+
+impl<'d, T: Instance, D> Endpoint<'d, T, D> {
+    /// Number of the current frame, as sent by the host in the last start of frame packet.
+    ///
+    /// Isochronous endpoints transfer one packet per frame, so this can be used to keep track of
+    /// the packets that were missed.
+    pub fn frame_number(&self) -> u16 {
+        unsafe { T::regs().sof_rd().read().count() }
+    }
+
+    /// Buffer `n` of a double buffered endpoint
+    fn iso_buffer(&self, n: usize) -> EndpointBuffer<T> {
+        let (offset, _) = iso_offset(self.buf.len);
+        EndpointBuffer::new(self.buf.addr + n as u16 * offset, self.buf.len)
+    }
+}
+
+impl<'d, T: Instance> Endpoint<'d, T, Out> {
+    async fn read_iso(&mut self, buf: &mut [u8]) -> Result<usize, EndpointError> {
+        let index = self.info.addr.index();
+        let n = self.next_buf;
+        let val = poll_fn(|cx| unsafe {
+            EP_OUT_WAKERS[index].register(cx.waker());
+            let val = T::dpram().ep_out_buffer_control(index).read();
+            if val.available(n) {
+                Poll::Pending
+            } else {
+                Poll::Ready(val)
+            }
+        })
+        .await;
+        self.next_buf ^= 1;
+
+        // the packet is dropped if it doesn't fit, there is no retransmission.
+        let rx_len = val.length(n) as usize;
+        let res = if rx_len > buf.len() {
+            Err(EndpointError::BufferOverflow)
+        } else {
+            self.iso_buffer(n).read(&mut buf[..rx_len]);
+            Ok(rx_len)
+        };
+
+        let (_, offset) = iso_offset(self.info.max_packet_size);
+        unsafe {
+            let reg = T::dpram().ep_out_buffer_control(index);
+            write_buffer_control_half(reg, n, |w| {
+                w.set_length(n, self.info.max_packet_size);
+                w.set_double_buffer_iso_offset(offset);
+            });
+            cortex_m::asm::delay(12);
+            write_buffer_control_half(reg, n, |w| {
+                w.set_length(n, self.info.max_packet_size);
+                w.set_double_buffer_iso_offset(offset);
+                w.set_available(n, true);
+            });
+        }
+
+        res
+    }
+}
+
+impl<'d, T: Instance> Endpoint<'d, T, In> {
+    async fn write_iso(&mut self, buf: &[u8]) -> Result<(), EndpointError> {
+        let index = self.info.addr.index();
+        let n = self.next_buf;
+        poll_fn(|cx| unsafe {
+            EP_IN_WAKERS[index].register(cx.waker());
+            let val = T::dpram().ep_in_buffer_control(index).read();
+            if val.available(n) {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await;
+        self.next_buf ^= 1;
+
+        self.iso_buffer(n).write(buf);
+
+        let (_, offset) = iso_offset(self.info.max_packet_size);
+        unsafe {
+            let reg = T::dpram().ep_in_buffer_control(index);
+            write_buffer_control_half(reg, n, |w| {
+                w.set_length(n, buf.len() as _);
+                w.set_full(n, true);
+                w.set_double_buffer_iso_offset(offset);
+            });
+            cortex_m::asm::delay(12);
+            write_buffer_control_half(reg, n, |w| {
+                w.set_length(n, buf.len() as _);
+                w.set_full(n, true);
+                w.set_double_buffer_iso_offset(offset);
+                w.set_available(n, true);
+            });
+        }
+
+        Ok(())
+    }
 }
 
 impl<'d, T: Instance> driver::Endpoint for Endpoint<'d, T, In> {
@@ -515,6 +682,7 @@ impl<'d, T: Instance> driver::Endpoint for Endpoint<'d, T, In> {
             }
         })
         .await;
+        self.next_buf = 0;
         trace!("wait_enabled IN OK");
     }
 }
@@ -537,6 +705,7 @@ impl<'d, T: Instance> driver::Endpoint for Endpoint<'d, T, Out> {
             }
         })
         .await;
+        self.next_buf = 0;
         trace!("wait_enabled OUT OK");
     }
 }
@@ -544,6 +713,10 @@ impl<'d, T: Instance> driver::Endpoint for Endpoint<'d, T, Out> {
 impl<'d, T: Instance> driver::EndpointOut for Endpoint<'d, T, Out> {
     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, EndpointError> {
         trace!("READ WAITING, buf.len() = {}", buf.len());
+        if self.info.ep_type == EndpointType::Isochronous {
+            return self.read_iso(buf).await;
+        }
+
         let index = self.info.addr.index();
         let val = poll_fn(|cx| unsafe {
             EP_OUT_WAKERS[index].register(cx.waker());
@@ -590,6 +763,10 @@ impl<'d, T: Instance> driver::EndpointIn for Endpoint<'d, T, In> {
 
         trace!("WRITE WAITING");
 
+        if self.info.ep_type == EndpointType::Isochronous {
+            return self.write_iso(buf).await;
+        }
+
         let index = self.info.addr.index();
         let val = poll_fn(|cx| unsafe {
             EP_IN_WAKERS[index].register(cx.waker());
@@ -13,6 +13,16 @@ use embassy_time::Duration;
 use crate::pac;
 use crate::peripherals::WATCHDOG;
 
+/// The reason for a system reset from the watchdog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ResetReason {
+    /// The reset was forced by [`Watchdog::trigger_reset`].
+    Forced,
+    /// The watchdog was not fed in time.
+    TimedOut,
+}
+
 /// Watchdog peripheral
 pub struct Watchdog {
     phantom: PhantomData<WATCHDOG>,
@@ -107,6 +117,65 @@ impl Watchdog {
         self.enable(true);
     }
 
+    /// Stop the watchdog timer
+    pub fn stop(&mut self) {
+        self.enable(false);
+    }
+
+    /// Store data in a scratch register, which persists through a reset by the watchdog.
+    ///
+    /// There are 8 scratch registers, `index` must be in `0..8`. Note that the boot ROM uses scratch
+    /// registers 4 to 7 to select the code executed after a reset, they should only be used with care.
+    pub fn set_scratch(&mut self, index: usize, value: u32) {
+        unsafe {
+            let watchdog = pac::WATCHDOG;
+            match index {
+                0 => watchdog.scratch0().write_value(value),
+                1 => watchdog.scratch1().write_value(value),
+                2 => watchdog.scratch2().write_value(value),
+                3 => watchdog.scratch3().write_value(value),
+                4 => watchdog.scratch4().write_value(value),
+                5 => watchdog.scratch5().write_value(value),
+                6 => watchdog.scratch6().write_value(value),
+                7 => watchdog.scratch7().write_value(value),
+                _ => panic!("Invalid watchdog scratch index"),
+            }
+        }
+    }
+
+    /// Read data from a scratch register, `index` must be in `0..8`.
+    pub fn get_scratch(&self, index: usize) -> u32 {
+        unsafe {
+            let watchdog = pac::WATCHDOG;
+            match index {
+                0 => watchdog.scratch0().read(),
+                1 => watchdog.scratch1().read(),
+                2 => watchdog.scratch2().read(),
+                3 => watchdog.scratch3().read(),
+                4 => watchdog.scratch4().read(),
+                5 => watchdog.scratch5().read(),
+                6 => watchdog.scratch6().read(),
+                7 => watchdog.scratch7().read(),
+                _ => panic!("Invalid watchdog scratch index"),
+            }
+        }
+    }
+
+    /// Reason of the last reset, if it was caused by the watchdog.
+    ///
+    /// Returns `None` after a hardware reset, e.g. at power-up or from the RUN pin.
+    pub fn reset_reason(&self) -> Option<ResetReason> {
+        let reason = unsafe { pac::WATCHDOG.reason().read() };
+
+        if reason.force() {
+            Some(ResetReason::Forced)
+        } else if reason.timer() {
+            Some(ResetReason::TimedOut)
+        } else {
+            None
+        }
+    }
+
     /// Trigger a system reset
     pub fn trigger_reset(&mut self) {
         unsafe {
@@ -106,6 +106,31 @@ fn main() {
         Some("tim5") => "TIM5",
         Some("tim12") => "TIM12",
         Some("tim15") => "TIM15",
+        Some("rtc") => {
+            let version = METADATA
+                .peripherals
+                .iter()
+                .find(|p| p.name == "RTC")
+                .and_then(|p| p.registers.as_ref())
+                .map(|r| r.version);
+            match version {
+                None => panic!("time-driver-rtc requested, but the chip doesn't have an RTC."),
+                Some("v1") | Some("v2f2") => {
+                    panic!("time-driver-rtc requested, but the RTC of the chip doesn't have a subsecond counter.")
+                }
+                Some(_) => "RTC",
+            }
+        }
+        Some("lptim1") => {
+            let supported = METADATA
+                .peripherals
+                .iter()
+                .any(|p| p.name == "LPTIM1" && p.registers.is_some());
+            if !supported {
+                panic!("time-driver-lptim1 requested, but the chip doesn't have a supported LPTIM1.")
+            }
+            "LPTIM1"
+        }
         Some("any") => {
             if singletons.contains(&"TIM2".to_string()) {
                 "TIM2"
@@ -169,6 +194,34 @@ fn main() {
         }
     });
 
+    // ========
+    // Generate the interrupts of each peripheral, by signal name
+
+    let mut peripheral_interrupts = TokenStream::new();
+    for p in METADATA.peripherals {
+        let mut signals = HashSet::new();
+        let mut pt = TokenStream::new();
+        for irq in p.interrupts {
+            if !signals.insert(irq.signal) {
+                continue;
+            }
+            let signal = format_ident!("{}", irq.signal);
+            let irq = format_ident!("{}", irq.interrupt);
+            pt.extend(quote!(pub type #signal = crate::interrupt::#irq;));
+        }
+        if !pt.is_empty() {
+            let name = format_ident!("{}", p.name);
+            peripheral_interrupts.extend(quote!(pub mod #name { #pt }));
+        }
+    }
+
+    g.extend(quote! {
+        #[allow(non_camel_case_types, non_snake_case, unused)]
+        pub mod peripheral_interrupts {
+            #peripheral_interrupts
+        }
+    });
+
     // ========
     // Generate FLASH regions
     let mut flash_regions = TokenStream::new();
@@ -427,6 +480,14 @@ fn main() {
         (("spi", "I2S_MCK"), quote!(crate::spi::MckPin)),
         (("spi", "I2S_CK"), quote!(crate::spi::CkPin)),
         (("spi", "I2S_WS"), quote!(crate::spi::WsPin)),
+        (("sai", "SCK_A"), quote!(crate::sai::SckAPin)),
+        (("sai", "FS_A"), quote!(crate::sai::FsAPin)),
+        (("sai", "SD_A"), quote!(crate::sai::SdAPin)),
+        (("sai", "MCLK_A"), quote!(crate::sai::MclkAPin)),
+        (("sai", "SCK_B"), quote!(crate::sai::SckBPin)),
+        (("sai", "FS_B"), quote!(crate::sai::FsBPin)),
+        (("sai", "SD_B"), quote!(crate::sai::SdBPin)),
+        (("sai", "MCLK_B"), quote!(crate::sai::MclkBPin)),
         (("i2c", "SDA"), quote!(crate::i2c::SdaPin)),
         (("i2c", "SCL"), quote!(crate::i2c::SclPin)),
         (("rcc", "MCO_1"), quote!(crate::rcc::McoPin)),
@@ -599,14 +660,17 @@ fn main() {
         (("sdmmc", "D4"), quote!(crate::sdmmc::D4Pin)),
         (("sdmmc", "D5"), quote!(crate::sdmmc::D5Pin)),
         (("sdmmc", "D6"), quote!(crate::sdmmc::D6Pin)),
-        (("sdmmc", "D6"), quote!(crate::sdmmc::D7Pin)),
-        (("sdmmc", "D8"), quote!(crate::sdmmc::D8Pin)),
+        (("sdmmc", "D7"), quote!(crate::sdmmc::D7Pin)),
         (("quadspi", "BK1_IO0"), quote!(crate::qspi::D0Pin)),
         (("quadspi", "BK1_IO1"), quote!(crate::qspi::D1Pin)),
         (("quadspi", "BK1_IO2"), quote!(crate::qspi::D2Pin)),
         (("quadspi", "BK1_IO3"), quote!(crate::qspi::D3Pin)),
         (("quadspi", "CLK"), quote!(crate::qspi::SckPin)),
         (("quadspi", "BK1_NCS"), quote!(crate::qspi::NSSPin)),
+        (("lptim", "OUT"), quote!(crate::lptim::OutputPin)),
+        (("lptim", "IN1"), quote!(crate::lptim::Input1Pin)),
+        (("lptim", "IN2"), quote!(crate::lptim::Input2Pin)),
+        (("lptim", "ETR"), quote!(crate::lptim::ExternalTriggerPin)),
     ].into();
 
     for p in METADATA.peripherals {
@@ -654,7 +718,10 @@ fn main() {
                     let ch: Option<u8> = if pin.signal.starts_with("INP") {
                         Some(pin.signal.strip_prefix("INP").unwrap().parse().unwrap())
                     } else if pin.signal.starts_with("INN") {
-                        // TODO handle in the future when embassy supports differential measurements
+                        let ch: u8 = pin.signal.strip_prefix("INN").unwrap().parse().unwrap();
+                        g.extend(quote! {
+                            impl_adc_neg_pin!( #peri, #pin_name, #ch);
+                        });
                         None
                     } else if pin.signal.starts_with("IN") {
                         Some(pin.signal.strip_prefix("IN").unwrap().parse().unwrap())
@@ -664,7 +731,15 @@ fn main() {
                     if let Some(ch) = ch {
                         g.extend(quote! {
                             impl_adc_pin!( #peri, #pin_name, #ch);
-                        })
+                        });
+
+                        // On ADC v3, the negative input of a channel in differential mode is the input of the next channel
+                        if regs.version == "v3" && ch > 1 {
+                            let neg_ch = ch - 1;
+                            g.extend(quote! {
+                                impl_adc_neg_pin!( #peri, #pin_name, #neg_ch);
+                            });
+                        }
                     }
                 }
 
@@ -693,6 +768,8 @@ fn main() {
         (("lpuart", "TX"), quote!(crate::usart::TxDma)),
         (("spi", "RX"), quote!(crate::spi::RxDma)),
         (("spi", "TX"), quote!(crate::spi::TxDma)),
+        (("sai", "A"), quote!(crate::sai::DmaA)),
+        (("sai", "B"), quote!(crate::sai::DmaB)),
         (("i2c", "RX"), quote!(crate::i2c::RxDma)),
         (("i2c", "TX"), quote!(crate::i2c::TxDma)),
         (("dcmi", "DCMI"), quote!(crate::dcmi::FrameDma)),
@@ -700,6 +777,13 @@ fn main() {
         // SDMMCv1 uses the same channel for both directions, so just implement for RX
         (("sdmmc", "RX"), quote!(crate::sdmmc::SdmmcDma)),
         (("quadspi", "QUADSPI"), quote!(crate::qspi::QuadDma)),
+        (("adc", "ADC1"), quote!(crate::adc::RxDma)),
+        (("adc", "ADC2"), quote!(crate::adc::RxDma)),
+        (("adc", "ADC3"), quote!(crate::adc::RxDma)),
+        (("adc", "ADC4"), quote!(crate::adc::RxDma)),
+        (("dac", "CH1"), quote!(crate::dac::DmaCh1)),
+        (("dac", "CH2"), quote!(crate::dac::DmaCh2)),
+        (("timer", "UP"), quote!(crate::timer::UpDma)),
     ]
     .into();
 
@@ -0,0 +1,217 @@
+use super::{Temperature, VrefInt, VREF_CALIB_MV, VREF_DEFAULT_MV};
+
+/// Factory calibration point of the temperature sensor
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TemperatureCalibration {
+    /// Temperature at which the calibration was acquired, in °C
+    pub celsius: i32,
+    /// Raw conversion of the temperature sensor at the default resolution
+    pub raw: u16,
+}
+
+// Calibration data of each family, from the datasheets:
+// - `VREFINT_CAL`: address of the raw conversion of VrefInt at VDDA = `VREF_CALIB_MV`
+// - `VREFINT_TYP_MV`: typical voltage of VrefInt, for the families without `VREFINT_CAL`
+// - `TS_CAL1`, `TS_CAL2`: address and temperature of the raw conversions of the temperature sensor at
+//   VDDA = `TS_CALIB_MV`
+// - `TS_TYP`: typical voltage in mV at a temperature in °C and average slope in µV/°C of the temperature
+//   sensor, for the families without two calibration points
+cfg_if::cfg_if! {
+    if #[cfg(any(stm32f030, stm32f070))] {
+        const VREFINT_CAL: Option<usize> = Some(0x1FFF_F7BA);
+        const VREFINT_TYP_MV: u32 = 1230;
+        const TS_CAL1: Option<(usize, i32)> = Some((0x1FFF_F7B8, 30));
+        const TS_CAL2: Option<(usize, i32)> = None;
+        const TS_TYP: (i32, i32, i32) = (1430, 30, -4300);
+        const TS_CALIB_MV: u32 = 3300;
+    } else if #[cfg(stm32f0)] {
+        const VREFINT_CAL: Option<usize> = Some(0x1FFF_F7BA);
+        const VREFINT_TYP_MV: u32 = 1230;
+        const TS_CAL1: Option<(usize, i32)> = Some((0x1FFF_F7B8, 30));
+        const TS_CAL2: Option<(usize, i32)> = Some((0x1FFF_F7C2, 110));
+        const TS_TYP: (i32, i32, i32) = (1430, 30, -4300);
+        const TS_CALIB_MV: u32 = 3300;
+    } else if #[cfg(stm32f1)] {
+        const VREFINT_CAL: Option<usize> = None;
+        const VREFINT_TYP_MV: u32 = 1200;
+        const TS_CAL1: Option<(usize, i32)> = None;
+        const TS_CAL2: Option<(usize, i32)> = None;
+        const TS_TYP: (i32, i32, i32) = (1430, 25, -4300);
+        const TS_CALIB_MV: u32 = 3300;
+    } else if #[cfg(stm32f2)] {
+        const VREFINT_CAL: Option<usize> = None;
+        const VREFINT_TYP_MV: u32 = 1210;
+        const TS_CAL1: Option<(usize, i32)> = None;
+        const TS_CAL2: Option<(usize, i32)> = None;
+        const TS_TYP: (i32, i32, i32) = (760, 25, 2500);
+        const TS_CALIB_MV: u32 = 3300;
+    } else if #[cfg(stm32f4)] {
+        const VREFINT_CAL: Option<usize> = Some(0x1FFF_7A2A);
+        const VREFINT_TYP_MV: u32 = 1210;
+        const TS_CAL1: Option<(usize, i32)> = Some((0x1FFF_7A2C, 30));
+        const TS_CAL2: Option<(usize, i32)> = Some((0x1FFF_7A2E, 110));
+        const TS_TYP: (i32, i32, i32) = (760, 25, 2500);
+        const TS_CALIB_MV: u32 = 3300;
+    } else if #[cfg(any(stm32f72x, stm32f73x))] {
+        const VREFINT_CAL: Option<usize> = Some(0x1FF0_7A2A);
+        const VREFINT_TYP_MV: u32 = 1210;
+        const TS_CAL1: Option<(usize, i32)> = Some((0x1FF0_7A2C, 30));
+        const TS_CAL2: Option<(usize, i32)> = Some((0x1FF0_7A2E, 110));
+        const TS_TYP: (i32, i32, i32) = (760, 25, 2500);
+        const TS_CALIB_MV: u32 = 3300;
+    } else if #[cfg(stm32f7)] {
+        const VREFINT_CAL: Option<usize> = Some(0x1FF0_F44A);
+        const VREFINT_TYP_MV: u32 = 1210;
+        const TS_CAL1: Option<(usize, i32)> = Some((0x1FF0_F44C, 30));
+        const TS_CAL2: Option<(usize, i32)> = Some((0x1FF0_F44E, 110));
+        const TS_TYP: (i32, i32, i32) = (760, 25, 2500);
+        const TS_CALIB_MV: u32 = 3300;
+    } else if #[cfg(any(stm32l4, stm32wb))] {
+        const VREFINT_CAL: Option<usize> = Some(0x1FFF_75AA);
+        const VREFINT_TYP_MV: u32 = 1212;
+        const TS_CAL1: Option<(usize, i32)> = Some((0x1FFF_75A8, 30));
+        const TS_CAL2: Option<(usize, i32)> = Some((0x1FFF_75CA, 130));
+        const TS_TYP: (i32, i32, i32) = (760, 30, 2500);
+        const TS_CALIB_MV: u32 = 3000;
+    } else if #[cfg(stm32l5)] {
+        const VREFINT_CAL: Option<usize> = Some(0x0BFA_05AA);
+        const VREFINT_TYP_MV: u32 = 1212;
+        const TS_CAL1: Option<(usize, i32)> = Some((0x0BFA_05A8, 30));
+        const TS_CAL2: Option<(usize, i32)> = Some((0x0BFA_05CA, 110));
+        const TS_TYP: (i32, i32, i32) = (760, 30, 2500);
+        const TS_CALIB_MV: u32 = 3000;
+    } else if #[cfg(stm32g0)] {
+        const VREFINT_CAL: Option<usize> = Some(0x1FFF_75AA);
+        const VREFINT_TYP_MV: u32 = 1212;
+        const TS_CAL1: Option<(usize, i32)> = Some((0x1FFF_75A8, 30));
+        const TS_CAL2: Option<(usize, i32)> = None;
+        const TS_TYP: (i32, i32, i32) = (760, 30, 2530);
+        const TS_CALIB_MV: u32 = 3000;
+    } else if #[cfg(any(stm32h7a3, stm32h7b3, stm32h7b0))] {
+        const VREFINT_CAL: Option<usize> = Some(0x08FF_F810);
+        const VREFINT_TYP_MV: u32 = 1216;
+        const TS_CAL1: Option<(usize, i32)> = Some((0x08FF_F814, 30));
+        const TS_CAL2: Option<(usize, i32)> = Some((0x08FF_F818, 110));
+        const TS_TYP: (i32, i32, i32) = (620, 30, 2000);
+        const TS_CALIB_MV: u32 = 3300;
+    } else if #[cfg(stm32h7)] {
+        const VREFINT_CAL: Option<usize> = Some(0x1FF1_E860);
+        const VREFINT_TYP_MV: u32 = 1216;
+        const TS_CAL1: Option<(usize, i32)> = Some((0x1FF1_E820, 30));
+        const TS_CAL2: Option<(usize, i32)> = Some((0x1FF1_E840, 110));
+        const TS_TYP: (i32, i32, i32) = (620, 30, 2000);
+        const TS_CALIB_MV: u32 = 3300;
+    }
+}
+
+/// Full scale of the raw conversions at the default resolution, which is also the resolution of the
+/// factory calibration values.
+#[cfg(not(adc_v4))]
+const MAX_COUNT: u32 = (1 << 12) - 1;
+#[cfg(adc_v4)]
+const MAX_COUNT: u32 = (1 << 16) - 1;
+
+fn read_calibration(addr: usize) -> u16 {
+    unsafe { core::ptr::read_volatile(addr as *const u16) }
+}
+
+impl VrefInt {
+    /// Raw conversion of the internal voltage reference at the default resolution, with VDDA at
+    /// [`VREF_CALIB_MV`].
+    ///
+    /// This is the factory calibration value VREFINT_CAL. On chips without it, the value is computed
+    /// from the typical voltage of the reference.
+    pub fn calibration_value() -> u16 {
+        match VREFINT_CAL {
+            Some(addr) => read_calibration(addr),
+            None => (VREFINT_TYP_MV * MAX_COUNT / VREF_CALIB_MV) as u16,
+        }
+    }
+}
+
+impl Temperature {
+    /// First factory calibration point TS_CAL1, if the chip has one.
+    pub fn ts_cal1() -> Option<TemperatureCalibration> {
+        TS_CAL1.map(|(addr, celsius)| TemperatureCalibration {
+            celsius,
+            raw: read_calibration(addr),
+        })
+    }
+
+    /// Second factory calibration point TS_CAL2, if the chip has one.
+    pub fn ts_cal2() -> Option<TemperatureCalibration> {
+        TS_CAL2.map(|(addr, celsius)| TemperatureCalibration {
+            celsius,
+            raw: read_calibration(addr),
+        })
+    }
+
+    /// Convert a raw conversion of the temperature sensor at the default resolution, with VDDA at
+    /// [`VREF_DEFAULT_MV`], to °C.
+    ///
+    /// Uses the factory calibration points of the chip, or the typical characteristics of the sensor
+    /// where they are missing.
+    pub fn convert_to_celsius(raw: u16) -> f32 {
+        // Scale the conversion to the VDDA of the calibration
+        let raw = raw as f32 * VREF_DEFAULT_MV as f32 / TS_CALIB_MV as f32;
+
+        match (Self::ts_cal1(), Self::ts_cal2()) {
+            (Some(cal1), Some(cal2)) => two_point(raw, cal1, cal2),
+            (Some(cal1), None) => {
+                let (_, _, slope_uv) = TS_TYP;
+                one_point(raw, cal1, slope_uv)
+            }
+            _ => {
+                let (mv, celsius, slope_uv) = TS_TYP;
+                typical(raw, mv, celsius, slope_uv)
+            }
+        }
+    }
+}
+
+fn to_millivolts(raw: f32) -> f32 {
+    raw * TS_CALIB_MV as f32 / MAX_COUNT as f32
+}
+
+fn two_point(raw: f32, cal1: TemperatureCalibration, cal2: TemperatureCalibration) -> f32 {
+    (raw - cal1.raw as f32) * (cal2.celsius - cal1.celsius) as f32 / (cal2.raw as f32 - cal1.raw as f32)
+        + cal1.celsius as f32
+}
+
+fn one_point(raw: f32, cal1: TemperatureCalibration, slope_uv: i32) -> f32 {
+    (to_millivolts(raw) - to_millivolts(cal1.raw as f32)) * 1000.0 / slope_uv as f32 + cal1.celsius as f32
+}
+
+fn typical(raw: f32, mv: i32, celsius: i32, slope_uv: i32) -> f32 {
+    (to_millivolts(raw) - mv as f32) * 1000.0 / slope_uv as f32 + celsius as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cal(celsius: i32, raw: u16) -> TemperatureCalibration {
+        TemperatureCalibration { celsius, raw }
+    }
+
+    #[test]
+    fn can_interpolate_between_calibration_points() {
+        let (cal1, cal2) = (cal(30, 1000), cal(130, 1400));
+
+        assert_eq!(30.0, two_point(1000.0, cal1, cal2));
+        assert_eq!(130.0, two_point(1400.0, cal1, cal2));
+        assert_eq!(80.0, two_point(1200.0, cal1, cal2));
+        assert_eq!(5.0, two_point(900.0, cal1, cal2));
+    }
+
+    #[test]
+    fn can_use_slope_from_calibration_point() {
+        let cal1 = cal(30, 1000);
+        let raw_per_degree = 2.0 * MAX_COUNT as f32 / TS_CALIB_MV as f32;
+
+        assert_eq!(30.0, one_point(1000.0, cal1, 2000));
+        assert!((one_point(1000.0 + 10.0 * raw_per_degree, cal1, 2000) - 40.0).abs() < 0.01);
+        assert!((one_point(1000.0 + 10.0 * raw_per_degree, cal1, -2000) - 20.0).abs() < 0.01);
+    }
+}
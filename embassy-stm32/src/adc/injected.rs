@@ -0,0 +1,143 @@
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use embassy_cortex_m::interrupt::{Interrupt, InterruptExt};
+
+use super::{Adc, AdcPin, Instance};
+use crate::interrupt;
+
+/// Number of channels of the injected group
+pub const INJECTED_MAX_LEN: usize = 4;
+
+/// ADC interrupt handler, signals the end of the conversions of the injected group.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        // The interrupt is shared between several ADCs on some chips, only the enabled ones are handled.
+        if Adc::<T>::injected_interrupt() {
+            T::state().waker.wake();
+        }
+    }
+}
+
+/// Edge of the external trigger starting the conversions
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TriggerEdge {
+    Rising,
+    Falling,
+    Both,
+}
+
+/// Trigger of the conversions of the injected group
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InjectedTrigger {
+    /// The conversions are started by [`Injected::start`].
+    Software,
+    /// The conversions are started by a hardware event, e.g. the TRGO of a timer.
+    ///
+    /// `source` is the value of the JEXTSEL field selecting the event, as listed in the reference manual
+    /// of the chip.
+    External { source: u8, edge: TriggerEdge },
+}
+
+/// Channel of the injected group
+pub struct InjectedChannel<T: Instance> {
+    pub(super) channel: u8,
+    pub(super) offset: u16,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> InjectedChannel<T> {
+    /// Converts `pin`, with `offset` subtracted from the results. The results can then be negative.
+    ///
+    /// Except on F2, F4 and F7, the offset is also subtracted from the regular conversions of the channel
+    /// while the injected group is configured.
+    pub fn new(pin: &mut impl AdcPin<T>, offset: u16) -> Self {
+        pin.set_as_analog();
+
+        Self {
+            channel: pin.channel(),
+            offset,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Injected group of up to 4 channels, converted in sequence on a trigger.
+///
+/// The conversions of the injected group are configured independently of the regular conversions, to
+/// sample e.g. the currents of a motor synchronized to the PWM of a timer.
+pub struct Injected<'a, 'd, T: Instance> {
+    adc: &'a mut Adc<'d, T>,
+    trigger: InjectedTrigger,
+    len: usize,
+}
+
+impl<'d, T: Instance> Adc<'d, T> {
+    /// Configure the injected group to convert `channels` in sequence on `trigger`.
+    ///
+    /// The conversions are not started before [`Injected::start`] is called.
+    pub fn injected<'a>(
+        &'a mut self,
+        _irq: impl interrupt::Binding<T::Interrupt, InterruptHandler<T>> + 'a,
+        channels: &[InjectedChannel<T>],
+        trigger: InjectedTrigger,
+    ) -> Injected<'a, 'd, T> {
+        assert!(!channels.is_empty() && channels.len() <= INJECTED_MAX_LEN);
+
+        unsafe {
+            self.configure_injected(channels, trigger);
+
+            T::Interrupt::steal().unpend();
+            T::Interrupt::steal().enable();
+        }
+
+        Injected {
+            adc: self,
+            trigger,
+            len: channels.len(),
+        }
+    }
+}
+
+impl<'a, 'd, T: Instance> Injected<'a, 'd, T> {
+    /// Start the conversions of the group for [`InjectedTrigger::Software`], or enable the trigger for
+    /// [`InjectedTrigger::External`].
+    pub fn start(&mut self) {
+        unsafe { self.adc.start_injected(self.trigger) };
+    }
+
+    /// Wait for the end of the conversions of the group, and copy their results into `buf`.
+    ///
+    /// The results are in the order of the channels of the group, with their offset subtracted. Results of
+    /// conversions completed before the call are returned immediately.
+    pub async fn read(&mut self, buf: &mut [i32]) {
+        poll_fn(|cx| {
+            T::state().waker.register(cx.waker());
+
+            if unsafe { self.adc.injected_complete() } {
+                Poll::Ready(())
+            } else {
+                unsafe { self.adc.enable_injected_interrupt() };
+                Poll::Pending
+            }
+        })
+        .await;
+
+        for (rank, res) in buf.iter_mut().take(self.len).enumerate() {
+            *res = unsafe { self.adc.injected_data(rank) };
+        }
+    }
+}
+
+impl<'a, 'd, T: Instance> Drop for Injected<'a, 'd, T> {
+    fn drop(&mut self) {
+        unsafe { self.adc.stop_injected() };
+    }
+}
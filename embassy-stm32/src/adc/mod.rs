@@ -7,16 +7,31 @@
 #[cfg_attr(adc_v4, path = "v4.rs")]
 mod _version;
 
+mod calibration;
+#[cfg(any(adc_v2, adc_v3, adc_v4))]
+mod injected;
+#[cfg(any(adc_v3, adc_g0, adc_v4))]
+mod oversampling;
 #[cfg(not(adc_f1))]
 mod resolution;
+#[cfg(all(any(adc_v2, adc_v3, adc_v4), not(gpdma)))]
+mod ringbuffered;
 mod sample_time;
 
 #[allow(unused)]
 pub use _version::*;
+pub use calibration::TemperatureCalibration;
+#[cfg(any(adc_v2, adc_v3, adc_v4))]
+pub use injected::*;
+#[cfg(any(adc_v3, adc_g0, adc_v4))]
+pub use oversampling::OversamplingRatio;
 #[cfg(not(adc_f1))]
 pub use resolution::Resolution;
+#[cfg(all(any(adc_v2, adc_v3, adc_v4), not(gpdma)))]
+pub use ringbuffered::{AdcStream, Error};
 pub use sample_time::SampleTime;
 
+use crate::interrupt::Interrupt;
 use crate::peripherals;
 
 pub struct Adc<'d, T: Instance> {
@@ -26,13 +41,43 @@ pub struct Adc<'d, T: Instance> {
 }
 
 pub(crate) mod sealed {
+    #[cfg(any(adc_v2, adc_v3, adc_v4))]
+    use embassy_sync::waitqueue::AtomicWaker;
+
+    #[cfg(any(adc_v2, adc_v3, adc_v4))]
+    pub struct State {
+        pub waker: AtomicWaker,
+    }
+
+    #[cfg(any(adc_v2, adc_v3, adc_v4))]
+    impl State {
+        pub const fn new() -> Self {
+            Self {
+                waker: AtomicWaker::new(),
+            }
+        }
+    }
+
     pub trait Instance {
         fn regs() -> crate::pac::adc::Adc;
         #[cfg(all(not(adc_f1), not(adc_v1)))]
         fn common_regs() -> crate::pac::adccommon::AdcCommon;
+        #[cfg(any(adc_v2, adc_v3, adc_v4))]
+        fn state() -> &'static State;
     }
 
     pub trait AdcPin<T: Instance> {
+        /// Set the pin to analog mode, for pins that are GPIOs
+        fn set_as_analog(&mut self) {}
+
+        fn channel(&self) -> u8;
+    }
+
+    #[cfg(any(adc_v3, adc_v4))]
+    pub trait AdcNegPin<T: Instance> {
+        fn set_as_analog(&mut self);
+
+        /// Channel of which the pin is the negative input
         fn channel(&self) -> u8;
     }
 
@@ -42,13 +87,22 @@ pub(crate) mod sealed {
 }
 
 #[cfg(not(any(adc_f1, adc_v1, adc_v2, adc_v4)))]
-pub trait Instance: sealed::Instance + crate::Peripheral<P = Self> {}
+pub trait Instance: sealed::Instance + crate::Peripheral<P = Self> {
+    type Interrupt: Interrupt;
+}
 #[cfg(any(adc_f1, adc_v1, adc_v2, adc_v4))]
-pub trait Instance: sealed::Instance + crate::Peripheral<P = Self> + crate::rcc::RccPeripheral {}
+pub trait Instance: sealed::Instance + crate::Peripheral<P = Self> + crate::rcc::RccPeripheral {
+    type Interrupt: Interrupt;
+}
 
 pub trait AdcPin<T: Instance>: sealed::AdcPin<T> {}
+/// Pin used as the negative input of a channel converted in differential mode
+#[cfg(any(adc_v3, adc_v4))]
+pub trait AdcNegPin<T: Instance>: sealed::AdcNegPin<T> {}
 pub trait InternalChannel<T>: sealed::InternalChannel<T> {}
 
+dma_trait!(RxDma, Instance);
+
 #[cfg(not(stm32h7))]
 foreach_peripheral!(
     (adc, $inst:ident) => {
@@ -64,9 +118,12 @@ foreach_peripheral!(
                     };
                 }
             }
+            #[cfg(any(adc_v2, adc_v3, adc_v4))]
+            fn state() -> &'static crate::adc::sealed::State {
+                static STATE: crate::adc::sealed::State = crate::adc::sealed::State::new();
+                &STATE
+            }
         }
-
-        impl crate::adc::Instance for peripherals::$inst {}
     };
 );
 
@@ -85,9 +142,12 @@ foreach_peripheral!(
                     };
                 }
             }
+            #[cfg(any(adc_v2, adc_v3, adc_v4))]
+            fn state() -> &'static crate::adc::sealed::State {
+                static STATE: crate::adc::sealed::State = crate::adc::sealed::State::new();
+                &STATE
+            }
         }
-
-        impl crate::adc::Instance for peripherals::ADC3 {}
     };
     (adc, $inst:ident) => {
         impl crate::adc::sealed::Instance for peripherals::$inst {
@@ -102,9 +162,20 @@ foreach_peripheral!(
                     };
                 }
             }
+            #[cfg(any(adc_v2, adc_v3, adc_v4))]
+            fn state() -> &'static crate::adc::sealed::State {
+                static STATE: crate::adc::sealed::State = crate::adc::sealed::State::new();
+                &STATE
+            }
         }
+    };
+);
 
-        impl crate::adc::Instance for peripherals::$inst {}
+foreach_interrupt!(
+    ($inst:ident, adc, $block:ident, GLOBAL, $irq:ident) => {
+        impl crate::adc::Instance for peripherals::$inst {
+            type Interrupt = crate::interrupt::$irq;
+        }
     };
 );
 
@@ -113,6 +184,27 @@ macro_rules! impl_adc_pin {
         impl crate::adc::AdcPin<peripherals::$inst> for crate::peripherals::$pin {}
 
         impl crate::adc::sealed::AdcPin<peripherals::$inst> for crate::peripherals::$pin {
+            fn set_as_analog(&mut self) {
+                unsafe { <Self as crate::gpio::sealed::Pin>::set_as_analog(self) };
+            }
+
+            fn channel(&self) -> u8 {
+                $ch
+            }
+        }
+    };
+}
+
+#[cfg(any(adc_v3, adc_v4))]
+macro_rules! impl_adc_neg_pin {
+    ($inst:ident, $pin:ident, $ch:expr) => {
+        impl crate::adc::AdcNegPin<peripherals::$inst> for crate::peripherals::$pin {}
+
+        impl crate::adc::sealed::AdcNegPin<peripherals::$inst> for crate::peripherals::$pin {
+            fn set_as_analog(&mut self) {
+                unsafe { <Self as crate::gpio::sealed::Pin>::set_as_analog(self) };
+            }
+
             fn channel(&self) -> u8 {
                 $ch
             }
@@ -0,0 +1,31 @@
+/// Number of conversions accumulated into one result by the hardware oversampler
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OversamplingRatio {
+    Mul2,
+    Mul4,
+    Mul8,
+    Mul16,
+    Mul32,
+    Mul64,
+    Mul128,
+    Mul256,
+    #[cfg(adc_v4)]
+    Mul512,
+    #[cfg(adc_v4)]
+    Mul1024,
+}
+
+impl OversamplingRatio {
+    /// Value of the OVSR field, the ratio is `2^(OVSR + 1)`
+    #[cfg(any(adc_v3, adc_g0))]
+    pub(crate) fn ovsr(&self) -> u8 {
+        *self as u8
+    }
+
+    /// Value of the OSVR field, the ratio is `OSVR + 1`
+    #[cfg(adc_v4)]
+    pub(crate) fn osvr(&self) -> u16 {
+        (2 << *self as u16) - 1
+    }
+}
@@ -0,0 +1,130 @@
+use core::future::poll_fn;
+use core::sync::atomic::{compiler_fence, Ordering};
+use core::task::Poll;
+
+use embassy_hal_common::into_ref;
+
+use super::{Adc, Instance, RxDma};
+use crate::dma::ringbuffer::OverrunError;
+use crate::dma::RingBuffer;
+use crate::Peripheral;
+
+/// ADC error
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// Samples were overwritten by the DMA before they were read
+    Overrun,
+}
+
+/// Continuous conversions of a single channel, written by DMA to a circular buffer.
+///
+/// Created by `Adc::read_continuous`. The conversions run in the background until the stream is
+/// dropped, the samples have to be read faster than the DMA buffer fills up.
+pub struct AdcStream<'a, 'd, T: Instance, D: RxDma<T>> {
+    adc: &'a mut Adc<'d, T>,
+    channel: u8,
+    ring_buf: RingBuffer<'a, D, u16>,
+    running: bool,
+}
+
+impl<'a, 'd, T: Instance, D: RxDma<T>> AdcStream<'a, 'd, T, D> {
+    pub(super) fn new(
+        adc: &'a mut Adc<'d, T>,
+        channel: u8,
+        dma: impl Peripheral<P = D> + 'a,
+        dma_buf: &'a mut [u16],
+    ) -> Self {
+        assert!(!dma_buf.is_empty() && dma_buf.len() <= 0xFFFF);
+
+        into_ref!(dma);
+        let request = dma.request();
+        let opts = Default::default();
+        let ring_buf = unsafe { RingBuffer::new_read(dma, request, T::regs().dr().ptr() as *mut u16, dma_buf, opts) };
+
+        let mut this = Self {
+            adc,
+            channel,
+            ring_buf,
+            running: false,
+        };
+        this.start();
+        this
+    }
+
+    /// Start the DMA and the conversions
+    fn start(&mut self) {
+        // Clear the ring buffer so that it is ready to receive data
+        self.ring_buf.clear();
+
+        // fence before starting DMA.
+        compiler_fence(Ordering::SeqCst);
+
+        self.ring_buf.start();
+        unsafe { self.adc.start_continuous(self.channel) };
+        self.running = true;
+    }
+
+    /// Stop the conversions and the DMA
+    fn stop(&mut self) {
+        unsafe { self.adc.stop_continuous() };
+
+        compiler_fence(Ordering::SeqCst);
+
+        self.ring_buf.request_stop();
+        while self.ring_buf.is_running() {}
+        self.running = false;
+    }
+
+    /// Wait until `buf` is filled with samples.
+    ///
+    /// The DMA signals when each half of its buffer is filled, so with a `buf` of half the length
+    /// of the DMA buffer, this returns the half-buffers as they are completed.
+    ///
+    /// The conversions are stopped if an error is returned, they are started again by the next call.
+    pub async fn read(&mut self, buf: &mut [u16]) -> Result<(), Error> {
+        if !self.running {
+            self.start();
+        }
+
+        let mut n = 0;
+        while n < buf.len() {
+            let res = poll_fn(|cx| {
+                self.ring_buf.set_waker(cx.waker());
+
+                compiler_fence(Ordering::SeqCst);
+
+                self.ring_buf.reload_position();
+                match self.ring_buf.read(&mut buf[n..]) {
+                    Ok(0) => Poll::Pending,
+                    Ok(len) => Poll::Ready(Ok(len)),
+                    Err(OverrunError) => Poll::Ready(Err(Error::Overrun)),
+                }
+            })
+            .await;
+
+            match res {
+                Ok(len) => n += len,
+                Err(e) => {
+                    self.stop();
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Length of the DMA buffer
+    pub fn capacity(&self) -> usize {
+        self.ring_buf.capacity()
+    }
+}
+
+impl<'a, 'd, T: Instance, D: RxDma<T>> Drop for AdcStream<'a, 'd, T, D> {
+    fn drop(&mut self) {
+        if self.running {
+            self.stop();
+        }
+    }
+}
@@ -7,6 +7,10 @@ use crate::Peripheral;
 
 pub const VDDA_CALIB_MV: u32 = 3300;
 pub const VREF_INT: u32 = 1230;
+/// Default VREF voltage used for sample conversion to millivolts.
+pub const VREF_DEFAULT_MV: u32 = 3300;
+/// VREF voltage used for factory calibration of VREFINTCAL register.
+pub const VREF_CALIB_MV: u32 = 3300;
 
 pub struct Vbat;
 impl InternalChannel<ADC> for Vbat {}
@@ -16,9 +20,9 @@ impl super::sealed::InternalChannel<ADC> for Vbat {
     }
 }
 
-pub struct Vref;
-impl InternalChannel<ADC> for Vref {}
-impl super::sealed::InternalChannel<ADC> for Vref {
+pub struct VrefInt;
+impl InternalChannel<ADC> for VrefInt {}
+impl super::sealed::InternalChannel<ADC> for VrefInt {
     fn channel(&self) -> u8 {
         17
     }
@@ -63,14 +67,14 @@ impl<'d, T: Instance> Adc<'d, T> {
         Vbat
     }
 
-    pub fn enable_vref(&self, delay: &mut impl DelayUs<u32>) -> Vref {
+    pub fn enable_vrefint(&self, delay: &mut impl DelayUs<u32>) -> VrefInt {
         // Table 28. Embedded internal reference voltage
         // tstart = 10μs
         unsafe {
             T::regs().ccr().modify(|reg| reg.set_vrefen(true));
         }
         delay.delay_us(10);
-        Vref
+        VrefInt
     }
 
     pub fn enable_temperature(&self, delay: &mut impl DelayUs<u32>) -> Temperature {
@@ -119,7 +123,7 @@ impl<'d, T: Instance> Adc<'d, T> {
     {
         let channel = pin.channel();
         unsafe {
-            pin.set_as_analog();
+            crate::gpio::sealed::Pin::set_as_analog(pin);
             self.read_channel(channel)
         }
     }
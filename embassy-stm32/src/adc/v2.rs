@@ -2,7 +2,11 @@ use embassy_hal_common::into_ref;
 use embedded_hal_02::blocking::delay::DelayUs;
 
 use super::InternalChannel;
-use crate::adc::{Adc, AdcPin, Instance, Resolution, SampleTime};
+use crate::adc::{
+    Adc, AdcPin, InjectedChannel, InjectedTrigger, Instance, Resolution, SampleTime, TriggerEdge, INJECTED_MAX_LEN,
+};
+#[cfg(not(gpdma))]
+use crate::adc::{AdcStream, RxDma};
 use crate::peripherals::ADC1;
 use crate::time::Hertz;
 use crate::Peripheral;
@@ -195,7 +199,7 @@ where
         P: crate::gpio::sealed::Pin,
     {
         unsafe {
-            pin.set_as_analog();
+            crate::gpio::sealed::Pin::set_as_analog(pin);
 
             self.read_channel(pin.channel())
         }
@@ -219,6 +223,145 @@ where
         val
     }
 
+    /// Start continuous conversions of `pin`, written by DMA to the circular buffer `dma_buf`.
+    ///
+    /// The conversions run until the returned [`AdcStream`] is dropped.
+    #[cfg(not(gpdma))]
+    pub fn read_continuous<'a, P, D>(
+        &'a mut self,
+        pin: &mut P,
+        dma: impl Peripheral<P = D> + 'a,
+        dma_buf: &'a mut [u16],
+    ) -> AdcStream<'a, 'd, T, D>
+    where
+        P: AdcPin<T>,
+        D: RxDma<T>,
+    {
+        pin.set_as_analog();
+
+        AdcStream::new(self, pin.channel(), dma, dma_buf)
+    }
+
+    #[cfg(not(gpdma))]
+    pub(super) unsafe fn start_continuous(&mut self, channel: u8) {
+        // Select channel
+        T::regs().sqr3().write(|reg| reg.set_sq(0, channel));
+
+        // Configure channel
+        Self::set_channel_sample_time(channel, self.sample_time);
+
+        T::regs().sr().modify(|reg| {
+            reg.set_ovr(crate::pac::adc::vals::Ovr::NOOVERRUN);
+        });
+
+        // Continuous conversions, with a DMA request for each of them
+        T::regs().cr2().modify(|reg| {
+            reg.set_cont(crate::pac::adc::vals::Cont::CONTINUOUS);
+            reg.set_dma(crate::pac::adc::vals::Dma::ENABLED);
+            reg.set_dds(crate::pac::adc::vals::Dds::CONTINUOUS);
+        });
+
+        // Start conversion
+        T::regs().cr2().modify(|reg| {
+            reg.set_swstart(true);
+        });
+    }
+
+    #[cfg(not(gpdma))]
+    pub(super) unsafe fn stop_continuous(&mut self) {
+        T::regs().cr2().modify(|reg| {
+            reg.set_cont(crate::pac::adc::vals::Cont::SINGLE);
+            reg.set_dma(crate::pac::adc::vals::Dma::DISABLED);
+            reg.set_dds(crate::pac::adc::vals::Dds::SINGLE);
+        });
+    }
+
+    pub(super) unsafe fn configure_injected(&mut self, channels: &[InjectedChannel<T>], trigger: InjectedTrigger) {
+        let len = channels.len();
+        T::regs().jsqr().write(|w| {
+            w.set_jl((len - 1) as u8);
+            // With less than 4 channels, the sequence ends with JSQ4
+            for (rank, ch) in channels.iter().enumerate() {
+                w.set_jsq(INJECTED_MAX_LEN - len + rank, ch.channel);
+            }
+        });
+
+        for (rank, ch) in channels.iter().enumerate() {
+            Self::set_channel_sample_time(ch.channel, self.sample_time);
+            T::regs().jofr(rank).write(|w| w.set_joffset(ch.offset));
+        }
+
+        T::regs()
+            .cr1()
+            .modify(|w| w.set_jeocie(crate::pac::adc::vals::Jeocie::DISABLED));
+        T::regs().cr2().modify(|w| {
+            // the trigger is enabled when the conversions are started
+            w.set_jexten(crate::pac::adc::vals::Jexten::DISABLED);
+            if let InjectedTrigger::External { source, .. } = trigger {
+                w.set_jextsel(crate::pac::adc::vals::Jextsel(source));
+            }
+        });
+        T::regs()
+            .sr()
+            .modify(|w| w.set_jeoc(crate::pac::adc::vals::Jeoc::NOTCOMPLETE));
+    }
+
+    pub(super) unsafe fn start_injected(&mut self, trigger: InjectedTrigger) {
+        T::regs().cr2().modify(|w| match trigger {
+            InjectedTrigger::Software => w.set_jswstart(true),
+            InjectedTrigger::External { edge, .. } => w.set_jexten(match edge {
+                TriggerEdge::Rising => crate::pac::adc::vals::Jexten::RISINGEDGE,
+                TriggerEdge::Falling => crate::pac::adc::vals::Jexten::FALLINGEDGE,
+                TriggerEdge::Both => crate::pac::adc::vals::Jexten::BOTHEDGES,
+            }),
+        });
+    }
+
+    pub(super) unsafe fn stop_injected(&mut self) {
+        T::regs()
+            .cr2()
+            .modify(|w| w.set_jexten(crate::pac::adc::vals::Jexten::DISABLED));
+        T::regs()
+            .cr1()
+            .modify(|w| w.set_jeocie(crate::pac::adc::vals::Jeocie::DISABLED));
+    }
+
+    pub(super) unsafe fn injected_complete(&mut self) -> bool {
+        if T::regs().sr().read().jeoc() == crate::pac::adc::vals::Jeoc::COMPLETE {
+            T::regs()
+                .sr()
+                .modify(|w| w.set_jeoc(crate::pac::adc::vals::Jeoc::NOTCOMPLETE));
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(super) unsafe fn enable_injected_interrupt(&mut self) {
+        T::regs()
+            .cr1()
+            .modify(|w| w.set_jeocie(crate::pac::adc::vals::Jeocie::ENABLED));
+    }
+
+    /// Disable the end of injected conversion interrupt if it is pending, returns whether it was
+    pub(super) unsafe fn injected_interrupt() -> bool {
+        let r = T::regs();
+        if r.cr1().read().jeocie() == crate::pac::adc::vals::Jeocie::ENABLED
+            && r.sr().read().jeoc() == crate::pac::adc::vals::Jeoc::COMPLETE
+        {
+            r.cr1()
+                .modify(|w| w.set_jeocie(crate::pac::adc::vals::Jeocie::DISABLED));
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(super) unsafe fn injected_data(&mut self, rank: usize) -> i32 {
+        // sign extended if an offset is subtracted
+        T::regs().jdr(rank).read().0 as u16 as i16 as i32
+    }
+
     unsafe fn set_channel_sample_time(ch: u8, sample_time: SampleTime) {
         let sample_time = sample_time.into();
         if ch <= 9 {
@@ -1,13 +1,23 @@
 use embassy_hal_common::into_ref;
 use embedded_hal_02::blocking::delay::DelayUs;
 
-use crate::adc::{Adc, AdcPin, Instance, Resolution, SampleTime};
+#[cfg(not(adc_g0))]
+use crate::adc::AdcNegPin;
+use crate::adc::{Adc, AdcPin, Instance, OversamplingRatio, Resolution, SampleTime};
+#[cfg(all(not(adc_g0), not(gpdma)))]
+use crate::adc::{AdcStream, RxDma};
+#[cfg(not(adc_g0))]
+use crate::adc::{InjectedChannel, InjectedTrigger, TriggerEdge, INJECTED_MAX_LEN};
 use crate::Peripheral;
 
 /// Default VREF voltage used for sample conversion to millivolts.
 pub const VREF_DEFAULT_MV: u32 = 3300;
 /// VREF voltage used for factory calibration of VREFINTCAL register.
+#[cfg(not(stm32wb))]
 pub const VREF_CALIB_MV: u32 = 3000;
+/// VREF voltage used for factory calibration of VREFINTCAL register.
+#[cfg(stm32wb)]
+pub const VREF_CALIB_MV: u32 = 3600;
 
 /// Sadly we cannot use `RccPeripheral::enable` since devices are quite inconsistent ADC clock
 /// configuration.
@@ -85,6 +95,21 @@ impl<'d, T: Instance> Adc<'d, T> {
             while T::regs().cr().read().adcal() {
                 // spin
             }
+
+            // The differential inputs have their own calibration factor
+            #[cfg(not(adc_g0))]
+            {
+                T::regs().cr().modify(|reg| {
+                    reg.set_adcaldif(true);
+                    reg.set_adcal(true);
+                });
+
+                while T::regs().cr().read().adcal() {
+                    // spin
+                }
+
+                T::regs().cr().modify(|reg| reg.set_adcaldif(false));
+            }
         }
 
         delay.delay_us(1);
@@ -144,14 +169,37 @@ impl<'d, T: Instance> Adc<'d, T> {
         }
     }
 
-    /*
-    /// Convert a raw sample from the `Temperature` to deg C
-    pub fn to_degrees_centigrade(sample: u16) -> f32 {
-        (130.0 - 30.0) / (VtempCal130::get().read() as f32 - VtempCal30::get().read() as f32)
-            * (sample as f32 - VtempCal30::get().read() as f32)
-            + 30.0
+    /// Enable the hardware oversampling of the regular conversions: `ratio` conversions are accumulated
+    /// and the sum is shifted right by `shift` bits, at most 8.
+    ///
+    /// The results can have more bits than the resolution, e.g. `Mul16` with a shift of 2 gives 14 bit
+    /// results from 12 bit conversions. They must fit in 16 bits.
+    pub fn set_oversampling(&mut self, ratio: OversamplingRatio, shift: u8) {
+        assert!(shift <= 8);
+
+        unsafe {
+            T::regs().cfgr2().modify(|reg| {
+                #[cfg(not(adc_g0))]
+                reg.set_rovse(true);
+                #[cfg(adc_g0)]
+                reg.set_ovse(true);
+                reg.set_ovsr(ratio.ovsr());
+                reg.set_ovss(shift);
+            });
+        }
+    }
+
+    /// Disable the hardware oversampling, each result is a single conversion.
+    pub fn disable_oversampling(&mut self) {
+        unsafe {
+            T::regs().cfgr2().modify(|reg| {
+                #[cfg(not(adc_g0))]
+                reg.set_rovse(false);
+                #[cfg(adc_g0)]
+                reg.set_ovse(false);
+            });
+        }
     }
-     */
 
     /// Perform a single conversion.
     fn convert(&mut self) -> u16 {
@@ -174,51 +222,247 @@ impl<'d, T: Instance> Adc<'d, T> {
         }
     }
 
+    unsafe fn enable(&mut self) {
+        // Make sure bits are off
+        while T::regs().cr().read().addis() {
+            // spin
+        }
+
+        // Enable ADC
+        T::regs().isr().modify(|reg| {
+            reg.set_adrdy(true);
+        });
+        T::regs().cr().modify(|reg| {
+            reg.set_aden(true);
+        });
+
+        while !T::regs().isr().read().adrdy() {
+            // spin
+        }
+    }
+
     pub fn read(&mut self, pin: &mut impl AdcPin<T>) -> u16 {
+        unsafe { self.read_channel(pin.channel()) }
+    }
+
+    /// Perform a single conversion of the voltage between `pin` and `neg_pin`, the negative input of the
+    /// channel of `pin`.
+    ///
+    /// The result is offset by half the full scale: it is 0 for `-VREF+`, mid scale for 0 V and full scale
+    /// for `VREF+`.
+    #[cfg(not(adc_g0))]
+    pub fn read_differential(&mut self, pin: &mut impl AdcPin<T>, neg_pin: &mut impl AdcNegPin<T>) -> u16 {
+        assert_eq!(pin.channel(), neg_pin.channel());
+
+        pin.set_as_analog();
+        neg_pin.set_as_analog();
+
         unsafe {
-            // Make sure bits are off
-            while T::regs().cr().read().addis() {
-                // spin
+            Self::set_differential(pin.channel(), true);
+            let val = self.read_channel(pin.channel());
+            Self::set_differential(pin.channel(), false);
+
+            val
+        }
+    }
+
+    /// Select the differential mode of `channel`, the ADC must not be enabled.
+    #[cfg(not(adc_g0))]
+    unsafe fn set_differential(channel: u8, differential: bool) {
+        // Wait for a previous disable to complete
+        while T::regs().cr().read().aden() {
+            // spin
+        }
+
+        T::regs().difsel().modify(|reg| match channel {
+            1..=15 => {
+                let bit = 1 << (channel - 1);
+                let bits = reg.difsel_1_15();
+                reg.set_difsel_1_15(if differential { bits | bit } else { bits & !bit });
+            }
+            16..=18 => {
+                let bit = 1 << (channel - 16);
+                let bits = reg.difsel_16_18();
+                reg.set_difsel_16_18(if differential { bits | bit } else { bits & !bit });
             }
+            _ => panic!("channel {} has no differential mode", channel),
+        });
+    }
 
-            // Enable ADC
-            T::regs().isr().modify(|reg| {
-                reg.set_adrdy(true);
-            });
-            T::regs().cr().modify(|reg| {
-                reg.set_aden(true);
+    unsafe fn read_channel(&mut self, channel: u8) -> u16 {
+        self.enable();
+
+        // Configure channel
+        Self::set_channel_sample_time(channel, self.sample_time);
+
+        // Select channel
+        #[cfg(not(stm32g0))]
+        T::regs().sqr1().write(|reg| reg.set_sq(0, channel));
+        #[cfg(stm32g0)]
+        T::regs().chselr().write(|reg| reg.set_chsel(1 << channel));
+
+        // Some models are affected by an erratum:
+        // If we perform conversions slower than 1 kHz, the first read ADC value can be
+        // corrupted, so we discard it and measure again.
+        //
+        // STM32L471xx: Section 2.7.3
+        // STM32G4: Section 2.7.3
+        #[cfg(any(rcc_l4, rcc_g4))]
+        let _ = self.convert();
+
+        let val = self.convert();
+
+        T::regs().cr().modify(|reg| reg.set_addis(true));
+
+        val
+    }
+
+    /// Start continuous conversions of `pin`, written by DMA to the circular buffer `dma_buf`.
+    ///
+    /// The conversions run until the returned [`AdcStream`] is dropped.
+    #[cfg(all(not(adc_g0), not(gpdma)))]
+    pub fn read_continuous<'a, D: RxDma<T>>(
+        &'a mut self,
+        pin: &mut impl AdcPin<T>,
+        dma: impl Peripheral<P = D> + 'a,
+        dma_buf: &'a mut [u16],
+    ) -> AdcStream<'a, 'd, T, D> {
+        pin.set_as_analog();
+
+        AdcStream::new(self, pin.channel(), dma, dma_buf)
+    }
+
+    #[cfg(all(not(adc_g0), not(gpdma)))]
+    pub(super) unsafe fn start_continuous(&mut self, channel: u8) {
+        self.enable();
+
+        // Configure channel
+        Self::set_channel_sample_time(channel, self.sample_time);
+
+        // Select channel
+        T::regs().sqr1().write(|reg| reg.set_sq(0, channel));
+
+        T::regs().isr().modify(|reg| {
+            reg.set_ovr(true);
+        });
+
+        // Continuous conversions, with the DMA in circular mode
+        T::regs().cfgr().modify(|reg| {
+            reg.set_cont(true);
+            reg.set_dmaen(true);
+            reg.set_dmacfg(true);
+        });
+
+        // Start conversion
+        T::regs().cr().modify(|reg| {
+            reg.set_adstart(true);
+        });
+    }
+
+    #[cfg(all(not(adc_g0), not(gpdma)))]
+    pub(super) unsafe fn stop_continuous(&mut self) {
+        T::regs().cr().modify(|reg| reg.set_adstp(true));
+        while T::regs().cr().read().adstart() {
+            // spin
+        }
+
+        T::regs().cfgr().modify(|reg| {
+            reg.set_cont(false);
+            reg.set_dmaen(false);
+            reg.set_dmacfg(false);
+        });
+
+        T::regs().cr().modify(|reg| reg.set_addis(true));
+    }
+
+    #[cfg(not(adc_g0))]
+    pub(super) unsafe fn configure_injected(&mut self, channels: &[InjectedChannel<T>], trigger: InjectedTrigger) {
+        self.enable();
+
+        T::regs().jsqr().write(|w| {
+            w.set_jl((channels.len() - 1) as u8);
+            for (rank, ch) in channels.iter().enumerate() {
+                w.set_jsq(rank, ch.channel);
+            }
+            if let InjectedTrigger::External { source, edge } = trigger {
+                w.set_jextsel(source);
+                w.set_jexten(match edge {
+                    TriggerEdge::Rising => 1,
+                    TriggerEdge::Falling => 2,
+                    TriggerEdge::Both => 3,
+                });
+            }
+        });
+
+        for (rank, ch) in channels.iter().enumerate() {
+            Self::set_channel_sample_time(ch.channel, self.sample_time);
+
+            // The offset registers are not tied to the injected ranks, they are matched by channel.
+            T::regs().ofr(rank).write(|w| {
+                w.set_offset1_en(ch.offset != 0);
+                w.set_offset1_ch(ch.channel);
+                w.set_offset(0, ch.offset);
             });
+        }
+
+        T::regs().ier().modify(|w| w.set_jeosie(false));
+        T::regs().isr().write(|w| w.set_jeos(true));
+    }
 
-            while !T::regs().isr().read().adrdy() {
+    #[cfg(not(adc_g0))]
+    pub(super) unsafe fn start_injected(&mut self, _trigger: InjectedTrigger) {
+        T::regs().cr().modify(|w| w.set_jadstart(true));
+    }
+
+    #[cfg(not(adc_g0))]
+    pub(super) unsafe fn stop_injected(&mut self) {
+        if T::regs().cr().read().jadstart() {
+            T::regs().cr().modify(|w| w.set_jadstp(true));
+            while T::regs().cr().read().jadstart() {
                 // spin
             }
+        }
 
-            // Configure channel
-            Self::set_channel_sample_time(pin.channel(), self.sample_time);
-
-            // Select channel
-            #[cfg(not(stm32g0))]
-            T::regs().sqr1().write(|reg| reg.set_sq(0, pin.channel()));
-            #[cfg(stm32g0)]
-            T::regs().chselr().write(|reg| reg.set_chsel(1 << pin.channel()));
+        T::regs().ier().modify(|w| w.set_jeosie(false));
+        for n in 0..INJECTED_MAX_LEN {
+            T::regs().ofr(n).write(|_| {});
+        }
 
-            // Some models are affected by an erratum:
-            // If we perform conversions slower than 1 kHz, the first read ADC value can be
-            // corrupted, so we discard it and measure again.
-            //
-            // STM32L471xx: Section 2.7.3
-            // STM32G4: Section 2.7.3
-            #[cfg(any(rcc_l4, rcc_g4))]
-            let _ = self.convert();
+        T::regs().cr().modify(|reg| reg.set_addis(true));
+    }
 
-            let val = self.convert();
+    #[cfg(not(adc_g0))]
+    pub(super) unsafe fn injected_complete(&mut self) -> bool {
+        if T::regs().isr().read().jeos() {
+            T::regs().isr().write(|w| w.set_jeos(true));
+            true
+        } else {
+            false
+        }
+    }
 
-            T::regs().cr().modify(|reg| reg.set_addis(true));
+    #[cfg(not(adc_g0))]
+    pub(super) unsafe fn enable_injected_interrupt(&mut self) {
+        T::regs().ier().modify(|w| w.set_jeosie(true));
+    }
 
-            val
+    /// Disable the end of injected sequence interrupt if it is pending, returns whether it was
+    #[cfg(not(adc_g0))]
+    pub(super) unsafe fn injected_interrupt() -> bool {
+        if T::regs().ier().read().jeosie() && T::regs().isr().read().jeos() {
+            T::regs().ier().modify(|w| w.set_jeosie(false));
+            true
+        } else {
+            false
         }
     }
 
+    #[cfg(not(adc_g0))]
+    pub(super) unsafe fn injected_data(&mut self, rank: usize) -> i32 {
+        // sign extended if an offset is subtracted
+        T::regs().jdr(rank).read().0 as u16 as i16 as i32
+    }
+
     #[cfg(stm32g0)]
     unsafe fn set_channel_sample_time(_ch: u8, sample_time: SampleTime) {
         T::regs().smpr().modify(|reg| reg.set_smp1(sample_time.into()));
@@ -1,10 +1,15 @@
 use core::sync::atomic::{AtomicU8, Ordering};
 
 use embedded_hal_02::blocking::delay::DelayUs;
-use pac::adc::vals::{Adcaldif, Boost, Difsel, Exten, Pcsel};
+use pac::adc::vals::{Adcaldif, Adstp, Boost, Difsel, Dmngt, Exten, Jexten, Jextsel, Pcsel};
 use pac::adccommon::vals::Presc;
 
-use super::{Adc, AdcPin, Instance, InternalChannel, Resolution, SampleTime};
+use super::{
+    Adc, AdcNegPin, AdcPin, InjectedChannel, InjectedTrigger, Instance, InternalChannel, OversamplingRatio, Resolution,
+    SampleTime, TriggerEdge, INJECTED_MAX_LEN,
+};
+#[cfg(not(gpdma))]
+use super::{AdcStream, RxDma};
 use crate::time::Hertz;
 use crate::{pac, Peripheral};
 
@@ -302,6 +307,23 @@ impl<'d, T: Instance> Adc<'d, T> {
             T::regs().cr().modify(|w| w.set_adcal(true));
 
             while T::regs().cr().read().adcal() {}
+
+            // The differential inputs have their own offset calibration factor
+            T::regs().cr().modify(|w| {
+                w.set_adcaldif(Adcaldif::DIFFERENTIAL);
+                w.set_adcallin(false);
+            });
+
+            T::regs().cr().modify(|w| w.set_adcal(true));
+
+            while T::regs().cr().read().adcal() {}
+        }
+    }
+
+    fn disable(&mut self) {
+        unsafe {
+            T::regs().cr().modify(|w| w.set_addis(true));
+            while T::regs().cr().read().aden() {}
         }
     }
 
@@ -364,6 +386,29 @@ impl<'d, T: Instance> Adc<'d, T> {
         }
     }
 
+    /// Enable the hardware oversampling of the regular conversions: `ratio` conversions are accumulated
+    /// and the sum is shifted right by `shift` bits, at most 11.
+    ///
+    /// The results must fit in 16 bits, e.g. `Mul16` with a shift of 4 for 16 bit conversions.
+    pub fn set_oversampling(&mut self, ratio: OversamplingRatio, shift: u8) {
+        assert!(shift <= 11);
+
+        unsafe {
+            T::regs().cfgr2().modify(|reg| {
+                reg.set_rovse(true);
+                reg.set_osvr(ratio.osvr());
+                reg.set_ovss(shift);
+            });
+        }
+    }
+
+    /// Disable the hardware oversampling, each result is a single conversion.
+    pub fn disable_oversampling(&mut self) {
+        unsafe {
+            T::regs().cfgr2().modify(|reg| reg.set_rovse(false));
+        }
+    }
+
     /// Perform a single conversion.
     fn convert(&mut self) -> u16 {
         unsafe {
@@ -391,12 +436,45 @@ impl<'d, T: Instance> Adc<'d, T> {
         P: crate::gpio::sealed::Pin,
     {
         unsafe {
-            pin.set_as_analog();
+            crate::gpio::sealed::Pin::set_as_analog(pin);
 
             self.read_channel(pin.channel())
         }
     }
 
+    /// Perform a single conversion of the voltage between `pin` and `neg_pin`, the negative input of the
+    /// channel of `pin`.
+    ///
+    /// The result is offset by half the full scale: it is 0 for `-VREF+`, mid scale for 0 V and full scale
+    /// for `VREF+`.
+    pub fn read_differential<P>(&mut self, pin: &mut P, neg_pin: &mut impl AdcNegPin<T>) -> u16
+    where
+        P: AdcPin<T>,
+        P: crate::gpio::sealed::Pin,
+    {
+        assert_eq!(pin.channel(), neg_pin.channel());
+
+        unsafe {
+            crate::gpio::sealed::Pin::set_as_analog(pin);
+            neg_pin.set_as_analog();
+
+            self.set_differential(pin.channel(), Difsel::DIFFERENTIAL);
+            let val = self.read_channel(pin.channel());
+            self.set_differential(pin.channel(), Difsel::SINGLEENDED);
+
+            val
+        }
+    }
+
+    /// The ADC has to be disabled to select the mode of a channel
+    fn set_differential(&mut self, channel: u8, difsel: Difsel) {
+        self.disable();
+        unsafe {
+            T::regs().difsel().modify(|w| w.set_difsel(channel as _, difsel));
+        }
+        self.enable();
+    }
+
     pub fn read_internal(&mut self, channel: &mut impl InternalChannel<T>) -> u16 {
         unsafe { self.read_channel(channel.channel()) }
     }
@@ -417,6 +495,149 @@ impl<'d, T: Instance> Adc<'d, T> {
         self.convert()
     }
 
+    /// Start continuous conversions of `pin`, written by DMA to the circular buffer `dma_buf`.
+    ///
+    /// The conversions run until the returned [`AdcStream`] is dropped.
+    ///
+    /// The DMA can not access the DTCM, so `dma_buf` must be placed in another RAM.
+    #[cfg(not(gpdma))]
+    pub fn read_continuous<'a, P, D>(
+        &'a mut self,
+        pin: &mut P,
+        dma: impl Peripheral<P = D> + 'a,
+        dma_buf: &'a mut [u16],
+    ) -> AdcStream<'a, 'd, T, D>
+    where
+        P: AdcPin<T>,
+        D: RxDma<T>,
+    {
+        pin.set_as_analog();
+
+        AdcStream::new(self, pin.channel(), dma, dma_buf)
+    }
+
+    #[cfg(not(gpdma))]
+    pub(super) unsafe fn start_continuous(&mut self, channel: u8) {
+        // Configure channel
+        Self::set_channel_sample_time(channel, self.sample_time);
+
+        T::regs().cfgr2().modify(|w| w.set_lshift(0));
+        T::regs()
+            .pcsel()
+            .write(|w| w.set_pcsel(channel as _, Pcsel::PRESELECTED));
+        T::regs().sqr1().write(|reg| {
+            reg.set_sq(0, channel);
+            reg.set_l(0);
+        });
+
+        T::regs().isr().modify(|reg| {
+            reg.set_ovr(true);
+        });
+
+        // Continuous conversions, with the DMA in circular mode
+        T::regs().cfgr().modify(|w| {
+            w.set_cont(true);
+            w.set_dmngt(Dmngt::DMA_CIRCULAR);
+        });
+
+        // Start conversion
+        T::regs().cr().modify(|reg| {
+            reg.set_adstart(true);
+        });
+    }
+
+    #[cfg(not(gpdma))]
+    pub(super) unsafe fn stop_continuous(&mut self) {
+        T::regs().cr().modify(|reg| reg.set_adstp(Adstp::STOP));
+        while T::regs().cr().read().adstart() {
+            // spin
+        }
+
+        // back to single conversion mode
+        T::regs().cfgr().modify(|w| {
+            w.set_cont(false);
+            w.set_dmngt(Dmngt::DR);
+        });
+    }
+
+    pub(super) unsafe fn configure_injected(&mut self, channels: &[InjectedChannel<T>], trigger: InjectedTrigger) {
+        T::regs().jsqr().write(|w| {
+            w.set_jl((channels.len() - 1) as u8);
+            for (rank, ch) in channels.iter().enumerate() {
+                w.set_jsq1(rank, ch.channel);
+            }
+            if let InjectedTrigger::External { source, edge } = trigger {
+                w.set_jextsel(Jextsel(source));
+                w.set_jexten(match edge {
+                    TriggerEdge::Rising => Jexten::RISINGEDGE,
+                    TriggerEdge::Falling => Jexten::FALLINGEDGE,
+                    TriggerEdge::Both => Jexten::BOTHEDGES,
+                });
+            }
+        });
+
+        for (rank, ch) in channels.iter().enumerate() {
+            Self::set_channel_sample_time(ch.channel, self.sample_time);
+            T::regs()
+                .pcsel()
+                .modify(|w| w.set_pcsel(ch.channel as _, Pcsel::PRESELECTED));
+
+            // The offset registers are not tied to the injected ranks, they are matched by channel.
+            T::regs().ofr(rank).write(|w| {
+                w.set_offset1_ch(ch.channel);
+                w.set_offset1(ch.offset as u32);
+            });
+        }
+
+        T::regs().ier().modify(|w| w.set_jeosie(false));
+        T::regs().isr().write(|w| w.set_jeos(true));
+    }
+
+    pub(super) unsafe fn start_injected(&mut self, _trigger: InjectedTrigger) {
+        T::regs().cr().modify(|w| w.set_jadstart(true));
+    }
+
+    pub(super) unsafe fn stop_injected(&mut self) {
+        if T::regs().cr().read().jadstart() {
+            T::regs().cr().modify(|w| w.set_jadstp(Adstp::STOP));
+            while T::regs().cr().read().jadstart() {
+                // spin
+            }
+        }
+
+        T::regs().ier().modify(|w| w.set_jeosie(false));
+        for n in 0..INJECTED_MAX_LEN {
+            T::regs().ofr(n).write(|_| {});
+        }
+    }
+
+    pub(super) unsafe fn injected_complete(&mut self) -> bool {
+        if T::regs().isr().read().jeos() {
+            T::regs().isr().write(|w| w.set_jeos(true));
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(super) unsafe fn enable_injected_interrupt(&mut self) {
+        T::regs().ier().modify(|w| w.set_jeosie(true));
+    }
+
+    /// Disable the end of injected sequence interrupt if it is pending, returns whether it was
+    pub(super) unsafe fn injected_interrupt() -> bool {
+        if T::regs().ier().read().jeosie() && T::regs().isr().read().jeos() {
+            T::regs().ier().modify(|w| w.set_jeosie(false));
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(super) unsafe fn injected_data(&mut self, rank: usize) -> i32 {
+        T::regs().jdr(rank).read().0 as i32
+    }
+
     unsafe fn set_channel_sample_time(ch: u8, sample_time: SampleTime) {
         let sample_time = sample_time.into();
         if ch <= 9 {
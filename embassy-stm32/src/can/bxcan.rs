@@ -1,11 +1,135 @@
+use core::future::poll_fn;
+use core::marker::PhantomData;
 use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Poll;
 
 pub use bxcan;
+use bxcan::{Frame, Mailbox, TransmitStatus};
 use embassy_hal_common::{into_ref, PeripheralRef};
+use embassy_sync::waitqueue::AtomicWaker;
 
 use crate::gpio::sealed::AFType;
+use crate::interrupt::{Interrupt, InterruptExt};
+use crate::pac::can::vals::{Bofie, Errie, Fmpie, Lec, Tmeie};
 use crate::rcc::RccPeripheral;
-use crate::{peripherals, Peripheral};
+use crate::{interrupt, peripherals, Peripheral};
+
+/// Interrupt handler for the TX interrupt.
+pub struct TxInterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::Handler<T::TXInterrupt> for TxInterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let regs = T::regs();
+        let tsr = regs.tsr().read();
+        if (0..3).any(|mb| tsr.rqcp(mb)) {
+            // Writing 1 to RQCP also clears TXOK, ALST and TERR
+            regs.tsr().write(|w| {
+                for mb in 0..3 {
+                    w.set_rqcp(mb, tsr.rqcp(mb));
+                }
+            });
+            T::state().tx_waker.wake();
+        }
+    }
+}
+
+/// Interrupt handler for the RX0 interrupt.
+pub struct Rx0InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::Handler<T::RX0Interrupt> for Rx0InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        on_rx_interrupt::<T>(0);
+    }
+}
+
+/// Interrupt handler for the RX1 interrupt.
+pub struct Rx1InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::Handler<T::RX1Interrupt> for Rx1InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        on_rx_interrupt::<T>(1);
+    }
+}
+
+/// Interrupt handler for the status change and error interrupt.
+pub struct SceInterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::Handler<T::SCEInterrupt> for SceInterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let regs = T::regs();
+        if regs.msr().read().erri() {
+            regs.msr().write(|w| w.set_erri(true));
+            if regs.esr().read().boff() {
+                T::state().bus_off.store(true, Ordering::Relaxed);
+                T::state().rx_waker.wake();
+            }
+        }
+    }
+}
+
+/// Leave the frames in the FIFO, and mask its interrupt until they are read.
+unsafe fn on_rx_interrupt<T: Instance>(fifo: usize) {
+    let regs = T::regs();
+    if regs.rfr(fifo).read().fmp() != 0 {
+        critical_section::with(|_| regs.ier().modify(|w| w.set_fmpie(fifo, Fmpie::DISABLED)));
+        T::state().rx_waker.wake();
+    }
+}
+
+/// CAN error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The controller entered the bus-off state, after too many transmit errors.
+    BusOff,
+    /// A FIFO was full, and a received frame was lost.
+    Overrun,
+}
+
+/// Error detected on the bus, by the last transmission or reception.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BusError {
+    Stuff,
+    Form,
+    Acknowledge,
+    BitRecessive,
+    BitDominant,
+    Crc,
+    Software,
+}
+
+/// Fault confinement state of the controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BusState {
+    /// Both error counters are below 96.
+    ErrorActive,
+    /// One of the error counters reached the warning limit of 96.
+    ErrorWarning,
+    /// One of the error counters is above 127: the controller only sends passive error flags.
+    ErrorPassive,
+    /// The transmit error counter is above 255: the controller doesn't take part in the bus
+    /// activity anymore, until it recovers.
+    BusOff,
+}
+
+/// Transmit and receive error counters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ErrorCounters {
+    pub transmit: u8,
+    pub receive: u8,
+}
 
 pub struct Can<'d, T: Instance> {
     can: bxcan::Can<BxcanInstance<'d, T>>,
@@ -17,20 +141,15 @@ impl<'d, T: Instance> Can<'d, T> {
         peri: impl Peripheral<P = T> + 'd,
         rx: impl Peripheral<P = impl RxPin<T>> + 'd,
         tx: impl Peripheral<P = impl TxPin<T>> + 'd,
+        irqs: impl interrupt::Binding<T::TXInterrupt, TxInterruptHandler<T>>
+            + interrupt::Binding<T::RX0Interrupt, Rx0InterruptHandler<T>>
+            + interrupt::Binding<T::RX1Interrupt, Rx1InterruptHandler<T>>
+            + interrupt::Binding<T::SCEInterrupt, SceInterruptHandler<T>>
+            + 'd,
     ) -> Self {
-        into_ref!(peri, rx, tx);
-
-        unsafe {
-            rx.set_as_af(rx.af_num(), AFType::Input);
-            tx.set_as_af(tx.af_num(), AFType::OutputPushPull);
-        }
-
-        T::enable();
-        T::reset();
-
-        Self {
-            can: bxcan::Can::builder(BxcanInstance(peri)).enable(),
-        }
+        let mut this = Self::new_disabled(peri, rx, tx, irqs);
+        this.can.modify_config().enable();
+        this
     }
 
     /// Creates a new Bxcan instance, keeping the peripheral in sleep mode.
@@ -39,6 +158,11 @@ impl<'d, T: Instance> Can<'d, T> {
         peri: impl Peripheral<P = T> + 'd,
         rx: impl Peripheral<P = impl RxPin<T>> + 'd,
         tx: impl Peripheral<P = impl TxPin<T>> + 'd,
+        _irqs: impl interrupt::Binding<T::TXInterrupt, TxInterruptHandler<T>>
+            + interrupt::Binding<T::RX0Interrupt, Rx0InterruptHandler<T>>
+            + interrupt::Binding<T::RX1Interrupt, Rx1InterruptHandler<T>>
+            + interrupt::Binding<T::SCEInterrupt, SceInterruptHandler<T>>
+            + 'd,
     ) -> Self {
         into_ref!(peri, rx, tx);
 
@@ -50,14 +174,123 @@ impl<'d, T: Instance> Can<'d, T> {
         T::enable();
         T::reset();
 
-        Self {
-            can: bxcan::Can::builder(BxcanInstance(peri)).leave_disabled(),
+        let can = bxcan::Can::builder(BxcanInstance(peri)).leave_disabled();
+
+        T::state().bus_off.store(false, Ordering::Relaxed);
+
+        // NOTE(unsafe) We own the peripheral
+        unsafe {
+            T::regs().ier().write(|w| {
+                w.set_tmeie(Tmeie::ENABLED);
+                w.set_fmpie(0, Fmpie::ENABLED);
+                w.set_fmpie(1, Fmpie::ENABLED);
+                w.set_bofie(Bofie::ENABLED);
+                w.set_errie(Errie::ENABLED);
+            });
+
+            T::TXInterrupt::steal().unpend();
+            T::TXInterrupt::steal().enable();
+            T::RX0Interrupt::steal().unpend();
+            T::RX0Interrupt::steal().enable();
+            T::RX1Interrupt::steal().unpend();
+            T::RX1Interrupt::steal().enable();
+            T::SCEInterrupt::steal().unpend();
+            T::SCEInterrupt::steal().enable();
+        }
+
+        Self { can }
+    }
+
+    /// Queues the frame for transmission, waiting for a free mailbox.
+    ///
+    /// If all the mailboxes are busy, and `frame` has a higher priority than one of their frames,
+    /// that frame is replaced and returned in the [`TransmitStatus`].
+    pub async fn write(&mut self, frame: &Frame) -> TransmitStatus {
+        self.split().0.write(frame).await
+    }
+
+    /// Waits for the transmission of the frame in `mb` to complete, successfully or not.
+    pub async fn flush(&self, mb: Mailbox) {
+        flush::<T>(mb).await
+    }
+
+    /// Waits for a frame, from any of the two FIFOs.
+    ///
+    /// Returns [`Error::BusOff`] once when the controller enters the bus-off state, and
+    /// [`Error::Overrun`] when frames were lost because the application didn't read them in time.
+    pub async fn read(&mut self) -> Result<Frame, Error> {
+        self.split().1.read().await
+    }
+
+    /// Splits the driver into a transmitter and a receiver, to use them from different tasks.
+    pub fn split<'c>(&'c mut self) -> (CanTx<'c, 'd, T>, CanRx<'c, 'd, T>) {
+        let (tx, rx0, rx1) = self.can.split_by_ref();
+        (CanTx { tx }, CanRx { rx0, rx1 })
+    }
+
+    /// Returns the current fault confinement state.
+    pub fn bus_state(&self) -> BusState {
+        let esr = unsafe { T::regs().esr().read() };
+        if esr.boff() {
+            BusState::BusOff
+        } else if esr.epvf() {
+            BusState::ErrorPassive
+        } else if esr.ewgf() {
+            BusState::ErrorWarning
+        } else {
+            BusState::ErrorActive
+        }
+    }
+
+    /// Returns the transmit and receive error counters.
+    pub fn error_counters(&self) -> ErrorCounters {
+        let esr = unsafe { T::regs().esr().read() };
+        ErrorCounters {
+            transmit: esr.tec(),
+            receive: esr.rec(),
+        }
+    }
+
+    /// Returns the error detected by the last transmission or reception, if any.
+    pub fn last_error(&self) -> Option<BusError> {
+        let esr = unsafe { T::regs().esr().read() };
+        match esr.lec() {
+            Lec::STUFF => Some(BusError::Stuff),
+            Lec::FORM => Some(BusError::Form),
+            Lec::ACK => Some(BusError::Acknowledge),
+            Lec::BITRECESSIVE => Some(BusError::BitRecessive),
+            Lec::BITDOMINANT => Some(BusError::BitDominant),
+            Lec::CRC => Some(BusError::Crc),
+            Lec::CUSTOM => Some(BusError::Software),
+            _ => None,
         }
     }
+
+    /// Enables or disables the automatic recovery from the bus-off state.
+    ///
+    /// When enabled, the controller leaves the bus-off state on its own after monitoring 128
+    /// occurrences of 11 recessive bits. Otherwise, [`Can::recover_from_bus_off`] must be called.
+    pub fn set_automatic_bus_off_recovery(&mut self, enabled: bool) {
+        unsafe { T::regs().mcr().modify(|w| w.set_abom(enabled)) }
+    }
+
+    /// Starts the recovery from the bus-off state, blocking until the controller is back on the bus.
+    ///
+    /// The controller may still need to monitor 128 occurrences of 11 recessive bits after that,
+    /// before it can transmit again.
+    pub fn recover_from_bus_off(&mut self) {
+        self.can.modify_config().enable();
+    }
 }
 
 impl<'d, T: Instance> Drop for Can<'d, T> {
     fn drop(&mut self) {
+        unsafe {
+            T::TXInterrupt::steal().disable();
+            T::RX0Interrupt::steal().disable();
+            T::RX1Interrupt::steal().disable();
+            T::SCEInterrupt::steal().disable();
+        }
         // Cannot call `free()` because it moves the instance.
         // Manually reset the peripheral.
         unsafe { T::regs().mcr().write(|w| w.set_reset(true)) }
@@ -79,15 +312,119 @@ impl<'d, T: Instance> DerefMut for Can<'d, T> {
     }
 }
 
+/// Transmitter half of a [`Can`].
+pub struct CanTx<'c, 'd, T: Instance> {
+    tx: &'c mut bxcan::Tx<BxcanInstance<'d, T>>,
+}
+
+impl<'c, 'd, T: Instance> CanTx<'c, 'd, T> {
+    /// Queues the frame for transmission, waiting for a free mailbox.
+    ///
+    /// See [`Can::write`].
+    pub async fn write(&mut self, frame: &Frame) -> TransmitStatus {
+        poll_fn(|cx| {
+            T::state().tx_waker.register(cx.waker());
+            match self.tx.transmit(frame) {
+                Ok(status) => Poll::Ready(status),
+                Err(nb::Error::WouldBlock) => Poll::Pending,
+                Err(nb::Error::Other(e)) => match e {},
+            }
+        })
+        .await
+    }
+
+    /// Waits for the transmission of the frame in `mb` to complete, successfully or not.
+    pub async fn flush(&self, mb: Mailbox) {
+        flush::<T>(mb).await
+    }
+}
+
+async fn flush<T: Instance>(mb: Mailbox) {
+    poll_fn(|cx| {
+        T::state().tx_waker.register(cx.waker());
+        if unsafe { T::regs().tsr().read().tme(mb as usize) } {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+/// Receiver half of a [`Can`].
+pub struct CanRx<'c, 'd, T: Instance> {
+    rx0: &'c mut bxcan::Rx0<BxcanInstance<'d, T>>,
+    rx1: &'c mut bxcan::Rx1<BxcanInstance<'d, T>>,
+}
+
+impl<'c, 'd, T: Instance> CanRx<'c, 'd, T> {
+    /// Waits for a frame, from any of the two FIFOs.
+    ///
+    /// See [`Can::read`].
+    pub async fn read(&mut self) -> Result<Frame, Error> {
+        poll_fn(|cx| {
+            T::state().rx_waker.register(cx.waker());
+
+            // No `swap`, for thumbv6m
+            if T::state().bus_off.load(Ordering::Relaxed) {
+                T::state().bus_off.store(false, Ordering::Relaxed);
+                return Poll::Ready(Err(Error::BusOff));
+            }
+
+            for res in [self.rx0.receive(), self.rx1.receive()] {
+                match res {
+                    Ok(frame) => return Poll::Ready(Ok(frame)),
+                    Err(nb::Error::Other(_)) => return Poll::Ready(Err(Error::Overrun)),
+                    Err(nb::Error::WouldBlock) => {}
+                }
+            }
+
+            // Both FIFOs are empty, wait for the next frame
+            critical_section::with(|_| unsafe {
+                T::regs().ier().modify(|w| {
+                    w.set_fmpie(0, Fmpie::ENABLED);
+                    w.set_fmpie(1, Fmpie::ENABLED);
+                })
+            });
+            Poll::Pending
+        })
+        .await
+    }
+}
+
 pub(crate) mod sealed {
+    use super::*;
+
+    pub struct State {
+        pub tx_waker: AtomicWaker,
+        pub rx_waker: AtomicWaker,
+        pub bus_off: AtomicBool,
+    }
+
+    impl State {
+        pub const fn new() -> Self {
+            Self {
+                tx_waker: AtomicWaker::new(),
+                rx_waker: AtomicWaker::new(),
+                bus_off: AtomicBool::new(false),
+            }
+        }
+    }
+
     pub trait Instance {
         const REGISTERS: *mut bxcan::RegisterBlock;
 
         fn regs() -> &'static crate::pac::can::Can;
+        fn state() -> &'static State;
     }
 }
 
-pub trait Instance: sealed::Instance + RccPeripheral {}
+pub trait Instance: sealed::Instance + RccPeripheral + 'static {
+    type TXInterrupt: Interrupt;
+    type RX0Interrupt: Interrupt;
+    type RX1Interrupt: Interrupt;
+    type SCEInterrupt: Interrupt;
+}
 
 pub struct BxcanInstance<'a, T>(PeripheralRef<'a, T>);
 
@@ -103,10 +440,19 @@ foreach_peripheral!(
             fn regs() -> &'static crate::pac::can::Can {
                 &crate::pac::$inst
             }
-        }
 
-        impl Instance for peripherals::$inst {}
+            fn state() -> &'static sealed::State {
+                static STATE: sealed::State = sealed::State::new();
+                &STATE
+            }
+        }
 
+        impl Instance for peripherals::$inst {
+            type TXInterrupt = crate::_generated::peripheral_interrupts::$inst::TX;
+            type RX0Interrupt = crate::_generated::peripheral_interrupts::$inst::RX0;
+            type RX1Interrupt = crate::_generated::peripheral_interrupts::$inst::RX1;
+            type SCEInterrupt = crate::_generated::peripheral_interrupts::$inst::SCE;
+        }
     };
 );
 
@@ -1,9 +1,18 @@
 #![macro_use]
 
+#[cfg(not(gpdma))]
+use core::future::poll_fn;
+#[cfg(not(gpdma))]
+use core::task::Poll;
+
 use embassy_hal_common::{into_ref, PeripheralRef};
 
+#[cfg(not(gpdma))]
+use crate::dma::{Transfer, TransferOptions};
 use crate::pac::dac;
 use crate::rcc::RccPeripheral;
+#[cfg(not(gpdma))]
+use crate::time::Hertz;
 use crate::{peripherals, Peripheral};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -97,6 +106,17 @@ pub enum Value {
     Bit12(u16, Alignment),
 }
 
+/// Result of the callback of [`Waveform::write_continuous`]
+#[cfg(not(gpdma))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CallbackResult {
+    /// Play the buffer and continue the waveform
+    Continue,
+    /// Stop the waveform
+    Stop,
+}
+
 pub struct Dac<'d, T: Instance> {
     channels: u8,
     _peri: PeripheralRef<'d, T>,
@@ -215,16 +235,241 @@ impl<'d, T: Instance> Dac<'d, T> {
         }
         Ok(())
     }
+
+    /// Output waveforms on channel 1, with the samples transferred by `dma` on the update events of `timer`.
+    #[cfg(not(gpdma))]
+    pub fn waveform_ch1<'a, D: DmaCh1<T>, TIM: TriggerTimer>(
+        &'a mut self,
+        dma: impl Peripheral<P = D> + 'a,
+        timer: impl Peripheral<P = TIM> + 'a,
+    ) -> Result<Waveform<'a, 'd, T, D, TIM>, Error> {
+        into_ref!(dma);
+        self.select_trigger_ch1(TIM::ch1_trigger())?;
+        #[cfg(any(bdma_v2, dma_v2, dmamux))]
+        let waveform = Waveform::new(self, Channel::Ch1, dma.request(), dma, timer);
+        #[cfg(not(any(bdma_v2, dma_v2, dmamux)))]
+        let waveform = Waveform::new(self, Channel::Ch1, dma, timer);
+        Ok(waveform)
+    }
+
+    /// Output waveforms on channel 2, with the samples transferred by `dma` on the update events of `timer`.
+    #[cfg(not(gpdma))]
+    pub fn waveform_ch2<'a, D: DmaCh2<T>, TIM: TriggerTimer>(
+        &'a mut self,
+        dma: impl Peripheral<P = D> + 'a,
+        timer: impl Peripheral<P = TIM> + 'a,
+    ) -> Result<Waveform<'a, 'd, T, D, TIM>, Error> {
+        into_ref!(dma);
+        self.select_trigger_ch2(TIM::ch2_trigger())?;
+        #[cfg(any(bdma_v2, dma_v2, dmamux))]
+        let waveform = Waveform::new(self, Channel::Ch2, dma.request(), dma, timer);
+        #[cfg(not(any(bdma_v2, dma_v2, dmamux)))]
+        let waveform = Waveform::new(self, Channel::Ch2, dma, timer);
+        Ok(waveform)
+    }
+}
+
+/// Waveform output of a DAC channel.
+///
+/// The samples are transferred by DMA to the channel on each update event of the timer, so the
+/// waveform is played at the sample rate without CPU involvement. Created by [`Dac::waveform_ch1`]
+/// or [`Dac::waveform_ch2`].
+#[cfg(not(gpdma))]
+pub struct Waveform<'a, 'd, T: Instance, D: crate::dma::Channel, TIM: TriggerTimer> {
+    _dac: &'a mut Dac<'d, T>,
+    ch: Channel,
+    dma: PeripheralRef<'a, D>,
+    // The DMA controllers without a request mux have no request to select
+    #[cfg(any(bdma_v2, dma_v2, dmamux))]
+    request: crate::dma::Request,
+    timer: PeripheralRef<'a, TIM>,
+}
+
+#[cfg(not(gpdma))]
+impl<'a, 'd, T: Instance, D: crate::dma::Channel, TIM: TriggerTimer> Waveform<'a, 'd, T, D, TIM> {
+    fn new(
+        dac: &'a mut Dac<'d, T>,
+        ch: Channel,
+        #[cfg(any(bdma_v2, dma_v2, dmamux))] request: crate::dma::Request,
+        dma: PeripheralRef<'a, D>,
+        timer: impl Peripheral<P = TIM> + 'a,
+    ) -> Self {
+        into_ref!(timer);
+
+        TIM::enable();
+        unsafe {
+            TIM::regs()
+                .cr2()
+                .modify(|w| w.set_mms(crate::pac::timer::vals::Mms::UPDATE));
+
+            // The channel was disabled to select the trigger
+            T::regs().cr().modify(|w| {
+                w.set_ten(ch.index(), true);
+                w.set_en(ch.index(), true);
+            });
+        }
+
+        Self {
+            _dac: dac,
+            ch,
+            dma,
+            #[cfg(any(bdma_v2, dma_v2, dmamux))]
+            request,
+            timer,
+        }
+    }
+
+    fn dhr_ptr(&self) -> *mut u16 {
+        T::regs().dhr12r(self.ch.index()).ptr() as *mut u16
+    }
+
+    fn set_dma_enable(ch: Channel, on: bool) {
+        unsafe { T::regs().cr().modify(|w| w.set_dmaen(ch.index(), on)) }
+    }
+
+    /// Start the timer, the DAC loads the sample written before the first update event.
+    fn start(timer: &mut TIM, ch: Channel, first: u16, sample_rate: Hertz) {
+        unsafe { T::regs().dhr12r(ch.index()).write(|w| w.set_dhr(first)) };
+
+        timer.stop();
+        timer.set_frequency(sample_rate);
+        timer.reset();
+        timer.start();
+    }
+
+    /// Play the 12 bit, right aligned samples of `data` once, at `sample_rate`.
+    ///
+    /// Returns once the last sample has been transferred, the output then holds the last sample.
+    pub async fn write_waveform(&mut self, data: &[u16], sample_rate: Hertz) {
+        assert!(!data.is_empty());
+
+        // The DAC requests the next sample when it loads the current one, the first sample is written
+        // before the timer starts.
+        Self::set_dma_enable(self.ch, true);
+        let transfer = if data.len() > 1 {
+            let dst = self.dhr_ptr();
+            #[cfg(any(bdma_v2, dma_v2, dmamux))]
+            let transfer =
+                unsafe { Transfer::new_write(&mut self.dma, self.request, &data[1..], dst, Default::default()) };
+            #[cfg(not(any(bdma_v2, dma_v2, dmamux)))]
+            let transfer = unsafe { Transfer::new_write(&mut self.dma, (), &data[1..], dst, Default::default()) };
+            Some(transfer)
+        } else {
+            None
+        };
+        Self::start(&mut self.timer, self.ch, data[0], sample_rate);
+
+        if let Some(transfer) = transfer {
+            transfer.await;
+        }
+        Self::set_dma_enable(self.ch, false);
+    }
+
+    /// Play a continuous waveform at `sample_rate`, from the 12 bit, right aligned samples of the two
+    /// buffers of `bufs`.
+    ///
+    /// The first sample is output one sample period after the call, the output keeps its previous value
+    /// until then.
+    ///
+    /// `callback` fills the buffers with the next samples, it is first called for both buffers and then
+    /// each time a buffer has been played, while the other buffer is played. The waveform is stopped
+    /// as soon as `callback` returns [`CallbackResult::Stop`], the samples of the other buffer which are
+    /// not played yet are discarded.
+    pub async fn write_continuous<const N: usize>(
+        &mut self,
+        bufs: &mut [[u16; N]; 2],
+        sample_rate: Hertz,
+        mut callback: impl FnMut(&mut [u16; N]) -> CallbackResult,
+    ) {
+        assert!(N > 0 && N * 2 <= 0xFFFF);
+
+        for buf in bufs.iter_mut() {
+            if callback(buf) == CallbackResult::Stop {
+                return;
+            }
+        }
+
+        let bufs = bufs.as_mut_ptr();
+        let options = TransferOptions {
+            circular: true,
+            half_transfer_ir: true,
+            ..Default::default()
+        };
+
+        // The circular transfer can't start at the second sample, so the output keeps its current value
+        // for the first period and the DMA provides all the samples, starting with the first one.
+        Self::set_dma_enable(self.ch, true);
+        let dst = self.dhr_ptr();
+        let data = core::ptr::slice_from_raw_parts(bufs as *const u16, N * 2);
+        #[cfg(any(bdma_v2, dma_v2, dmamux))]
+        let mut transfer = unsafe { Transfer::new_write_raw(&mut self.dma, self.request, data, dst, options) };
+        #[cfg(not(any(bdma_v2, dma_v2, dmamux)))]
+        let mut transfer = unsafe { Transfer::new_write_raw(&mut self.dma, (), data, dst, options) };
+        let current = unsafe { T::regs().dor(self.ch.index()).read().dor() };
+        Self::start(&mut self.timer, self.ch, current, sample_rate);
+
+        // Index of the next buffer to fill, once the DMA has moved to the other one
+        let mut next = 0;
+        loop {
+            poll_fn(|cx| {
+                transfer.set_waker(cx.waker());
+
+                let playing = if transfer.get_remaining_transfers() as usize > N {
+                    0
+                } else {
+                    1
+                };
+                if playing != next {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await;
+
+            if callback(unsafe { &mut *bufs.add(next) }) == CallbackResult::Stop {
+                break;
+            }
+            next ^= 1;
+        }
+
+        drop(transfer);
+        Self::set_dma_enable(self.ch, false);
+    }
+}
+
+#[cfg(not(gpdma))]
+impl<'a, 'd, T: Instance, D: crate::dma::Channel, TIM: TriggerTimer> Drop for Waveform<'a, 'd, T, D, TIM> {
+    fn drop(&mut self) {
+        self.timer.stop();
+        unsafe {
+            T::regs().cr().modify(|w| {
+                w.set_dmaen(self.ch.index(), false);
+                w.set_ten(self.ch.index(), false);
+            });
+        }
+    }
 }
 
 pub(crate) mod sealed {
     pub trait Instance {
         fn regs() -> &'static crate::pac::dac::Dac;
     }
+
+    pub trait TriggerTimer {
+        fn ch1_trigger() -> super::Ch1Trigger;
+        fn ch2_trigger() -> super::Ch2Trigger;
+    }
 }
 
 pub trait Instance: sealed::Instance + RccPeripheral + 'static {}
 
+/// Timer whose update event can trigger the conversions of both DAC channels
+pub trait TriggerTimer: sealed::TriggerTimer + crate::timer::Basic16bitInstance {}
+
+dma_trait!(DmaCh1, Instance);
+dma_trait!(DmaCh2, Instance);
+
 pub trait DacPin<T: Instance, const C: u8>: crate::gpio::Pin + 'static {}
 
 foreach_peripheral!(
@@ -271,6 +516,34 @@ foreach_peripheral!(
     };
 );
 
+macro_rules! impl_dac_trigger_timer {
+    ($inst:ident, $trigger:ident) => {
+        impl crate::dac::sealed::TriggerTimer for peripherals::$inst {
+            fn ch1_trigger() -> crate::dac::Ch1Trigger {
+                crate::dac::Ch1Trigger::$trigger
+            }
+
+            fn ch2_trigger() -> crate::dac::Ch2Trigger {
+                crate::dac::Ch2Trigger::$trigger
+            }
+        }
+
+        impl crate::dac::TriggerTimer for peripherals::$inst {}
+    };
+}
+
+foreach_peripheral!(
+    (timer, TIM2) => {
+        impl_dac_trigger_timer!(TIM2, Tim2);
+    };
+    (timer, TIM6) => {
+        impl_dac_trigger_timer!(TIM6, Tim6);
+    };
+    (timer, TIM7) => {
+        impl_dac_trigger_timer!(TIM7, Tim7);
+    };
+);
+
 macro_rules! impl_dac_pin {
     ($inst:ident, $pin:ident, $ch:expr) => {
         impl crate::dac::DacPin<peripherals::$inst, $ch> for crate::peripherals::$pin {}
@@ -21,11 +21,20 @@ use crate::pac::bdma::{regs, vals};
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
-pub struct TransferOptions {}
+pub struct TransferOptions {
+    /// Restart the transfer from the start of the buffer when it completes. The transfer then runs
+    /// until it is stopped.
+    pub circular: bool,
+    /// Wake the transfer's waker when half of the buffer has been transferred
+    pub half_transfer_ir: bool,
+}
 
 impl Default for TransferOptions {
     fn default() -> Self {
-        Self {}
+        Self {
+            circular: false,
+            half_transfer_ir: false,
+        }
     }
 }
 
@@ -260,7 +269,7 @@ impl<'a, C: Channel> Transfer<'a, C> {
         mem_len: usize,
         incr_mem: bool,
         data_size: WordSize,
-        _options: TransferOptions,
+        options: TransferOptions,
     ) -> Self {
         let ch = channel.regs().ch(channel.num());
 
@@ -291,6 +300,10 @@ impl<'a, C: Channel> Transfer<'a, C> {
             w.set_dir(dir.into());
             w.set_teie(true);
             w.set_tcie(true);
+            if options.circular {
+                w.set_circ(vals::Circ::ENABLED);
+            }
+            w.set_htie(options.half_transfer_ir);
             w.set_en(true);
         });
 
@@ -300,12 +313,17 @@ impl<'a, C: Channel> Transfer<'a, C> {
     fn clear_irqs(&mut self) {
         unsafe {
             self.channel.regs().ifcr().write(|w| {
+                w.set_htif(self.channel.num(), true);
                 w.set_tcif(self.channel.num(), true);
                 w.set_teif(self.channel.num(), true);
             })
         }
     }
 
+    pub fn set_waker(&mut self, waker: &Waker) {
+        STATE.ch_wakers[self.channel.index()].register(waker);
+    }
+
     pub fn request_stop(&mut self) {
         let ch = self.channel.regs().ch(self.channel.num());
 
@@ -320,9 +338,10 @@ impl<'a, C: Channel> Transfer<'a, C> {
 
     pub fn is_running(&mut self) -> bool {
         let ch = self.channel.regs().ch(self.channel.num());
-        let en = unsafe { ch.cr().read() }.en();
+        let cr = unsafe { ch.cr().read() };
+        let circular = cr.circ() == vals::Circ::ENABLED;
         let tcif = STATE.complete_count[self.channel.index()].load(Ordering::Acquire) != 0;
-        en && !tcif
+        cr.en() && (circular || !tcif)
     }
 
     /// Gets the total remaining transfers for the channel
@@ -393,8 +412,36 @@ pub struct RingBuffer<'a, C: Channel, W: Word> {
 
 impl<'a, C: Channel, W: Word> RingBuffer<'a, C, W> {
     pub unsafe fn new_read(
+        channel: impl Peripheral<P = C> + 'a,
+        request: Request,
+        peri_addr: *mut W,
+        buffer: &'a mut [W],
+        options: TransferOptions,
+    ) -> Self {
+        Self::new_inner(channel, request, Dir::PeripheralToMemory, peri_addr, buffer, options)
+    }
+
+    /// Create a ring buffer which the DMA controller reads in a circular way, and writes to the peripheral.
+    /// The initial content of `buffer` is transferred first, use [`RingBuffer::write`] to replace the
+    /// already transferred data.
+    ///
+    /// # Safety
+    ///
+    /// `peri_addr` must be the data register of the peripheral selected by `request`.
+    pub unsafe fn new_write(
+        channel: impl Peripheral<P = C> + 'a,
+        request: Request,
+        buffer: &'a mut [W],
+        peri_addr: *mut W,
+        options: TransferOptions,
+    ) -> Self {
+        Self::new_inner(channel, request, Dir::MemoryToPeripheral, peri_addr, buffer, options)
+    }
+
+    unsafe fn new_inner(
         channel: impl Peripheral<P = C> + 'a,
         _request: Request,
+        dir: Dir,
         peri_addr: *mut W,
         buffer: &'a mut [W],
         _options: TransferOptions,
@@ -404,7 +451,6 @@ impl<'a, C: Channel, W: Word> RingBuffer<'a, C, W> {
         let len = buffer.len();
         assert!(len > 0 && len <= 0xFFFF);
 
-        let dir = Dir::PeripheralToMemory;
         let data_size = W::size();
 
         let channel_number = channel.num();
@@ -462,6 +508,14 @@ impl<'a, C: Channel, W: Word> RingBuffer<'a, C, W> {
         self.ringbuf.read(DmaCtrlImpl(self.channel.reborrow()), buf)
     }
 
+    /// Write bytes to a ring buffer created with [`RingBuffer::new_write`]
+    /// Only the portion already transferred by the DMA controller can be written, which is what
+    /// [`RingBuffer::len`] returns. OverrunError is returned if the DMA controller has transferred
+    /// past the written portion, i.e. it sent stale data.
+    pub fn write(&mut self, buf: &[W]) -> Result<usize, OverrunError> {
+        self.ringbuf.write(DmaCtrlImpl(self.channel.reborrow()), buf)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.ringbuf.is_empty()
     }
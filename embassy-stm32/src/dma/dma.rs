@@ -29,6 +29,11 @@ pub struct TransferOptions {
     pub flow_ctrl: FlowControl,
     /// FIFO threshold for DMA FIFO mode. If none, direct mode is used.
     pub fifo_threshold: Option<FifoThreshold>,
+    /// Restart the transfer from the start of the buffer when it completes. The transfer then runs
+    /// until it is stopped.
+    pub circular: bool,
+    /// Wake the transfer's waker when half of the buffer has been transferred
+    pub half_transfer_ir: bool,
 }
 
 impl Default for TransferOptions {
@@ -38,6 +43,8 @@ impl Default for TransferOptions {
             mburst: Burst::Single,
             flow_ctrl: FlowControl::Dma,
             fifo_threshold: None,
+            circular: false,
+            half_transfer_ir: false,
         }
     }
 }
@@ -384,6 +391,11 @@ impl<'a, C: Channel> Transfer<'a, C> {
             w.set_mburst(options.mburst.into());
             w.set_pfctrl(options.flow_ctrl.into());
 
+            if options.circular {
+                w.set_circ(vals::Circ::ENABLED);
+            }
+            w.set_htie(options.half_transfer_ir);
+
             w.set_en(true);
         });
 
@@ -396,12 +408,17 @@ impl<'a, C: Channel> Transfer<'a, C> {
 
         unsafe {
             self.channel.regs().ifcr(isrn).write(|w| {
+                w.set_htif(isrbit, true);
                 w.set_tcif(isrbit, true);
                 w.set_teif(isrbit, true);
             })
         }
     }
 
+    pub fn set_waker(&mut self, waker: &Waker) {
+        STATE.ch_wakers[self.channel.index()].register(waker);
+    }
+
     pub fn request_stop(&mut self) {
         let ch = self.channel.regs().st(self.channel.num());
 
@@ -634,8 +651,36 @@ pub struct RingBuffer<'a, C: Channel, W: Word> {
 
 impl<'a, C: Channel, W: Word> RingBuffer<'a, C, W> {
     pub unsafe fn new_read(
+        channel: impl Peripheral<P = C> + 'a,
+        request: Request,
+        peri_addr: *mut W,
+        buffer: &'a mut [W],
+        options: TransferOptions,
+    ) -> Self {
+        Self::new_inner(channel, request, Dir::PeripheralToMemory, peri_addr, buffer, options)
+    }
+
+    /// Create a ring buffer which the DMA controller reads in a circular way, and writes to the peripheral.
+    /// The initial content of `buffer` is transferred first, use [`RingBuffer::write`] to replace the
+    /// already transferred data.
+    ///
+    /// # Safety
+    ///
+    /// `peri_addr` must be the data register of the peripheral selected by `request`.
+    pub unsafe fn new_write(
+        channel: impl Peripheral<P = C> + 'a,
+        request: Request,
+        buffer: &'a mut [W],
+        peri_addr: *mut W,
+        options: TransferOptions,
+    ) -> Self {
+        Self::new_inner(channel, request, Dir::MemoryToPeripheral, peri_addr, buffer, options)
+    }
+
+    unsafe fn new_inner(
         channel: impl Peripheral<P = C> + 'a,
         _request: Request,
+        dir: Dir,
         peri_addr: *mut W,
         buffer: &'a mut [W],
         options: TransferOptions,
@@ -645,7 +690,6 @@ impl<'a, C: Channel, W: Word> RingBuffer<'a, C, W> {
         let len = buffer.len();
         assert!(len > 0 && len <= 0xFFFF);
 
-        let dir = Dir::PeripheralToMemory;
         let data_size = W::size();
 
         let channel_number = channel.num();
@@ -718,6 +762,14 @@ impl<'a, C: Channel, W: Word> RingBuffer<'a, C, W> {
         self.ringbuf.read(DmaCtrlImpl(self.channel.reborrow()), buf)
     }
 
+    /// Write bytes to a ring buffer created with [`RingBuffer::new_write`]
+    /// Only the portion already transferred by the DMA controller can be written, which is what
+    /// [`RingBuffer::len`] returns. OverrunError is returned if the DMA controller has transferred
+    /// past the written portion, i.e. it sent stale data.
+    pub fn write(&mut self, buf: &[W]) -> Result<usize, OverrunError> {
+        self.ringbuf.write(DmaCtrlImpl(self.channel.reborrow()), buf)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.ringbuf.is_empty()
     }
@@ -5,8 +5,12 @@ use core::sync::atomic::{compiler_fence, Ordering};
 
 use super::word::Word;
 
-/// A "read-only" ring-buffer to be used together with the DMA controller which
-/// writes in a circular way, "uncontrolled" to the buffer.
+/// A ring-buffer to be used together with the DMA controller which
+/// writes (or reads) in a circular way, "uncontrolled" to the buffer.
+///
+/// When the DMA controller writes, the portion between `first` and `end` holds the
+/// data to be read. When it reads, the same portion holds the already transferred
+/// data, which can be overwritten with the next data to transfer.
 ///
 /// A snapshot of the ring buffer state can be attained by setting the `ndtr` field
 /// to the current register value. `ndtr` describes the current position of the DMA
@@ -94,7 +98,29 @@ impl<'a, W: Word> DmaRingBuffer<'a, W> {
 
     /// Read bytes from the ring buffer
     /// OverrunError is returned if the portion to be read was overwritten by the DMA controller.
-    pub fn read(&mut self, mut dma: impl DmaCtrl, buf: &mut [W]) -> Result<usize, OverrunError> {
+    pub fn read(&mut self, dma: impl DmaCtrl, buf: &mut [W]) -> Result<usize, OverrunError> {
+        self.transfer(dma, buf.len(), |dma_buf, data_range, offset| {
+            Self::copy_to(dma_buf, &mut buf[offset..], data_range)
+        })
+    }
+
+    /// Write bytes to the ring buffer, in the portion that was already read by the DMA controller
+    /// OverrunError is returned if the DMA controller has read past the written portion, i.e. it
+    /// has transferred stale data.
+    pub fn write(&mut self, dma: impl DmaCtrl, buf: &[W]) -> Result<usize, OverrunError> {
+        self.transfer(dma, buf.len(), |dma_buf, data_range, offset| {
+            Self::copy_from(dma_buf, &buf[offset..], data_range)
+        })
+    }
+
+    /// Move the `first` position over the portion between `first` and `end`, handing out at most
+    /// `buf_len` elements of it to `copy` along with the offset in the user buffer.
+    fn transfer(
+        &mut self,
+        mut dma: impl DmaCtrl,
+        buf_len: usize,
+        mut copy: impl FnMut(&mut [W], Range<usize>, usize) -> usize,
+    ) -> Result<usize, OverrunError> {
         let end = self.end();
 
         compiler_fence(Ordering::SeqCst);
@@ -119,7 +145,7 @@ impl<'a, W: Word> DmaRingBuffer<'a, W> {
             }
 
             // Copy out the bytes from the dma buffer
-            let len = self.copy_to(buf, self.first..end);
+            let len = copy(self.dma_buf, self.first..end, 0);
 
             compiler_fence(Ordering::SeqCst);
 
@@ -157,11 +183,11 @@ impl<'a, W: Word> DmaRingBuffer<'a, W> {
             // If the unread portion wraps then the writer must also have wrapped
             assert!(complete_count == 1);
 
-            if self.first + buf.len() < self.dma_buf.len() {
+            if self.first + buf_len < self.dma_buf.len() {
                 // The provided read buffer is not large enough to include all bytes from the tail of the dma buffer.
 
                 // Copy out from the dma buffer
-                let len = self.copy_to(buf, self.first..self.dma_buf.len());
+                let len = copy(self.dma_buf, self.first..self.dma_buf.len(), 0);
 
                 compiler_fence(Ordering::SeqCst);
 
@@ -180,8 +206,8 @@ impl<'a, W: Word> DmaRingBuffer<'a, W> {
                 // so the next read will not have any unread tail bytes in the ring buffer.
 
                 // Copy out from the dma buffer
-                let tail = self.copy_to(buf, self.first..self.dma_buf.len());
-                let head = self.copy_to(&mut buf[tail..], 0..end);
+                let tail = copy(self.dma_buf, self.first..self.dma_buf.len(), 0);
+                let head = copy(self.dma_buf, 0..end, tail);
 
                 compiler_fence(Ordering::SeqCst);
 
@@ -200,7 +226,7 @@ impl<'a, W: Word> DmaRingBuffer<'a, W> {
     }
 
     /// Copy from the dma buffer at `data_range` into `buf`
-    fn copy_to(&mut self, buf: &mut [W], data_range: Range<usize>) -> usize {
+    fn copy_to(dma_buf: &[W], buf: &mut [W], data_range: Range<usize>) -> usize {
         // Limit the number of bytes that can be copied
         let length = usize::min(data_range.len(), buf.len());
 
@@ -208,7 +234,7 @@ impl<'a, W: Word> DmaRingBuffer<'a, W> {
         // We need to do it like this instead of a simple copy_from_slice() because
         // reading from a part of memory that may be simultaneously written to is unsafe
         unsafe {
-            let dma_buf = self.dma_buf.as_ptr();
+            let dma_buf = dma_buf.as_ptr();
 
             for i in 0..length {
                 buf[i] = core::ptr::read_volatile(dma_buf.offset((data_range.start + i) as isize));
@@ -217,6 +243,25 @@ impl<'a, W: Word> DmaRingBuffer<'a, W> {
 
         length
     }
+
+    /// Copy from `buf` into the dma buffer at `data_range`
+    fn copy_from(dma_buf: &mut [W], buf: &[W], data_range: Range<usize>) -> usize {
+        // Limit the number of bytes that can be copied
+        let length = usize::min(data_range.len(), buf.len());
+
+        // Copy from write buffer into dma buffer
+        // The dma buffer is read by the DMA controller at the same time, so it is written with
+        // volatile stores as well
+        unsafe {
+            let dma_buf = dma_buf.as_mut_ptr();
+
+            for (i, &word) in buf[..length].iter().enumerate() {
+                core::ptr::write_volatile(dma_buf.add(data_range.start + i), word);
+            }
+        }
+
+        length
+    }
 }
 
 #[cfg(test)]
@@ -400,6 +445,59 @@ mod tests {
         assert_eq!(1, ctrl.complete_count); // The complete counter is not reset
     }
 
+    #[test]
+    fn can_write() {
+        let mut dma_buf = [0u8; 16];
+        let mut ctrl = TestCtrl::new();
+        let mut ringbuf = DmaRingBuffer::new(&mut dma_buf);
+
+        // Nothing was read by the dma controller yet
+        assert_eq!(0, ringbuf.write(&mut ctrl, &[1, 2]).unwrap());
+
+        // The dma controller has read 4 bytes
+        ringbuf.ndtr = 12;
+        assert_eq!(4, ringbuf.len());
+
+        assert_eq!(2, ringbuf.write(&mut ctrl, &[1, 2]).unwrap());
+        assert_eq!(2, ringbuf.write(&mut ctrl, &[3, 4, 5]).unwrap());
+        assert_eq!(0, ringbuf.len());
+        assert_eq!([1, 2, 3, 4, 0], ringbuf.dma_buf[..5]);
+    }
+
+    #[test]
+    fn can_write_with_wrap() {
+        let mut dma_buf = [0u8; 16];
+        let mut ctrl = TestCtrl::new();
+        let mut ringbuf = DmaRingBuffer::new(&mut dma_buf);
+        ringbuf.first = 12;
+        ringbuf.ndtr = 10;
+
+        // The dma controller has read 4 + 6 bytes and has reloaded NDTR
+        ctrl.complete_count = 1;
+        ctrl.set_next_ndtr(10);
+
+        assert_eq!(10, ringbuf.write(&mut ctrl, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]).unwrap());
+        assert_eq!([1, 2, 3, 4], ringbuf.dma_buf[12..]);
+        assert_eq!([5, 6, 7, 8, 9, 10], ringbuf.dma_buf[..6]);
+
+        assert_eq!(0, ctrl.complete_count); // The interrupt flag IS cleared
+    }
+
+    #[test]
+    fn cannot_write_when_dma_reader_passed_the_written_portion() {
+        let mut dma_buf = [0u8; 16];
+        let mut ctrl = TestCtrl::new();
+        let mut ringbuf = DmaRingBuffer::new(&mut dma_buf);
+        ringbuf.first = 6;
+        ringbuf.ndtr = 10;
+        ctrl.set_next_ndtr(8);
+
+        // The dma controller has read all 16 bytes, and 2 more
+        ctrl.complete_count = 1;
+
+        assert_eq!(Err(OverrunError), ringbuf.write(&mut ctrl, &[1, 2]));
+    }
+
     #[test]
     fn cannot_read_when_dma_writer_overwrites_during_wrapping_read() {
         let mut dma_buf: [u8; 16] = array::from_fn(|idx| idx as u8); // 0, 1, ..., 15
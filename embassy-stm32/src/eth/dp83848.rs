@@ -0,0 +1,86 @@
+//! Texas Instruments DP83848 Ethernet PHY
+
+use core::task::Context;
+
+use super::mii::*;
+use super::{Link, LinkInterrupt, LinkSpeed, NoInterrupt, StationManagement, PHY};
+
+const PHY_REG_PHYSTS: u8 = 0x10; // PHY Status Register
+const PHY_REG_MICR: u8 = 0x11; // MII Interrupt Control Register
+const PHY_REG_MISR: u8 = 0x12; // MII Interrupt Status and Misc. Control Register
+
+const PHY_REG_PHYSTS_SPEED_10: u16 = 1 << 1;
+const PHY_REG_PHYSTS_FULL_DUPLEX: u16 = 1 << 2;
+
+const PHY_REG_MICR_INT_OE: u16 = 1 << 0;
+const PHY_REG_MICR_INTEN: u16 = 1 << 1;
+
+const PHY_REG_MISR_ANC_INT_EN: u16 = 1 << 2;
+const PHY_REG_MISR_LINK_INT_EN: u16 = 1 << 5;
+
+/// Texas Instruments DP83848 Ethernet PHY
+///
+/// The PWR_DOWN/INT pin of the PHY is asserted when the link changes, it can be used as a
+/// [`LinkInterrupt`] instead of polling the link.
+pub struct Dp83848<I = NoInterrupt> {
+    phy_addr: u8,
+    interrupt: I,
+    link: Option<Link>,
+}
+
+impl Dp83848 {
+    /// Create a new DP83848, at address `phy_addr` on the SMI bus, and poll its link.
+    pub const fn new(phy_addr: u8) -> Self {
+        Self::with_interrupt(phy_addr, NoInterrupt::new())
+    }
+}
+
+impl<I: LinkInterrupt> Dp83848<I> {
+    /// Create a new DP83848, at address `phy_addr` on the SMI bus, with its PWR_DOWN/INT pin.
+    pub const fn with_interrupt(phy_addr: u8, interrupt: I) -> Self {
+        Self {
+            phy_addr,
+            interrupt,
+            link: None,
+        }
+    }
+}
+
+unsafe impl<I: LinkInterrupt> PHY for Dp83848<I> {
+    fn phy_reset<S: StationManagement>(&mut self, sm: &mut S) {
+        reset(sm, self.phy_addr);
+    }
+
+    fn phy_init<S: StationManagement>(&mut self, sm: &mut S) {
+        sm.smi_write(
+            self.phy_addr,
+            PHY_REG_MISR,
+            PHY_REG_MISR_ANC_INT_EN | PHY_REG_MISR_LINK_INT_EN,
+        );
+        sm.smi_write(self.phy_addr, PHY_REG_MICR, PHY_REG_MICR_INTEN | PHY_REG_MICR_INT_OE);
+        start_autonegotiation(sm, self.phy_addr);
+    }
+
+    fn poll_link<S: StationManagement>(&mut self, sm: &mut S, cx: &mut Context) -> Option<Link> {
+        let phy_addr = self.phy_addr;
+        poll_link(&mut self.interrupt, &mut self.link, cx, || {
+            // Reading the status clears the interrupt
+            sm.smi_read(phy_addr, PHY_REG_MISR);
+
+            if !link_up(sm, phy_addr) {
+                return None;
+            }
+
+            let physts = sm.smi_read(phy_addr, PHY_REG_PHYSTS);
+            let speed = if physts & PHY_REG_PHYSTS_SPEED_10 != 0 {
+                LinkSpeed::Mbps10
+            } else {
+                LinkSpeed::Mbps100
+            };
+            Some(Link {
+                speed,
+                full_duplex: physts & PHY_REG_PHYSTS_FULL_DUPLEX != 0,
+            })
+        })
+    }
+}
@@ -0,0 +1,83 @@
+//! Microchip KSZ8081 Ethernet PHY
+
+use core::task::Context;
+
+use super::mii::*;
+use super::{Link, LinkInterrupt, LinkSpeed, NoInterrupt, StationManagement, PHY};
+
+const PHY_REG_ICSR: u8 = 0x1B; // Interrupt Control/Status Register
+const PHY_REG_CTRL1: u8 = 0x1E; // PHY Control 1 Register
+
+const PHY_REG_ICSR_LINK_DOWN_EN: u16 = 1 << 10;
+const PHY_REG_ICSR_LINK_UP_EN: u16 = 1 << 8;
+
+const PHY_REG_CTRL1_MODE_MASK: u16 = 0b111;
+const PHY_REG_CTRL1_MODE_10HD: u16 = 0b001;
+const PHY_REG_CTRL1_MODE_100HD: u16 = 0b010;
+const PHY_REG_CTRL1_MODE_10FD: u16 = 0b101;
+const PHY_REG_CTRL1_MODE_100FD: u16 = 0b110;
+
+/// Microchip KSZ8081 Ethernet PHY
+///
+/// The INTRP pin of the PHY is asserted when the link goes up or down, it can be used as a
+/// [`LinkInterrupt`] instead of polling the link.
+pub struct Ksz8081<I = NoInterrupt> {
+    phy_addr: u8,
+    interrupt: I,
+    link: Option<Link>,
+}
+
+impl Ksz8081 {
+    /// Create a new KSZ8081, at address `phy_addr` on the SMI bus, and poll its link.
+    pub const fn new(phy_addr: u8) -> Self {
+        Self::with_interrupt(phy_addr, NoInterrupt::new())
+    }
+}
+
+impl<I: LinkInterrupt> Ksz8081<I> {
+    /// Create a new KSZ8081, at address `phy_addr` on the SMI bus, with its INTRP pin.
+    pub const fn with_interrupt(phy_addr: u8, interrupt: I) -> Self {
+        Self {
+            phy_addr,
+            interrupt,
+            link: None,
+        }
+    }
+}
+
+unsafe impl<I: LinkInterrupt> PHY for Ksz8081<I> {
+    fn phy_reset<S: StationManagement>(&mut self, sm: &mut S) {
+        reset(sm, self.phy_addr);
+    }
+
+    fn phy_init<S: StationManagement>(&mut self, sm: &mut S) {
+        sm.smi_write(
+            self.phy_addr,
+            PHY_REG_ICSR,
+            PHY_REG_ICSR_LINK_DOWN_EN | PHY_REG_ICSR_LINK_UP_EN,
+        );
+        start_autonegotiation(sm, self.phy_addr);
+    }
+
+    fn poll_link<S: StationManagement>(&mut self, sm: &mut S, cx: &mut Context) -> Option<Link> {
+        let phy_addr = self.phy_addr;
+        poll_link(&mut self.interrupt, &mut self.link, cx, || {
+            // Reading the status clears the interrupt
+            sm.smi_read(phy_addr, PHY_REG_ICSR);
+
+            if !link_up(sm, phy_addr) {
+                return None;
+            }
+
+            let (speed, full_duplex) = match sm.smi_read(phy_addr, PHY_REG_CTRL1) & PHY_REG_CTRL1_MODE_MASK {
+                PHY_REG_CTRL1_MODE_10HD => (LinkSpeed::Mbps10, false),
+                PHY_REG_CTRL1_MODE_100HD => (LinkSpeed::Mbps100, false),
+                PHY_REG_CTRL1_MODE_10FD => (LinkSpeed::Mbps10, true),
+                PHY_REG_CTRL1_MODE_100FD => (LinkSpeed::Mbps100, true),
+                // Still auto-negotiating
+                _ => return None,
+            };
+            Some(Link { speed, full_duplex })
+        })
+    }
+}
@@ -0,0 +1,85 @@
+//! Microchip LAN8742A Ethernet PHY
+
+use core::task::Context;
+
+use super::mii::*;
+use super::{Link, LinkInterrupt, LinkSpeed, NoInterrupt, StationManagement, PHY};
+
+const PHY_REG_ISFR: u8 = 29; // Interrupt Source Flag Register
+const PHY_REG_IMR: u8 = 30; // Interrupt Mask Register
+const PHY_REG_PSCSR: u8 = 31; // PHY Special Control/Status Register
+
+const PHY_REG_INT_AN_COMPLETE: u16 = 1 << 6;
+const PHY_REG_INT_LINK_DOWN: u16 = 1 << 4;
+
+const PHY_REG_PSCSR_SPEED_SHIFT: u16 = 2;
+const PHY_REG_PSCSR_SPEED_MASK: u16 = 0b111;
+const PHY_REG_PSCSR_SPEED_10HD: u16 = 0b001;
+const PHY_REG_PSCSR_SPEED_10FD: u16 = 0b101;
+const PHY_REG_PSCSR_SPEED_100HD: u16 = 0b010;
+const PHY_REG_PSCSR_SPEED_100FD: u16 = 0b110;
+
+/// Microchip LAN8742A Ethernet PHY
+///
+/// The nINT pin of the PHY is asserted when the link goes up or down, it can be used as a
+/// [`LinkInterrupt`] instead of polling the link.
+pub struct Lan8742<I = NoInterrupt> {
+    phy_addr: u8,
+    interrupt: I,
+    link: Option<Link>,
+}
+
+impl Lan8742 {
+    /// Create a new LAN8742A, at address `phy_addr` on the SMI bus, and poll its link.
+    pub const fn new(phy_addr: u8) -> Self {
+        Self::with_interrupt(phy_addr, NoInterrupt::new())
+    }
+}
+
+impl<I: LinkInterrupt> Lan8742<I> {
+    /// Create a new LAN8742A, at address `phy_addr` on the SMI bus, with its nINT pin.
+    pub const fn with_interrupt(phy_addr: u8, interrupt: I) -> Self {
+        Self {
+            phy_addr,
+            interrupt,
+            link: None,
+        }
+    }
+}
+
+unsafe impl<I: LinkInterrupt> PHY for Lan8742<I> {
+    fn phy_reset<S: StationManagement>(&mut self, sm: &mut S) {
+        reset(sm, self.phy_addr);
+    }
+
+    fn phy_init<S: StationManagement>(&mut self, sm: &mut S) {
+        sm.smi_write(
+            self.phy_addr,
+            PHY_REG_IMR,
+            PHY_REG_INT_AN_COMPLETE | PHY_REG_INT_LINK_DOWN,
+        );
+        start_autonegotiation(sm, self.phy_addr);
+    }
+
+    fn poll_link<S: StationManagement>(&mut self, sm: &mut S, cx: &mut Context) -> Option<Link> {
+        let phy_addr = self.phy_addr;
+        poll_link(&mut self.interrupt, &mut self.link, cx, || {
+            // Reading the flags clears the interrupt
+            sm.smi_read(phy_addr, PHY_REG_ISFR);
+
+            if !link_up(sm, phy_addr) {
+                return None;
+            }
+
+            let pscsr = sm.smi_read(phy_addr, PHY_REG_PSCSR);
+            let (speed, full_duplex) = match (pscsr >> PHY_REG_PSCSR_SPEED_SHIFT) & PHY_REG_PSCSR_SPEED_MASK {
+                PHY_REG_PSCSR_SPEED_10HD => (LinkSpeed::Mbps10, false),
+                PHY_REG_PSCSR_SPEED_10FD => (LinkSpeed::Mbps10, true),
+                PHY_REG_PSCSR_SPEED_100HD => (LinkSpeed::Mbps100, false),
+                PHY_REG_PSCSR_SPEED_100FD => (LinkSpeed::Mbps100, true),
+                _ => return None,
+            };
+            Some(Link { speed, full_duplex })
+        })
+    }
+}
@@ -0,0 +1,104 @@
+//! Standard registers of the Ethernet PHYs, shared by the PHY drivers.
+
+use core::task::Context;
+
+use super::{Link, LinkInterrupt, LinkSpeed, StationManagement};
+
+#[allow(dead_code)]
+mod phy_consts {
+    pub const PHY_REG_BCR: u8 = 0x00;
+    pub const PHY_REG_BSR: u8 = 0x01;
+    pub const PHY_REG_ID1: u8 = 0x02;
+    pub const PHY_REG_ID2: u8 = 0x03;
+    pub const PHY_REG_ANTX: u8 = 0x04;
+    pub const PHY_REG_ANRX: u8 = 0x05;
+    pub const PHY_REG_ANEXP: u8 = 0x06;
+    pub const PHY_REG_ANNPTX: u8 = 0x07;
+    pub const PHY_REG_ANNPRX: u8 = 0x08;
+    pub const PHY_REG_CTL: u8 = 0x0D; // Ethernet PHY Register Control
+    pub const PHY_REG_ADDAR: u8 = 0x0E; // Ethernet PHY Address or Data
+
+    pub const PHY_REG_BCR_COLTEST: u16 = 1 << 7;
+    pub const PHY_REG_BCR_FD: u16 = 1 << 8;
+    pub const PHY_REG_BCR_ANRST: u16 = 1 << 9;
+    pub const PHY_REG_BCR_ISOLATE: u16 = 1 << 10;
+    pub const PHY_REG_BCR_POWERDN: u16 = 1 << 11;
+    pub const PHY_REG_BCR_AN: u16 = 1 << 12;
+    pub const PHY_REG_BCR_100M: u16 = 1 << 13;
+    pub const PHY_REG_BCR_LOOPBACK: u16 = 1 << 14;
+    pub const PHY_REG_BCR_RESET: u16 = 1 << 15;
+
+    pub const PHY_REG_BSR_JABBER: u16 = 1 << 1;
+    pub const PHY_REG_BSR_UP: u16 = 1 << 2;
+    pub const PHY_REG_BSR_FAULT: u16 = 1 << 4;
+    pub const PHY_REG_BSR_ANDONE: u16 = 1 << 5;
+
+    pub const PHY_REG_AN_10HD: u16 = 1 << 5;
+    pub const PHY_REG_AN_10FD: u16 = 1 << 6;
+    pub const PHY_REG_AN_100HD: u16 = 1 << 7;
+    pub const PHY_REG_AN_100FD: u16 = 1 << 8;
+}
+pub(crate) use self::phy_consts::*;
+
+/// Reset the PHY and wait for it to come out of reset.
+pub(crate) fn reset<S: StationManagement>(sm: &mut S, phy_addr: u8) {
+    sm.smi_write(phy_addr, PHY_REG_BCR, PHY_REG_BCR_RESET);
+    while sm.smi_read(phy_addr, PHY_REG_BCR) & PHY_REG_BCR_RESET == PHY_REG_BCR_RESET {}
+}
+
+/// Enable and restart the auto-negotiation.
+pub(crate) fn start_autonegotiation<S: StationManagement>(sm: &mut S, phy_addr: u8) {
+    sm.smi_write(
+        phy_addr,
+        PHY_REG_BCR,
+        PHY_REG_BCR_AN | PHY_REG_BCR_ANRST | PHY_REG_BCR_100M,
+    );
+}
+
+/// Returns whether the link is up, and auto-negotiation is done.
+pub(crate) fn link_up<S: StationManagement>(sm: &mut S, phy_addr: u8) -> bool {
+    let bsr = sm.smi_read(phy_addr, PHY_REG_BSR);
+
+    // No link without autonegotiate
+    if bsr & PHY_REG_BSR_ANDONE == 0 {
+        return false;
+    }
+    // No link if link is down
+    bsr & PHY_REG_BSR_UP != 0
+}
+
+/// Returns the best mode advertised by both the PHY and its link partner.
+pub(crate) fn negotiated_link<S: StationManagement>(sm: &mut S, phy_addr: u8) -> Link {
+    let common = sm.smi_read(phy_addr, PHY_REG_ANTX) & sm.smi_read(phy_addr, PHY_REG_ANRX);
+
+    let (speed, full_duplex) = if common & PHY_REG_AN_100FD != 0 {
+        (LinkSpeed::Mbps100, true)
+    } else if common & PHY_REG_AN_100HD != 0 {
+        (LinkSpeed::Mbps100, false)
+    } else if common & PHY_REG_AN_10FD != 0 {
+        (LinkSpeed::Mbps10, true)
+    } else {
+        (LinkSpeed::Mbps10, false)
+    };
+    Link { speed, full_duplex }
+}
+
+/// Poll the link of a PHY, which is read again with `read_link` when `interrupt` is asserted.
+///
+/// `read_link` must also clear the interrupt of the PHY.
+pub(crate) fn poll_link<I: LinkInterrupt>(
+    interrupt: &mut I,
+    link: &mut Option<Link>,
+    cx: &mut Context,
+    read_link: impl FnOnce() -> Option<Link>,
+) -> Option<Link> {
+    if interrupt.poll_asserted(cx).is_ready() {
+        *link = read_link();
+
+        // Wait for the next interrupt, or poll again if it's still asserted.
+        if interrupt.poll_asserted(cx).is_ready() {
+            cx.waker().wake_by_ref();
+        }
+    }
+    *link
+}
@@ -3,10 +3,16 @@
 #[cfg_attr(any(eth_v1a, eth_v1b, eth_v1c), path = "v1/mod.rs")]
 #[cfg_attr(eth_v2, path = "v2/mod.rs")]
 mod _version;
+pub mod dp83848;
 pub mod generic_smi;
+pub mod ksz8081;
+pub mod lan8742;
+mod mii;
+#[cfg(not(eth_v1a))]
+pub mod ptp;
 
 use core::mem::MaybeUninit;
-use core::task::Context;
+use core::task::{Context, Poll};
 
 use embassy_net_driver::{Capabilities, LinkState};
 use embassy_sync::waitqueue::AtomicWaker;
@@ -81,12 +87,18 @@ impl<'d, T: Instance, P: PHY> embassy_net_driver::Driver for Ethernet<'d, T, P>
     }
 
     fn link_state(&mut self, cx: &mut Context) -> LinkState {
-        // TODO: wake cx.waker on link state change
-        cx.waker().wake_by_ref();
-        if P::poll_link(self) {
-            LinkState::Up
-        } else {
-            LinkState::Down
+        match self.phy.poll_link(&mut self.station_management, cx) {
+            Some(link) => {
+                if self.link != Some(link) {
+                    self.link = Some(link);
+                    self.configure_link(link);
+                }
+                LinkState::Up
+            }
+            None => {
+                self.link = None;
+                LinkState::Down
+            }
         }
     }
 
@@ -95,6 +107,31 @@ impl<'d, T: Instance, P: PHY> embassy_net_driver::Driver for Ethernet<'d, T, P>
     }
 }
 
+impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
+    /// Returns the Station Management Interface, to access the registers of the PHYs.
+    pub fn station_management(&mut self) -> &mut EthernetStationManagement<T> {
+        &mut self.station_management
+    }
+
+    /// Returns the PHY.
+    pub fn phy_mut(&mut self) -> &mut P {
+        &mut self.phy
+    }
+}
+
+#[cfg(not(eth_v1a))]
+impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
+    /// Returns the PTP clock, used to timestamp the PTP messages.
+    ///
+    /// The clock can be used while the driver is owned by the network stack.
+    pub fn ptp_clock(&self) -> ptp::PtpClock<'d> {
+        // NOTE(unsafe) We got the peripheral singleton, which means that `rcc::init` was called
+        let hclk = unsafe { crate::rcc::get_freqs().ahb1 };
+        let (_, addend) = ptp::clock_increment(hclk.0);
+        ptp::PtpClock::new(addend)
+    }
+}
+
 pub struct RxToken<'a, 'd> {
     rx: &'a mut RDesRing<'d>,
 }
@@ -104,8 +141,16 @@ impl<'a, 'd> embassy_net_driver::RxToken for RxToken<'a, 'd> {
     where
         F: FnOnce(&mut [u8]) -> R,
     {
+        // The timestamp must be read before the packet is borrowed.
+        #[cfg(not(eth_v1a))]
+        let timestamp = self.rx.timestamp();
+
         // NOTE(unwrap): we checked the queue wasn't full when creating the token.
         let pkt = unwrap!(self.rx.available());
+        #[cfg(not(eth_v1a))]
+        if let Some(timestamp) = timestamp {
+            ptp::record_rx_timestamp(pkt, timestamp);
+        }
         let r = f(pkt);
         self.rx.pop_packet();
         r
@@ -124,21 +169,45 @@ impl<'a, 'd> embassy_net_driver::TxToken for TxToken<'a, 'd> {
         // NOTE(unwrap): we checked the queue wasn't full when creating the token.
         let pkt = unwrap!(self.tx.available());
         let r = f(&mut pkt[..len]);
+        #[cfg(not(eth_v1a))]
+        if let Some(id) = ptp::MessageId::parse(&pkt[..len]) {
+            self.tx.request_timestamp(id);
+        }
         self.tx.transmit(len);
         r
     }
 }
 
-/// Station Management Interface (SMI) on an ethernet PHY
+/// Station Management Interface (SMI), the MDIO bus connecting the PHYs.
 ///
 /// # Safety
 ///
 /// The methods cannot move out of self
 pub unsafe trait StationManagement {
-    /// Read a register over SMI.
-    fn smi_read(&mut self, reg: u8) -> u16;
-    /// Write a register over SMI.
-    fn smi_write(&mut self, reg: u8, val: u16);
+    /// Read a register of the PHY at `phy_addr` over SMI.
+    fn smi_read(&mut self, phy_addr: u8, reg: u8) -> u16;
+    /// Write a register of the PHY at `phy_addr` over SMI.
+    fn smi_write(&mut self, phy_addr: u8, reg: u8, val: u16);
+}
+
+/// Speed of an Ethernet link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LinkSpeed {
+    /// 10 Mbit/s
+    Mbps10,
+    /// 100 Mbit/s
+    Mbps100,
+}
+
+/// Speed and duplex mode of an established Ethernet link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Link {
+    /// Link speed.
+    pub speed: LinkSpeed,
+    /// Whether the link is full duplex.
+    pub full_duplex: bool,
 }
 
 /// Traits for an Ethernet PHY
@@ -148,11 +217,64 @@ pub unsafe trait StationManagement {
 /// The methods cannot move S
 pub unsafe trait PHY {
     /// Reset PHY and wait for it to come out of reset.
-    fn phy_reset<S: StationManagement>(sm: &mut S);
+    fn phy_reset<S: StationManagement>(&mut self, sm: &mut S);
     /// PHY initialisation.
-    fn phy_init<S: StationManagement>(sm: &mut S);
-    /// Poll link to see if it is up and FD with 100Mbps
-    fn poll_link<S: StationManagement>(sm: &mut S) -> bool;
+    fn phy_init<S: StationManagement>(&mut self, sm: &mut S);
+    /// Poll link to see if it is up, and with which speed and duplex mode.
+    ///
+    /// `cx` is woken when the link should be polled again.
+    fn poll_link<S: StationManagement>(&mut self, sm: &mut S, cx: &mut Context) -> Option<Link>;
+}
+
+/// Interrupt output of a PHY, to be notified of the link changes instead of polling the PHY.
+///
+/// This is implemented by [`ExtiInput`](crate::exti::ExtiInput), for the interrupt pin of the
+/// PHY.
+pub trait LinkInterrupt {
+    /// Poll whether the interrupt is asserted, and register `cx` to be woken when it is.
+    fn poll_asserted(&mut self, cx: &mut Context) -> Poll<()>;
+}
+
+/// No PHY interrupt, the link is polled.
+///
+/// With the `time` feature, the link is polled every 500 ms, otherwise it's polled continuously.
+#[derive(Default)]
+pub struct NoInterrupt {
+    #[cfg(feature = "time")]
+    timer: Option<embassy_time::Timer>,
+}
+
+impl NoInterrupt {
+    /// Create a new `NoInterrupt`.
+    pub const fn new() -> Self {
+        Self {
+            #[cfg(feature = "time")]
+            timer: None,
+        }
+    }
+}
+
+impl LinkInterrupt for NoInterrupt {
+    #[cfg(feature = "time")]
+    fn poll_asserted(&mut self, cx: &mut Context) -> Poll<()> {
+        use core::future::Future;
+        use core::pin::Pin;
+
+        let timer = self
+            .timer
+            .get_or_insert_with(|| embassy_time::Timer::after(embassy_time::Duration::from_millis(500)));
+        if Pin::new(timer).poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        self.timer = None;
+        Poll::Ready(())
+    }
+
+    #[cfg(not(feature = "time"))]
+    fn poll_asserted(&mut self, cx: &mut Context) -> Poll<()> {
+        cx.waker().wake_by_ref();
+        Poll::Ready(())
+    }
 }
 
 pub(crate) mod sealed {
@@ -0,0 +1,334 @@
+//! IEEE 1588 Precision Time Protocol (PTP) support.
+//!
+//! The MAC timestamps the PTP event messages (Sync, Delay_Req, Pdelay_Req and Pdelay_Resp) it
+//! sends and receives, over Ethernet, UDP/IPv4 or UDP/IPv6. The timestamps are kept by the driver
+//! until the application fetches them with [`PtpClock::rx_timestamp`] and
+//! [`PtpClock::tx_timestamp`], identified by the type and sequence ID of the message.
+//!
+//! The [`PtpClock`] also gives access to the hardware clock used for the timestamps, so the
+//! application can synchronize it with a master clock.
+
+use core::cell::RefCell;
+use core::marker::PhantomData;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+
+use super::_version;
+
+const NANOS_PER_SECOND: u32 = 1_000_000_000;
+
+/// Number of timestamps kept for each direction, the oldest ones are dropped first.
+const TIMESTAMP_QUEUE_LEN: usize = 4;
+
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_PTP: u16 = 0x88f7;
+const IP_PROTOCOL_UDP: u8 = 17;
+const PTP_EVENT_PORT: u16 = 319;
+
+/// A time of the PTP clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timestamp {
+    /// Seconds.
+    pub seconds: u32,
+    /// Nanoseconds, always less than 1 000 000 000.
+    pub nanoseconds: u32,
+}
+
+impl Timestamp {
+    /// Create a timestamp from a number of nanoseconds.
+    pub const fn from_nanos(nanos: u64) -> Self {
+        Self {
+            seconds: (nanos / NANOS_PER_SECOND as u64) as u32,
+            nanoseconds: (nanos % NANOS_PER_SECOND as u64) as u32,
+        }
+    }
+
+    /// Returns the timestamp as a number of nanoseconds.
+    pub const fn as_nanos(&self) -> u64 {
+        self.seconds as u64 * NANOS_PER_SECOND as u64 + self.nanoseconds as u64
+    }
+}
+
+/// Identifies a PTP event message, to retrieve its timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MessageId {
+    /// The `messageType` of the message: 0 for Sync, 1 for Delay_Req, 2 for Pdelay_Req and 3 for
+    /// Pdelay_Resp.
+    pub message_type: u8,
+    /// The `sequenceId` of the message.
+    pub sequence_id: u16,
+}
+
+impl MessageId {
+    /// Returns the ID of the PTP event message in an Ethernet frame, if any.
+    pub(crate) fn parse(frame: &[u8]) -> Option<Self> {
+        let mut ethertype = read_u16(frame, 12)?;
+        let mut offset = 14;
+        if ethertype == ETHERTYPE_VLAN {
+            ethertype = read_u16(frame, 16)?;
+            offset += 4;
+        }
+
+        let ptp = match ethertype {
+            ETHERTYPE_PTP => frame.get(offset..)?,
+            ETHERTYPE_IPV4 => {
+                let ip = frame.get(offset..)?;
+                let header_len = ((*ip.first()? & 0x0f) as usize) * 4;
+                // Fragments other than the first one don't have a UDP header
+                let fragment_offset = read_u16(ip, 6)? & 0x1fff;
+                if *ip.get(9)? != IP_PROTOCOL_UDP || fragment_offset != 0 {
+                    return None;
+                }
+                udp_payload(ip.get(header_len..)?)?
+            }
+            ETHERTYPE_IPV6 => {
+                let ip = frame.get(offset..)?;
+                if *ip.get(6)? != IP_PROTOCOL_UDP {
+                    return None;
+                }
+                udp_payload(ip.get(40..)?)?
+            }
+            _ => return None,
+        };
+
+        // Only the event messages are timestamped.
+        let message_type = *ptp.first()? & 0x0f;
+        if message_type > 3 {
+            return None;
+        }
+        Some(Self {
+            message_type,
+            sequence_id: read_u16(ptp, 30)?,
+        })
+    }
+}
+
+/// Returns the payload of an UDP datagram sent to the PTP event port.
+fn udp_payload(udp: &[u8]) -> Option<&[u8]> {
+    if read_u16(udp, 2)? != PTP_EVENT_PORT {
+        return None;
+    }
+    udp.get(8..)
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> Option<u16> {
+    let bytes = buf.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+struct TimestampQueue {
+    entries: [Option<(MessageId, Timestamp)>; TIMESTAMP_QUEUE_LEN],
+    next: usize,
+}
+
+impl TimestampQueue {
+    const fn new() -> Self {
+        Self {
+            entries: [None; TIMESTAMP_QUEUE_LEN],
+            next: 0,
+        }
+    }
+
+    fn push(&mut self, id: MessageId, timestamp: Timestamp) {
+        self.entries[self.next] = Some((id, timestamp));
+        self.next = (self.next + 1) % TIMESTAMP_QUEUE_LEN;
+    }
+
+    fn take(&mut self, id: MessageId) -> Option<Timestamp> {
+        self.entries.iter_mut().find_map(|entry| match *entry {
+            Some((i, timestamp)) if i == id => {
+                *entry = None;
+                Some(timestamp)
+            }
+            _ => None,
+        })
+    }
+}
+
+static RX_TIMESTAMPS: Mutex<CriticalSectionRawMutex, RefCell<TimestampQueue>> =
+    Mutex::new(RefCell::new(TimestampQueue::new()));
+static TX_TIMESTAMPS: Mutex<CriticalSectionRawMutex, RefCell<TimestampQueue>> =
+    Mutex::new(RefCell::new(TimestampQueue::new()));
+
+/// Keep the timestamp of a received frame, if it's a PTP event message.
+pub(crate) fn record_rx_timestamp(frame: &[u8], timestamp: Timestamp) {
+    if let Some(id) = MessageId::parse(frame) {
+        RX_TIMESTAMPS.lock(|q| q.borrow_mut().push(id, timestamp));
+    }
+}
+
+/// Keep the timestamp of a sent PTP event message.
+pub(crate) fn record_tx_timestamp(id: MessageId, timestamp: Timestamp) {
+    TX_TIMESTAMPS.lock(|q| q.borrow_mut().push(id, timestamp));
+}
+
+/// Computes the sub-second increment and the addend for the PTP clock, driven by HCLK.
+///
+/// The addend is added to an accumulator at each HCLK cycle, and the clock is increased by the
+/// sub-second increment, in nanoseconds, when the accumulator overflows. The increment is
+/// chosen so that the accumulator overflows about every other cycle, which leaves room to
+/// speed up the clock.
+pub(crate) fn clock_increment(hclk: u32) -> (u8, u32) {
+    let increment = (2 * NANOS_PER_SECOND + hclk - 1) / hclk;
+    assert!(increment <= u8::MAX as u32);
+    let addend = ((NANOS_PER_SECOND as u64) << 32) / (increment as u64 * hclk as u64);
+    (increment as u8, addend as u32)
+}
+
+/// The PTP hardware clock.
+///
+/// The clock starts at zero when the Ethernet driver is created, and runs at the nominal rate.
+pub struct PtpClock<'d> {
+    addend: u32,
+    _phantom: PhantomData<&'d ()>,
+}
+
+impl<'d> PtpClock<'d> {
+    pub(crate) fn new(addend: u32) -> Self {
+        Self {
+            addend,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the current time of the clock.
+    pub fn now(&self) -> Timestamp {
+        _version::ptp_now()
+    }
+
+    /// Set the time of the clock.
+    pub fn set_time(&mut self, time: Timestamp) {
+        assert!(time.nanoseconds < NANOS_PER_SECOND);
+        _version::ptp_set_time(time);
+    }
+
+    /// Move the clock forward, or backward if `offset` is negative, by `offset` nanoseconds.
+    pub fn adjust_time(&mut self, offset: i64) {
+        let offset_abs = Timestamp::from_nanos(offset.unsigned_abs());
+        _version::ptp_add_time(offset_abs, offset < 0);
+    }
+
+    /// Set the rate of the clock, `ppb` parts per billion faster than the nominal rate, or slower
+    /// if negative.
+    ///
+    /// The rate can be adjusted up to about +/- 10%.
+    pub fn adjust_rate(&mut self, ppb: i32) {
+        let ppb = ppb.clamp(-100_000_000, 100_000_000) as i64;
+        let addend = self.addend as i64 + self.addend as i64 * ppb / NANOS_PER_SECOND as i64;
+        _version::ptp_set_addend(addend as u32);
+    }
+
+    /// Returns the time at which the PTP event message `id` was received.
+    ///
+    /// The timestamp is returned only once. The driver keeps only the most recent timestamps, so
+    /// it should be retrieved soon after the message is received.
+    pub fn rx_timestamp(&self, id: MessageId) -> Option<Timestamp> {
+        RX_TIMESTAMPS.lock(|q| q.borrow_mut().take(id))
+    }
+
+    /// Returns the time at which the PTP event message `id` was sent.
+    ///
+    /// The timestamp is available shortly after the message is sent, and is returned only once.
+    pub fn tx_timestamp(&self, id: MessageId) -> Option<Timestamp> {
+        TX_TIMESTAMPS.lock(|q| q.borrow_mut().take(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ptp_message(message_type: u8, sequence_id: u16) -> [u8; 34] {
+        let mut msg = [0; 34];
+        msg[0] = 0x10 | message_type;
+        msg[1] = 2;
+        msg[30..32].copy_from_slice(&sequence_id.to_be_bytes());
+        msg
+    }
+
+    fn ethernet_frame(ethertype: u16, payload: &[u8]) -> ([u8; 128], usize) {
+        let mut frame = [0; 128];
+        frame[12..14].copy_from_slice(&ethertype.to_be_bytes());
+        frame[14..14 + payload.len()].copy_from_slice(payload);
+        (frame, 14 + payload.len())
+    }
+
+    fn udp_ipv4(dst_port: u16, payload: &[u8]) -> ([u8; 96], usize) {
+        let mut ip = [0; 96];
+        ip[0] = 0x45;
+        ip[9] = IP_PROTOCOL_UDP;
+        ip[22..24].copy_from_slice(&dst_port.to_be_bytes());
+        ip[28..28 + payload.len()].copy_from_slice(payload);
+        (ip, 28 + payload.len())
+    }
+
+    #[test]
+    fn parses_ethernet_message() {
+        let (frame, len) = ethernet_frame(ETHERTYPE_PTP, &ptp_message(1, 0x1234));
+        let id = MessageId::parse(&frame[..len]);
+        assert_eq!(
+            id,
+            Some(MessageId {
+                message_type: 1,
+                sequence_id: 0x1234
+            })
+        );
+    }
+
+    #[test]
+    fn parses_udp_message() {
+        let (ip, ip_len) = udp_ipv4(PTP_EVENT_PORT, &ptp_message(0, 7));
+        let (frame, len) = ethernet_frame(ETHERTYPE_IPV4, &ip[..ip_len]);
+        let id = MessageId::parse(&frame[..len]);
+        assert_eq!(
+            id,
+            Some(MessageId {
+                message_type: 0,
+                sequence_id: 7
+            })
+        );
+    }
+
+    #[test]
+    fn ignores_general_messages() {
+        let (ip, ip_len) = udp_ipv4(320, &ptp_message(8, 7));
+        let (frame, len) = ethernet_frame(ETHERTYPE_IPV4, &ip[..ip_len]);
+        assert_eq!(MessageId::parse(&frame[..len]), None);
+
+        let (frame, len) = ethernet_frame(ETHERTYPE_PTP, &ptp_message(8, 7));
+        assert_eq!(MessageId::parse(&frame[..len]), None);
+    }
+
+    #[test]
+    fn ignores_truncated_frames() {
+        let (frame, len) = ethernet_frame(ETHERTYPE_PTP, &ptp_message(0, 7));
+        assert_eq!(MessageId::parse(&frame[..len - 3]), None);
+        assert_eq!(MessageId::parse(&frame[..10]), None);
+    }
+
+    #[test]
+    fn queue_returns_timestamp_once() {
+        let id = MessageId {
+            message_type: 0,
+            sequence_id: 1,
+        };
+        let mut queue = TimestampQueue::new();
+        queue.push(id, Timestamp::from_nanos(1_500_000_000));
+        assert_eq!(queue.take(id), Some(Timestamp::from_nanos(1_500_000_000)));
+        assert_eq!(queue.take(id), None);
+    }
+
+    #[test]
+    fn clock_increment_for_168mhz() {
+        let (increment, addend) = clock_increment(168_000_000);
+        assert_eq!(increment, 12);
+        // The accumulator overflows at 1e9 / 12 Hz.
+        let rate = addend as u64 * 168_000_000 >> 32;
+        assert_eq!(rate, 83_333_333);
+    }
+}
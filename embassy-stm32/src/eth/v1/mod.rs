@@ -3,14 +3,19 @@
 mod rx_desc;
 mod tx_desc;
 
+use core::marker::PhantomData;
 use core::sync::atomic::{fence, Ordering};
 
 use embassy_cortex_m::interrupt::{Interrupt, InterruptExt};
 use embassy_hal_common::{into_ref, PeripheralRef};
-use stm32_metapac::eth::vals::{Apcs, Cr, Dm, DmaomrSr, Fes, Ftf, Ifg, MbProgress, Mw, Pbl, Rsf, St, Tsf};
+#[cfg(not(eth_v1a))]
+use stm32_metapac::eth::vals::Edfe;
+use stm32_metapac::eth::vals::{Apcs, Cr, Dm, DmaomrSr, Fes, Ftf, Ifg, MbProgress, Mw, Pam, Pbl, Rsf, St, Tsf};
 
 pub(crate) use self::rx_desc::{RDes, RDesRing};
 pub(crate) use self::tx_desc::{TDes, TDesRing};
+#[cfg(not(eth_v1a))]
+use super::ptp::{self, Timestamp};
 use super::*;
 use crate::gpio::sealed::{AFType, Pin as __GpioPin};
 use crate::gpio::AnyPin;
@@ -50,9 +55,9 @@ pub struct Ethernet<'d, T: Instance, P: PHY> {
     pub(crate) rx: RDesRing<'d>,
 
     pins: [PeripheralRef<'d, AnyPin>; 9],
-    _phy: P,
-    clock_range: Cr,
-    phy_addr: u8,
+    pub(crate) phy: P,
+    pub(crate) station_management: EthernetStationManagement<T>,
+    pub(crate) link: Option<Link>,
     pub(crate) mac_addr: [u8; 6],
 }
 
@@ -112,7 +117,6 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
         tx_en: impl Peripheral<P = impl TXEnPin<T>> + 'd,
         phy: P,
         mac_addr: [u8; 6],
-        phy_addr: u8,
     ) -> Self {
         into_ref!(peri, ref_clk, mdio, mdc, crs, rx_d0, rx_d1, tx_d0, tx_d1, tx_en);
 
@@ -188,6 +192,9 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
             // pause time
             mac.macfcr().modify(|w| w.set_pt(0x100));
 
+            // pass all multicast frames, the network stack filters the groups it joined
+            mac.macffr().modify(|w| w.set_pam(Pam::ENABLED));
+
             // Transfer and Forward, Receive and Forward
             dma.dmaomr().modify(|w| {
                 w.set_tsf(Tsf::STOREFORWARD);
@@ -195,7 +202,9 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
             });
 
             dma.dmabmr().modify(|w| {
-                w.set_pbl(Pbl::PBL32) // programmable burst length - 32 ?
+                w.set_pbl(Pbl::PBL32); // programmable burst length - 32 ?
+                #[cfg(not(eth_v1a))]
+                w.set_edfe(Edfe::ENABLED); // enhanced descriptors, to get the timestamps
             });
 
             // TODO MTU size setting not found for v1 ethernet, check if correct
@@ -217,6 +226,9 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
                 }
             };
 
+            #[cfg(not(eth_v1a))]
+            ptp_init(hclk.0);
+
             let pins = [
                 ref_clk.map_into(),
                 mdio.map_into(),
@@ -232,9 +244,12 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
             let mut this = Self {
                 _peri: peri,
                 pins,
-                _phy: phy,
-                clock_range,
-                phy_addr,
+                phy,
+                station_management: EthernetStationManagement {
+                    peri: PhantomData,
+                    clock_range,
+                },
+                link: None,
                 mac_addr,
                 tx: TDesRing::new(&mut queue.tx_desc, &mut queue.tx_buf),
                 rx: RDesRing::new(&mut queue.rx_desc, &mut queue.rx_buf),
@@ -264,8 +279,8 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
                 w.set_tie(true);
             });
 
-            P::phy_reset(&mut this);
-            P::phy_init(&mut this);
+            this.phy.phy_reset(&mut this.station_management);
+            this.phy.phy_init(&mut this.station_management);
 
             interrupt::ETH::steal().unpend();
             interrupt::ETH::steal().enable();
@@ -275,14 +290,40 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
     }
 }
 
-unsafe impl<'d, T: Instance, P: PHY> StationManagement for Ethernet<'d, T, P> {
-    fn smi_read(&mut self, reg: u8) -> u16 {
+impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
+    /// Configure the MAC for the speed and duplex mode of the link.
+    pub(crate) fn configure_link(&mut self, link: Link) {
+        // NOTE(unsafe) We have `&mut self` and the interrupt doesn't use this registers
+        unsafe {
+            ETH.ethernet_mac().maccr().modify(|w| {
+                w.set_fes(match link.speed {
+                    LinkSpeed::Mbps10 => Fes::FES10,
+                    LinkSpeed::Mbps100 => Fes::FES100,
+                });
+                w.set_dm(if link.full_duplex {
+                    Dm::FULLDUPLEX
+                } else {
+                    Dm::HALFDUPLEX
+                });
+            });
+        }
+    }
+}
+
+/// Station Management Interface (SMI) of the Ethernet peripheral.
+pub struct EthernetStationManagement<T: Instance> {
+    peri: PhantomData<T>,
+    clock_range: Cr,
+}
+
+unsafe impl<T: Instance> StationManagement for EthernetStationManagement<T> {
+    fn smi_read(&mut self, phy_addr: u8, reg: u8) -> u16 {
         // NOTE(unsafe) These registers aren't used in the interrupt and we have `&mut self`
         unsafe {
             let mac = ETH.ethernet_mac();
 
             mac.macmiiar().modify(|w| {
-                w.set_pa(self.phy_addr);
+                w.set_pa(phy_addr);
                 w.set_mr(reg);
                 w.set_mw(Mw::READ); // read operation
                 w.set_cr(self.clock_range);
@@ -293,14 +334,14 @@ unsafe impl<'d, T: Instance, P: PHY> StationManagement for Ethernet<'d, T, P> {
         }
     }
 
-    fn smi_write(&mut self, reg: u8, val: u16) {
+    fn smi_write(&mut self, phy_addr: u8, reg: u8, val: u16) {
         // NOTE(unsafe) These registers aren't used in the interrupt and we have `&mut self`
         unsafe {
             let mac = ETH.ethernet_mac();
 
             mac.macmiidr().write(|w| w.set_md(val));
             mac.macmiiar().modify(|w| {
-                w.set_pa(self.phy_addr);
+                w.set_pa(phy_addr);
                 w.set_mr(reg);
                 w.set_mw(Mw::WRITE); // write
                 w.set_cr(self.clock_range);
@@ -338,3 +379,82 @@ impl<'d, T: Instance, P: PHY> Drop for Ethernet<'d, T, P> {
         })
     }
 }
+
+/// Start the PTP clock from zero, and enable the timestamping of the PTP messages.
+#[cfg(not(eth_v1a))]
+unsafe fn ptp_init(hclk: u32) {
+    let ptp = ETH.ethernet_ptp();
+    let (increment, addend) = ptp::clock_increment(hclk);
+
+    ptp.ptptscr().write(|w| {
+        w.set_tse(true);
+        w.set_tsssr(true); // the sub-seconds are nanoseconds
+        w.set_tsptppsv2e(true);
+        w.set_tssptpoefe(true); // PTP over Ethernet
+        w.set_tssipv4fe(true); // PTP over UDP/IPv4
+        w.set_tssipv6fe(true); // PTP over UDP/IPv6
+        w.set_tscnt(0b01); // event and general messages, sent and received
+    });
+    ptp.ptpssir().write(|w| w.set_stssi(increment));
+    ptp_set_addend(addend);
+    ptp.ptptscr().modify(|w| w.set_tsfcu(true)); // fine update
+
+    ptp_set_time(Timestamp::default());
+}
+
+#[cfg(not(eth_v1a))]
+pub(crate) fn ptp_now() -> Timestamp {
+    // NOTE(unsafe) Read-only registers
+    unsafe {
+        let ptp = ETH.ethernet_ptp();
+        loop {
+            let seconds = ptp.ptptshr().read().sts();
+            let nanoseconds = ptp.ptptslr().read().stss();
+            // The nanoseconds may have rolled over between the reads
+            if ptp.ptptshr().read().sts() == seconds {
+                return Timestamp { seconds, nanoseconds };
+            }
+        }
+    }
+}
+
+#[cfg(not(eth_v1a))]
+pub(crate) fn ptp_set_time(time: Timestamp) {
+    // NOTE(unsafe) Exclusive access to the registers
+    critical_section::with(|_| unsafe {
+        let ptp = ETH.ethernet_ptp();
+
+        ptp.ptptshur().write(|w| w.set_tsus(time.seconds));
+        ptp.ptptslur().write(|w| w.set_tsuss(time.nanoseconds));
+        ptp.ptptscr().modify(|w| w.set_tssti(true));
+        while ptp.ptptscr().read().tssti() {}
+    })
+}
+
+#[cfg(not(eth_v1a))]
+pub(crate) fn ptp_add_time(offset: Timestamp, negative: bool) {
+    // NOTE(unsafe) Exclusive access to the registers
+    critical_section::with(|_| unsafe {
+        let ptp = ETH.ethernet_ptp();
+
+        ptp.ptptshur().write(|w| w.set_tsus(offset.seconds));
+        ptp.ptptslur().write(|w| {
+            w.set_tsuss(offset.nanoseconds);
+            w.set_tsupns(negative); // subtract
+        });
+        ptp.ptptscr().modify(|w| w.set_tsstu(true));
+        while ptp.ptptscr().read().tsstu() {}
+    })
+}
+
+#[cfg(not(eth_v1a))]
+pub(crate) fn ptp_set_addend(addend: u32) {
+    // NOTE(unsafe) Exclusive access to the registers
+    critical_section::with(|_| unsafe {
+        let ptp = ETH.ethernet_ptp();
+
+        ptp.ptptsar().write(|w| w.set_tsa(addend));
+        ptp.ptptscr().modify(|w| w.set_ttsaru(true));
+        while ptp.ptptscr().read().ttsaru() {}
+    })
+}
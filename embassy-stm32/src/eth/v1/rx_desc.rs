@@ -3,6 +3,8 @@ use core::sync::atomic::{compiler_fence, fence, Ordering};
 use stm32_metapac::eth::vals::{Rpd, Rps};
 use vcell::VolatileCell;
 
+#[cfg(not(eth_v1a))]
+use crate::eth::ptp::Timestamp;
 use crate::eth::RX_BUFFER_SIZE;
 use crate::pac::ETH;
 
@@ -15,6 +17,9 @@ mod rx_consts {
     pub const RXDESC_0_LS: u32 = 1 << 8;
     /// Error summary
     pub const RXDESC_0_ES: u32 = 1 << 15;
+    /// Timestamp valid, with enhanced descriptors
+    #[cfg(not(eth_v1a))]
+    pub const RXDESC_0_TSV: u32 = 1 << 7;
     /// Frame length
     pub const RXDESC_0_FL_MASK: u32 = 0x3FFF;
     pub const RXDESC_0_FL_SHIFT: usize = 16;
@@ -36,12 +41,20 @@ use super::Packet;
 /// * rdes1: allocated buffer length
 /// * rdes2: data buffer address
 /// * rdes3: next descriptor address
+/// * rdes4: extended status, with enhanced descriptors
+/// * rdes5:
+/// * rdes6: timestamp low, with enhanced descriptors
+/// * rdes7: timestamp high, with enhanced descriptors
 #[repr(C)]
 pub(crate) struct RDes {
     rdes0: VolatileCell<u32>,
     rdes1: VolatileCell<u32>,
     rdes2: VolatileCell<u32>,
     rdes3: VolatileCell<u32>,
+    rdes4: VolatileCell<u32>,
+    rdes5: VolatileCell<u32>,
+    rdes6: VolatileCell<u32>,
+    rdes7: VolatileCell<u32>,
 }
 
 impl RDes {
@@ -51,6 +64,10 @@ impl RDes {
             rdes1: VolatileCell::new(0),
             rdes2: VolatileCell::new(0),
             rdes3: VolatileCell::new(0),
+            rdes4: VolatileCell::new(0),
+            rdes5: VolatileCell::new(0),
+            rdes6: VolatileCell::new(0),
+            rdes7: VolatileCell::new(0),
         }
     }
 
@@ -105,6 +122,18 @@ impl RDes {
         ((self.rdes0.get() >> RXDESC_0_FL_SHIFT) & RXDESC_0_FL_MASK) as usize
     }
 
+    #[cfg(not(eth_v1a))]
+    #[inline(always)]
+    fn timestamp(&self) -> Option<Timestamp> {
+        if self.rdes0.get() & RXDESC_0_TSV == 0 {
+            return None;
+        }
+        Some(Timestamp {
+            seconds: self.rdes7.get(),
+            nanoseconds: self.rdes6.get(),
+        })
+    }
+
     fn setup(&self, next: Option<&Self>, buf: *mut u8) {
         // Defer this initialization to this function, so we can have `RingEntry` on bss.
         self.rdes1.set(self.rdes1.get() | RXDESC_1_RCH);
@@ -217,6 +246,16 @@ impl<'a> RDesRing<'a> {
         return Some(&mut self.buffers[self.index].0[..len]);
     }
 
+    /// Get the timestamp of the packet returned by `available`, if any.
+    #[cfg(not(eth_v1a))]
+    pub(crate) fn timestamp(&self) -> Option<Timestamp> {
+        let descriptor = &self.descriptors[self.index];
+        if !descriptor.available() {
+            return None;
+        }
+        descriptor.timestamp()
+    }
+
     /// Pop the packet previously returned by `available`.
     pub(crate) fn pop_packet(&mut self) {
         let descriptor = &mut self.descriptors[self.index];
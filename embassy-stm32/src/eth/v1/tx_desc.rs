@@ -2,6 +2,8 @@ use core::sync::atomic::{compiler_fence, fence, Ordering};
 
 use vcell::VolatileCell;
 
+#[cfg(not(eth_v1a))]
+use crate::eth::ptp::{self, MessageId, Timestamp};
 use crate::eth::TX_BUFFER_SIZE;
 use crate::pac::ETH;
 
@@ -18,6 +20,10 @@ mod tx_consts {
     pub const TXDESC_0_TER: u32 = 1 << 21;
     // Second address chained
     pub const TXDESC_0_TCH: u32 = 1 << 20;
+    // Transmit timestamp enable
+    pub const TXDESC_0_TTSE: u32 = 1 << 25;
+    // Transmit timestamp status
+    pub const TXDESC_0_TTSS: u32 = 1 << 17;
     // Error status
     pub const TXDESC_0_ES: u32 = 1 << 15;
 
@@ -35,12 +41,20 @@ use super::Packet;
 /// * tdes1: buffer lengths
 /// * tdes2: data buffer address
 /// * tdes3: next descriptor address
+/// * tdes4:
+/// * tdes5:
+/// * tdes6: timestamp low, with enhanced descriptors
+/// * tdes7: timestamp high, with enhanced descriptors
 #[repr(C)]
 pub(crate) struct TDes {
     tdes0: VolatileCell<u32>,
     tdes1: VolatileCell<u32>,
     tdes2: VolatileCell<u32>,
     tdes3: VolatileCell<u32>,
+    tdes4: VolatileCell<u32>,
+    tdes5: VolatileCell<u32>,
+    tdes6: VolatileCell<u32>,
+    tdes7: VolatileCell<u32>,
 }
 
 impl TDes {
@@ -50,6 +64,10 @@ impl TDes {
             tdes1: VolatileCell::new(0),
             tdes2: VolatileCell::new(0),
             tdes3: VolatileCell::new(0),
+            tdes4: VolatileCell::new(0),
+            tdes5: VolatileCell::new(0),
+            tdes6: VolatileCell::new(0),
+            tdes7: VolatileCell::new(0),
         }
     }
 
@@ -89,6 +107,26 @@ impl TDes {
         self.tdes0.set(self.tdes0.get() | TXDESC_0_TER);
     }
 
+    #[cfg(not(eth_v1a))]
+    fn set_timestamp_enabled(&self, enabled: bool) {
+        if enabled {
+            self.tdes0.set(self.tdes0.get() | TXDESC_0_TTSE);
+        } else {
+            self.tdes0.set(self.tdes0.get() & !TXDESC_0_TTSE);
+        }
+    }
+
+    #[cfg(not(eth_v1a))]
+    fn timestamp(&self) -> Option<Timestamp> {
+        if self.tdes0.get() & TXDESC_0_TTSS == 0 {
+            return None;
+        }
+        Some(Timestamp {
+            seconds: self.tdes7.get(),
+            nanoseconds: self.tdes6.get(),
+        })
+    }
+
     // set up as a part fo the ring buffer - configures the tdes
     fn setup(&self, next: Option<&Self>) {
         // Defer this initialization to this function, so we can have `RingEntry` on bss.
@@ -107,6 +145,12 @@ pub(crate) struct TDesRing<'a> {
     descriptors: &'a mut [TDes],
     buffers: &'a mut [Packet<TX_BUFFER_SIZE>],
     index: usize,
+    /// PTP message to timestamp in the next transmission
+    #[cfg(not(eth_v1a))]
+    timestamp_request: Option<MessageId>,
+    /// Descriptor and PTP message waiting for its timestamp
+    #[cfg(not(eth_v1a))]
+    timestamp_pending: Option<(usize, MessageId)>,
 }
 
 impl<'a> TDesRing<'a> {
@@ -131,6 +175,10 @@ impl<'a> TDesRing<'a> {
             descriptors,
             buffers,
             index: 0,
+            #[cfg(not(eth_v1a))]
+            timestamp_request: None,
+            #[cfg(not(eth_v1a))]
+            timestamp_pending: None,
         }
     }
 
@@ -140,6 +188,9 @@ impl<'a> TDesRing<'a> {
 
     /// Return the next available packet buffer for transmitting, or None
     pub(crate) fn available(&mut self) -> Option<&mut [u8]> {
+        #[cfg(not(eth_v1a))]
+        self.collect_timestamp();
+
         let descriptor = &mut self.descriptors[self.index];
         if descriptor.available() {
             Some(&mut self.buffers[self.index].0)
@@ -156,6 +207,13 @@ impl<'a> TDesRing<'a> {
         descriptor.set_buffer1(self.buffers[self.index].0.as_ptr());
         descriptor.set_buffer1_len(len);
 
+        #[cfg(not(eth_v1a))]
+        {
+            let request = self.timestamp_request.take();
+            descriptor.set_timestamp_enabled(request.is_some());
+            self.timestamp_pending = request.map(|id| (self.index, id));
+        }
+
         descriptor.set_owned();
 
         // Ensure changes to the descriptor are committed before DMA engine sees tail pointer store.
@@ -171,4 +229,24 @@ impl<'a> TDesRing<'a> {
         // Request the DMA engine to poll the latest tx descriptor
         unsafe { ETH.ethernet_dma().dmatpdr().modify(|w| w.0 = 1) }
     }
+
+    /// Capture the timestamp of the next packet transmitted, identified by `id`.
+    #[cfg(not(eth_v1a))]
+    pub(crate) fn request_timestamp(&mut self, id: MessageId) {
+        self.timestamp_request = Some(id);
+    }
+
+    /// Record the timestamp of the last PTP message transmitted, once the DMA is done with it.
+    #[cfg(not(eth_v1a))]
+    fn collect_timestamp(&mut self) {
+        if let Some((index, id)) = self.timestamp_pending {
+            let descriptor = &self.descriptors[index];
+            if descriptor.available() {
+                self.timestamp_pending = None;
+                if let Some(timestamp) = descriptor.timestamp() {
+                    ptp::record_tx_timestamp(id, timestamp);
+                }
+            }
+        }
+    }
 }
@@ -2,6 +2,7 @@ use core::sync::atomic::{fence, Ordering};
 
 use vcell::VolatileCell;
 
+use crate::eth::ptp::{self, MessageId, Timestamp};
 use crate::eth::{Packet, RX_BUFFER_SIZE, TX_BUFFER_SIZE};
 use crate::pac::ETH;
 
@@ -16,9 +17,14 @@ mod emac_consts {
     pub const EMAC_DES0_BUF1AP: u32 = 0xFFFF_FFFF;
 
     pub const EMAC_TDES2_IOC: u32 = 0x8000_0000;
+    pub const EMAC_TDES2_TTSE: u32 = 0x4000_0000;
     pub const EMAC_TDES2_B1L: u32 = 0x0000_3FFF;
+    pub const EMAC_TDES3_TTSS: u32 = 0x0002_0000;
+
+    pub const EMAC_RDES1_TSA: u32 = 0x0000_4000;
 
     pub const EMAC_RDES3_IOC: u32 = 0x4000_0000;
+    pub const EMAC_RDES3_RS1V: u32 = 0x0400_0000;
     pub const EMAC_RDES3_PL: u32 = 0x0000_7FFF;
     pub const EMAC_RDES3_BUF1V: u32 = 0x0100_0000;
     pub const EMAC_RDES3_PKTLEN: u32 = 0x0000_7FFF;
@@ -27,8 +33,8 @@ use emac_consts::*;
 
 /// Transmit Descriptor representation
 ///
-/// * tdes0: transmit buffer address
-/// * tdes1:
+/// * tdes0: transmit buffer address, or timestamp low on write-back
+/// * tdes1: timestamp high on write-back
 /// * tdes2: buffer lengths
 /// * tdes3: control and payload/frame length
 #[repr(C)]
@@ -53,12 +59,27 @@ impl TDes {
     fn available(&self) -> bool {
         self.tdes3.get() & EMAC_DES3_OWN == 0
     }
+
+    /// Return the timestamp written back by the DMA, if captured
+    fn timestamp(&self) -> Option<Timestamp> {
+        if self.tdes3.get() & EMAC_TDES3_TTSS == 0 {
+            return None;
+        }
+        Some(Timestamp {
+            seconds: self.tdes1.get(),
+            nanoseconds: self.tdes0.get(),
+        })
+    }
 }
 
 pub(crate) struct TDesRing<'a> {
     descriptors: &'a mut [TDes],
     buffers: &'a mut [Packet<TX_BUFFER_SIZE>],
     index: usize,
+    /// PTP message to timestamp in the next transmission
+    timestamp_request: Option<MessageId>,
+    /// Descriptor and PTP message waiting for its timestamp
+    timestamp_pending: Option<(usize, MessageId)>,
 }
 
 impl<'a> TDesRing<'a> {
@@ -86,6 +107,8 @@ impl<'a> TDesRing<'a> {
             descriptors,
             buffers,
             index: 0,
+            timestamp_request: None,
+            timestamp_pending: None,
         }
     }
 
@@ -95,6 +118,8 @@ impl<'a> TDesRing<'a> {
 
     /// Return the next available packet buffer for transmitting, or None
     pub(crate) fn available(&mut self) -> Option<&mut [u8]> {
+        self.collect_timestamp();
+
         let d = &mut self.descriptors[self.index];
         if d.available() {
             Some(&mut self.buffers[self.index].0)
@@ -111,7 +136,10 @@ impl<'a> TDesRing<'a> {
 
         // Read format
         td.tdes0.set(self.buffers[self.index].0.as_ptr() as u32);
-        td.tdes2.set(len as u32 & EMAC_TDES2_B1L | EMAC_TDES2_IOC);
+        let request = self.timestamp_request.take();
+        let ttse = if request.is_some() { EMAC_TDES2_TTSE } else { 0 };
+        td.tdes2.set(len as u32 & EMAC_TDES2_B1L | EMAC_TDES2_IOC | ttse);
+        self.timestamp_pending = request.map(|id| (self.index, id));
 
         // FD: Contains first buffer of packet
         // LD: Contains last buffer of packet
@@ -132,12 +160,30 @@ impl<'a> TDesRing<'a> {
         // NOTE(unsafe) Atomic write
         unsafe { ETH.ethernet_dma().dmactx_dtpr().write(|w| w.0 = 0) }
     }
+
+    /// Capture the timestamp of the next packet transmitted, identified by `id`.
+    pub(crate) fn request_timestamp(&mut self, id: MessageId) {
+        self.timestamp_request = Some(id);
+    }
+
+    /// Record the timestamp of the last PTP message transmitted, once the DMA is done with it.
+    fn collect_timestamp(&mut self) {
+        if let Some((index, id)) = self.timestamp_pending {
+            let td = &self.descriptors[index];
+            if td.available() {
+                self.timestamp_pending = None;
+                if let Some(timestamp) = td.timestamp() {
+                    ptp::record_tx_timestamp(id, timestamp);
+                }
+            }
+        }
+    }
 }
 
 /// Receive Descriptor representation
 ///
-/// * rdes0: recieve buffer address
-/// * rdes1:
+/// * rdes0: recieve buffer address, or timestamp low in a context descriptor
+/// * rdes1: status, or timestamp high in a context descriptor
 /// * rdes2:
 /// * rdes3: OWN and Status
 #[repr(C)]
@@ -175,6 +221,18 @@ impl RDes {
         self.rdes3.get() & EMAC_DES3_OWN == 0 // Owned by us
     }
 
+    /// Return true if this RDes is a context descriptor, holding the timestamp of the previous packet
+    #[inline(always)]
+    fn is_context(&self) -> bool {
+        self.rdes3.get() & EMAC_DES3_CTXT != 0
+    }
+
+    /// Return true if a context descriptor with a timestamp follows this RDes
+    #[inline(always)]
+    fn has_timestamp(&self) -> bool {
+        self.rdes3.get() & EMAC_RDES3_RS1V != 0 && self.rdes1.get() & EMAC_RDES1_TSA != 0
+    }
+
     #[inline(always)]
     fn set_ready(&mut self, buf: *mut u8) {
         self.rdes0.set(buf as u32);
@@ -228,6 +286,12 @@ impl<'a> RDesRing<'a> {
                 return None;
             }
 
+            // Context descriptors are read along with the packet before them, skip them.
+            if descriptor.is_context() {
+                self.pop_packet();
+                continue;
+            }
+
             // If packet is invalid, pop it and try again.
             if !descriptor.valid() {
                 warn!("invalid packet: {:08x}", descriptor.rdes0.get());
@@ -243,6 +307,26 @@ impl<'a> RDesRing<'a> {
         return Some(&mut self.buffers[self.index].0[..len]);
     }
 
+    /// Get the timestamp of the packet returned by `available`, if any.
+    pub(crate) fn timestamp(&self) -> Option<Timestamp> {
+        let descriptor = &self.descriptors[self.index];
+        if !descriptor.available() || !descriptor.has_timestamp() {
+            return None;
+        }
+
+        let context = &self.descriptors[(self.index + 1) % self.descriptors.len()];
+        if !context.available() || !context.is_context() {
+            return None;
+        }
+
+        let (nanoseconds, seconds) = (context.rdes0.get(), context.rdes1.get());
+        // All ones indicates a corrupted timestamp
+        if nanoseconds == u32::MAX && seconds == u32::MAX {
+            return None;
+        }
+        Some(Timestamp { seconds, nanoseconds })
+    }
+
     /// Pop the packet previously returned by `available`.
     pub(crate) fn pop_packet(&mut self) {
         let descriptor = &mut self.descriptors[self.index];
@@ -1,11 +1,13 @@
 mod descriptors;
 
+use core::marker::PhantomData;
 use core::sync::atomic::{fence, Ordering};
 
 use embassy_cortex_m::interrupt::{Interrupt, InterruptExt};
 use embassy_hal_common::{into_ref, PeripheralRef};
 
 pub(crate) use self::descriptors::{RDes, RDesRing, TDes, TDesRing};
+use super::ptp::{self, Timestamp};
 use super::*;
 use crate::gpio::sealed::{AFType, Pin as _};
 use crate::gpio::{AnyPin, Speed};
@@ -42,9 +44,9 @@ pub struct Ethernet<'d, T: Instance, P: PHY> {
     pub(crate) tx: TDesRing<'d>,
     pub(crate) rx: RDesRing<'d>,
     pins: [PeripheralRef<'d, AnyPin>; 9],
-    _phy: P,
-    clock_range: u8,
-    phy_addr: u8,
+    pub(crate) phy: P,
+    pub(crate) station_management: EthernetStationManagement<T>,
+    pub(crate) link: Option<Link>,
     pub(crate) mac_addr: [u8; 6],
 }
 
@@ -76,7 +78,6 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
         tx_en: impl Peripheral<P = impl TXEnPin<T>> + 'd,
         phy: P,
         mac_addr: [u8; 6],
-        phy_addr: u8,
     ) -> Self {
         into_ref!(peri, ref_clk, mdio, mdc, crs, rx_d0, rx_d1, tx_d0, tx_d1, tx_en);
 
@@ -146,6 +147,9 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
 
             mac.macqtx_fcr().modify(|w| w.set_pt(0x100));
 
+            // pass all multicast frames, the network stack filters the groups it joined
+            mac.macpfr().modify(|w| w.set_pm(true));
+
             // disable all MMC RX interrupts
             mac.mmc_rx_interrupt_mask().write(|w| {
                 w.set_rxcrcerpim(true);
@@ -190,6 +194,8 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
                 }
             };
 
+            ptp_init(hclk.0);
+
             let pins = [
                 ref_clk.map_into(),
                 mdio.map_into(),
@@ -207,9 +213,12 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
                 tx: TDesRing::new(&mut queue.tx_desc, &mut queue.tx_buf),
                 rx: RDesRing::new(&mut queue.rx_desc, &mut queue.rx_buf),
                 pins,
-                _phy: phy,
-                clock_range,
-                phy_addr,
+                phy,
+                station_management: EthernetStationManagement {
+                    peri: PhantomData,
+                    clock_range,
+                },
+                link: None,
                 mac_addr,
             };
 
@@ -235,8 +244,8 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
                 w.set_tie(true);
             });
 
-            P::phy_reset(&mut this);
-            P::phy_init(&mut this);
+            this.phy.phy_reset(&mut this.station_management);
+            this.phy.phy_init(&mut this.station_management);
 
             interrupt::ETH::steal().unpend();
             interrupt::ETH::steal().enable();
@@ -246,14 +255,33 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
     }
 }
 
-unsafe impl<'d, T: Instance, P: PHY> StationManagement for Ethernet<'d, T, P> {
-    fn smi_read(&mut self, reg: u8) -> u16 {
+impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
+    /// Configure the MAC for the speed and duplex mode of the link.
+    pub(crate) fn configure_link(&mut self, link: Link) {
+        // NOTE(unsafe) We have `&mut self` and the interrupt doesn't use this registers
+        unsafe {
+            ETH.ethernet_mac().maccr().modify(|w| {
+                w.set_fes(link.speed == LinkSpeed::Mbps100);
+                w.set_dm(link.full_duplex);
+            });
+        }
+    }
+}
+
+/// Station Management Interface (SMI) of the Ethernet peripheral.
+pub struct EthernetStationManagement<T: Instance> {
+    peri: PhantomData<T>,
+    clock_range: u8,
+}
+
+unsafe impl<T: Instance> StationManagement for EthernetStationManagement<T> {
+    fn smi_read(&mut self, phy_addr: u8, reg: u8) -> u16 {
         // NOTE(unsafe) These registers aren't used in the interrupt and we have `&mut self`
         unsafe {
             let mac = ETH.ethernet_mac();
 
             mac.macmdioar().modify(|w| {
-                w.set_pa(self.phy_addr);
+                w.set_pa(phy_addr);
                 w.set_rda(reg);
                 w.set_goc(0b11); // read
                 w.set_cr(self.clock_range);
@@ -264,14 +292,14 @@ unsafe impl<'d, T: Instance, P: PHY> StationManagement for Ethernet<'d, T, P> {
         }
     }
 
-    fn smi_write(&mut self, reg: u8, val: u16) {
+    fn smi_write(&mut self, phy_addr: u8, reg: u8, val: u16) {
         // NOTE(unsafe) These registers aren't used in the interrupt and we have `&mut self`
         unsafe {
             let mac = ETH.ethernet_mac();
 
             mac.macmdiodr().write(|w| w.set_md(val));
             mac.macmdioar().modify(|w| {
-                w.set_pa(self.phy_addr);
+                w.set_pa(phy_addr);
                 w.set_rda(reg);
                 w.set_goc(0b01); // write
                 w.set_cr(self.clock_range);
@@ -319,3 +347,84 @@ impl<'d, T: Instance, P: PHY> Drop for Ethernet<'d, T, P> {
         })
     }
 }
+
+/// Start the PTP clock from zero, and enable the timestamping of the PTP messages.
+unsafe fn ptp_init(hclk: u32) {
+    let mac = ETH.ethernet_mac();
+    let (increment, addend) = ptp::clock_increment(hclk);
+
+    mac.mactscr().write(|w| {
+        w.set_tsena(true);
+        w.set_tsctrlssr(true); // the sub-seconds are nanoseconds
+        w.set_tsver2ena(true);
+        w.set_tsipena(true); // PTP over Ethernet
+        w.set_tsipv4ena(true); // PTP over UDP/IPv4
+        w.set_tsipv6ena(true); // PTP over UDP/IPv6
+        w.set_snaptypsel(0b01); // event and general messages, sent and received
+    });
+    mac.macssir().write(|w| w.set_ssinc(increment));
+    ptp_set_addend(addend);
+    mac.mactscr().modify(|w| w.set_tscfupdt(true)); // fine update
+
+    ptp_set_time(Timestamp::default());
+}
+
+pub(crate) fn ptp_now() -> Timestamp {
+    // NOTE(unsafe) Read-only registers
+    unsafe {
+        let mac = ETH.ethernet_mac();
+        loop {
+            let seconds = mac.macstsr().read().tss();
+            let nanoseconds = mac.macstnr().read().tsss();
+            // The nanoseconds may have rolled over between the reads
+            if mac.macstsr().read().tss() == seconds {
+                return Timestamp { seconds, nanoseconds };
+            }
+        }
+    }
+}
+
+pub(crate) fn ptp_set_time(time: Timestamp) {
+    // NOTE(unsafe) Exclusive access to the registers
+    critical_section::with(|_| unsafe {
+        let mac = ETH.ethernet_mac();
+
+        mac.macstsur().write(|w| w.set_tss(time.seconds));
+        mac.macstnur().write(|w| w.set_tsss(time.nanoseconds));
+        mac.mactscr().modify(|w| w.set_tsinit(true));
+        while mac.mactscr().read().tsinit() {}
+    })
+}
+
+pub(crate) fn ptp_add_time(offset: Timestamp, negative: bool) {
+    // NOTE(unsafe) Exclusive access to the registers
+    critical_section::with(|_| unsafe {
+        let mac = ETH.ethernet_mac();
+
+        if negative {
+            // Subtractions are written as the complement of the offset, modulo 10^9 for the
+            // nanoseconds as TSSS must stay below 10^9
+            mac.macstsur().write(|w| w.set_tss(0u32.wrapping_sub(offset.seconds)));
+            mac.macstnur().write(|w| {
+                w.set_tsss((1_000_000_000 - offset.nanoseconds) % 1_000_000_000);
+                w.set_addsub(true);
+            });
+        } else {
+            mac.macstsur().write(|w| w.set_tss(offset.seconds));
+            mac.macstnur().write(|w| w.set_tsss(offset.nanoseconds));
+        }
+        mac.mactscr().modify(|w| w.set_tsupdt(true));
+        while mac.mactscr().read().tsupdt() {}
+    })
+}
+
+pub(crate) fn ptp_set_addend(addend: u32) {
+    // NOTE(unsafe) Exclusive access to the registers
+    critical_section::with(|_| unsafe {
+        let mac = ETH.ethernet_mac();
+
+        mac.mactsar().write(|w| w.set_tsar(addend));
+        mac.mactscr().modify(|w| w.set_tsaddreg(true));
+        while mac.mactscr().read().tsaddreg() {}
+    })
+}
@@ -44,6 +44,9 @@ pub unsafe fn on_irq() {
     #[cfg(any(exti_c0, exti_g0, exti_l5, exti_u5, exti_h5, exti_h50))]
     let bits = EXTI.rpr(0).read().0 | EXTI.fpr(0).read().0;
 
+    // Only handle the GPIO lines, the lines above are used by other peripherals, e.g. the RTC.
+    let bits = bits & 0xFFFF;
+
     // Mask all the channels that fired.
     cpu_regs().imr(0).modify(|w| w.0 &= !bits);
 
@@ -127,6 +130,30 @@ impl<'d, T: GpioPin> ExtiInput<'d, T> {
     }
 }
 
+/// The interrupt pins of the PHYs are active low.
+#[cfg(eth)]
+impl<'d, T: GpioPin> crate::eth::LinkInterrupt for ExtiInput<'d, T> {
+    fn poll_asserted(&mut self, cx: &mut Context) -> Poll<()> {
+        let pin = self.pin.pin.pin.pin();
+        EXTI_WAKERS[pin as usize].register(cx.waker());
+        if self.is_low() {
+            return Poll::Ready(());
+        }
+
+        // The interrupt is masked once it has fired, unmask it again.
+        if !unsafe { cpu_regs().imr(0).read().line(pin as _) } {
+            enable_line(pin, self.pin.pin.pin.port(), false, true);
+        }
+
+        // The pin may have been asserted before the interrupt was unmasked.
+        if self.is_low() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
 mod eh02 {
     use core::convert::Infallible;
 
@@ -204,25 +231,30 @@ struct ExtiInputFuture<'a> {
     phantom: PhantomData<&'a mut AnyPin>,
 }
 
+/// Configure the edges of the EXTI line, and unmask its interrupt.
+fn enable_line(pin: u8, port: u8, rising: bool, falling: bool) {
+    critical_section::with(|_| unsafe {
+        let pin = pin as usize;
+        exticr_regs().exticr(pin / 4).modify(|w| w.set_exti(pin % 4, port));
+        EXTI.rtsr(0).modify(|w| w.set_line(pin, rising));
+        EXTI.ftsr(0).modify(|w| w.set_line(pin, falling));
+
+        // clear pending bit
+        #[cfg(not(any(exti_c0, exti_g0, exti_l5, exti_u5, exti_h5, exti_h50)))]
+        EXTI.pr(0).write(|w| w.set_line(pin, true));
+        #[cfg(any(exti_c0, exti_g0, exti_l5, exti_u5, exti_h5, exti_h50))]
+        {
+            EXTI.rpr(0).write(|w| w.set_line(pin, true));
+            EXTI.fpr(0).write(|w| w.set_line(pin, true));
+        }
+
+        cpu_regs().imr(0).modify(|w| w.set_line(pin, true));
+    });
+}
+
 impl<'a> ExtiInputFuture<'a> {
     fn new(pin: u8, port: u8, rising: bool, falling: bool) -> Self {
-        critical_section::with(|_| unsafe {
-            let pin = pin as usize;
-            exticr_regs().exticr(pin / 4).modify(|w| w.set_exti(pin % 4, port));
-            EXTI.rtsr(0).modify(|w| w.set_line(pin, rising));
-            EXTI.ftsr(0).modify(|w| w.set_line(pin, falling));
-
-            // clear pending bit
-            #[cfg(not(any(exti_c0, exti_g0, exti_l5, exti_u5, exti_h5, exti_h50)))]
-            EXTI.pr(0).write(|w| w.set_line(pin, true));
-            #[cfg(any(exti_c0, exti_g0, exti_l5, exti_u5, exti_h5, exti_h50))]
-            {
-                EXTI.rpr(0).write(|w| w.set_line(pin, true));
-                EXTI.fpr(0).write(|w| w.set_line(pin, true));
-            }
-
-            cpu_regs().imr(0).modify(|w| w.set_line(pin, true));
-        });
+        enable_line(pin, port, rising, falling);
 
         Self {
             pin,
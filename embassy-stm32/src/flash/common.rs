@@ -55,6 +55,44 @@ impl<'d, MODE> Flash<'d, MODE> {
     }
 }
 
+#[cfg(any(flash_l4, flash_h7))]
+impl<'d, MODE> Flash<'d, MODE> {
+    /// Returns true if bank 2 is mapped at the start of the flash, and bank 1 at the end.
+    ///
+    /// The bank mapped at the start of the flash is the one the chip booted from. The code always runs from
+    /// the start of the flash, so the bank at the end can be written with a new firmware.
+    pub fn is_bank_swapped(&self) -> bool {
+        family::is_bank_swapped()
+    }
+
+    /// Programs the option bytes to boot from the bank currently mapped at the end of the flash, and resets
+    /// the chip to apply them. Only returns on error.
+    ///
+    /// On STM32L4, the bank is booted through the system bootloader, which requires a valid stack pointer
+    /// at the start of the bank and falls back to the other bank otherwise.
+    ///
+    /// Returns [`Error::SingleBank`] if the flash is configured with a single bank.
+    pub fn blocking_swap_banks(&mut self) -> Result<(), Error> {
+        unsafe { family::swap_banks() }
+    }
+}
+
+#[cfg(flash_l4)]
+impl<'d, MODE> Flash<'d, MODE> {
+    /// Reads the option bytes currently in effect.
+    pub fn option_bytes(&self) -> super::OptionBytes {
+        family::read_option_bytes()
+    }
+
+    /// Programs the option bytes and resets the chip to apply them. Only returns on error.
+    ///
+    /// Lowering the read protection from level 1 to level 0 mass erases the flash, and level 2 can never be
+    /// left again.
+    pub fn blocking_program_option_bytes(&mut self, option_bytes: &super::OptionBytes) -> Result<(), Error> {
+        unsafe { family::program_option_bytes(option_bytes) }
+    }
+}
+
 pub(super) fn blocking_read(base: u32, size: u32, offset: u32, bytes: &mut [u8]) -> Result<(), Error> {
     if offset + bytes.len() as u32 > size {
         return Err(Error::Size);
@@ -179,3 +179,37 @@ unsafe fn blocking_wait_ready(bank: pac::flash::Bank) -> Result<(), Error> {
         }
     }
 }
+
+pub(crate) fn is_bank_swapped() -> bool {
+    unsafe { pac::FLASH.optcr().read().swap_bank() }
+}
+
+/// Swaps the banks by toggling the SWAP_BANK option bit, and resets the chip to apply it. Only returns on error.
+pub(crate) unsafe fn swap_banks() -> Result<(), Error> {
+    if !is_dual_bank() {
+        return Err(Error::SingleBank);
+    }
+
+    let swap = !is_bank_swapped();
+
+    pac::FLASH.optkeyr().write(|w| w.set_optkeyr(0x0819_2A3B));
+    pac::FLASH.optkeyr().write(|w| w.set_optkeyr(0x4C5D_6E7F));
+
+    pac::FLASH.optsr_prg().modify(|w| w.set_swap_bank_opt(swap));
+    pac::FLASH.optcr().modify(|w| w.set_optstart(true));
+    while pac::FLASH.optsr_cur().read().opt_busy() {}
+
+    let ret = if pac::FLASH.optsr_cur().read().optchangeerr() {
+        pac::FLASH.optccr().write(|w| w.set_clr_optchangeerr(true));
+        Err(Error::Prog)
+    } else {
+        Ok(())
+    };
+
+    pac::FLASH.optcr().modify(|w| w.set_optlock(true));
+
+    if ret.is_ok() {
+        cortex_m::peripheral::SCB::sys_reset();
+    }
+    ret
+}
@@ -1,11 +1,17 @@
 use core::ptr::write_volatile;
 
 use atomic_polyfill::{fence, Ordering};
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+use embassy_sync::waitqueue::AtomicWaker;
+use pac::flash::regs::Sr;
 
 use super::{FlashRegion, FlashSector, FLASH_REGIONS, WRITE_SIZE};
 use crate::flash::Error;
 use crate::pac;
 
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+static WAKER: AtomicWaker = AtomicWaker::new();
+
 pub const fn is_default_layout() -> bool {
     true
 }
@@ -14,6 +20,17 @@ pub const fn get_flash_regions() -> &'static [&'static FlashRegion] {
     &FLASH_REGIONS
 }
 
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+pub(crate) unsafe fn on_interrupt() {
+    // Clear IRQ flags
+    pac::FLASH.sr().write(|w| {
+        w.set_operr(true);
+        w.set_eop(true);
+    });
+
+    WAKER.wake();
+}
+
 pub(crate) unsafe fn lock() {
     #[cfg(any(flash_wl, flash_wb, flash_l4))]
     pac::FLASH.cr().modify(|w| w.set_lock(true));
@@ -43,6 +60,26 @@ pub(crate) unsafe fn unlock() {
     }
 }
 
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+pub(crate) unsafe fn enable_write() {
+    assert_eq!(0, WRITE_SIZE % 4);
+
+    pac::FLASH.cr().write(|w| {
+        w.set_pg(true);
+        w.set_eopie(true);
+        w.set_errie(true);
+    });
+}
+
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+pub(crate) unsafe fn disable_write() {
+    pac::FLASH.cr().write(|w| {
+        w.set_pg(false);
+        w.set_eopie(false);
+        w.set_errie(false);
+    });
+}
+
 pub(crate) unsafe fn enable_blocking_write() {
     assert_eq!(0, WRITE_SIZE % 4);
 
@@ -55,7 +92,18 @@ pub(crate) unsafe fn disable_blocking_write() {
     pac::FLASH.cr().write(|w| w.set_pg(false));
 }
 
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+pub(crate) async unsafe fn write(start_address: u32, buf: &[u8; WRITE_SIZE]) -> Result<(), Error> {
+    write_start(start_address, buf);
+    wait_ready().await
+}
+
 pub(crate) unsafe fn blocking_write(start_address: u32, buf: &[u8; WRITE_SIZE]) -> Result<(), Error> {
+    write_start(start_address, buf);
+    wait_ready_blocking()
+}
+
+unsafe fn write_start(start_address: u32, buf: &[u8; WRITE_SIZE]) {
     let mut address = start_address;
     for val in buf.chunks(4) {
         write_volatile(address as *mut u32, u32::from_le_bytes(val.try_into().unwrap()));
@@ -64,11 +112,46 @@ pub(crate) unsafe fn blocking_write(start_address: u32, buf: &[u8; WRITE_SIZE])
         // prevents parallelism errors
         fence(Ordering::SeqCst);
     }
+}
 
-    wait_ready_blocking()
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+pub(crate) async unsafe fn erase_sector(sector: &FlashSector) -> Result<(), Error> {
+    pac::FLASH.cr().modify(|w| {
+        w.set_eopie(true);
+        w.set_errie(true);
+    });
+
+    erase_start(sector);
+    let ret: Result<(), Error> = wait_ready().await;
+
+    pac::FLASH.cr().modify(|w| {
+        w.set_per(false);
+        w.set_eopie(false);
+        w.set_errie(false);
+    });
+
+    clear_all_err();
+    ret
 }
 
 pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), Error> {
+    erase_start(sector);
+    let ret: Result<(), Error> = wait_ready_blocking();
+
+    #[cfg(any(flash_wl, flash_wb, flash_l4))]
+    pac::FLASH.cr().modify(|w| w.set_per(false));
+
+    #[cfg(any(flash_l0, flash_l1))]
+    pac::FLASH.pecr().modify(|w| {
+        w.set_erase(false);
+        w.set_prog(false);
+    });
+
+    clear_all_err();
+    ret
+}
+
+unsafe fn erase_start(sector: &FlashSector) {
     #[cfg(any(flash_l0, flash_l1))]
     {
         pac::FLASH.pecr().modify(|w| {
@@ -83,8 +166,13 @@ pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), E
     {
         let idx = (sector.start - super::FLASH_BASE as u32) / super::BANK1_REGION.erase_size as u32;
 
+        // With swapped banks, the first half of the address space is mapped to bank 2
         #[cfg(flash_l4)]
-        let (idx, bank) = if idx > 255 { (idx - 256, true) } else { (idx, false) };
+        let (idx, bank) = if idx > 255 {
+            (idx - 256, !is_bank_swapped())
+        } else {
+            (idx, is_bank_swapped())
+        };
 
         pac::FLASH.cr().modify(|w| {
             w.set_per(true);
@@ -97,22 +185,171 @@ pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), E
             w.set_bker(bank);
         });
     }
+}
 
-    let ret: Result<(), Error> = wait_ready_blocking();
+#[cfg(flash_l4)]
+pub(crate) fn is_bank_swapped() -> bool {
+    <crate::peripherals::SYSCFG as crate::rcc::sealed::RccPeripheral>::enable();
+    unsafe { pac::SYSCFG.memrmp().read().fb_mode() }
+}
 
-    #[cfg(any(flash_wl, flash_wb, flash_l4))]
-    pac::FLASH.cr().modify(|w| w.set_per(false));
+/// Makes the system bootloader boot the bank which is currently mapped at the end of the flash, by
+/// toggling the BFB2 option bit, and reloads the option bytes. Only returns on error.
+#[cfg(flash_l4)]
+pub(crate) unsafe fn swap_banks() -> Result<(), Error> {
+    if !is_dual_bank() {
+        return Err(Error::SingleBank);
+    }
 
-    #[cfg(any(flash_l0, flash_l1))]
-    pac::FLASH.pecr().modify(|w| {
-        w.set_erase(false);
-        w.set_prog(false);
-    });
+    let mut option_bytes = read_option_bytes();
+    option_bytes.dual_bank_boot = !is_bank_swapped();
+    program_option_bytes(&option_bytes)
+}
+
+/// Read protection level of the flash
+#[cfg(flash_l4)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReadProtection {
+    /// No protection
+    Level0,
+    /// The flash can't be read by the debugger or the system bootloader. Going back to level 0 mass erases
+    /// the flash.
+    Level1,
+    /// Like level 1, and the debug port and the option bytes are disabled for good. This is irreversible.
+    Level2,
+}
+
+/// Option bytes of the flash, see the reference manual for their meaning
+#[cfg(flash_l4)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OptionBytes {
+    pub read_protection: ReadProtection,
+    /// Brownout reset threshold, 0 (1.7 V) to 4 (2.8 V)
+    pub bor_level: u8,
+    /// Write protected areas of bank 1 as first and last page index, `None` if the area is disabled
+    pub bank1_write_protection: [Option<(u8, u8)>; 2],
+    /// Write protected areas of bank 2, only available on dual-bank devices
+    pub bank2_write_protection: [Option<(u8, u8)>; 2],
+    /// Boot configuration, together with the BOOT0 pin or `n_boot0`
+    pub n_boot1: bool,
+    /// Boot from main flash if the boot configuration selects the BOOT0 pin as boot source and `n_swboot0` is cleared
+    pub n_boot0: bool,
+    /// Take BOOT0 from `n_boot0` instead of the BOOT0 pin
+    pub n_swboot0: bool,
+    /// Boot from bank 2 through the system bootloader if it holds a valid firmware
+    pub dual_bank_boot: bool,
+    /// Split the flash in two banks, only available on devices with 256 KB or 512 KB of flash
+    pub dual_bank: bool,
+}
+
+#[cfg(flash_l4)]
+pub(crate) fn read_option_bytes() -> OptionBytes {
+    let optr = unsafe { pac::FLASH.optr().read() };
+    let area = |strt: u8, end: u8| if strt <= end { Some((strt, end)) } else { None };
+    let (wrp1a, wrp1b, wrp2a, wrp2b) = unsafe {
+        (
+            pac::FLASH.wrp1ar().read(),
+            pac::FLASH.wrp1br().read(),
+            pac::FLASH.wrp2ar().read(),
+            pac::FLASH.wrp2br().read(),
+        )
+    };
+    let dual_bank = is_dual_bank();
+
+    OptionBytes {
+        read_protection: match optr.rdp() {
+            0xAA => ReadProtection::Level0,
+            0xCC => ReadProtection::Level2,
+            _ => ReadProtection::Level1,
+        },
+        bor_level: optr.bor_lev(),
+        bank1_write_protection: [
+            area(wrp1a.wrp1a_strt(), wrp1a.wrp1a_end()),
+            area(wrp1b.wrp1b_strt(), wrp1b.wrp1b_end()),
+        ],
+        bank2_write_protection: match dual_bank {
+            true => [
+                area(wrp2a.wrp2a_strt(), wrp2a.wrp2a_end()),
+                area(wrp2b.wrp2b_strt(), wrp2b.wrp2b_end()),
+            ],
+            false => [None, None],
+        },
+        n_boot1: optr.n_boot1(),
+        n_boot0: optr.n_boot0(),
+        n_swboot0: optr.n_swboot0(),
+        dual_bank_boot: optr.bfb(0),
+        dual_bank: optr.dualbank(),
+    }
+}
+
+/// Programs the option bytes and reloads them, which resets the chip. Only returns on error.
+#[cfg(flash_l4)]
+pub(crate) unsafe fn program_option_bytes(option_bytes: &OptionBytes) -> Result<(), Error> {
+    if option_bytes.bor_level > 4 {
+        return Err(Error::Size);
+    }
+    let area = |area: Option<(u8, u8)>| area.unwrap_or((0xFF, 0x00));
 
     clear_all_err();
+    unlock();
+    pac::FLASH.optkeyr().write(|w| w.set_optkeyr(0x0819_2A3B));
+    pac::FLASH.optkeyr().write(|w| w.set_optkeyr(0x4C5D_6E7F));
+
+    pac::FLASH.optr().modify(|w| {
+        w.set_rdp(match option_bytes.read_protection {
+            ReadProtection::Level0 => 0xAA,
+            ReadProtection::Level1 => 0xBB,
+            ReadProtection::Level2 => 0xCC,
+        });
+        w.set_bor_lev(option_bytes.bor_level);
+        w.set_n_boot1(option_bytes.n_boot1);
+        w.set_n_boot0(option_bytes.n_boot0);
+        w.set_n_swboot0(option_bytes.n_swboot0);
+        w.set_bfb(0, option_bytes.dual_bank_boot);
+        w.set_dualbank(option_bytes.dual_bank);
+    });
+
+    let [wrp1a, wrp1b] = option_bytes.bank1_write_protection.map(area);
+    pac::FLASH.wrp1ar().write(|w| {
+        w.set_wrp1a_strt(wrp1a.0);
+        w.set_wrp1a_end(wrp1a.1);
+    });
+    pac::FLASH.wrp1br().write(|w| {
+        w.set_wrp1b_strt(wrp1b.0);
+        w.set_wrp1b_end(wrp1b.1);
+    });
+    if is_dual_bank() {
+        let [wrp2a, wrp2b] = option_bytes.bank2_write_protection.map(area);
+        pac::FLASH.wrp2ar().write(|w| {
+            w.set_wrp2a_strt(wrp2a.0);
+            w.set_wrp2a_end(wrp2a.1);
+        });
+        pac::FLASH.wrp2br().write(|w| {
+            w.set_wrp2b_strt(wrp2b.0);
+            w.set_wrp2b_end(wrp2b.1);
+        });
+    }
+
+    pac::FLASH.cr().modify(|w| w.set_optstrt(true));
+    let ret = wait_ready_blocking();
+
+    if ret.is_ok() {
+        // resets the chip
+        pac::FLASH.cr().modify(|w| w.set_obl_launch(true));
+    }
+
+    pac::FLASH.cr().modify(|w| w.set_optlock(true));
+    lock();
     ret
 }
 
+#[cfg(flash_l4)]
+const fn is_dual_bank() -> bool {
+    FLASH_REGIONS.len() == 2
+}
+
 pub(crate) unsafe fn clear_all_err() {
     pac::FLASH.sr().modify(|w| {
         #[cfg(any(flash_wl, flash_wb, flash_l4, flash_l0))]
@@ -151,39 +388,62 @@ pub(crate) unsafe fn clear_all_err() {
     });
 }
 
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+async unsafe fn wait_ready() -> Result<(), Error> {
+    use core::task::Poll;
+
+    use futures::future::poll_fn;
+
+    poll_fn(|cx| {
+        WAKER.register(cx.waker());
+
+        let sr = pac::FLASH.sr().read();
+        if !sr.bsy() {
+            Poll::Ready(get_result(sr))
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
 unsafe fn wait_ready_blocking() -> Result<(), Error> {
     loop {
         let sr = pac::FLASH.sr().read();
 
         if !sr.bsy() {
-            #[cfg(any(flash_wl, flash_wb, flash_l4))]
-            if sr.progerr() {
-                return Err(Error::Prog);
-            }
-
-            if sr.wrperr() {
-                return Err(Error::Protected);
-            }
-
-            if sr.pgaerr() {
-                return Err(Error::Unaligned);
-            }
-
-            if sr.sizerr() {
-                return Err(Error::Size);
-            }
-
-            #[cfg(any(flash_wl, flash_wb, flash_l4))]
-            if sr.miserr() {
-                return Err(Error::Miss);
-            }
-
-            #[cfg(any(flash_wl, flash_wb, flash_l4))]
-            if sr.pgserr() {
-                return Err(Error::Seq);
-            }
-
-            return Ok(());
+            return get_result(sr);
         }
     }
 }
+
+fn get_result(sr: Sr) -> Result<(), Error> {
+    #[cfg(any(flash_wl, flash_wb, flash_l4))]
+    if sr.progerr() {
+        return Err(Error::Prog);
+    }
+
+    if sr.wrperr() {
+        return Err(Error::Protected);
+    }
+
+    if sr.pgaerr() {
+        return Err(Error::Unaligned);
+    }
+
+    if sr.sizerr() {
+        return Err(Error::Size);
+    }
+
+    #[cfg(any(flash_wl, flash_wb, flash_l4))]
+    if sr.miserr() {
+        return Err(Error::Miss);
+    }
+
+    #[cfg(any(flash_wl, flash_wb, flash_l4))]
+    if sr.pgserr() {
+        return Err(Error::Seq);
+    }
+
+    Ok(())
+}
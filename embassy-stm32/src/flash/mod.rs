@@ -1,11 +1,11 @@
 use embedded_storage::nor_flash::{NorFlashError, NorFlashErrorKind};
 
-#[cfg(flash_f4)]
+#[cfg(any(flash_f4, flash_l4, flash_wl, flash_wb))]
 mod asynch;
 #[cfg(flash)]
 mod common;
 
-#[cfg(flash_f4)]
+#[cfg(any(flash_f4, flash_l4, flash_wl, flash_wb))]
 pub use asynch::InterruptHandler;
 #[cfg(flash)]
 pub use common::*;
@@ -85,6 +85,8 @@ pub enum Error {
     Protected,
     Unaligned,
     Parallelism,
+    /// The chip has a single bank, which can't be swapped.
+    SingleBank,
 }
 
 impl NorFlashError for Error {
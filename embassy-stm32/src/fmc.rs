@@ -4,6 +4,7 @@ use embassy_hal_common::into_ref;
 
 use crate::gpio::sealed::AFType;
 use crate::gpio::{Pull, Speed};
+use crate::pac::fmc::vals;
 use crate::Peripheral;
 
 pub struct Fmc<'d, T: Instance> {
@@ -87,7 +88,221 @@ macro_rules! fmc_sdram_constructor {
     };
 }
 
+/// NOR/PSRAM/SRAM bank, selected by the NE1 to NE4 pins
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NorSramBank {
+    Bank1,
+    Bank2,
+    Bank3,
+    Bank4,
+}
+
+/// Type of memory of a NOR/PSRAM/SRAM bank
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MemoryType {
+    Sram,
+    Psram,
+    Nor,
+}
+
+/// Shape of the asynchronous accesses, with different timings for reads and writes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AccessMode {
+    A,
+    B,
+    C,
+    D,
+}
+
+/// Timings of asynchronous NOR/PSRAM/SRAM accesses, in FMC kernel clock cycles
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NorSramTiming {
+    /// Duration of the address setup phase, 0 to 15 (ADDSET)
+    pub address_setup: u8,
+    /// Duration of the address hold phase, 1 to 15, only used in access mode D (ADDHLD)
+    pub address_hold: u8,
+    /// Duration of the data phase, 1 to 255 (DATAST)
+    pub data_setup: u8,
+    /// Duration of the bus turnaround phase, 0 to 15 (BUSTURN)
+    pub bus_turnaround: u8,
+    /// Access mode, only used with different read and write timings (ACCMOD)
+    pub access_mode: AccessMode,
+}
+
+impl Default for NorSramTiming {
+    /// The slowest timings
+    fn default() -> Self {
+        Self {
+            address_setup: 15,
+            address_hold: 15,
+            data_setup: 255,
+            bus_turnaround: 15,
+            access_mode: AccessMode::A,
+        }
+    }
+}
+
+/// NOR/PSRAM/SRAM bank configuration
+#[non_exhaustive]
+pub struct NorSramConfig {
+    /// Type of memory
+    pub memory_type: MemoryType,
+    /// Timings of the reads, and of the writes without `write_timing`
+    pub read_timing: NorSramTiming,
+    /// Timings of the writes, if different from the ones of the reads (EXTMOD)
+    pub write_timing: Option<NorSramTiming>,
+    /// Allow writes (WREN)
+    pub write_enable: bool,
+}
+
+impl Default for NorSramConfig {
+    fn default() -> Self {
+        Self {
+            memory_type: MemoryType::Sram,
+            read_timing: Default::default(),
+            write_timing: None,
+            write_enable: true,
+        }
+    }
+}
+
+/// NOR/PSRAM/SRAM memory, configured by one of the `nor_*` and `sram_*` constructors of [`Fmc`]
+pub struct NorSram<'d, T: Instance> {
+    bank: NorSramBank,
+    _peri: PhantomData<&'d mut T>,
+}
+
+impl<'d, T: Instance> NorSram<'d, T> {
+    /// The bank of the memory
+    pub fn bank(&self) -> NorSramBank {
+        self.bank
+    }
+
+    /// Start of the memory, in the memory map
+    pub fn ptr(&self) -> *mut u8 {
+        (0x6000_0000 + self.bank as usize * 0x0400_0000) as *mut u8
+    }
+}
+
+fn timing_regs(timing: &NorSramTiming) -> (u8, u8, u8, u8, vals::Accmod) {
+    let accmod = match timing.access_mode {
+        AccessMode::A => vals::Accmod::A,
+        AccessMode::B => vals::Accmod::B,
+        AccessMode::C => vals::Accmod::C,
+        AccessMode::D => vals::Accmod::D,
+    };
+    (
+        timing.address_setup,
+        timing.address_hold,
+        timing.data_setup,
+        timing.bus_turnaround,
+        accmod,
+    )
+}
+
+macro_rules! config_bcr {
+    ($w:ident, $width:expr, $config:expr) => {
+        $w.set_mbken(true);
+        $w.set_muxen(false);
+        $w.set_mtyp(match $config.memory_type {
+            MemoryType::Sram => vals::Mtyp::SRAM,
+            MemoryType::Psram => vals::Mtyp::PSRAM,
+            MemoryType::Nor => vals::Mtyp::FLASH,
+        });
+        $w.set_mwid($width);
+        // NOR flash accesses must be enabled for NOR memories
+        $w.set_faccen($config.memory_type == MemoryType::Nor);
+        $w.set_bursten(false);
+        $w.set_wren($config.write_enable);
+        $w.set_waiten(false);
+        $w.set_extmod($config.write_timing.is_some());
+        $w.set_asyncwait(false);
+        $w.set_cburstrw(false);
+    };
+}
+
+macro_rules! fmc_nor_sram_constructor {
+    ($name:ident: (
+        bank: $bank:expr,
+        width: $width:expr,
+        addr: [$(($addr_pin_name:ident: $addr_signal:ident)),*],
+        d: [$(($d_pin_name:ident: $d_signal:ident)),*],
+        ctrl: [$(($ctrl_pin_name:ident: $ctrl_signal:ident)),*]
+    )) => {
+        pub fn $name(
+            _instance: impl Peripheral<P = T> + 'd,
+            $($addr_pin_name: impl Peripheral<P = impl $addr_signal<T>> + 'd),*,
+            $($d_pin_name: impl Peripheral<P = impl $d_signal<T>> + 'd),*,
+            $($ctrl_pin_name: impl Peripheral<P = impl $ctrl_signal<T>> + 'd),*,
+            config: NorSramConfig
+        ) -> NorSram<'d, T> {
+
+        critical_section::with(|_| unsafe {
+            config_pins!(
+                $($addr_pin_name),*,
+                $($d_pin_name),*,
+                $($ctrl_pin_name),*
+            );
+        });
+
+            let mut fmc = Self { peri: PhantomData };
+            fmc.init_nor_sram($bank, $width, config)
+        }
+    };
+}
+
 impl<'d, T: Instance> Fmc<'d, T> {
+    /// Configure the NOR/PSRAM/SRAM `bank`, and enable the FMC
+    fn init_nor_sram(&mut self, bank: NorSramBank, width: vals::Mwid, config: NorSramConfig) -> NorSram<'d, T> {
+        use stm32_fmc::FmcPeripheral;
+
+        self.enable();
+
+        let regs = T::REGS;
+        let n = bank as usize;
+        let (addset, addhld, datast, busturn, accmod) = timing_regs(&config.read_timing);
+
+        // NOTE(unsafe) We have exclusive access to the FMC
+        unsafe {
+            regs.btr(n).write(|w| {
+                w.set_addset(addset);
+                w.set_addhld(addhld);
+                w.set_datast(datast);
+                w.set_busturn(busturn);
+                w.set_accmod(accmod);
+            });
+            if let Some(write_timing) = &config.write_timing {
+                let (addset, addhld, datast, busturn, accmod) = timing_regs(write_timing);
+                regs.bwtr(n).write(|w| {
+                    w.set_addset(addset);
+                    w.set_addhld(addhld);
+                    w.set_datast(datast);
+                    w.set_busturn(busturn);
+                    w.set_accmod(accmod);
+                });
+            }
+            match bank {
+                NorSramBank::Bank1 => regs.bcr1().modify(|w| {
+                    config_bcr!(w, width, config);
+                }),
+                _ => regs.bcr(n - 1).modify(|w| {
+                    config_bcr!(w, width, config);
+                }),
+            }
+        }
+
+        self.memory_controller_enable();
+
+        NorSram {
+            bank,
+            _peri: PhantomData,
+        }
+    }
+
     fmc_sdram_constructor!(sdram_a12bits_d32bits_4banks_bank1: (
         bank: stm32_fmc::SdramTargetBank::Bank1,
         addr: [
@@ -145,6 +360,220 @@ impl<'d, T: Instance> Fmc<'d, T> {
             (sdcke: SDCKE1Pin), (sdclk: SDCLKPin), (sdncas: SDNCASPin), (sdne: SDNE1Pin), (sdnras: SDNRASPin), (sdnwe: SDNWEPin)
         ]
     ));
+
+    fmc_sdram_constructor!(sdram_a12bits_d16bits_4banks_bank1: (
+        bank: stm32_fmc::SdramTargetBank::Bank1,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin), (a10: A10Pin), (a11: A11Pin)
+        ],
+        ba: [(ba0: BA0Pin), (ba1: BA1Pin)],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin)
+        ],
+        nbl: [
+            (nbl0: NBL0Pin), (nbl1: NBL1Pin)
+        ],
+        ctrl: [
+            (sdcke: SDCKE0Pin), (sdclk: SDCLKPin), (sdncas: SDNCASPin), (sdne: SDNE0Pin), (sdnras: SDNRASPin), (sdnwe: SDNWEPin)
+        ]
+    ));
+
+    fmc_sdram_constructor!(sdram_a13bits_d16bits_4banks_bank1: (
+        bank: stm32_fmc::SdramTargetBank::Bank1,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin), (a10: A10Pin), (a11: A11Pin), (a12: A12Pin)
+        ],
+        ba: [(ba0: BA0Pin), (ba1: BA1Pin)],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin)
+        ],
+        nbl: [
+            (nbl0: NBL0Pin), (nbl1: NBL1Pin)
+        ],
+        ctrl: [
+            (sdcke: SDCKE0Pin), (sdclk: SDCLKPin), (sdncas: SDNCASPin), (sdne: SDNE0Pin), (sdnras: SDNRASPin), (sdnwe: SDNWEPin)
+        ]
+    ));
+
+    fmc_sdram_constructor!(sdram_a13bits_d16bits_4banks_bank2: (
+        bank: stm32_fmc::SdramTargetBank::Bank2,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin), (a10: A10Pin), (a11: A11Pin), (a12: A12Pin)
+        ],
+        ba: [(ba0: BA0Pin), (ba1: BA1Pin)],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin)
+        ],
+        nbl: [
+            (nbl0: NBL0Pin), (nbl1: NBL1Pin)
+        ],
+        ctrl: [
+            (sdcke: SDCKE1Pin), (sdclk: SDCLKPin), (sdncas: SDNCASPin), (sdne: SDNE1Pin), (sdnras: SDNRASPin), (sdnwe: SDNWEPin)
+        ]
+    ));
+
+    fmc_sdram_constructor!(sdram_a13bits_d32bits_4banks_bank1: (
+        bank: stm32_fmc::SdramTargetBank::Bank1,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin), (a10: A10Pin), (a11: A11Pin), (a12: A12Pin)
+        ],
+        ba: [(ba0: BA0Pin), (ba1: BA1Pin)],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin),
+            (d16: D16Pin), (d17: D17Pin), (d18: D18Pin), (d19: D19Pin), (d20: D20Pin), (d21: D21Pin), (d22: D22Pin), (d23: D23Pin),
+            (d24: D24Pin), (d25: D25Pin), (d26: D26Pin), (d27: D27Pin), (d28: D28Pin), (d29: D29Pin), (d30: D30Pin), (d31: D31Pin)
+        ],
+        nbl: [
+            (nbl0: NBL0Pin), (nbl1: NBL1Pin), (nbl2: NBL2Pin), (nbl3: NBL3Pin)
+        ],
+        ctrl: [
+            (sdcke: SDCKE0Pin), (sdclk: SDCLKPin), (sdncas: SDNCASPin), (sdne: SDNE0Pin), (sdnras: SDNRASPin), (sdnwe: SDNWEPin)
+        ]
+    ));
+
+    fmc_sdram_constructor!(sdram_a13bits_d32bits_4banks_bank2: (
+        bank: stm32_fmc::SdramTargetBank::Bank2,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin), (a10: A10Pin), (a11: A11Pin), (a12: A12Pin)
+        ],
+        ba: [(ba0: BA0Pin), (ba1: BA1Pin)],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin),
+            (d16: D16Pin), (d17: D17Pin), (d18: D18Pin), (d19: D19Pin), (d20: D20Pin), (d21: D21Pin), (d22: D22Pin), (d23: D23Pin),
+            (d24: D24Pin), (d25: D25Pin), (d26: D26Pin), (d27: D27Pin), (d28: D28Pin), (d29: D29Pin), (d30: D30Pin), (d31: D31Pin)
+        ],
+        nbl: [
+            (nbl0: NBL0Pin), (nbl1: NBL1Pin), (nbl2: NBL2Pin), (nbl3: NBL3Pin)
+        ],
+        ctrl: [
+            (sdcke: SDCKE1Pin), (sdclk: SDCLKPin), (sdncas: SDNCASPin), (sdne: SDNE1Pin), (sdnras: SDNRASPin), (sdnwe: SDNWEPin)
+        ]
+    ));
+
+    fmc_nor_sram_constructor!(sram_a19bits_d16bits_bank1: (
+        bank: NorSramBank::Bank1,
+        width: vals::Mwid::BITS16,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin), (a10: A10Pin), (a11: A11Pin), (a12: A12Pin), (a13: A13Pin), (a14: A14Pin), (a15: A15Pin), (a16: A16Pin), (a17: A17Pin), (a18: A18Pin)
+        ],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin)
+        ],
+        ctrl: [
+            (nbl0: NBL0Pin), (nbl1: NBL1Pin), (noe: NOEPin), (nwe: NWEPin), (ne: NE1Pin)
+        ]
+    ));
+
+    fmc_nor_sram_constructor!(sram_a19bits_d16bits_bank2: (
+        bank: NorSramBank::Bank2,
+        width: vals::Mwid::BITS16,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin), (a10: A10Pin), (a11: A11Pin), (a12: A12Pin), (a13: A13Pin), (a14: A14Pin), (a15: A15Pin), (a16: A16Pin), (a17: A17Pin), (a18: A18Pin)
+        ],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin)
+        ],
+        ctrl: [
+            (nbl0: NBL0Pin), (nbl1: NBL1Pin), (noe: NOEPin), (nwe: NWEPin), (ne: NE2Pin)
+        ]
+    ));
+
+    fmc_nor_sram_constructor!(sram_a19bits_d16bits_bank3: (
+        bank: NorSramBank::Bank3,
+        width: vals::Mwid::BITS16,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin), (a10: A10Pin), (a11: A11Pin), (a12: A12Pin), (a13: A13Pin), (a14: A14Pin), (a15: A15Pin), (a16: A16Pin), (a17: A17Pin), (a18: A18Pin)
+        ],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin)
+        ],
+        ctrl: [
+            (nbl0: NBL0Pin), (nbl1: NBL1Pin), (noe: NOEPin), (nwe: NWEPin), (ne: NE3Pin)
+        ]
+    ));
+
+    fmc_nor_sram_constructor!(sram_a19bits_d16bits_bank4: (
+        bank: NorSramBank::Bank4,
+        width: vals::Mwid::BITS16,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin), (a10: A10Pin), (a11: A11Pin), (a12: A12Pin), (a13: A13Pin), (a14: A14Pin), (a15: A15Pin), (a16: A16Pin), (a17: A17Pin), (a18: A18Pin)
+        ],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin)
+        ],
+        ctrl: [
+            (nbl0: NBL0Pin), (nbl1: NBL1Pin), (noe: NOEPin), (nwe: NWEPin), (ne: NE4Pin)
+        ]
+    ));
+
+    fmc_nor_sram_constructor!(nor_a23bits_d16bits_bank1: (
+        bank: NorSramBank::Bank1,
+        width: vals::Mwid::BITS16,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin), (a10: A10Pin), (a11: A11Pin), (a12: A12Pin), (a13: A13Pin), (a14: A14Pin), (a15: A15Pin), (a16: A16Pin), (a17: A17Pin), (a18: A18Pin), (a19: A19Pin), (a20: A20Pin), (a21: A21Pin), (a22: A22Pin)
+        ],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin)
+        ],
+        ctrl: [
+            (noe: NOEPin), (nwe: NWEPin), (ne: NE1Pin)
+        ]
+    ));
+
+    fmc_nor_sram_constructor!(nor_a23bits_d16bits_bank2: (
+        bank: NorSramBank::Bank2,
+        width: vals::Mwid::BITS16,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin), (a10: A10Pin), (a11: A11Pin), (a12: A12Pin), (a13: A13Pin), (a14: A14Pin), (a15: A15Pin), (a16: A16Pin), (a17: A17Pin), (a18: A18Pin), (a19: A19Pin), (a20: A20Pin), (a21: A21Pin), (a22: A22Pin)
+        ],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin)
+        ],
+        ctrl: [
+            (noe: NOEPin), (nwe: NWEPin), (ne: NE2Pin)
+        ]
+    ));
+
+    fmc_nor_sram_constructor!(nor_a23bits_d16bits_bank3: (
+        bank: NorSramBank::Bank3,
+        width: vals::Mwid::BITS16,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin), (a10: A10Pin), (a11: A11Pin), (a12: A12Pin), (a13: A13Pin), (a14: A14Pin), (a15: A15Pin), (a16: A16Pin), (a17: A17Pin), (a18: A18Pin), (a19: A19Pin), (a20: A20Pin), (a21: A21Pin), (a22: A22Pin)
+        ],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin)
+        ],
+        ctrl: [
+            (noe: NOEPin), (nwe: NWEPin), (ne: NE3Pin)
+        ]
+    ));
+
+    fmc_nor_sram_constructor!(nor_a23bits_d16bits_bank4: (
+        bank: NorSramBank::Bank4,
+        width: vals::Mwid::BITS16,
+        addr: [
+            (a0: A0Pin), (a1: A1Pin), (a2: A2Pin), (a3: A3Pin), (a4: A4Pin), (a5: A5Pin), (a6: A6Pin), (a7: A7Pin), (a8: A8Pin), (a9: A9Pin), (a10: A10Pin), (a11: A11Pin), (a12: A12Pin), (a13: A13Pin), (a14: A14Pin), (a15: A15Pin), (a16: A16Pin), (a17: A17Pin), (a18: A18Pin), (a19: A19Pin), (a20: A20Pin), (a21: A21Pin), (a22: A22Pin)
+        ],
+        d: [
+            (d0: D0Pin), (d1: D1Pin), (d2: D2Pin), (d3: D3Pin), (d4: D4Pin), (d5: D5Pin), (d6: D6Pin), (d7: D7Pin),
+            (d8: D8Pin), (d9: D9Pin), (d10: D10Pin), (d11: D11Pin), (d12: D12Pin), (d13: D13Pin), (d14: D14Pin), (d15: D15Pin)
+        ],
+        ctrl: [
+            (noe: NOEPin), (nwe: NWEPin), (ne: NE4Pin)
+        ]
+    ));
 }
 
 pub(crate) mod sealed {
@@ -1,5 +1,9 @@
 #![macro_use]
 
+use embassy_hal_common::{into_ref, PeripheralRef};
+
+use crate::gpio::sealed::{AFType, Pin as _};
+use crate::gpio::{AnyPin, Flex, Pull, Speed};
 use crate::interrupt::Interrupt;
 
 #[cfg_attr(i2c_v1, path = "v1.rs")]
@@ -12,18 +16,165 @@ mod timeout;
 #[cfg(feature = "time")]
 pub use timeout::*;
 
-use crate::peripherals;
+use crate::{peripherals, Peripheral};
 
 #[derive(Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
+    /// A START or STOP condition was detected at an unexpected place.
     Bus,
+    /// Another controller won the arbitration, the transfer was aborted.
     Arbitration,
+    /// The address or a data byte was not acknowledged.
     Nack,
+    /// The transfer took too long, either from the timeout of a [`TimeoutI2c`] or because SCL
+    /// was held low for longer than the hardware timeout.
     Timeout,
+    /// SMBus packet error checking failed.
     Crc,
+    /// A byte was received before the previous one was read.
     Overrun,
     ZeroLengthTransfer,
+    /// SDA is still held low after the bus recovery.
+    BusStuck,
+}
+
+/// Configuration of the addresses an [`I2cSlave`] responds to.
+#[non_exhaustive]
+#[derive(Copy, Clone)]
+pub struct SlaveConfig {
+    /// Primary 7-bit own address.
+    pub address: u8,
+    /// Optional second 7-bit own address.
+    pub secondary_address: Option<u8>,
+    /// Also respond to the general call address (0x00).
+    pub general_call: bool,
+    pub sda_pullup: bool,
+    pub scl_pullup: bool,
+}
+
+impl Default for SlaveConfig {
+    fn default() -> Self {
+        Self {
+            address: 0x55,
+            secondary_address: None,
+            general_call: false,
+            sda_pullup: false,
+            scl_pullup: false,
+        }
+    }
+}
+
+/// Direction of a transfer, as requested by the controller.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CommandKind {
+    /// The controller reads from us, answer with [`I2cSlave::respond_to_read`].
+    Read,
+    /// The controller writes to us, answer with [`I2cSlave::respond_to_write`].
+    Write,
+}
+
+/// A transfer addressed to an [`I2cSlave`], returned by [`I2cSlave::listen`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Command {
+    pub kind: CommandKind,
+    /// The own address that matched, 0 for a general call.
+    pub address: u8,
+}
+
+/// SCL and SDA, kept by the drivers to be able to free a stuck bus.
+pub(crate) struct Pins<'d> {
+    scl: PeripheralRef<'d, AnyPin>,
+    scl_af: u8,
+    scl_pull: Pull,
+    sda: PeripheralRef<'d, AnyPin>,
+    sda_af: u8,
+    sda_pull: Pull,
+}
+
+impl<'d> Pins<'d> {
+    pub(crate) fn new<T: Instance>(
+        scl: impl Peripheral<P = impl SclPin<T>> + 'd,
+        sda: impl Peripheral<P = impl SdaPin<T>> + 'd,
+        scl_pullup: bool,
+        sda_pullup: bool,
+    ) -> Self {
+        into_ref!(scl, sda);
+
+        let pull = |pullup| match pullup {
+            true => Pull::Up,
+            false => Pull::None,
+        };
+
+        Self {
+            scl_af: scl.af_num(),
+            scl: scl.map_into(),
+            scl_pull: pull(scl_pullup),
+            sda_af: sda.af_num(),
+            sda: sda.map_into(),
+            sda_pull: pull(sda_pullup),
+        }
+    }
+
+    /// Hand the pins over to the peripheral.
+    pub(crate) fn set_as_af(&mut self) {
+        unsafe {
+            self.scl
+                .set_as_af_pull(self.scl_af, AFType::OutputOpenDrain, self.scl_pull);
+            self.sda
+                .set_as_af_pull(self.sda_af, AFType::OutputOpenDrain, self.sda_pull);
+        }
+    }
+
+    /// Bus clear procedure of the I2C specification: clock SCL until the target holding SDA low
+    /// lets go of it, which happens at the latest after 9 clocks, then generate a STOP condition.
+    ///
+    /// The peripheral must be disabled, the pins are left disconnected.
+    pub(crate) fn clear_bus(&mut self) -> Result<(), Error> {
+        let mut scl = Flex::new(&mut *self.scl);
+        let mut sda = Flex::new(&mut *self.sda);
+
+        scl.set_high();
+        sda.set_high();
+        scl.set_as_input_output(Speed::Low, self.scl_pull);
+        sda.set_as_input_output(Speed::Low, self.sda_pull);
+
+        // Clock at 100 kHz, which every device supports
+        let half_period = unsafe { crate::rcc::get_freqs() }.sys.0 / 200_000;
+        let delay = || cortex_m::asm::delay(half_period);
+
+        delay();
+        if sda.is_high() {
+            return Ok(());
+        }
+
+        for _ in 0..9 {
+            scl.set_low();
+            delay();
+            scl.set_high();
+            delay();
+            if sda.is_high() {
+                break;
+            }
+        }
+
+        // STOP: SDA goes high while SCL is high
+        scl.set_low();
+        delay();
+        sda.set_low();
+        delay();
+        scl.set_high();
+        delay();
+        sda.set_high();
+        delay();
+
+        match sda.is_high() {
+            true => Ok(()),
+            false => Err(Error::BusStuck),
+        }
+    }
 }
 
 pub(crate) mod sealed {
@@ -36,6 +187,7 @@ pub(crate) mod sealed {
 
 pub trait Instance: sealed::Instance + 'static {
     type Interrupt: Interrupt;
+    type ErrorInterrupt: Interrupt;
 }
 
 pin_trait!(SclPin, Instance);
@@ -58,6 +210,7 @@ foreach_interrupt!(
 
         impl Instance for peripherals::$inst {
             type Interrupt = crate::interrupt::$irq;
+            type ErrorInterrupt = crate::_generated::peripheral_interrupts::$inst::ER;
         }
     };
 );
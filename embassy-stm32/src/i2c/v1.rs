@@ -1,12 +1,14 @@
+use core::future::poll_fn;
 use core::marker::PhantomData;
+use core::task::Poll;
 
+use embassy_cortex_m::interrupt::{Interrupt, InterruptExt};
 use embassy_embedded_hal::SetConfig;
 use embassy_hal_common::{into_ref, PeripheralRef};
+use embassy_sync::waitqueue::AtomicWaker;
 
 use crate::dma::NoDma;
-use crate::gpio::sealed::AFType;
-use crate::gpio::Pull;
-use crate::i2c::{Error, Instance, SclPin, SdaPin};
+use crate::i2c::{Command, CommandKind, Error, Instance, Pins, SclPin, SdaPin, SlaveConfig};
 use crate::pac::i2c;
 use crate::time::Hertz;
 use crate::{interrupt, Peripheral};
@@ -17,7 +19,31 @@ pub struct InterruptHandler<T: Instance> {
 }
 
 impl<T: Instance> interrupt::Handler<T::Interrupt> for InterruptHandler<T> {
-    unsafe fn on_interrupt() {}
+    unsafe fn on_interrupt() {
+        // Only unmasked by the slave driver. The flags are cleared by the driver, so mask the
+        // interrupts until it had a chance to do so.
+        critical_section::with(|_| {
+            T::regs().cr2().modify(|w| {
+                w.set_itevten(false);
+                w.set_itbufen(false);
+            });
+        });
+        T::state().waker.wake();
+    }
+}
+
+/// Error interrupt handler.
+pub struct ErrorInterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::Handler<T::ErrorInterrupt> for ErrorInterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        critical_section::with(|_| {
+            T::regs().cr2().modify(|w| w.set_iterren(false));
+        });
+        T::state().waker.wake();
+    }
 }
 
 #[non_exhaustive]
@@ -36,16 +62,22 @@ impl Default for Config {
     }
 }
 
-pub struct State {}
+pub struct State {
+    waker: AtomicWaker,
+}
 
 impl State {
     pub(crate) const fn new() -> Self {
-        Self {}
+        Self {
+            waker: AtomicWaker::new(),
+        }
     }
 }
 
 pub struct I2c<'d, T: Instance, TXDMA = NoDma, RXDMA = NoDma> {
     phantom: PhantomData<&'d mut T>,
+    pins: Pins<'d>,
+    freq: Hertz,
     #[allow(dead_code)]
     tx_dma: PeripheralRef<'d, TXDMA>,
     #[allow(dead_code)]
@@ -57,35 +89,24 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
         _peri: impl Peripheral<P = T> + 'd,
         scl: impl Peripheral<P = impl SclPin<T>> + 'd,
         sda: impl Peripheral<P = impl SdaPin<T>> + 'd,
-        _irq: impl interrupt::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        _irq: impl interrupt::Binding<T::Interrupt, InterruptHandler<T>>
+            + interrupt::Binding<T::ErrorInterrupt, ErrorInterruptHandler<T>>
+            + 'd,
         tx_dma: impl Peripheral<P = TXDMA> + 'd,
         rx_dma: impl Peripheral<P = RXDMA> + 'd,
         freq: Hertz,
         config: Config,
     ) -> Self {
-        into_ref!(scl, sda, tx_dma, rx_dma);
+        into_ref!(tx_dma, rx_dma);
 
         T::enable();
         T::reset();
 
-        unsafe {
-            scl.set_as_af_pull(
-                scl.af_num(),
-                AFType::OutputOpenDrain,
-                match config.scl_pullup {
-                    true => Pull::Up,
-                    false => Pull::None,
-                },
-            );
-            sda.set_as_af_pull(
-                sda.af_num(),
-                AFType::OutputOpenDrain,
-                match config.sda_pullup {
-                    true => Pull::Up,
-                    false => Pull::None,
-                },
-            );
+        let mut pins = Pins::new(scl, sda, config.scl_pullup, config.sda_pullup);
+        if let Err(e) = pins.clear_bus() {
+            warn!("I2C bus recovery failed: {:?}", e);
         }
+        pins.set_as_af();
 
         unsafe {
             T::regs().cr1().modify(|reg| {
@@ -94,21 +115,7 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
             });
         }
 
-        let timings = Timings::new(T::frequency(), freq.into());
-
-        unsafe {
-            T::regs().cr2().modify(|reg| {
-                reg.set_freq(timings.freq);
-            });
-            T::regs().ccr().modify(|reg| {
-                reg.set_f_s(timings.mode.f_s());
-                reg.set_duty(timings.duty.duty());
-                reg.set_ccr(timings.ccr);
-            });
-            T::regs().trise().modify(|reg| {
-                reg.set_trise(timings.trise);
-            });
-        }
+        unsafe { set_timings::<T>(freq) };
 
         unsafe {
             T::regs().cr1().modify(|reg| {
@@ -118,11 +125,38 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
 
         Self {
             phantom: PhantomData,
+            pins,
+            freq,
             tx_dma,
             rx_dma,
         }
     }
 
+    /// Free the bus if a target holds SDA low, e.g. because it was reset in the middle of a transfer.
+    ///
+    /// This is done by [`new`](Self::new) already, call it again to recover from an
+    /// [`Error::Bus`] or [`Error::Timeout`].
+    pub fn recover_bus(&mut self) -> Result<(), Error> {
+        unsafe {
+            T::regs().cr1().modify(|reg| reg.set_pe(false));
+        }
+
+        let result = self.pins.clear_bus();
+        self.pins.set_as_af();
+
+        // A software reset gets the peripheral out of a busy state it could have been left in.
+        unsafe {
+            T::regs().cr1().modify(|reg| reg.set_swrst(true));
+            T::regs().cr1().modify(|reg| reg.set_swrst(false));
+        }
+        unsafe {
+            set_timings::<T>(self.freq);
+            T::regs().cr1().modify(|reg| reg.set_pe(true));
+        }
+
+        result
+    }
+
     unsafe fn check_and_clear_error_flags(&self) -> Result<i2c::regs::Sr1, Error> {
         // Note that flags should only be cleared once they have been registered. If flags are
         // cleared otherwise, there may be an inherent race condition and flags may be missed.
@@ -144,12 +178,16 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
         }
 
         if sr1.af() {
+            // Release the bus, the controller has to end the transfer after a NACK
             T::regs().sr1().modify(|reg| reg.set_af(false));
+            T::regs().cr1().modify(|reg| reg.set_stop(true));
             return Err(Error::Nack);
         }
 
         if sr1.arlo() {
+            // The peripheral went back to slave mode, make sure it does not retry the START on its own
             T::regs().sr1().modify(|reg| reg.set_arlo(false));
+            T::regs().cr1().modify(|reg| reg.set_start(false));
             return Err(Error::Arbitration);
         }
 
@@ -362,6 +400,228 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
     }
 }
 
+/// I2C slave (target) driver.
+///
+/// The peripheral answers to the addresses in [`SlaveConfig`] and stretches SCL until the
+/// application has handled each byte, so no data is lost if the application is slow.
+pub struct I2cSlave<'d, T: Instance> {
+    _peri: PeripheralRef<'d, T>,
+    _pins: Pins<'d>,
+}
+
+impl<'d, T: Instance> I2cSlave<'d, T> {
+    /// Create a new I2C slave.
+    ///
+    /// `freq` is the bus frequency the controller is expected to use.
+    pub fn new(
+        peri: impl Peripheral<P = T> + 'd,
+        scl: impl Peripheral<P = impl SclPin<T>> + 'd,
+        sda: impl Peripheral<P = impl SdaPin<T>> + 'd,
+        _irq: impl interrupt::Binding<T::Interrupt, InterruptHandler<T>>
+            + interrupt::Binding<T::ErrorInterrupt, ErrorInterruptHandler<T>>
+            + 'd,
+        freq: Hertz,
+        config: SlaveConfig,
+    ) -> Self {
+        into_ref!(peri);
+
+        T::enable();
+        T::reset();
+
+        let mut pins = Pins::new(scl, sda, config.scl_pullup, config.sda_pullup);
+        pins.set_as_af();
+
+        let regs = T::regs();
+
+        unsafe {
+            regs.cr1().modify(|reg| reg.set_pe(false));
+
+            set_timings::<T>(freq);
+
+            regs.oar1().write(|reg| {
+                // Bit 14 must be kept at 1 by software
+                reg.0 = 1 << 14;
+                reg.set_add((config.address as u16) << 1);
+                reg.set_addmode(i2c::vals::Addmode::BIT7);
+            });
+            regs.oar2().write(|reg| {
+                if let Some(address) = config.secondary_address {
+                    reg.set_add2(address);
+                    reg.set_endual(i2c::vals::Endual::DUAL);
+                }
+            });
+
+            regs.cr1().modify(|reg| {
+                reg.set_engc(config.general_call);
+                reg.set_nostretch(false);
+                reg.set_pe(true);
+            });
+            // ACK can only be set once the peripheral is enabled
+            regs.cr1().modify(|reg| reg.set_ack(true));
+        }
+
+        unsafe { T::Interrupt::steal() }.unpend();
+        unsafe { T::Interrupt::steal() }.enable();
+        unsafe { T::ErrorInterrupt::steal() }.unpend();
+        unsafe { T::ErrorInterrupt::steal() }.enable();
+
+        Self {
+            _peri: peri,
+            _pins: pins,
+        }
+    }
+
+    /// Wait until `done` returns true. The buffer interrupts (TxE, RxNE) are only unmasked
+    /// if `buffer` is set.
+    async fn wait_event(
+        &mut self,
+        buffer: bool,
+        done: impl Fn(i2c::regs::Sr1) -> bool,
+    ) -> Result<i2c::regs::Sr1, Error> {
+        let regs = T::regs();
+
+        poll_fn(|cx| {
+            T::state().waker.register(cx.waker());
+
+            let sr1 = unsafe { regs.sr1().read() };
+            // See `I2c::check_and_clear_error_flags` about BERR
+            if sr1.berr() {
+                unsafe { regs.sr1().modify(|reg| reg.set_berr(false)) };
+            }
+            if sr1.ovr() {
+                unsafe { regs.sr1().modify(|reg| reg.set_ovr(false)) };
+                return Poll::Ready(Err(Error::Overrun));
+            }
+            if done(sr1) {
+                return Poll::Ready(Ok(sr1));
+            }
+
+            critical_section::with(|_| unsafe {
+                regs.cr2().modify(|reg| {
+                    reg.set_itevten(true);
+                    reg.set_iterren(true);
+                    reg.set_itbufen(buffer);
+                })
+            });
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Wait for a controller to address us.
+    ///
+    /// The returned command must be answered with [`respond_to_read`](Self::respond_to_read) or
+    /// [`respond_to_write`](Self::respond_to_write), SCL is held low until then.
+    pub async fn listen(&mut self) -> Result<Command, Error> {
+        let regs = T::regs();
+
+        self.wait_event(false, |sr1| {
+            // Leftovers from the end of the previous transfer
+            if sr1.af() {
+                unsafe { regs.sr1().modify(|reg| reg.set_af(false)) };
+            }
+            if sr1.stopf() {
+                // Cleared by reading SR1 followed by a write to CR1
+                unsafe { regs.cr1().modify(|_| {}) };
+            }
+            sr1.addr()
+        })
+        .await?;
+
+        // Reading SR2 after SR1 clears ADDR. The peripheral keeps stretching SCL until the first
+        // byte is written to or read from DR.
+        let sr2 = unsafe { regs.sr2().read() };
+
+        let address = if sr2.gencall() {
+            0
+        } else if sr2.dualf() {
+            unsafe { regs.oar2().read().add2() }
+        } else {
+            unsafe { (regs.oar1().read().add() >> 1) as u8 }
+        };
+        let kind = match sr2.tra() {
+            true => CommandKind::Read,
+            false => CommandKind::Write,
+        };
+
+        Ok(Command { kind, address })
+    }
+
+    /// Receive the data of a write command into `buffer`.
+    ///
+    /// Returns once the controller ends the transfer with a STOP or a repeated START, in the latter
+    /// case the next [`listen`](Self::listen) returns immediately. Bytes that do not fit in `buffer`
+    /// are acknowledged and dropped. Returns the number of bytes stored.
+    pub async fn respond_to_write(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let regs = T::regs();
+        let mut len = 0;
+
+        loop {
+            let sr1 = self
+                .wait_event(true, |sr1| sr1.rxne() || sr1.stopf() || sr1.addr())
+                .await?;
+
+            if sr1.rxne() {
+                let byte = unsafe { regs.dr().read().dr() };
+                if let Some(b) = buffer.get_mut(len) {
+                    *b = byte;
+                    len += 1;
+                }
+            } else {
+                if sr1.stopf() {
+                    unsafe { regs.cr1().modify(|_| {}) };
+                }
+                return Ok(len);
+            }
+        }
+    }
+
+    /// Send `data` in response to a read command.
+    ///
+    /// If the controller reads more than `data.len()` bytes, 0xFF is sent for the rest. Returns
+    /// the number of bytes the controller has read.
+    pub async fn respond_to_read(&mut self, data: &[u8]) -> Result<usize, Error> {
+        let regs = T::regs();
+        let mut len = 0;
+
+        loop {
+            // DR is only written once the previous byte is out (BTF), so that no byte is left
+            // behind in DR when the controller NACKs.
+            let first = len == 0;
+            let sr1 = self
+                .wait_event(first, |sr1| {
+                    sr1.af() || sr1.stopf() || sr1.addr() || (sr1.txe() && (first || sr1.btf()))
+                })
+                .await?;
+
+            if sr1.af() {
+                // The controller does not want any more data
+                unsafe { regs.sr1().modify(|reg| reg.set_af(false)) };
+                return Ok(len);
+            }
+            if sr1.stopf() || sr1.addr() {
+                if sr1.stopf() {
+                    unsafe { regs.cr1().modify(|_| {}) };
+                }
+                return Ok(len);
+            }
+
+            let byte = data.get(len).copied().unwrap_or(0xFF);
+            unsafe { regs.dr().write(|reg| reg.set_dr(byte)) };
+            len += 1;
+        }
+    }
+}
+
+impl<'d, T: Instance> Drop for I2cSlave<'d, T> {
+    fn drop(&mut self) {
+        // Stop acknowledging our address, otherwise the bus would be held forever.
+        unsafe {
+            T::regs().cr1().modify(|reg| reg.set_pe(false));
+        }
+    }
+}
+
 impl<'d, T: Instance> embedded_hal_02::blocking::i2c::Read for I2c<'d, T> {
     type Error = Error;
 
@@ -402,6 +662,7 @@ mod eh1 {
                 Self::Crc => embedded_hal_1::i2c::ErrorKind::Other,
                 Self::Overrun => embedded_hal_1::i2c::ErrorKind::Overrun,
                 Self::ZeroLengthTransfer => embedded_hal_1::i2c::ErrorKind::Other,
+                Self::BusStuck => embedded_hal_1::i2c::ErrorKind::Bus,
             }
         }
     }
@@ -535,22 +796,26 @@ impl Timings {
     }
 }
 
+unsafe fn set_timings<T: Instance>(freq: Hertz) {
+    let timings = Timings::new(T::frequency(), freq);
+
+    T::regs().cr2().modify(|reg| {
+        reg.set_freq(timings.freq);
+    });
+    T::regs().ccr().modify(|reg| {
+        reg.set_f_s(timings.mode.f_s());
+        reg.set_duty(timings.duty.duty());
+        reg.set_ccr(timings.ccr);
+    });
+    T::regs().trise().modify(|reg| {
+        reg.set_trise(timings.trise);
+    });
+}
+
 impl<'d, T: Instance> SetConfig for I2c<'d, T> {
     type Config = Hertz;
     fn set_config(&mut self, config: &Self::Config) {
-        let timings = Timings::new(T::frequency(), *config);
-        unsafe {
-            T::regs().cr2().modify(|reg| {
-                reg.set_freq(timings.freq);
-            });
-            T::regs().ccr().modify(|reg| {
-                reg.set_f_s(timings.mode.f_s());
-                reg.set_duty(timings.duty.duty());
-                reg.set_ccr(timings.ccr);
-            });
-            T::regs().trise().modify(|reg| {
-                reg.set_trise(timings.trise);
-            });
-        }
+        self.freq = *config;
+        unsafe { set_timings::<T>(*config) };
     }
 }
@@ -10,9 +10,7 @@ use embassy_hal_common::{into_ref, PeripheralRef};
 use embassy_sync::waitqueue::AtomicWaker;
 
 use crate::dma::{NoDma, Transfer};
-use crate::gpio::sealed::AFType;
-use crate::gpio::Pull;
-use crate::i2c::{Error, Instance, SclPin, SdaPin};
+use crate::i2c::{Command, CommandKind, Error, Instance, Pins, SclPin, SdaPin, SlaveConfig};
 use crate::pac::i2c;
 use crate::time::Hertz;
 use crate::{interrupt, Peripheral};
@@ -35,6 +33,39 @@ impl<T: Instance> interrupt::Handler<T::Interrupt> for InterruptHandler<T> {
         critical_section::with(|_| {
             regs.cr1().modify(|w| w.set_tcie(false));
         });
+
+        // Slave events stay flagged until the driver handles them, mask them in the meantime.
+        if isr.addr() || isr.rxne() || isr.txis() || isr.stopf() || isr.nackf() {
+            T::state().waker.wake();
+            critical_section::with(|_| {
+                regs.cr1().modify(|w| {
+                    w.set_addrie(false);
+                    w.set_rxie(false);
+                    w.set_txie(false);
+                    w.set_stopie(false);
+                    w.set_nackie(false);
+                });
+            });
+        }
+    }
+}
+
+/// Error interrupt handler.
+pub struct ErrorInterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::Handler<T::ErrorInterrupt> for ErrorInterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let regs = T::regs();
+        let isr = regs.isr().read();
+
+        if isr.berr() || isr.arlo() || isr.ovr() || isr.timeout() {
+            T::state().waker.wake();
+            critical_section::with(|_| {
+                regs.cr1().modify(|w| w.set_errie(false));
+            });
+        }
     }
 }
 
@@ -43,6 +74,9 @@ impl<T: Instance> interrupt::Handler<T::Interrupt> for InterruptHandler<T> {
 pub struct Config {
     pub sda_pullup: bool,
     pub scl_pullup: bool,
+    /// Abort transfers with [`Error::Timeout`] when SCL is held low for longer than this, in
+    /// microseconds. Only supported by the instances with SMBus support.
+    pub timeout_us: Option<u32>,
 }
 
 impl Default for Config {
@@ -50,6 +84,7 @@ impl Default for Config {
         Self {
             sda_pullup: false,
             scl_pullup: false,
+            timeout_us: None,
         }
     }
 }
@@ -68,6 +103,7 @@ impl State {
 
 pub struct I2c<'d, T: Instance, TXDMA = NoDma, RXDMA = NoDma> {
     _peri: PeripheralRef<'d, T>,
+    pins: Pins<'d>,
     tx_dma: PeripheralRef<'d, TXDMA>,
     #[allow(dead_code)]
     rx_dma: PeripheralRef<'d, RXDMA>,
@@ -78,35 +114,24 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
         peri: impl Peripheral<P = T> + 'd,
         scl: impl Peripheral<P = impl SclPin<T>> + 'd,
         sda: impl Peripheral<P = impl SdaPin<T>> + 'd,
-        _irq: impl interrupt::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        _irq: impl interrupt::Binding<T::Interrupt, InterruptHandler<T>>
+            + interrupt::Binding<T::ErrorInterrupt, ErrorInterruptHandler<T>>
+            + 'd,
         tx_dma: impl Peripheral<P = TXDMA> + 'd,
         rx_dma: impl Peripheral<P = RXDMA> + 'd,
         freq: Hertz,
         config: Config,
     ) -> Self {
-        into_ref!(peri, scl, sda, tx_dma, rx_dma);
+        into_ref!(peri, tx_dma, rx_dma);
 
         T::enable();
         T::reset();
 
-        unsafe {
-            scl.set_as_af_pull(
-                scl.af_num(),
-                AFType::OutputOpenDrain,
-                match config.scl_pullup {
-                    true => Pull::Up,
-                    false => Pull::None,
-                },
-            );
-            sda.set_as_af_pull(
-                sda.af_num(),
-                AFType::OutputOpenDrain,
-                match config.sda_pullup {
-                    true => Pull::Up,
-                    false => Pull::None,
-                },
-            );
+        let mut pins = Pins::new(scl, sda, config.scl_pullup, config.sda_pullup);
+        if let Err(e) = pins.clear_bus() {
+            warn!("I2C bus recovery failed: {:?}", e);
         }
+        pins.set_as_af();
 
         unsafe {
             T::regs().cr1().modify(|reg| {
@@ -127,6 +152,20 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
             });
         }
 
+        if let Some(timeout_us) = config.timeout_us {
+            // tTIMEOUT = (TIMEOUTA + 1) * 2048 * tI2CCLK, with TIDLE = 0 (SCL low timeout)
+            let ticks = T::frequency().0 as u64 * timeout_us as u64 / 1_000_000 / 2048;
+            assert!((1..=4096).contains(&ticks), "I2C timeout out of range");
+
+            unsafe {
+                T::regs().timeoutr().write(|reg| {
+                    reg.set_timeouta(ticks as u16 - 1);
+                    reg.set_tidle(false);
+                    reg.set_timouten(true);
+                });
+            }
+        }
+
         unsafe {
             T::regs().cr1().modify(|reg| {
                 reg.set_pe(true);
@@ -135,14 +174,36 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
 
         unsafe { T::Interrupt::steal() }.unpend();
         unsafe { T::Interrupt::steal() }.enable();
+        unsafe { T::ErrorInterrupt::steal() }.unpend();
+        unsafe { T::ErrorInterrupt::steal() }.enable();
 
         Self {
             _peri: peri,
+            pins,
             tx_dma,
             rx_dma,
         }
     }
 
+    /// Free the bus if a target holds SDA low, e.g. because it was reset in the middle of a transfer.
+    ///
+    /// This is done by [`new`](Self::new) already, call it again to recover from an
+    /// [`Error::Bus`] or [`Error::Timeout`].
+    pub fn recover_bus(&mut self) -> Result<(), Error> {
+        unsafe {
+            T::regs().cr1().modify(|reg| reg.set_pe(false));
+        }
+
+        let result = self.pins.clear_bus();
+        self.pins.set_as_af();
+
+        unsafe {
+            T::regs().cr1().modify(|reg| reg.set_pe(true));
+        }
+
+        result
+    }
+
     fn master_stop(&mut self) {
         unsafe {
             T::regs().cr2().write(|w| w.set_stop(true));
@@ -254,44 +315,13 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
         Ok(())
     }
 
-    fn flush_txdr(&self) {
-        //if $i2c.isr.read().txis().bit_is_set() {
-        //$i2c.txdr.write(|w| w.txdata().bits(0));
-        //}
-
-        unsafe {
-            if T::regs().isr().read().txis() {
-                T::regs().txdr().write(|w| w.set_txdata(0));
-            }
-            if !T::regs().isr().read().txe() {
-                T::regs().isr().modify(|w| w.set_txe(true))
-            }
-        }
-
-        // If TXDR is not flagged as empty, write 1 to flush it
-        //if $i2c.isr.read().txe().is_not_empty() {
-        //$i2c.isr.write(|w| w.txe().set_bit());
-        //}
-    }
-
     fn wait_txe(&self, check_timeout: impl Fn() -> Result<(), Error>) -> Result<(), Error> {
         loop {
-            unsafe {
-                let isr = T::regs().isr().read();
-                if isr.txe() {
-                    return Ok(());
-                } else if isr.berr() {
-                    T::regs().icr().write(|reg| reg.set_berrcf(true));
-                    return Err(Error::Bus);
-                } else if isr.arlo() {
-                    T::regs().icr().write(|reg| reg.set_arlocf(true));
-                    return Err(Error::Arbitration);
-                } else if isr.nackf() {
-                    T::regs().icr().write(|reg| reg.set_nackcf(true));
-                    self.flush_txdr();
-                    return Err(Error::Nack);
-                }
+            let isr = unsafe { T::regs().isr().read() };
+            if isr.txe() {
+                return Ok(());
             }
+            check_error_flags::<T>(isr)?;
 
             check_timeout()?;
         }
@@ -299,22 +329,11 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
 
     fn wait_rxne(&self, check_timeout: impl Fn() -> Result<(), Error>) -> Result<(), Error> {
         loop {
-            unsafe {
-                let isr = T::regs().isr().read();
-                if isr.rxne() {
-                    return Ok(());
-                } else if isr.berr() {
-                    T::regs().icr().write(|reg| reg.set_berrcf(true));
-                    return Err(Error::Bus);
-                } else if isr.arlo() {
-                    T::regs().icr().write(|reg| reg.set_arlocf(true));
-                    return Err(Error::Arbitration);
-                } else if isr.nackf() {
-                    T::regs().icr().write(|reg| reg.set_nackcf(true));
-                    self.flush_txdr();
-                    return Err(Error::Nack);
-                }
+            let isr = unsafe { T::regs().isr().read() };
+            if isr.rxne() {
+                return Ok(());
             }
+            check_error_flags::<T>(isr)?;
 
             check_timeout()?;
         }
@@ -322,22 +341,11 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
 
     fn wait_tc(&self, check_timeout: impl Fn() -> Result<(), Error>) -> Result<(), Error> {
         loop {
-            unsafe {
-                let isr = T::regs().isr().read();
-                if isr.tc() {
-                    return Ok(());
-                } else if isr.berr() {
-                    T::regs().icr().write(|reg| reg.set_berrcf(true));
-                    return Err(Error::Bus);
-                } else if isr.arlo() {
-                    T::regs().icr().write(|reg| reg.set_arlocf(true));
-                    return Err(Error::Arbitration);
-                } else if isr.nackf() {
-                    T::regs().icr().write(|reg| reg.set_nackcf(true));
-                    self.flush_txdr();
-                    return Err(Error::Nack);
-                }
+            let isr = unsafe { T::regs().isr().read() };
+            if isr.tc() {
+                return Ok(());
             }
+            check_error_flags::<T>(isr)?;
 
             check_timeout()?;
         }
@@ -466,6 +474,8 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
                 if first_slice {
                     w.set_tcie(true);
                 }
+                w.set_nackie(true);
+                w.set_errie(true);
             });
             let dst = regs.txdr().ptr() as *mut u8;
 
@@ -485,6 +495,8 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
                         w.set_txdmaen(false);
                     }
                     w.set_tcie(false);
+                    w.set_nackie(false);
+                    w.set_errie(false);
                 })
             }
         });
@@ -493,6 +505,8 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
             state.waker.register(cx.waker());
 
             let isr = unsafe { T::regs().isr().read() };
+            check_error_flags::<T>(isr)?;
+
             if remaining_len == total_len {
                 // NOTE(unsafe) self.tx_dma does not fiddle with the i2c registers
                 if first_slice {
@@ -563,6 +577,8 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
             regs.cr1().modify(|w| {
                 w.set_rxdmaen(true);
                 w.set_tcie(true);
+                w.set_nackie(true);
+                w.set_errie(true);
             });
             let src = regs.rxdr().ptr() as *mut u8;
 
@@ -580,6 +596,8 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
                 regs.cr1().modify(|w| {
                     w.set_rxdmaen(false);
                     w.set_tcie(false);
+                    w.set_nackie(false);
+                    w.set_errie(false);
                 })
             }
         });
@@ -588,6 +606,8 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
             state.waker.register(cx.waker());
 
             let isr = unsafe { T::regs().isr().read() };
+            check_error_flags::<T>(isr)?;
+
             if remaining_len == total_len {
                 // NOTE(unsafe) self.rx_dma does not fiddle with the i2c registers
                 unsafe {
@@ -828,6 +848,284 @@ impl<'d, T: Instance, TXDMA, RXDMA> I2c<'d, T, TXDMA, RXDMA> {
     }
 }
 
+fn flush_txdr<T: Instance>() {
+    //if $i2c.isr.read().txis().bit_is_set() {
+    //$i2c.txdr.write(|w| w.txdata().bits(0));
+    //}
+
+    unsafe {
+        if T::regs().isr().read().txis() {
+            T::regs().txdr().write(|w| w.set_txdata(0));
+        }
+        if !T::regs().isr().read().txe() {
+            T::regs().isr().modify(|w| w.set_txe(true))
+        }
+    }
+
+    // If TXDR is not flagged as empty, write 1 to flush it
+    //if $i2c.isr.read().txe().is_not_empty() {
+    //$i2c.isr.write(|w| w.txe().set_bit());
+    //}
+}
+
+fn check_error_flags<T: Instance>(isr: i2c::regs::Isr) -> Result<(), Error> {
+    unsafe {
+        if isr.berr() {
+            T::regs().icr().write(|reg| reg.set_berrcf(true));
+            return Err(Error::Bus);
+        } else if isr.arlo() {
+            T::regs().icr().write(|reg| reg.set_arlocf(true));
+            return Err(Error::Arbitration);
+        } else if isr.nackf() {
+            // A STOP is generated automatically after a NACK
+            T::regs().icr().write(|reg| reg.set_nackcf(true));
+            flush_txdr::<T>();
+            return Err(Error::Nack);
+        } else if isr.timeout() {
+            T::regs().icr().write(|reg| reg.set_timoutcf(true));
+            return Err(Error::Timeout);
+        }
+    }
+
+    Ok(())
+}
+
+/// I2C slave (target) driver.
+///
+/// The peripheral answers to the addresses in [`SlaveConfig`] and stretches SCL until the
+/// application has handled each event, so no data is lost if the application is slow.
+pub struct I2cSlave<'d, T: Instance> {
+    _peri: PeripheralRef<'d, T>,
+    _pins: Pins<'d>,
+}
+
+impl<'d, T: Instance> I2cSlave<'d, T> {
+    /// Create a new I2C slave.
+    ///
+    /// `freq` is the bus frequency the controller is expected to use, it sets the data hold time.
+    pub fn new(
+        peri: impl Peripheral<P = T> + 'd,
+        scl: impl Peripheral<P = impl SclPin<T>> + 'd,
+        sda: impl Peripheral<P = impl SdaPin<T>> + 'd,
+        _irq: impl interrupt::Binding<T::Interrupt, InterruptHandler<T>>
+            + interrupt::Binding<T::ErrorInterrupt, ErrorInterruptHandler<T>>
+            + 'd,
+        freq: Hertz,
+        config: SlaveConfig,
+    ) -> Self {
+        into_ref!(peri);
+
+        T::enable();
+        T::reset();
+
+        let mut pins = Pins::new(scl, sda, config.scl_pullup, config.sda_pullup);
+        pins.set_as_af();
+
+        let regs = T::regs();
+        let timings = Timings::new(T::frequency(), freq);
+
+        unsafe {
+            regs.cr1().modify(|reg| {
+                reg.set_pe(false);
+                reg.set_anfoff(false);
+            });
+
+            regs.timingr().write(|reg| {
+                reg.set_presc(timings.prescale);
+                reg.set_scll(timings.scll);
+                reg.set_sclh(timings.sclh);
+                reg.set_sdadel(timings.sdadel);
+                reg.set_scldel(timings.scldel);
+            });
+
+            // The own addresses can only be changed while they are disabled.
+            regs.oar1().write(|reg| reg.set_oa1en(false));
+            regs.oar1().write(|reg| {
+                reg.set_oa1((config.address as u16) << 1);
+                reg.set_oa1mode(i2c::vals::Addmode::BIT7);
+                reg.set_oa1en(true);
+            });
+            regs.oar2().write(|reg| reg.set_oa2en(false));
+            if let Some(address) = config.secondary_address {
+                regs.oar2().write(|reg| {
+                    reg.set_oa2(address);
+                    reg.set_oa2msk(i2c::vals::Oamsk::NOMASK);
+                    reg.set_oa2en(true);
+                });
+            }
+
+            regs.cr1().modify(|reg| {
+                reg.set_gcen(config.general_call);
+                reg.set_nostretch(false);
+                reg.set_sbc(false);
+                reg.set_pe(true);
+            });
+        }
+
+        unsafe { T::Interrupt::steal() }.unpend();
+        unsafe { T::Interrupt::steal() }.enable();
+        unsafe { T::ErrorInterrupt::steal() }.unpend();
+        unsafe { T::ErrorInterrupt::steal() }.enable();
+
+        Self {
+            _peri: peri,
+            _pins: pins,
+        }
+    }
+
+    /// Wait until `done` returns true, with the interrupts set by `enable` unmasked in the meantime.
+    async fn wait_event(
+        &mut self,
+        enable: impl Fn(&mut i2c::regs::Cr1),
+        done: impl Fn(i2c::regs::Isr) -> bool,
+    ) -> Result<i2c::regs::Isr, Error> {
+        let regs = T::regs();
+
+        poll_fn(|cx| {
+            T::state().waker.register(cx.waker());
+
+            let isr = unsafe { regs.isr().read() };
+            if isr.berr() {
+                unsafe { regs.icr().write(|reg| reg.set_berrcf(true)) };
+                return Poll::Ready(Err(Error::Bus));
+            }
+            if isr.arlo() {
+                unsafe { regs.icr().write(|reg| reg.set_arlocf(true)) };
+                return Poll::Ready(Err(Error::Arbitration));
+            }
+            if isr.ovr() {
+                unsafe { regs.icr().write(|reg| reg.set_ovrcf(true)) };
+                return Poll::Ready(Err(Error::Overrun));
+            }
+            if done(isr) {
+                return Poll::Ready(Ok(isr));
+            }
+
+            critical_section::with(|_| unsafe {
+                regs.cr1().modify(|reg| {
+                    enable(reg);
+                    reg.set_errie(true);
+                })
+            });
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Wait for a controller to address us.
+    ///
+    /// SCL is held low until the returned command is answered with [`respond_to_read`](Self::respond_to_read)
+    /// or [`respond_to_write`](Self::respond_to_write), which must be called next.
+    pub async fn listen(&mut self) -> Result<Command, Error> {
+        let isr = self.wait_event(|reg| reg.set_addrie(true), |isr| isr.addr()).await?;
+
+        let kind = match isr.dir() {
+            i2c::vals::Dir::READ => CommandKind::Read,
+            _ => CommandKind::Write,
+        };
+
+        Ok(Command {
+            kind,
+            address: isr.addcode(),
+        })
+    }
+
+    /// Receive the data of a write command into `buffer`.
+    ///
+    /// Returns once the controller ends the transfer with a STOP or a repeated START, in the latter
+    /// case the next [`listen`](Self::listen) returns immediately. Bytes that do not fit in `buffer`
+    /// are acknowledged and dropped. Returns the number of bytes stored.
+    pub async fn respond_to_write(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let regs = T::regs();
+        let mut len = 0;
+
+        // Clearing ADDR releases SCL
+        unsafe { regs.icr().write(|reg| reg.set_addrcf(true)) };
+
+        loop {
+            let isr = self
+                .wait_event(
+                    |reg| {
+                        reg.set_rxie(true);
+                        reg.set_stopie(true);
+                        reg.set_addrie(true);
+                    },
+                    |isr| isr.rxne() || isr.stopf() || isr.addr(),
+                )
+                .await?;
+
+            if isr.rxne() {
+                let byte = unsafe { regs.rxdr().read().rxdata() };
+                if let Some(b) = buffer.get_mut(len) {
+                    *b = byte;
+                    len += 1;
+                }
+            } else {
+                if isr.stopf() {
+                    unsafe { regs.icr().write(|reg| reg.set_stopcf(true)) };
+                }
+                return Ok(len);
+            }
+        }
+    }
+
+    /// Send `data` in response to a read command.
+    ///
+    /// If the controller reads more than `data.len()` bytes, 0xFF is sent for the rest. Returns
+    /// the number of bytes the controller has read.
+    pub async fn respond_to_read(&mut self, data: &[u8]) -> Result<usize, Error> {
+        let regs = T::regs();
+        let mut len: usize = 0;
+
+        unsafe {
+            // Drop a byte that is left over from a previous transfer
+            regs.isr().modify(|reg| reg.set_txe(true));
+            regs.icr().write(|reg| reg.set_addrcf(true));
+        }
+
+        loop {
+            let isr = self
+                .wait_event(
+                    |reg| {
+                        reg.set_txie(true);
+                        reg.set_nackie(true);
+                        reg.set_stopie(true);
+                        reg.set_addrie(true);
+                    },
+                    |isr| isr.txis() || isr.nackf() || isr.stopf() || isr.addr(),
+                )
+                .await?;
+
+            if isr.nackf() {
+                // The controller does not want any more data. TXDR already holds the next byte
+                // unless it is empty.
+                unsafe { regs.icr().write(|reg| reg.set_nackcf(true)) };
+                if !isr.txe() {
+                    len = len.saturating_sub(1);
+                }
+            } else if isr.txis() {
+                let byte = data.get(len).copied().unwrap_or(0xFF);
+                unsafe { regs.txdr().write(|reg| reg.set_txdata(byte)) };
+                len += 1;
+            } else {
+                if isr.stopf() {
+                    unsafe { regs.icr().write(|reg| reg.set_stopcf(true)) };
+                }
+                return Ok(len);
+            }
+        }
+    }
+}
+
+impl<'d, T: Instance> Drop for I2cSlave<'d, T> {
+    fn drop(&mut self) {
+        // Stop acknowledging our address, otherwise the bus would be held forever.
+        unsafe {
+            T::regs().cr1().modify(|reg| reg.set_pe(false));
+        }
+    }
+}
+
 mod eh02 {
     use super::*;
 
@@ -996,6 +1294,7 @@ mod eh1 {
                 Self::Crc => embedded_hal_1::i2c::ErrorKind::Other,
                 Self::Overrun => embedded_hal_1::i2c::ErrorKind::Overrun,
                 Self::ZeroLengthTransfer => embedded_hal_1::i2c::ErrorKind::Other,
+                Self::BusStuck => embedded_hal_1::i2c::ErrorKind::Bus,
             }
         }
     }
@@ -1,5 +1,14 @@
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::sync::atomic::{compiler_fence, Ordering};
+use core::task::{Context, Poll};
+
 use embassy_hal_common::into_ref;
+use futures::Stream;
 
+use crate::dma::ringbuffer::OverrunError;
+use crate::dma::RingBuffer;
 use crate::gpio::sealed::{AFType, Pin as _};
 use crate::gpio::AnyPin;
 use crate::pac::spi::vals;
@@ -153,14 +162,24 @@ impl<'d, T: Instance, Tx, Rx> I2S<'d, T, Tx, Rx> {
     ) -> Self {
         into_ref!(sd, ws, ck, mck);
 
+        let sd_af_type = match config.function {
+            Function::Transmit => AFType::OutputPushPull,
+            Function::Receive => AFType::Input,
+        };
+        // The master drives the clock and word select, the slave follows them
+        let clock_af_type = match config.mode {
+            Mode::Master => AFType::OutputPushPull,
+            Mode::Slave => AFType::Input,
+        };
+
         unsafe {
-            sd.set_as_af(sd.af_num(), AFType::OutputPushPull);
+            sd.set_as_af(sd.af_num(), sd_af_type);
             sd.set_speed(crate::gpio::Speed::VeryHigh);
 
-            ws.set_as_af(ws.af_num(), AFType::OutputPushPull);
+            ws.set_as_af(ws.af_num(), clock_af_type);
             ws.set_speed(crate::gpio::Speed::VeryHigh);
 
-            ck.set_as_af(ck.af_num(), AFType::OutputPushPull);
+            ck.set_as_af(ck.af_num(), clock_af_type);
             ck.set_speed(crate::gpio::Speed::VeryHigh);
 
             mck.set_as_af(mck.af_num(), AFType::OutputPushPull);
@@ -260,6 +279,152 @@ impl<'d, T: Instance, Tx, Rx> I2S<'d, T, Tx, Rx> {
     {
         self._peri.read(data).await
     }
+
+    /// Start a continuous reception into `dma_buf`, handed out in blocks of `N` samples by the
+    /// returned stream. Samples are half-words, so 24 and 32 bit data take two of them.
+    ///
+    /// `dma_buf` must hold at least two blocks, so one can be copied out while the DMA controller
+    /// fills the other.
+    pub fn stream<'a, const N: usize>(&'a mut self, dma_buf: &'a mut [u16]) -> I2sStream<'a, T, Rx, N>
+    where
+        Rx: RxDma<T>,
+    {
+        assert!(N > 0 && dma_buf.len() >= 2 * N);
+
+        let request = self._peri.rxdma.request();
+        let src = T::REGS.rx_ptr();
+        let mut ring_buf =
+            unsafe { RingBuffer::new_read(&mut self._peri.rxdma, request, src, dma_buf, Default::default()) };
+
+        // Drop any sample received, and the overrun it caused, before the DMA was started
+        unsafe {
+            let _ = T::REGS.dr().read();
+            let _ = T::REGS.sr().read();
+        }
+
+        compiler_fence(Ordering::SeqCst);
+
+        ring_buf.start();
+        set_rxdmaen(T::REGS, true);
+
+        I2sStream {
+            _phantom: PhantomData,
+            ring_buf,
+        }
+    }
+
+    /// Start a continuous transmission of `dma_buf`, and return a writer to fill it with the next
+    /// samples. The buffer starts out silent, and its length sets the latency of the written samples.
+    pub fn ring_buffered_writer<'a>(&'a mut self, dma_buf: &'a mut [u16]) -> RingBufferedI2sWriter<'a, T, Tx>
+    where
+        Tx: TxDma<T>,
+    {
+        dma_buf.fill(0);
+
+        let request = self._peri.txdma.request();
+        let dst = T::REGS.tx_ptr();
+        let mut ring_buf =
+            unsafe { RingBuffer::new_write(&mut self._peri.txdma, request, dma_buf, dst, Default::default()) };
+
+        compiler_fence(Ordering::SeqCst);
+
+        ring_buf.start();
+        set_txdmaen(T::REGS, true);
+
+        RingBufferedI2sWriter {
+            _phantom: PhantomData,
+            ring_buf,
+        }
+    }
+}
+
+/// Continuous reception of blocks of `N` samples, see [`I2S::stream`].
+pub struct I2sStream<'a, T: Instance, C: RxDma<T>, const N: usize> {
+    _phantom: PhantomData<T>,
+    ring_buf: RingBuffer<'a, C, u16>,
+}
+
+impl<'a, T: Instance, C: RxDma<T>, const N: usize> Unpin for I2sStream<'a, T, C, N> {}
+
+impl<'a, T: Instance, C: RxDma<T>, const N: usize> Stream for I2sStream<'a, T, C, N> {
+    type Item = Result<[u16; N], Error>;
+
+    /// Returns the next block, or `Error::Overrun` if samples were overwritten before they were
+    /// read. Reception goes on after an overrun, from the latest samples.
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.ring_buf.set_waker(cx.waker());
+
+        compiler_fence(Ordering::SeqCst);
+
+        self.ring_buf.reload_position();
+        if self.ring_buf.len() < N {
+            return Poll::Pending;
+        }
+
+        let mut block = [0; N];
+        let mut pos = 0;
+        while pos < N {
+            match self.ring_buf.read(&mut block[pos..]) {
+                Ok(len) => pos += len,
+                Err(OverrunError) => {
+                    self.ring_buf.clear();
+                    return Poll::Ready(Some(Err(Error::Overrun)));
+                }
+            }
+        }
+
+        Poll::Ready(Some(Ok(block)))
+    }
+}
+
+impl<'a, T: Instance, C: RxDma<T>, const N: usize> Drop for I2sStream<'a, T, C, N> {
+    fn drop(&mut self) {
+        set_rxdmaen(T::REGS, false);
+    }
+}
+
+/// Continuous transmission from a ring buffer, see [`I2S::ring_buffered_writer`].
+pub struct RingBufferedI2sWriter<'a, T: Instance, C: TxDma<T>> {
+    _phantom: PhantomData<T>,
+    ring_buf: RingBuffer<'a, C, u16>,
+}
+
+impl<'a, T: Instance, C: TxDma<T>> RingBufferedI2sWriter<'a, T, C> {
+    /// Write samples, waiting for the DMA controller to transfer enough of the buffer to make
+    /// room for them.
+    ///
+    /// `Error::Overrun` is returned if the buffer ran empty, and old samples were transmitted
+    /// again. Transmission goes on, so the remaining samples can be written by calling this again.
+    pub async fn write(&mut self, data: &[u16]) -> Result<(), Error> {
+        let mut pos = 0;
+
+        poll_fn(|cx| {
+            self.ring_buf.set_waker(cx.waker());
+
+            compiler_fence(Ordering::SeqCst);
+
+            self.ring_buf.reload_position();
+            while pos < data.len() {
+                match self.ring_buf.write(&data[pos..]) {
+                    Ok(0) => return Poll::Pending,
+                    Ok(len) => pos += len,
+                    Err(OverrunError) => {
+                        self.ring_buf.clear();
+                        return Poll::Ready(Err(Error::Overrun));
+                    }
+                }
+            }
+
+            Poll::Ready(Ok(()))
+        })
+        .await
+    }
+}
+
+impl<'a, T: Instance, C: TxDma<T>> Drop for RingBufferedI2sWriter<'a, T, C> {
+    fn drop(&mut self) {
+        set_txdmaen(T::REGS, false);
+    }
 }
 
 impl<'d, T: Instance, Tx, Rx> Drop for I2S<'d, T, Tx, Rx> {
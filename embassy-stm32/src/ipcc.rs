@@ -1,9 +1,76 @@
+use core::future::poll_fn;
+use core::task::Poll;
+
+use atomic_polyfill::{AtomicU8, Ordering};
 use embassy_hal_common::{into_ref, Peripheral, PeripheralRef};
+use embassy_sync::waitqueue::AtomicWaker;
 
+use crate::interrupt;
 use crate::ipcc::sealed::Instance;
 use crate::peripherals::IPCC;
 use crate::rcc::sealed::RccPeripheral;
 
+const CHANNEL_COUNT: usize = 6;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NEW_AW: AtomicWaker = AtomicWaker::new();
+static TX_WAKERS: [AtomicWaker; CHANNEL_COUNT] = [NEW_AW; CHANNEL_COUNT];
+static RX_WAKERS: [AtomicWaker; CHANNEL_COUNT] = [NEW_AW; CHANNEL_COUNT];
+/// Bit `n` is set while a task waits for channel `n` to be released by CPU2.
+static TX_WAITING: AtomicU8 = AtomicU8::new(0);
+/// Bit `n` is set while a task waits for CPU2 to send on channel `n`.
+static RX_WAITING: AtomicU8 = AtomicU8::new(0);
+
+/// Receive interrupt handler, for the async API of [`Ipcc`].
+///
+/// Not needed if the [`tl_mbox`](crate::tl_mbox) interrupt handlers are bound.
+pub struct ReceiveInterruptHandler {}
+
+impl interrupt::Handler<interrupt::IPCC_C1_RX> for ReceiveInterruptHandler {
+    unsafe fn on_interrupt() {
+        on_rx_interrupt();
+    }
+}
+
+/// Transmit interrupt handler, for the async API of [`Ipcc`].
+///
+/// Not needed if the [`tl_mbox`](crate::tl_mbox) interrupt handlers are bound.
+pub struct TransmitInterruptHandler {}
+
+impl interrupt::Handler<interrupt::IPCC_C1_TX> for TransmitInterruptHandler {
+    unsafe fn on_interrupt() {
+        on_tx_interrupt();
+    }
+}
+
+/// Masks the channels CPU2 has sent on and wakes the tasks waiting for them.
+pub(crate) fn on_rx_interrupt() {
+    let regs = IPCC::regs();
+    let waiting = RX_WAITING.load(Ordering::Acquire);
+
+    for (n, waker) in RX_WAKERS.iter().enumerate() {
+        if waiting & (1 << n) != 0 && unsafe { regs.cpu(1).sr().read().chf(n) } {
+            unsafe { regs.cpu(0).mr().modify(|w| w.set_chom(n, true)) };
+            RX_WAITING.fetch_and(!(1 << n), Ordering::AcqRel);
+            waker.wake();
+        }
+    }
+}
+
+/// Masks the channels released by CPU2 and wakes the tasks waiting for them.
+pub(crate) fn on_tx_interrupt() {
+    let regs = IPCC::regs();
+    let waiting = TX_WAITING.load(Ordering::Acquire);
+
+    for (n, waker) in TX_WAKERS.iter().enumerate() {
+        if waiting & (1 << n) != 0 && unsafe { !regs.cpu(0).sr().read().chf(n) } {
+            unsafe { regs.cpu(0).mr().modify(|w| w.set_chfm(n, true)) };
+            TX_WAITING.fetch_and(!(1 << n), Ordering::AcqRel);
+            waker.wake();
+        }
+    }
+}
+
 #[non_exhaustive]
 #[derive(Clone, Copy, Default)]
 pub struct Config {
@@ -163,6 +230,78 @@ impl<'d> Ipcc<'d> {
         self.c2_is_active_flag(channel) && self.c1_get_rx_channel(channel)
     }
 
+    /// Waits until CPU2 released `channel`, then calls `f` to fill the shared memory of the channel and
+    /// notifies CPU2.
+    ///
+    /// Requires the [`TransmitInterruptHandler`] or the [`tl_mbox`](crate::tl_mbox) interrupt handlers to be
+    /// bound. Channels used by the [`tl_mbox`](crate::tl_mbox) must not be used concurrently.
+    pub async fn send(&mut self, channel: IpccChannel, f: impl FnOnce()) {
+        self.flush(channel).await;
+
+        f();
+        self.c1_set_flag_channel(channel);
+    }
+
+    /// Waits until CPU2 released `channel`, i.e. it has processed the data sent last on it.
+    pub async fn flush(&mut self, channel: IpccChannel) {
+        let n = channel as usize;
+
+        poll_fn(|cx| {
+            TX_WAKERS[n].register(cx.waker());
+
+            if !self.c1_is_active_flag(channel) {
+                return Poll::Ready(());
+            }
+
+            critical_section::with(|_| {
+                TX_WAITING.fetch_or(1 << n, Ordering::AcqRel);
+                self.c1_set_tx_channel(channel, true);
+            });
+
+            // CPU2 may have released the channel before the interrupt was enabled
+            if !self.c1_is_active_flag(channel) {
+                return Poll::Ready(());
+            }
+
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Waits until CPU2 sent on `channel`, then calls `f` to read the shared memory of the channel and
+    /// releases the channel to CPU2.
+    ///
+    /// Requires the [`ReceiveInterruptHandler`] or the [`tl_mbox`](crate::tl_mbox) interrupt handlers to be
+    /// bound. Channels used by the [`tl_mbox`](crate::tl_mbox) must not be used concurrently.
+    pub async fn receive<R>(&mut self, channel: IpccChannel, f: impl FnOnce() -> R) -> R {
+        let n = channel as usize;
+
+        poll_fn(|cx| {
+            RX_WAKERS[n].register(cx.waker());
+
+            if self.c2_is_active_flag(channel) {
+                return Poll::Ready(());
+            }
+
+            critical_section::with(|_| {
+                RX_WAITING.fetch_or(1 << n, Ordering::AcqRel);
+                self.c1_set_rx_channel(channel, true);
+            });
+
+            if self.c2_is_active_flag(channel) {
+                return Poll::Ready(());
+            }
+
+            Poll::Pending
+        })
+        .await;
+
+        let ret = f();
+        self.c1_clear_flag_channel(channel);
+
+        ret
+    }
+
     pub fn as_mut_ptr(&self) -> *mut Self {
         unsafe { &mut core::ptr::read(self) as *mut _ }
     }
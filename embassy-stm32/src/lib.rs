@@ -13,7 +13,7 @@ mod traits;
 pub mod dma;
 pub mod gpio;
 pub mod rcc;
-#[cfg(feature = "_time-driver")]
+#[cfg(all(feature = "_time-driver", not(any(time_driver_rtc, time_driver_lptim1))))]
 mod time_driver;
 pub mod timer;
 
@@ -43,6 +43,8 @@ pub mod flash;
 pub mod i2s;
 #[cfg(stm32wb)]
 pub mod ipcc;
+#[cfg(lptim)]
+pub mod lptim;
 pub mod pwm;
 #[cfg(quadspi)]
 pub mod qspi;
@@ -50,6 +52,8 @@ pub mod qspi;
 pub mod rng;
 #[cfg(all(rtc, not(rtc_v1)))]
 pub mod rtc;
+#[cfg(all(sai, not(gpdma)))]
+pub mod sai;
 #[cfg(sdmmc)]
 pub mod sdmmc;
 #[cfg(spi)]
@@ -123,6 +127,12 @@ pub(crate) use stm32_metapac as pac;
 #[non_exhaustive]
 pub struct Config {
     pub rcc: rcc::Config,
+    /// Configuration of the RTC used by the time driver
+    #[cfg(time_driver_rtc)]
+    pub rtc: rtc::RtcConfig,
+    /// Kernel clock of the LPTIM used by the time driver
+    #[cfg(time_driver_lptim1)]
+    pub lptim_clock: lptim::ClockSource,
     #[cfg(dbgmcu)]
     pub enable_debug_during_sleep: bool,
     #[cfg(bdma)]
@@ -137,6 +147,10 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             rcc: Default::default(),
+            #[cfg(time_driver_rtc)]
+            rtc: Default::default(),
+            #[cfg(time_driver_lptim1)]
+            lptim_clock: lptim::ClockSource::Lse,
             #[cfg(dbgmcu)]
             enable_debug_during_sleep: true,
             #[cfg(bdma)]
@@ -197,8 +211,12 @@ pub fn init(config: Config) -> Peripherals {
         rcc::init(config.rcc);
 
         // must be after rcc init
-        #[cfg(feature = "_time-driver")]
+        #[cfg(all(feature = "_time-driver", not(any(time_driver_rtc, time_driver_lptim1))))]
         time_driver::init();
+        #[cfg(time_driver_rtc)]
+        rtc::time_driver::init(config.rtc);
+        #[cfg(time_driver_lptim1)]
+        lptim::time_driver::init(config.lptim_clock);
     }
 
     p
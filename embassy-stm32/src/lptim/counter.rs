@@ -0,0 +1,89 @@
+//! Pulse counter: the LPTIM counts the edges of an external input, without any kernel clock, so
+//! pulses keep being counted in STOP modes.
+use embassy_hal_common::{into_ref, PeripheralRef};
+
+use super::{read_cnt, set_external_clock, set_prescaler, Input1Pin, Instance};
+#[allow(unused_imports)]
+use crate::gpio::sealed::{AFType, Pin};
+use crate::gpio::{AnyPin, Pull};
+use crate::Peripheral;
+
+/// Edge of the input which is counted
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Edge {
+    Rising,
+    Falling,
+}
+
+pub struct PulseCounter<'d, T: Instance> {
+    _inner: PeripheralRef<'d, T>,
+    pin: PeripheralRef<'d, AnyPin>,
+}
+
+impl<'d, T: Instance> PulseCounter<'d, T> {
+    pub fn new(
+        tim: impl Peripheral<P = T> + 'd,
+        pin: impl Peripheral<P = impl Input1Pin<T>> + 'd,
+        pull: Pull,
+        edge: Edge,
+    ) -> Self {
+        into_ref!(tim, pin);
+
+        T::enable();
+        T::reset();
+
+        unsafe {
+            pin.set_as_af_pull(pin.af_num(), AFType::Input, pull);
+
+            T::regs().cfgr().write(|w| {
+                set_prescaler(w, 0);
+                set_external_clock(w, true);
+                w.set_ckpol(match edge {
+                    Edge::Rising => 0,
+                    Edge::Falling => 1,
+                });
+            });
+        }
+
+        let r = T::regs();
+        unsafe {
+            r.cr().write(|w| w.set_enable(true));
+            // The write is synchronized with the input, so it can't be waited for. The register
+            // keeps its value when the timer is disabled, so it is only written once.
+            r.arr().write(|w| w.set_arr(u16::MAX));
+            r.cr().modify(|w| w.set_cntstrt(true));
+        }
+
+        Self {
+            _inner: tim,
+            pin: pin.map_into(),
+        }
+    }
+
+    /// Number of edges counted, wrapping around after 65535.
+    pub fn count(&self) -> u16 {
+        unsafe { read_cnt(T::regs()) }
+    }
+
+    /// Set the count to 0.
+    pub fn reset(&mut self) {
+        // The counter can only be cleared by disabling the timer
+        let r = T::regs();
+        unsafe {
+            r.cr().write(|w| w.set_enable(false));
+            r.cr().write(|w| w.set_enable(true));
+            r.cr().modify(|w| w.set_cntstrt(true));
+        }
+    }
+}
+
+impl<'d, T: Instance> Drop for PulseCounter<'d, T> {
+    fn drop(&mut self) {
+        unsafe {
+            T::regs().cr().write(|w| w.set_enable(false));
+            self.pin.set_as_disconnected();
+        }
+        T::disable();
+    }
+}
@@ -0,0 +1,148 @@
+//! Low-power timer (LPTIM)
+//!
+//! The LPTIM is a 16 bit timer which can be clocked by LSI or LSE, or by an external input, and
+//! keeps running in STOP modes.
+pub mod counter;
+pub mod pwm;
+#[cfg(time_driver_lptim1)]
+pub(crate) mod time_driver;
+
+use crate::pac::lptim::{regs, Lptim};
+use crate::rcc::RccPeripheral;
+use crate::time::Hertz;
+use crate::{peripherals, Peripheral};
+
+/// Kernel clock of the LPTIM
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ClockSource {
+    /// APB clock, stopped in STOP modes
+    Pclk,
+    /// 32 kHz LSI, enabled by the driver
+    Lsi,
+    /// 16 MHz HSI, enabled by the driver
+    Hsi,
+    /// 32.768 kHz LSE crystal, enabled by the driver
+    Lse,
+}
+
+/// Select the kernel clock of `T`, returning its frequency.
+fn enable_clock<T: Instance>(source: ClockSource) -> Hertz {
+    let (sel, freq) = match source {
+        ClockSource::Pclk => (0, T::frequency()),
+        ClockSource::Lsi => {
+            unsafe {
+                crate::pac::RCC.csr().modify(|w| w.set_lsion(true));
+                while !crate::pac::RCC.csr().read().lsirdy() {}
+            }
+            (1, crate::rcc::LSI_FREQ)
+        }
+        ClockSource::Hsi => {
+            unsafe {
+                crate::pac::RCC.cr().modify(|w| w.set_hsion(true));
+                while !crate::pac::RCC.cr().read().hsirdy() {}
+            }
+            (2, crate::rcc::HSI_FREQ)
+        }
+        ClockSource::Lse => {
+            unsafe { crate::rcc::enable_lse() };
+            (3, Hertz(32_768))
+        }
+    };
+
+    T::select_clock(sel);
+    freq
+}
+
+fn set_prescaler(w: &mut regs::Cfgr, presc: u8) {
+    #[cfg(lptim_v1)]
+    w.set_presc(presc);
+    #[cfg(lptim_g0)]
+    w.set_presc(crate::pac::lptim::vals::Presc(presc));
+}
+
+fn set_external_clock(w: &mut regs::Cfgr, external: bool) {
+    #[cfg(lptim_v1)]
+    w.set_cksel(external);
+    #[cfg(lptim_g0)]
+    w.set_cksel(crate::pac::lptim::vals::Cksel(external as u8));
+}
+
+/// Write the autoreload register and wait until it is taken into account. The kernel clock
+/// must be running.
+unsafe fn write_arr(r: Lptim, arr: u16) {
+    r.icr().write(|w| w.set_arrokcf(true));
+    r.arr().write(|w| w.set_arr(arr));
+    while !r.isr().read().arrok() {}
+}
+
+/// Write the compare register and wait until it is taken into account. The kernel clock must
+/// be running.
+unsafe fn write_cmp(r: Lptim, cmp: u16) {
+    r.icr().write(|w| w.set_cmpokcf(true));
+    r.cmp().write(|w| w.set_cmp(cmp));
+    while !r.isr().read().cmpok() {}
+}
+
+/// Read the counter, which is not synchronized with the APB clock: it is only valid when two
+/// consecutive reads return the same value.
+unsafe fn read_cnt(r: Lptim) -> u16 {
+    loop {
+        let cnt = r.cnt().read().cnt();
+        if r.cnt().read().cnt() == cnt {
+            return cnt;
+        }
+    }
+}
+
+pub(crate) mod sealed {
+    pub trait Instance: crate::rcc::RccPeripheral {
+        fn regs() -> crate::pac::lptim::Lptim;
+
+        /// Write the kernel clock selection of the RCC, 0 to 3 for PCLK, LSI, HSI and LSE.
+        fn select_clock(sel: u8);
+    }
+}
+
+pub trait Instance: Peripheral<P = Self> + sealed::Instance + RccPeripheral + 'static {}
+
+pin_trait!(OutputPin, Instance);
+pin_trait!(Input1Pin, Instance);
+pin_trait!(Input2Pin, Instance);
+pin_trait!(ExternalTriggerPin, Instance);
+
+macro_rules! impl_lptim {
+    ($inst:ident, $set_sel:ident, $sel:ident) => {
+        impl sealed::Instance for peripherals::$inst {
+            fn regs() -> crate::pac::lptim::Lptim {
+                crate::pac::$inst
+            }
+
+            fn select_clock(sel: u8) {
+                #[cfg(rcc_f7)]
+                unsafe {
+                    crate::pac::RCC
+                        .dckcfgr2()
+                        .modify(|w| w.$set_sel(crate::pac::rcc::vals::Lptimsel(sel)))
+                };
+                #[cfg(rcc_g0)]
+                unsafe {
+                    crate::pac::RCC
+                        .ccipr()
+                        .modify(|w| w.$set_sel(crate::pac::rcc::vals::$sel(sel)))
+                };
+            }
+        }
+
+        impl Instance for peripherals::$inst {}
+    };
+}
+
+foreach_peripheral!(
+    (lptim, LPTIM1) => {
+        impl_lptim!(LPTIM1, set_lptim1sel, Lptim1sel);
+    };
+    (lptim, LPTIM2) => {
+        impl_lptim!(LPTIM2, set_lptim2sel, Lptim2sel);
+    };
+);
@@ -0,0 +1,84 @@
+//! PWM output of the LPTIM, which can keep running in STOP modes with the LSI or LSE clock.
+use embassy_hal_common::{into_ref, PeripheralRef};
+
+use super::{enable_clock, set_prescaler, write_arr, write_cmp, ClockSource, Instance, OutputPin};
+#[allow(unused_imports)]
+use crate::gpio::sealed::{AFType, Pin};
+use crate::gpio::AnyPin;
+use crate::time::Hertz;
+use crate::Peripheral;
+
+pub struct LptimPwm<'d, T: Instance> {
+    _inner: PeripheralRef<'d, T>,
+    pin: PeripheralRef<'d, AnyPin>,
+}
+
+impl<'d, T: Instance> LptimPwm<'d, T> {
+    /// Output a PWM signal at `freq` on `pin`, with a duty cycle of 0 until set.
+    ///
+    /// The prescaler is chosen to give the finest resolution, with up to 65536 steps.
+    pub fn new(
+        tim: impl Peripheral<P = T> + 'd,
+        pin: impl Peripheral<P = impl OutputPin<T>> + 'd,
+        clock: ClockSource,
+        freq: Hertz,
+    ) -> Self {
+        into_ref!(tim, pin);
+
+        T::enable();
+        T::reset();
+
+        let ticks = enable_clock::<T>(clock).0 / freq.0;
+        let presc = unwrap!((0..8).find(|presc| ticks >> presc <= 0x1_0000));
+        let arr = (ticks >> presc) - 1;
+        assert!(arr > 0);
+
+        unsafe {
+            pin.set_low();
+            pin.set_as_af(pin.af_num(), AFType::OutputPushPull);
+
+            let r = T::regs();
+            r.cfgr().write(|w| {
+                set_prescaler(w, presc);
+                // The output is set when the counter passes the compare value, and reset when it
+                // reaches the autoreload value.
+                w.set_wave(false);
+                w.set_wavpol(false);
+                // Update the compare value at the end of the period
+                w.set_preload(true);
+            });
+            r.cr().write(|w| w.set_enable(true));
+
+            write_arr(r, arr as u16);
+            write_cmp(r, arr as u16);
+
+            r.cr().modify(|w| w.set_cntstrt(true));
+        }
+
+        Self {
+            _inner: tim,
+            pin: pin.map_into(),
+        }
+    }
+
+    pub fn get_max_duty(&self) -> u16 {
+        unsafe { T::regs().arr().read().arr() }
+    }
+
+    /// Set the duty cycle, from 0 to [`LptimPwm::get_max_duty`] included.
+    pub fn set_duty(&mut self, duty: u16) {
+        let max = self.get_max_duty();
+        assert!(duty <= max);
+        unsafe { write_cmp(T::regs(), max - duty) }
+    }
+}
+
+impl<'d, T: Instance> Drop for LptimPwm<'d, T> {
+    fn drop(&mut self) {
+        unsafe {
+            T::regs().cr().write(|w| w.set_enable(false));
+            self.pin.set_as_disconnected();
+        }
+        T::disable();
+    }
+}
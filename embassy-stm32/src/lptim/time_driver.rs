@@ -0,0 +1,261 @@
+//! Time driver based on LPTIM1.
+//!
+//! Clocked by LSE or LSI, the timer keeps running in STOP modes, so timers keep working while
+//! the chip is in deep sleep. The kernel clock divided by a power of 2 up to 128 must match the
+//! tick rate of embassy-time, e.g. the LSE with the `tick-hz-32_768` feature.
+//!
+//! The timer has a single compare register, so a single alarm is available. Alarms more than 2^16
+//! ticks away wake the chip up every 2^16 ticks until they are due.
+use core::cell::Cell;
+use core::{mem, ptr};
+
+use atomic_polyfill::{AtomicBool, AtomicU32, AtomicU8, Ordering};
+use critical_section::CriticalSection;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::driver::{AlarmHandle, Driver};
+use embassy_time::TICK_HZ;
+
+use super::sealed::Instance;
+use super::{enable_clock, read_cnt, set_prescaler, write_arr, ClockSource};
+use crate::interrupt::InterruptExt;
+use crate::rcc::sealed::RccPeripheral;
+use crate::{interrupt, peripherals};
+
+const ALARM_COUNT: usize = 1;
+
+type T = peripherals::LPTIM1;
+
+/// EXTI line of the LPTIM1 wakeup event. It is a direct line, always enabled, on other chips.
+#[cfg(stm32f7)]
+const EXTI_LINE: usize = 23;
+
+foreach_interrupt! {
+    (LPTIM1, lptim, $block:ident, GLOBAL, $irq:ident) => {
+        type Interrupt = crate::interrupt::$irq;
+
+        #[interrupt]
+        fn $irq() {
+            DRIVER.on_interrupt()
+        }
+    };
+}
+
+struct AlarmState {
+    timestamp: Cell<u64>,
+
+    // This is really a Option<(fn(*mut ()), *mut ())>
+    // but fn pointers aren't allowed in const yet
+    callback: Cell<*const ()>,
+    ctx: Cell<*mut ()>,
+}
+
+unsafe impl Send for AlarmState {}
+
+impl AlarmState {
+    const fn new() -> Self {
+        Self {
+            timestamp: Cell::new(u64::MAX),
+            callback: Cell::new(ptr::null()),
+            ctx: Cell::new(ptr::null_mut()),
+        }
+    }
+}
+
+struct LptimDriver {
+    /// Number of 2^16 periods elapsed since boot.
+    period: AtomicU32,
+    alarm_count: AtomicU8,
+    /// A compare value was written, and not yet taken into account by the timer.
+    cmp_pending: AtomicBool,
+    /// Timestamp at which to fire alarm. u64::MAX if no alarm is scheduled.
+    alarms: Mutex<CriticalSectionRawMutex, [AlarmState; ALARM_COUNT]>,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ALARM_STATE_NEW: AlarmState = AlarmState::new();
+
+embassy_time::time_driver_impl!(static DRIVER: LptimDriver = LptimDriver {
+    period: AtomicU32::new(0),
+    alarm_count: AtomicU8::new(0),
+    cmp_pending: AtomicBool::new(false),
+    alarms: Mutex::const_new(CriticalSectionRawMutex::new(), [ALARM_STATE_NEW; ALARM_COUNT]),
+});
+
+impl LptimDriver {
+    fn init(&'static self, clock: ClockSource) {
+        let r = T::regs();
+
+        <T as RccPeripheral>::enable();
+        <T as RccPeripheral>::reset();
+
+        let freq = enable_clock::<T>(clock).0;
+        assert!(freq % TICK_HZ as u32 == 0);
+        // The prescaler divides by a power of 2 up to 128
+        let div = freq / TICK_HZ as u32;
+        if !div.is_power_of_two() || div > 128 {
+            panic!("LPTIM clock of {} Hz can't be divided to the tick rate", freq);
+        }
+        let presc = div.trailing_zeros() as u8;
+
+        critical_section::with(|_| unsafe {
+            r.cfgr().write(|w| set_prescaler(w, presc));
+            // The interrupts can only be configured while the timer is disabled, the compare
+            // match interrupt is always enabled and the alarm is checked when it fires.
+            r.ier().write(|w| {
+                w.set_arrmie(true);
+                w.set_cmpmie(true);
+            });
+            r.cr().write(|w| w.set_enable(true));
+            write_arr(r, u16::MAX);
+
+            #[cfg(stm32f7)]
+            {
+                use crate::pac::EXTI;
+
+                EXTI.rtsr(0).modify(|w| w.set_line(EXTI_LINE, true));
+                EXTI.imr(0).modify(|w| w.set_line(EXTI_LINE, true));
+            }
+
+            let irq: Interrupt = mem::transmute(());
+            irq.unpend();
+            irq.enable();
+
+            r.cr().modify(|w| w.set_cntstrt(true));
+        })
+    }
+
+    fn on_interrupt(&self) {
+        let r = T::regs();
+
+        critical_section::with(|cs| unsafe {
+            let isr = r.isr().read();
+            r.icr().write(|w| {
+                w.set_arrmcf(isr.arrm());
+                w.set_cmpmcf(isr.cmpm());
+            });
+
+            #[cfg(stm32f7)]
+            {
+                use crate::pac::exti::regs::Lines;
+                use crate::pac::EXTI;
+
+                let mut lines = Lines(0);
+                lines.set_line(EXTI_LINE, true);
+                EXTI.pr(0).write_value(lines);
+            }
+
+            if isr.arrm() {
+                // The flag is set when the counter reaches the autoreload value, one tick
+                // before it wraps around.
+                while read_cnt(r) == u16::MAX {}
+                // Make sure `now` doesn't see the flag as still pending
+                while r.isr().read().arrm() {}
+                self.period.fetch_add(1, Ordering::Relaxed);
+            }
+
+            let now = self.now();
+            for n in 0..ALARM_COUNT {
+                if self.alarms.borrow(cs)[n].timestamp.get() <= now {
+                    self.trigger_alarm(n, cs);
+                }
+            }
+        })
+    }
+
+    fn get_alarm<'a>(&'a self, cs: CriticalSection<'a>, alarm: AlarmHandle) -> &'a AlarmState {
+        // safety: we're allowed to assume the AlarmState is created by us, and
+        // we never create one that's out of bounds.
+        unsafe { self.alarms.borrow(cs).get_unchecked(alarm.id() as usize) }
+    }
+
+    fn trigger_alarm(&self, n: usize, cs: CriticalSection) {
+        let alarm = &self.alarms.borrow(cs)[n];
+        alarm.timestamp.set(u64::MAX);
+
+        // Call after clearing alarm, so the callback can set another alarm.
+
+        // safety:
+        // - we can ignore the possibility of `f` being unset (null) because of the safety contract of `allocate_alarm`.
+        // - other than that we only store valid function pointers into alarm.callback
+        let f: fn(*mut ()) = unsafe { mem::transmute(alarm.callback.get()) };
+        f(alarm.ctx.get());
+    }
+
+    /// Write the compare register, waiting for a previous write to be taken into account first.
+    fn write_cmp(&self, _cs: CriticalSection, cmp: u16) {
+        let r = T::regs();
+        unsafe {
+            if self.cmp_pending.load(Ordering::Relaxed) {
+                while !r.isr().read().cmpok() {}
+            }
+            r.icr().write(|w| w.set_cmpokcf(true));
+            r.cmp().write(|w| w.set_cmp(cmp));
+        }
+        self.cmp_pending.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Driver for LptimDriver {
+    fn now(&self) -> u64 {
+        let r = T::regs();
+
+        critical_section::with(|_| unsafe {
+            let period = self.period.load(Ordering::Relaxed);
+            let counter = read_cnt(r);
+            // Account for a wrap around the interrupt handler did not handle yet
+            let pending = r.isr().read().arrm() && counter < 0x8000;
+            ((period + pending as u32) as u64) << 16 | counter as u64
+        })
+    }
+
+    unsafe fn allocate_alarm(&self) -> Option<AlarmHandle> {
+        let id = self.alarm_count.fetch_update(Ordering::AcqRel, Ordering::Acquire, |x| {
+            if x < ALARM_COUNT as u8 {
+                Some(x + 1)
+            } else {
+                None
+            }
+        });
+
+        match id {
+            Ok(id) => Some(AlarmHandle::new(id)),
+            Err(_) => None,
+        }
+    }
+
+    fn set_alarm_callback(&self, alarm: AlarmHandle, callback: fn(*mut ()), ctx: *mut ()) {
+        critical_section::with(|cs| {
+            let alarm = self.get_alarm(cs, alarm);
+
+            alarm.callback.set(callback as *const ());
+            alarm.ctx.set(ctx);
+        })
+    }
+
+    fn set_alarm(&self, alarm: AlarmHandle, timestamp: u64) -> bool {
+        critical_section::with(|cs| {
+            let alarm = self.get_alarm(cs, alarm);
+
+            let t = self.now();
+            if timestamp <= t {
+                // If alarm timestamp has passed the alarm will not fire.
+                // Disarm the alarm and return `false` to indicate that.
+                alarm.timestamp.set(u64::MAX);
+                return false;
+            }
+
+            alarm.timestamp.set(timestamp);
+
+            // The compare value is only taken into account a few ticks after it is written.
+            let safe_timestamp = timestamp.max(t + 3);
+            self.write_cmp(cs, safe_timestamp as u16);
+
+            true
+        })
+    }
+}
+
+pub(crate) fn init(clock: ClockSource) {
+    DRIVER.init(clock)
+}
@@ -7,7 +7,7 @@ use super::simple_pwm::*;
 use super::*;
 #[allow(unused_imports)]
 use crate::gpio::sealed::{AFType, Pin};
-use crate::gpio::AnyPin;
+use crate::gpio::{AnyPin, Pull};
 use crate::time::Hertz;
 use crate::Peripheral;
 
@@ -41,8 +41,33 @@ complementary_channel_impl!(new_ch2, Ch2, Channel2Pin, Channel2ComplementaryPin)
 complementary_channel_impl!(new_ch3, Ch3, Channel3Pin, Channel3ComplementaryPin);
 complementary_channel_impl!(new_ch4, Ch4, Channel4Pin, Channel4ComplementaryPin);
 
+/// The break input (BKIN) of an advanced-control timer, see [`ComplementaryPwm::enable_break`].
+pub struct BreakPin<'d, Perip> {
+    _pin: PeripheralRef<'d, AnyPin>,
+    phantom: PhantomData<Perip>,
+}
+
+impl<'d, Perip: CaptureCompare16bitInstance> BreakPin<'d, Perip> {
+    /// Configure `pin` as the break input. Use `pull` to keep the input inactive while it is
+    /// not driven, e.g. [`Pull::Up`] for the default active-low polarity.
+    pub fn new(pin: impl Peripheral<P = impl BreakInputPin<Perip>> + 'd, pull: Pull) -> Self {
+        into_ref!(pin);
+        critical_section::with(|_| unsafe {
+            pin.set_as_af_pull(pin.af_num(), AFType::Input, pull);
+        });
+        BreakPin {
+            _pin: pin.map_into(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// PWM on the channels of an advanced-control timer, each with a complementary output and
+/// dead-time insertion, e.g. to drive the half-bridges of a three-phase motor.
 pub struct ComplementaryPwm<'d, T> {
     inner: PeripheralRef<'d, T>,
+    counting_mode: CountingMode,
+    freq: Hertz,
 }
 
 impl<'d, T: ComplementaryCaptureCompare16bitInstance> ComplementaryPwm<'d, T> {
@@ -67,9 +92,13 @@ impl<'d, T: ComplementaryCaptureCompare16bitInstance> ComplementaryPwm<'d, T> {
         T::enable();
         <T as crate::rcc::sealed::RccPeripheral>::reset();
 
-        let mut this = Self { inner: tim };
+        let mut this = Self {
+            inner: tim,
+            counting_mode: CountingMode::EdgeAlignedUp,
+            freq,
+        };
 
-        this.inner.set_frequency(freq);
+        this.set_freq(freq);
         this.inner.start();
 
         unsafe {
@@ -102,7 +131,25 @@ impl<'d, T: ComplementaryCaptureCompare16bitInstance> ComplementaryPwm<'d, T> {
     }
 
     pub fn set_freq(&mut self, freq: Hertz) {
-        self.inner.set_frequency(freq);
+        self.freq = freq;
+        // In center-aligned modes the counter counts up and down in each period
+        let multiplier = if self.counting_mode.is_center_aligned() {
+            2u8
+        } else {
+            1u8
+        };
+        self.inner.set_frequency(freq * multiplier);
+    }
+
+    /// Set the counting mode, [`CountingMode::EdgeAlignedUp`] by default. The timer is
+    /// restarted and the frequency is kept.
+    pub fn set_counting_mode(&mut self, mode: CountingMode) {
+        // The alignment can't be changed while the counter is enabled
+        self.inner.stop();
+        unsafe { self.inner.set_counting_mode(mode) };
+        self.counting_mode = mode;
+        self.set_freq(self.freq);
+        self.inner.start();
     }
 
     pub fn get_max_duty(&self) -> u16 {
@@ -123,6 +170,43 @@ impl<'d, T: ComplementaryCaptureCompare16bitInstance> ComplementaryPwm<'d, T> {
             self.inner.set_dead_time_value(value);
         }
     }
+
+    /// Enable the break input. While it is active, the outputs are disabled in hardware and
+    /// driven to their idle level, low, within a few timer clock cycles.
+    ///
+    /// Unless [`BreakConfig::automatic_output_enable`] is set, the outputs stay disabled after
+    /// the break until [`ComplementaryPwm::clear_break`] is called.
+    pub fn enable_break(&mut self, _pin: BreakPin<'d, T>, config: BreakConfig) {
+        unsafe {
+            self.inner.clear_break_flag();
+            self.inner.set_break(Some(config));
+        }
+    }
+
+    /// Disable the break input. The outputs stay disabled if a break already occurred, until
+    /// [`ComplementaryPwm::clear_break`] is called.
+    pub fn disable_break(&mut self) {
+        unsafe { self.inner.set_break(None) }
+    }
+
+    /// Disable the outputs from software, as if the break input became active.
+    pub fn trigger_break(&mut self) {
+        unsafe { self.inner.generate_break() }
+    }
+
+    /// Whether a break occurred since the last call to [`ComplementaryPwm::clear_break`].
+    pub fn break_occurred(&self) -> bool {
+        unsafe { self.inner.get_break_flag() }
+    }
+
+    /// Re-enable the outputs after a break. The outputs stay disabled if the break input is
+    /// still active.
+    pub fn clear_break(&mut self) {
+        unsafe {
+            self.inner.clear_break_flag();
+            self.inner.enable_outputs(true);
+        }
+    }
 }
 
 fn compute_dead_time_value(value: u16) -> (Ckd, u8) {
@@ -1,7 +1,7 @@
 pub mod complementary_pwm;
 pub mod simple_pwm;
 
-use stm32_metapac::timer::vals::Ckd;
+use stm32_metapac::timer::vals::{Ckd, Cms, Dir};
 
 #[cfg(feature = "unstable-pac")]
 pub mod low_level {
@@ -54,6 +54,66 @@ impl From<OutputCompareMode> for stm32_metapac::timer::vals::Ocm {
     }
 }
 
+/// How the counter counts, and where the PWM pulses are placed in the period.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CountingMode {
+    /// The counter counts up from 0, the pulses start at the beginning of the period
+    #[default]
+    EdgeAlignedUp,
+    /// The counter counts down to 0, the pulses end at the end of the period
+    EdgeAlignedDown,
+    /// The counter counts up then down, the pulses are centered in the period. The compare
+    /// interrupt flags are set when counting down.
+    CenterAlignedDownInterrupts,
+    /// Center aligned, the compare interrupt flags are set when counting up.
+    CenterAlignedUpInterrupts,
+    /// Center aligned, the compare interrupt flags are set when counting up and down.
+    CenterAlignedBothInterrupts,
+}
+
+impl CountingMode {
+    pub fn is_center_aligned(&self) -> bool {
+        !matches!(self, CountingMode::EdgeAlignedUp | CountingMode::EdgeAlignedDown)
+    }
+}
+
+impl From<CountingMode> for (Cms, Dir) {
+    fn from(mode: CountingMode) -> Self {
+        match mode {
+            CountingMode::EdgeAlignedUp => (Cms::EDGEALIGNED, Dir::UP),
+            CountingMode::EdgeAlignedDown => (Cms::EDGEALIGNED, Dir::DOWN),
+            CountingMode::CenterAlignedDownInterrupts => (Cms::CENTERALIGNED1, Dir::UP),
+            CountingMode::CenterAlignedUpInterrupts => (Cms::CENTERALIGNED2, Dir::UP),
+            CountingMode::CenterAlignedBothInterrupts => (Cms::CENTERALIGNED3, Dir::UP),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BreakPolarity {
+    ActiveLow,
+    ActiveHigh,
+}
+
+/// Break input configuration of an advanced-control timer.
+#[non_exhaustive]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BreakConfig {
+    pub polarity: BreakPolarity,
+    /// Re-enable the outputs at the next update event once the break input is inactive again.
+    /// Otherwise they stay disabled until re-enabled in software.
+    pub automatic_output_enable: bool,
+}
+
+impl Default for BreakConfig {
+    fn default() -> Self {
+        Self {
+            polarity: BreakPolarity::ActiveLow,
+            automatic_output_enable: false,
+        }
+    }
+}
+
 pub(crate) mod sealed {
     use super::*;
 
@@ -68,6 +128,9 @@ pub(crate) mod sealed {
         unsafe fn set_compare_value(&mut self, channel: Channel, value: u16);
 
         unsafe fn get_max_compare_value(&self) -> u16;
+
+        // Can only be changed while the counter is stopped.
+        unsafe fn set_counting_mode(&mut self, mode: CountingMode);
     }
 
     pub trait ComplementaryCaptureCompare16bitInstance: CaptureCompare16bitInstance {
@@ -76,6 +139,16 @@ pub(crate) mod sealed {
         unsafe fn set_dead_time_value(&mut self, value: u8);
 
         unsafe fn enable_complementary_channel(&mut self, channel: Channel, enable: bool);
+
+        // Enable the break input with the given configuration, or disable it.
+        unsafe fn set_break(&mut self, config: Option<BreakConfig>);
+
+        // Disable the outputs from software, like the break input does.
+        unsafe fn generate_break(&mut self);
+
+        unsafe fn get_break_flag(&self) -> bool;
+
+        unsafe fn clear_break_flag(&mut self);
     }
 
     pub trait CaptureCompare32bitInstance: crate::timer::sealed::GeneralPurpose32bitInstance {
@@ -134,6 +207,15 @@ macro_rules! impl_compare_capable_16bit {
                 use crate::timer::sealed::GeneralPurpose16bitInstance;
                 Self::regs_gp16().arr().read().arr()
             }
+
+            unsafe fn set_counting_mode(&mut self, mode: CountingMode) {
+                use crate::timer::sealed::GeneralPurpose16bitInstance;
+                let (cms, dir) = mode.into();
+                Self::regs_gp16().cr1().modify(|w| {
+                    w.set_cms(cms);
+                    w.set_dir(dir);
+                });
+            }
         }
     };
 }
@@ -221,6 +303,15 @@ foreach_interrupt! {
                 use crate::timer::sealed::AdvancedControlInstance;
                 Self::regs_advanced().arr().read().arr()
             }
+
+            unsafe fn set_counting_mode(&mut self, mode: CountingMode) {
+                use crate::timer::sealed::AdvancedControlInstance;
+                let (cms, dir) = mode.into();
+                Self::regs_advanced().cr1().modify(|w| {
+                    w.set_cms(cms);
+                    w.set_dir(dir);
+                });
+            }
         }
 
         impl CaptureCompare16bitInstance for crate::peripherals::$inst {
@@ -244,6 +335,38 @@ foreach_interrupt! {
                     .ccer()
                     .modify(|w| w.set_ccne(channel.raw(), enable));
             }
+
+            unsafe fn set_break(&mut self, config: Option<BreakConfig>) {
+                use crate::timer::sealed::AdvancedControlInstance;
+                Self::regs_advanced().bdtr().modify(|w| match config {
+                    Some(config) => {
+                        w.set_bkp(config.polarity == BreakPolarity::ActiveHigh);
+                        w.set_aoe(config.automatic_output_enable);
+                        // Drive the outputs to their idle level on break, instead of releasing them
+                        w.set_ossi(stm32_metapac::timer::vals::Ossi::IDLELEVEL);
+                        w.set_bke(true);
+                    }
+                    None => {
+                        w.set_bke(false);
+                        w.set_aoe(false);
+                    }
+                });
+            }
+
+            unsafe fn generate_break(&mut self) {
+                use crate::timer::sealed::AdvancedControlInstance;
+                Self::regs_advanced().egr().write(|w| w.set_bg(true));
+            }
+
+            unsafe fn get_break_flag(&self) -> bool {
+                use crate::timer::sealed::AdvancedControlInstance;
+                Self::regs_advanced().sr().read().bif()
+            }
+
+            unsafe fn clear_break_flag(&mut self) {
+                use crate::timer::sealed::AdvancedControlInstance;
+                Self::regs_advanced().sr().modify(|w| w.set_bif(false));
+            }
         }
 
         impl ComplementaryCaptureCompare16bitInstance for crate::peripherals::$inst {
@@ -1,12 +1,15 @@
 use core::marker::PhantomData;
 
 use embassy_hal_common::{into_ref, PeripheralRef};
+use stm32_metapac::timer::vals::{Ocpe, Opm};
 
 use super::*;
+use crate::dma::Transfer;
 #[allow(unused_imports)]
 use crate::gpio::sealed::{AFType, Pin};
 use crate::gpio::AnyPin;
 use crate::time::Hertz;
+use crate::timer::UpDma;
 use crate::Peripheral;
 
 pub struct Ch1;
@@ -110,4 +113,129 @@ impl<'d, T: CaptureCompare16bitInstance> SimplePwm<'d, T> {
         assert!(duty < self.get_max_duty());
         unsafe { self.inner.set_compare_value(channel, duty) }
     }
+
+    /// Enable or disable one-pulse mode, where the counter stops at the end of each period and
+    /// a single period is started with [`SimplePwm::trigger`].
+    ///
+    /// While stopped the counter is at 0, so the channels are switched to PWM mode 2 in which
+    /// the output is inactive until the counter reaches the duty value: the duty sets the delay
+    /// from the trigger to the pulse, which lasts until the end of the period.
+    pub fn set_one_pulse_mode(&mut self, enable: bool) {
+        let mode = match enable {
+            true => OutputCompareMode::PwmMode2,
+            false => OutputCompareMode::PwmMode1,
+        };
+
+        self.inner.stop();
+        self.inner.reset();
+        unsafe {
+            T::regs_gp16().cr1().modify(|w| {
+                w.set_opm(match enable {
+                    true => Opm::ENABLED,
+                    false => Opm::DISABLED,
+                })
+            });
+            for channel in [Channel::Ch1, Channel::Ch2, Channel::Ch3, Channel::Ch4] {
+                self.inner.set_output_compare_mode(channel, mode);
+            }
+        }
+        if !enable {
+            self.inner.start();
+        }
+    }
+
+    /// Start a period in one-pulse mode.
+    pub fn trigger(&mut self) {
+        self.inner.start();
+    }
+
+    /// Whether the counter is running, which in one-pulse mode means a pulse is in progress.
+    pub fn is_running(&self) -> bool {
+        unsafe { T::regs_gp16().cr1().read().cen() }
+    }
+
+    /// Load the duty cycle of `channel` from `duty` at each update event, with DMA. This can be
+    /// used to generate a bit stream, e.g. the data of WS2812 LEDs.
+    ///
+    /// Each value is output during the period following the one where it was loaded, so the
+    /// last value of `duty` is still output after the transfer completes, and should usually
+    /// be 0.
+    pub async fn waveform_up(&mut self, dma: impl Peripheral<P = impl UpDma<T>>, channel: Channel, duty: &[u16]) {
+        assert!(duty.iter().all(|&d| d < self.get_max_duty()));
+        into_ref!(dma);
+
+        let r = T::regs_gp16();
+        let raw_channel = channel.raw();
+        unsafe {
+            let original_preload = r.ccmr_output(raw_channel / 2).read().ocpe(raw_channel % 2);
+            r.ccmr_output(raw_channel / 2)
+                .modify(|w| w.set_ocpe(raw_channel % 2, Ocpe::ENABLED));
+            r.dier().modify(|w| w.set_ude(true));
+
+            let dst = r.ccr(raw_channel).ptr() as *mut u16;
+            // The DMA controllers without a request mux have no request to select
+            #[cfg(any(bdma_v2, dma_v2, dmamux, gpdma))]
+            {
+                let request = dma.request();
+                Transfer::new_write(&mut dma, request, duty, dst, Default::default()).await;
+            }
+            #[cfg(not(any(bdma_v2, dma_v2, dmamux, gpdma)))]
+            Transfer::new_write(&mut dma, (), duty, dst, Default::default()).await;
+
+            r.dier().modify(|w| w.set_ude(false));
+            r.ccmr_output(raw_channel / 2)
+                .modify(|w| w.set_ocpe(raw_channel % 2, original_preload));
+        }
+    }
+
+    /// Load the duty cycles of the channels `first` to `last` at each update event, with a DMA
+    /// burst transfer. `duty` holds the values of the channels for each period in turn, e.g.
+    /// `[ch1, ch2, ch3, ch1, ch2, ch3, ...]`.
+    ///
+    /// As with [`SimplePwm::waveform_up`], the last values are still output after the transfer
+    /// completes.
+    pub async fn waveform_burst(
+        &mut self,
+        dma: impl Peripheral<P = impl UpDma<T>>,
+        first: Channel,
+        last: Channel,
+        duty: &[u16],
+    ) {
+        assert!(first.raw() <= last.raw());
+        let channel_count = last.raw() - first.raw() + 1;
+        assert!(duty.len() % channel_count == 0);
+        assert!(duty.iter().all(|&d| d < self.get_max_duty()));
+        into_ref!(dma);
+
+        let r = T::regs_gp16();
+        unsafe {
+            let original_ccmr = [r.ccmr_output(0).read(), r.ccmr_output(1).read()];
+            for raw_channel in first.raw()..=last.raw() {
+                r.ccmr_output(raw_channel / 2)
+                    .modify(|w| w.set_ocpe(raw_channel % 2, Ocpe::ENABLED));
+            }
+            let original_dcr = r.dcr().read();
+            r.dcr().write(|w| {
+                // Offset of CCR1 in 32 bit words
+                w.set_dba(13 + first.raw() as u8);
+                w.set_dbl(channel_count as u8 - 1);
+            });
+            r.dier().modify(|w| w.set_ude(true));
+
+            let dst = r.dmar().ptr() as *mut u16;
+            // The DMA controllers without a request mux have no request to select
+            #[cfg(any(bdma_v2, dma_v2, dmamux, gpdma))]
+            {
+                let request = dma.request();
+                Transfer::new_write(&mut dma, request, duty, dst, Default::default()).await;
+            }
+            #[cfg(not(any(bdma_v2, dma_v2, dmamux, gpdma)))]
+            Transfer::new_write(&mut dma, (), duty, dst, Default::default()).await;
+
+            r.dier().modify(|w| w.set_ude(false));
+            r.dcr().write_value(original_dcr);
+            r.ccmr_output(0).write_value(original_ccmr[0]);
+            r.ccmr_output(1).write_value(original_ccmr[1]);
+        }
+    }
 }
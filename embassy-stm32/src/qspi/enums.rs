@@ -292,3 +292,20 @@ impl Into<u8> for DummyCycles {
         }
     }
 }
+
+#[derive(Copy, Clone)]
+pub enum MatchMode {
+    /// All the masked bits of the status must match
+    AND,
+    /// Any of the masked bits of the status must match
+    OR,
+}
+
+impl From<MatchMode> for bool {
+    fn from(mode: MatchMode) -> Self {
+        match mode {
+            MatchMode::AND => false,
+            MatchMode::OR => true,
+        }
+    }
+}
@@ -2,6 +2,7 @@
 
 pub mod enums;
 
+use embassy_hal_common::drop::OnDrop;
 use embassy_hal_common::{into_ref, PeripheralRef};
 use enums::*;
 
@@ -43,6 +44,29 @@ impl Default for TransferConfig {
     }
 }
 
+/// Auto-polling configuration, to wait for a status of the flash, like the end of a write
+pub struct AutoPollConfig {
+    /// Status bits to compare (PSMKR)
+    pub mask: u32,
+    /// Value the masked status bits must match (PSMAR)
+    pub match_value: u32,
+    /// Number of CLK cycles between two reads of the status (PIR)
+    pub interval: u16,
+    /// Whether all or any of the masked bits must match (PMM)
+    pub match_mode: MatchMode,
+}
+
+impl Default for AutoPollConfig {
+    fn default() -> Self {
+        Self {
+            mask: 0,
+            match_value: 0,
+            interval: 16,
+            match_mode: MatchMode::AND,
+        }
+    }
+}
+
 pub struct Config {
     /// Flash memory size representend as 2^[0-32], as reasonable minimum 1KiB(9) was chosen.
     /// If you need other value the whose predefined use `Other` variant.
@@ -279,7 +303,154 @@ impl<'d, T: Instance, Dma> Qspi<'d, T, Dma> {
         }
     }
 
+    pub async fn read_dma(&mut self, buf: &mut [u8], transaction: TransferConfig)
+    where
+        Dma: QuadDma<T>,
+    {
+        // Arm `OnDrop` before the transfer, so the transfer is stopped first
+        let on_drop = OnDrop::new(abort::<T>);
+
+        unsafe {
+            self.setup_transaction(QspiMode::IndirectWrite, &transaction);
+
+            T::REGS.ccr().modify(|v| {
+                v.set_fmode(QspiMode::IndirectRead.into());
+            });
+            let current_ar = T::REGS.ar().read().address();
+            T::REGS.ar().write(|v| {
+                v.set_address(current_ar);
+            });
+
+            let request = self.dma.request();
+            let transfer = Transfer::new_read(
+                &mut self.dma,
+                request,
+                T::REGS.dr().ptr() as *mut u8,
+                buf,
+                Default::default(),
+            );
+
+            T::REGS.cr().modify(|v| v.set_dmaen(true));
+
+            transfer.await;
+        }
+
+        on_drop.defuse();
+        Self::finish_dma();
+    }
+
+    pub async fn write_dma(&mut self, buf: &[u8], transaction: TransferConfig)
+    where
+        Dma: QuadDma<T>,
+    {
+        // Arm `OnDrop` before the transfer, so the transfer is stopped first
+        let on_drop = OnDrop::new(abort::<T>);
+
+        unsafe {
+            self.setup_transaction(QspiMode::IndirectWrite, &transaction);
+
+            T::REGS.ccr().modify(|v| {
+                v.set_fmode(QspiMode::IndirectWrite.into());
+            });
+
+            let request = self.dma.request();
+            let transfer = Transfer::new_write(
+                &mut self.dma,
+                request,
+                buf,
+                T::REGS.dr().ptr() as *mut u8,
+                Default::default(),
+            );
+
+            T::REGS.cr().modify(|v| v.set_dmaen(true));
+
+            transfer.await;
+        }
+
+        on_drop.defuse();
+        Self::finish_dma();
+    }
+
+    /// Read the status of the flash with `transaction` until the bits selected by `config.mask`
+    /// match `config.match_value`, and return the matching status.
+    ///
+    /// `transaction.data_len` is the size of the status, from 1 to 4 bytes.
+    pub fn blocking_auto_poll(&mut self, transaction: TransferConfig, config: AutoPollConfig) -> u32 {
+        exit_memory_mapped::<T>();
+
+        unsafe {
+            while T::REGS.sr().read().busy() {}
+
+            T::REGS.cr().modify(|v| {
+                v.set_dmaen(false);
+                v.set_apms(true);
+                v.set_pmm(config.match_mode.into());
+            });
+            T::REGS.psmkr().write(|v| v.set_mask(config.mask));
+            T::REGS.psmar().write(|v| v.set_match_(config.match_value));
+            T::REGS.pir().write(|v| v.set_interval(config.interval));
+
+            self.setup_transaction(QspiMode::AutoPolling, &transaction);
+
+            while !T::REGS.sr().read().smf() {}
+            let status = T::REGS.dr().read().data();
+
+            // Polling stops automatically on a match
+            while T::REGS.sr().read().busy() {}
+            T::REGS.fcr().modify(|v| {
+                v.set_csmf(true);
+                v.set_ctcf(true);
+            });
+
+            status
+        }
+    }
+
+    /// Enable the memory-mapped mode: the flash is read with `transaction` when the QUADSPI
+    /// region of the memory map is accessed, which allows executing code from it (XIP).
+    ///
+    /// The address and length of the reads come from the accesses, `transaction.address` and
+    /// `transaction.data_len` are ignored. If `timeout` is set, nCS is released after that many
+    /// CLK cycles without accesses, to reduce the consumption of the flash.
+    ///
+    /// Indirect and auto-polling transactions abort the memory-mapped mode.
+    pub fn enable_memory_mapped(&mut self, transaction: TransferConfig, timeout: Option<u16>) {
+        exit_memory_mapped::<T>();
+
+        unsafe {
+            while T::REGS.sr().read().busy() {}
+
+            T::REGS.cr().modify(|v| v.set_tcen(timeout.is_some()));
+            if let Some(timeout) = timeout {
+                T::REGS.lptr().write(|v| v.set_timeout(timeout));
+            }
+        }
+
+        let transaction = TransferConfig {
+            address: None,
+            data_len: None,
+            ..transaction
+        };
+        self.setup_transaction(QspiMode::MemoryMapped, &transaction);
+    }
+
+    /// Disable the memory-mapped mode
+    pub fn disable_memory_mapped(&mut self) {
+        exit_memory_mapped::<T>();
+    }
+
+    /// Wait for the end of a DMA transaction, once the DMA transfer is done
+    fn finish_dma() {
+        unsafe {
+            while !T::REGS.sr().read().tcf() {}
+            T::REGS.fcr().modify(|v| v.set_ctcf(true));
+            T::REGS.cr().modify(|v| v.set_dmaen(false));
+        }
+    }
+
     fn setup_transaction(&mut self, fmode: QspiMode, transaction: &TransferConfig) {
+        exit_memory_mapped::<T>();
+
         unsafe {
             T::REGS.fcr().modify(|v| {
                 v.set_csmf(true);
@@ -314,6 +485,23 @@ impl<'d, T: Instance, Dma> Qspi<'d, T, Dma> {
     }
 }
 
+/// Abort the memory-mapped mode if it is enabled, as the peripheral stays busy in it
+fn exit_memory_mapped<T: Instance>() {
+    let memory_mapped: u8 = QspiMode::MemoryMapped.into();
+    if unsafe { T::REGS.ccr().read().fmode() } == memory_mapped {
+        abort::<T>();
+    }
+}
+
+/// Abort the current transaction, and wait for the peripheral to be idle
+fn abort<T: Instance>() {
+    unsafe {
+        T::REGS.cr().modify(|v| v.set_abort(true));
+        while T::REGS.cr().read().abort() {}
+        T::REGS.cr().modify(|v| v.set_dmaen(false));
+    }
+}
+
 pub(crate) mod sealed {
     use super::*;
 
@@ -91,6 +91,50 @@ pub(crate) unsafe fn get_freqs() -> &'static Clocks {
     &*CLOCK_FREQS.as_ptr()
 }
 
+/// Turn on the LSE crystal oscillator, unlocking the backup domain it belongs to, and wait until it is
+/// stable.
+#[cfg(any(rcc_f7, rcc_g0, rcc_g4, rcc_l0, rcc_l4, rcc_l5, rcc_wb, rcc_wl5, rcc_wle))]
+pub(crate) unsafe fn enable_lse() {
+    #[cfg(not(any(rcc_l0, rcc_wb, rcc_wl5, rcc_wle)))]
+    {
+        use sealed::RccPeripheral;
+        crate::peripherals::PWR::enable();
+    }
+    #[cfg(rcc_l0)]
+    crate::pac::RCC.apb1enr().modify(|w| w.set_pwren(true));
+
+    #[cfg(any(rcc_f7, rcc_g0, rcc_g4, rcc_l4, rcc_l5, rcc_wb))]
+    {
+        crate::pac::PWR.cr1().modify(|w| w.set_dbp(true));
+        while !crate::pac::PWR.cr1().read().dbp() {}
+    }
+    #[cfg(any(rcc_wl5, rcc_wle))]
+    {
+        use crate::pac::pwr::vals::Dbp;
+
+        crate::pac::PWR.cr1().modify(|w| w.set_dbp(Dbp::ENABLED));
+        while crate::pac::PWR.cr1().read().dbp() != Dbp::ENABLED {}
+    }
+    // The PAC has no registers for the PWR of L0, DBP is bit 8 of PWR_CR
+    #[cfg(rcc_l0)]
+    {
+        let cr = crate::pac::PWR as *mut u32;
+        cr.write_volatile(cr.read_volatile() | 1 << 8);
+        while cr.read_volatile() & 1 << 8 == 0 {}
+    }
+
+    #[cfg(not(rcc_l0))]
+    {
+        crate::pac::RCC.bdcr().modify(|w| w.set_lseon(true));
+        while !crate::pac::RCC.bdcr().read().lserdy() {}
+    }
+    #[cfg(rcc_l0)]
+    {
+        crate::pac::RCC.csr().modify(|w| w.set_lseon(true));
+        while !crate::pac::RCC.csr().read().lserdy() {}
+    }
+}
+
 #[cfg(feature = "unstable-pac")]
 pub mod low_level {
     pub use super::sealed::*;
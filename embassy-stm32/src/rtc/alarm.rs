@@ -0,0 +1,219 @@
+use super::byte_to_bcd2;
+use super::datetime::{day_of_week_to_u8, DateTime, DayOfWeek, Error};
+use crate::pac::rtc::regs::Alrmr;
+use crate::pac::rtc::vals::{AlrmrMsk, AlrmrPm, AlrmrWdsel};
+
+/// RTC alarm
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Alarm {
+    /// Alarm A
+    A,
+    /// Alarm B
+    #[cfg(not(rtc_v2f0))]
+    B,
+}
+
+/// Number of alarms available on this chip
+#[cfg(rtc_v2f0)]
+pub(super) const ALARM_COUNT: usize = 1;
+#[cfg(not(rtc_v2f0))]
+pub(super) const ALARM_COUNT: usize = 2;
+
+impl Alarm {
+    pub(super) fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Date an alarm matches on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlarmDate {
+    /// Day of the month, 1..=31
+    Day(u8),
+    /// Day of the week
+    Weekday(DayOfWeek),
+}
+
+/// Calendar fields an alarm matches on.
+///
+/// Fields which are not set are ignored in the comparison, so an alarm without any fields set fires
+/// every second.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AlarmConfig {
+    date: Option<AlarmDate>,
+    hour: Option<u8>,
+    minute: Option<u8>,
+    second: Option<u8>,
+    #[cfg(not(rtc_v2f2))]
+    subsecond: Option<(u16, u8)>,
+}
+
+impl AlarmConfig {
+    /// Alarm firing every second
+    pub const fn new() -> Self {
+        Self {
+            date: None,
+            hour: None,
+            minute: None,
+            second: None,
+            #[cfg(not(rtc_v2f2))]
+            subsecond: None,
+        }
+    }
+
+    /// Alarm matching the day of month and the time of `t`, i.e. firing once a month
+    pub fn at(t: &DateTime) -> Self {
+        Self::new()
+            .date(AlarmDate::Day(t.day))
+            .hour(t.hour)
+            .minute(t.minute)
+            .second(t.second)
+    }
+
+    /// Match on the day of the month or the day of the week
+    pub fn date(mut self, date: AlarmDate) -> Self {
+        self.date = Some(date);
+        self
+    }
+
+    /// Match on the hour, 0..=23
+    pub fn hour(mut self, hour: u8) -> Self {
+        self.hour = Some(hour);
+        self
+    }
+
+    /// Match on the minute, 0..=59
+    pub fn minute(mut self, minute: u8) -> Self {
+        self.minute = Some(minute);
+        self
+    }
+
+    /// Match on the second, 0..=59
+    pub fn second(mut self, second: u8) -> Self {
+        self.second = Some(second);
+        self
+    }
+
+    /// Match on the `bits` least significant bits of the subsecond counter, 1..=15.
+    ///
+    /// The subsecond counter counts down from the synchronous prescaler value, so with the default
+    /// prescaler of 255, `subsecond(0, 7)` fires twice per second.
+    #[cfg(not(rtc_v2f2))]
+    pub fn subsecond(mut self, value: u16, bits: u8) -> Self {
+        self.subsecond = Some((value, bits));
+        self
+    }
+
+    pub(super) fn validate(&self) -> Result<(), Error> {
+        match self.date {
+            Some(AlarmDate::Day(day)) if !(1..=31).contains(&day) => return Err(Error::InvalidDay),
+            _ => {}
+        }
+        if matches!(self.hour, Some(hour) if hour > 23) {
+            return Err(Error::InvalidHour);
+        }
+        if matches!(self.minute, Some(minute) if minute > 59) {
+            return Err(Error::InvalidMinute);
+        }
+        if matches!(self.second, Some(second) if second > 59) {
+            return Err(Error::InvalidSecond);
+        }
+        #[cfg(not(rtc_v2f2))]
+        if matches!(self.subsecond, Some((_, bits)) if bits == 0 || bits > 15) {
+            return Err(Error::InvalidSecond);
+        }
+        Ok(())
+    }
+
+    pub(super) fn alrmr(&self) -> Alrmr {
+        // `MASK` (0) means the field takes part in the comparison
+        fn msk(field: Option<u8>) -> AlrmrMsk {
+            match field {
+                Some(_) => AlrmrMsk::MASK,
+                None => AlrmrMsk::NOTMASK,
+            }
+        }
+
+        let mut w = Alrmr(0);
+        w.set_pm(AlrmrPm::AM);
+
+        w.set_msk1(msk(self.second));
+        let (st, su) = byte_to_bcd2(self.second.unwrap_or(0));
+        w.set_st(st);
+        w.set_su(su);
+
+        w.set_msk2(msk(self.minute));
+        let (mnt, mnu) = byte_to_bcd2(self.minute.unwrap_or(0));
+        w.set_mnt(mnt);
+        w.set_mnu(mnu);
+
+        w.set_msk3(msk(self.hour));
+        let (ht, hu) = byte_to_bcd2(self.hour.unwrap_or(0));
+        w.set_ht(ht);
+        w.set_hu(hu);
+
+        match self.date {
+            Some(AlarmDate::Day(day)) => {
+                let (dt, du) = byte_to_bcd2(day);
+                w.set_msk4(AlrmrMsk::MASK);
+                w.set_wdsel(AlrmrWdsel::DATEUNITS);
+                w.set_dt(dt);
+                w.set_du(du);
+            }
+            Some(AlarmDate::Weekday(weekday)) => {
+                w.set_msk4(AlrmrMsk::MASK);
+                w.set_wdsel(AlrmrWdsel::WEEKDAY);
+                w.set_du(day_of_week_to_u8(weekday));
+            }
+            None => w.set_msk4(AlrmrMsk::NOTMASK),
+        }
+
+        w
+    }
+
+    #[cfg(not(rtc_v2f2))]
+    pub(super) fn alrmssr(&self) -> crate::pac::rtc::regs::Alrmssr {
+        let mut w = crate::pac::rtc::regs::Alrmssr(0);
+        if let Some((value, bits)) = self.subsecond {
+            w.set_ss(value);
+            w.set_maskss(bits);
+        }
+        w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_encode_alarm() {
+        let alrmr = AlarmConfig::new().hour(7).minute(45).alrmr();
+        assert!(alrmr.msk1() == AlrmrMsk::NOTMASK);
+        assert!(alrmr.msk2() == AlrmrMsk::MASK);
+        assert!(alrmr.msk3() == AlrmrMsk::MASK);
+        assert!(alrmr.msk4() == AlrmrMsk::NOTMASK);
+        assert_eq!((4, 5), (alrmr.mnt(), alrmr.mnu()));
+        assert_eq!((0, 7), (alrmr.ht(), alrmr.hu()));
+
+        let alrmr = AlarmConfig::new().date(AlarmDate::Weekday(DayOfWeek::Friday)).alrmr();
+        assert!(alrmr.msk4() == AlrmrMsk::MASK);
+        assert!(alrmr.wdsel() == AlrmrWdsel::WEEKDAY);
+        assert_eq!(day_of_week_to_u8(DayOfWeek::Friday), alrmr.du());
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        assert_eq!(
+            Err(Error::InvalidDay),
+            AlarmConfig::new().date(AlarmDate::Day(0)).validate()
+        );
+        assert_eq!(Err(Error::InvalidHour), AlarmConfig::new().hour(24).validate());
+        assert_eq!(
+            Err(Error::InvalidSecond),
+            AlarmConfig::new().subsecond(0, 16).validate()
+        );
+        assert_eq!(Ok(()), AlarmConfig::new().minute(59).subsecond(0, 15).validate());
+    }
+}
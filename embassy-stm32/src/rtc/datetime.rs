@@ -108,7 +108,7 @@ impl From<DayOfWeek> for chrono::Weekday {
     }
 }
 
-fn day_of_week_from_u8(v: u8) -> Result<DayOfWeek, Error> {
+pub(super) fn day_of_week_from_u8(v: u8) -> Result<DayOfWeek, Error> {
     Ok(match v {
         0 => DayOfWeek::Monday,
         1 => DayOfWeek::Tuesday,
@@ -199,3 +199,172 @@ pub(super) fn datetime(
         second,
     })
 }
+
+/// Number of days between 1970-01-01 and the given civil date.
+pub(super) fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    // See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+/// Number of seconds between 1970-01-01 00:00:00 and `dt`.
+///
+/// The day of the week is not taken into account.
+pub(super) fn datetime_to_seconds(dt: &DateTime) -> i64 {
+    let days = days_from_civil(dt.year as i64, dt.month, dt.day);
+
+    days * 86_400 + dt.hour as i64 * 3_600 + dt.minute as i64 * 60 + dt.second as i64
+}
+
+/// Civil date of the given number of days since 1970-01-01, as `(year, month, day)`.
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u8;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u8;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+/// Inverse of [`datetime_to_seconds`], including the day of the week.
+pub(super) fn seconds_to_datetime(seconds: i64) -> DateTime {
+    let days = seconds.div_euclid(86_400);
+    let seconds_of_day = seconds.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+
+    DateTime {
+        year: year as u16,
+        month,
+        day,
+        // 1970-01-01 was a Thursday
+        day_of_week: day_of_week_from_u8((days + 3).rem_euclid(7) as u8).unwrap(),
+        hour: (seconds_of_day / 3_600) as u8,
+        minute: (seconds_of_day / 60 % 60) as u8,
+        second: (seconds_of_day % 60) as u8,
+    }
+}
+
+/// Offset of a local time zone from UTC.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UtcOffset {
+    minutes: i16,
+}
+
+impl UtcOffset {
+    /// Coordinated Universal Time.
+    pub const UTC: Self = Self { minutes: 0 };
+
+    /// Create an offset of a whole number of hours, e.g. `-5` for UTC-05:00.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offset is 24 hours or more.
+    pub const fn from_hours(hours: i8) -> Self {
+        ::core::assert!(hours > -24 && hours < 24);
+        Self {
+            minutes: hours as i16 * 60,
+        }
+    }
+
+    /// Create an offset in minutes, e.g. `330` for UTC+05:30 or `-570` for UTC-09:30.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offset is 24 hours or more.
+    pub const fn from_minutes(minutes: i16) -> Self {
+        ::core::assert!(minutes > -24 * 60 && minutes < 24 * 60);
+        Self { minutes }
+    }
+
+    /// The offset in minutes.
+    pub const fn minutes(&self) -> i16 {
+        self.minutes
+    }
+
+    /// Convert a UTC date and time to local time.
+    ///
+    /// If `daylight_savings` is set, one additional hour is added.
+    pub fn to_local(&self, utc: &DateTime, daylight_savings: bool) -> DateTime {
+        let mut offset = self.minutes as i64 * 60;
+        if daylight_savings {
+            offset += 3_600;
+        }
+
+        seconds_to_datetime(datetime_to_seconds(utc) + offset)
+    }
+}
+
+impl Default for UtcOffset {
+    fn default() -> Self {
+        Self::UTC
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(year: u16, month: u8, day: u8, hour: u8, minute: u8) -> DateTime {
+        DateTime {
+            year,
+            month,
+            day,
+            day_of_week: DayOfWeek::Monday,
+            hour,
+            minute,
+            second: 0,
+        }
+    }
+
+    #[test]
+    fn seconds_roundtrip() {
+        let dt = seconds_to_datetime(datetime_to_seconds(&utc(2024, 2, 29, 13, 37)));
+        assert_eq!((2024, 2, 29, 13, 37), (dt.year, dt.month, dt.day, dt.hour, dt.minute));
+        assert_eq!(DayOfWeek::Thursday, dt.day_of_week);
+
+        let dt = seconds_to_datetime(0);
+        assert_eq!((1970, 1, 1, 0, 0), (dt.year, dt.month, dt.day, dt.hour, dt.minute));
+        assert_eq!(DayOfWeek::Thursday, dt.day_of_week);
+    }
+
+    #[test]
+    fn local_time_crosses_date_boundary() {
+        // UTC+05:45 (Nepal), forward into the next year
+        let local = UtcOffset::from_minutes(5 * 60 + 45).to_local(&utc(2022, 12, 31, 20, 30), false);
+        assert_eq!(
+            (2023, 1, 1, 2, 15),
+            (local.year, local.month, local.day, local.hour, local.minute)
+        );
+        assert_eq!(DayOfWeek::Sunday, local.day_of_week);
+
+        // UTC-09:30 (Marquesas Islands), back into February of a leap year
+        let local = UtcOffset::from_minutes(-(9 * 60 + 30)).to_local(&utc(2024, 3, 1, 4, 0), false);
+        assert_eq!(
+            (2024, 2, 29, 18, 30),
+            (local.year, local.month, local.day, local.hour, local.minute)
+        );
+
+        // UTC+01:00 with daylight savings time
+        let local = UtcOffset::from_hours(1).to_local(&utc(2023, 7, 1, 22, 0), true);
+        assert_eq!(
+            (2023, 7, 2, 0, 0),
+            (local.year, local.month, local.day, local.hour, local.minute)
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use super::datetime::datetime_to_seconds;
+use super::DateTime;
+
+/// Drift of the RTC clock, as measured between two time synchronizations.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RtcDrift {
+    /// Estimated clock error in ppm.
+    ///
+    /// A positive value means the RTC runs fast, a negative value means it runs slow.
+    pub ppm: f32,
+    /// Reference time elapsed between the two observations, in seconds.
+    ///
+    /// As the RTC only has a resolution of one second, the estimate gets more accurate
+    /// the longer this interval is. Over one day, the resolution is about 11.6 ppm.
+    pub interval_secs: u32,
+}
+
+impl RtcDrift {
+    /// The value to pass to `Rtc::calibrate` to compensate for this drift.
+    ///
+    /// This assumes no calibration was active while the drift was measured. Otherwise the
+    /// previously applied calibration has to be added to this value.
+    pub fn calibration(&self) -> f32 {
+        -self.ppm
+    }
+}
+
+/// Estimates the RTC clock drift from occasional synchronizations with a trusted time source.
+///
+/// Every time the application learns the true time (e.g. over the network), it passes the
+/// current RTC time and the true time to [`RtcDriftEstimator::observe`]. Starting with the second
+/// observation, the drift accumulated since the previous observation is returned.
+pub struct RtcDriftEstimator {
+    last: Option<(i64, i64)>,
+}
+
+impl RtcDriftEstimator {
+    /// Create a new estimator without any observations.
+    pub const fn new() -> Self {
+        Self { last: None }
+    }
+
+    /// Record a pair of RTC time and true time, taken at the same instant.
+    ///
+    /// Returns the drift since the previous observation, or `None` if this is the first
+    /// observation or no time has passed since the previous one. If the RTC is set to the
+    /// true time after the observation, call [`RtcDriftEstimator::resynchronized`] as well.
+    pub fn observe(&mut self, rtc: DateTime, truth: DateTime) -> Option<RtcDrift> {
+        let rtc = datetime_to_seconds(&rtc);
+        let truth = datetime_to_seconds(&truth);
+
+        let drift = self.last.and_then(|(last_rtc, last_truth)| {
+            let rtc_elapsed = rtc - last_rtc;
+            let true_elapsed = truth - last_truth;
+
+            if true_elapsed <= 0 {
+                return None;
+            }
+
+            Some(RtcDrift {
+                ppm: (rtc_elapsed - true_elapsed) as f32 * 1_000_000.0 / true_elapsed as f32,
+                interval_secs: true_elapsed.min(u32::MAX as i64) as u32,
+            })
+        });
+
+        self.last = Some((rtc, truth));
+
+        drift
+    }
+
+    /// Record that the RTC was set to the true time of the last observation.
+    pub fn resynchronized(&mut self) {
+        if let Some((_, truth)) = self.last {
+            self.last = Some((truth, truth));
+        }
+    }
+
+    /// Forget all previous observations.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+impl Default for RtcDriftEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::DayOfWeek;
+
+    fn datetime(day: u8, hour: u8, minute: u8, second: u8) -> DateTime {
+        DateTime {
+            year: 2023,
+            month: 2,
+            day,
+            day_of_week: DayOfWeek::Monday,
+            hour,
+            minute,
+            second,
+        }
+    }
+
+    #[test]
+    fn first_observation_has_no_estimate() {
+        let mut estimator = RtcDriftEstimator::new();
+        assert_eq!(None, estimator.observe(datetime(1, 0, 0, 0), datetime(1, 0, 0, 0)));
+    }
+
+    #[test]
+    fn can_estimate_drift() {
+        let mut estimator = RtcDriftEstimator::new();
+        estimator.observe(datetime(1, 0, 0, 0), datetime(1, 0, 0, 0));
+
+        // RTC gained 2 seconds over 27.5 days
+        let drift = estimator
+            .observe(datetime(28, 12, 0, 2), datetime(28, 12, 0, 0))
+            .unwrap();
+        assert_eq!(27 * 86_400 + 12 * 3_600, drift.interval_secs);
+        assert!((drift.ppm - 0.8418).abs() < 0.001);
+        assert!(drift.calibration() < 0.0);
+
+        // RTC was re-synchronized and lost 1 second over 10000 seconds
+        estimator.resynchronized();
+        let drift = estimator
+            .observe(datetime(28, 14, 46, 39), datetime(28, 14, 46, 40))
+            .unwrap();
+        assert_eq!(10_000, drift.interval_secs);
+        assert!((drift.ppm + 100.0).abs() < 0.001);
+    }
+}
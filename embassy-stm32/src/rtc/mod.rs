@@ -1,8 +1,35 @@
 //! RTC peripheral abstraction
+//!
+//! The tamper detection (`RtcTamper`) is only available on the chips with the RTC2 tamper registers.
+//! It isn't supported on F2, whose RTC has a single tamper input with a different register layout, nor
+//! on the chips with RTC3, whose tamper inputs belong to the separate TAMP peripheral (G0, G4, L5, U5,
+//! WL), as stm32-metapac doesn't describe its registers yet.
+use core::future::poll_fn;
 use core::marker::PhantomData;
-mod datetime;
+use core::task::Poll;
+use core::time::Duration;
 
-pub use self::datetime::{DateTime, DayOfWeek, Error as DateTimeError};
+use atomic_polyfill::{AtomicBool, AtomicU8, Ordering};
+use embassy_sync::waitqueue::AtomicWaker;
+mod alarm;
+mod datetime;
+mod drift;
+mod scheduler;
+#[cfg(not(any(rtc_v2f2, rtc_v3, rtc_v3u5)))]
+mod tamper;
+#[cfg(time_driver_rtc)]
+pub(crate) mod time_driver;
+mod timestamp;
+mod wakeup;
+
+pub use self::alarm::{Alarm, AlarmConfig, AlarmDate};
+pub use self::datetime::{DateTime, DayOfWeek, Error as DateTimeError, UtcOffset};
+pub use self::drift::{RtcDrift, RtcDriftEstimator};
+pub use self::scheduler::{RtcScheduler, SchedulerFull};
+#[cfg(not(any(rtc_v2f2, rtc_v3, rtc_v3u5)))]
+pub use self::tamper::{RtcTamper, Tamper, TamperConfig, TamperFilter, TamperLevel, TamperPrecharge};
+pub use self::timestamp::{RtcTimestamp, TimestampEdge};
+pub use self::wakeup::RtcWakeup;
 
 /// refer to AN4759 to compare features of RTC2 and RTC3
 #[cfg_attr(any(rtc_v1), path = "v1.rs")]
@@ -17,6 +44,101 @@ mod _version;
 pub use _version::*;
 use embassy_hal_common::Peripheral;
 
+use crate::interrupt;
+
+static ALARM_WAKER: AtomicWaker = AtomicWaker::new();
+/// Bit `n` is set when alarm `n` fired and has not been waited for yet.
+static ALARM_FIRED: AtomicU8 = AtomicU8::new(0);
+
+static WAKEUP_WAKER: AtomicWaker = AtomicWaker::new();
+/// Set when the wakeup timer fired and has not been waited for yet.
+static WAKEUP_FIRED: AtomicBool = AtomicBool::new(false);
+
+/// RTC interrupt handler.
+///
+/// Bind this to the interrupts the RTC alarms, the wakeup timer and the tamper detection are routed to,
+/// e.g. `RTC_ALARM`, `RTC_WKUP`, `TAMP_STAMP` or `RTC`, depending on the chip.
+pub struct InterruptHandler {
+    _private: (),
+}
+
+impl<I: interrupt::Interrupt> interrupt::Handler<I> for InterruptHandler {
+    unsafe fn on_interrupt() {
+        let fired = _version::on_alarm_interrupt();
+        if fired != 0 {
+            clear_exti(_version::EXTI_ALARM_LINE);
+            ALARM_FIRED.fetch_or(fired, Ordering::SeqCst);
+            ALARM_WAKER.wake();
+        }
+
+        if _version::on_wakeup_interrupt() {
+            clear_exti(_version::EXTI_WAKEUP_LINE);
+            WAKEUP_FIRED.store(true, Ordering::SeqCst);
+            WAKEUP_WAKER.wake();
+        }
+
+        #[cfg(not(any(rtc_v2f2, rtc_v3, rtc_v3u5)))]
+        tamper::on_interrupt();
+    }
+}
+
+/// Enable the interrupt `I` and the EXTI line `exti_line` the RTC event is routed through, if any.
+fn enable_interrupt<I: interrupt::Interrupt>(exti_line: Option<usize>) {
+    use crate::interrupt::InterruptExt;
+
+    if let Some(line) = exti_line {
+        use crate::pac::EXTI;
+
+        critical_section::with(|_| unsafe {
+            EXTI.rtsr(0).modify(|w| w.set_line(line, true));
+            #[cfg(exti_w)]
+            EXTI.cpu(crate::pac::CORE_INDEX)
+                .imr(0)
+                .modify(|w| w.set_line(line, true));
+            #[cfg(not(exti_w))]
+            EXTI.imr(0).modify(|w| w.set_line(line, true));
+        });
+    }
+
+    let irq = unsafe { I::steal() };
+    irq.unpend();
+    irq.enable();
+}
+
+fn clear_exti(exti_line: Option<usize>) {
+    if let Some(line) = exti_line {
+        use crate::pac::exti::regs::Lines;
+        use crate::pac::EXTI;
+
+        let mut lines = Lines(0);
+        lines.set_line(line, true);
+
+        #[cfg(not(any(exti_c0, exti_g0, exti_l5, exti_u5, exti_h5, exti_h50)))]
+        unsafe {
+            EXTI.pr(0).write_value(lines)
+        };
+        #[cfg(any(exti_c0, exti_g0, exti_l5, exti_u5, exti_h5, exti_h50))]
+        unsafe {
+            EXTI.rpr(0).write_value(lines)
+        };
+    }
+}
+
+/// Wait until `alarm` fires.
+async fn wait_alarm(alarm: Alarm) {
+    let bit = 1 << alarm.index();
+    poll_fn(|cx| {
+        ALARM_WAKER.register(cx.waker());
+
+        if ALARM_FIRED.fetch_and(!bit, Ordering::SeqCst) & bit != 0 {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
 /// Errors that can occur on methods on [RtcClock]
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum RtcError {
@@ -60,6 +182,8 @@ pub struct RtcConfig {
     /// ck_spre frequency = ck_apre frequency/(PREDIV_S+1)
     /// ck_spre must be 1Hz
     sync_prescaler: u16,
+    /// Backup register holding the time of the first boot, see [`Rtc::uptime`]
+    uptime_register: Option<usize>,
 }
 
 impl Default for RtcConfig {
@@ -70,6 +194,7 @@ impl Default for RtcConfig {
             clock_config: RtcClockSource::LSI,
             async_prescaler: 127,
             sync_prescaler: 255,
+            uptime_register: None,
         }
     }
 }
@@ -92,6 +217,88 @@ impl RtcConfig {
         self.sync_prescaler = prescaler;
         self
     }
+
+    /// Set both prescalers of RTC config from a validated [`RtcPrescaler`]
+    pub fn prescaler(mut self, prescaler: RtcPrescaler) -> Self {
+        self.async_prescaler = prescaler.async_prescaler;
+        self.sync_prescaler = prescaler.sync_prescaler;
+        self
+    }
+
+    /// Use the backup register `register` to keep track of the uptime, see [`Rtc::uptime`]
+    pub fn uptime_register(mut self, register: usize) -> Self {
+        self.uptime_register = Some(register);
+        self
+    }
+}
+
+/// Range checked pair of RTC prescaler factors.
+///
+/// The constructors panic on invalid values, which turns into a compile time error when used in a
+/// constant:
+///
+/// ```ignore
+/// const PRESCALER: RtcPrescaler = RtcPrescaler::for_frequency(32_768);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RtcPrescaler {
+    async_prescaler: u8,
+    sync_prescaler: u16,
+}
+
+impl RtcPrescaler {
+    /// Maximum asynchronous prescaler factor
+    pub const MAX_ASYNC: u8 = 127;
+    /// Maximum synchronous prescaler factor
+    pub const MAX_SYNC: u16 = 32767;
+
+    /// Prescaler factors as written to the registers, i.e. the clock is divided by `async_prescaler + 1`
+    /// and `sync_prescaler + 1`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `async_prescaler` is larger than 127 or `sync_prescaler` is larger than 32767.
+    pub const fn new(async_prescaler: u8, sync_prescaler: u16) -> Self {
+        if async_prescaler > Self::MAX_ASYNC {
+            ::core::panic!("asynchronous prescaler must be at most 127");
+        }
+        if sync_prescaler > Self::MAX_SYNC {
+            ::core::panic!("synchronous prescaler must be at most 32767");
+        }
+
+        Self {
+            async_prescaler,
+            sync_prescaler,
+        }
+    }
+
+    /// Prescaler factors producing the 1 Hz calendar clock from an RTC clock of `hz`, using the largest
+    /// asynchronous prescaler to minimize power consumption.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hz` is not divisible by 128 or too large.
+    pub const fn for_frequency(hz: u32) -> Self {
+        let async_div = Self::MAX_ASYNC as u32 + 1;
+        if hz == 0 || hz % async_div != 0 {
+            ::core::panic!("RTC clock frequency must be a multiple of 128 Hz");
+        }
+        if hz / async_div > Self::MAX_SYNC as u32 + 1 {
+            ::core::panic!("RTC clock frequency too high");
+        }
+
+        Self::new(Self::MAX_ASYNC, (hz / async_div - 1) as u16)
+    }
+
+    /// Asynchronous prescaler factor
+    pub const fn async_prescaler(&self) -> u8 {
+        self.async_prescaler
+    }
+
+    /// Synchronous prescaler factor
+    pub const fn sync_prescaler(&self) -> u16 {
+        self.sync_prescaler
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -121,10 +328,47 @@ impl<'d, T: Instance> Rtc<'d, T> {
         };
 
         rtc_struct.apply_config(rtc_config);
+        rtc_struct.init_uptime();
 
         rtc_struct
     }
 
+    /// Store the current time in the uptime register, unless the register already holds a boot time.
+    fn init_uptime(&mut self) {
+        let Some(register) = self.rtc_config.uptime_register else {
+            return;
+        };
+
+        if self.read_backup_register(register) != Some(0) || !self.is_running() {
+            return;
+        }
+
+        if let Ok(now) = self.now() {
+            let now = self::datetime::datetime_to_seconds(&now);
+            // 0 marks the register as unset, which is only reached at 1970-01-01 00:00:00
+            self.write_backup_register(register, now.max(1) as u32);
+        }
+    }
+
+    /// Time elapsed since the boot time stored in the uptime register, see [`RtcConfig::uptime_register`].
+    ///
+    /// The boot time is stored the first time the RTC is created with a running calendar, or when the time is set
+    /// for the first time. It is kept as long as the backup domain stays powered, so the uptime survives resets
+    /// and main supply loss in battery-backed designs. It only restarts when the backup domain loses power and
+    /// the backup registers are cleared.
+    ///
+    /// Returns `None` if no uptime register is configured, no boot time has been stored yet or the backup
+    /// registers are not available on this chip.
+    pub fn uptime(&self) -> Option<Duration> {
+        let boot = self.read_backup_register(self.rtc_config.uptime_register?)?;
+        if boot == 0 {
+            return None;
+        }
+
+        let now = self::datetime::datetime_to_seconds(&self.now().ok()?);
+        Some(Duration::from_secs((now - boot as i64).max(0) as u64))
+    }
+
     /// Set the datetime to a new value.
     ///
     /// # Errors
@@ -133,6 +377,7 @@ impl<'d, T: Instance> Rtc<'d, T> {
     pub fn set_datetime(&mut self, t: DateTime) -> Result<(), RtcError> {
         self::datetime::validate_datetime(&t).map_err(RtcError::InvalidDateTime)?;
         self.write(true, |rtc| self::datetime::write_date_time(rtc, t));
+        self.init_uptime();
 
         Ok(())
     }
@@ -162,6 +407,62 @@ impl<'d, T: Instance> Rtc<'d, T> {
         }
     }
 
+    /// Return the current datetime together with the elapsed fraction of the current second.
+    ///
+    /// The resolution of the fraction is one period of the synchronous prescaler input, i.e. 1/256 s
+    /// with the default configuration.
+    ///
+    /// # Errors
+    ///
+    /// Will return an `RtcError::InvalidDateTime` if the stored value in the system is not a valid [`DayOfWeek`].
+    #[cfg(not(rtc_v2f2))]
+    pub fn now_subsec(&self) -> Result<(DateTime, Duration), RtcError> {
+        // the shadow registers are not valid until they were synchronized after initialization or a shift
+        while !_version::shadow_registers_synced() {}
+
+        // Reading RTC_SSR locks RTC_TR and RTC_DR until RTC_DR is read by `now`, so all
+        // values belong to the same second.
+        let ss = unsafe { T::regs().ssr().read().ss() } as u64;
+        let now = self.now()?;
+
+        Ok((now, self.subsecond(ss)))
+    }
+
+    /// Converts a value of the subsecond counter, which counts down from the synchronous prescaler,
+    /// to the elapsed fraction of the second.
+    #[cfg(not(rtc_v2f2))]
+    fn subsecond(&self, ss: u64) -> Duration {
+        let ticks = self.rtc_config.sync_prescaler as u64 + 1;
+        // after a shift operation, the counter can exceed the prescaler for a moment
+        let elapsed = ticks.saturating_sub(ss + 1);
+        Duration::from_nanos(elapsed * 1_000_000_000 / ticks)
+    }
+
+    /// Set the datetime to a new value, given in UTC.
+    ///
+    /// Keeping the calendar in UTC makes alarms and timestamps independent of the local time zone. Use
+    /// [`Rtc::now_local`] to get the local time.
+    ///
+    /// # Errors
+    ///
+    /// Will return `RtcError::InvalidDateTime` if the datetime is not a valid range.
+    pub fn set_utc(&mut self, dt: DateTime) -> Result<(), RtcError> {
+        self.set_datetime(dt)
+    }
+
+    /// Return the current local datetime, assuming the calendar is kept in UTC.
+    ///
+    /// If daylight savings time is active (see [`Rtc::set_daylight_savings`]), one additional
+    /// hour is added on top of `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Will return an `RtcError::InvalidDateTime` if the stored value in the system is not a valid [`DayOfWeek`].
+    pub fn now_local(&self, offset: UtcOffset) -> Result<DateTime, RtcError> {
+        let utc = self.now()?;
+        Ok(offset.to_local(&utc, self.get_daylight_savings()))
+    }
+
     /// Check if daylight savings time is active.
     pub fn get_daylight_savings(&self) -> bool {
         let cr = unsafe { T::regs().cr().read() };
@@ -175,13 +476,47 @@ impl<'d, T: Instance> Rtc<'d, T> {
         })
     }
 
+    /// Enable the interrupt the RTC alarms are routed to, which is required by [`Rtc::wait_for_alarm`].
+    pub fn enable_interrupt<I: interrupt::Interrupt>(
+        &mut self,
+        _irq: impl interrupt::Binding<I, InterruptHandler> + 'd,
+    ) {
+        enable_interrupt::<I>(_version::EXTI_ALARM_LINE);
+    }
+
+    /// Program and enable `alarm`, replacing its previous configuration.
+    ///
+    /// # Errors
+    ///
+    /// Will return `RtcError::InvalidDateTime` if a field of `config` is out of range.
+    pub fn enable_alarm(&mut self, alarm: Alarm, config: AlarmConfig) -> Result<(), RtcError> {
+        config.validate().map_err(RtcError::InvalidDateTime)?;
+
+        ALARM_FIRED.fetch_and(!(1 << alarm.index()), Ordering::SeqCst);
+        self.set_alarm(alarm, &config);
+
+        Ok(())
+    }
+
+    /// Wait until `alarm` fires.
+    ///
+    /// The alarm has to be enabled with [`Rtc::enable_alarm`] and the RTC interrupt with
+    /// [`Rtc::enable_interrupt`], otherwise this never returns. Alarms which fired since they were
+    /// enabled or last waited for are returned immediately. Alarms matching on some fields only keep
+    /// firing on every match until they are disabled.
+    pub async fn wait_for_alarm(&mut self, alarm: Alarm) {
+        wait_alarm(alarm).await
+    }
+
     pub fn get_config(&self) -> RtcConfig {
         self.rtc_config
     }
 
+    /// Number of backup registers. The backup registers of the chips with RTC3 (e.g. G0, G4, L5, U5, WL)
+    /// belong to the TAMP peripheral, which stm32-metapac doesn't describe yet, so they have none.
     pub const BACKUP_REGISTER_COUNT: usize = T::BACKUP_REGISTER_COUNT;
 
-    /// Read content of the backup register.
+    /// Read content of the backup register, or `None` if it doesn't exist.
     ///
     /// The registers retain their values during wakes from standby mode or system resets. They also
     /// retain their value when Vdd is switched off as long as V_BAT is powered.
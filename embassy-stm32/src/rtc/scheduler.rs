@@ -0,0 +1,114 @@
+use super::datetime::{datetime_to_seconds, seconds_to_datetime};
+use super::{Alarm, AlarmConfig, DateTime, Instance, InterruptHandler, Rtc, RtcError};
+use crate::interrupt;
+
+/// Alarm A is used by the scheduler.
+const ALARM: Alarm = Alarm::A;
+
+/// Error returned by [`RtcScheduler::schedule`] if all entries are in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SchedulerFull;
+
+/// Multiplexes RTC Alarm A onto up to `N` scheduled events.
+///
+/// Alarm A is always programmed for the soonest event. Events are identified by an `Id` chosen by the user.
+pub struct RtcScheduler<'r, 'd, T: Instance, Id: Copy, const N: usize> {
+    rtc: &'r mut Rtc<'d, T>,
+    /// Sorted by time, soonest first. Times are in seconds since 1970-01-01.
+    entries: [Option<(Id, i64)>; N],
+    len: usize,
+}
+
+impl<'r, 'd, T: Instance, Id: Copy, const N: usize> RtcScheduler<'r, 'd, T, Id, N> {
+    /// Create a scheduler with no events, taking over Alarm A. Bind the [`InterruptHandler`] to the alarm
+    /// interrupt of the chip (e.g. `RTC_ALARM` or `RTC`).
+    pub fn new<I: interrupt::Interrupt>(
+        rtc: &'r mut Rtc<'d, T>,
+        _irq: impl interrupt::Binding<I, InterruptHandler> + 'r,
+    ) -> Self {
+        super::enable_interrupt::<I>(super::_version::EXTI_ALARM_LINE);
+
+        Self {
+            rtc,
+            entries: [None; N],
+            len: 0,
+        }
+    }
+
+    /// Schedule the event `id` at `when`.
+    ///
+    /// Events at the same time are returned by [`RtcScheduler::next_due`] in the order they were scheduled.
+    /// Events in the past are due immediately.
+    pub fn schedule(&mut self, id: Id, when: DateTime) -> Result<(), SchedulerFull> {
+        if self.len == N {
+            return Err(SchedulerFull);
+        }
+
+        let when = datetime_to_seconds(&when);
+        let pos = self.entries[..self.len]
+            .iter()
+            .position(|e| matches!(e, Some((_, t)) if *t > when))
+            .unwrap_or(self.len);
+
+        self.entries[pos..=self.len].rotate_right(1);
+        self.entries[pos] = Some((id, when));
+        self.len += 1;
+
+        Ok(())
+    }
+
+    /// Remove all scheduled events.
+    pub fn clear(&mut self) {
+        self.entries = [None; N];
+        self.len = 0;
+        self.rtc.disable_alarm(ALARM);
+    }
+
+    /// Number of scheduled events.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no events are scheduled.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Wait for the next event to become due, remove it and return its id.
+    ///
+    /// Never returns if no events are scheduled.
+    pub async fn next_due(&mut self) -> Result<Id, RtcError> {
+        loop {
+            let (id, when) = match self.entries[0] {
+                Some(entry) => entry,
+                None => core::future::pending().await,
+            };
+
+            if datetime_to_seconds(&self.rtc.now()?) >= when {
+                self.entries[..self.len].rotate_left(1);
+                self.len -= 1;
+                self.entries[self.len] = None;
+
+                if self.len == 0 {
+                    self.rtc.disable_alarm(ALARM);
+                }
+
+                return Ok(id);
+            }
+
+            // The alarm only matches on day of month and time, so it may fire a month early for events
+            // far in the future. The time is checked again after every alarm.
+            self.rtc
+                .enable_alarm(ALARM, AlarmConfig::at(&seconds_to_datetime(when)))?;
+
+            // The event may have become due while the alarm was programmed, in which case the alarm
+            // would not fire until the next month.
+            if datetime_to_seconds(&self.rtc.now()?) >= when {
+                continue;
+            }
+
+            super::wait_alarm(ALARM).await;
+        }
+    }
+}
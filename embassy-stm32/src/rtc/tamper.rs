@@ -0,0 +1,276 @@
+use core::future::poll_fn;
+use core::task::Poll;
+
+use atomic_polyfill::{AtomicU8, Ordering};
+use embassy_sync::waitqueue::AtomicWaker;
+
+use super::{Instance, InterruptHandler, Rtc};
+use crate::interrupt;
+use crate::pac::rtc::vals::{Tampflt, Tampfreq, Tampprch, Tamppudis, Tamptrg};
+
+static TAMPER_WAKER: AtomicWaker = AtomicWaker::new();
+/// Bit `n` is set when tamper input `n` was triggered and has not been waited for yet.
+static TAMPER_FIRED: AtomicU8 = AtomicU8::new(0);
+
+#[cfg(any(rtc_v2f3, rtc_v2f4))]
+const TAMPER_COUNT: usize = 2;
+#[cfg(not(any(rtc_v2f3, rtc_v2f4)))]
+const TAMPER_COUNT: usize = 3;
+
+/// EXTI line the tamper interrupt is routed through.
+#[cfg(any(rtc_v2f4, rtc_v2f7, rtc_v2l1))]
+const EXTI_TAMPER_LINE: Option<usize> = Some(21);
+#[cfg(any(rtc_v2h7, rtc_v2wb))]
+const EXTI_TAMPER_LINE: Option<usize> = Some(18);
+#[cfg(any(rtc_v2f0, rtc_v2f3, rtc_v2l0, rtc_v2l4))]
+const EXTI_TAMPER_LINE: Option<usize> = Some(19);
+
+/// Tamper input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Tamper {
+    /// RTC_TAMP1
+    Tamper1,
+    /// RTC_TAMP2
+    Tamper2,
+    /// RTC_TAMP3
+    #[cfg(not(any(rtc_v2f3, rtc_v2f4)))]
+    Tamper3,
+}
+
+impl Tamper {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn from_index(index: usize) -> Self {
+        match index {
+            0 => Tamper::Tamper1,
+            1 => Tamper::Tamper2,
+            #[cfg(not(any(rtc_v2f3, rtc_v2f4)))]
+            2 => Tamper::Tamper3,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Active level of a tamper input.
+///
+/// Without filtering, a tamper event is detected on the rising edge for `High` and on the falling edge
+/// for `Low`. With filtering, the input has to stay at the level for the configured number of samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TamperLevel {
+    /// Falling edge or low level
+    Low,
+    /// Rising edge or high level
+    High,
+}
+
+/// Number of consecutive samples at the active level needed to detect a tamper event
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TamperFilter {
+    /// 2 consecutive samples
+    Samples2,
+    /// 4 consecutive samples
+    Samples4,
+    /// 8 consecutive samples
+    Samples8,
+}
+
+/// Duration the tamper inputs are precharged by the internal pull-up before sampling, in RTCCLK cycles
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TamperPrecharge {
+    /// 1 RTCCLK cycle
+    Cycles1,
+    /// 2 RTCCLK cycles
+    Cycles2,
+    /// 4 RTCCLK cycles
+    Cycles4,
+    /// 8 RTCCLK cycles
+    Cycles8,
+}
+
+/// Configuration shared by all tamper inputs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TamperConfig {
+    /// Level detection with filtering, or edge detection if `None`
+    pub filter: Option<TamperFilter>,
+    /// Sampling frequency as divider of RTCCLK, 256 to 32768. Only used with filtering.
+    pub sampling_divider: u16,
+    /// Precharge of the inputs, or no pull-up if `None`. Only used with filtering.
+    pub precharge: Option<TamperPrecharge>,
+}
+
+impl Default for TamperConfig {
+    /// Edge detection
+    fn default() -> Self {
+        Self {
+            filter: None,
+            sampling_divider: 32768,
+            precharge: Some(TamperPrecharge::Cycles1),
+        }
+    }
+}
+
+/// Tamper detection of the RTC.
+///
+/// A tamper event erases the backup registers (unless disabled per input, where supported), and can be
+/// waited for with [`RtcTamper::wait_for_tamper`]. Bind the [`InterruptHandler`] to the tamper interrupt of
+/// the chip, e.g. `TAMP_STAMP` or `RTC`.
+///
+/// Not available on F2, nor on the chips with the TAMP peripheral (G0, G4, L5, U5, WL), see the
+/// [module documentation](super).
+pub struct RtcTamper<'r, 'd, T: Instance> {
+    rtc: &'r mut Rtc<'d, T>,
+}
+
+impl<'r, 'd, T: Instance> RtcTamper<'r, 'd, T> {
+    /// Create the tamper detection with all inputs disabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config.sampling_divider` is not a power of two between 256 and 32768.
+    pub fn new<I: interrupt::Interrupt>(
+        rtc: &'r mut Rtc<'d, T>,
+        _irq: impl interrupt::Binding<I, InterruptHandler> + 'r,
+        config: TamperConfig,
+    ) -> Self {
+        let freq = match config.sampling_divider {
+            32768 => Tampfreq::DIV32768,
+            16384 => Tampfreq::DIV16384,
+            8192 => Tampfreq::DIV8192,
+            4096 => Tampfreq::DIV4096,
+            2048 => Tampfreq::DIV2048,
+            1024 => Tampfreq::DIV1024,
+            512 => Tampfreq::DIV512,
+            256 => Tampfreq::DIV256,
+            _ => panic!("invalid tamper sampling divider"),
+        };
+
+        rtc.write(false, |r| unsafe {
+            tamper_reg(r).modify(|w| {
+                for n in 0..TAMPER_COUNT {
+                    w.set_tampe(n, false);
+                }
+                w.set_tampie(true);
+                w.set_tampfreq(freq);
+                w.set_tampflt(match config.filter {
+                    None => Tampflt::IMMEDIATE,
+                    Some(TamperFilter::Samples2) => Tampflt::SAMPLES2,
+                    Some(TamperFilter::Samples4) => Tampflt::SAMPLES4,
+                    Some(TamperFilter::Samples8) => Tampflt::SAMPLES8,
+                });
+                w.set_tampprch(match config.precharge {
+                    None | Some(TamperPrecharge::Cycles1) => Tampprch::CYCLES1,
+                    Some(TamperPrecharge::Cycles2) => Tampprch::CYCLES2,
+                    Some(TamperPrecharge::Cycles4) => Tampprch::CYCLES4,
+                    Some(TamperPrecharge::Cycles8) => Tampprch::CYCLES8,
+                });
+                w.set_tamppudis(match config.precharge {
+                    Some(_) => Tamppudis::ENABLED,
+                    None => Tamppudis::DISABLED,
+                });
+            });
+        });
+
+        TAMPER_FIRED.store(0, Ordering::SeqCst);
+        super::enable_interrupt::<I>(EXTI_TAMPER_LINE);
+
+        Self { rtc }
+    }
+
+    /// Enable detection on `tamper`.
+    pub fn enable(&mut self, tamper: Tamper, level: TamperLevel) {
+        let n = tamper.index();
+        self.rtc.write(false, |r| unsafe {
+            let reg = tamper_reg(r);
+            let edge = reg.read().tampflt() == Tampflt::IMMEDIATE;
+
+            // TAMPxTRG selects the rising edge or the low level when cleared
+            let trg = match (edge, level) {
+                (true, TamperLevel::High) | (false, TamperLevel::Low) => Tamptrg(0),
+                (true, TamperLevel::Low) | (false, TamperLevel::High) => Tamptrg(1),
+            };
+
+            // the trigger has to be configured before the input is enabled
+            reg.modify(|w| w.set_tamptrg(n, trg));
+            r.isr().modify(|w| w.set_tampf(n, false));
+            reg.modify(|w| w.set_tampe(n, true));
+        })
+    }
+
+    /// Disable detection on `tamper`.
+    pub fn disable(&mut self, tamper: Tamper) {
+        self.rtc.write(false, |r| unsafe {
+            tamper_reg(r).modify(|w| w.set_tampe(tamper.index(), false))
+        })
+    }
+
+    /// Select whether a tamper event on `tamper` erases the backup registers, which is the default.
+    ///
+    /// Keeping the backup registers allows to use the tamper input as a generic wakeup source powered by
+    /// the backup domain.
+    #[cfg(any(rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l4, rtc_v2wb))]
+    pub fn set_backup_erase(&mut self, tamper: Tamper, erase: bool) {
+        self.rtc.write(false, |r| unsafe {
+            r.tampcr().modify(|w| w.set_tampxnoerase(tamper.index(), !erase))
+        })
+    }
+
+    /// Wait until a tamper event is detected and return the input which detected it.
+    ///
+    /// Returns immediately if a tamper event was detected since the last call.
+    pub async fn wait_for_tamper(&mut self) -> Tamper {
+        poll_fn(|cx| {
+            TAMPER_WAKER.register(cx.waker());
+
+            let fired = TAMPER_FIRED.load(Ordering::SeqCst);
+            if fired == 0 {
+                return Poll::Pending;
+            }
+
+            let n = fired.trailing_zeros() as usize;
+            TAMPER_FIRED.fetch_and(!(1 << n), Ordering::SeqCst);
+            Poll::Ready(Tamper::from_index(n))
+        })
+        .await
+    }
+}
+
+#[cfg(any(rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l4, rtc_v2wb))]
+fn tamper_reg(
+    r: &crate::pac::rtc::Rtc,
+) -> crate::pac::common::Reg<crate::pac::rtc::regs::Tampcr, crate::pac::common::RW> {
+    r.tampcr()
+}
+
+#[cfg(not(any(rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l4, rtc_v2wb)))]
+fn tamper_reg(
+    r: &crate::pac::rtc::Rtc,
+) -> crate::pac::common::Reg<crate::pac::rtc::regs::Tafcr, crate::pac::common::RW> {
+    r.tafcr()
+}
+
+/// Clears the pending tamper flags and wakes the waiting task.
+pub(super) unsafe fn on_interrupt() {
+    let r = crate::pac::RTC;
+    let isr = r.isr().read();
+    let reg = tamper_reg(&r).read();
+
+    let mut fired = 0;
+    for n in 0..TAMPER_COUNT {
+        if isr.tampf(n) && reg.tampe(n) {
+            r.isr().modify(|w| w.set_tampf(n, false));
+            fired |= 1 << n;
+        }
+    }
+
+    if fired != 0 {
+        super::clear_exti(EXTI_TAMPER_LINE);
+        TAMPER_FIRED.fetch_or(fired, Ordering::SeqCst);
+        TAMPER_WAKER.wake();
+    }
+}
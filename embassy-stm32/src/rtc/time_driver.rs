@@ -0,0 +1,313 @@
+//! Time driver based on the RTC.
+//!
+//! `now` is derived from the calendar and the subsecond counter, and alarms are scheduled with the wakeup
+//! timer. Both keep running in STOP modes, so timers keep working while the chip is in deep sleep.
+//!
+//! The resolution of `now` is one period of the synchronous prescaler input. Use an asynchronous
+//! prescaler of 0 for the finest resolution, e.g. [`RtcPrescaler::for_frequency`] with a larger
+//! synchronous prescaler, at the cost of a slightly higher power consumption:
+//!
+//! ```ignore
+//! config.rtc = RtcConfig::default()
+//!     .clock_config(RtcClockSource::LSE)
+//!     .prescaler(RtcPrescaler::new(0, 32767));
+//! ```
+//!
+//! [`RtcPrescaler::for_frequency`]: super::RtcPrescaler::for_frequency
+use core::cell::{Cell, RefCell};
+use core::{mem, ptr};
+
+use atomic_polyfill::{AtomicU8, Ordering};
+use critical_section::CriticalSection;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::driver::{AlarmHandle, Driver};
+use embassy_time::TICK_HZ;
+
+use super::datetime::days_from_civil;
+use super::{bcd2_to_byte, Rtc, RtcConfig};
+use crate::pac::rtc::vals::Wucksel;
+use crate::{interrupt, peripherals};
+
+const ALARM_COUNT: usize = 3;
+
+foreach_interrupt! {
+    (RTC, rtc, $block:ident, WKUP, $irq:ident) => {
+        type WakeupInterrupt = crate::interrupt::$irq;
+
+        #[interrupt]
+        fn $irq() {
+            DRIVER.on_interrupt()
+        }
+    };
+    // the wakeup timer shares the interrupt of the tamper detection on G0
+    (RTC, rtc, $block:ident, TAMP, $irq:ident) => {
+        #[cfg(stm32g0)]
+        type WakeupInterrupt = crate::interrupt::$irq;
+
+        #[cfg(stm32g0)]
+        #[interrupt]
+        fn $irq() {
+            DRIVER.on_interrupt()
+        }
+    };
+}
+
+struct AlarmState {
+    timestamp: Cell<u64>,
+
+    // This is really a Option<(fn(*mut ()), *mut ())>
+    // but fn pointers aren't allowed in const yet
+    callback: Cell<*const ()>,
+    ctx: Cell<*mut ()>,
+}
+
+unsafe impl Send for AlarmState {}
+
+impl AlarmState {
+    const fn new() -> Self {
+        Self {
+            timestamp: Cell::new(u64::MAX),
+            callback: Cell::new(ptr::null()),
+            ctx: Cell::new(ptr::null_mut()),
+        }
+    }
+}
+
+struct State {
+    rtc: Rtc<'static, peripherals::RTC>,
+    /// Calendar time in ticks at initialization, `now` counts from there.
+    start: u64,
+    /// Timestamp the wakeup timer is programmed for. u64::MAX if it is disabled.
+    armed: u64,
+}
+
+struct RtcDriver {
+    alarm_count: AtomicU8,
+    state: Mutex<CriticalSectionRawMutex, RefCell<Option<State>>>,
+    /// Timestamp at which to fire alarm. u64::MAX if no alarm is scheduled.
+    alarms: Mutex<CriticalSectionRawMutex, [AlarmState; ALARM_COUNT]>,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ALARM_STATE_NEW: AlarmState = AlarmState::new();
+
+embassy_time::time_driver_impl!(static DRIVER: RtcDriver = RtcDriver {
+    alarm_count: AtomicU8::new(0),
+    state: Mutex::const_new(CriticalSectionRawMutex::new(), RefCell::new(None)),
+    alarms: Mutex::const_new(CriticalSectionRawMutex::new(), [ALARM_STATE_NEW; ALARM_COUNT]),
+});
+
+impl RtcDriver {
+    fn init(&'static self, config: RtcConfig) {
+        let mut rtc = Rtc::new(unsafe { peripherals::RTC::steal() }, config);
+
+        // The shadow registers are only synchronized some RTCCLK cycles after a wakeup from STOP mode,
+        // so the counters are read directly.
+        rtc.write(false, |r| unsafe { r.cr().modify(|w| w.set_bypshad(true)) });
+        rtc.disable_wakeup();
+
+        let start = calendar_ticks(&config);
+        critical_section::with(|cs| {
+            self.state.borrow(cs).replace(Some(State {
+                rtc,
+                start,
+                armed: u64::MAX,
+            }));
+        });
+
+        super::enable_interrupt::<WakeupInterrupt>(super::_version::EXTI_WAKEUP_LINE);
+    }
+
+    fn on_interrupt(&self) {
+        critical_section::with(|cs| {
+            if !unsafe { super::_version::on_wakeup_interrupt() } {
+                return;
+            }
+            super::clear_exti(super::_version::EXTI_WAKEUP_LINE);
+
+            // the wakeup timer is periodic, it is reprogrammed for the next alarm below
+            if let Some(state) = self.state.borrow(cs).borrow_mut().as_mut() {
+                state.rtc.disable_wakeup();
+                state.armed = u64::MAX;
+            }
+
+            let now = self.now();
+            for n in 0..ALARM_COUNT {
+                if self.alarms.borrow(cs)[n].timestamp.get() <= now {
+                    self.trigger_alarm(n, cs);
+                }
+            }
+
+            self.arm(cs);
+        })
+    }
+
+    /// Program the wakeup timer for the earliest scheduled alarm.
+    ///
+    /// Long delays are counted with the 1 Hz clock and can end early, the wakeup timer is then
+    /// programmed again for the remaining time.
+    fn arm(&self, cs: CriticalSection) {
+        let next = self
+            .alarms
+            .borrow(cs)
+            .iter()
+            .map(|a| a.timestamp.get())
+            .min()
+            .unwrap_or(u64::MAX);
+        let now = self.now();
+
+        let mut state = self.state.borrow(cs).borrow_mut();
+        let Some(state) = state.as_mut() else {
+            return;
+        };
+
+        if next == state.armed {
+            return;
+        }
+        state.armed = next;
+
+        if next == u64::MAX {
+            state.rtc.disable_wakeup();
+            return;
+        }
+
+        let config = state.rtc.get_config();
+        let rtcclk = (config.async_prescaler as u32 + 1) * (config.sync_prescaler as u32 + 1);
+        let (wucksel, wut) = wakeup_config(next.saturating_sub(now), rtcclk);
+        state.rtc.set_wakeup(wucksel, wut);
+    }
+
+    fn get_alarm<'a>(&'a self, cs: CriticalSection<'a>, alarm: AlarmHandle) -> &'a AlarmState {
+        // safety: we're allowed to assume the AlarmState is created by us, and
+        // we never create one that's out of bounds.
+        unsafe { self.alarms.borrow(cs).get_unchecked(alarm.id() as usize) }
+    }
+
+    fn trigger_alarm(&self, n: usize, cs: CriticalSection) {
+        let alarm = &self.alarms.borrow(cs)[n];
+        alarm.timestamp.set(u64::MAX);
+
+        // Call after clearing alarm, so the callback can set another alarm.
+
+        // safety:
+        // - we can ignore the possibility of `f` being unset (null) because of the safety contract of `allocate_alarm`.
+        // - other than that we only store valid function pointers into alarm.callback
+        let f: fn(*mut ()) = unsafe { mem::transmute(alarm.callback.get()) };
+        f(alarm.ctx.get());
+    }
+}
+
+impl Driver for RtcDriver {
+    fn now(&self) -> u64 {
+        critical_section::with(|cs| match self.state.borrow(cs).borrow().as_ref() {
+            Some(state) => calendar_ticks(&state.rtc.get_config()) - state.start,
+            None => 0,
+        })
+    }
+
+    unsafe fn allocate_alarm(&self) -> Option<AlarmHandle> {
+        let id = self.alarm_count.fetch_update(Ordering::AcqRel, Ordering::Acquire, |x| {
+            if x < ALARM_COUNT as u8 {
+                Some(x + 1)
+            } else {
+                None
+            }
+        });
+
+        match id {
+            Ok(id) => Some(AlarmHandle::new(id)),
+            Err(_) => None,
+        }
+    }
+
+    fn set_alarm_callback(&self, alarm: AlarmHandle, callback: fn(*mut ()), ctx: *mut ()) {
+        critical_section::with(|cs| {
+            let alarm = self.get_alarm(cs, alarm);
+
+            alarm.callback.set(callback as *const ());
+            alarm.ctx.set(ctx);
+        })
+    }
+
+    fn set_alarm(&self, alarm: AlarmHandle, timestamp: u64) -> bool {
+        critical_section::with(|cs| {
+            let alarm = self.get_alarm(cs, alarm);
+
+            if timestamp <= self.now() {
+                // If alarm timestamp has passed the alarm will not fire.
+                // Disarm the alarm and return `false` to indicate that.
+                alarm.timestamp.set(u64::MAX);
+                self.arm(cs);
+
+                return false;
+            }
+
+            alarm.timestamp.set(timestamp);
+            self.arm(cs);
+
+            true
+        })
+    }
+}
+
+/// Current calendar time in ticks since 1970-01-01, including the subsecond counter.
+fn calendar_ticks(config: &RtcConfig) -> u64 {
+    let r = crate::pac::RTC;
+
+    // Without the shadow registers, the counters may advance between the reads, so they are read
+    // until two consecutive reads agree.
+    let read = || unsafe { (r.ssr().read().ss(), r.tr().read(), r.dr().read()) };
+    let (mut ss, mut tr, mut dr) = read();
+    loop {
+        let (ss2, tr2, dr2) = read();
+        if (ss2, tr2.0, dr2.0) == (ss, tr.0, dr.0) {
+            break;
+        }
+        (ss, tr, dr) = (ss2, tr2, dr2);
+    }
+
+    let year = bcd2_to_byte((dr.yt(), dr.yu())) as i64 + 1970;
+    let month = bcd2_to_byte((dr.mt() as u8, dr.mu()));
+    let day = bcd2_to_byte((dr.dt(), dr.du()));
+    let seconds = days_from_civil(year, month, day) as u64 * 86_400
+        + bcd2_to_byte((tr.ht(), tr.hu())) as u64 * 3_600
+        + bcd2_to_byte((tr.mnt(), tr.mnu())) as u64 * 60
+        + bcd2_to_byte((tr.st(), tr.su())) as u64;
+
+    // the subsecond counter counts down from the synchronous prescaler, and can exceed it for a moment
+    // after a shift operation
+    let subticks = config.sync_prescaler as u64 + 1;
+    let elapsed = subticks.saturating_sub(ss as u64 + 1);
+
+    seconds * TICK_HZ + elapsed * TICK_HZ / subticks
+}
+
+/// Wakeup clock selection and auto-reload value for a wakeup after `ticks`, given the RTC clock
+/// frequency `rtcclk`.
+///
+/// Delays up to 2^16 periods of RTCCLK/16 are rounded up, so the wakeup does not happen before the alarm
+/// is due. Longer delays are counted in seconds and rounded down, capped to 2^16 seconds.
+fn wakeup_config(ticks: u64, rtcclk: u32) -> (Wucksel, u16) {
+    const MAX_COUNT: u64 = 1 << 16;
+
+    let ticks = ticks.min(MAX_COUNT * TICK_HZ);
+    for (wucksel, div) in [
+        (Wucksel::DIV2, 2),
+        (Wucksel::DIV4, 4),
+        (Wucksel::DIV8, 8),
+        (Wucksel::DIV16, 16),
+    ] {
+        let count = (ticks * rtcclk as u64 + div * TICK_HZ - 1) / (div * TICK_HZ);
+        if count <= MAX_COUNT {
+            return (wucksel, (count.max(1) - 1) as u16);
+        }
+    }
+
+    let secs = (ticks / TICK_HZ).clamp(1, MAX_COUNT);
+    (Wucksel::CLOCKSPARE, (secs - 1) as u16)
+}
+
+pub(crate) fn init(config: RtcConfig) {
+    DRIVER.init(config)
+}
@@ -0,0 +1,89 @@
+#[cfg(not(rtc_v2f2))]
+use core::time::Duration;
+
+use super::datetime::day_of_week_from_u8;
+use super::{bcd2_to_byte, DayOfWeek, Instance, Rtc, RtcError};
+use crate::pac::rtc::vals::Tsedge;
+
+/// Edge of the timestamp pin triggering a timestamp event
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TimestampEdge {
+    /// Rising edge
+    Rising,
+    /// Falling edge
+    Falling,
+}
+
+/// Calendar time captured by a timestamp event. The year is not captured by the hardware.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RtcTimestamp {
+    /// 1..12, 1 is January
+    pub month: u8,
+    /// 1..28,29,30,31 depending on month
+    pub day: u8,
+    ///
+    pub day_of_week: DayOfWeek,
+    /// 0..23
+    pub hour: u8,
+    /// 0..59
+    pub minute: u8,
+    /// 0..59
+    pub second: u8,
+    /// Fraction of the second
+    #[cfg(not(rtc_v2f2))]
+    pub subsecond: Duration,
+}
+
+impl<'d, T: Instance> Rtc<'d, T> {
+    /// Capture the calendar time on every `edge` of the timestamp pin.
+    pub fn enable_timestamp(&mut self, edge: TimestampEdge) {
+        self.write(false, |rtc| unsafe {
+            // the edge must only be changed while timestamps are disabled
+            rtc.cr().modify(|w| w.set_tse(false));
+            rtc.cr().modify(|w| {
+                w.set_tsedge(match edge {
+                    TimestampEdge::Rising => Tsedge::RISINGEDGE,
+                    TimestampEdge::Falling => Tsedge::FALLINGEDGE,
+                })
+            });
+            rtc.cr().modify(|w| w.set_tse(true));
+        })
+    }
+
+    /// Stop capturing timestamps.
+    pub fn disable_timestamp(&mut self) {
+        self.write(false, |rtc| unsafe { rtc.cr().modify(|w| w.set_tse(false)) })
+    }
+
+    /// Return the pending timestamp, if a timestamp event occurred since the last call.
+    ///
+    /// Only one timestamp is kept, later events are lost until it is taken.
+    ///
+    /// # Errors
+    ///
+    /// Will return an `RtcError::InvalidDateTime` if the captured value is not a valid [`DayOfWeek`].
+    pub fn take_timestamp(&mut self) -> Result<Option<RtcTimestamp>, RtcError> {
+        if !super::_version::timestamp_pending() {
+            return Ok(None);
+        }
+
+        let r = T::regs();
+        let (tr, dr) = unsafe { (r.tstr().read(), r.tsdr().read()) };
+        #[cfg(not(rtc_v2f2))]
+        let ss = unsafe { r.tsssr().read().ss() } as u64;
+
+        self.clear_timestamp();
+
+        Ok(Some(RtcTimestamp {
+            month: bcd2_to_byte((dr.mt() as u8, dr.mu())),
+            day: bcd2_to_byte((dr.dt(), dr.du())),
+            day_of_week: day_of_week_from_u8(dr.wdu()).map_err(RtcError::InvalidDateTime)?,
+            hour: bcd2_to_byte((tr.ht(), tr.hu())),
+            minute: bcd2_to_byte((tr.mnt(), tr.mnu())),
+            second: bcd2_to_byte((tr.st(), tr.su())),
+            #[cfg(not(rtc_v2f2))]
+            subsecond: self.subsecond(ss),
+        }))
+    }
+}
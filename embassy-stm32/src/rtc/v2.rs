@@ -1,6 +1,7 @@
-use stm32_metapac::rtc::vals::{Init, Osel, Pol};
+use stm32_metapac::rtc::vals::{Init, Osel, Pol, Wucksel};
 
-use super::{sealed, Instance, RtcConfig};
+use super::alarm::ALARM_COUNT;
+use super::{sealed, Alarm, AlarmConfig, Instance, RtcConfig};
 use crate::pac::rtc::Rtc;
 
 impl<'d, T: Instance> super::Rtc<'d, T> {
@@ -161,6 +162,107 @@ impl<'d, T: Instance> super::Rtc<'d, T> {
         })
     }
 
+    /// Write the calibration register directly, bypassing the ppm conversion of `calibrate`.
+    ///
+    /// If `add_pulses` is set, 512 pulses are added (`CALP`) during each 32 second calibration
+    /// cycle. `masked` pulses (`CALM`, at most 511) are then masked out of the same cycle.
+    #[cfg(not(rtc_v2f2))]
+    pub fn calibrate_coarse(&mut self, add_pulses: bool, masked: u16) {
+        assert!(masked <= 511, "at most 511 pulses can be masked");
+
+        self.write(false, |rtc| unsafe {
+            rtc.calr().write(|w| {
+                w.set_calp(if add_pulses {
+                    stm32_metapac::rtc::vals::Calp::INCREASEFREQ
+                } else {
+                    stm32_metapac::rtc::vals::Calp::NOCHANGE
+                });
+                w.set_calm(masked);
+            });
+        })
+    }
+
+    /// Returns `true` if the calendar has been initialized.
+    ///
+    /// The calendar keeps running across resets as long as the backup domain stays powered, so this
+    /// can be used to tell if the time has to be set after a reset.
+    pub fn is_running(&self) -> bool {
+        unsafe { T::regs().isr().read().inits() }
+    }
+
+    /// Program and enable the alarm `alarm`, including its interrupt.
+    pub(super) fn set_alarm(&mut self, alarm: Alarm, config: &AlarmConfig) {
+        let alarm = alarm.index();
+        self.write(false, |rtc| unsafe {
+            rtc.cr().modify(|w| {
+                w.set_alre(alarm, false);
+                w.set_alrie(alarm, false);
+            });
+            while !rtc.isr().read().alrwf(alarm) {}
+
+            rtc.alrmr(alarm).write_value(config.alrmr());
+            #[cfg(not(rtc_v2f2))]
+            rtc.alrmssr(alarm).write_value(config.alrmssr());
+            rtc.isr().modify(|w| w.set_alrf(alarm, false));
+
+            rtc.cr().modify(|w| {
+                w.set_alre(alarm, true);
+                w.set_alrie(alarm, true);
+            });
+        })
+    }
+
+    /// Disable the alarm `alarm` and its interrupt.
+    pub fn disable_alarm(&mut self, alarm: Alarm) {
+        let alarm = alarm.index();
+        self.write(false, |rtc| unsafe {
+            rtc.cr().modify(|w| {
+                w.set_alre(alarm, false);
+                w.set_alrie(alarm, false);
+            });
+        })
+    }
+
+    /// Program and enable the wakeup timer, including its interrupt.
+    pub(super) fn set_wakeup(&mut self, wucksel: Wucksel, wut: u16) {
+        self.write(false, |rtc| unsafe {
+            rtc.cr().modify(|w| {
+                w.set_wute(false);
+                w.set_wutie(false);
+            });
+            while !rtc.isr().read().wutwf() {}
+
+            rtc.wutr().write(|w| w.set_wut(wut));
+            rtc.cr().modify(|w| w.set_wucksel(wucksel));
+            rtc.isr().modify(|w| w.set_wutf(false));
+
+            rtc.cr().modify(|w| {
+                w.set_wute(true);
+                w.set_wutie(true);
+            });
+        })
+    }
+
+    /// Disable the wakeup timer and its interrupt.
+    pub(super) fn disable_wakeup(&mut self) {
+        self.write(false, |rtc| unsafe {
+            rtc.cr().modify(|w| {
+                w.set_wute(false);
+                w.set_wutie(false);
+            });
+        })
+    }
+
+    /// Clear the timestamp flags after the timestamp was read.
+    pub(super) fn clear_timestamp(&mut self) {
+        self.write(false, |rtc| unsafe {
+            rtc.isr().modify(|w| {
+                w.set_tsf(false);
+                w.set_tsovf(false);
+            })
+        })
+    }
+
     pub(super) fn write<F, R>(&mut self, init_mode: bool, f: F) -> R
     where
         F: FnOnce(&crate::pac::rtc::Rtc) -> R,
@@ -197,6 +299,58 @@ impl<'d, T: Instance> super::Rtc<'d, T> {
     }
 }
 
+/// Clears the pending alarm flags and returns them, bit `n` is set if alarm `n` fired.
+pub(super) unsafe fn on_alarm_interrupt() -> u8 {
+    let r = crate::pac::RTC;
+    let isr = r.isr().read();
+    let cr = r.cr().read();
+
+    let mut fired = 0;
+    for alarm in 0..ALARM_COUNT {
+        if isr.alrf(alarm) && cr.alrie(alarm) {
+            r.isr().modify(|w| w.set_alrf(alarm, false));
+            fired |= 1 << alarm;
+        }
+    }
+    fired
+}
+
+/// Returns `true` if the calendar shadow registers hold the current calendar values.
+#[cfg(not(rtc_v2f2))]
+pub(super) fn shadow_registers_synced() -> bool {
+    unsafe { crate::pac::RTC.isr().read().rsf() }
+}
+
+/// Returns `true` if a timestamp event occurred.
+pub(super) fn timestamp_pending() -> bool {
+    unsafe { crate::pac::RTC.isr().read().tsf() }
+}
+
+/// Clears the pending wakeup timer flag, returns `true` if the wakeup timer fired.
+pub(super) unsafe fn on_wakeup_interrupt() -> bool {
+    let r = crate::pac::RTC;
+    if r.isr().read().wutf() && r.cr().read().wutie() {
+        r.isr().modify(|w| w.set_wutf(false));
+        true
+    } else {
+        false
+    }
+}
+
+/// EXTI line the alarm interrupt is routed through.
+#[cfg(rtc_v2l4)]
+pub(super) const EXTI_ALARM_LINE: Option<usize> = Some(18);
+#[cfg(not(rtc_v2l4))]
+pub(super) const EXTI_ALARM_LINE: Option<usize> = Some(17);
+
+/// EXTI line the wakeup timer interrupt is routed through.
+#[cfg(any(rtc_v2f2, rtc_v2f4, rtc_v2f7, rtc_v2l1))]
+pub(super) const EXTI_WAKEUP_LINE: Option<usize> = Some(22);
+#[cfg(any(rtc_v2h7, rtc_v2wb))]
+pub(super) const EXTI_WAKEUP_LINE: Option<usize> = Some(19);
+#[cfg(not(any(rtc_v2f2, rtc_v2f4, rtc_v2f7, rtc_v2l1, rtc_v2h7, rtc_v2wb)))]
+pub(super) const EXTI_WAKEUP_LINE: Option<usize> = Some(20);
+
 impl sealed::Instance for crate::peripherals::RTC {
     const BACKUP_REGISTER_COUNT: usize = 20;
 
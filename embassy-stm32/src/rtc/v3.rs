@@ -1,6 +1,9 @@
-use stm32_metapac::rtc::vals::{Calp, Calw16, Calw8, Fmt, Init, Key, Osel, Pol, TampalrmPu, TampalrmType};
+use stm32_metapac::rtc::vals::{
+    Alrf, Calp, Calrf, Calw16, Calw8, Fmt, Init, Key, Osel, Pol, TampalrmPu, TampalrmType, Tsf, Wucksel, Wutf,
+};
 
-use super::{sealed, Instance, RtcCalibrationCyclePeriod, RtcConfig};
+use super::alarm::ALARM_COUNT;
+use super::{sealed, Alarm, AlarmConfig, Instance, RtcCalibrationCyclePeriod, RtcConfig};
 use crate::pac::rtc::Rtc;
 
 impl<'d, T: Instance> super::Rtc<'d, T> {
@@ -148,6 +151,100 @@ impl<'d, T: Instance> super::Rtc<'d, T> {
         })
     }
 
+    /// Write the calibration register directly, bypassing the ppm conversion of `calibrate`.
+    ///
+    /// If `add_pulses` is set, 512 pulses are added (`CALP`) during each 32 second calibration
+    /// cycle. `masked` pulses (`CALM`, at most 511) are then masked out of the same cycle.
+    pub fn calibrate_coarse(&mut self, add_pulses: bool, masked: u16) {
+        assert!(masked <= 511, "at most 511 pulses can be masked");
+
+        self.write(false, |rtc| unsafe {
+            rtc.calr().write(|w| {
+                w.set_calp(if add_pulses { Calp::INCREASEFREQ } else { Calp::NOCHANGE });
+                w.set_calm(masked);
+            });
+        })
+    }
+
+    /// Returns `true` if the calendar has been initialized.
+    ///
+    /// The calendar keeps running across resets as long as the backup domain stays powered, so this
+    /// can be used to tell if the time has to be set after a reset.
+    pub fn is_running(&self) -> bool {
+        unsafe { T::regs().icsr().read().inits() }
+    }
+
+    /// Program and enable the alarm `alarm`, including its interrupt.
+    pub(super) fn set_alarm(&mut self, alarm: Alarm, config: &AlarmConfig) {
+        let alarm = alarm.index();
+        self.write(false, |rtc| unsafe {
+            rtc.cr().modify(|w| {
+                w.set_alre(alarm, false);
+                w.set_alraie(alarm, false);
+            });
+
+            rtc.alrmr(alarm).write_value(config.alrmr());
+            rtc.alrmssr(alarm).write_value(config.alrmssr());
+            rtc.scr().write(|w| w.set_calrf(alarm, Calrf::CLEAR));
+
+            rtc.cr().modify(|w| {
+                w.set_alre(alarm, true);
+                w.set_alraie(alarm, true);
+            });
+        })
+    }
+
+    /// Disable the alarm `alarm` and its interrupt.
+    pub fn disable_alarm(&mut self, alarm: Alarm) {
+        let alarm = alarm.index();
+        self.write(false, |rtc| unsafe {
+            rtc.cr().modify(|w| {
+                w.set_alre(alarm, false);
+                w.set_alraie(alarm, false);
+            });
+        })
+    }
+
+    /// Program and enable the wakeup timer, including its interrupt.
+    pub(super) fn set_wakeup(&mut self, wucksel: Wucksel, wut: u16) {
+        self.write(false, |rtc| unsafe {
+            rtc.cr().modify(|w| {
+                w.set_wute(false);
+                w.set_wutie(false);
+            });
+            while !rtc.icsr().read().wutwf() {}
+
+            rtc.wutr().write(|w| w.set_wut(wut));
+            rtc.cr().modify(|w| w.set_wucksel(wucksel));
+            rtc.scr().write(|w| w.set_cwutf(Calrf::CLEAR));
+
+            rtc.cr().modify(|w| {
+                w.set_wute(true);
+                w.set_wutie(true);
+            });
+        })
+    }
+
+    /// Disable the wakeup timer and its interrupt.
+    pub(super) fn disable_wakeup(&mut self) {
+        self.write(false, |rtc| unsafe {
+            rtc.cr().modify(|w| {
+                w.set_wute(false);
+                w.set_wutie(false);
+            });
+        })
+    }
+
+    /// Clear the timestamp flags after the timestamp was read.
+    pub(super) fn clear_timestamp(&mut self) {
+        self.write(false, |rtc| unsafe {
+            rtc.scr().write(|w| {
+                w.set_ctsf(Calrf::CLEAR);
+                w.set_ctsovf(Calrf::CLEAR);
+            })
+        })
+    }
+
     pub(super) fn write<F, R>(&mut self, init_mode: bool, f: F) -> R
     where
         F: FnOnce(&crate::pac::rtc::Rtc) -> R,
@@ -182,24 +279,79 @@ impl<'d, T: Instance> super::Rtc<'d, T> {
     }
 }
 
-impl sealed::Instance for crate::peripherals::RTC {
-    const BACKUP_REGISTER_COUNT: usize = 32;
-
-    fn read_backup_register(_rtc: &Rtc, register: usize) -> Option<u32> {
-        if register < Self::BACKUP_REGISTER_COUNT {
-            //Some(rtc.bkpr()[register].read().bits())
-            None // RTC3 backup registers come from the TAMP peripe=heral, not RTC. Not() even in the L412 PAC
-        } else {
-            None
+/// Clears the pending alarm flags and returns them, bit `n` is set if alarm `n` fired.
+pub(super) unsafe fn on_alarm_interrupt() -> u8 {
+    let r = crate::pac::RTC;
+    let sr = r.sr().read();
+    let cr = r.cr().read();
+
+    let mut fired = 0;
+    for alarm in 0..ALARM_COUNT {
+        if sr.alrf(alarm) == Alrf::MATCH && cr.alraie(alarm) {
+            r.scr().write(|w| w.set_calrf(alarm, Calrf::CLEAR));
+            fired |= 1 << alarm;
         }
     }
+    fired
+}
 
-    fn write_backup_register(_rtc: &Rtc, register: usize, _value: u32) {
-        if register < Self::BACKUP_REGISTER_COUNT {
-            // RTC3 backup registers come from the TAMP peripe=heral, not RTC. Not() even in the L412 PAC
-            //unsafe { self.rtc.bkpr()[register].write(|w| w.bits(value)) }
-        }
+/// EXTI line the alarm interrupt is routed through, if it is configurable.
+#[cfg(any(stm32g4, stm32l5, stm32wl))]
+pub(super) const EXTI_ALARM_LINE: Option<usize> = Some(17);
+#[cfg(stm32l4)]
+pub(super) const EXTI_ALARM_LINE: Option<usize> = Some(18);
+#[cfg(not(any(stm32g4, stm32l4, stm32l5, stm32wl)))]
+pub(super) const EXTI_ALARM_LINE: Option<usize> = None;
+
+/// Returns `true` if the calendar shadow registers hold the current calendar values.
+pub(super) fn shadow_registers_synced() -> bool {
+    unsafe { crate::pac::RTC.icsr().read().rsf() }
+}
+
+/// Returns `true` if a timestamp event occurred.
+pub(super) fn timestamp_pending() -> bool {
+    unsafe { crate::pac::RTC.sr().read().tsf() == Tsf::TIMESTAMPEVENT }
+}
+
+/// Clears the pending wakeup timer flag, returns `true` if the wakeup timer fired.
+pub(super) unsafe fn on_wakeup_interrupt() -> bool {
+    let r = crate::pac::RTC;
+    if r.sr().read().wutf() == Wutf::ZERO && r.cr().read().wutie() {
+        r.scr().write(|w| w.set_cwutf(Calrf::CLEAR));
+        true
+    } else {
+        false
     }
 }
 
+/// EXTI line the wakeup timer interrupt is routed through, if it is configurable.
+#[cfg(any(stm32g4, stm32l4, stm32wl))]
+pub(super) const EXTI_WAKEUP_LINE: Option<usize> = Some(20);
+#[cfg(stm32l5)]
+pub(super) const EXTI_WAKEUP_LINE: Option<usize> = Some(17);
+#[cfg(not(any(stm32g4, stm32l4, stm32l5, stm32wl)))]
+pub(super) const EXTI_WAKEUP_LINE: Option<usize> = None;
+
+impl sealed::Instance for crate::peripherals::RTC {
+    // The backup registers of RTC3 belong to the TAMP peripheral, which has no register block in
+    // stm32-metapac yet.
+    const BACKUP_REGISTER_COUNT: usize = 0;
+
+    unsafe fn enable_peripheral_clk() {
+        // enable peripheral clock for communication
+        #[cfg(rcc_g0)]
+        crate::pac::RCC.apbenr1().modify(|w| w.set_rtcapben(true));
+        #[cfg(any(rcc_g4, rcc_l4, rcc_wl5, rcc_wle))]
+        crate::pac::RCC.apb1enr1().modify(|w| w.set_rtcapben(true));
+        #[cfg(rtc_v3u5)]
+        crate::pac::RCC.apb3enr().modify(|w| w.set_rtcapben(true));
+    }
+
+    fn read_backup_register(_rtc: &Rtc, _register: usize) -> Option<u32> {
+        None
+    }
+
+    fn write_backup_register(_rtc: &Rtc, _register: usize, _value: u32) {}
+}
+
 impl Instance for crate::peripherals::RTC {}
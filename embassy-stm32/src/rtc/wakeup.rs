@@ -0,0 +1,129 @@
+use core::future::poll_fn;
+use core::task::Poll;
+use core::time::Duration;
+
+use atomic_polyfill::Ordering;
+
+use super::{Instance, InterruptHandler, Rtc, WAKEUP_FIRED, WAKEUP_WAKER};
+use crate::interrupt;
+use crate::pac::rtc::vals::Wucksel;
+
+/// Periodic wakeup timer of the RTC.
+///
+/// The wakeup timer keeps running in STOP and STANDBY modes and wakes up the microcontroller after every
+/// period. Bind the [`InterruptHandler`] to the wakeup interrupt of the chip (e.g. `RTC_WKUP` or `RTC`)
+/// to be able to wait for it.
+pub struct RtcWakeup<'r, 'd, T: Instance> {
+    rtc: &'r mut Rtc<'d, T>,
+}
+
+impl<'r, 'd, T: Instance> RtcWakeup<'r, 'd, T> {
+    /// Create the wakeup timer, which is started by [`RtcWakeup::enable_wakeup`].
+    pub fn new<I: interrupt::Interrupt>(
+        rtc: &'r mut Rtc<'d, T>,
+        _irq: impl interrupt::Binding<I, InterruptHandler> + 'r,
+    ) -> Self {
+        super::enable_interrupt::<I>(super::_version::EXTI_WAKEUP_LINE);
+
+        Self { rtc }
+    }
+
+    /// Start the wakeup timer, firing every `period`.
+    ///
+    /// Periods up to 32 s (with the prescalers set up for a 32.768 kHz RTC clock) are counted with a
+    /// resolution of at least 1/2048 s, longer periods up to 36 hours with a resolution of one second.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` can not be represented by the wakeup timer.
+    pub fn enable_wakeup(&mut self, period: Duration) {
+        let config = self.rtc.get_config();
+        let rtcclk = (config.async_prescaler as u32 + 1) * (config.sync_prescaler as u32 + 1);
+        let (wucksel, wut) = wakeup_config(period, rtcclk).expect("wakeup period out of range");
+
+        WAKEUP_FIRED.store(false, Ordering::SeqCst);
+        self.rtc.set_wakeup(wucksel, wut);
+    }
+
+    /// Stop the wakeup timer.
+    pub fn disable_wakeup(&mut self) {
+        self.rtc.disable_wakeup();
+    }
+
+    /// Wait until the wakeup timer fires.
+    ///
+    /// Returns immediately if it fired since the last call. Never returns if the wakeup timer is disabled.
+    pub async fn wait(&mut self) {
+        poll_fn(|cx| {
+            WAKEUP_WAKER.register(cx.waker());
+
+            if WAKEUP_FIRED.swap(false, Ordering::SeqCst) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+/// Wakeup clock selection and auto-reload value for a wakeup every `period`, given the RTC clock
+/// frequency `rtcclk`. The finest clock able to count `period` is used.
+fn wakeup_config(period: Duration, rtcclk: u32) -> Option<(Wucksel, u16)> {
+    const MAX_TICKS: u64 = 1 << 16;
+
+    if period > Duration::from_secs(2 * MAX_TICKS) {
+        return None;
+    }
+
+    let micros = period.as_micros() as u64;
+    for (wucksel, div) in [
+        (Wucksel::DIV2, 2),
+        (Wucksel::DIV4, 4),
+        (Wucksel::DIV8, 8),
+        (Wucksel::DIV16, 16),
+    ] {
+        let ticks = (micros * rtcclk as u64 + div * 500_000) / (div * 1_000_000);
+        if (1..=MAX_TICKS).contains(&ticks) {
+            return Some((wucksel, (ticks - 1) as u16));
+        }
+    }
+
+    // 1 Hz calendar clock, with 2^16 added to the counter for the longest periods
+    let secs = (micros + 500_000) / 1_000_000;
+    match secs {
+        0 => None,
+        1..=MAX_TICKS => Some((Wucksel::CLOCKSPARE, (secs - 1) as u16)),
+        _ if secs <= 2 * MAX_TICKS => Some((Wucksel::CLOCKSPAREWITHOFFSET, (secs - 1 - MAX_TICKS) as u16)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(period: Duration) -> Option<(u8, u16)> {
+        wakeup_config(period, 32_768).map(|(wucksel, wut)| (wucksel.0, wut))
+    }
+
+    #[test]
+    fn can_compute_wakeup_config() {
+        // 16384 Hz, 1 ms rounds to 16 ticks
+        assert_eq!(Some((Wucksel::DIV2.0, 15)), config(Duration::from_millis(1)));
+        assert_eq!(Some((Wucksel::DIV2.0, 16383)), config(Duration::from_secs(1)));
+        // 4096 Hz
+        assert_eq!(Some((Wucksel::DIV8.0, 40959)), config(Duration::from_secs(10)));
+        assert_eq!(Some((Wucksel::CLOCKSPARE.0, 59)), config(Duration::from_secs(60)));
+        assert_eq!(
+            Some((Wucksel::CLOCKSPAREWITHOFFSET.0, 0)),
+            config(Duration::from_secs(65_537))
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_range_period() {
+        assert_eq!(None, config(Duration::from_micros(10)));
+        assert_eq!(None, config(Duration::from_secs(2 * 65_536 + 1)));
+    }
+}
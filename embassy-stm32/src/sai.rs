@@ -0,0 +1,762 @@
+//! Serial Audio Interface (SAI)
+//!
+//! The driver is available on the chips with a SAI register block in stm32-metapac: F446, F469, F479,
+//! F745 to F779, and L451, L452, L462, L471, L475, L476, L486.
+//!
+//! The SAI of the other chips isn't described by stm32-metapac yet, or uses the GPDMA, and isn't
+//! supported: F413, F423, F427, F429, F437, F439, F722, F723, F730, F732, F733, L431 to L443, L496,
+//! L4A6, L4+, G4, H5, H7, L5, U5 and WB.
+#![macro_use]
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::sync::atomic::{compiler_fence, Ordering};
+use core::task::{Context, Poll};
+
+use embassy_hal_common::{into_ref, PeripheralRef};
+use futures::Stream;
+
+use crate::dma::ringbuffer::OverrunError;
+use crate::dma::word::{Word, WordSize};
+use crate::dma::{Channel, RingBuffer};
+use crate::gpio::sealed::{AFType, Pin as _};
+use crate::gpio::AnyPin;
+use crate::pac::sai::{vals, Sai as Regs};
+use crate::rcc::RccPeripheral;
+use crate::{peripherals, Peripheral};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    NotATransmitter,
+    NotAReceiver,
+    /// Samples were lost: the receiver was not read in time, or the transmitter was not written
+    /// in time and repeated old samples.
+    Overrun,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Master,
+    Slave,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum TxRx {
+    Transmitter,
+    Receiver,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum StereoMono {
+    Stereo,
+    /// Each sample is sent in both slots of the frame, only valid with two slots.
+    Mono,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum DataSize {
+    Data8,
+    Data10,
+    Data16,
+    Data20,
+    Data24,
+    Data32,
+}
+
+impl DataSize {
+    fn ds(&self) -> vals::Ds {
+        match self {
+            DataSize::Data8 => vals::Ds::BIT8,
+            DataSize::Data10 => vals::Ds::BIT10,
+            DataSize::Data16 => vals::Ds::BIT16,
+            DataSize::Data20 => vals::Ds::BIT20,
+            DataSize::Data24 => vals::Ds::BIT24,
+            DataSize::Data32 => vals::Ds::BIT32,
+        }
+    }
+
+    fn word_size(&self) -> WordSize {
+        match self {
+            DataSize::Data8 => WordSize::OneByte,
+            DataSize::Data10 | DataSize::Data16 => WordSize::TwoBytes,
+            DataSize::Data20 | DataSize::Data24 | DataSize::Data32 => WordSize::FourBytes,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum SlotSize {
+    /// Same as the data size
+    DataSize,
+    Channel16,
+    Channel32,
+}
+
+impl SlotSize {
+    fn bits(&self, data_size: DataSize) -> u16 {
+        match self {
+            SlotSize::DataSize => match data_size {
+                DataSize::Data8 => 8,
+                DataSize::Data10 => 10,
+                DataSize::Data16 => 16,
+                DataSize::Data20 => 20,
+                DataSize::Data24 => 24,
+                DataSize::Data32 => 32,
+            },
+            SlotSize::Channel16 => 16,
+            SlotSize::Channel32 => 32,
+        }
+    }
+
+    fn slotsz(&self) -> vals::Slotsz {
+        match self {
+            SlotSize::DataSize => vals::Slotsz::DATASIZE,
+            SlotSize::Channel16 => vals::Slotsz::BIT16,
+            SlotSize::Channel32 => vals::Slotsz::BIT32,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum BitOrder {
+    LsbFirst,
+    MsbFirst,
+}
+
+/// Clock edge on which the received signals are sampled. The generated signals change on the
+/// other edge.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum ClockStrobe {
+    Falling,
+    Rising,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum FrameSyncPolarity {
+    ActiveLow,
+    ActiveHigh,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum FrameSyncOffset {
+    /// FS is asserted on the first bit of the first slot
+    OnFirstBit,
+    /// FS is asserted one bit before the first bit of the first slot, as in I2S
+    BeforeFirstBit,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum FrameSyncDefinition {
+    /// FS only signals the start of the frame
+    StartOfFrame,
+    /// FS also identifies the left and right channel, as in I2S
+    ChannelIdentification,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum FifoThreshold {
+    Empty,
+    Quarter,
+    Half,
+    ThreeQuarters,
+    Full,
+}
+
+impl FifoThreshold {
+    fn fth(&self) -> vals::Fth {
+        match self {
+            FifoThreshold::Empty => vals::Fth::EMPTY,
+            FifoThreshold::Quarter => vals::Fth::QUARTER1,
+            FifoThreshold::Half => vals::Fth::QUARTER2,
+            FifoThreshold::ThreeQuarters => vals::Fth::QUARTER3,
+            FifoThreshold::Full => vals::Fth::FULL,
+        }
+    }
+}
+
+/// [`Sai`] sub-block configuration.
+///
+/// The default is a master transmitter of stereo I2S frames, made of two 16 bit slots. The slots
+/// must fit in `frame_length`, which must be a power of two when the master clock is enabled.
+///
+/// The samples of the enabled slots are interleaved in the DMA buffer, in slot order: with four
+/// slots enabled, the buffer holds slot 0, 1, 2, 3 of the first frame, then of the second frame,
+/// and so on.
+#[non_exhaustive]
+#[derive(Copy, Clone)]
+pub struct Config {
+    pub mode: Mode,
+    pub tx_rx: TxRx,
+    pub stereo_mono: StereoMono,
+    pub data_size: DataSize,
+    pub bit_order: BitOrder,
+    pub clock_strobe: ClockStrobe,
+    pub slot_size: SlotSize,
+    /// Number of slots in a frame, 1 to 16
+    pub slot_count: u8,
+    /// Bit mask of the slots used, from bit 0 for the first slot
+    pub slot_enable: u16,
+    /// Offset of the data in the slot, in bits
+    pub first_bit_offset: u8,
+    /// Length of the frame, in clock cycles, 8 to 256
+    pub frame_length: u16,
+    /// Length of the active level of FS, in clock cycles
+    pub frame_sync_active_level_length: u8,
+    pub frame_sync_polarity: FrameSyncPolarity,
+    pub frame_sync_offset: FrameSyncOffset,
+    pub frame_sync_definition: FrameSyncDefinition,
+    /// Generate the master clock, at the frame rate times 256. If disabled, SCK is generated
+    /// directly from the divided kernel clock.
+    pub master_clock: bool,
+    /// Divide the kernel clock by twice this value, 0 meaning no division, up to 15.
+    pub master_clock_divider: u8,
+    pub fifo_threshold: FifoThreshold,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Master,
+            tx_rx: TxRx::Transmitter,
+            stereo_mono: StereoMono::Stereo,
+            data_size: DataSize::Data16,
+            bit_order: BitOrder::MsbFirst,
+            clock_strobe: ClockStrobe::Rising,
+            slot_size: SlotSize::DataSize,
+            slot_count: 2,
+            slot_enable: 0b11,
+            first_bit_offset: 0,
+            frame_length: 32,
+            frame_sync_active_level_length: 16,
+            frame_sync_polarity: FrameSyncPolarity::ActiveLow,
+            frame_sync_offset: FrameSyncOffset::BeforeFirstBit,
+            frame_sync_definition: FrameSyncDefinition::ChannelIdentification,
+            master_clock: true,
+            master_clock_divider: 0,
+            fifo_threshold: FifoThreshold::Half,
+        }
+    }
+}
+
+impl Config {
+    /// TDM frames of `slot_count` slots, all enabled, as used by multichannel codecs. FS is a
+    /// one clock pulse, active high, before the first bit of the first slot (DSP mode A).
+    pub fn tdm(slot_count: u8, data_size: DataSize, slot_size: SlotSize) -> Self {
+        assert!((1..=16).contains(&slot_count));
+        let frame_length = slot_count as u16 * slot_size.bits(data_size);
+        assert!((8..=256).contains(&frame_length));
+
+        Self {
+            data_size,
+            slot_size,
+            slot_count,
+            slot_enable: ((1u32 << slot_count) - 1) as u16,
+            frame_length,
+            frame_sync_active_level_length: 1,
+            frame_sync_polarity: FrameSyncPolarity::ActiveHigh,
+            frame_sync_definition: FrameSyncDefinition::StartOfFrame,
+            ..Default::default()
+        }
+    }
+}
+
+/// Marker for sub-block A.
+pub struct A;
+/// Marker for sub-block B.
+pub struct B;
+
+/// One of the two sub-blocks of a SAI, see [`split_subblocks`].
+pub struct SubBlock<'d, T: Instance, S: SubBlockInstance> {
+    _peri: PhantomData<(&'d mut T, S)>,
+}
+
+/// Split the SAI into its two sub-blocks, which are independent audio interfaces that can also
+/// share their clocks, see [`Sai::new_synchronous_a`].
+pub fn split_subblocks<'d, T: Instance>(
+    _peri: impl Peripheral<P = T> + 'd,
+) -> (SubBlock<'d, T, A>, SubBlock<'d, T, B>) {
+    T::enable();
+    T::reset();
+
+    (SubBlock { _peri: PhantomData }, SubBlock { _peri: PhantomData })
+}
+
+/// A SAI sub-block, transferring samples through a DMA ring buffer.
+pub struct Sai<'d, T: Instance, C: Channel, W: Word> {
+    _peri: PhantomData<&'d mut T>,
+    sd: PeripheralRef<'d, AnyPin>,
+    fs: Option<PeripheralRef<'d, AnyPin>>,
+    sck: Option<PeripheralRef<'d, AnyPin>>,
+    mclk: Option<PeripheralRef<'d, AnyPin>>,
+    ring_buf: RingBuffer<'d, C, W>,
+    sub_block: usize,
+    tx_rx: TxRx,
+}
+
+impl<'d, T: Instance, C: Channel, W: Word> Sai<'d, T, C, W> {
+    pub fn new_a(
+        _peri: SubBlock<'d, T, A>,
+        sck: impl Peripheral<P = impl SckAPin<T>> + 'd,
+        sd: impl Peripheral<P = impl SdAPin<T>> + 'd,
+        fs: impl Peripheral<P = impl FsAPin<T>> + 'd,
+        mclk: impl Peripheral<P = impl MclkAPin<T>> + 'd,
+        dma: impl Peripheral<P = C> + 'd,
+        dma_buf: &'d mut [W],
+        config: Config,
+    ) -> Self
+    where
+        C: DmaA<T>,
+    {
+        into_ref!(sck, sd, fs, mclk, dma);
+
+        let request = dma.request();
+        Self::new_inner(
+            0,
+            Some((sck.af_num(), sck.map_into())),
+            (sd.af_num(), sd.map_into()),
+            Some((fs.af_num(), fs.map_into())),
+            Some((mclk.af_num(), mclk.map_into())),
+            dma,
+            request,
+            dma_buf,
+            config,
+        )
+    }
+
+    pub fn new_b(
+        _peri: SubBlock<'d, T, B>,
+        sck: impl Peripheral<P = impl SckBPin<T>> + 'd,
+        sd: impl Peripheral<P = impl SdBPin<T>> + 'd,
+        fs: impl Peripheral<P = impl FsBPin<T>> + 'd,
+        mclk: impl Peripheral<P = impl MclkBPin<T>> + 'd,
+        dma: impl Peripheral<P = C> + 'd,
+        dma_buf: &'d mut [W],
+        config: Config,
+    ) -> Self
+    where
+        C: DmaB<T>,
+    {
+        into_ref!(sck, sd, fs, mclk, dma);
+
+        let request = dma.request();
+        Self::new_inner(
+            1,
+            Some((sck.af_num(), sck.map_into())),
+            (sd.af_num(), sd.map_into()),
+            Some((fs.af_num(), fs.map_into())),
+            Some((mclk.af_num(), mclk.map_into())),
+            dma,
+            request,
+            dma_buf,
+            config,
+        )
+    }
+
+    /// Create sub-block A running on the SCK and FS of sub-block B, e.g. to receive and transmit
+    /// in the same frames. It must be a slave, and be started before sub-block B.
+    pub fn new_synchronous_a(
+        _peri: SubBlock<'d, T, A>,
+        sd: impl Peripheral<P = impl SdAPin<T>> + 'd,
+        dma: impl Peripheral<P = C> + 'd,
+        dma_buf: &'d mut [W],
+        config: Config,
+    ) -> Self
+    where
+        C: DmaA<T>,
+    {
+        into_ref!(sd, dma);
+
+        let request = dma.request();
+        Self::new_inner(
+            0,
+            None,
+            (sd.af_num(), sd.map_into()),
+            None,
+            None,
+            dma,
+            request,
+            dma_buf,
+            config,
+        )
+    }
+
+    /// Create sub-block B running on the SCK and FS of sub-block A, e.g. to receive and transmit
+    /// in the same frames. It must be a slave, and be started before sub-block A.
+    pub fn new_synchronous_b(
+        _peri: SubBlock<'d, T, B>,
+        sd: impl Peripheral<P = impl SdBPin<T>> + 'd,
+        dma: impl Peripheral<P = C> + 'd,
+        dma_buf: &'d mut [W],
+        config: Config,
+    ) -> Self
+    where
+        C: DmaB<T>,
+    {
+        into_ref!(sd, dma);
+
+        let request = dma.request();
+        Self::new_inner(
+            1,
+            None,
+            (sd.af_num(), sd.map_into()),
+            None,
+            None,
+            dma,
+            request,
+            dma_buf,
+            config,
+        )
+    }
+
+    /// The pins are given with their alternate function number.
+    fn new_inner(
+        sub_block: usize,
+        sck: Option<(u8, PeripheralRef<'d, AnyPin>)>,
+        (sd_af, sd): (u8, PeripheralRef<'d, AnyPin>),
+        fs: Option<(u8, PeripheralRef<'d, AnyPin>)>,
+        mclk: Option<(u8, PeripheralRef<'d, AnyPin>)>,
+        dma: PeripheralRef<'d, C>,
+        request: crate::dma::Request,
+        dma_buf: &'d mut [W],
+        config: Config,
+    ) -> Self {
+        assert!(W::size() == config.data_size.word_size());
+        assert!((1..=16).contains(&config.slot_count));
+        assert!((8..=256).contains(&config.frame_length));
+        assert!(config.frame_sync_active_level_length > 0);
+        assert!(config.frame_sync_active_level_length as u16 <= config.frame_length);
+        assert!(config.slot_count as u16 * config.slot_size.bits(config.data_size) <= config.frame_length);
+        assert!((config.slot_enable as u32) >> config.slot_count == 0);
+        assert!(config.master_clock_divider <= 15);
+
+        let synchronous = sck.is_none();
+        if synchronous {
+            assert!(config.mode == Mode::Slave);
+        }
+
+        let sd_af_type = match config.tx_rx {
+            TxRx::Transmitter => AFType::OutputPushPull,
+            TxRx::Receiver => AFType::Input,
+        };
+        let clock_af_type = match config.mode {
+            Mode::Master => AFType::OutputPushPull,
+            Mode::Slave => AFType::Input,
+        };
+
+        unsafe {
+            sd.set_as_af(sd_af, sd_af_type);
+            sd.set_speed(crate::gpio::Speed::VeryHigh);
+
+            if let Some((af, sck)) = &sck {
+                sck.set_as_af(*af, clock_af_type);
+                sck.set_speed(crate::gpio::Speed::VeryHigh);
+            }
+            if let Some((af, fs)) = &fs {
+                fs.set_as_af(*af, clock_af_type);
+                fs.set_speed(crate::gpio::Speed::VeryHigh);
+            }
+            if let Some((af, mclk)) = &mclk {
+                mclk.set_as_af(*af, AFType::OutputPushPull);
+                mclk.set_speed(crate::gpio::Speed::VeryHigh);
+            }
+        }
+
+        let ch = T::REGS.ch(sub_block);
+        unsafe {
+            ch.cr1().modify(|w| w.set_saien(vals::Saien::DISABLED));
+            while ch.cr1().read().saien() == vals::Saien::ENABLED {}
+
+            ch.cr2().write(|w| {
+                w.set_fth(config.fifo_threshold.fth());
+                w.set_fflush(vals::Fflush::FLUSH);
+            });
+
+            ch.frcr().write(|w| {
+                w.set_frl((config.frame_length - 1) as u8);
+                w.set_fsall(config.frame_sync_active_level_length - 1);
+                w.set_fsdef(config.frame_sync_definition == FrameSyncDefinition::ChannelIdentification);
+                w.set_fspol(match config.frame_sync_polarity {
+                    FrameSyncPolarity::ActiveLow => vals::Fspol::FALLINGEDGE,
+                    FrameSyncPolarity::ActiveHigh => vals::Fspol::RISINGEDGE,
+                });
+                w.set_fsoff(match config.frame_sync_offset {
+                    FrameSyncOffset::OnFirstBit => vals::Fsoff::ONFIRST,
+                    FrameSyncOffset::BeforeFirstBit => vals::Fsoff::BEFOREFIRST,
+                });
+            });
+
+            ch.slotr().write(|w| {
+                w.set_fboff(config.first_bit_offset);
+                w.set_slotsz(config.slot_size.slotsz());
+                w.set_nbslot(config.slot_count - 1);
+                w.set_sloten(vals::Sloten(config.slot_enable));
+            });
+
+            ch.cr1().write(|w| {
+                w.set_mode(match (config.mode, config.tx_rx) {
+                    (Mode::Master, TxRx::Transmitter) => vals::Mode::MASTERTX,
+                    (Mode::Master, TxRx::Receiver) => vals::Mode::MASTERRX,
+                    (Mode::Slave, TxRx::Transmitter) => vals::Mode::SLAVETX,
+                    (Mode::Slave, TxRx::Receiver) => vals::Mode::SLAVERX,
+                });
+                w.set_prtcfg(vals::Prtcfg::FREE);
+                w.set_ds(config.data_size.ds());
+                w.set_lsbfirst(match config.bit_order {
+                    BitOrder::LsbFirst => vals::Lsbfirst::LSBFIRST,
+                    BitOrder::MsbFirst => vals::Lsbfirst::MSBFIRST,
+                });
+                w.set_ckstr(match config.clock_strobe {
+                    ClockStrobe::Falling => vals::Ckstr::FALLINGEDGE,
+                    ClockStrobe::Rising => vals::Ckstr::RISINGEDGE,
+                });
+                w.set_syncen(match synchronous {
+                    true => vals::Syncen::INTERNAL,
+                    false => vals::Syncen::ASYNCHRONOUS,
+                });
+                w.set_mono(match config.stereo_mono {
+                    StereoMono::Stereo => vals::Mono::STEREO,
+                    StereoMono::Mono => vals::Mono::MONO,
+                });
+                w.set_outdriv(vals::Outdriv::IMMEDIATELY);
+                w.set_nodiv(match config.master_clock {
+                    true => vals::Nodiv::MASTERCLOCK,
+                    false => vals::Nodiv::NODIV,
+                });
+                w.set_mckdiv(config.master_clock_divider);
+                w.set_dmaen(vals::Dmaen::ENABLED);
+            });
+        }
+
+        let dr = ch.dr().ptr() as *mut W;
+        let ring_buf = match config.tx_rx {
+            TxRx::Transmitter => {
+                // Send silence until the first samples are written
+                dma_buf.fill(W::default());
+                unsafe { RingBuffer::new_write(dma, request, dma_buf, dr, Default::default()) }
+            }
+            TxRx::Receiver => unsafe { RingBuffer::new_read(dma, request, dr, dma_buf, Default::default()) },
+        };
+
+        Self {
+            _peri: PhantomData,
+            sd,
+            fs: fs.map(|(_, pin)| pin),
+            sck: sck.map(|(_, pin)| pin),
+            mclk: mclk.map(|(_, pin)| pin),
+            ring_buf,
+            sub_block,
+            tx_rx: config.tx_rx,
+        }
+    }
+
+    /// Start transferring samples. This is done by the first [`Sai::read`] or [`Sai::write`] too,
+    /// but synchronous sub-blocks must be started before the sub-block they follow.
+    pub fn start(&mut self) {
+        let ch = T::REGS.ch(self.sub_block);
+        if unsafe { ch.cr1().read() }.saien() == vals::Saien::ENABLED {
+            return;
+        }
+
+        self.ring_buf.clear();
+
+        compiler_fence(Ordering::SeqCst);
+
+        self.ring_buf.start();
+        unsafe {
+            ch.clrfr().write(|w| w.set_covrudr(true));
+            ch.cr1().modify(|w| w.set_saien(vals::Saien::ENABLED));
+        }
+    }
+
+    /// Mute the output: the transmitter keeps sending frames, with zeros in all slots, while the
+    /// written samples are consumed but not sent.
+    pub fn set_mute(&mut self, mute: bool) -> Result<(), Error> {
+        if self.tx_rx != TxRx::Transmitter {
+            return Err(Error::NotATransmitter);
+        }
+
+        let ch = T::REGS.ch(self.sub_block);
+        unsafe {
+            ch.cr2().modify(|w| {
+                w.set_muteval(vals::Muteval::SENDZERO);
+                w.set_mute(match mute {
+                    true => vals::Mute::ENABLED,
+                    false => vals::Mute::DISABLED,
+                });
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Write samples, waiting for the DMA controller to transfer enough of the ring buffer to
+    /// make room for them.
+    pub async fn write(&mut self, data: &[W]) -> Result<(), Error> {
+        if self.tx_rx != TxRx::Transmitter {
+            return Err(Error::NotATransmitter);
+        }
+        self.start();
+
+        let mut pos = 0;
+        poll_fn(|cx| {
+            self.ring_buf.set_waker(cx.waker());
+
+            compiler_fence(Ordering::SeqCst);
+
+            self.ring_buf.reload_position();
+            while pos < data.len() {
+                match self.ring_buf.write(&data[pos..]) {
+                    Ok(0) => return Poll::Pending,
+                    Ok(len) => pos += len,
+                    Err(OverrunError) => {
+                        self.ring_buf.clear();
+                        return Poll::Ready(Err(Error::Overrun));
+                    }
+                }
+            }
+
+            Poll::Ready(Ok(()))
+        })
+        .await
+    }
+
+    /// Read samples, waiting for the DMA controller to receive them.
+    pub async fn read(&mut self, data: &mut [W]) -> Result<(), Error> {
+        if self.tx_rx != TxRx::Receiver {
+            return Err(Error::NotAReceiver);
+        }
+        self.start();
+
+        let mut pos = 0;
+        poll_fn(|cx| {
+            self.ring_buf.set_waker(cx.waker());
+
+            compiler_fence(Ordering::SeqCst);
+
+            self.ring_buf.reload_position();
+            while pos < data.len() {
+                match self.ring_buf.read(&mut data[pos..]) {
+                    Ok(0) => return Poll::Pending,
+                    Ok(len) => pos += len,
+                    Err(OverrunError) => {
+                        self.ring_buf.clear();
+                        return Poll::Ready(Err(Error::Overrun));
+                    }
+                }
+            }
+
+            Poll::Ready(Ok(()))
+        })
+        .await
+    }
+
+    /// Get a stream of received blocks of `N` samples. The ring buffer must hold at least two
+    /// blocks, so one can be copied out while the DMA controller fills the other.
+    pub fn stream<const N: usize>(&mut self) -> Result<SaiStream<'_, 'd, T, C, W, N>, Error> {
+        if self.tx_rx != TxRx::Receiver {
+            return Err(Error::NotAReceiver);
+        }
+        assert!(N > 0 && self.ring_buf.capacity() >= 2 * N);
+        self.start();
+
+        Ok(SaiStream { sai: self })
+    }
+}
+
+impl<'d, T: Instance, C: Channel, W: Word> Drop for Sai<'d, T, C, W> {
+    fn drop(&mut self) {
+        let ch = T::REGS.ch(self.sub_block);
+        unsafe {
+            ch.cr1().modify(|w| {
+                w.set_saien(vals::Saien::DISABLED);
+                w.set_dmaen(vals::Dmaen::DISABLED);
+            });
+            self.sd.set_as_disconnected();
+            for pin in [&self.fs, &self.sck, &self.mclk].into_iter().flatten() {
+                pin.set_as_disconnected();
+            }
+        }
+    }
+}
+
+/// Stream of received blocks of `N` samples, see [`Sai::stream`].
+pub struct SaiStream<'s, 'd, T: Instance, C: Channel, W: Word, const N: usize> {
+    sai: &'s mut Sai<'d, T, C, W>,
+}
+
+impl<'s, 'd, T: Instance, C: Channel, W: Word, const N: usize> Unpin for SaiStream<'s, 'd, T, C, W, N> {}
+
+impl<'s, 'd, T: Instance, C: Channel, W: Word, const N: usize> Stream for SaiStream<'s, 'd, T, C, W, N> {
+    type Item = Result<[W; N], Error>;
+
+    /// Returns the next block, or `Error::Overrun` if samples were overwritten before they were
+    /// read. Reception goes on after an overrun, from the latest samples.
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let ring_buf = &mut self.sai.ring_buf;
+        ring_buf.set_waker(cx.waker());
+
+        compiler_fence(Ordering::SeqCst);
+
+        ring_buf.reload_position();
+        if ring_buf.len() < N {
+            return Poll::Pending;
+        }
+
+        let mut block = [W::default(); N];
+        let mut pos = 0;
+        while pos < N {
+            match ring_buf.read(&mut block[pos..]) {
+                Ok(len) => pos += len,
+                Err(OverrunError) => {
+                    ring_buf.clear();
+                    return Poll::Ready(Some(Err(Error::Overrun)));
+                }
+            }
+        }
+
+        Poll::Ready(Some(Ok(block)))
+    }
+}
+
+pub(crate) mod sealed {
+    use super::*;
+
+    pub trait Instance {
+        const REGS: Regs;
+    }
+
+    pub trait SubBlock {}
+}
+
+pub trait Instance: Peripheral<P = Self> + sealed::Instance + RccPeripheral {}
+
+pub trait SubBlockInstance: sealed::SubBlock {}
+
+impl sealed::SubBlock for A {}
+impl SubBlockInstance for A {}
+impl sealed::SubBlock for B {}
+impl SubBlockInstance for B {}
+
+pin_trait!(SckAPin, Instance);
+pin_trait!(FsAPin, Instance);
+pin_trait!(SdAPin, Instance);
+pin_trait!(MclkAPin, Instance);
+pin_trait!(SckBPin, Instance);
+pin_trait!(FsBPin, Instance);
+pin_trait!(SdBPin, Instance);
+pin_trait!(MclkBPin, Instance);
+
+dma_trait!(DmaA, Instance);
+dma_trait!(DmaB, Instance);
+
+foreach_peripheral!(
+    (sai, $inst:ident) => {
+        impl sealed::Instance for peripherals::$inst {
+            const REGS: Regs = crate::pac::$inst;
+        }
+
+        impl Instance for peripherals::$inst {}
+    };
+);
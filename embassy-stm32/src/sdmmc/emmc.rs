@@ -0,0 +1,382 @@
+//! eMMC devices.
+//!
+//! eMMCs are initialized with SEND_OP_COND (CMD1) instead of ACMD41, and get their RCA from the
+//! host. Their capabilities are in the 512 bytes Extended CSD register (EXT_CSD), whose fields
+//! are written with SWITCH (CMD6) to change the bus width and the timing.
+
+use core::future::poll_fn;
+use core::task::Poll;
+
+use embassy_hal_common::drop::OnDrop;
+use sdio_host::{BusWidth, CardStatus, CurrentState};
+
+use super::{
+    clk_div, Cmd, Error, Instance, InterruptHandler, PowerCtrl, Response, Sdmmc, SdmmcDma, Signalling, SD_INIT_FREQ,
+};
+use crate::time::Hertz;
+
+// EXT_CSD fields
+const EXT_CSD_BUS_WIDTH: u8 = 183;
+const EXT_CSD_HS_TIMING: u8 = 185;
+const EXT_CSD_REV: usize = 192;
+const EXT_CSD_DEVICE_TYPE: usize = 196;
+const EXT_CSD_SEC_COUNT: usize = 212;
+
+const DEVICE_TYPE_HS_52: u8 = 1 << 1;
+/// High speed DDR at 52 MHz, at 3.3 V or 1.8 V I/O.
+#[cfg(sdmmc_v2)]
+const DEVICE_TYPE_HS_DDR_52: u8 = 1 << 2;
+
+const HS_TIMING_HIGH_SPEED: u8 = 1;
+
+// OCR, in the R3 response to CMD1
+const OCR_READY: u32 = 1 << 31;
+const OCR_ACCESS_MODE_SECTOR: u32 = 1 << 30;
+/// 2.7 V to 3.6 V, and 1.7 V to 1.95 V.
+const OCR_VOLTAGE_WINDOW: u32 = 0x00FF_8080;
+
+/// SWITCH_ERROR flag of the card status.
+const STATUS_SWITCH_ERROR: u32 = 1 << 7;
+
+/// Relative Card Address given to the eMMC, as it is the only device on the bus.
+const EMMC_RCA: u32 = 1;
+
+/// Polls of the card status before giving up on an eMMC leaving the programming state.
+const BUSY_RETRIES: u32 = 0x00FF_FFFF;
+
+/// Clock limits of the eMMC timings
+const LEGACY_MAX_FREQ: u32 = 26_000_000;
+const HIGH_SPEED_MAX_FREQ: u32 = 52_000_000;
+
+/// Timing of the eMMC bus
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EmmcTiming {
+    /// Backwards compatible timing, up to 26 MHz
+    Legacy,
+    /// High speed, single data rate, up to 52 MHz
+    HighSpeed,
+    /// High speed, dual data rate, up to 52 MHz. Only with SDMMCv2, and a 4 or 8 bit bus.
+    HighSpeedDdr,
+}
+
+#[derive(Clone, Copy, Debug)]
+/// eMMC device
+pub struct Emmc {
+    /// Relative Card Address
+    pub rca: u32,
+    /// Operation Conditions Register, as returned by CMD1
+    pub ocr: u32,
+    /// Card ID. Its layout differs from the one of SD cards.
+    pub cid: u128,
+    /// Card Specific Data. Its layout differs from the one of SD cards.
+    pub csd: u128,
+    /// Extended CSD revision
+    pub ext_csd_rev: u8,
+    /// Timings supported by the device, DEVICE_TYPE field of the Extended CSD
+    pub device_type: u8,
+    /// Number of 512 bytes sectors
+    pub sector_count: u32,
+    /// Width of the bus in use
+    pub bus_width: BusWidth,
+    /// Timing of the bus in use
+    pub timing: EmmcTiming,
+}
+
+impl Emmc {
+    /// Size in bytes
+    pub fn size(&self) -> u64 {
+        u64::from(self.sector_count) * 512
+    }
+
+    /// Devices above 2GB are sector addressed, the others byte addressed.
+    pub(super) fn sector_addressed(&self) -> bool {
+        self.ocr & OCR_ACCESS_MODE_SECTOR != 0
+    }
+}
+
+impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
+    /// Initializes an eMMC (if present) and sets the bus at the
+    /// specified frequency.
+    ///
+    /// The bus is switched to 8 bits if the D4 to D7 pins were given, or 4 bits if the D1 to D3
+    /// pins were. Above 26 MHz, the timing is switched to high speed if the device supports it,
+    /// and on SDMMCv2 to high speed DDR with a 4 or 8 bit bus.
+    pub async fn init_emmc(&mut self, freq: Hertz) -> Result<(), Error> {
+        let regs = T::regs();
+        let ker_ck = T::kernel_clk();
+
+        self.card = None;
+        self.sdio_card = None;
+        self.emmc = None;
+        self.signalling = Signalling::default();
+
+        // NOTE(unsafe) We have exclusive access to the peripheral
+        unsafe {
+            // While the SD/SDIO card or eMMC is in identification mode,
+            // the SDMMC_CK frequency must be no more than 400 kHz.
+            let (_bypass, clkdiv, init_clock) = unwrap!(clk_div(ker_ck, SD_INIT_FREQ.0));
+            self.clock = init_clock;
+
+            // CPSMACT and DPSMACT must be 0 to set WIDBUS
+            Self::wait_idle();
+
+            regs.clkcr().modify(|w| {
+                w.set_widbus(0);
+                w.set_clkdiv(clkdiv);
+                #[cfg(sdmmc_v1)]
+                w.set_bypass(_bypass);
+                #[cfg(sdmmc_v2)]
+                {
+                    w.set_ddr(false);
+                    w.set_busspeed(false);
+                }
+            });
+
+            regs.power().modify(|w| w.set_pwrctrl(PowerCtrl::On as u8));
+        }
+        Self::cmd(Cmd::idle(), false)?;
+
+        let ocr = loop {
+            // The R3 response has no CRC
+            match Self::cmd(Cmd::send_op_cond(OCR_ACCESS_MODE_SECTOR | OCR_VOLTAGE_WINDOW), false) {
+                Ok(_) => (),
+                Err(Error::Crc) => (),
+                Err(err) => return Err(err),
+            }
+            let ocr = unsafe { regs.respr(0).read().cardstatus() };
+            if ocr & OCR_READY != 0 {
+                // Power up done
+                break ocr;
+            }
+        };
+
+        Self::cmd(Cmd::all_send_cid(), false)?; // CMD2
+        let cid = Self::long_response();
+
+        Self::cmd(Cmd::set_rel_addr(EMMC_RCA << 16), false)?; // CMD3
+
+        Self::cmd(Cmd::send_csd(EMMC_RCA << 16), false)?; // CMD9
+        let csd = Self::long_response();
+
+        Self::cmd(Cmd::sel_desel_card(EMMC_RCA << 16), false)?; // CMD7
+
+        let mut ext_csd = [0u32; 128];
+        self.read_ext_csd(&mut ext_csd).await?;
+        // NOTE(unsafe) The bytes are stored in the order they are received
+        let ext_csd = unsafe { &*(&ext_csd as *const [u32; 128] as *const [u8; 512]) };
+
+        let mut emmc = Emmc {
+            rca: EMMC_RCA,
+            ocr,
+            cid,
+            csd,
+            ext_csd_rev: ext_csd[EXT_CSD_REV],
+            device_type: ext_csd[EXT_CSD_DEVICE_TYPE],
+            sector_count: 0,
+            bus_width: BusWidth::One,
+            timing: EmmcTiming::Legacy,
+        };
+        emmc.sector_count = if emmc.sector_addressed() {
+            u32::from_le_bytes(unwrap!(ext_csd[EXT_CSD_SEC_COUNT..][..4].try_into()))
+        } else {
+            csd_sector_count(csd)
+        };
+
+        // Set bus width
+        let width = match (self.d3.is_some(), self.d7.is_some()) {
+            (_, true) => BusWidth::Eight,
+            (true, false) => BusWidth::Four,
+            (false, false) => BusWidth::One,
+        };
+        if width != BusWidth::One {
+            Self::switch(EXT_CSD_BUS_WIDTH, bus_width_value(width, false))?;
+            Self::set_widbus(width);
+        }
+        emmc.bus_width = width;
+
+        // Set timing. HS_TIMING must be set before switching to DDR.
+        let mut freq = freq.0;
+        if freq > LEGACY_MAX_FREQ && emmc.device_type & DEVICE_TYPE_HS_52 != 0 {
+            Self::switch(EXT_CSD_HS_TIMING, HS_TIMING_HIGH_SPEED)?;
+            emmc.timing = EmmcTiming::HighSpeed;
+            freq = freq.min(HIGH_SPEED_MAX_FREQ);
+
+            #[cfg(sdmmc_v2)]
+            if width != BusWidth::One && emmc.device_type & DEVICE_TYPE_HS_DDR_52 != 0 {
+                Self::switch(EXT_CSD_BUS_WIDTH, bus_width_value(width, true))?;
+                emmc.timing = EmmcTiming::HighSpeedDdr;
+
+                // DDR requires CLKDIV > 0
+                freq = freq.min(ker_ck.0 / 2);
+
+                // CPSMACT and DPSMACT must be 0 to set DDR
+                Self::wait_idle();
+                unsafe {
+                    regs.clkcr().modify(|w| {
+                        w.set_ddr(true);
+                        w.set_busspeed(true);
+                    })
+                };
+            }
+        } else {
+            freq = freq.min(LEGACY_MAX_FREQ);
+        }
+        self.clkcr_set_clkdiv(freq, width)?;
+
+        if CardStatus::from(Self::wait_ready(EMMC_RCA)?).state() != CurrentState::Transfer {
+            return Err(Error::SignalingSwitchFailed);
+        }
+
+        self.emmc = Some(emmc);
+        Ok(())
+    }
+
+    /// Get a reference to the initialized eMMC
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::NoCard if [`init_emmc`](#method.init_emmc)
+    /// has not previously succeeded
+    #[inline(always)]
+    pub fn emmc(&self) -> Result<&Emmc, Error> {
+        self.emmc.as_ref().ok_or(Error::NoCard)
+    }
+
+    /// Reads the Extended CSD (CMD8)
+    async fn read_ext_csd(&mut self, ext_csd: &mut [u32; 128]) -> Result<(), Error> {
+        // Arm `OnDrop` after the buffer, so it will be dropped first
+        let regs = T::regs();
+        let on_drop = OnDrop::new(|| unsafe { Self::on_drop() });
+
+        let _transfer = self.prepare_datapath_read(ext_csd, 512, 9);
+        InterruptHandler::<T>::data_interrupts(true);
+        Self::cmd(Cmd::hs_send_ext_csd(0), true)?; // CMD8
+
+        let res = poll_fn(|cx| {
+            T::state().register(cx.waker());
+            let status = unsafe { regs.star().read() };
+
+            if status.dcrcfail() {
+                return Poll::Ready(Err(Error::Crc));
+            } else if status.dtimeout() {
+                return Poll::Ready(Err(Error::Timeout));
+            } else if status.dataend() {
+                return Poll::Ready(Ok(()));
+            }
+            Poll::Pending
+        })
+        .await;
+        Self::clear_interrupt_flags();
+
+        if res.is_ok() {
+            on_drop.defuse();
+            Self::stop_datapath();
+        }
+        res
+    }
+
+    /// Writes `value` to the byte `index` of the Extended CSD (CMD6), and waits for the eMMC to
+    /// apply it.
+    fn switch(index: u8, value: u8) -> Result<(), Error> {
+        Self::cmd(Cmd::switch(index, value), false)?;
+
+        if Self::wait_ready(EMMC_RCA)? & STATUS_SWITCH_ERROR != 0 {
+            return Err(Error::SignalingSwitchFailed);
+        }
+        Ok(())
+    }
+
+    /// Polls the card status (CMD13) until the eMMC is ready for data, and returns it.
+    ///
+    /// This is how the end of the busy signal of R1b responses and writes is detected.
+    pub(super) fn wait_ready(rca: u32) -> Result<u32, Error> {
+        let regs = T::regs();
+
+        for _ in 0..BUSY_RETRIES {
+            Self::cmd(Cmd::card_status(rca << 16), false)?; // CMD13
+
+            // NOTE(unsafe) Atomic read with no side-effects
+            let r1 = unsafe { regs.respr(0).read().cardstatus() };
+            let status = CardStatus::from(r1);
+            if status.ready_for_data() && status.state() != CurrentState::Programming {
+                return Ok(r1);
+            }
+        }
+        Err(Error::SoftwareTimeout)
+    }
+
+    /// Sets the WIDBUS field in CLKCR
+    fn set_widbus(width: BusWidth) {
+        // CPSMACT and DPSMACT must be 0 to set WIDBUS
+        Self::wait_idle();
+
+        // NOTE(unsafe) We have exclusive access to the regblock
+        unsafe {
+            T::regs().clkcr().modify(|w| {
+                w.set_widbus(match width {
+                    BusWidth::One => 0,
+                    BusWidth::Four => 1,
+                    BusWidth::Eight => 2,
+                    _ => panic!("Invalid Bus Width"),
+                })
+            })
+        };
+    }
+
+    /// Returns the long response of the last command
+    fn long_response() -> u128 {
+        let regs = T::regs();
+
+        // NOTE(unsafe) Atomic reads with no side-effects
+        unsafe {
+            let r0 = regs.respr(0).read().cardstatus() as u128;
+            let r1 = regs.respr(1).read().cardstatus() as u128;
+            let r2 = regs.respr(2).read().cardstatus() as u128;
+            let r3 = regs.respr(3).read().cardstatus() as u128;
+            (r0 << 96) | (r1 << 64) | (r2 << 32) | r3
+        }
+    }
+}
+
+/// BUS_WIDTH field of the Extended CSD
+fn bus_width_value(width: BusWidth, ddr: bool) -> u8 {
+    match (width, ddr) {
+        (BusWidth::Four, false) => 1,
+        (BusWidth::Eight, false) => 2,
+        (BusWidth::Four, true) => 5,
+        (BusWidth::Eight, true) => 6,
+        _ => 0,
+    }
+}
+
+/// Number of 512 bytes sectors of byte addressed devices, from C_SIZE, C_SIZE_MULT and
+/// READ_BL_LEN of the CSD.
+fn csd_sector_count(csd: u128) -> u32 {
+    let c_size = ((csd >> 62) & 0xFFF) as u32;
+    let c_size_mult = ((csd >> 47) & 0x7) as u32;
+    let read_bl_len = ((csd >> 80) & 0xF) as u32;
+
+    let bytes = ((c_size as u64 + 1) << (c_size_mult + 2)) << read_bl_len;
+    (bytes / 512) as u32
+}
+
+/// eMMC Commands
+impl Cmd {
+    /// CMD1: SEND_OP_COND, R3 response
+    const fn send_op_cond(ocr: u32) -> Cmd {
+        Cmd::new(1, ocr, Response::Short)
+    }
+
+    /// CMD3: SET_RELATIVE_ADDR, the host gives the RCA
+    const fn set_rel_addr(rca: u32) -> Cmd {
+        Cmd::new(3, rca, Response::Short)
+    }
+
+    /// CMD6: SWITCH, writing `value` to the byte `index` of the Extended CSD
+    const fn switch(index: u8, value: u8) -> Cmd {
+        // Access mode 0b11: write byte
+        let arg = 0b11 << 24 | (index as u32) << 16 | (value as u32) << 8;
+        Cmd::new(6, arg, Response::Short)
+    }
+}
@@ -20,6 +20,11 @@ use crate::rcc::RccPeripheral;
 use crate::time::Hertz;
 use crate::{interrupt, peripherals, Peripheral};
 
+mod emmc;
+mod sdio;
+pub use emmc::{Emmc, EmmcTiming};
+pub use sdio::SdioCard;
+
 /// Interrupt handler.
 pub struct InterruptHandler<T: Instance> {
     _phantom: PhantomData<T>,
@@ -105,6 +110,9 @@ pub enum Error {
     BadClock,
     SignalingSwitchFailed,
     PeripheralBusy,
+    SdioError,
+    /// An SDIO transfer was requested with an empty buffer.
+    EmptyBuffer,
 }
 
 /// A SD command
@@ -228,9 +236,14 @@ const DMA_TRANSFER_OPTIONS: crate::dma::TransferOptions = crate::dma::TransferOp
     mburst: crate::dma::Burst::Incr4,
     flow_ctrl: crate::dma::FlowControl::Peripheral,
     fifo_threshold: Some(crate::dma::FifoThreshold::Full),
+    circular: false,
+    half_transfer_ir: false,
 };
 #[cfg(all(sdmmc_v1, not(dma)))]
-const DMA_TRANSFER_OPTIONS: crate::dma::TransferOptions = crate::dma::TransferOptions {};
+const DMA_TRANSFER_OPTIONS: crate::dma::TransferOptions = crate::dma::TransferOptions {
+    circular: false,
+    half_transfer_ir: false,
+};
 
 /// SDMMC configuration
 ///
@@ -262,6 +275,10 @@ pub struct Sdmmc<'d, T: Instance, Dma: SdmmcDma<T> = NoDma> {
     d1: Option<PeripheralRef<'d, AnyPin>>,
     d2: Option<PeripheralRef<'d, AnyPin>>,
     d3: Option<PeripheralRef<'d, AnyPin>>,
+    d4: Option<PeripheralRef<'d, AnyPin>>,
+    d5: Option<PeripheralRef<'d, AnyPin>>,
+    d6: Option<PeripheralRef<'d, AnyPin>>,
+    d7: Option<PeripheralRef<'d, AnyPin>>,
 
     config: Config,
     /// Current clock to card
@@ -270,6 +287,10 @@ pub struct Sdmmc<'d, T: Instance, Dma: SdmmcDma<T> = NoDma> {
     signalling: Signalling,
     /// Card
     card: Option<Card>,
+    /// SDIO card
+    sdio_card: Option<SdioCard>,
+    /// eMMC device
+    emmc: Option<Emmc>,
 }
 
 #[cfg(sdmmc_v1)]
@@ -304,6 +325,10 @@ impl<'d, T: Instance, Dma: SdmmcDma<T>> Sdmmc<'d, T, Dma> {
             None,
             None,
             None,
+            None,
+            None,
+            None,
+            None,
             config,
         )
     }
@@ -347,6 +372,69 @@ impl<'d, T: Instance, Dma: SdmmcDma<T>> Sdmmc<'d, T, Dma> {
             Some(d1.map_into()),
             Some(d2.map_into()),
             Some(d3.map_into()),
+            None,
+            None,
+            None,
+            None,
+            config,
+        )
+    }
+
+    pub fn new_8bit(
+        sdmmc: impl Peripheral<P = T> + 'd,
+        _irq: impl interrupt::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        dma: impl Peripheral<P = Dma> + 'd,
+        clk: impl Peripheral<P = impl CkPin<T>> + 'd,
+        cmd: impl Peripheral<P = impl CmdPin<T>> + 'd,
+        d0: impl Peripheral<P = impl D0Pin<T>> + 'd,
+        d1: impl Peripheral<P = impl D1Pin<T>> + 'd,
+        d2: impl Peripheral<P = impl D2Pin<T>> + 'd,
+        d3: impl Peripheral<P = impl D3Pin<T>> + 'd,
+        d4: impl Peripheral<P = impl D4Pin<T>> + 'd,
+        d5: impl Peripheral<P = impl D5Pin<T>> + 'd,
+        d6: impl Peripheral<P = impl D6Pin<T>> + 'd,
+        d7: impl Peripheral<P = impl D7Pin<T>> + 'd,
+        config: Config,
+    ) -> Self {
+        into_ref!(clk, cmd, d0, d1, d2, d3, d4, d5, d6, d7);
+
+        critical_section::with(|_| unsafe {
+            clk.set_as_af_pull(clk.af_num(), AFType::OutputPushPull, Pull::None);
+            cmd.set_as_af_pull(cmd.af_num(), AFType::OutputPushPull, Pull::Up);
+            d0.set_as_af_pull(d0.af_num(), AFType::OutputPushPull, Pull::Up);
+            d1.set_as_af_pull(d1.af_num(), AFType::OutputPushPull, Pull::Up);
+            d2.set_as_af_pull(d2.af_num(), AFType::OutputPushPull, Pull::Up);
+            d3.set_as_af_pull(d3.af_num(), AFType::OutputPushPull, Pull::Up);
+            d4.set_as_af_pull(d4.af_num(), AFType::OutputPushPull, Pull::Up);
+            d5.set_as_af_pull(d5.af_num(), AFType::OutputPushPull, Pull::Up);
+            d6.set_as_af_pull(d6.af_num(), AFType::OutputPushPull, Pull::Up);
+            d7.set_as_af_pull(d7.af_num(), AFType::OutputPushPull, Pull::Up);
+
+            clk.set_speed(Speed::VeryHigh);
+            cmd.set_speed(Speed::VeryHigh);
+            d0.set_speed(Speed::VeryHigh);
+            d1.set_speed(Speed::VeryHigh);
+            d2.set_speed(Speed::VeryHigh);
+            d3.set_speed(Speed::VeryHigh);
+            d4.set_speed(Speed::VeryHigh);
+            d5.set_speed(Speed::VeryHigh);
+            d6.set_speed(Speed::VeryHigh);
+            d7.set_speed(Speed::VeryHigh);
+        });
+
+        Self::new_inner(
+            sdmmc,
+            dma,
+            clk.map_into(),
+            cmd.map_into(),
+            d0.map_into(),
+            Some(d1.map_into()),
+            Some(d2.map_into()),
+            Some(d3.map_into()),
+            Some(d4.map_into()),
+            Some(d5.map_into()),
+            Some(d6.map_into()),
+            Some(d7.map_into()),
             config,
         )
     }
@@ -383,6 +471,10 @@ impl<'d, T: Instance> Sdmmc<'d, T, NoDma> {
             None,
             None,
             None,
+            None,
+            None,
+            None,
+            None,
             config,
         )
     }
@@ -425,6 +517,68 @@ impl<'d, T: Instance> Sdmmc<'d, T, NoDma> {
             Some(d1.map_into()),
             Some(d2.map_into()),
             Some(d3.map_into()),
+            None,
+            None,
+            None,
+            None,
+            config,
+        )
+    }
+
+    pub fn new_8bit(
+        sdmmc: impl Peripheral<P = T> + 'd,
+        _irq: impl interrupt::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        clk: impl Peripheral<P = impl CkPin<T>> + 'd,
+        cmd: impl Peripheral<P = impl CmdPin<T>> + 'd,
+        d0: impl Peripheral<P = impl D0Pin<T>> + 'd,
+        d1: impl Peripheral<P = impl D1Pin<T>> + 'd,
+        d2: impl Peripheral<P = impl D2Pin<T>> + 'd,
+        d3: impl Peripheral<P = impl D3Pin<T>> + 'd,
+        d4: impl Peripheral<P = impl D4Pin<T>> + 'd,
+        d5: impl Peripheral<P = impl D5Pin<T>> + 'd,
+        d6: impl Peripheral<P = impl D6Pin<T>> + 'd,
+        d7: impl Peripheral<P = impl D7Pin<T>> + 'd,
+        config: Config,
+    ) -> Self {
+        into_ref!(clk, cmd, d0, d1, d2, d3, d4, d5, d6, d7);
+
+        critical_section::with(|_| unsafe {
+            clk.set_as_af_pull(clk.af_num(), AFType::OutputPushPull, Pull::None);
+            cmd.set_as_af_pull(cmd.af_num(), AFType::OutputPushPull, Pull::Up);
+            d0.set_as_af_pull(d0.af_num(), AFType::OutputPushPull, Pull::Up);
+            d1.set_as_af_pull(d1.af_num(), AFType::OutputPushPull, Pull::Up);
+            d2.set_as_af_pull(d2.af_num(), AFType::OutputPushPull, Pull::Up);
+            d3.set_as_af_pull(d3.af_num(), AFType::OutputPushPull, Pull::Up);
+            d4.set_as_af_pull(d4.af_num(), AFType::OutputPushPull, Pull::Up);
+            d5.set_as_af_pull(d5.af_num(), AFType::OutputPushPull, Pull::Up);
+            d6.set_as_af_pull(d6.af_num(), AFType::OutputPushPull, Pull::Up);
+            d7.set_as_af_pull(d7.af_num(), AFType::OutputPushPull, Pull::Up);
+
+            clk.set_speed(Speed::VeryHigh);
+            cmd.set_speed(Speed::VeryHigh);
+            d0.set_speed(Speed::VeryHigh);
+            d1.set_speed(Speed::VeryHigh);
+            d2.set_speed(Speed::VeryHigh);
+            d3.set_speed(Speed::VeryHigh);
+            d4.set_speed(Speed::VeryHigh);
+            d5.set_speed(Speed::VeryHigh);
+            d6.set_speed(Speed::VeryHigh);
+            d7.set_speed(Speed::VeryHigh);
+        });
+
+        Self::new_inner(
+            sdmmc,
+            NoDma.into_ref(),
+            clk.map_into(),
+            cmd.map_into(),
+            d0.map_into(),
+            Some(d1.map_into()),
+            Some(d2.map_into()),
+            Some(d3.map_into()),
+            Some(d4.map_into()),
+            Some(d5.map_into()),
+            Some(d6.map_into()),
+            Some(d7.map_into()),
             config,
         )
     }
@@ -440,6 +594,10 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
         d1: Option<PeripheralRef<'d, AnyPin>>,
         d2: Option<PeripheralRef<'d, AnyPin>>,
         d3: Option<PeripheralRef<'d, AnyPin>>,
+        d4: Option<PeripheralRef<'d, AnyPin>>,
+        d5: Option<PeripheralRef<'d, AnyPin>>,
+        d6: Option<PeripheralRef<'d, AnyPin>>,
+        d7: Option<PeripheralRef<'d, AnyPin>>,
         config: Config,
     ) -> Self {
         into_ref!(sdmmc, dma);
@@ -482,11 +640,17 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
             d1,
             d2,
             d3,
+            d4,
+            d5,
+            d6,
+            d7,
 
             config,
             clock: SD_INIT_FREQ,
             signalling: Default::default(),
             card: None,
+            sdio_card: None,
+            emmc: None,
         }
     }
 
@@ -1016,6 +1180,8 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
             true => BusWidth::Four,
             false => BusWidth::One,
         };
+        self.sdio_card = None;
+        self.emmc = None;
 
         // NOTE(unsafe) We have exclusive access to the peripheral
         unsafe {
@@ -1032,6 +1198,12 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
                 w.set_clkdiv(clkdiv);
                 #[cfg(sdmmc_v1)]
                 w.set_bypass(_bypass);
+                // An eMMC may have left the bus in DDR mode
+                #[cfg(sdmmc_v2)]
+                {
+                    w.set_ddr(false);
+                    w.set_busspeed(false);
+                }
             });
 
             regs.power().modify(|w| w.set_pwrctrl(PowerCtrl::On as u8));
@@ -1158,18 +1330,13 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
 
     #[inline(always)]
     pub async fn read_block(&mut self, block_idx: u32, buffer: &mut DataBlock) -> Result<(), Error> {
-        let card_capacity = self.card()?.card_type;
+        let address = self.block_address(block_idx)?;
 
         // NOTE(unsafe) DataBlock uses align 4
         let buffer = unsafe { &mut *((&mut buffer.0) as *mut [u8; 512] as *mut [u32; 128]) };
 
         // Always read 1 block of 512 bytes
-        // SDSC cards are byte addressed hence the blockaddress is in multiples of 512 bytes
-        let address = match card_capacity {
-            CardCapacity::SDSC => block_idx * 512,
-            _ => block_idx,
-        };
-        Self::cmd(Cmd::set_block_length(512), false)?; // CMD16
+        self.set_block_length()?;
 
         let regs = T::regs();
         let on_drop = OnDrop::new(|| unsafe { Self::on_drop() });
@@ -1203,18 +1370,13 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
     }
 
     pub async fn write_block(&mut self, block_idx: u32, buffer: &DataBlock) -> Result<(), Error> {
-        let card = self.card.as_mut().ok_or(Error::NoCard)?;
+        let address = self.block_address(block_idx)?;
 
         // NOTE(unsafe) DataBlock uses align 4
         let buffer = unsafe { &*((&buffer.0) as *const [u8; 512] as *const [u32; 128]) };
 
-        // Always read 1 block of 512 bytes
-        // SDSC cards are byte addressed hence the blockaddress is in multiples of 512 bytes
-        let address = match card.card_type {
-            CardCapacity::SDSC => block_idx * 512,
-            _ => block_idx,
-        };
-        Self::cmd(Cmd::set_block_length(512), false)?; // CMD16
+        // Always write 1 block of 512 bytes
+        self.set_block_length()?;
 
         let regs = T::regs();
         let on_drop = OnDrop::new(|| unsafe { Self::on_drop() });
@@ -1251,6 +1413,11 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
                 Self::stop_datapath();
                 drop(transfer);
 
+                if let Some(emmc) = &self.emmc {
+                    // eMMCs have no SD Status, wait for the end of programming instead
+                    return Self::wait_ready(emmc.rca).map(|_| ());
+                }
+
                 // TODO: Make this configurable
                 let mut timeout: u32 = 0x00FF_FFFF;
 
@@ -1269,6 +1436,32 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
         }
     }
 
+    /// Returns the address of the block `block_idx` on the card or eMMC.
+    fn block_address(&self, block_idx: u32) -> Result<u32, Error> {
+        let byte_addressed = match (&self.card, &self.emmc) {
+            (Some(card), _) => matches!(card.card_type, CardCapacity::SDSC),
+            (None, Some(emmc)) => !emmc.sector_addressed(),
+            (None, None) => return Err(Error::NoCard),
+        };
+
+        // SDSC cards and eMMCs up to 2GB are byte addressed hence the blockaddress is in multiples
+        // of 512 bytes
+        if byte_addressed {
+            Ok(block_idx * 512)
+        } else {
+            Ok(block_idx)
+        }
+    }
+
+    /// Set the block length of SD cards to 512 bytes (CMD16). eMMCs always use 512 bytes blocks,
+    /// and don't support CMD16 in DDR mode.
+    fn set_block_length(&self) -> Result<(), Error> {
+        match self.emmc {
+            Some(_) => Ok(()),
+            None => Self::cmd(Cmd::set_block_length(512), false), // CMD16
+        }
+    }
+
     /// Get a reference to the initialized card
     ///
     /// # Errors
@@ -1304,6 +1497,18 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Drop for Sdmmc<'d, T, Dma> {
             if let Some(x) = &mut self.d3 {
                 x.set_as_disconnected();
             }
+            if let Some(x) = &mut self.d4 {
+                x.set_as_disconnected();
+            }
+            if let Some(x) = &mut self.d5 {
+                x.set_as_disconnected();
+            }
+            if let Some(x) = &mut self.d6 {
+                x.set_as_disconnected();
+            }
+            if let Some(x) = &mut self.d7 {
+                x.set_as_disconnected();
+            }
         });
     }
 }
@@ -1553,8 +1758,10 @@ mod sdmmc_rs {
         }
 
         fn num_blocks(&self) -> Result<BlockCount, Self::Error> {
-            let card = self.card()?;
-            let count = card.csd.block_count();
+            let count = match self.emmc() {
+                Ok(emmc) => emmc.sector_count,
+                Err(_) => self.card()?.csd.block_count(),
+            };
             Ok(BlockCount(count))
         }
     }
@@ -0,0 +1,616 @@
+//! SDIO cards, like WiFi modules.
+//!
+//! The I/O functions of the card are accessed with IO_RW_DIRECT (CMD52), reading or writing a
+//! single byte, and IO_RW_EXTENDED (CMD53), transferring data with the datapath. Function 0 holds
+//! the Card Common Control Registers (CCCR), and the Function Basic Registers (FBR) of the
+//! other functions.
+
+use core::future::poll_fn;
+use core::task::Poll;
+
+use embassy_hal_common::drop::OnDrop;
+use sdio_host::BusWidth;
+
+use super::{
+    clk_div, Cmd, Error, Instance, InterruptHandler, PowerCtrl, Response, Sdmmc, SdmmcDma, Signalling, SD_INIT_FREQ,
+};
+use crate::time::Hertz;
+
+// CCCR registers
+const CCCR_REVISION: u32 = 0x00;
+const CCCR_IO_ENABLE: u32 = 0x02;
+const CCCR_IO_READY: u32 = 0x03;
+const CCCR_INT_ENABLE: u32 = 0x04;
+const CCCR_INT_PENDING: u32 = 0x05;
+const CCCR_IO_ABORT: u32 = 0x06;
+const CCCR_BUS_INTERFACE: u32 = 0x07;
+const CCCR_CAPABILITY: u32 = 0x08;
+const CCCR_CIS_POINTER: u32 = 0x09;
+const CCCR_HIGH_SPEED: u32 = 0x13;
+
+// FBR registers, at `fbr(func)`. The block size register of function 0 is in the CCCR, at the
+// same offset.
+const FBR_INTERFACE: u32 = 0x00;
+const FBR_BLOCK_SIZE: u32 = 0x10;
+
+const IO_ABORT_RES: u8 = 1 << 3;
+const INT_ENABLE_MASTER: u8 = 1 << 0;
+const BUS_WIDTH_MASK: u8 = 0b11;
+const BUS_WIDTH_4: u8 = 0b10;
+const CAPABILITY_LSC: u8 = 1 << 6;
+const CAPABILITY_4BLS: u8 = 1 << 7;
+const HIGH_SPEED_SHS: u8 = 1 << 0;
+const HIGH_SPEED_EHS: u8 = 1 << 1;
+
+// I/O OCR, in the R4 response to CMD5
+const IO_OCR_READY: u32 = 1 << 31;
+const IO_OCR_MEMORY_PRESENT: u32 = 1 << 27;
+const IO_OCR_VOLTAGE_WINDOW: u32 = 0x00FF_8000;
+
+/// COM_CRC_ERROR, ILLEGAL_COMMAND, ERROR, FUNCTION_NUMBER and OUT_OF_RANGE flags of a R5
+/// response.
+const R5_ERROR_FLAGS: u32 = 0xCB00;
+
+// CIS tuples
+const CISTPL_NULL: u8 = 0x00;
+const CISTPL_MANFID: u8 = 0x20;
+const CISTPL_END: u8 = 0xFF;
+/// Tuples read before giving up on finding CISTPL_MANFID, for broken CIS chains.
+const CIS_MAX_TUPLES: usize = 32;
+
+/// Polls of the I/O Ready register before giving up on a function enabling.
+const FUNCTION_READY_RETRIES: u32 = 0xFFFF;
+
+/// Address of the FBR of function `func`.
+const fn fbr(func: u8) -> u32 {
+    func as u32 * 0x100
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+/// SDIO Card
+pub struct SdioCard {
+    /// Relative Card Address
+    pub rca: u32,
+    /// I/O Operation Conditions Register, as returned by CMD5
+    pub ocr: u32,
+    /// Number of I/O functions, not counting function 0
+    pub num_functions: u8,
+    /// The card also has SD memory. Only its I/O functions are used.
+    pub memory_present: bool,
+    /// CCCR format version
+    pub cccr_revision: u8,
+    /// SDIO specification version
+    pub sdio_revision: u8,
+    /// Card Capability register of the CCCR
+    pub capability: u8,
+    /// Manufacturer code, from the CISTPL_MANFID tuple of the common CIS, or 0 if missing
+    pub manufacturer: u16,
+    /// Card identifier, from the CISTPL_MANFID tuple of the common CIS, or 0 if missing
+    pub card_id: u16,
+    /// Standard SDIO function interface codes of functions 1 to 7, from their FBR
+    pub interface_codes: [u8; 7],
+    /// Block sizes of functions 0 to 7, 0 until set
+    block_sizes: [u16; 8],
+}
+
+impl SdioCard {
+    /// Block size of function `func`, set by [`Sdmmc::set_block_size`], or 0 if not set.
+    pub fn block_size(&self, func: u8) -> u16 {
+        self.block_sizes[func as usize]
+    }
+}
+
+impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
+    /// Initializes an SDIO card (if present) and sets the bus at the
+    /// specified frequency.
+    ///
+    /// The bus is switched to 4 bits if the D1 to D3 pins were given, and to high speed above
+    /// 25 MHz if the card supports it. Only the I/O functions of combo cards are used.
+    pub async fn init_sdio_card(&mut self, freq: Hertz) -> Result<(), Error> {
+        let regs = T::regs();
+        let ker_ck = T::kernel_clk();
+
+        self.card = None;
+        self.sdio_card = None;
+        self.emmc = None;
+        self.signalling = Signalling::default();
+
+        // NOTE(unsafe) We have exclusive access to the peripheral
+        unsafe {
+            // While the SD/SDIO card or eMMC is in identification mode,
+            // the SDMMC_CK frequency must be no more than 400 kHz.
+            let (_bypass, clkdiv, init_clock) = unwrap!(clk_div(ker_ck, SD_INIT_FREQ.0));
+            self.clock = init_clock;
+
+            // CPSMACT and DPSMACT must be 0 to set WIDBUS
+            Self::wait_idle();
+
+            regs.clkcr().modify(|w| {
+                w.set_widbus(0);
+                w.set_clkdiv(clkdiv);
+                #[cfg(sdmmc_v1)]
+                w.set_bypass(_bypass);
+                // An eMMC may have left the bus in DDR mode
+                #[cfg(sdmmc_v2)]
+                {
+                    w.set_ddr(false);
+                    w.set_busspeed(false);
+                }
+            });
+
+            regs.power().modify(|w| w.set_pwrctrl(PowerCtrl::On as u8));
+        }
+
+        // Reset the I/O functions, in case the card was already initialized. A card in
+        // identification mode doesn't respond.
+        let _ = Self::io_rw_direct(true, 0, CCCR_IO_ABORT, IO_ABORT_RES);
+        Self::cmd(Cmd::idle(), false)?;
+
+        // Query the voltage window of the card, then wait for it to power up. Cards without I/O
+        // functions don't respond.
+        let ocr = Self::io_send_op_cond(0)?;
+        let ocr = loop {
+            let ocr = Self::io_send_op_cond(ocr & IO_OCR_VOLTAGE_WINDOW)?;
+            if ocr & IO_OCR_READY != 0 {
+                break ocr;
+            }
+        };
+        let num_functions = ((ocr >> 28) & 0x7) as u8;
+        if num_functions == 0 {
+            return Err(Error::UnsupportedCardType);
+        }
+
+        Self::cmd(Cmd::send_rel_addr(), false)?; // CMD3
+        let rca = unsafe { regs.respr(0).read().cardstatus() } >> 16;
+        Self::cmd(Cmd::sel_desel_card(rca << 16), false)?; // CMD7
+
+        let revision = Self::io_rw_direct(false, 0, CCCR_REVISION, 0)?;
+        let capability = Self::io_rw_direct(false, 0, CCCR_CAPABILITY, 0)?;
+
+        let mut card = SdioCard {
+            rca,
+            ocr,
+            num_functions,
+            memory_present: ocr & IO_OCR_MEMORY_PRESENT != 0,
+            cccr_revision: revision & 0x0F,
+            sdio_revision: revision >> 4,
+            capability,
+            ..Default::default()
+        };
+
+        for func in 1..=num_functions {
+            let interface = Self::io_rw_direct(false, 0, fbr(func) + FBR_INTERFACE, 0)?;
+            card.interface_codes[func as usize - 1] = interface & 0x0F;
+        }
+
+        let mut cis = 0;
+        for i in 0..3 {
+            cis |= (Self::io_rw_direct(false, 0, CCCR_CIS_POINTER + i, 0)? as u32) << (8 * i);
+        }
+        if let Some((manufacturer, card_id)) = Self::read_manfid(cis)? {
+            card.manufacturer = manufacturer;
+            card.card_id = card_id;
+        }
+
+        // Set bus width. 4 bits is mandatory, except for low speed cards.
+        let width = if self.d3.is_some() && (capability & CAPABILITY_LSC == 0 || capability & CAPABILITY_4BLS != 0) {
+            let bus = Self::io_rw_direct(false, 0, CCCR_BUS_INTERFACE, 0)?;
+            Self::io_rw_direct(true, 0, CCCR_BUS_INTERFACE, (bus & !BUS_WIDTH_MASK) | BUS_WIDTH_4)?;
+
+            // CPSMACT and DPSMACT must be 0 to set WIDBUS
+            Self::wait_idle();
+            unsafe { regs.clkcr().modify(|w| w.set_widbus(1)) };
+            BusWidth::Four
+        } else {
+            BusWidth::One
+        };
+
+        // Set Clock
+        let mut freq = freq.0;
+        if capability & CAPABILITY_LSC != 0 {
+            // Low speed cards are limited to 400 kHz
+            freq = freq.min(SD_INIT_FREQ.0);
+        } else if freq > 25_000_000 {
+            let high_speed = Self::io_rw_direct(false, 0, CCCR_HIGH_SPEED, 0)?;
+            if high_speed & HIGH_SPEED_SHS != 0 {
+                Self::io_rw_direct(true, 0, CCCR_HIGH_SPEED, high_speed | HIGH_SPEED_EHS)?;
+                self.signalling = Signalling::SDR25;
+            } else {
+                freq = 25_000_000;
+            }
+        }
+        self.clkcr_set_clkdiv(freq, width)?;
+
+        self.sdio_card = Some(card);
+        Ok(())
+    }
+
+    /// Get a reference to the initialized SDIO card
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::NoCard if [`init_sdio_card`](#method.init_sdio_card)
+    /// has not previously succeeded
+    #[inline(always)]
+    pub fn sdio_card(&self) -> Result<&SdioCard, Error> {
+        self.sdio_card.as_ref().ok_or(Error::NoCard)
+    }
+
+    /// Read the register at `addr` of function `func` (CMD52).
+    pub fn read_direct(&mut self, func: u8, addr: u32) -> Result<u8, Error> {
+        self.check_function(func)?;
+        Self::io_rw_direct(false, func, addr, 0)
+    }
+
+    /// Write `val` to the register at `addr` of function `func` (CMD52).
+    pub fn write_direct(&mut self, func: u8, addr: u32, val: u8) -> Result<(), Error> {
+        self.check_function(func)?;
+        Self::io_rw_direct(true, func, addr, val).map(drop)
+    }
+
+    /// Read `buffer` from function `func`, starting at `addr` (CMD53).
+    ///
+    /// If `incrementing` is false, all the data is read from `addr`, like from a FIFO. The
+    /// transfer is made in blocks if its length is a multiple of the
+    /// [block size](Self::set_block_size) of the function, and in bytes otherwise, which
+    /// is limited to 512 bytes. [`Error::EmptyBuffer`] is returned if `buffer` is empty.
+    pub async fn read_extended(
+        &mut self,
+        func: u8,
+        addr: u32,
+        incrementing: bool,
+        buffer: &mut [u32],
+    ) -> Result<(), Error> {
+        let len = buffer.len() * 4;
+        let (block_mode, count, block_size) = self.extended_mode(func, len)?;
+
+        // Arm `OnDrop` after the buffer, so it will be dropped first
+        let on_drop = OnDrop::new(|| unsafe { Self::sdio_on_drop(func) });
+
+        Self::set_sdio_data_mode(block_mode);
+        let _transfer = self.prepare_datapath_read(buffer, len as u32, block_size);
+        InterruptHandler::<T>::data_interrupts(true);
+        Self::io_rw_extended(false, func, block_mode, incrementing, addr, count)?;
+
+        let res = Self::wait_data_end().await;
+        Self::clear_interrupt_flags();
+
+        if res.is_ok() {
+            on_drop.defuse();
+            Self::stop_datapath();
+        }
+        res
+    }
+
+    /// Write `buffer` to function `func`, starting at `addr` (CMD53).
+    ///
+    /// See [`read_extended`](Self::read_extended).
+    pub async fn write_extended(
+        &mut self,
+        func: u8,
+        addr: u32,
+        incrementing: bool,
+        buffer: &[u32],
+    ) -> Result<(), Error> {
+        let len = buffer.len() * 4;
+        let (block_mode, count, block_size) = self.extended_mode(func, len)?;
+
+        let on_drop = OnDrop::new(|| unsafe { Self::sdio_on_drop(func) });
+
+        Self::set_sdio_data_mode(block_mode);
+
+        // sdmmc_v1 uses different cmd/dma order than v2, but only for writes
+        #[cfg(sdmmc_v1)]
+        Self::io_rw_extended(true, func, block_mode, incrementing, addr, count)?;
+
+        let _transfer = self.prepare_datapath_write(buffer, len as u32, block_size);
+        InterruptHandler::<T>::data_interrupts(true);
+
+        #[cfg(sdmmc_v2)]
+        Self::io_rw_extended(true, func, block_mode, incrementing, addr, count)?;
+
+        let res = Self::wait_data_end().await;
+        Self::clear_interrupt_flags();
+
+        if res.is_ok() {
+            on_drop.defuse();
+            Self::stop_datapath();
+        }
+        res
+    }
+
+    /// Set the block size of function `func`, used by the block transfers of
+    /// [`read_extended`](Self::read_extended) and [`write_extended`](Self::write_extended).
+    ///
+    /// The size must be a power of 2, supported by the function.
+    pub fn set_block_size(&mut self, func: u8, size: u16) -> Result<(), Error> {
+        assert!(
+            size.is_power_of_two() && size <= 2048,
+            "Block size must be a power of 2, up to 2048 bytes"
+        );
+        self.check_function(func)?;
+
+        let addr = fbr(func) + FBR_BLOCK_SIZE;
+        Self::io_rw_direct(true, 0, addr, size as u8)?;
+        Self::io_rw_direct(true, 0, addr + 1, (size >> 8) as u8)?;
+
+        self.sdio_card.as_mut().unwrap().block_sizes[func as usize] = size;
+        Ok(())
+    }
+
+    /// Enable the I/O function `func`, and wait for it to be ready.
+    pub fn enable_function(&mut self, func: u8) -> Result<(), Error> {
+        self.update_cccr_bit(func, CCCR_IO_ENABLE, true)?;
+
+        for _ in 0..FUNCTION_READY_RETRIES {
+            if Self::io_rw_direct(false, 0, CCCR_IO_READY, 0)? & (1 << func) != 0 {
+                return Ok(());
+            }
+        }
+        Err(Error::SoftwareTimeout)
+    }
+
+    /// Disable the I/O function `func`.
+    pub fn disable_function(&mut self, func: u8) -> Result<(), Error> {
+        self.update_cccr_bit(func, CCCR_IO_ENABLE, false)
+    }
+
+    /// Enable the interrupts of function `func`, see [`wait_for_interrupt`](Self::wait_for_interrupt).
+    pub fn enable_interrupt(&mut self, func: u8) -> Result<(), Error> {
+        self.update_cccr_bit(func, CCCR_INT_ENABLE, true)
+    }
+
+    /// Disable the interrupts of function `func`.
+    pub fn disable_interrupt(&mut self, func: u8) -> Result<(), Error> {
+        self.update_cccr_bit(func, CCCR_INT_ENABLE, false)
+    }
+
+    /// Wait for an interrupt of the card, signalled on D1.
+    ///
+    /// Returns the Interrupt Pending register of the CCCR, where bit `n` is set if function `n`
+    /// has an interrupt pending. The interrupt must be cleared in the function, otherwise this
+    /// returns immediately.
+    pub async fn wait_for_interrupt(&mut self) -> Result<u8, Error> {
+        self.sdio_card()?;
+        let regs = T::regs();
+
+        // NOTE(unsafe) We have exclusive access to the peripheral
+        unsafe {
+            regs.dctrl().modify(|w| w.set_sdioen(true));
+            regs.icr().write(|w| w.set_sdioitc(true));
+        }
+
+        // An interrupt signalled before the flag was cleared would be missed.
+        let pending = Self::io_rw_direct(false, 0, CCCR_INT_PENDING, 0)?;
+        if pending != 0 {
+            return Ok(pending);
+        }
+
+        let on_drop = OnDrop::new(|| unsafe { regs.maskr().modify(|w| w.set_sdioitie(false)) });
+        poll_fn(|cx| {
+            T::state().register(cx.waker());
+            // NOTE(unsafe) Atomic read with no side-effects
+            if unsafe { regs.star().read().sdioit() } {
+                return Poll::Ready(());
+            }
+            // The interrupt handler disables the interrupt, enable it again.
+            unsafe { regs.maskr().modify(|w| w.set_sdioitie(true)) };
+            Poll::Pending
+        })
+        .await;
+        drop(on_drop);
+
+        unsafe { regs.icr().write(|w| w.set_sdioitc(true)) };
+        Self::io_rw_direct(false, 0, CCCR_INT_PENDING, 0)
+    }
+
+    fn check_function(&self, func: u8) -> Result<(), Error> {
+        let card = self.sdio_card()?;
+        assert!(func <= card.num_functions, "Invalid function number");
+        Ok(())
+    }
+
+    /// Set or clear the bit of function `func` in the CCCR register `addr`, along with the
+    /// master bit of the Int Enable register.
+    fn update_cccr_bit(&mut self, func: u8, addr: u32, set: bool) -> Result<(), Error> {
+        self.check_function(func)?;
+        assert!(func != 0, "Function 0 can't be enabled or disabled");
+
+        let mut val = Self::io_rw_direct(false, 0, addr, 0)?;
+        if set {
+            val |= 1 << func;
+        } else {
+            val &= !(1 << func);
+        }
+        if addr == CCCR_INT_ENABLE {
+            val = match val & !INT_ENABLE_MASTER {
+                0 => 0,
+                val => val | INT_ENABLE_MASTER,
+            };
+        }
+        Self::io_rw_direct(true, 0, addr, val).map(drop)
+    }
+
+    /// Returns the block mode flag and the count of CMD53, and the block size of the datapath,
+    /// for a transfer of `len` bytes.
+    fn extended_mode(&self, func: u8, len: usize) -> Result<(bool, u16, u8), Error> {
+        self.check_function(func)?;
+        // A count of 0 would mean 512 bytes, or an infinite transfer in block mode.
+        if len == 0 {
+            return Err(Error::EmptyBuffer);
+        }
+        let block_size = self.sdio_card()?.block_size(func) as usize;
+
+        if block_size != 0 && len % block_size == 0 {
+            let count = len / block_size;
+            assert!(count <= 511, "Up to 511 blocks per transfer");
+            Ok((true, count as u16, block_size.trailing_zeros() as u8))
+        } else {
+            assert!(len <= 512, "Up to 512 bytes per transfer, outside of block mode");
+            // A count of 0 means 512 bytes.
+            Ok((false, (len % 512) as u16, 0))
+        }
+    }
+
+    /// Send CMD5, returning the I/O OCR.
+    fn io_send_op_cond(ocr: u32) -> Result<u32, Error> {
+        match Self::cmd(Cmd::io_send_op_cond(ocr), false) {
+            // R4 responses have no CRC
+            Ok(_) | Err(Error::Crc) => Ok(unsafe { T::regs().respr(0).read().cardstatus() }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Send CMD52, returning the byte read.
+    fn io_rw_direct(write: bool, func: u8, addr: u32, val: u8) -> Result<u8, Error> {
+        Self::cmd(Cmd::io_rw_direct(write, func, addr, val), false)?;
+        let r5 = unsafe { T::regs().respr(0).read().cardstatus() };
+        if r5 & R5_ERROR_FLAGS != 0 {
+            return Err(Error::SdioError);
+        }
+        Ok(r5 as u8)
+    }
+
+    /// Send CMD53, starting the data transfer.
+    fn io_rw_extended(
+        write: bool,
+        func: u8,
+        block_mode: bool,
+        incrementing: bool,
+        addr: u32,
+        count: u16,
+    ) -> Result<(), Error> {
+        Self::cmd(
+            Cmd::io_rw_extended(write, func, block_mode, incrementing, addr, count),
+            true,
+        )?;
+        let r5 = unsafe { T::regs().respr(0).read().cardstatus() };
+        if r5 & R5_ERROR_FLAGS != 0 {
+            return Err(Error::SdioError);
+        }
+        Ok(())
+    }
+
+    /// Find the CISTPL_MANFID tuple in the CIS at `addr`, returning the manufacturer code and
+    /// card identifier.
+    fn read_manfid(mut addr: u32) -> Result<Option<(u16, u16)>, Error> {
+        let read = |addr| Self::io_rw_direct(false, 0, addr, 0);
+
+        for _ in 0..CIS_MAX_TUPLES {
+            let code = read(addr)?;
+            if code == CISTPL_NULL {
+                addr += 1;
+                continue;
+            }
+            if code == CISTPL_END {
+                break;
+            }
+
+            let link = read(addr + 1)?;
+            if code == CISTPL_MANFID && link >= 4 {
+                let manufacturer = u16::from_le_bytes([read(addr + 2)?, read(addr + 3)?]);
+                let card_id = u16::from_le_bytes([read(addr + 4)?, read(addr + 5)?]);
+                return Ok(Some((manufacturer, card_id)));
+            }
+            if link == 0xFF {
+                break;
+            }
+            addr += 2 + link as u32;
+        }
+        Ok(None)
+    }
+
+    /// Select the SDIO multibyte or block mode of the DPSM.
+    fn set_sdio_data_mode(block_mode: bool) {
+        // NOTE(unsafe) We have exclusive access to the regisers
+        unsafe {
+            T::regs().dctrl().modify(|w| {
+                w.set_sdioen(true);
+                #[cfg(sdmmc_v1)]
+                w.set_dtmode(!block_mode);
+                #[cfg(sdmmc_v2)]
+                w.set_dtmode(if block_mode { 0 } else { 1 });
+            });
+        }
+    }
+
+    async fn wait_data_end() -> Result<(), Error> {
+        let regs = T::regs();
+        poll_fn(|cx| {
+            T::state().register(cx.waker());
+            let status = unsafe { regs.star().read() };
+
+            if status.dcrcfail() {
+                return Poll::Ready(Err(Error::Crc));
+            } else if status.dtimeout() {
+                return Poll::Ready(Err(Error::Timeout));
+            } else if status.dataend() {
+                return Poll::Ready(Ok(()));
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Like `on_drop`, but SDIO cards don't support CMD12. The transfer of `func` is aborted
+    /// through the I/O Abort register instead.
+    ///
+    /// # Safety
+    ///
+    /// Ensure that `regs` has exclusive access to the regblocks
+    unsafe fn sdio_on_drop(func: u8) {
+        let regs = T::regs();
+        if Self::data_active() {
+            Self::clear_interrupt_flags();
+            // CP state machine must be idle
+            while Self::cmd_active() {}
+
+            regs.argr()
+                .write(|w| w.set_cmdarg(Cmd::io_rw_direct(true, 0, CCCR_IO_ABORT, func).arg));
+
+            regs.cmdr().write(|w| {
+                w.set_waitint(false);
+                w.set_waitresp(Response::Short as u8);
+                w.set_cmdindex(52);
+                w.set_cpsmen(true);
+
+                #[cfg(sdmmc_v2)]
+                {
+                    w.set_cmdstop(true);
+                    w.set_cmdtrans(false);
+                }
+            });
+
+            // Wait for the abort
+            while Self::data_active() {}
+        }
+        InterruptHandler::<T>::data_interrupts(false);
+        Self::clear_interrupt_flags();
+        Self::stop_datapath();
+    }
+}
+
+/// SDIO Commands
+impl Cmd {
+    /// IO_SEND_OP_COND, R4 response
+    const fn io_send_op_cond(ocr: u32) -> Cmd {
+        Cmd::new(5, ocr, Response::Short)
+    }
+
+    /// IO_RW_DIRECT, R5 response
+    const fn io_rw_direct(write: bool, func: u8, addr: u32, val: u8) -> Cmd {
+        let arg = (write as u32) << 31 | (func as u32 & 0x7) << 28 | (addr & 0x1_FFFF) << 9 | val as u32;
+        Cmd::new(52, arg, Response::Short)
+    }
+
+    /// IO_RW_EXTENDED, R5 response
+    const fn io_rw_extended(write: bool, func: u8, block_mode: bool, incrementing: bool, addr: u32, count: u16) -> Cmd {
+        let arg = (write as u32) << 31
+            | (func as u32 & 0x7) << 28
+            | (block_mode as u32) << 27
+            | (incrementing as u32) << 26
+            | (addr & 0x1_FFFF) << 9
+            | (count as u32 & 0x1FF);
+        Cmd::new(53, arg, Response::Short)
+    }
+}
@@ -31,11 +31,27 @@ pub enum BitOrder {
     MsbFirst,
 }
 
+/// Frame format.
+#[cfg(not(spi_f1))]
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// Motorola SPI, with the clock polarity and phase given by [`Config::mode`].
+    Motorola,
+    /// TI synchronous serial frame format. The clock polarity and phase are fixed, and NSS
+    /// pulses before each frame, so the NSS pin must be given to the driver.
+    Ti,
+}
+
 #[non_exhaustive]
 #[derive(Copy, Clone)]
 pub struct Config {
     pub mode: Mode,
     pub bit_order: BitOrder,
+    #[cfg(not(spi_f1))]
+    pub frame_format: FrameFormat,
+    /// Release NSS between frames when it is driven by the peripheral, see [`Spi::new_with_nss`].
+    #[cfg(not(any(spi_v1, spi_f1)))]
+    pub nss_pulse: bool,
 }
 
 impl Default for Config {
@@ -43,6 +59,10 @@ impl Default for Config {
         Self {
             mode: MODE_0,
             bit_order: BitOrder::MsbFirst,
+            #[cfg(not(spi_f1))]
+            frame_format: FrameFormat::Motorola,
+            #[cfg(not(any(spi_v1, spi_f1)))]
+            nss_pulse: false,
         }
     }
 }
@@ -68,6 +88,28 @@ impl Config {
             BitOrder::MsbFirst => vals::Lsbfirst::MSBFIRST,
         }
     }
+
+    #[cfg(any(spi_v1, spi_v2))]
+    fn raw_frame_format(&self) -> vals::Frf {
+        match self.frame_format {
+            FrameFormat::Motorola => vals::Frf::MOTOROLA,
+            FrameFormat::Ti => vals::Frf::TI,
+        }
+    }
+
+    #[cfg(any(spi_v3, spi_v4, spi_v5))]
+    fn raw_frame_format(&self) -> vals::Sp {
+        match self.frame_format {
+            FrameFormat::Motorola => vals::Sp::MOTOROLA,
+            FrameFormat::Ti => vals::Sp::TI,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Role {
+    Master,
+    Slave,
 }
 
 pub struct Spi<'d, T: Instance, Tx, Rx> {
@@ -75,8 +117,9 @@ pub struct Spi<'d, T: Instance, Tx, Rx> {
     sck: Option<PeripheralRef<'d, AnyPin>>,
     mosi: Option<PeripheralRef<'d, AnyPin>>,
     miso: Option<PeripheralRef<'d, AnyPin>>,
-    txdma: PeripheralRef<'d, Tx>,
-    rxdma: PeripheralRef<'d, Rx>,
+    nss: Option<PeripheralRef<'d, AnyPin>>,
+    pub(crate) txdma: PeripheralRef<'d, Tx>,
+    pub(crate) rxdma: PeripheralRef<'d, Rx>,
     current_word_size: word_impl::Config,
 }
 
@@ -112,10 +155,98 @@ impl<'d, T: Instance, Tx, Rx> Spi<'d, T, Tx, Rx> {
             Some(sck.map_into()),
             Some(mosi.map_into()),
             Some(miso.map_into()),
+            None,
             txdma,
             rxdma,
             freq,
             config,
+            Role::Master,
+        )
+    }
+
+    /// Create a master whose NSS pin is driven by the peripheral. NSS is low while the peripheral
+    /// is enabled, or pulses between frames with [`Config::nss_pulse`] and in TI mode.
+    pub fn new_with_nss(
+        peri: impl Peripheral<P = T> + 'd,
+        sck: impl Peripheral<P = impl SckPin<T>> + 'd,
+        mosi: impl Peripheral<P = impl MosiPin<T>> + 'd,
+        miso: impl Peripheral<P = impl MisoPin<T>> + 'd,
+        nss: impl Peripheral<P = impl CsPin<T>> + 'd,
+        txdma: impl Peripheral<P = Tx> + 'd,
+        rxdma: impl Peripheral<P = Rx> + 'd,
+        freq: Hertz,
+        config: Config,
+    ) -> Self {
+        into_ref!(peri, sck, mosi, miso, nss);
+
+        let sck_pull_mode = match config.mode.polarity {
+            Polarity::IdleLow => Pull::Down,
+            Polarity::IdleHigh => Pull::Up,
+        };
+
+        unsafe {
+            sck.set_as_af_pull(sck.af_num(), AFType::OutputPushPull, sck_pull_mode);
+            sck.set_speed(crate::gpio::Speed::VeryHigh);
+            mosi.set_as_af(mosi.af_num(), AFType::OutputPushPull);
+            mosi.set_speed(crate::gpio::Speed::VeryHigh);
+            miso.set_as_af(miso.af_num(), AFType::Input);
+            miso.set_speed(crate::gpio::Speed::VeryHigh);
+            nss.set_as_af_pull(nss.af_num(), AFType::OutputPushPull, Pull::Up);
+            nss.set_speed(crate::gpio::Speed::VeryHigh);
+        }
+
+        Self::new_inner(
+            peri,
+            Some(sck.map_into()),
+            Some(mosi.map_into()),
+            Some(miso.map_into()),
+            Some(nss.map_into()),
+            txdma,
+            rxdma,
+            freq,
+            config,
+            Role::Master,
+        )
+    }
+
+    /// Create a slave, selected by the master through the NSS pin.
+    ///
+    /// The transfer functions load the data and wait for the master to clock it, so they must be
+    /// called before the master starts the transfer.
+    pub fn new_slave(
+        peri: impl Peripheral<P = T> + 'd,
+        sck: impl Peripheral<P = impl SckPin<T>> + 'd,
+        mosi: impl Peripheral<P = impl MosiPin<T>> + 'd,
+        miso: impl Peripheral<P = impl MisoPin<T>> + 'd,
+        nss: impl Peripheral<P = impl CsPin<T>> + 'd,
+        txdma: impl Peripheral<P = Tx> + 'd,
+        rxdma: impl Peripheral<P = Rx> + 'd,
+        config: Config,
+    ) -> Self {
+        into_ref!(peri, sck, mosi, miso, nss);
+
+        unsafe {
+            sck.set_as_af(sck.af_num(), AFType::Input);
+            mosi.set_as_af(mosi.af_num(), AFType::Input);
+            miso.set_as_af(miso.af_num(), AFType::OutputPushPull);
+            miso.set_speed(crate::gpio::Speed::VeryHigh);
+            nss.set_as_af_pull(nss.af_num(), AFType::Input, Pull::Up);
+        }
+
+        // The clock comes from the master, so the baud rate setting is unused
+        let freq = T::frequency();
+
+        Self::new_inner(
+            peri,
+            Some(sck.map_into()),
+            Some(mosi.map_into()),
+            Some(miso.map_into()),
+            Some(nss.map_into()),
+            txdma,
+            rxdma,
+            freq,
+            config,
+            Role::Slave,
         )
     }
 
@@ -141,10 +272,12 @@ impl<'d, T: Instance, Tx, Rx> Spi<'d, T, Tx, Rx> {
             Some(sck.map_into()),
             None,
             Some(miso.map_into()),
+            None,
             txdma,
             rxdma,
             freq,
             config,
+            Role::Master,
         )
     }
 
@@ -170,10 +303,12 @@ impl<'d, T: Instance, Tx, Rx> Spi<'d, T, Tx, Rx> {
             Some(sck.map_into()),
             Some(mosi.map_into()),
             None,
+            None,
             txdma,
             rxdma,
             freq,
             config,
+            Role::Master,
         )
     }
 
@@ -191,7 +326,18 @@ impl<'d, T: Instance, Tx, Rx> Spi<'d, T, Tx, Rx> {
             mosi.set_speed(crate::gpio::Speed::Medium);
         }
 
-        Self::new_inner(peri, None, Some(mosi.map_into()), None, txdma, rxdma, freq, config)
+        Self::new_inner(
+            peri,
+            None,
+            Some(mosi.map_into()),
+            None,
+            None,
+            txdma,
+            rxdma,
+            freq,
+            config,
+            Role::Master,
+        )
     }
 
     #[cfg(stm32wl)]
@@ -209,7 +355,7 @@ impl<'d, T: Instance, Tx, Rx> Spi<'d, T, Tx, Rx> {
         let mut config = Config::default();
         config.mode = MODE_0;
         config.bit_order = BitOrder::MsbFirst;
-        Self::new_inner(peri, None, None, None, txdma, rxdma, freq, config)
+        Self::new_inner(peri, None, None, None, None, txdma, rxdma, freq, config, Role::Master)
     }
 
     #[allow(dead_code)]
@@ -220,7 +366,7 @@ impl<'d, T: Instance, Tx, Rx> Spi<'d, T, Tx, Rx> {
         freq: Hertz,
         config: Config,
     ) -> Self {
-        Self::new_inner(peri, None, None, None, txdma, rxdma, freq, config)
+        Self::new_inner(peri, None, None, None, None, txdma, rxdma, freq, config, Role::Master)
     }
 
     fn new_inner(
@@ -228,10 +374,12 @@ impl<'d, T: Instance, Tx, Rx> Spi<'d, T, Tx, Rx> {
         sck: Option<PeripheralRef<'d, AnyPin>>,
         mosi: Option<PeripheralRef<'d, AnyPin>>,
         miso: Option<PeripheralRef<'d, AnyPin>>,
+        nss: Option<PeripheralRef<'d, AnyPin>>,
         txdma: impl Peripheral<P = Tx> + 'd,
         rxdma: impl Peripheral<P = Rx> + 'd,
         freq: Hertz,
         config: Config,
+        role: Role,
     ) -> Self {
         into_ref!(peri, txdma, rxdma);
 
@@ -243,24 +391,34 @@ impl<'d, T: Instance, Tx, Rx> Spi<'d, T, Tx, Rx> {
 
         let lsbfirst = config.raw_byte_order();
 
+        // With an NSS pin, NSS is an output of the master and an input of the slave. Without one,
+        // it is managed in software and kept inactive.
+        let hardware_nss = nss.is_some();
+        let ssoe = hardware_nss && role == Role::Master;
+
         T::enable();
         T::reset();
 
         #[cfg(any(spi_v1, spi_f1))]
         unsafe {
             T::REGS.cr2().modify(|w| {
-                w.set_ssoe(false);
+                w.set_ssoe(ssoe);
+                #[cfg(spi_v1)]
+                w.set_frf(config.raw_frame_format());
             });
             T::REGS.cr1().modify(|w| {
                 w.set_cpha(cpha);
                 w.set_cpol(cpol);
 
-                w.set_mstr(vals::Mstr::MASTER);
+                w.set_mstr(match role {
+                    Role::Master => vals::Mstr::MASTER,
+                    Role::Slave => vals::Mstr::SLAVE,
+                });
                 w.set_br(br);
                 w.set_spe(true);
                 w.set_lsbfirst(lsbfirst);
                 w.set_ssi(true);
-                w.set_ssm(true);
+                w.set_ssm(!hardware_nss);
                 w.set_crcen(false);
                 w.set_bidimode(vals::Bidimode::UNIDIRECTIONAL);
                 if mosi.is_none() {
@@ -275,17 +433,22 @@ impl<'d, T: Instance, Tx, Rx> Spi<'d, T, Tx, Rx> {
                 let (ds, frxth) = <u8 as sealed::Word>::CONFIG;
                 w.set_frxth(frxth);
                 w.set_ds(ds);
-                w.set_ssoe(false);
+                w.set_ssoe(ssoe);
+                w.set_frf(config.raw_frame_format());
+                w.set_nssp(ssoe && config.nss_pulse);
             });
             T::REGS.cr1().modify(|w| {
                 w.set_cpha(cpha);
                 w.set_cpol(cpol);
 
-                w.set_mstr(vals::Mstr::MASTER);
+                w.set_mstr(match role {
+                    Role::Master => vals::Mstr::MASTER,
+                    Role::Slave => vals::Mstr::SLAVE,
+                });
                 w.set_br(br);
                 w.set_lsbfirst(lsbfirst);
                 w.set_ssi(true);
-                w.set_ssm(true);
+                w.set_ssm(!hardware_nss);
                 w.set_crcen(false);
                 w.set_bidimode(vals::Bidimode::UNIDIRECTIONAL);
                 w.set_spe(true);
@@ -295,19 +458,31 @@ impl<'d, T: Instance, Tx, Rx> Spi<'d, T, Tx, Rx> {
         unsafe {
             T::REGS.ifcr().write(|w| w.0 = 0xffff_ffff);
             T::REGS.cfg2().modify(|w| {
-                //w.set_ssoe(true);
-                w.set_ssoe(false);
+                w.set_ssoe(ssoe);
                 w.set_cpha(cpha);
                 w.set_cpol(cpol);
                 w.set_lsbfirst(lsbfirst);
-                w.set_ssm(true);
-                w.set_master(vals::Master::MASTER);
+                w.set_ssm(!hardware_nss);
+                w.set_master(match role {
+                    Role::Master => vals::Master::MASTER,
+                    Role::Slave => vals::Master::SLAVE,
+                });
                 w.set_comm(vals::Comm::FULLDUPLEX);
-                w.set_ssom(vals::Ssom::ASSERTED);
-                w.set_midi(0);
+                w.set_sp(config.raw_frame_format());
+                if ssoe && config.nss_pulse {
+                    // Release SS for one clock cycle between frames
+                    w.set_ssom(vals::Ssom::NOTASSERTED);
+                    w.set_midi(1);
+                } else {
+                    w.set_ssom(vals::Ssom::ASSERTED);
+                    w.set_midi(0);
+                }
                 w.set_mssi(0);
                 w.set_afcntr(vals::Afcntr::CONTROLLED);
-                w.set_ssiop(vals::Ssiop::ACTIVEHIGH);
+                w.set_ssiop(match hardware_nss {
+                    true => vals::Ssiop::ACTIVELOW,
+                    false => vals::Ssiop::ACTIVEHIGH,
+                });
             });
             T::REGS.cfg1().modify(|w| {
                 w.set_crcen(false);
@@ -329,6 +504,7 @@ impl<'d, T: Instance, Tx, Rx> Spi<'d, T, Tx, Rx> {
             sck,
             mosi,
             miso,
+            nss,
             txdma,
             rxdma,
             current_word_size: <u8 as sealed::Word>::CONFIG,
@@ -351,12 +527,35 @@ impl<'d, T: Instance, Tx, Rx> Spi<'d, T, Tx, Rx> {
             });
         }
 
+        #[cfg(spi_v1)]
+        unsafe {
+            T::REGS.cr2().modify(|w| {
+                w.set_frf(config.raw_frame_format());
+            });
+        }
+
+        #[cfg(spi_v2)]
+        unsafe {
+            T::REGS.cr2().modify(|w| {
+                w.set_frf(config.raw_frame_format());
+                w.set_nssp(w.ssoe() && config.nss_pulse);
+            });
+        }
+
         #[cfg(any(spi_v3, spi_v4, spi_v5))]
         unsafe {
             T::REGS.cfg2().modify(|w| {
                 w.set_cpha(cpha);
                 w.set_cpol(cpol);
                 w.set_lsbfirst(lsbfirst);
+                w.set_sp(config.raw_frame_format());
+                if w.ssoe() && config.nss_pulse {
+                    w.set_ssom(vals::Ssom::NOTASSERTED);
+                    w.set_midi(1);
+                } else {
+                    w.set_ssom(vals::Ssom::ASSERTED);
+                    w.set_midi(0);
+                }
             });
         }
     }
@@ -383,9 +582,29 @@ impl<'d, T: Instance, Tx, Rx> Spi<'d, T, Tx, Rx> {
             BitOrder::MsbFirst
         };
 
+        #[cfg(any(spi_v1, spi_v2))]
+        let frame_format = match unsafe { T::REGS.cr2().read() }.frf() {
+            vals::Frf::TI => FrameFormat::Ti,
+            _ => FrameFormat::Motorola,
+        };
+        #[cfg(any(spi_v3, spi_v4, spi_v5))]
+        let frame_format = match cfg.sp() {
+            vals::Sp::TI => FrameFormat::Ti,
+            _ => FrameFormat::Motorola,
+        };
+
+        #[cfg(spi_v2)]
+        let nss_pulse = unsafe { T::REGS.cr2().read() }.nssp();
+        #[cfg(any(spi_v3, spi_v4, spi_v5))]
+        let nss_pulse = cfg.ssom() == vals::Ssom::NOTASSERTED;
+
         Config {
             mode: Mode { polarity, phase },
             bit_order,
+            #[cfg(not(spi_f1))]
+            frame_format,
+            #[cfg(not(any(spi_v1, spi_f1)))]
+            nss_pulse,
         }
     }
 
@@ -654,6 +873,9 @@ impl<'d, T: Instance, Tx, Rx> Drop for Spi<'d, T, Tx, Rx> {
             self.sck.as_ref().map(|x| x.set_as_disconnected());
             self.mosi.as_ref().map(|x| x.set_as_disconnected());
             self.miso.as_ref().map(|x| x.set_as_disconnected());
+            if let Some(nss) = &self.nss {
+                nss.set_as_disconnected();
+            }
         }
     }
 }
@@ -679,7 +901,7 @@ fn compute_baud_rate(clocks: Hertz, freq: Hertz) -> Br {
     Br(val)
 }
 
-trait RegsExt {
+pub(crate) trait RegsExt {
     fn tx_ptr<W>(&self) -> *mut W;
     fn rx_ptr<W>(&self) -> *mut W;
 }
@@ -776,7 +998,7 @@ fn flush_rx_fifo(regs: Regs) {
     }
 }
 
-fn set_txdmaen(regs: Regs, val: bool) {
+pub(crate) fn set_txdmaen(regs: Regs, val: bool) {
     unsafe {
         #[cfg(not(any(spi_v3, spi_v4, spi_v5)))]
         regs.cr2().modify(|reg| {
@@ -789,7 +1011,7 @@ fn set_txdmaen(regs: Regs, val: bool) {
     }
 }
 
-fn set_rxdmaen(regs: Regs, val: bool) {
+pub(crate) fn set_rxdmaen(regs: Regs, val: bool) {
     unsafe {
         #[cfg(not(any(spi_v3, spi_v4, spi_v5)))]
         regs.cr2().modify(|reg| {
@@ -1,5 +1,8 @@
 use stm32_metapac::timer::vals;
 
+pub mod pwm_input;
+pub mod qei;
+
 use crate::interrupt::Interrupt;
 use crate::rcc::sealed::RccPeripheral as __RccPeri;
 use crate::rcc::RccPeripheral;
@@ -11,12 +14,34 @@ pub mod low_level {
 }
 
 pub(crate) mod sealed {
+    use core::sync::atomic::AtomicI32;
+
+    use embassy_sync::waitqueue::AtomicWaker;
+
     use super::*;
+
+    pub struct State {
+        pub waker: AtomicWaker,
+        /// Counter overflows minus underflows, for drivers extending the counter
+        pub overflows: AtomicI32,
+    }
+
+    impl State {
+        pub const fn new() -> Self {
+            Self {
+                waker: AtomicWaker::new(),
+                overflows: AtomicI32::new(0),
+            }
+        }
+    }
+
     pub trait Basic16bitInstance: RccPeripheral {
         type Interrupt: Interrupt;
 
         fn regs() -> crate::pac::timer::TimBasic;
 
+        fn state() -> &'static State;
+
         fn start(&mut self);
 
         fn stop(&mut self);
@@ -45,7 +70,7 @@ pub(crate) mod sealed {
     }
 }
 
-pub trait GeneralPurpose16bitInstance: sealed::GeneralPurpose16bitInstance + 'static {}
+pub trait GeneralPurpose16bitInstance: sealed::GeneralPurpose16bitInstance + Basic16bitInstance + 'static {}
 
 pub trait GeneralPurpose32bitInstance: sealed::GeneralPurpose32bitInstance + 'static {}
 
@@ -63,6 +88,11 @@ macro_rules! impl_basic_16bit_timer {
                 crate::pac::timer::TimBasic(crate::pac::$inst.0)
             }
 
+            fn state() -> &'static sealed::State {
+                static STATE: sealed::State = sealed::State::new();
+                &STATE
+            }
+
             fn start(&mut self) {
                 unsafe {
                     Self::regs().cr1().modify(|r| r.set_cen(true));
@@ -223,3 +253,5 @@ foreach_interrupt! {
         }
     };
 }
+
+dma_trait!(UpDma, Basic16bitInstance);
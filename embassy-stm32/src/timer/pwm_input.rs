@@ -0,0 +1,141 @@
+//! PWM input: measure the period and duty cycle of a signal with input capture.
+
+use embassy_hal_common::{into_ref, PeripheralRef};
+use stm32_metapac::timer::vals;
+
+#[allow(unused_imports)]
+use crate::gpio::sealed::{AFType, Pin};
+use crate::gpio::{AnyPin, Pull};
+use crate::pwm::{CaptureCompare16bitInstance, Channel1Pin, Channel2Pin};
+use crate::time::Hertz;
+use crate::Peripheral;
+
+/// PWM input.
+///
+/// The counter runs at the given frequency and is reset on each rising edge of the signal, after
+/// capturing the period in one channel. The other channel captures the width of the high pulse
+/// on the falling edges. Periods longer than 65535 counter ticks cannot be measured.
+pub struct PwmInput<'d, T: CaptureCompare16bitInstance> {
+    _inner: PeripheralRef<'d, T>,
+    pin: PeripheralRef<'d, AnyPin>,
+    /// Channel capturing the period, the other one captures the width
+    period_channel: usize,
+}
+
+impl<'d, T: CaptureCompare16bitInstance> PwmInput<'d, T> {
+    /// Measure the signal on channel 1, with the counter ticking at `freq`.
+    pub fn new(
+        tim: impl Peripheral<P = T> + 'd,
+        pin: impl Peripheral<P = impl Channel1Pin<T>> + 'd,
+        pull: Pull,
+        freq: Hertz,
+    ) -> Self {
+        into_ref!(pin);
+        unsafe { pin.set_as_af_pull(pin.af_num(), AFType::Input, pull) };
+
+        Self::new_inner(tim, pin.map_into(), 0, freq)
+    }
+
+    /// Measure the signal on channel 2, with the counter ticking at `freq`.
+    pub fn new_alt(
+        tim: impl Peripheral<P = T> + 'd,
+        pin: impl Peripheral<P = impl Channel2Pin<T>> + 'd,
+        pull: Pull,
+        freq: Hertz,
+    ) -> Self {
+        into_ref!(pin);
+        unsafe { pin.set_as_af_pull(pin.af_num(), AFType::Input, pull) };
+
+        Self::new_inner(tim, pin.map_into(), 1, freq)
+    }
+
+    fn new_inner(
+        tim: impl Peripheral<P = T> + 'd,
+        pin: PeripheralRef<'d, AnyPin>,
+        period_channel: usize,
+        freq: Hertz,
+    ) -> Self {
+        into_ref!(tim);
+
+        T::enable();
+        <T as crate::rcc::sealed::RccPeripheral>::reset();
+
+        let timer_f = <T as crate::rcc::sealed::RccPeripheral>::frequency().0;
+        let psc: u16 = unwrap!((timer_f / freq.0 - 1).try_into());
+
+        let width_channel = 1 - period_channel;
+        let r = T::regs_gp16();
+        unsafe {
+            r.psc().write(|w| w.set_psc(psc));
+            r.arr().write(|w| w.set_arr(u16::MAX));
+            r.egr().write(|w| w.set_ug(true));
+
+            r.ccmr_input(0).modify(|w| {
+                // Both channels capture the input pin: the period channel directly,
+                // the width channel through the alternate mapping.
+                w.set_ccs(period_channel, vals::CcmrInputCcs::TI4);
+                w.set_ccs(width_channel, vals::CcmrInputCcs::TI3);
+            });
+            r.ccer().modify(|w| {
+                w.set_ccp(period_channel, false);
+                w.set_ccp(width_channel, true);
+                w.set_cce(period_channel, true);
+                w.set_cce(width_channel, true);
+            });
+            r.smcr().modify(|w| {
+                w.set_ts(match period_channel {
+                    0 => vals::Ts::TI1FP1,
+                    _ => vals::Ts::TI2FP2,
+                });
+                w.set_sms(vals::Sms::RESET_MODE);
+            });
+        }
+
+        Self {
+            _inner: tim,
+            pin,
+            period_channel,
+        }
+    }
+
+    /// Start measuring.
+    pub fn enable(&mut self) {
+        unsafe { T::regs_gp16().cr1().modify(|w| w.set_cen(true)) };
+    }
+
+    /// Stop measuring.
+    pub fn disable(&mut self) {
+        unsafe { T::regs_gp16().cr1().modify(|w| w.set_cen(false)) };
+    }
+
+    /// Whether the measurement is running.
+    pub fn is_enabled(&self) -> bool {
+        unsafe { T::regs_gp16().cr1().read().cen() }
+    }
+
+    /// Period of the signal, in counter ticks.
+    pub fn get_period_ticks(&self) -> u16 {
+        unsafe { T::regs_gp16().ccr(self.period_channel).read().ccr() }
+    }
+
+    /// Width of the high pulses of the signal, in counter ticks.
+    pub fn get_width_ticks(&self) -> u16 {
+        unsafe { T::regs_gp16().ccr(1 - self.period_channel).read().ccr() }
+    }
+
+    /// Duty cycle of the signal, from 0.0 to 1.0.
+    pub fn get_duty_cycle(&self) -> f32 {
+        let period = self.get_period_ticks();
+        if period == 0 {
+            return 0.;
+        }
+        self.get_width_ticks() as f32 / period as f32
+    }
+}
+
+impl<'d, T: CaptureCompare16bitInstance> Drop for PwmInput<'d, T> {
+    fn drop(&mut self) {
+        self.disable();
+        unsafe { self.pin.set_as_disconnected() };
+    }
+}
@@ -0,0 +1,251 @@
+//! Quadrature encoder interface, with a position extended in the background past the 16 bit counter.
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering;
+use core::task::Poll;
+
+use embassy_cortex_m::interrupt::{Interrupt, InterruptExt};
+use embassy_hal_common::{into_ref, PeripheralRef};
+use stm32_metapac::timer::vals;
+
+#[allow(unused_imports)]
+use crate::gpio::sealed::{AFType, Pin};
+use crate::gpio::AnyPin;
+use crate::pwm::simple_pwm::{Ch1, Ch2};
+use crate::pwm::{CaptureCompare16bitInstance, Channel1Pin, Channel2Pin};
+use crate::{interrupt, Peripheral};
+
+/// Interrupt handler, counting the overflows of the counter.
+pub struct InterruptHandler<T: CaptureCompare16bitInstance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: CaptureCompare16bitInstance> interrupt::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        if handle_update::<T>().is_some() {
+            T::state().waker.wake();
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Direction {
+    Upcounting,
+    Downcounting,
+}
+
+/// Inputs on which the counter counts.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EncoderMode {
+    /// Count on the edges of channel 1, two counts per cycle
+    Ch1Edges,
+    /// Count on the edges of channel 2, two counts per cycle
+    Ch2Edges,
+    /// Count on the edges of both channels, four counts per cycle
+    BothEdges,
+}
+
+#[non_exhaustive]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Config {
+    pub mode: EncoderMode,
+    /// Invert channel 1, which reverses the counting direction
+    pub invert_ch1: bool,
+    pub invert_ch2: bool,
+    /// Digital filter of the inputs, the ICxF value from the reference manual (0 to 15). The
+    /// input must be stable during up to 8 samples, at up to 1/32 of the timer clock.
+    pub filter: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            mode: EncoderMode::BothEdges,
+            invert_ch1: false,
+            invert_ch2: false,
+            filter: 0,
+        }
+    }
+}
+
+pub struct QeiPin<'d, Perip, Channel> {
+    _pin: PeripheralRef<'d, AnyPin>,
+    phantom: PhantomData<(Perip, Channel)>,
+}
+
+macro_rules! channel_impl {
+    ($new_chx:ident, $channel:ident, $pin_trait:ident) => {
+        impl<'d, Perip: CaptureCompare16bitInstance> QeiPin<'d, Perip, $channel> {
+            pub fn $new_chx(pin: impl Peripheral<P = impl $pin_trait<Perip>> + 'd) -> Self {
+                into_ref!(pin);
+                critical_section::with(|_| unsafe {
+                    pin.set_as_af(pin.af_num(), AFType::Input);
+                    #[cfg(gpio_v2)]
+                    pin.set_speed(crate::gpio::Speed::VeryHigh);
+                });
+                QeiPin {
+                    _pin: pin.map_into(),
+                    phantom: PhantomData,
+                }
+            }
+        }
+    };
+}
+
+channel_impl!(new_ch1, Ch1, Channel1Pin);
+channel_impl!(new_ch2, Ch2, Channel2Pin);
+
+/// Quadrature encoder interface.
+///
+/// The timer counts the edges of the encoder signals in hardware, and its overflows are
+/// counted in the interrupt handler, so the position can be read as a 64 bit value.
+pub struct Qei<'d, T: CaptureCompare16bitInstance> {
+    _inner: PeripheralRef<'d, T>,
+}
+
+impl<'d, T: CaptureCompare16bitInstance> Qei<'d, T> {
+    pub fn new(
+        tim: impl Peripheral<P = T> + 'd,
+        _ch1: QeiPin<'d, T, Ch1>,
+        _ch2: QeiPin<'d, T, Ch2>,
+        _irq: impl interrupt::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        config: Config,
+    ) -> Self {
+        into_ref!(tim);
+        assert!(config.filter <= 15);
+
+        T::enable();
+        <T as crate::rcc::sealed::RccPeripheral>::reset();
+
+        let r = T::regs_gp16();
+        unsafe {
+            r.ccmr_input(0).modify(|w| {
+                // IC1 on TI1 and IC2 on TI2
+                w.set_ccs(0, vals::CcmrInputCcs::TI4);
+                w.set_ccs(1, vals::CcmrInputCcs::TI4);
+                w.set_icf(0, vals::Icf(config.filter));
+                w.set_icf(1, vals::Icf(config.filter));
+            });
+            r.ccer().modify(|w| {
+                w.set_ccp(0, config.invert_ch1);
+                w.set_ccp(1, config.invert_ch2);
+            });
+            r.smcr().modify(|w| {
+                w.set_sms(match config.mode {
+                    EncoderMode::Ch2Edges => vals::Sms::ENCODER_MODE_1,
+                    EncoderMode::Ch1Edges => vals::Sms::ENCODER_MODE_2,
+                    EncoderMode::BothEdges => vals::Sms::ENCODER_MODE_3,
+                });
+            });
+            r.arr().write(|w| w.set_arr(u16::MAX));
+
+            r.sr().modify(|w| w.set_uif(false));
+            r.dier().modify(|w| w.set_uie(true));
+            r.cr1().modify(|w| w.set_cen(true));
+        }
+
+        T::state().overflows.store(0, Ordering::Relaxed);
+
+        unsafe { T::Interrupt::steal() }.unpend();
+        unsafe { T::Interrupt::steal() }.enable();
+
+        Self { _inner: tim }
+    }
+
+    /// Raw value of the 16 bit counter.
+    pub fn count(&self) -> u16 {
+        unsafe { T::regs_gp16().cnt().read().cnt() }
+    }
+
+    /// Direction in which the encoder last moved.
+    pub fn read_direction(&self) -> Direction {
+        match unsafe { T::regs_gp16().cr1().read().dir() } {
+            vals::Dir::DOWN => Direction::Downcounting,
+            _ => Direction::Upcounting,
+        }
+    }
+
+    /// Position of the encoder, in counts since it was created or [`Qei::reset`].
+    pub fn position(&self) -> i64 {
+        let r = T::regs_gp16();
+        critical_section::with(|_| loop {
+            // Account for an overflow the interrupt handler did not handle yet
+            handle_update::<T>();
+
+            let count = unsafe { r.cnt().read().cnt() };
+            if !unsafe { r.sr().read().uif() } {
+                let overflows = T::state().overflows.load(Ordering::Relaxed);
+                return ((overflows as i64) << 16) + count as i64;
+            }
+        })
+    }
+
+    /// Set the position to 0.
+    pub fn reset(&mut self) {
+        let r = T::regs_gp16();
+        critical_section::with(|_| unsafe {
+            r.cnt().write(|w| w.set_cnt(0));
+            r.sr().modify(|w| w.set_uif(false));
+            T::state().overflows.store(0, Ordering::Relaxed);
+        });
+    }
+
+    /// Wait for the counter to overflow or underflow, which happens every 65536 counts, e.g.
+    /// to update a position kept at a coarser resolution.
+    pub async fn wait_for_overflow(&mut self) -> Direction {
+        let s = T::state();
+        let start = s.overflows.load(Ordering::Relaxed);
+        poll_fn(|cx| {
+            s.waker.register(cx.waker());
+
+            match s.overflows.load(Ordering::Relaxed).wrapping_sub(start) {
+                0 => Poll::Pending,
+                diff if diff > 0 => Poll::Ready(Direction::Upcounting),
+                _ => Poll::Ready(Direction::Downcounting),
+            }
+        })
+        .await
+    }
+}
+
+impl<'d, T: CaptureCompare16bitInstance> Drop for Qei<'d, T> {
+    fn drop(&mut self) {
+        let r = T::regs_gp16();
+        unsafe {
+            r.cr1().modify(|w| w.set_cen(false));
+            r.dier().modify(|w| w.set_uie(false));
+            r.smcr().modify(|w| w.set_sms(vals::Sms::DISABLED));
+        }
+    }
+}
+
+/// Count a pending overflow or underflow of the counter, if any.
+fn handle_update<T: CaptureCompare16bitInstance>() -> Option<Direction> {
+    let r = T::regs_gp16();
+    unsafe {
+        if !r.sr().read().uif() {
+            return None;
+        }
+        r.sr().modify(|w| w.set_uif(false));
+
+        // The counter wraps to 0 when counting up, and to ARR when counting down.
+        let direction = match r.cnt().read().cnt() < 0x8000 {
+            true => Direction::Upcounting,
+            false => Direction::Downcounting,
+        };
+
+        let overflows = &T::state().overflows;
+        let value = overflows.load(Ordering::Relaxed);
+        overflows.store(
+            match direction {
+                Direction::Upcounting => value.wrapping_add(1),
+                Direction::Downcounting => value.wrapping_sub(1),
+            },
+            Ordering::Relaxed,
+        );
+
+        Some(direction)
+    }
+}
@@ -0,0 +1,181 @@
+/// Maximum size of legacy advertising or scan response data
+pub const MAX_ADVERTISING_DATA_LEN: usize = 31;
+
+const AD_TYPE_FLAGS: u8 = 0x01;
+const AD_TYPE_COMPLETE_16_BIT_UUIDS: u8 = 0x03;
+const AD_TYPE_COMPLETE_128_BIT_UUIDS: u8 = 0x07;
+const AD_TYPE_COMPLETE_LOCAL_NAME: u8 = 0x09;
+const AD_TYPE_MANUFACTURER_SPECIFIC_DATA: u8 = 0xFF;
+
+/// Error returned if an AD structure doesn't fit into the advertising data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AdvertisingDataTooLong;
+
+/// UUID of a GATT service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Uuid {
+    /// 16-bit UUID assigned by the Bluetooth SIG
+    Uuid16(u16),
+    /// 128-bit UUID, in little-endian byte order
+    Uuid128([u8; 16]),
+}
+
+/// Builder for advertising and scan response data.
+///
+/// Every method appends an AD structure (length, type, data) and fails if the data would exceed the
+/// 31 bytes available for legacy advertising, leaving the data unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdvertisingData {
+    buf: [u8; MAX_ADVERTISING_DATA_LEN],
+    len: usize,
+}
+
+impl AdvertisingData {
+    /// LE limited discoverable mode
+    pub const FLAG_LE_LIMITED_DISCOVERABLE: u8 = 0x01;
+    /// LE general discoverable mode
+    pub const FLAG_LE_GENERAL_DISCOVERABLE: u8 = 0x02;
+    /// BR/EDR not supported
+    pub const FLAG_BR_EDR_NOT_SUPPORTED: u8 = 0x04;
+
+    /// Create empty advertising data
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; MAX_ADVERTISING_DATA_LEN],
+            len: 0,
+        }
+    }
+
+    /// Add the flags AD structure, see the `FLAG_*` constants
+    pub fn add_flags(self, flags: u8) -> Result<Self, AdvertisingDataTooLong> {
+        self.add(AD_TYPE_FLAGS, &[flags])
+    }
+
+    /// Add the complete local name of the device
+    pub fn add_complete_local_name(self, name: &str) -> Result<Self, AdvertisingDataTooLong> {
+        self.add(AD_TYPE_COMPLETE_LOCAL_NAME, name.as_bytes())
+    }
+
+    /// Add a service UUID to the complete list of service UUIDs of its size.
+    ///
+    /// The first UUID of each size creates the list, following UUIDs of the same size are appended to it.
+    pub fn add_service_uuid(self, uuid: Uuid) -> Result<Self, AdvertisingDataTooLong> {
+        let bytes;
+        let (ty, data) = match uuid {
+            Uuid::Uuid16(uuid) => {
+                bytes = uuid.to_le_bytes();
+                (AD_TYPE_COMPLETE_16_BIT_UUIDS, &bytes[..])
+            }
+            Uuid::Uuid128(ref uuid) => (AD_TYPE_COMPLETE_128_BIT_UUIDS, &uuid[..]),
+        };
+
+        match self.find(ty) {
+            Some(pos) => self.extend(pos, data),
+            None => self.add(ty, data),
+        }
+    }
+
+    /// Add manufacturer specific data, prefixed by the company identifier assigned by the Bluetooth SIG
+    pub fn add_manufacturer_data(self, company_id: u16, data: &[u8]) -> Result<Self, AdvertisingDataTooLong> {
+        let this = self.add(AD_TYPE_MANUFACTURER_SPECIFIC_DATA, &company_id.to_le_bytes())?;
+        this.extend(this.len - 4, data)
+    }
+
+    /// The encoded advertising data
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    fn add(mut self, ty: u8, data: &[u8]) -> Result<Self, AdvertisingDataTooLong> {
+        if self.len + 2 + data.len() > MAX_ADVERTISING_DATA_LEN {
+            return Err(AdvertisingDataTooLong);
+        }
+
+        self.buf[self.len] = data.len() as u8 + 1;
+        self.buf[self.len + 1] = ty;
+        self.buf[self.len + 2..][..data.len()].copy_from_slice(data);
+        self.len += 2 + data.len();
+
+        Ok(self)
+    }
+
+    /// Append `data` to the AD structure starting at `pos`
+    fn extend(mut self, pos: usize, data: &[u8]) -> Result<Self, AdvertisingDataTooLong> {
+        if self.len + data.len() > MAX_ADVERTISING_DATA_LEN {
+            return Err(AdvertisingDataTooLong);
+        }
+
+        let end = pos + 1 + self.buf[pos] as usize;
+        self.buf.copy_within(end..self.len, end + data.len());
+        self.buf[end..][..data.len()].copy_from_slice(data);
+        self.buf[pos] += data.len() as u8;
+        self.len += data.len();
+
+        Ok(self)
+    }
+
+    /// Position of the first AD structure of type `ty`
+    fn find(&self, ty: u8) -> Option<usize> {
+        let mut pos = 0;
+        while pos < self.len {
+            if self.buf[pos + 1] == ty {
+                return Some(pos);
+            }
+            pos += 1 + self.buf[pos] as usize;
+        }
+        None
+    }
+}
+
+impl Default for AdvertisingData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_encode_ad_structures() {
+        let data = AdvertisingData::new()
+            .add_flags(AdvertisingData::FLAG_LE_GENERAL_DISCOVERABLE | AdvertisingData::FLAG_BR_EDR_NOT_SUPPORTED)
+            .unwrap()
+            .add_service_uuid(Uuid::Uuid16(0x180D))
+            .unwrap()
+            .add_complete_local_name("embassy")
+            .unwrap()
+            .add_service_uuid(Uuid::Uuid16(0x180F))
+            .unwrap()
+            .add_manufacturer_data(0x0030, &[0xAB])
+            .unwrap();
+
+        assert_eq!(
+            &[
+                0x02, 0x01, 0x06, // flags
+                0x05, 0x03, 0x0D, 0x18, 0x0F, 0x18, // 16-bit service UUIDs
+                0x08, 0x09, b'e', b'm', b'b', b'a', b's', b's', b'y', // name
+                0x04, 0xFF, 0x30, 0x00, 0xAB, // manufacturer data
+            ],
+            data.as_bytes()
+        );
+    }
+
+    #[test]
+    fn enforces_length_limit() {
+        let data = AdvertisingData::new()
+            .add_complete_local_name("a name of 27 characters....")
+            .unwrap();
+        assert_eq!(29, data.as_bytes().len());
+
+        assert_eq!(Err(AdvertisingDataTooLong), data.add_flags(0x06));
+        assert_eq!(Err(AdvertisingDataTooLong), data.add_manufacturer_data(0x0030, &[]));
+        assert_eq!(
+            Err(AdvertisingDataTooLong),
+            AdvertisingData::new().add_complete_local_name("a name of 30 characters.......")
+        );
+    }
+}
@@ -1,14 +1,10 @@
 use core::mem::MaybeUninit;
 
-use embassy_futures::block_on;
-
-use super::cmd::CmdSerial;
+use super::cmd::{AclDataSerial, CmdSerial};
 use super::consts::TlPacketType;
-use super::evt::EvtBox;
 use super::unsafe_linked_list::LinkedListNode;
 use super::{
-    channels, BleTable, BLE_CMD_BUFFER, CS_BUFFER, EVT_QUEUE, HCI_ACL_DATA_BUFFER, TL_BLE_TABLE, TL_CHANNEL,
-    TL_REF_TABLE,
+    channels, BleTable, BLE_CMD_BUFFER, CS_BUFFER, EVT_QUEUE, HCI_ACL_DATA_BUFFER, TL_BLE_TABLE, TL_REF_TABLE,
 };
 use crate::ipcc::Ipcc;
 use crate::tl_mbox::cmd::CmdPacket;
@@ -34,21 +30,7 @@ impl Ble {
     }
 
     pub(crate) fn evt_handler(ipcc: &mut Ipcc) {
-        unsafe {
-            let mut node_ptr = core::ptr::null_mut();
-            let node_ptr_ptr: *mut _ = &mut node_ptr;
-
-            while !LinkedListNode::is_empty(EVT_QUEUE.as_mut_ptr()) {
-                LinkedListNode::remove_head(EVT_QUEUE.as_mut_ptr(), node_ptr_ptr);
-
-                let event = node_ptr.cast();
-                let event = EvtBox::new(event);
-
-                block_on(TL_CHANNEL.send(event));
-            }
-        }
-
-        ipcc.c1_clear_flag_channel(channels::cpu2::IPCC_BLE_EVENT_CHANNEL);
+        unsafe { super::forward_events(ipcc, EVT_QUEUE.as_mut_ptr(), channels::cpu2::IPCC_BLE_EVENT_CHANNEL) }
     }
 
     pub(crate) fn send_cmd(ipcc: &mut Ipcc, buf: &[u8]) {
@@ -65,4 +47,19 @@ impl Ble {
 
         ipcc.c1_set_flag_channel(channels::cpu1::IPCC_BLE_CMD_CHANNEL);
     }
+
+    /// `buf` contains the whole ACL data packet, including the packet type and the ACL header
+    pub(crate) fn send_acl_data(ipcc: &mut Ipcc, buf: &[u8]) {
+        unsafe {
+            let pacl_buffer = (*TL_REF_TABLE.assume_init().ble_table).phci_acl_data_buffer;
+            let pacl_serial: *mut AclDataSerial = &mut (*pacl_buffer).acl_data_serial;
+            let pacl_serial_buf: *mut u8 = pacl_serial.cast();
+
+            core::ptr::copy(buf.as_ptr(), pacl_serial_buf, buf.len());
+
+            (*pacl_serial).ty = TlPacketType::AclData as u8;
+        }
+
+        ipcc.c1_set_flag_channel(channels::cpu1::IPCC_HCI_ACL_DATA_CHANNEL);
+    }
 }
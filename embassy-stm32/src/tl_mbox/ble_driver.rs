@@ -0,0 +1,186 @@
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_sync::mutex::Mutex;
+use embassy_sync::signal::Signal;
+
+use super::consts::{self, TlPacketType};
+use super::evt::EvtBox;
+use super::view::EvtView;
+use super::{channels, TlMbox, TlMboxError};
+use crate::ipcc::Ipcc;
+
+/// Maximum ACL payload accepted by the HCI ACL data buffer of CPU2
+const ACL_MAX_PAYLOAD: usize = 251;
+
+/// Packet received from the BLE controller
+pub enum BlePacket {
+    /// HCI event, see [`EvtBox::evt_view`]
+    Event(EvtBox),
+    /// ACL data, see [`EvtBox::acl_data_view`]
+    AclData(EvtBox),
+}
+
+/// Response of the controller to a command sent with [`Ble::send_command`]
+pub struct CommandComplete {
+    evt: EvtBox,
+}
+
+impl CommandComplete {
+    /// Opcode of the completed command
+    pub fn opcode(&self) -> u16 {
+        let (_, opcode, _) = self.parse();
+        opcode
+    }
+
+    /// HCI status code, 0 on success
+    pub fn status(&self) -> u8 {
+        let (status, _, _) = self.parse();
+        status
+    }
+
+    /// Return parameters of the command, following the status.
+    ///
+    /// Empty for commands the controller answers with a command status event. The outcome of these
+    /// commands is reported by a later event, see [`Ble::read`].
+    pub fn return_parameters(&self) -> &[u8] {
+        let (_, _, params) = self.parse();
+        params
+    }
+
+    fn parse(&self) -> (u8, u16, &[u8]) {
+        // validated when the response was received
+        self.evt
+            .evt_view()
+            .ok()
+            .and_then(|evt| command_response(&evt))
+            .unwrap_or((0, 0, &[]))
+    }
+}
+
+/// Returns status, opcode and return parameters of a command complete or command status event
+fn command_response<'a>(evt: &EvtView<'a>) -> Option<(u8, u16, &'a [u8])> {
+    if evt.packet_type() != TlPacketType::BleEvt as u8 {
+        return None;
+    }
+
+    let payload = evt.payload();
+    match evt.evt_code() {
+        // num hci command packets, opcode, status, return parameters
+        consts::HCI_EVT_COMMAND_COMPLETE if payload.len() >= 4 => {
+            Some((payload[3], u16::from_le_bytes([payload[1], payload[2]]), &payload[4..]))
+        }
+        // status, num hci command packets, opcode
+        consts::HCI_EVT_COMMAND_STATUS if payload.len() >= 4 => {
+            Some((payload[0], u16::from_le_bytes([payload[2], payload[3]]), &[]))
+        }
+        _ => None,
+    }
+}
+
+/// HCI command and ACL data interface to the BLE controller on CPU2, to run a BLE host stack on CPU1.
+///
+/// Packets are only taken from the [`TlMbox`] by [`Ble::read`], so CPU2 is held back while the host
+/// stack doesn't read. [`Ble::send_command`] waits for the completion of the command to be read, so
+/// [`Ble::read`] has to run concurrently, e.g. with [`join`](embassy_futures::join::join) or from
+/// another task. Events received on the system channel are discarded.
+pub struct Ble<'a, 'd> {
+    _mbox: &'a TlMbox,
+    ipcc: Mutex<NoopRawMutex, &'a mut Ipcc<'d>>,
+    /// Held while a command waits for its completion, only one command is sent at a time
+    command: Mutex<NoopRawMutex, ()>,
+    /// Opcode of the command waiting for its completion
+    opcode: Cell<Option<u16>>,
+    completion: Signal<NoopRawMutex, EvtBox>,
+}
+
+impl<'a, 'd> Ble<'a, 'd> {
+    /// Create the driver, which exchanges the packets with CPU2 through the mailbox `mbox` and `ipcc`.
+    pub fn new(mbox: &'a TlMbox, ipcc: &'a mut Ipcc<'d>) -> Self {
+        Self {
+            _mbox: mbox,
+            ipcc: Mutex::new(ipcc),
+            command: Mutex::new(()),
+            opcode: Cell::new(None),
+            completion: Signal::new(),
+        }
+    }
+
+    /// Sends a HCI command and waits for the command complete or command status event answering it.
+    ///
+    /// The answer is received by [`Ble::read`], which doesn't return it. Concurrent commands are sent
+    /// one after the other.
+    pub async fn send_command(&self, opcode: u16, params: &[u8]) -> Result<CommandComplete, TlMboxError> {
+        if params.len() > 255 {
+            return Err(TlMboxError::InvalidPacket);
+        }
+
+        let mut buf = [0u8; 4 + 255];
+        let [opcode_lo, opcode_hi] = opcode.to_le_bytes();
+        buf[0] = TlPacketType::BleCmd as u8;
+        buf[1] = opcode_lo;
+        buf[2] = opcode_hi;
+        buf[3] = params.len() as u8;
+        buf[4..][..params.len()].copy_from_slice(params);
+
+        let _command = self.command.lock().await;
+        {
+            let mut ipcc = self.ipcc.lock().await;
+            // wait for CPU2 to release the buffer of the previous command
+            ipcc.flush(channels::cpu1::IPCC_BLE_CMD_CHANNEL).await;
+
+            // the completion of a cancelled command may have been received
+            self.completion.reset();
+            self.opcode.set(Some(opcode));
+            super::ble::Ble::send_cmd(&mut ipcc, &buf[..4 + params.len()]);
+        }
+
+        let evt = self.completion.wait().await;
+        Ok(CommandComplete { evt })
+    }
+
+    /// Sends ACL data to the connection `handle`. `flags` are the packet boundary and broadcast flags.
+    pub async fn send_acl_data(&self, handle: u16, flags: u8, data: &[u8]) -> Result<(), TlMboxError> {
+        if data.len() > ACL_MAX_PAYLOAD || handle > 0x0FFF || flags > 0x0F {
+            return Err(TlMboxError::InvalidPacket);
+        }
+
+        let mut buf = [0u8; 5 + ACL_MAX_PAYLOAD];
+        buf[0] = TlPacketType::AclData as u8;
+        buf[1..3].copy_from_slice(&(handle | (flags as u16) << 12).to_le_bytes());
+        buf[3..5].copy_from_slice(&(data.len() as u16).to_le_bytes());
+        buf[5..][..data.len()].copy_from_slice(data);
+
+        let mut ipcc = self.ipcc.lock().await;
+        // wait for CPU2 to release the buffer of the previous packet
+        ipcc.flush(channels::cpu1::IPCC_HCI_ACL_DATA_CHANNEL).await;
+        super::ble::Ble::send_acl_data(&mut ipcc, &buf[..5 + data.len()]);
+
+        Ok(())
+    }
+
+    /// Waits for the next event or ACL data packet from the controller.
+    ///
+    /// The answers to the commands sent with [`Ble::send_command`] are passed to it instead.
+    pub async fn read(&self) -> BlePacket {
+        loop {
+            let evt = super::receive().await;
+
+            match TlPacketType::try_from(evt.packet_type()) {
+                Ok(TlPacketType::BleEvt) if evt.evt_view().is_ok() => {
+                    let response = evt.evt_view().ok().and_then(|evt| command_response(&evt));
+                    match (response, self.opcode.get()) {
+                        (Some((_, op, _)), Some(opcode)) if op == opcode => {
+                            self.opcode.set(None);
+                            self.completion.signal(evt);
+                        }
+                        _ => return BlePacket::Event(evt),
+                    }
+                }
+                Ok(TlPacketType::AclData) if evt.acl_data_view().is_ok() => return BlePacket::AclData(evt),
+                // system events and malformed packets
+                _ => {}
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use super::PacketError;
+
 #[derive(PartialEq)]
 #[repr(C)]
 pub enum TlPacketType {
@@ -25,7 +27,7 @@ pub enum TlPacketType {
 }
 
 impl TryFrom<u8> for TlPacketType {
-    type Error = ();
+    type Error = PacketError;
 
     fn try_from(value: u8) -> Result<Self, Self::Error> {
         match value {
@@ -47,7 +49,38 @@ impl TryFrom<u8> for TlPacketType {
             0x40 => Ok(TlPacketType::TracesApp),
             0x41 => Ok(TlPacketType::TracesWl),
 
-            _ => Err(()),
+            _ => Err(PacketError::UnknownPacketType(value)),
         }
     }
 }
+
+/// HCI event code of a command complete event
+pub const HCI_EVT_COMMAND_COMPLETE: u8 = 0x0E;
+/// HCI event code of a command status event
+pub const HCI_EVT_COMMAND_STATUS: u8 = 0x0F;
+
+/// HCI_READ_BD_ADDR, returns the public device address
+pub const HCI_READ_BD_ADDR: u16 = 0x1009;
+/// HCI_LE_SET_ADVERTISING_DATA
+pub const HCI_LE_SET_ADVERTISING_DATA: u16 = 0x2008;
+/// HCI_LE_SET_SCAN_RESPONSE_DATA
+pub const HCI_LE_SET_SCAN_RESPONSE_DATA: u16 = 0x2009;
+/// ACI_HAL_WRITE_CONFIG_DATA, writes a value into the configuration data of the BLE stack
+pub const ACI_HAL_WRITE_CONFIG_DATA: u16 = 0xFC0C;
+
+/// offset of the public address in the BLE stack configuration data
+pub const CONFIG_DATA_PUBADDR_OFFSET: u8 = 0x00;
+/// offset of the static random address in the BLE stack configuration data
+pub const CONFIG_DATA_RANDOM_ADDRESS_OFFSET: u8 = 0x2E;
+
+/// address of the 64-bit unique device identifier (UID64)
+pub const UID64_ADDRESS: usize = 0x1FFF_7580;
+
+/// SHCI_C2_FUS_GET_STATE, returns the state of the firmware upgrade service
+pub const SHCI_OPCODE_FUS_GET_STATE: u16 = 0xFC52;
+/// SHCI_C2_FUS_FW_UPGRADE, installs the image previously written to flash
+pub const SHCI_OPCODE_FUS_FW_UPGRADE: u16 = 0xFC54;
+/// SHCI_C2_FUS_FW_DELETE, deletes the wireless stack
+pub const SHCI_OPCODE_FUS_FW_DELETE: u16 = 0xFC55;
+/// SHCI_C2_FUS_START_WS, starts the wireless stack
+pub const SHCI_OPCODE_FUS_START_WS: u16 = 0xFC5A;
@@ -1,8 +1,7 @@
-use core::mem::MaybeUninit;
+use core::mem::{self, MaybeUninit};
 
-use super::cmd::{AclDataPacket, AclDataSerial};
-use super::consts::TlPacketType;
-use super::{PacketHeader, TL_EVT_HEADER_SIZE};
+use super::view::{self, AclDataView, EvtView, PacketError};
+use super::{consts, PacketHeader, TL_BLE_EVENT_FRAME_SIZE};
 use crate::tl_mbox::mm;
 
 /// the payload of [`Evt`] for a command status event
@@ -19,7 +18,8 @@ pub struct CsEvt {
 #[repr(C, packed)]
 pub struct CcEvt {
     pub num_cmd: u8,
-    pub cmd_code: u8,
+    pub cmd_code: u16,
+    /// status followed by the return parameters
     pub payload: [u8; 1],
 }
 
@@ -64,7 +64,16 @@ impl EvtBox {
         Self { ptr }
     }
 
-    /// Copies the event data from inner pointer and returns and event structure
+    /// Returns the packet without releasing it to CPU2
+    pub(super) fn into_raw(self) -> *mut EvtPacket {
+        let ptr = self.ptr;
+        core::mem::forget(self);
+        ptr
+    }
+
+    /// Copies the event data from inner pointer and returns and event structure.
+    ///
+    /// See [`EvtBox::evt_view`] and [`EvtBox::payload`] to access the event without copying it.
     pub fn evt(&self) -> EvtPacket {
         let mut evt = MaybeUninit::uninit();
         unsafe {
@@ -73,59 +82,82 @@ impl EvtBox {
         }
     }
 
-    /// Returns the size of a buffer required to hold this event
-    pub fn size(&self) -> Result<usize, ()> {
+    /// The received packet, starting at the packet type.
+    ///
+    /// The slice covers the whole event buffer provided to CPU2, so the length fields of the packet can be
+    /// checked against it.
+    fn frame(&self) -> &[u8] {
         unsafe {
-            let evt_kind = TlPacketType::try_from((*self.ptr).evt_serial.kind)?;
+            let evt_serial: *const u8 = core::ptr::addr_of!((*self.ptr).evt_serial).cast();
+            core::slice::from_raw_parts(evt_serial, TL_BLE_EVENT_FRAME_SIZE)
+        }
+    }
 
-            if evt_kind == TlPacketType::AclData {
-                let acl_data: *const AclDataPacket = self.ptr.cast();
-                let acl_serial: *const AclDataSerial = &(*acl_data).acl_data_serial;
+    /// Returns the packet type, e.g. `0x04` for BLE events, `0x02` for ACL data and `0x12` for system events
+    pub fn packet_type(&self) -> u8 {
+        self.frame()[0]
+    }
+
+    /// Returns a view of the event, or an error if the packet is no event or malformed
+    pub fn evt_view(&self) -> Result<EvtView<'_>, PacketError> {
+        EvtView::parse(self.frame())
+    }
 
-                Ok((*acl_serial).length as usize + 5)
-            } else {
-                let evt_data: *const EvtPacket = self.ptr.cast();
-                let evt_serial: *const EvtSerial = &(*evt_data).evt_serial;
+    /// Returns a view of the ACL data, or an error if the packet is no ACL data or malformed
+    pub fn acl_data_view(&self) -> Result<AclDataView<'_>, PacketError> {
+        AclDataView::parse(self.frame())
+    }
+
+    /// Returns the event code, or `None` if the packet is no event or malformed
+    pub fn evt_code(&self) -> Option<u8> {
+        self.evt_view().ok().map(|evt| evt.evt_code())
+    }
 
-                Ok((*evt_serial).evt.payload_len as usize + TL_EVT_HEADER_SIZE)
-            }
+    /// Returns the payload of the event or the data of the ACL data packet, without copying it out of the
+    /// shared buffer. Empty if the packet is malformed.
+    pub fn payload(&self) -> &[u8] {
+        match self.acl_data_view() {
+            Ok(acl) => acl.data(),
+            Err(_) => self.evt_view().map(|evt| evt.payload()).unwrap_or(&[]),
         }
     }
 
-    /// writes an underlying [`EvtPacket`] into the provided buffer. Returns the number of bytes that were
-    /// written. Returns an error if event kind is unkown or if provided buffer size is not enough
-    pub fn copy_into_slice(&self, buf: &mut [u8]) -> Result<usize, ()> {
-        unsafe {
-            let evt_kind = TlPacketType::try_from((*self.ptr).evt_serial.kind)?;
+    /// Returns the payload of a command status event
+    pub fn cs_evt(&self) -> Option<&CsEvt> {
+        let payload = self.typed_payload(consts::HCI_EVT_COMMAND_STATUS, mem::size_of::<CsEvt>())?;
 
-            let evt_data: *const EvtPacket = self.ptr.cast();
-            let evt_serial: *const EvtSerial = &(*evt_data).evt_serial;
-            let evt_serial_buf: *const u8 = evt_serial.cast();
+        // safety: CsEvt is packed and the payload is long enough
+        Some(unsafe { &*payload.as_ptr().cast() })
+    }
 
-            let acl_data: *const AclDataPacket = self.ptr.cast();
-            let acl_serial: *const AclDataSerial = &(*acl_data).acl_data_serial;
-            let acl_serial_buf: *const u8 = acl_serial.cast();
+    /// Returns the payload of a command complete event. The status and the return parameters start at
+    /// offset 3 of [`EvtBox::payload`].
+    pub fn cc_evt(&self) -> Option<&CcEvt> {
+        let payload = self.typed_payload(consts::HCI_EVT_COMMAND_COMPLETE, mem::size_of::<CcEvt>())?;
 
-            if let TlPacketType::AclData = evt_kind {
-                let len = (*acl_serial).length as usize + 5;
-                if len > buf.len() {
-                    return Err(());
-                }
+        // safety: CcEvt is packed and the payload is long enough
+        Some(unsafe { &*payload.as_ptr().cast() })
+    }
 
-                core::ptr::copy(evt_serial_buf, buf.as_mut_ptr(), len);
+    fn typed_payload(&self, evt_code: u8, min_len: usize) -> Option<&[u8]> {
+        let evt = self.evt_view().ok()?;
+        (evt.evt_code() == evt_code && evt.payload().len() >= min_len).then(|| evt.payload())
+    }
 
-                Ok(len)
-            } else {
-                let len = (*evt_serial).evt.payload_len as usize + TL_EVT_HEADER_SIZE;
-                if len > buf.len() {
-                    return Err(());
-                }
+    /// Returns the size of a buffer required to hold this event
+    pub fn size(&self) -> Result<usize, PacketError> {
+        view::packet(self.frame()).map(|packet| packet.len())
+    }
 
-                core::ptr::copy(acl_serial_buf, buf.as_mut_ptr(), len);
+    /// writes an underlying [`EvtPacket`] into the provided buffer. Returns the number of bytes that were
+    /// written. Returns an error if event kind is unkown or if provided buffer size is not enough
+    pub fn copy_into_slice(&self, buf: &mut [u8]) -> Result<usize, PacketError> {
+        let packet = view::packet(self.frame())?;
+        buf.get_mut(..packet.len())
+            .ok_or(PacketError::BufferTooSmall)?
+            .copy_from_slice(packet);
 
-                Ok(len)
-            }
-        }
+        Ok(packet.len())
     }
 }
 
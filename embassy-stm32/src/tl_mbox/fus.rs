@@ -0,0 +1,102 @@
+//! Commands of the firmware upgrade service (FUS) running on CPU2
+
+use super::{consts, TlMbox, TlMboxError};
+use crate::ipcc::Ipcc;
+
+/// State of the firmware upgrade service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FusState {
+    /// No operation ongoing
+    Idle,
+    /// Upgrade of the wireless stack ongoing
+    FirmwareUpgradeOngoing,
+    /// Upgrade of the FUS itself ongoing
+    FusUpgradeOngoing,
+    /// Service ongoing, e.g. the deletion of the wireless stack
+    ServiceOngoing,
+    /// The last operation failed, see [`FusStatus::error_code`]
+    Error,
+    /// State value not known to this driver
+    Unknown(u8),
+}
+
+impl From<u8> for FusState {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => FusState::Idle,
+            0x10..=0x1F => FusState::FirmwareUpgradeOngoing,
+            0x20..=0x2F => FusState::FusUpgradeOngoing,
+            0x30..=0x3F => FusState::ServiceOngoing,
+            0xFF => FusState::Error,
+            _ => FusState::Unknown(value),
+        }
+    }
+}
+
+/// Response to [`TlMbox::fus_get_state`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FusStatus {
+    pub state: FusState,
+    /// Error code of the last operation, e.g. 1 if no image was found or 3 if the image is not authentic.
+    /// 0 if no error occurred.
+    pub error_code: u8,
+}
+
+impl TlMbox {
+    /// Returns the state of the FUS.
+    ///
+    /// If the wireless stack is running, the first call makes CPU2 reboot into the FUS and the returned
+    /// state is not meaningful. Call it again once CPU2 reports that the FUS is ready.
+    pub async fn fus_get_state(&self, ipcc: &mut Ipcc<'_>) -> Result<FusStatus, TlMboxError> {
+        let mut ret = [0u8; 1];
+        let (state, n) = self
+            .sys_cmd(ipcc, consts::SHCI_OPCODE_FUS_GET_STATE, &[], &mut ret)
+            .await?;
+
+        Ok(FusStatus {
+            state: state.into(),
+            error_code: if n > 0 { ret[0] } else { 0 },
+        })
+    }
+
+    /// Starts the installation of the wireless stack or FUS image previously written to flash.
+    ///
+    /// CPU2 reboots several times during the upgrade, poll [`TlMbox::fus_get_state`] to follow it.
+    pub async fn fus_upgrade_firmware(&self, ipcc: &mut Ipcc<'_>) -> Result<(), TlMboxError> {
+        self.fus_cmd(ipcc, consts::SHCI_OPCODE_FUS_FW_UPGRADE).await
+    }
+
+    /// Deletes the wireless stack installed on CPU2.
+    pub async fn fus_delete_wireless_stack(&self, ipcc: &mut Ipcc<'_>) -> Result<(), TlMboxError> {
+        self.fus_cmd(ipcc, consts::SHCI_OPCODE_FUS_FW_DELETE).await
+    }
+
+    /// Makes CPU2 leave the FUS and start the installed wireless stack.
+    pub async fn fus_start_wireless_stack(&self, ipcc: &mut Ipcc<'_>) -> Result<(), TlMboxError> {
+        self.fus_cmd(ipcc, consts::SHCI_OPCODE_FUS_START_WS).await
+    }
+
+    async fn fus_cmd(&self, ipcc: &mut Ipcc<'_>, opcode: u16) -> Result<(), TlMboxError> {
+        match self.sys_cmd(ipcc, opcode, &[], &mut []).await? {
+            (0, _) => Ok(()),
+            (status, _) => Err(TlMboxError::CommandFailed(status)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_decode_fus_state() {
+        assert_eq!(FusState::Idle, FusState::from(0x00));
+        assert_eq!(FusState::FirmwareUpgradeOngoing, FusState::from(0x12));
+        assert_eq!(FusState::FusUpgradeOngoing, FusState::from(0x2F));
+        assert_eq!(FusState::ServiceOngoing, FusState::from(0x30));
+        assert_eq!(FusState::Error, FusState::from(0xFF));
+        assert_eq!(FusState::Unknown(0x05), FusState::from(0x05));
+    }
+}
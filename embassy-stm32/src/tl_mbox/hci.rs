@@ -0,0 +1,183 @@
+use embassy_futures::yield_now;
+
+use super::ble::Ble;
+use super::consts::TlPacketType;
+use super::{channels, TlMbox, TlMboxError, TL_EVT_HEADER_SIZE};
+use crate::ipcc::Ipcc;
+
+/// Size of the command header following the packet type: opcode (2), parameter length (1)
+const CMD_HEADER_SIZE: usize = 3;
+/// Size of the ACL header following the packet type: handle (2), data length (2)
+const ACL_HEADER_SIZE: usize = 4;
+/// Maximum ACL payload accepted by the HCI ACL data buffer of CPU2
+const ACL_MAX_PAYLOAD: usize = 251;
+
+/// HCI transport to the BLE controller on CPU2, using the H4 (UART) framing.
+///
+/// Every packet is preceded by a one byte packet type: `0x01` for commands, `0x02` for ACL data
+/// and `0x04` for events. Commands and ACL data written to the transport are forwarded to CPU2
+/// once complete, events and ACL data received from CPU2 are read back in the same framing.
+/// This allows to connect the controller to any host stack speaking H4, e.g. through a UART or
+/// a USB CDC-ACM class.
+///
+/// Events received on the system channel are not part of the HCI stream and are discarded.
+pub struct HciTransport<'a, 'd> {
+    _mbox: &'a TlMbox,
+    ipcc: &'a mut Ipcc<'d>,
+
+    tx_buf: [u8; 1 + CMD_HEADER_SIZE + 255],
+    tx_len: usize,
+
+    rx_buf: [u8; TL_EVT_HEADER_SIZE + 255],
+    rx_pos: usize,
+    rx_len: usize,
+}
+
+impl<'a, 'd> HciTransport<'a, 'd> {
+    /// Create the transport, which exchanges the packets with CPU2 through the mailbox `mbox` and `ipcc`.
+    pub fn new(mbox: &'a TlMbox, ipcc: &'a mut Ipcc<'d>) -> Self {
+        Self {
+            _mbox: mbox,
+            ipcc,
+            tx_buf: [0; 1 + CMD_HEADER_SIZE + 255],
+            tx_len: 0,
+            rx_buf: [0; TL_EVT_HEADER_SIZE + 255],
+            rx_pos: 0,
+            rx_len: 0,
+        }
+    }
+
+    /// Reads bytes of the H4 stream received from the controller.
+    ///
+    /// Waits until a packet is available if no bytes are pending.
+    pub async fn read(&mut self, buf: &mut [u8]) -> Result<usize, TlMboxError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        while self.rx_pos == self.rx_len {
+            let event = super::receive().await;
+
+            match TlPacketType::try_from(event.packet_type()) {
+                Ok(TlPacketType::BleEvt) | Ok(TlPacketType::AclData) => {}
+                _ => continue,
+            }
+
+            // the kind byte of the event is the H4 packet type
+            self.rx_len = event
+                .copy_into_slice(&mut self.rx_buf)
+                .map_err(|_| TlMboxError::InvalidPacket)?;
+            self.rx_pos = 0;
+        }
+
+        let n = buf.len().min(self.rx_len - self.rx_pos);
+        buf[..n].copy_from_slice(&self.rx_buf[self.rx_pos..][..n]);
+        self.rx_pos += n;
+
+        Ok(n)
+    }
+
+    /// Writes bytes of the H4 stream to the controller.
+    ///
+    /// Packets are forwarded to CPU2 as soon as they are complete. Returns the number of bytes consumed,
+    /// which is less than `buf.len()` if `buf` contains the end of a packet followed by more data.
+    pub async fn write(&mut self, buf: &[u8]) -> Result<usize, TlMboxError> {
+        let mut written = 0;
+
+        while written < buf.len() {
+            let needed = self.packet_len()? - self.tx_len;
+            let n = needed.min(buf.len() - written);
+
+            self.tx_buf[self.tx_len..][..n].copy_from_slice(&buf[written..][..n]);
+            self.tx_len += n;
+            written += n;
+
+            if self.tx_len == self.packet_len()? {
+                self.send_packet().await;
+                return Ok(written);
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// Returns the length of the packet in `tx_buf`, including the packet type. As long as the header is
+    /// incomplete, the length up to the end of the header is returned.
+    ///
+    /// Invalid packets are dropped, the host has to resynchronize the stream, e.g. by resetting the controller.
+    fn packet_len(&mut self) -> Result<usize, TlMboxError> {
+        if self.tx_len == 0 {
+            return Ok(1);
+        }
+
+        let (header_len, payload_len) = match TlPacketType::try_from(self.tx_buf[0]) {
+            Ok(TlPacketType::BleCmd) => (CMD_HEADER_SIZE, self.tx_buf[3] as usize),
+            Ok(TlPacketType::AclData) => (
+                ACL_HEADER_SIZE,
+                u16::from_le_bytes([self.tx_buf[3], self.tx_buf[4]]) as usize,
+            ),
+            _ => {
+                self.tx_len = 0;
+                return Err(TlMboxError::InvalidPacket);
+            }
+        };
+
+        if self.tx_len < 1 + header_len {
+            Ok(1 + header_len)
+        } else if self.tx_buf[0] == TlPacketType::AclData as u8 && payload_len > ACL_MAX_PAYLOAD {
+            self.tx_len = 0;
+            Err(TlMboxError::InvalidPacket)
+        } else {
+            Ok(1 + header_len + payload_len)
+        }
+    }
+
+    async fn send_packet(&mut self) {
+        let channel = if self.tx_buf[0] == TlPacketType::BleCmd as u8 {
+            channels::cpu1::IPCC_BLE_CMD_CHANNEL
+        } else {
+            channels::cpu1::IPCC_HCI_ACL_DATA_CHANNEL
+        };
+
+        // wait for CPU2 to release the buffer of the previous packet
+        while self.ipcc.c1_is_active_flag(channel) {
+            yield_now().await;
+        }
+
+        let packet = &self.tx_buf[..self.tx_len];
+        if self.tx_buf[0] == TlPacketType::BleCmd as u8 {
+            Ble::send_cmd(self.ipcc, packet);
+        } else {
+            Ble::send_acl_data(self.ipcc, packet);
+        }
+
+        self.tx_len = 0;
+    }
+}
+
+#[cfg(feature = "nightly")]
+mod eio {
+    use super::*;
+
+    impl embedded_io::Error for TlMboxError {
+        fn kind(&self) -> embedded_io::ErrorKind {
+            embedded_io::ErrorKind::Other
+        }
+    }
+
+    impl<'a, 'd> embedded_io::Io for HciTransport<'a, 'd> {
+        type Error = TlMboxError;
+    }
+
+    impl<'a, 'd> embedded_io::asynch::Read for HciTransport<'a, 'd> {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Self::read(self, buf).await
+        }
+    }
+
+    impl<'a, 'd> embedded_io::asynch::Write for HciTransport<'a, 'd> {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            Self::write(self, buf).await
+        }
+    }
+}
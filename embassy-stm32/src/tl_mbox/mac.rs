@@ -0,0 +1,96 @@
+use core::mem::MaybeUninit;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+
+use super::cmd::{CmdPacket, CmdSerial};
+use super::consts::TlPacketType;
+use super::{
+    channels, Mac802_15_4Table, MAC_802_15_4_CMD_BUFFER, MAC_802_15_4_NOTIF_BUFFER_SIZE,
+    MAC_802_15_4_NOTIF_RSP_EVT_BUFFER, TL_MAC_802_15_4_TABLE, TL_PACKET_HEADER_SIZE, TL_REF_TABLE,
+};
+use crate::ipcc::Ipcc;
+
+/// Signaled when a notification is pending
+static NOTIFICATION: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+pub struct Mac;
+
+impl Mac {
+    pub(crate) fn new(ipcc: &mut Ipcc) -> Self {
+        unsafe {
+            TL_MAC_802_15_4_TABLE = MaybeUninit::new(Mac802_15_4Table {
+                pcmd_rsp_buffer: MAC_802_15_4_CMD_BUFFER.as_mut_ptr().cast(),
+                pnotack_buffer: MAC_802_15_4_NOTIF_RSP_EVT_BUFFER.as_mut_ptr().cast(),
+                evt_queue: core::ptr::null(),
+            });
+        }
+
+        ipcc.c1_set_rx_channel(channels::cpu2::IPCC_MAC_802_15_4_NOTIFICATION_ACK_CHANNEL, true);
+
+        Mac
+    }
+
+    /// `buf` contains the whole command packet, starting with the packet type
+    pub(crate) fn send_cmd(ipcc: &mut Ipcc, buf: &[u8]) {
+        unsafe {
+            let pcmd_buffer: *mut CmdPacket = (*TL_REF_TABLE.assume_init().mac_802_15_4_table).pcmd_rsp_buffer.cast();
+            let pcmd_serial: *mut CmdSerial = &mut (*pcmd_buffer).cmd_serial;
+            let pcmd_serial_buf: *mut u8 = pcmd_serial.cast();
+
+            core::ptr::copy(buf.as_ptr(), pcmd_serial_buf, buf.len());
+
+            (*pcmd_serial).ty = TlPacketType::OtCmd as u8;
+        }
+
+        ipcc.c1_set_flag_channel(channels::cpu1::IPCC_MAC_802_15_4_CMD_RSP_CHANNEL);
+    }
+
+    /// Returns the response to the last command, starting with the packet type.
+    ///
+    /// CPU2 writes the response into the command buffer, it is valid once CPU2 released the channel.
+    pub(crate) fn cmd_response() -> &'static [u8] {
+        unsafe {
+            let pcmd_buffer: *const u8 = (*TL_REF_TABLE.assume_init().mac_802_15_4_table).pcmd_rsp_buffer;
+            core::slice::from_raw_parts(
+                pcmd_buffer.add(TL_PACKET_HEADER_SIZE),
+                core::mem::size_of::<CmdPacket>() - TL_PACKET_HEADER_SIZE,
+            )
+        }
+    }
+
+    /// Masks the notification channel until the notification is acknowledged and wakes the waiting task
+    pub(crate) fn notification_handler(ipcc: &mut Ipcc) {
+        ipcc.c1_set_rx_channel(channels::cpu2::IPCC_MAC_802_15_4_NOTIFICATION_ACK_CHANNEL, false);
+        NOTIFICATION.signal(());
+    }
+
+    /// Waits for a notification of CPU2
+    pub(crate) async fn wait_notification() {
+        NOTIFICATION.wait().await
+    }
+
+    /// Returns the pending notification, starting with the packet type.
+    ///
+    /// The buffer is only valid until the notification is acknowledged with [`Mac::send_ack`].
+    pub(crate) fn notification() -> &'static [u8] {
+        unsafe {
+            let pnotack_buffer: *const u8 = (*TL_REF_TABLE.assume_init().mac_802_15_4_table).pnotack_buffer;
+            core::slice::from_raw_parts(
+                pnotack_buffer.add(TL_PACKET_HEADER_SIZE),
+                MAC_802_15_4_NOTIF_BUFFER_SIZE - TL_PACKET_HEADER_SIZE,
+            )
+        }
+    }
+
+    /// Acknowledges the pending notification, which releases the notification buffer to CPU2
+    pub(crate) fn send_ack(ipcc: &mut Ipcc) {
+        unsafe {
+            let pnotack_buffer = (*TL_REF_TABLE.assume_init().mac_802_15_4_table).pnotack_buffer;
+            *pnotack_buffer.add(TL_PACKET_HEADER_SIZE) = TlPacketType::OtAck as u8;
+        }
+
+        ipcc.c1_clear_flag_channel(channels::cpu2::IPCC_MAC_802_15_4_NOTIFICATION_ACK_CHANNEL);
+        ipcc.c1_set_rx_channel(channels::cpu2::IPCC_MAC_802_15_4_NOTIFICATION_ACK_CHANNEL, true);
+    }
+}
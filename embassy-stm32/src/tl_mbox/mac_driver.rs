@@ -0,0 +1,78 @@
+use embassy_futures::yield_now;
+
+use super::view::EvtView;
+use super::{channels, mac, TlMbox, TlMboxError};
+use crate::ipcc::{Ipcc, IpccChannel};
+
+/// Command and notification interface to the 802.15.4 MAC on CPU2, to run a Thread or Zigbee stack on CPU1.
+///
+/// Requires the 802.15.4 MAC firmware to be installed on CPU2. Responses and notifications are returned
+/// as events, the event code and payload are defined by ST's MAC interface.
+pub struct Mac<'a, 'd> {
+    _mbox: &'a TlMbox,
+    ipcc: &'a mut Ipcc<'d>,
+}
+
+impl<'a, 'd> Mac<'a, 'd> {
+    pub fn new(mbox: &'a TlMbox, ipcc: &'a mut Ipcc<'d>) -> Self {
+        Self { _mbox: mbox, ipcc }
+    }
+
+    /// Sends a MAC command and waits for its response, which is copied into `buf`.
+    pub async fn send_command<'b>(
+        &mut self,
+        opcode: u16,
+        params: &[u8],
+        buf: &'b mut [u8],
+    ) -> Result<EvtView<'b>, TlMboxError> {
+        if params.len() > 255 {
+            return Err(TlMboxError::InvalidPacket);
+        }
+
+        let mut cmd = [0u8; 4 + 255];
+        let [opcode_lo, opcode_hi] = opcode.to_le_bytes();
+        cmd[1] = opcode_lo;
+        cmd[2] = opcode_hi;
+        cmd[3] = params.len() as u8;
+        cmd[4..][..params.len()].copy_from_slice(params);
+
+        self.wait_channel_free(channels::cpu1::IPCC_MAC_802_15_4_CMD_RSP_CHANNEL)
+            .await;
+        mac::Mac::send_cmd(self.ipcc, &cmd[..4 + params.len()]);
+
+        // CPU2 clears the flag once the response is written
+        self.wait_channel_free(channels::cpu1::IPCC_MAC_802_15_4_CMD_RSP_CHANNEL)
+            .await;
+
+        copy_evt(mac::Mac::cmd_response(), buf)
+    }
+
+    /// Waits for the next notification of the MAC, which is copied into `buf`.
+    ///
+    /// The notification buffer is released to CPU2 before returning, even if `buf` is too small.
+    pub async fn read_notification<'b>(&mut self, buf: &'b mut [u8]) -> Result<EvtView<'b>, TlMboxError> {
+        mac::Mac::wait_notification().await;
+
+        let res = copy_evt(mac::Mac::notification(), buf);
+        mac::Mac::send_ack(self.ipcc);
+
+        res
+    }
+
+    async fn wait_channel_free(&mut self, channel: IpccChannel) {
+        while self.ipcc.c1_is_active_flag(channel) {
+            yield_now().await;
+        }
+    }
+}
+
+/// Copies the event at the start of `src` into `buf` and returns a view of the copy
+fn copy_evt<'b>(src: &[u8], buf: &'b mut [u8]) -> Result<EvtView<'b>, TlMboxError> {
+    let evt = EvtView::parse(src).map_err(|_| TlMboxError::InvalidResponse)?;
+    let len = evt.as_bytes().len();
+
+    let buf = buf.get_mut(..len).ok_or(TlMboxError::BufferTooSmall)?;
+    buf.copy_from_slice(evt.as_bytes());
+
+    EvtView::parse(buf).map_err(|_| TlMboxError::InvalidResponse)
+}
@@ -1,28 +1,48 @@
+use core::future::{poll_fn, Future};
 use core::mem::MaybeUninit;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
 
+use atomic_polyfill::{AtomicBool, Ordering};
 use bit_field::BitField;
+use embassy_futures::yield_now;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-use embassy_sync::channel::Channel;
+use embassy_sync::channel::{Channel, TrySendError};
+use futures::Stream;
 
-use self::ble::Ble;
+pub use self::adv::{AdvertisingData, AdvertisingDataTooLong, Uuid, MAX_ADVERTISING_DATA_LEN};
+pub use self::ble_driver::{Ble, BlePacket, CommandComplete};
 use self::cmd::{AclDataPacket, CmdPacket};
-use self::evt::{CsEvt, EvtBox};
+use self::consts::TlPacketType;
+pub use self::evt::{CcEvt, CsEvt, EvtBox};
+pub use self::fus::{FusState, FusStatus};
+pub use self::hci::HciTransport;
+pub use self::mac_driver::Mac;
 use self::mm::MemoryManager;
 use self::shci::{shci_ble_init, ShciBleInitCmdParam};
 use self::sys::Sys;
 use self::unsafe_linked_list::LinkedListNode;
+pub use self::view::{AclDataView, EvtView, PacketError};
 use crate::interrupt;
-use crate::ipcc::Ipcc;
+use crate::interrupt::{Interrupt, InterruptExt};
+use crate::ipcc::{Ipcc, IpccChannel};
 
+mod adv;
 mod ble;
+mod ble_driver;
 mod channels;
 mod cmd;
 mod consts;
 mod evt;
+mod fus;
+mod hci;
+mod mac;
+mod mac_driver;
 mod mm;
 mod shci;
 mod sys;
 mod unsafe_linked_list;
+mod view;
 
 pub type PacketHeader = LinkedListNode;
 
@@ -54,17 +74,42 @@ pub struct FusInfoTable {
     fus_info: u32,
 }
 
+impl FusInfoTable {
+    pub fn version_major(&self) -> u8 {
+        let version = self.version;
+        version.get_bits(24..32) as u8
+    }
+
+    pub fn version_minor(&self) -> u8 {
+        let version = self.version;
+        version.get_bits(16..24) as u8
+    }
+
+    pub fn subversion(&self) -> u8 {
+        let version = self.version;
+        version.get_bits(8..16) as u8
+    }
+}
+
 /// Interrupt handler.
 pub struct ReceiveInterruptHandler {}
 
 impl interrupt::Handler<interrupt::IPCC_C1_RX> for ReceiveInterruptHandler {
-    unsafe fn on_interrupt() {}
+    unsafe fn on_interrupt() {
+        let mut ipcc = Ipcc::new_inner(crate::Peripherals::steal().IPCC);
+        TlMbox::interrupt_ipcc_rx_handler(&mut ipcc);
+        crate::ipcc::on_rx_interrupt();
+    }
 }
 
 pub struct TransmitInterruptHandler {}
 
 impl interrupt::Handler<interrupt::IPCC_C1_TX> for TransmitInterruptHandler {
-    unsafe fn on_interrupt() {}
+    unsafe fn on_interrupt() {
+        let mut ipcc = Ipcc::new_inner(crate::Peripherals::steal().IPCC);
+        TlMbox::interrupt_ipcc_tx_handler(&mut ipcc);
+        crate::ipcc::on_tx_interrupt();
+    }
 }
 
 /// # Version
@@ -103,6 +148,25 @@ impl WirelessFwInfoTable {
         (version.get_bits(8..15) & 0xff) as u8
     }
 
+    /// 0: mass market, other values are reserved for custom branches
+    pub fn branch(&self) -> u8 {
+        let version = self.version;
+        version.get_bits(4..8) as u8
+    }
+
+    /// 0: untracked, 15: released, other values: tracked version
+    pub fn build(&self) -> u8 {
+        let version = self.version;
+        version.get_bits(0..4) as u8
+    }
+
+    /// Type of the wireless stack, e.g. 0x01 for the full BLE stack, 0x02 for the BLE HCI layer only,
+    /// 0x10 for Thread FTD or 0x40 for the 802.15.4 MAC
+    pub fn stack_type(&self) -> u8 {
+        let info_stack = self.info_stack;
+        info_stack.get_bits(0..8) as u8
+    }
+
     /// size of FLASH, expressed in number of 4K sectors
     pub fn flash_size(&self) -> u8 {
         let memory_size = self.memory_size;
@@ -130,6 +194,30 @@ pub struct DeviceInfoTable {
     pub wireless_fw_info_table: WirelessFwInfoTable,
 }
 
+/// Errors reported by the commands issued through [`TlMbox`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TlMboxError {
+    /// The command was rejected by CPU2 with the given HCI status code
+    CommandFailed(u8),
+    /// The response to the command was malformed
+    InvalidResponse,
+    /// A malformed or unsupported packet was written to the [`HciTransport`]
+    InvalidPacket,
+    /// The buffer provided for the response is too small
+    BufferTooSmall,
+}
+
+/// Kind of BLE device address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BdAddrKind {
+    /// Public device address, derived from an IEEE assigned company identifier
+    Public,
+    /// Static random device address. The two most significant bits of the address must be set
+    StaticRandom,
+}
+
 #[repr(C, packed)]
 struct BleTable {
     pcmd_buffer: *mut CmdPacket,
@@ -194,8 +282,8 @@ struct TracesTable {
 
 #[repr(C, packed)]
 struct Mac802_15_4Table {
-    pcmd_rsp_buffer: *const u8,
-    pnotack_buffer: *const u8,
+    pcmd_rsp_buffer: *mut u8,
+    pnotack_buffer: *mut u8,
     evt_queue: *const u8,
 }
 
@@ -286,12 +374,21 @@ static mut BLE_CMD_BUFFER: MaybeUninit<CmdPacket> = MaybeUninit::uninit();
 //                                            "magic" numbers from ST ---v---v
 static mut HCI_ACL_DATA_BUFFER: MaybeUninit<[u8; TL_PACKET_HEADER_SIZE + 5 + 251]> = MaybeUninit::uninit();
 
+#[link_section = "MB_MEM2"]
+static mut MAC_802_15_4_CMD_BUFFER: MaybeUninit<CmdPacket> = MaybeUninit::uninit();
+
+const MAC_802_15_4_NOTIF_BUFFER_SIZE: usize = TL_PACKET_HEADER_SIZE + TL_EVT_HEADER_SIZE + 255;
+
+#[link_section = "MB_MEM2"]
+static mut MAC_802_15_4_NOTIF_RSP_EVT_BUFFER: MaybeUninit<[u8; MAC_802_15_4_NOTIF_BUFFER_SIZE]> = MaybeUninit::uninit();
+
 // TODO: get a better size, this is a placeholder
 pub(crate) static TL_CHANNEL: Channel<CriticalSectionRawMutex, EvtBox, 5> = Channel::new();
 
 pub struct TlMbox {
     _sys: Sys,
-    _ble: Ble,
+    _ble: ble::Ble,
+    _mac: mac::Mac,
     _mm: MemoryManager,
 }
 
@@ -334,33 +431,28 @@ impl TlMbox {
             CS_BUFFER = MaybeUninit::zeroed();
             BLE_CMD_BUFFER = MaybeUninit::zeroed();
             HCI_ACL_DATA_BUFFER = MaybeUninit::zeroed();
+            MAC_802_15_4_CMD_BUFFER = MaybeUninit::zeroed();
+            MAC_802_15_4_NOTIF_RSP_EVT_BUFFER = MaybeUninit::zeroed();
         }
 
         ipcc.init();
 
         let _sys = Sys::new(ipcc);
-        let _ble = Ble::new(ipcc);
+        let _ble = ble::Ble::new(ipcc);
+        let _mac = mac::Mac::new(ipcc);
         let _mm = MemoryManager::new();
 
-        //        rx_irq.disable();
-        //        tx_irq.disable();
-        //
-        //        rx_irq.set_handler_context(ipcc.as_mut_ptr() as *mut ());
-        //        tx_irq.set_handler_context(ipcc.as_mut_ptr() as *mut ());
-        //
-        //        rx_irq.set_handler(|ipcc| {
-        //            let ipcc: &mut Ipcc = unsafe { &mut *ipcc.cast() };
-        //            Self::interrupt_ipcc_rx_handler(ipcc);
-        //        });
-        //        tx_irq.set_handler(|ipcc| {
-        //            let ipcc: &mut Ipcc = unsafe { &mut *ipcc.cast() };
-        //            Self::interrupt_ipcc_tx_handler(ipcc);
-        //        });
-        //
-        //        rx_irq.enable();
-        //        tx_irq.enable();
-
-        TlMbox { _sys, _ble, _mm }
+        unsafe {
+            let rx_irq = interrupt::IPCC_C1_RX::steal();
+            rx_irq.unpend();
+            rx_irq.enable();
+
+            let tx_irq = interrupt::IPCC_C1_TX::steal();
+            tx_irq.unpend();
+            tx_irq.enable();
+        }
+
+        TlMbox { _sys, _ble, _mac, _mm }
     }
 
     pub fn wireless_fw_info(&self) -> Option<WirelessFwInfoTable> {
@@ -374,6 +466,195 @@ impl TlMbox {
         }
     }
 
+    /// Returns the version of the firmware upgrade service (FUS) running on CPU2
+    pub fn fus_info(&self) -> Option<FusInfoTable> {
+        let info = unsafe { &(*(*TL_REF_TABLE.as_ptr()).device_info_table).fus_info_table };
+
+        // zero version indicates that CPU2 wasn't active and didn't fill the information table
+        if info.version != 0 {
+            Some(*info)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the factory programmed 64-bit unique device identifier
+    pub fn uid64(&self) -> u64 {
+        unsafe { core::ptr::read_volatile(consts::UID64_ADDRESS as *const u64) }
+    }
+
+    /// Returns the public device address derived from [`TlMbox::uid64`], the same way as ST's reference
+    /// applications do. The address is returned in little-endian byte order, as expected by
+    /// [`TlMbox::set_bd_addr`].
+    pub fn factory_bd_addr(&self) -> [u8; 6] {
+        let uid64 = self.uid64();
+        let udn = uid64 as u32;
+        let device_id = (uid64 >> 32) as u8;
+        let company_id = (uid64 >> 40) as u32;
+
+        [
+            udn as u8,
+            (udn >> 8) as u8,
+            device_id,
+            company_id as u8,
+            (company_id >> 8) as u8,
+            (company_id >> 16) as u8,
+        ]
+    }
+
+    /// Sets the device address used by the BLE stack. `addr` is in little-endian byte order.
+    ///
+    /// Must be called after the BLE stack has been reset and before advertising or scanning is started.
+    pub async fn set_bd_addr(&self, ipcc: &mut Ipcc<'_>, addr: [u8; 6], kind: BdAddrKind) -> Result<(), TlMboxError> {
+        let offset = match kind {
+            BdAddrKind::Public => consts::CONFIG_DATA_PUBADDR_OFFSET,
+            BdAddrKind::StaticRandom => consts::CONFIG_DATA_RANDOM_ADDRESS_OFFSET,
+        };
+
+        let mut params = [0u8; 8];
+        params[0] = offset;
+        params[1] = addr.len() as u8;
+        params[2..].copy_from_slice(&addr);
+
+        self.ble_cmd(ipcc, consts::ACI_HAL_WRITE_CONFIG_DATA, &params, &mut [])
+            .await
+            .map(|_| ())
+    }
+
+    /// Reads the public device address of the BLE stack, in little-endian byte order
+    pub async fn read_bd_addr(&self, ipcc: &mut Ipcc<'_>) -> Result<[u8; 6], TlMboxError> {
+        let mut addr = [0u8; 6];
+
+        let len = self.ble_cmd(ipcc, consts::HCI_READ_BD_ADDR, &[], &mut addr).await?;
+        if len != addr.len() {
+            return Err(TlMboxError::InvalidResponse);
+        }
+
+        Ok(addr)
+    }
+
+    /// Sets the data used for advertising
+    pub async fn set_advertising_data(&self, ipcc: &mut Ipcc<'_>, data: &AdvertisingData) -> Result<(), TlMboxError> {
+        self.set_ad_data(ipcc, consts::HCI_LE_SET_ADVERTISING_DATA, data).await
+    }
+
+    /// Sets the data returned in response to scan requests
+    pub async fn set_scan_response_data(&self, ipcc: &mut Ipcc<'_>, data: &AdvertisingData) -> Result<(), TlMboxError> {
+        self.set_ad_data(ipcc, consts::HCI_LE_SET_SCAN_RESPONSE_DATA, data)
+            .await
+    }
+
+    async fn set_ad_data(&self, ipcc: &mut Ipcc<'_>, opcode: u16, data: &AdvertisingData) -> Result<(), TlMboxError> {
+        // the data is always transferred with the maximum length, padded with zeros
+        let mut params = [0u8; 1 + MAX_ADVERTISING_DATA_LEN];
+        let data = data.as_bytes();
+        params[0] = data.len() as u8;
+        params[1..][..data.len()].copy_from_slice(data);
+
+        self.ble_cmd(ipcc, opcode, &params, &mut []).await.map(|_| ())
+    }
+
+    /// Sends a HCI command and waits for its completion. The return parameters of the command are written
+    /// into `ret` and the number of bytes written is returned.
+    ///
+    /// Other events received while waiting are discarded.
+    async fn ble_cmd(
+        &self,
+        ipcc: &mut Ipcc<'_>,
+        opcode: u16,
+        params: &[u8],
+        ret: &mut [u8],
+    ) -> Result<usize, TlMboxError> {
+        let mut buf = [0u8; 4 + 255];
+        let [opcode_lo, opcode_hi] = opcode.to_le_bytes();
+        buf[0] = TlPacketType::BleCmd as u8;
+        buf[1] = opcode_lo;
+        buf[2] = opcode_hi;
+        buf[3] = params.len() as u8;
+        buf[4..][..params.len()].copy_from_slice(params);
+
+        self.send_ble_cmd(ipcc, &buf[..4 + params.len()]);
+
+        loop {
+            let event = self.read().await;
+
+            let evt = match event.evt_view() {
+                Ok(evt) if evt.packet_type() == TlPacketType::BleEvt as u8 => evt,
+                _ => continue,
+            };
+            let payload = evt.payload();
+
+            match evt.evt_code() {
+                // num hci command packets, opcode, status, return parameters
+                consts::HCI_EVT_COMMAND_COMPLETE if payload.len() >= 4 && payload[1..3] == opcode.to_le_bytes() => {
+                    let status = payload[3];
+                    if status != 0 {
+                        return Err(TlMboxError::CommandFailed(status));
+                    }
+
+                    let n = (payload.len() - 4).min(ret.len());
+                    ret[..n].copy_from_slice(&payload[4..][..n]);
+
+                    return Ok(n);
+                }
+                // status, num hci command packets, opcode
+                consts::HCI_EVT_COMMAND_STATUS if payload.len() >= 4 && payload[2..4] == opcode.to_le_bytes() => {
+                    let status = payload[0];
+                    if status != 0 {
+                        return Err(TlMboxError::CommandFailed(status));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Sends a system command and waits for its response. The return parameters following the status are
+    /// written into `ret` and the status and number of bytes written are returned.
+    ///
+    /// The response is written by CPU2 into the command buffer, it is not received as an event.
+    async fn sys_cmd(
+        &self,
+        ipcc: &mut Ipcc<'_>,
+        opcode: u16,
+        params: &[u8],
+        ret: &mut [u8],
+    ) -> Result<(u8, usize), TlMboxError> {
+        let mut buf = [0u8; 4 + 255];
+        let [opcode_lo, opcode_hi] = opcode.to_le_bytes();
+        buf[0] = TlPacketType::SysCmd as u8;
+        buf[1] = opcode_lo;
+        buf[2] = opcode_hi;
+        buf[3] = params.len() as u8;
+        buf[4..][..params.len()].copy_from_slice(params);
+
+        while ipcc.c1_is_active_flag(channels::cpu1::IPCC_SYSTEM_CMD_RSP_CHANNEL) {
+            yield_now().await;
+        }
+        Sys::send_cmd(ipcc, &buf[..4 + params.len()]);
+
+        // CPU2 clears the flag once the response is written
+        while ipcc.c1_is_active_flag(channels::cpu1::IPCC_SYSTEM_CMD_RSP_CHANNEL) {
+            yield_now().await;
+        }
+
+        let evt = EvtView::parse(Sys::cmd_response(ipcc)).map_err(|_| TlMboxError::InvalidResponse)?;
+        let payload = evt.payload();
+
+        // num command packets, opcode, status, return parameters
+        if evt.evt_code() != consts::HCI_EVT_COMMAND_COMPLETE
+            || payload.len() < 4
+            || payload[1..3] != opcode.to_le_bytes()
+        {
+            return Err(TlMboxError::InvalidResponse);
+        }
+
+        let n = (payload.len() - 4).min(ret.len());
+        ret[..n].copy_from_slice(&payload[4..][..n]);
+
+        Ok((payload[3], n))
+    }
+
     pub fn shci_ble_init(&self, ipcc: &mut Ipcc, param: ShciBleInitCmdParam) {
         shci_ble_init(ipcc, param);
     }
@@ -387,29 +668,101 @@ impl TlMbox {
     // }
 
     pub async fn read(&self) -> EvtBox {
-        TL_CHANNEL.recv().await
+        receive().await
+    }
+
+    /// Returns a stream of the events received on the system and BLE channels.
+    ///
+    /// Events are shared with [`TlMbox::read`] and the drivers built on the mailbox, each event is only
+    /// returned once.
+    pub fn events(&self) -> EvtStream<'_> {
+        EvtStream { _mbox: self }
     }
 
-    #[allow(dead_code)]
     fn interrupt_ipcc_rx_handler(ipcc: &mut Ipcc) {
         if ipcc.is_rx_pending(channels::cpu2::IPCC_SYSTEM_EVENT_CHANNEL) {
             sys::Sys::evt_handler(ipcc);
-        } else if ipcc.is_rx_pending(channels::cpu2::IPCC_BLE_EVENT_CHANNEL) {
+        }
+        if ipcc.is_rx_pending(channels::cpu2::IPCC_BLE_EVENT_CHANNEL) {
             ble::Ble::evt_handler(ipcc);
-        } else {
-            todo!()
+        }
+        if ipcc.is_rx_pending(channels::cpu2::IPCC_MAC_802_15_4_NOTIFICATION_ACK_CHANNEL) {
+            mac::Mac::notification_handler(ipcc);
         }
     }
 
-    #[allow(dead_code)]
     fn interrupt_ipcc_tx_handler(ipcc: &mut Ipcc) {
         if ipcc.is_tx_pending(channels::cpu1::IPCC_SYSTEM_CMD_RSP_CHANNEL) {
-            // TODO: handle this case
+            // the response is read by the task waiting for it
             let _ = sys::Sys::cmd_evt_handler(ipcc);
-        } else if ipcc.is_tx_pending(channels::cpu1::IPCC_MM_RELEASE_BUFFER_CHANNEL) {
+        }
+        if ipcc.is_tx_pending(channels::cpu1::IPCC_MM_RELEASE_BUFFER_CHANNEL) {
             mm::MemoryManager::evt_handler(ipcc);
-        } else {
-            todo!()
         }
     }
 }
+
+/// Stream of the events received from CPU2, see [`TlMbox::events`]
+pub struct EvtStream<'a> {
+    _mbox: &'a TlMbox,
+}
+
+impl<'a> EvtStream<'a> {
+    /// Waits for the next event. Never returns `None`, the stream does not end.
+    pub async fn recv(&mut self) -> Option<EvtBox> {
+        Some(receive().await)
+    }
+}
+
+impl<'a> Stream for EvtStream<'a> {
+    type Item = EvtBox;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<EvtBox>> {
+        poll_receive(cx).map(Some)
+    }
+}
+
+/// Set when the event queues were left unprocessed because [`TL_CHANNEL`] was full
+static RX_PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Moves the events of `queue` into [`TL_CHANNEL`] and releases `channel` to CPU2 once `queue` is empty.
+///
+/// If [`TL_CHANNEL`] is full, the remaining events are left in `queue` and `channel` is masked until the
+/// next event is received.
+unsafe fn forward_events(ipcc: &mut Ipcc, queue: *mut LinkedListNode, channel: IpccChannel) {
+    let mut node_ptr = core::ptr::null_mut();
+    let node_ptr_ptr: *mut _ = &mut node_ptr;
+
+    while !LinkedListNode::is_empty(queue) {
+        LinkedListNode::remove_head(queue, node_ptr_ptr);
+
+        if let Err(TrySendError::Full(event)) = TL_CHANNEL.try_send(EvtBox::new(node_ptr.cast())) {
+            LinkedListNode::insert_head(queue, event.into_raw().cast());
+            ipcc.c1_set_rx_channel(channel, false);
+            RX_PAUSED.store(true, Ordering::Release);
+            return;
+        }
+    }
+
+    ipcc.c1_clear_flag_channel(channel);
+}
+
+fn poll_receive(cx: &mut Context<'_>) -> Poll<EvtBox> {
+    let event = ready!(Pin::new(&mut TL_CHANNEL.recv()).poll(cx));
+
+    if RX_PAUSED.swap(false, Ordering::AcqRel) {
+        // the masked channels keep their flag set, so the interrupt fires again once unmasked
+        critical_section::with(|_| {
+            let mut ipcc = Ipcc::new_inner(unsafe { crate::Peripherals::steal() }.IPCC);
+            ipcc.c1_set_rx_channel(channels::cpu2::IPCC_SYSTEM_EVENT_CHANNEL, true);
+            ipcc.c1_set_rx_channel(channels::cpu2::IPCC_BLE_EVENT_CHANNEL, true);
+        });
+    }
+
+    Poll::Ready(event)
+}
+
+/// Waits for the next event received on the system or BLE channel
+pub(crate) async fn receive() -> EvtBox {
+    poll_fn(poll_receive).await
+}
@@ -1,12 +1,10 @@
 use core::mem::MaybeUninit;
 
-use embassy_futures::block_on;
-
 use super::cmd::{CmdPacket, CmdSerial};
 use super::consts::TlPacketType;
-use super::evt::{CcEvt, EvtBox, EvtSerial};
+use super::evt::{CcEvt, EvtSerial};
 use super::unsafe_linked_list::LinkedListNode;
-use super::{channels, SysTable, SYSTEM_EVT_QUEUE, SYS_CMD_BUF, TL_CHANNEL, TL_REF_TABLE, TL_SYS_TABLE};
+use super::{channels, SysTable, SYSTEM_EVT_QUEUE, SYS_CMD_BUF, TL_REF_TABLE, TL_SYS_TABLE};
 use crate::ipcc::Ipcc;
 
 pub struct Sys;
@@ -29,21 +27,12 @@ impl Sys {
 
     pub(crate) fn evt_handler(ipcc: &mut Ipcc) {
         unsafe {
-            let mut node_ptr = core::ptr::null_mut();
-            let node_ptr_ptr: *mut _ = &mut node_ptr;
-
-            while !LinkedListNode::is_empty(SYSTEM_EVT_QUEUE.as_mut_ptr()) {
-                LinkedListNode::remove_head(SYSTEM_EVT_QUEUE.as_mut_ptr(), node_ptr_ptr);
-
-                let event = node_ptr.cast();
-                let event = EvtBox::new(event);
-
-                // TODO: not really happy about this
-                block_on(TL_CHANNEL.send(event));
-            }
+            super::forward_events(
+                ipcc,
+                SYSTEM_EVT_QUEUE.as_mut_ptr(),
+                channels::cpu2::IPCC_SYSTEM_EVENT_CHANNEL,
+            )
         }
-
-        ipcc.c1_clear_flag_channel(channels::cpu2::IPCC_SYSTEM_EVENT_CHANNEL);
     }
 
     pub(crate) fn cmd_evt_handler(ipcc: &mut Ipcc) -> CcEvt {
@@ -67,7 +56,17 @@ impl Sys {
         }
     }
 
-    #[allow(dead_code)]
+    /// Returns the buffer holding the response to the last system command, starting with the event header.
+    pub(crate) fn cmd_response(ipcc: &mut Ipcc) -> &'static [u8] {
+        ipcc.c1_set_tx_channel(channels::cpu1::IPCC_SYSTEM_CMD_RSP_CHANNEL, false);
+
+        unsafe {
+            let cmd: *const CmdPacket = (*TL_SYS_TABLE.as_ptr()).pcmd_buffer;
+            let cmd_serial: *const CmdSerial = &(*cmd).cmd_serial;
+            core::slice::from_raw_parts(cmd_serial.cast(), core::mem::size_of::<CmdSerial>())
+        }
+    }
+
     pub(crate) fn send_cmd(ipcc: &mut Ipcc, buf: &[u8]) {
         unsafe {
             // TODO: check this
@@ -0,0 +1,157 @@
+use super::consts::TlPacketType;
+use super::TL_EVT_HEADER_SIZE;
+
+/// Size of the ACL data header: packet type (1), handle (2), data length (2)
+const TL_ACL_HEADER_SIZE: usize = 5;
+
+/// Errors detected while parsing a packet received from CPU2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PacketError {
+    /// The packet type is unknown
+    UnknownPacketType(u8),
+    /// The packet type is known, but not the one expected, e.g. ACL data where an event was expected
+    UnexpectedPacketType(u8),
+    /// The length of the packet exceeds the buffer it was received in
+    Truncated,
+    /// The buffer provided to copy the packet into is too small
+    BufferTooSmall,
+}
+
+/// Bounds checked view of an event packet: packet type, event code, payload length and payload
+#[derive(Clone, Copy)]
+pub struct EvtView<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> EvtView<'a> {
+    /// Parses the event packet at the start of `buf`. Bytes following the packet are ignored.
+    pub fn parse(buf: &'a [u8]) -> Result<Self, PacketError> {
+        let kind = *buf.first().ok_or(PacketError::Truncated)?;
+        if TlPacketType::try_from(kind)? == TlPacketType::AclData {
+            return Err(PacketError::UnexpectedPacketType(kind));
+        }
+
+        let payload_len = *buf.get(2).ok_or(PacketError::Truncated)? as usize;
+        let buf = buf
+            .get(..TL_EVT_HEADER_SIZE + payload_len)
+            .ok_or(PacketError::Truncated)?;
+
+        Ok(Self { buf })
+    }
+
+    /// Packet type, e.g. `0x04` for BLE events and `0x12` for system events
+    pub fn packet_type(&self) -> u8 {
+        self.buf[0]
+    }
+
+    /// Event code, e.g. `0x0E` for a HCI command complete event
+    pub fn evt_code(&self) -> u8 {
+        self.buf[1]
+    }
+
+    /// Parameters of the event, following the payload length
+    pub fn payload(&self) -> &'a [u8] {
+        &self.buf[TL_EVT_HEADER_SIZE..]
+    }
+
+    /// The whole packet, starting with the packet type
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+}
+
+/// Bounds checked view of an ACL data packet: packet type, handle with flags, data length and data
+#[derive(Clone, Copy)]
+pub struct AclDataView<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> AclDataView<'a> {
+    /// Parses the ACL data packet at the start of `buf`. Bytes following the packet are ignored.
+    pub fn parse(buf: &'a [u8]) -> Result<Self, PacketError> {
+        let kind = *buf.first().ok_or(PacketError::Truncated)?;
+        if TlPacketType::try_from(kind)? != TlPacketType::AclData {
+            return Err(PacketError::UnexpectedPacketType(kind));
+        }
+
+        let header = buf.get(..TL_ACL_HEADER_SIZE).ok_or(PacketError::Truncated)?;
+        let data_len = u16::from_le_bytes([header[3], header[4]]) as usize;
+        let buf = buf.get(..TL_ACL_HEADER_SIZE + data_len).ok_or(PacketError::Truncated)?;
+
+        Ok(Self { buf })
+    }
+
+    /// Connection handle, 0..=0xEFF
+    pub fn handle(&self) -> u16 {
+        u16::from_le_bytes([self.buf[1], self.buf[2]]) & 0x0FFF
+    }
+
+    /// Packet boundary and broadcast flags, i.e. the 4 most significant bits of the handle field
+    pub fn flags(&self) -> u8 {
+        self.buf[2] >> 4
+    }
+
+    /// Data of the packet, following the data length
+    pub fn data(&self) -> &'a [u8] {
+        &self.buf[TL_ACL_HEADER_SIZE..]
+    }
+
+    /// The whole packet, starting with the packet type
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+}
+
+/// Returns the event or ACL data packet at the start of `buf`, depending on its packet type
+pub(super) fn packet(buf: &[u8]) -> Result<&[u8], PacketError> {
+    match buf.first() {
+        Some(&kind) if kind == TlPacketType::AclData as u8 => AclDataView::parse(buf).map(|v| v.as_bytes()),
+        _ => EvtView::parse(buf).map(|v| v.as_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_event() {
+        // command complete of HCI_RESET, followed by garbage
+        let buf = [0x04, 0x0E, 0x04, 0x01, 0x03, 0x0C, 0x00, 0xFF, 0xFF];
+
+        let evt = EvtView::parse(&buf).unwrap();
+        assert_eq!(0x04, evt.packet_type());
+        assert_eq!(0x0E, evt.evt_code());
+        assert_eq!(&[0x01, 0x03, 0x0C, 0x00], evt.payload());
+        assert_eq!(Ok(&buf[..7]), packet(&buf));
+
+        assert_eq!(
+            Some(PacketError::UnexpectedPacketType(0x02)),
+            EvtView::parse(&[0x02, 0, 0, 0, 0]).err()
+        );
+        assert_eq!(Err(PacketError::UnknownPacketType(0x03)), packet(&[0x03, 0x00, 0x00]));
+    }
+
+    #[test]
+    fn can_parse_acl_data() {
+        let buf = [0x02, 0x01, 0x20, 0x02, 0x00, 0xAA, 0xBB];
+
+        let acl = AclDataView::parse(&buf).unwrap();
+        assert_eq!(0x001, acl.handle());
+        assert_eq!(0x2, acl.flags());
+        assert_eq!(&[0xAA, 0xBB], acl.data());
+        assert_eq!(Ok(&buf[..]), packet(&buf));
+    }
+
+    #[test]
+    fn rejects_lengths_beyond_buffer() {
+        assert_eq!(Err(PacketError::Truncated), packet(&[0x04, 0x0E, 0x04, 0x01]));
+        assert_eq!(Err(PacketError::Truncated), packet(&[0x04, 0x0E]));
+        assert_eq!(
+            Err(PacketError::Truncated),
+            packet(&[0x02, 0x01, 0x00, 0xFF, 0xFF, 0x00])
+        );
+        assert_eq!(Err(PacketError::Truncated), packet(&[]));
+    }
+}
@@ -204,7 +204,7 @@ impl<'d, T: BasicInstance> BufferedUart<'d, T> {
             tx.set_as_af(tx.af_num(), AFType::OutputPushPull);
         }
 
-        configure(r, &config, T::frequency(), T::KIND, true, true);
+        configure(r, &config, T::kernel_clock(&config), T::KIND, true, true);
 
         unsafe {
             r.cr1().modify(|w| {
@@ -30,10 +30,17 @@ impl<T: BasicInstance> interrupt::Handler<T::Interrupt> for InterruptHandler<T>
         let r = T::regs();
         let s = T::state();
 
-        let (sr, cr1, cr3) = unsafe { (sr(r).read(), r.cr1().read(), r.cr3().read()) };
+        let (sr, cr1, cr2, cr3) = unsafe { (sr(r).read(), r.cr1().read(), r.cr2().read(), r.cr3().read()) };
+
+        // Clear the wakeup flag, whose only purpose is to wake the microcontroller up from STOP modes
+        #[cfg(any(usart_v3, usart_v4))]
+        if sr.wuf() && cr3.wufie() {
+            unsafe { r.icr().write(|w| w.set_wuf(true)) };
+        }
 
         let mut wake = false;
-        let has_errors = (sr.pe() && cr1.peie()) || ((sr.fe() || sr.ne() || sr.ore()) && cr3.eie());
+        let has_errors =
+            (sr.pe() && cr1.peie()) || ((sr.fe() || sr.ne() || sr.ore()) && cr3.eie()) || (sr.lbd() && cr2.lbdie());
         if has_errors {
             // clear all interrupts and DMA Rx Request
             unsafe {
@@ -45,6 +52,10 @@ impl<T: BasicInstance> interrupt::Handler<T::Interrupt> for InterruptHandler<T>
                     // disable idle line interrupt
                     w.set_idleie(false);
                 });
+                r.cr2().modify(|w| {
+                    // disable LIN break interrupt
+                    w.set_lbdie(false);
+                });
                 r.cr3().modify(|w| {
                     // disable Error Interrupt: (Frame error, Noise error, Overrun error)
                     w.set_eie(false);
@@ -109,6 +120,45 @@ pub enum StopBits {
     STOP1P5,
 }
 
+/// Polarity of the driver enable signal, see [`Uart::new_with_de`]
+#[cfg(not(any(usart_v1, usart_v2)))]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DePolarity {
+    ActiveHigh,
+    ActiveLow,
+}
+
+/// Kernel clock of LPUART1
+#[cfg(any(rcc_l0, rcc_l4, rcc_l5, rcc_g0, rcc_g4, rcc_wb, rcc_wl5, rcc_wle))]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LpuartClockSource {
+    /// APB clock, stopped in STOP modes
+    Pclk,
+    /// 16 MHz HSI, enabled by the driver and kept running in STOP modes
+    Hsi,
+    /// 32.768 kHz LSE crystal, for baudrates up to 9600, enabled by the driver
+    Lse,
+}
+
+/// Event waking the microcontroller up from STOP modes
+#[cfg(any(usart_v3, usart_v4))]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WakeupEvent {
+    /// Start bit of a character
+    StartBit,
+    /// Character received
+    RxNotEmpty,
+}
+
+/// Length of the LIN break detected by the receiver
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LinBreakLength {
+    #[doc = "10 bits"]
+    Bits10,
+    #[doc = "11 bits"]
+    Bits11,
+}
+
 #[non_exhaustive]
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Config {
@@ -126,6 +176,32 @@ pub struct Config {
     /// but will effectively disable noise detection.
     #[cfg(not(usart_v1))]
     pub assume_noise_free: bool,
+
+    /// Time between the activation of the driver enable signal and the start bit of the first
+    /// transmitted character, in sample time units (1/16 or 1/8 of a bit, depending on the
+    /// oversampling), up to 31.
+    #[cfg(not(any(usart_v1, usart_v2)))]
+    pub de_assertion_time: u8,
+    /// Time between the end of the last stop bit and the deactivation of the driver enable
+    /// signal, in sample time units, up to 31.
+    #[cfg(not(any(usart_v1, usart_v2)))]
+    pub de_deassertion_time: u8,
+    #[cfg(not(any(usart_v1, usart_v2)))]
+    pub de_polarity: DePolarity,
+
+    /// Enable LIN mode, detecting breaks of the given length. A detected break makes reads fail
+    /// with [`Error::Break`], and [`UartTx::send_break`] sends a 13 bit break. LIN mode requires
+    /// one stop bit, and is not available on LPUART.
+    pub lin_mode: Option<LinBreakLength>,
+
+    /// Kernel clock of LPUART1, ignored by the other instances.
+    #[cfg(any(rcc_l0, rcc_l4, rcc_l5, rcc_g0, rcc_g4, rcc_wb, rcc_wl5, rcc_wle))]
+    pub lpuart_clock_source: LpuartClockSource,
+
+    /// Keep receiving in STOP modes, and wake the microcontroller up on the given event. The
+    /// kernel clock must keep running in STOP modes, e.g. HSI or LSE for LPUART1.
+    #[cfg(any(usart_v3, usart_v4))]
+    pub wakeup_from_stop: Option<WakeupEvent>,
 }
 
 impl Default for Config {
@@ -139,6 +215,17 @@ impl Default for Config {
             detect_previous_overrun: false,
             #[cfg(not(usart_v1))]
             assume_noise_free: false,
+            #[cfg(not(any(usart_v1, usart_v2)))]
+            de_assertion_time: 0,
+            #[cfg(not(any(usart_v1, usart_v2)))]
+            de_deassertion_time: 0,
+            #[cfg(not(any(usart_v1, usart_v2)))]
+            de_polarity: DePolarity::ActiveHigh,
+            lin_mode: None,
+            #[cfg(any(rcc_l0, rcc_l4, rcc_l5, rcc_g0, rcc_g4, rcc_wb, rcc_wl5, rcc_wle))]
+            lpuart_clock_source: LpuartClockSource::Pclk,
+            #[cfg(any(usart_v3, usart_v4))]
+            wakeup_from_stop: None,
         }
     }
 }
@@ -158,6 +245,8 @@ pub enum Error {
     Parity,
     /// Buffer too large for DMA
     BufferTooLong,
+    /// LIN break detected
+    Break,
 }
 
 enum ReadCompletionEvent {
@@ -234,7 +323,7 @@ impl<'d, T: BasicInstance, TxDma> UartTx<'d, T, TxDma> {
             tx.set_as_af(tx.af_num(), AFType::OutputPushPull);
         }
 
-        configure(r, &config, T::frequency(), T::KIND, false, true);
+        configure(r, &config, T::kernel_clock(&config), T::KIND, false, true);
 
         // create state once!
         let _s = T::state();
@@ -281,6 +370,12 @@ impl<'d, T: BasicInstance, TxDma> UartTx<'d, T, TxDma> {
         }
         Ok(())
     }
+
+    /// Send a break after the character being transmitted, if any. The break is 13 bits long in
+    /// LIN mode, and one character long otherwise.
+    pub fn send_break(&mut self) {
+        send_break(T::regs());
+    }
 }
 
 impl<'d, T: BasicInstance, RxDma> UartRx<'d, T, RxDma> {
@@ -335,7 +430,7 @@ impl<'d, T: BasicInstance, RxDma> UartRx<'d, T, RxDma> {
             rx.set_as_af(rx.af_num(), AFType::Input);
         }
 
-        configure(r, &config, T::frequency(), T::KIND, true, false);
+        configure(r, &config, T::kernel_clock(&config), T::KIND, true, false);
 
         unsafe { T::Interrupt::steal() }.unpend();
         unsafe { T::Interrupt::steal() }.enable();
@@ -375,6 +470,10 @@ impl<'d, T: BasicInstance, RxDma> UartRx<'d, T, RxDma> {
             } else {
                 // No error flags from previous iterations were set: Check the actual status register
                 let sr = r.sr().read();
+                if sr.lbd() {
+                    clear_interrupt_flags(r, sr);
+                    return Err(Error::Break);
+                }
                 if !sr.rxne() {
                     return Ok(false);
                 }
@@ -389,7 +488,10 @@ impl<'d, T: BasicInstance, RxDma> UartRx<'d, T, RxDma> {
     unsafe fn check_rx_flags(&mut self) -> Result<bool, Error> {
         let r = T::regs();
         let sr = r.isr().read();
-        if sr.pe() {
+        if sr.lbd() {
+            r.icr().write(|w| w.set_lbd(true));
+            return Err(Error::Break);
+        } else if sr.pe() {
             r.icr().write(|w| w.set_pe(true));
             return Err(Error::Parity);
         } else if sr.fe() {
@@ -467,6 +569,10 @@ impl<'d, T: BasicInstance, RxDma> UartRx<'d, T, RxDma> {
                     // disable idle line interrupt
                     w.set_idleie(false);
                 });
+                r.cr2().modify(|w| {
+                    // disable LIN break interrupt
+                    w.set_lbdie(false);
+                });
                 r.cr3().modify(|w| {
                     // disable Error Interrupt: (Frame error, Noise error, Overrun error)
                     w.set_eie(false);
@@ -504,6 +610,11 @@ impl<'d, T: BasicInstance, RxDma> UartRx<'d, T, RxDma> {
                 w.set_peie(w.pce());
             });
 
+            r.cr2().modify(|w| {
+                // enable LIN break interrupt if in LIN mode
+                w.set_lbdie(w.linen());
+            });
+
             r.cr3().modify(|w| {
                 // enable Error Interrupt: (Frame error, Noise error, Overrun error)
                 w.set_eie(true);
@@ -542,6 +653,9 @@ impl<'d, T: BasicInstance, RxDma> UartRx<'d, T, RxDma> {
                 if sr.ore() {
                     return Err(Error::Overrun);
                 }
+                if sr.lbd() {
+                    return Err(Error::Break);
+                }
 
                 unreachable!();
             }
@@ -580,7 +694,7 @@ impl<'d, T: BasicInstance, RxDma> UartRx<'d, T, RxDma> {
 
             compiler_fence(Ordering::SeqCst);
 
-            let has_errors = sr.pe() || sr.fe() || sr.ne() || sr.ore();
+            let has_errors = sr.pe() || sr.fe() || sr.ne() || sr.ore() || sr.lbd();
 
             if has_errors {
                 // all Rx interrupts and Rx DMA Request have already been cleared in interrupt handler
@@ -597,6 +711,9 @@ impl<'d, T: BasicInstance, RxDma> UartRx<'d, T, RxDma> {
                 if sr.ore() {
                     return Poll::Ready(Err(Error::Overrun));
                 }
+                if sr.lbd() {
+                    return Poll::Ready(Err(Error::Break));
+                }
             }
 
             if enable_idle_line_detection && sr.idle() {
@@ -736,7 +853,7 @@ impl<'d, T: BasicInstance, TxDma, RxDma> Uart<'d, T, TxDma, RxDma> {
             tx.set_as_af(tx.af_num(), AFType::OutputPushPull);
         }
 
-        configure(r, &config, T::frequency(), T::KIND, true, true);
+        configure(r, &config, T::kernel_clock(&config), T::KIND, true, true);
 
         unsafe { T::Interrupt::steal() }.unpend();
         unsafe { T::Interrupt::steal() }.enable();
@@ -774,6 +891,11 @@ impl<'d, T: BasicInstance, TxDma, RxDma> Uart<'d, T, TxDma, RxDma> {
         self.tx.blocking_flush()
     }
 
+    /// Send a break, see [`UartTx::send_break`].
+    pub fn send_break(&mut self) {
+        self.tx.send_break()
+    }
+
     pub async fn read(&mut self, buffer: &mut [u8]) -> Result<(), Error>
     where
         RxDma: crate::usart::RxDma<T>,
@@ -878,6 +1000,17 @@ fn configure(r: Regs, config: &Config, pclk_freq: Hertz, kind: Kind, enable_rx:
 
     let div = found.expect("USART: baudrate too low");
 
+    if config.lin_mode.is_some() {
+        #[cfg(any(usart_v3, usart_v4))]
+        assert!(kind == Kind::Uart, "USART: LIN mode is not available on LPUART");
+        assert!(
+            config.stop_bits == StopBits::STOP1,
+            "USART: LIN mode requires one stop bit"
+        );
+    }
+    #[cfg(not(any(usart_v1, usart_v2)))]
+    assert!(config.de_assertion_time <= 31 && config.de_deassertion_time <= 31);
+
     #[cfg(not(usart_v1))]
     let oversampling = if over8 { "8 bit" } else { "16 bit" };
     #[cfg(usart_v1)]
@@ -897,7 +1030,25 @@ fn configure(r: Regs, config: &Config, pclk_freq: Hertz, kind: Kind, enable_rx:
                 StopBits::STOP1P5 => vals::Stop::STOP1P5,
                 StopBits::STOP2 => vals::Stop::STOP2,
             });
+            if let Some(lin_break_length) = config.lin_mode {
+                w.set_linen(true);
+                w.set_lbdl(match lin_break_length {
+                    LinBreakLength::Bits10 => vals::Lbdl::BIT10,
+                    LinBreakLength::Bits11 => vals::Lbdl::BIT11,
+                });
+            }
         });
+        // The wakeup event can only be selected while the uart is disabled
+        #[cfg(any(usart_v3, usart_v4))]
+        if let Some(event) = config.wakeup_from_stop {
+            r.cr3().modify(|w| {
+                w.set_wus(match event {
+                    WakeupEvent::StartBit => vals::Wus::START,
+                    WakeupEvent::RxNotEmpty => vals::Wus::RXNE,
+                });
+                w.set_wufie(true);
+            });
+        }
         r.cr1().write(|w| {
             // enable uart
             w.set_ue(true);
@@ -905,6 +1056,9 @@ fn configure(r: Regs, config: &Config, pclk_freq: Hertz, kind: Kind, enable_rx:
             w.set_te(enable_tx);
             // enable receiver
             w.set_re(enable_rx);
+            // keep running in STOP modes
+            #[cfg(any(usart_v3, usart_v4))]
+            w.set_uesm(config.wakeup_from_stop.is_some());
             // configure word size
             w.set_m0(if config.parity != Parity::ParityNone {
                 vals::M0::BIT9
@@ -920,11 +1074,21 @@ fn configure(r: Regs, config: &Config, pclk_freq: Hertz, kind: Kind, enable_rx:
             });
             #[cfg(not(usart_v1))]
             w.set_over8(vals::Over8(over8 as _));
+            #[cfg(not(any(usart_v1, usart_v2)))]
+            {
+                w.set_deat(config.de_assertion_time);
+                w.set_dedt(config.de_deassertion_time);
+            }
         });
 
         #[cfg(not(usart_v1))]
         r.cr3().modify(|w| {
             w.set_onebit(config.assume_noise_free);
+            #[cfg(not(any(usart_v1, usart_v2)))]
+            w.set_dep(match config.de_polarity {
+                DePolarity::ActiveHigh => vals::Dep::HIGH,
+                DePolarity::ActiveLow => vals::Dep::LOW,
+            });
         });
     }
 }
@@ -979,6 +1143,7 @@ mod eh1 {
                 Self::Overrun => embedded_hal_1::serial::ErrorKind::Overrun,
                 Self::Parity => embedded_hal_1::serial::ErrorKind::Parity,
                 Self::BufferTooLong => embedded_hal_1::serial::ErrorKind::Other,
+                Self::Break => embedded_hal_1::serial::ErrorKind::Other,
             }
         }
     }
@@ -1132,8 +1297,20 @@ fn sr(r: crate::pac::usart::Usart) -> crate::pac::common::Reg<regs::Sr, crate::p
 
 #[cfg(any(usart_v1, usart_v2))]
 #[allow(unused)]
-unsafe fn clear_interrupt_flags(_r: Regs, _sr: regs::Sr) {
-    // On v1 the flags are cleared implicitly by reads and writes to DR.
+unsafe fn clear_interrupt_flags(r: Regs, sr: regs::Sr) {
+    // On v1 the flags are cleared implicitly by reads and writes to DR,
+    // except for the LIN break flag which is cleared by writing 0.
+    if sr.lbd() {
+        // Writing 1 to the other flags has no effect.
+        let mut w = regs::Sr(0x3FF);
+        w.set_lbd(false);
+        r.sr().write_value(w);
+    }
+}
+
+#[cfg(any(usart_v1, usart_v2))]
+fn send_break(r: Regs) {
+    unsafe { r.cr1().modify(|w| w.set_sbk(true)) };
 }
 
 #[cfg(any(usart_v3, usart_v4))]
@@ -1157,6 +1334,11 @@ unsafe fn clear_interrupt_flags(r: Regs, sr: regs::Isr) {
     r.icr().write(|w| *w = regs::Icr(sr.0));
 }
 
+#[cfg(any(usart_v3, usart_v4))]
+fn send_break(r: Regs) {
+    unsafe { r.rqr().write(|w| w.set_sbkrq(true)) };
+}
+
 pub(crate) mod sealed {
     use embassy_sync::waitqueue::AtomicWaker;
 
@@ -1190,6 +1372,11 @@ pub(crate) mod sealed {
         fn regs() -> Regs;
         fn state() -> &'static State;
 
+        /// Select the kernel clock, returning its frequency.
+        fn kernel_clock(_config: &Config) -> Hertz {
+            Self::frequency()
+        }
+
         #[cfg(feature = "nightly")]
         fn buffered_state() -> &'static buffered::State;
     }
@@ -1215,10 +1402,15 @@ dma_trait!(RxDma, BasicInstance);
 
 macro_rules! impl_usart {
     ($inst:ident, $irq:ident, $kind:expr) => {
+        impl_usart!($inst, $irq, $kind, {});
+    };
+    ($inst:ident, $irq:ident, $kind:expr, { $($extra:tt)* }) => {
         impl sealed::BasicInstance for crate::peripherals::$inst {
             const KIND: Kind = $kind;
             type Interrupt = crate::interrupt::$irq;
 
+            $($extra)*
+
             fn regs() -> Regs {
                 Regs(crate::pac::$inst.0)
             }
@@ -1240,6 +1432,54 @@ macro_rules! impl_usart {
 }
 
 foreach_interrupt!(
+    (LPUART1, usart, LPUART, $signal_name:ident, $irq:ident) => {
+        impl_usart!(LPUART1, $irq, Kind::Lpuart, {
+            #[cfg(any(rcc_l0, rcc_l4, rcc_l5, rcc_g0, rcc_g4, rcc_wb, rcc_wl5, rcc_wle))]
+            fn kernel_clock(config: &Config) -> Hertz {
+                use crate::rcc::sealed::RccPeripheral;
+
+                let (sel, freq) = match config.lpuart_clock_source {
+                    LpuartClockSource::Pclk => (0, Self::frequency()),
+                    LpuartClockSource::Hsi => {
+                        // Keep the HSI running in STOP modes, for the LPUART to wake up the MCU
+                        #[cfg(rcc_l0)]
+                        unsafe {
+                            crate::pac::RCC.cr().modify(|w| {
+                                w.set_hsi16on(true);
+                                w.set_hsi16keron(true);
+                            });
+                            while !crate::pac::RCC.cr().read().hsi16rdyf() {}
+                        }
+                        #[cfg(not(rcc_l0))]
+                        unsafe {
+                            crate::pac::RCC.cr().modify(|w| {
+                                w.set_hsion(true);
+                                w.set_hsikeron(true);
+                            });
+                            while !crate::pac::RCC.cr().read().hsirdy() {}
+                        }
+                        (2, Hertz(16_000_000))
+                    }
+                    LpuartClockSource::Lse => {
+                        unsafe { crate::rcc::enable_lse() };
+                        (3, Hertz(32_768))
+                    }
+                };
+
+                #[cfg(rcc_l0)]
+                let sel = crate::pac::rcc::vals::Uartsel(sel);
+                #[cfg(rcc_g0)]
+                let sel = crate::pac::rcc::vals::Lpuart1sel(sel);
+                #[cfg(not(rcc_l5))]
+                unsafe { crate::pac::RCC.ccipr().modify(|w| w.set_lpuart1sel(sel)) };
+                #[cfg(rcc_l5)]
+                unsafe { crate::pac::RCC.ccipr1().modify(|w| w.set_lpuart1sel(sel)) };
+
+                freq
+            }
+        });
+    };
+
     ($inst:ident, usart, LPUART, $signal_name:ident, $irq:ident) => {
         impl_usart!($inst, $irq, Kind::Lpuart);
     };
@@ -116,22 +116,7 @@ impl<'d, T: BasicInstance, RxDma: super::RxDma<T>> RingBufferedUartRx<'d, T, RxD
             self.start()?;
         }
 
-        // SAFETY: read only and we only use Rx related flags
-        let s = unsafe { sr(r).read() };
-        let has_errors = s.pe() || s.fe() || s.ne() || s.ore();
-        if has_errors {
-            self.teardown_uart();
-
-            if s.pe() {
-                return Err(Error::Parity);
-            } else if s.fe() {
-                return Err(Error::Framing);
-            } else if s.ne() {
-                return Err(Error::Noise);
-            } else {
-                return Err(Error::Overrun);
-            }
-        }
+        self.check_errors()?;
 
         self.ring_buf.reload_position();
         match self.ring_buf.read(buf) {
@@ -163,8 +148,90 @@ impl<'d, T: BasicInstance, RxDma: super::RxDma<T>> RingBufferedUartRx<'d, T, RxD
         Ok(len)
     }
 
-    /// Wait for uart idle or dma half-full or full
-    async fn wait_for_data_or_idle(&mut self) -> Result<(), Error> {
+    /// Read a frame: wait for bytes, then keep reading until the line goes idle, or until `buf`
+    /// is full. Returns the number of bytes read.
+    ///
+    /// As the bytes are received in the background, none are lost between two calls. The
+    /// remainder of a frame longer than `buf` is returned by the next call.
+    ///
+    /// Background receive is started if `start()` has not been previously called.
+    ///
+    /// Receive in the background is terminated if an error is returned.
+    /// It must then manually be started again by calling `start()` or by re-calling `read_until_idle()`.
+    pub async fn read_until_idle(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let r = T::regs();
+
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // Start background receive if it was not already started
+        // SAFETY: read only
+        let is_started = unsafe { r.cr3().read().dmar() };
+        if !is_started {
+            self.start()?;
+        }
+
+        self.check_errors()?;
+
+        let mut len = 0;
+        loop {
+            self.ring_buf.reload_position();
+            match self.ring_buf.read(&mut buf[len..]) {
+                Ok(n) => len += n,
+                Err(OverrunError) => {
+                    // Stop any transfer from now on
+                    // The user must re-start to receive any more data
+                    self.teardown_uart();
+                    return Err(Error::Overrun);
+                }
+            }
+
+            if len == buf.len() {
+                return Ok(len);
+            }
+
+            let idle = self.wait_for_data_or_idle().await?;
+            if idle && len > 0 {
+                // Get the last bytes of the frame, which may wrap around the end of the ring buffer
+                while len < buf.len() {
+                    self.ring_buf.reload_position();
+                    match self.ring_buf.read(&mut buf[len..]).map_err(|_err| Error::Overrun)? {
+                        0 => break,
+                        n => len += n,
+                    }
+                }
+                return Ok(len);
+            }
+        }
+    }
+
+    /// Check for reception errors, stopping the background receive if there are any
+    fn check_errors(&mut self) -> Result<(), Error> {
+        let r = T::regs();
+
+        // SAFETY: read only and we only use Rx related flags
+        let s = unsafe { sr(r).read() };
+        let has_errors = s.pe() || s.fe() || s.ne() || s.ore();
+        if has_errors {
+            self.teardown_uart();
+
+            if s.pe() {
+                return Err(Error::Parity);
+            } else if s.fe() {
+                return Err(Error::Framing);
+            } else if s.ne() {
+                return Err(Error::Noise);
+            } else {
+                return Err(Error::Overrun);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wait for uart idle or dma half-full or full, returning `true` if idle line was detected
+    async fn wait_for_data_or_idle(&mut self) -> Result<bool, Error> {
         let r = T::regs();
 
         // make sure USART state is restored to neutral state
@@ -242,8 +309,8 @@ impl<'d, T: BasicInstance, RxDma: super::RxDma<T>> RingBufferedUartRx<'d, T, RxD
         });
 
         match select(dma, uart).await {
-            Either::Left(((), _)) => Ok(()),
-            Either::Right((Ok(()), _)) => Ok(()),
+            Either::Left(((), _)) => Ok(false),
+            Either::Right((Ok(()), _)) => Ok(true),
             Either::Right((Err(e), _)) => {
                 self.teardown_uart();
                 Err(e)
@@ -642,7 +642,24 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
     async fn disable(&mut self) {}
 
     async fn remote_wakeup(&mut self) -> Result<(), Unsupported> {
-        Err(Unsupported)
+        let regs = T::regs();
+
+        // Leave suspend mode, and send the resume signaling, which must last from 1 to 15 ms.
+        unsafe {
+            regs.cntr().modify(|w| {
+                w.set_fsusp(false);
+                w.set_lpmode(false);
+                w.set_resume(true);
+            })
+        };
+
+        #[cfg(feature = "time")]
+        embassy_time::Timer::after(embassy_time::Duration::from_millis(5)).await;
+        #[cfg(not(feature = "time"))]
+        cortex_m::asm::delay(unsafe { crate::rcc::get_freqs() }.sys.0 / 200);
+
+        unsafe { regs.cntr().modify(|w| w.set_resume(false)) };
+        Ok(())
     }
 }
 
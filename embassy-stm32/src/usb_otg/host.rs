@@ -0,0 +1,1121 @@
+//! USB host mode.
+//!
+//! The OTG peripheral talks to a single device plugged in its port, using the internal
+//! full-speed PHY. Full-speed and low-speed devices are supported, there is no support for hubs.
+//!
+//! The host doesn't switch the power of the port (VBUS) on its own: boards usually have a power
+//! switch driven by a GPIO, which must be set by the application.
+//!
+//! Transfers happen on channels, which are allocated for an endpoint of the device. Each channel
+//! runs one transaction at a time, and several channels can be used concurrently.
+
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use core::task::Poll;
+
+use atomic_polyfill::{AtomicU16, AtomicU8, Ordering};
+use embassy_cortex_m::interrupt::{Interrupt, InterruptExt};
+use embassy_hal_common::drop::OnDrop;
+use embassy_hal_common::{into_ref, Peripheral};
+use embassy_sync::waitqueue::AtomicWaker;
+pub use embassy_usb_driver::{Direction, EndpointAddress, EndpointType};
+use futures::future::poll_fn;
+
+use super::usb::{power_down, power_up};
+use super::{DmPin, DpPin, Instance, PhyType};
+use crate::gpio::sealed::AFType;
+use crate::interrupt;
+use crate::pac::otg::{regs, vals};
+use crate::rcc::sealed::RccPeripheral;
+
+const PID_DATA0: u8 = 0;
+const PID_DATA1: u8 = 2;
+const PID_SETUP: u8 = 3;
+
+const STATUS_PENDING: u8 = 0;
+const STATUS_DONE: u8 = 1;
+const STATUS_NAK: u8 = 2;
+const STATUS_STALL: u8 = 3;
+const STATUS_ERROR: u8 = 4;
+
+/// Number of attempts for a transaction failing with an error, as allowed by the USB specification.
+const MAX_ATTEMPTS: usize = 3;
+
+const REQUEST_SET_ADDRESS: u8 = 0x05;
+const REQUEST_GET_DESCRIPTOR: u8 = 0x06;
+const REQUEST_SET_CONFIGURATION: u8 = 0x09;
+
+/// Device descriptor type.
+pub const DESCRIPTOR_TYPE_DEVICE: u8 = 0x01;
+/// Configuration descriptor type.
+pub const DESCRIPTOR_TYPE_CONFIGURATION: u8 = 0x02;
+/// String descriptor type.
+pub const DESCRIPTOR_TYPE_STRING: u8 = 0x03;
+
+/// Host error.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The device was disconnected.
+    Disconnected,
+    /// The device answered with a STALL handshake.
+    Stall,
+    /// The transaction failed after several attempts, because of timeouts, CRC, babble or data
+    /// toggle errors.
+    Transaction,
+    /// The device sent more data than the buffer can hold.
+    BufferOverflow,
+    /// All host channels are in use.
+    NoFreeChannel,
+    /// The device sent an invalid descriptor.
+    InvalidDescriptor,
+}
+
+/// Speed of the device connected to the port.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Speed {
+    /// Low speed (1.5 Mbit/s)
+    Low,
+    /// Full speed (12 Mbit/s)
+    Full,
+    /// High speed (480 Mbit/s)
+    High,
+}
+
+/// SETUP packet of a control transfer.
+///
+/// The length of the data stage is given by the buffer passed to the transfer.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SetupPacket {
+    /// bmRequestType, the direction bit is set by the transfer.
+    pub request_type: u8,
+    /// bRequest
+    pub request: u8,
+    /// wValue
+    pub value: u16,
+    /// wIndex
+    pub index: u16,
+}
+
+impl SetupPacket {
+    fn to_bytes(self, direction: Direction, length: u16) -> [u8; 8] {
+        let request_type = match direction {
+            Direction::In => self.request_type | 0x80,
+            Direction::Out => self.request_type & !0x80,
+        };
+        let value = self.value.to_le_bytes();
+        let index = self.index.to_le_bytes();
+        let length = length.to_le_bytes();
+        [
+            request_type,
+            self.request,
+            value[0],
+            value[1],
+            index[0],
+            index[1],
+            length[0],
+            length[1],
+        ]
+    }
+}
+
+/// Device descriptor of the connected device.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceDescriptor {
+    /// bcdUSB
+    pub usb_version: u16,
+    /// bDeviceClass
+    pub device_class: u8,
+    /// bDeviceSubClass
+    pub device_sub_class: u8,
+    /// bDeviceProtocol
+    pub device_protocol: u8,
+    /// bMaxPacketSize0
+    pub max_packet_size_0: u8,
+    /// idVendor
+    pub vendor_id: u16,
+    /// idProduct
+    pub product_id: u16,
+    /// bcdDevice
+    pub device_release: u16,
+    /// iManufacturer
+    pub manufacturer: u8,
+    /// iProduct
+    pub product: u8,
+    /// iSerialNumber
+    pub serial_number: u8,
+    /// bNumConfigurations
+    pub num_configurations: u8,
+}
+
+impl DeviceDescriptor {
+    fn parse(buf: &[u8]) -> Result<Self, Error> {
+        if buf.len() < 18 || buf[0] < 18 || buf[1] != DESCRIPTOR_TYPE_DEVICE {
+            return Err(Error::InvalidDescriptor);
+        }
+
+        Ok(Self {
+            usb_version: u16::from_le_bytes([buf[2], buf[3]]),
+            device_class: buf[4],
+            device_sub_class: buf[5],
+            device_protocol: buf[6],
+            max_packet_size_0: buf[7],
+            vendor_id: u16::from_le_bytes([buf[8], buf[9]]),
+            product_id: u16::from_le_bytes([buf[10], buf[11]]),
+            device_release: u16::from_le_bytes([buf[12], buf[13]]),
+            manufacturer: buf[14],
+            product: buf[15],
+            serial_number: buf[16],
+            num_configurations: buf[17],
+        })
+    }
+}
+
+/// Interrupt handler for host mode.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let r = T::regs();
+        let state = T::host_state();
+
+        // SAFETY: atomic read with no side effects
+        let ints = unsafe { r.gintsts().read() };
+
+        if ints.hprtint() {
+            // SAFETY: HPRT is shared with `Host`, the write-1-to-clear bits are only cleared here
+            critical_section::with(|_| unsafe {
+                let hprt = r.hprt().read();
+                trace!("port irq val={:b}", hprt.0);
+
+                let mut w = hprt_preserve(hprt);
+                w.set_pcdet(hprt.pcdet());
+                w.set_penchng(hprt.penchng());
+                w.set_pocchng(hprt.pocchng());
+                r.hprt().write_value(w);
+            });
+
+            state.port_waker.wake();
+            state.wake_channels();
+        }
+
+        if ints.discint() {
+            trace!("disconnect");
+            // SAFETY: atomic clear on rc_w1 register
+            unsafe { r.gintsts().write(|w| w.set_discint(true)) };
+
+            state.port_waker.wake();
+            state.wake_channels();
+        }
+
+        // SAFETY: atomic read with no side effects
+        if ints.sof() && unsafe { r.gintmsk().read().sofm() } {
+            // Frame waits are the only users of SOF interrupts, which are enabled again while waiting.
+            // SAFETY: GINTMSK is shared with `Channel` so critical section is needed for RMW
+            critical_section::with(|_| unsafe { r.gintmsk().modify(|w| w.set_sofm(false)) });
+            // SAFETY: atomic clear on rc_w1 register
+            unsafe { r.gintsts().write(|w| w.set_sof(true)) };
+
+            state.wake_channels();
+        }
+
+        // Handle RX before channel interrupts, so that IN data is available on transfer completion.
+        // SAFETY: atomic read with no side effects
+        while unsafe { r.gintsts().read().rxflvl() } {
+            // SAFETY: atomic "pop" register
+            let status = unsafe { r.grxstsp().read() };
+            let ch = status.epnum() as usize;
+            let len = status.bcnt() as usize;
+
+            match status.pktstsh() {
+                vals::Pktstsh::IN_DATA_RX => {
+                    trace!("IN_DATA_RX ch={} len={}", ch, len);
+
+                    let received = state.ch_received[ch].load(Ordering::Relaxed) as usize;
+                    let capacity = state.ch_capacity[ch].load(Ordering::Acquire) as usize;
+                    // SAFETY: the buffer is valid for `capacity` bytes while a transaction is pending,
+                    // its `Channel` sets the capacity to 0 before releasing it
+                    let buf = unsafe { core::slice::from_raw_parts_mut(*state.ch_buffers[ch].get(), capacity) };
+
+                    for i in (0..len).step_by(4) {
+                        // RX FIFO is shared so always read from fifo(0)
+                        // SAFETY: FIFO reads are exclusive to IRQ
+                        let data = unsafe { r.fifo(0).read().0 }.to_ne_bytes();
+                        for (j, byte) in data.iter().enumerate().take(len - i) {
+                            if let Some(b) = buf.get_mut(received + i + j) {
+                                *b = *byte;
+                            }
+                        }
+                    }
+
+                    // A total larger than the capacity is reported as an overflow.
+                    state.ch_received[ch].store((received + len) as u16, Ordering::Release);
+                }
+                vals::Pktstsh::IN_DATA_DONE => {
+                    trace!("IN_DATA_DONE ch={}", ch);
+                }
+                x => trace!("unknown PKTSTS: {}", x.0),
+            }
+        }
+
+        if ints.hcint() {
+            // SAFETY: atomic read with no side effects
+            let mut ch_mask = unsafe { r.haint().read().haint() };
+            let mut ch = 0;
+
+            // Iterate over channels while there are non-zero bits in the mask
+            while ch_mask != 0 {
+                if ch_mask & 1 != 0 {
+                    on_channel_interrupt::<T>(ch);
+                }
+
+                ch_mask >>= 1;
+                ch += 1;
+            }
+        }
+    }
+}
+
+fn on_channel_interrupt<T: Instance>(ch: usize) {
+    let r = T::regs();
+    let state = T::host_state();
+
+    // SAFETY: HCINT is exclusive to IRQ
+    let ints = unsafe { r.hcint(ch).read() };
+    unsafe { r.hcint(ch).write_value(ints) };
+    // SAFETY: atomic read with no side effects
+    let hcchar = unsafe { r.hcchar(ch).read() };
+    trace!("ch={} irq val={:b}", ch, ints.0);
+
+    let cause = if ints.xfrc() {
+        Some(STATUS_DONE)
+    } else if ints.stall() {
+        Some(STATUS_STALL)
+    } else if ints.nak() {
+        if hcchar.epdir() && hcchar.eptyp() != vals::Eptyp::INTERRUPT {
+            // Keep polling until the device has data
+            // SAFETY: HCCHAR is not accessed by `Channel` while the channel is enabled
+            unsafe {
+                r.hcchar(ch).modify(|w| {
+                    w.set_chdis(false);
+                    w.set_chena(true);
+                })
+            };
+            None
+        } else {
+            Some(STATUS_NAK)
+        }
+    } else if ints.txerr() || ints.bberr() || ints.dterr() || ints.frmor() {
+        Some(STATUS_ERROR)
+    } else {
+        None
+    };
+
+    match cause {
+        Some(STATUS_DONE) => state.finish(ch, STATUS_DONE),
+        Some(cause) if ints.chh() || !hcchar.chena() => state.finish(ch, cause),
+        Some(cause) => {
+            // The transaction ends once the channel is halted.
+            state.ch_halt_cause[ch].store(cause, Ordering::Relaxed);
+            // SAFETY: HCCHAR is not accessed by `Channel` while the channel is enabled
+            unsafe {
+                r.hcchar(ch).modify(|w| {
+                    w.set_chdis(true);
+                    w.set_chena(true);
+                })
+            };
+        }
+        None if ints.chh() => {
+            let cause = state.ch_halt_cause[ch].swap(STATUS_PENDING, Ordering::Relaxed);
+            let status = if cause == STATUS_PENDING { STATUS_ERROR } else { cause };
+            state.finish(ch, status);
+        }
+        None => {}
+    }
+}
+
+/// Returns HPRT with the write-1-to-clear bits cleared, so that it can be written back safely.
+fn hprt_preserve(mut hprt: regs::Hprt) -> regs::Hprt {
+    hprt.set_pena(false);
+    hprt.set_pcdet(false);
+    hprt.set_penchng(false);
+    hprt.set_pocchng(false);
+    hprt
+}
+
+/// Modifies HPRT without clearing its write-1-to-clear bits.
+fn hprt_modify<T: Instance>(f: impl FnOnce(&mut regs::Hprt)) {
+    let r = T::regs();
+    // SAFETY: HPRT is shared with IRQ so critical section is needed for RMW
+    critical_section::with(|_| unsafe {
+        let mut hprt = hprt_preserve(r.hprt().read());
+        f(&mut hprt);
+        r.hprt().write_value(hprt);
+    });
+}
+
+fn is_connected<T: Instance>() -> bool {
+    // SAFETY: atomic read with no side effects
+    unsafe { T::regs().hprt().read().pcsts() }
+}
+
+#[cfg_attr(feature = "time", allow(clippy::extra_unused_type_parameters))]
+async fn delay_ms<T: Instance>(ms: u32) {
+    #[cfg(feature = "time")]
+    embassy_time::Timer::after(embassy_time::Duration::from_millis(ms as u64)).await;
+    #[cfg(not(feature = "time"))]
+    cortex_m::asm::delay(T::frequency().0 / 1000 * ms);
+}
+
+/// Internal state for host mode.
+pub struct State<const CH_COUNT: usize> {
+    port_waker: AtomicWaker,
+    ch_wakers: [AtomicWaker; CH_COUNT],
+    /// Channels in use, one bit per channel.
+    ch_allocated: AtomicU16,
+    /// Status of the current transaction of each channel.
+    ch_status: [AtomicU8; CH_COUNT],
+    /// Status of a transaction ended by halting the channel, set once halted.
+    ch_halt_cause: [AtomicU8; CH_COUNT],
+    /// Buffers receiving IN data, valid for [State::ch_capacity] bytes.
+    ch_buffers: [UnsafeCell<*mut u8>; CH_COUNT],
+    ch_capacity: [AtomicU16; CH_COUNT],
+    ch_received: [AtomicU16; CH_COUNT],
+}
+
+unsafe impl<const CH_COUNT: usize> Send for State<CH_COUNT> {}
+unsafe impl<const CH_COUNT: usize> Sync for State<CH_COUNT> {}
+
+impl<const CH_COUNT: usize> State<CH_COUNT> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    pub const fn new() -> Self {
+        const NEW_AW: AtomicWaker = AtomicWaker::new();
+        const NEW_STATUS: AtomicU8 = AtomicU8::new(STATUS_PENDING);
+        // Not null, so an empty slice can be made from it before a buffer is set
+        const NEW_BUF: UnsafeCell<*mut u8> = UnsafeCell::new(NonNull::dangling().as_ptr());
+        const NEW_SIZE: AtomicU16 = AtomicU16::new(0);
+
+        Self {
+            port_waker: NEW_AW,
+            ch_wakers: [NEW_AW; CH_COUNT],
+            ch_allocated: AtomicU16::new(0),
+            ch_status: [NEW_STATUS; CH_COUNT],
+            ch_halt_cause: [NEW_STATUS; CH_COUNT],
+            ch_buffers: [NEW_BUF; CH_COUNT],
+            ch_capacity: [NEW_SIZE; CH_COUNT],
+            ch_received: [NEW_SIZE; CH_COUNT],
+        }
+    }
+
+    fn finish(&self, ch: usize, status: u8) {
+        self.ch_status[ch].store(status, Ordering::Release);
+        self.ch_wakers[ch].wake();
+    }
+
+    fn wake_channels(&self) {
+        for waker in self.ch_wakers.iter() {
+            waker.wake();
+        }
+    }
+}
+
+/// USB host driver.
+pub struct Host<'d, T: Instance> {
+    phantom: PhantomData<&'d mut T>,
+}
+
+impl<'d, T: Instance> Host<'d, T> {
+    /// Initializes USB OTG peripheral as a host with internal Full-Speed PHY.
+    ///
+    /// The port is powered on, but VBUS must be supplied to the device by the application.
+    pub fn new_fs(
+        _peri: impl Peripheral<P = T> + 'd,
+        _irq: impl interrupt::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        dp: impl Peripheral<P = impl DpPin<T>> + 'd,
+        dm: impl Peripheral<P = impl DmPin<T>> + 'd,
+    ) -> Self {
+        into_ref!(dp, dm);
+
+        unsafe {
+            dp.set_as_af(dp.af_num(), AFType::OutputPushPull);
+            dm.set_as_af(dm.af_num(), AFType::OutputPushPull);
+        }
+
+        // SAFETY: the peripheral is owned by `Host`
+        unsafe {
+            power_up::<T>(PhyType::InternalFullSpeed);
+
+            <T as RccPeripheral>::enable();
+            <T as RccPeripheral>::reset();
+
+            T::Interrupt::steal().unpend();
+            T::Interrupt::steal().enable();
+
+            Self::init();
+        }
+
+        Self { phantom: PhantomData }
+    }
+
+    unsafe fn init() {
+        let r = T::regs();
+        let core_id = r.cid().read().0;
+        info!("Core id {:08x}", core_id);
+
+        // Wait for AHB ready.
+        while !r.grstctl().read().ahbidl() {}
+
+        // Configure as host.
+        r.gusbcfg().write(|w| {
+            // Force host mode
+            w.set_fhmod(true);
+            // Enable internal full-speed PHY
+            w.set_physel(true);
+        });
+
+        // Configuring Vbus sense and SOF output
+        match core_id {
+            0x0000_1200 | 0x0000_1100 => {
+                r.gccfg_v1().modify(|w| {
+                    // Enable internal full-speed PHY, logic is inverted
+                    w.set_pwrdwn(true);
+                    w.set_novbussens(true);
+                    w.set_vbusasen(false);
+                    w.set_vbusbsen(false);
+                    w.set_sofouten(false);
+                });
+            }
+            0x0000_2000 | 0x0000_2100 | 0x0000_2300 | 0x0000_3000 | 0x0000_3100 => {
+                r.gccfg_v2().modify(|w| {
+                    // Enable internal full-speed PHY, logic is inverted
+                    w.set_pwrdwn(true);
+                    w.set_vbden(false);
+                });
+            }
+            _ => unimplemented!("Unknown USB core id {:X}", core_id),
+        }
+
+        // Wait for the switch to host mode, which takes up to 25 ms.
+        while !r.gintsts().read().cmod() {}
+
+        // FS/LS PHY clock at 48 MHz, until a low-speed device is connected.
+        r.hcfg().write(|w| {
+            w.set_fslspcs(1);
+            w.set_fslss(true);
+        });
+
+        // Half of the FIFO is used for reception, the rest is shared by the non-periodic
+        // (control and bulk) and periodic (interrupt) transmit queues.
+        let rx_fifo_size_words = T::FIFO_DEPTH_WORDS / 2;
+        let tx_fifo_size_words = T::FIFO_DEPTH_WORDS / 4;
+        r.grxfsiz().write(|w| w.set_rxfd(rx_fifo_size_words));
+        r.hnptxfsiz().write(|w| {
+            w.set_sa(rx_fifo_size_words);
+            w.set_fd(tx_fifo_size_words);
+        });
+        r.hptxfsiz().write(|w| {
+            w.set_sa(rx_fifo_size_words + tx_fifo_size_words);
+            w.set_fd(tx_fifo_size_words);
+        });
+
+        // Flush FIFOs
+        r.grstctl().write(|w| {
+            w.set_txfflsh(true);
+            w.set_txfnum(0x10);
+        });
+        while r.grstctl().read().txfflsh() {}
+        r.grstctl().write(|w| w.set_rxfflsh(true));
+        while r.grstctl().read().rxfflsh() {}
+
+        // Unmask and clear core interrupts
+        for ch in 0..T::CHANNEL_COUNT {
+            r.hcint(ch).write_value(regs::Hcint(0xFFFF_FFFF));
+        }
+        r.gintsts().write_value(regs::Gintsts(0xFFFF_FFFF));
+        r.gintmsk().write(|w| {
+            w.set_rxflvlm(true);
+            w.set_prtim(true);
+            w.set_hcim(true);
+            w.set_discint(true);
+        });
+
+        // Unmask global interrupt
+        r.gahbcfg().write(|w| w.set_gint(true));
+
+        // Power on the port
+        hprt_modify::<T>(|w| w.set_ppwr(true));
+    }
+
+    /// Returns whether a device is connected to the port.
+    pub fn is_connected(&self) -> bool {
+        is_connected::<T>()
+    }
+
+    /// Waits for a device to be connected, and resets it.
+    ///
+    /// Returns the speed of the device, which is ready for enumeration.
+    pub async fn wait_for_device(&self) -> Speed {
+        loop {
+            poll_fn(|cx| {
+                T::host_state().port_waker.register(cx.waker());
+                if is_connected::<T>() {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await;
+
+            trace!("device connected");
+
+            // Wait for the connection to be stable, as required by the USB specification.
+            delay_ms::<T>(100).await;
+
+            if let Ok(speed) = self.reset().await {
+                return speed;
+            }
+        }
+    }
+
+    /// Waits for the device to be disconnected.
+    pub async fn wait_for_disconnect(&self) {
+        poll_fn(|cx| {
+            T::host_state().port_waker.register(cx.waker());
+            if is_connected::<T>() {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await
+    }
+
+    /// Resets the device connected to the port.
+    ///
+    /// Returns the speed of the device. The device then has the default address 0.
+    pub async fn reset(&self) -> Result<Speed, Error> {
+        let r = T::regs();
+
+        loop {
+            trace!("port reset");
+
+            hprt_modify::<T>(|w| w.set_prst(true));
+            delay_ms::<T>(15).await;
+            hprt_modify::<T>(|w| w.set_prst(false));
+
+            poll_fn(|cx| {
+                T::host_state().port_waker.register(cx.waker());
+                // SAFETY: atomic read with no side effects
+                let hprt = unsafe { r.hprt().read() };
+                if !hprt.pcsts() {
+                    Poll::Ready(Err(Error::Disconnected))
+                } else if hprt.pena() {
+                    Poll::Ready(Ok(()))
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await?;
+
+            // SAFETY: atomic read with no side effects
+            let speed = match unsafe { r.hprt().read().pspd() } {
+                0 => Speed::High,
+                1 => Speed::Full,
+                _ => Speed::Low,
+            };
+
+            // The PHY clock is 6 MHz for low-speed devices, and 48 MHz otherwise. The port must be
+            // reset again when it changes.
+            let (fslspcs, frame_interval) = match speed {
+                Speed::Low => (2, 6000),
+                _ => (1, 48000),
+            };
+            // SAFETY: HCFG and HFIR are only accessed by `Host`
+            unsafe {
+                r.hfir().write(|w| w.set_frivl(frame_interval));
+                if r.hcfg().read().fslspcs() != fslspcs {
+                    r.hcfg().modify(|w| w.set_fslspcs(fslspcs));
+                    continue;
+                }
+            }
+
+            // Reset recovery time
+            delay_ms::<T>(20).await;
+
+            trace!("port enabled, speed={:?}", speed);
+            return Ok(speed);
+        }
+    }
+
+    /// Allocates a channel for an endpoint of the device at `device_address`.
+    ///
+    /// Control channels are bidirectional, the direction of `endpoint` is ignored for them.
+    /// `interval_ms` is the polling interval of interrupt endpoints.
+    pub fn alloc_channel(
+        &self,
+        device_address: u8,
+        endpoint: EndpointAddress,
+        ep_type: EndpointType,
+        max_packet_size: u16,
+        interval_ms: u8,
+    ) -> Result<Channel<'_, T>, Error> {
+        let state = T::host_state();
+
+        let index = critical_section::with(|_| {
+            let allocated = state.ch_allocated.load(Ordering::Relaxed);
+            let index = (0..T::CHANNEL_COUNT).find(|i| allocated & (1 << i) == 0)?;
+            state.ch_allocated.store(allocated | (1 << index), Ordering::Relaxed);
+            Some(index)
+        })
+        .ok_or(Error::NoFreeChannel)?;
+
+        trace!(
+            "allocating ch={} addr={} ep={:?} type={:?} mps={}",
+            index,
+            device_address,
+            endpoint,
+            ep_type,
+            max_packet_size
+        );
+
+        // SAFETY: HAINTMSK is shared between channels so critical section is needed for RMW
+        critical_section::with(|_| unsafe {
+            T::regs().haintmsk().modify(|w| w.set_haintm(w.haintm() | (1 << index)));
+        });
+
+        Ok(Channel {
+            _phantom: PhantomData,
+            index,
+            device_address,
+            endpoint,
+            ep_type,
+            max_packet_size,
+            interval_ms,
+            data_toggle: false,
+        })
+    }
+
+    /// Enumerates the device connected to the port, assigning it `device_address`.
+    ///
+    /// Returns the device descriptor, and a control channel to configure the device.
+    pub async fn enumerate(&self, device_address: u8) -> Result<(DeviceDescriptor, Channel<'_, T>), Error> {
+        let mut control = self.alloc_channel(
+            0,
+            EndpointAddress::from_parts(0, Direction::Out),
+            EndpointType::Control,
+            8,
+            0,
+        )?;
+
+        // Read the beginning of the device descriptor, to get the max packet size of EP0.
+        let mut buf = [0; 18];
+        let n = control.get_descriptor(DESCRIPTOR_TYPE_DEVICE, 0, &mut buf[..8]).await?;
+        if n < 8 {
+            return Err(Error::InvalidDescriptor);
+        }
+        control.set_max_packet_size(buf[7] as u16);
+
+        control
+            .control_out(
+                &SetupPacket {
+                    request_type: 0x00,
+                    request: REQUEST_SET_ADDRESS,
+                    value: device_address as u16,
+                    index: 0,
+                },
+                &[],
+            )
+            .await?;
+
+        // Set address recovery time
+        delay_ms::<T>(2).await;
+        control.set_device_address(device_address);
+
+        let n = control.get_descriptor(DESCRIPTOR_TYPE_DEVICE, 0, &mut buf).await?;
+        let descriptor = DeviceDescriptor::parse(&buf[..n])?;
+        debug!("device enumerated: {:?}", descriptor);
+
+        Ok((descriptor, control))
+    }
+}
+
+impl<'d, T: Instance> Drop for Host<'d, T> {
+    fn drop(&mut self) {
+        hprt_modify::<T>(|w| w.set_ppwr(false));
+        power_down::<T>();
+    }
+}
+
+/// Host channel, used for transfers with an endpoint of the device.
+pub struct Channel<'h, T: Instance> {
+    _phantom: PhantomData<&'h T>,
+    index: usize,
+    device_address: u8,
+    endpoint: EndpointAddress,
+    ep_type: EndpointType,
+    max_packet_size: u16,
+    interval_ms: u8,
+    data_toggle: bool,
+}
+
+impl<'h, T: Instance> Channel<'h, T> {
+    /// Sets the address of the device, after it was assigned one.
+    pub fn set_device_address(&mut self, device_address: u8) {
+        self.device_address = device_address;
+    }
+
+    /// Sets the max packet size of the endpoint.
+    pub fn set_max_packet_size(&mut self, max_packet_size: u16) {
+        self.max_packet_size = max_packet_size;
+    }
+
+    /// Resets the data toggle, after the endpoint was reset by a SET_CONFIGURATION,
+    /// SET_INTERFACE or CLEAR_FEATURE(ENDPOINT_HALT) request.
+    pub fn reset_data_toggle(&mut self) {
+        self.data_toggle = false;
+    }
+
+    /// Runs a control transfer reading data from the device.
+    ///
+    /// Returns the number of bytes read, up to the length of `buf`.
+    pub async fn control_in(&mut self, setup: &SetupPacket, buf: &mut [u8]) -> Result<usize, Error> {
+        assert!(self.ep_type == EndpointType::Control);
+
+        let setup = setup.to_bytes(Direction::In, buf.len() as u16);
+        self.transaction_out(PID_SETUP, &setup).await?;
+
+        // Data stage
+        let mut len = 0;
+        let mut pid = PID_DATA1;
+        while len < buf.len() {
+            let n = self.transaction_in(pid, &mut buf[len..]).await?;
+            len += n;
+            pid = if pid == PID_DATA1 { PID_DATA0 } else { PID_DATA1 };
+            if n < self.max_packet_size as usize {
+                break;
+            }
+        }
+
+        // Status stage
+        self.transaction_out(PID_DATA1, &[]).await?;
+
+        Ok(len)
+    }
+
+    /// Runs a control transfer writing `data` to the device.
+    pub async fn control_out(&mut self, setup: &SetupPacket, data: &[u8]) -> Result<(), Error> {
+        assert!(self.ep_type == EndpointType::Control);
+
+        let setup = setup.to_bytes(Direction::Out, data.len() as u16);
+        self.transaction_out(PID_SETUP, &setup).await?;
+
+        // Data stage
+        let mut pid = PID_DATA1;
+        for chunk in data.chunks(self.max_packet_size as usize) {
+            self.transaction_out(pid, chunk).await?;
+            pid = if pid == PID_DATA1 { PID_DATA0 } else { PID_DATA1 };
+        }
+
+        // Status stage
+        self.transaction_in(PID_DATA1, &mut []).await?;
+
+        Ok(())
+    }
+
+    /// Reads a descriptor of the device with a GET_DESCRIPTOR request.
+    pub async fn get_descriptor(&mut self, descriptor_type: u8, index: u8, buf: &mut [u8]) -> Result<usize, Error> {
+        let setup = SetupPacket {
+            request_type: 0x00,
+            request: REQUEST_GET_DESCRIPTOR,
+            value: (descriptor_type as u16) << 8 | index as u16,
+            index: 0,
+        };
+        self.control_in(&setup, buf).await
+    }
+
+    /// Selects a configuration of the device with a SET_CONFIGURATION request.
+    pub async fn set_configuration(&mut self, configuration: u8) -> Result<(), Error> {
+        let setup = SetupPacket {
+            request_type: 0x00,
+            request: REQUEST_SET_CONFIGURATION,
+            value: configuration as u16,
+            index: 0,
+        };
+        self.control_out(&setup, &[]).await
+    }
+
+    /// Reads data from a bulk or interrupt IN endpoint.
+    ///
+    /// Packets are read until a short packet is received or `buf` is full. Interrupt endpoints
+    /// are polled at their interval until the device has data.
+    pub async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        assert!(self.ep_type != EndpointType::Control && self.endpoint.is_in());
+
+        let mut len = 0;
+        loop {
+            let pid = self.data_pid();
+            let n = self.transaction_in(pid, &mut buf[len..]).await?;
+            self.data_toggle = !self.data_toggle;
+            len += n;
+            if n < self.max_packet_size as usize || len == buf.len() {
+                return Ok(len);
+            }
+        }
+    }
+
+    /// Writes data to a bulk or interrupt OUT endpoint.
+    ///
+    /// `data` is split into packets of the max packet size, an empty `data` sends a zero-length
+    /// packet.
+    pub async fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        assert!(self.ep_type != EndpointType::Control && self.endpoint.is_out());
+
+        if data.is_empty() {
+            let pid = self.data_pid();
+            self.transaction_out(pid, &[]).await?;
+            self.data_toggle = !self.data_toggle;
+        }
+
+        for chunk in data.chunks(self.max_packet_size as usize) {
+            let pid = self.data_pid();
+            self.transaction_out(pid, chunk).await?;
+            self.data_toggle = !self.data_toggle;
+        }
+
+        Ok(())
+    }
+
+    fn data_pid(&self) -> u8 {
+        if self.data_toggle {
+            PID_DATA1
+        } else {
+            PID_DATA0
+        }
+    }
+
+    fn is_periodic(&self) -> bool {
+        matches!(self.ep_type, EndpointType::Interrupt | EndpointType::Isochronous)
+    }
+
+    async fn transaction_in(&mut self, pid: u8, buf: &mut [u8]) -> Result<usize, Error> {
+        self.transaction(Direction::In, pid, buf.as_mut_ptr(), buf.len()).await
+    }
+
+    async fn transaction_out(&mut self, pid: u8, data: &[u8]) -> Result<(), Error> {
+        self.transaction(Direction::Out, pid, data.as_ptr() as *mut u8, data.len())
+            .await
+            .map(|_| ())
+    }
+
+    /// Runs a single-packet transaction, retrying on NAK and on errors.
+    async fn transaction(&mut self, dir: Direction, pid: u8, buf: *mut u8, len: usize) -> Result<usize, Error> {
+        let mut attempts = 0;
+        loop {
+            match self.transaction_once(dir, pid, buf, len).await? {
+                STATUS_DONE => {
+                    if dir == Direction::Out {
+                        return Ok(len);
+                    }
+
+                    let received = T::host_state().ch_received[self.index].load(Ordering::Acquire) as usize;
+                    if received > len {
+                        return Err(Error::BufferOverflow);
+                    }
+                    return Ok(received);
+                }
+                STATUS_NAK => {
+                    // The device isn't ready yet, try again later.
+                    let frames = if self.ep_type == EndpointType::Interrupt {
+                        self.interval_ms.max(1) as u16
+                    } else {
+                        1
+                    };
+                    self.wait_frames(frames).await?;
+                }
+                STATUS_STALL => return Err(Error::Stall),
+                _ => {
+                    attempts += 1;
+                    if attempts == MAX_ATTEMPTS {
+                        return Err(Error::Transaction);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn transaction_once(&mut self, dir: Direction, pid: u8, buf: *mut u8, len: usize) -> Result<u8, Error> {
+        let r = T::regs();
+        let state = T::host_state();
+        let index = self.index;
+
+        // Wait for the end of a previous halt request
+        // SAFETY: atomic read with no side effects
+        while unsafe { r.hcchar(index).read().chena() } {
+            self.wait_frames(1).await?;
+        }
+
+        let size_words = (len + 3) / 4;
+        if dir == Direction::Out {
+            // Wait for space in the transmit FIFO and queue, which might be used by other channels.
+            loop {
+                // SAFETY: atomic reads with no side effects
+                let (fifo_space, queue_space) = unsafe {
+                    if self.is_periodic() {
+                        let hptxsts = r.hptxsts().read();
+                        (hptxsts.ptxfsavl() as usize, hptxsts.ptxqsav())
+                    } else {
+                        let hnptxsts = r.hnptxsts().read();
+                        (hnptxsts.nptxfsav() as usize, hnptxsts.nptqxsav())
+                    }
+                };
+                if fifo_space >= size_words && queue_space > 0 {
+                    break;
+                }
+                self.wait_frames(1).await?;
+            }
+        } else {
+            state.ch_received[index].store(0, Ordering::Relaxed);
+            // SAFETY: the IRQ doesn't access the buffer while the capacity is 0
+            unsafe { *state.ch_buffers[index].get() = buf };
+            state.ch_capacity[index].store(len as u16, Ordering::Release);
+        }
+
+        // Stop the transaction and release the buffer if the future is dropped.
+        let _on_drop = OnDrop::new(|| {
+            state.ch_capacity[index].store(0, Ordering::Release);
+            // SAFETY: HCCHAR is shared with IRQ so critical section is needed for RMW
+            critical_section::with(|_| unsafe {
+                if r.hcchar(index).read().chena() {
+                    r.hcchar(index).modify(|w| {
+                        w.set_chdis(true);
+                        w.set_chena(true);
+                    });
+                }
+            });
+        });
+
+        state.ch_status[index].store(STATUS_PENDING, Ordering::Relaxed);
+        state.ch_halt_cause[index].store(STATUS_PENDING, Ordering::Relaxed);
+
+        let eptyp = match self.ep_type {
+            EndpointType::Control => vals::Eptyp::CONTROL,
+            EndpointType::Isochronous => vals::Eptyp::ISOCHRONOUS,
+            EndpointType::Bulk => vals::Eptyp::BULK,
+            EndpointType::Interrupt => vals::Eptyp::INTERRUPT,
+        };
+
+        // SAFETY: FIFO writes must not be interleaved with other channels, and HCCHAR is shared with IRQ
+        critical_section::with(|_| unsafe {
+            r.hcint(index).write_value(regs::Hcint(0xFFFF_FFFF));
+            r.hcintmsk(index).write(|w| {
+                w.set_xfrcm(true);
+                w.set_chhm(true);
+                w.set_stallm(true);
+                w.set_nakm(true);
+                w.set_txerrm(true);
+                w.set_bberrm(true);
+                w.set_frmorm(true);
+                w.set_dterrm(true);
+            });
+
+            r.hctsiz(index).write(|w| {
+                // IN transfers are sized in max packets, the device may send less
+                w.set_xfrsiz(match dir {
+                    Direction::In => self.max_packet_size as u32,
+                    Direction::Out => len as u32,
+                });
+                w.set_pktcnt(1);
+                w.set_dpid(pid);
+            });
+
+            let odd_frame = r.hfnum().read().frnum() & 1 == 0;
+            r.hcchar(index).write(|w| {
+                w.set_mpsiz(self.max_packet_size);
+                w.set_epnum(self.endpoint.index() as u8);
+                w.set_epdir(dir == Direction::In);
+                w.set_lsdev(r.hprt().read().pspd() == 2);
+                w.set_eptyp(eptyp);
+                w.set_mcnt(1);
+                w.set_dad(self.device_address);
+                // Periodic transactions are run in the next frame
+                w.set_oddfrm(self.is_periodic() && odd_frame);
+                w.set_chena(true);
+            });
+
+            if dir == Direction::Out {
+                let data = core::slice::from_raw_parts(buf, len);
+                for chunk in data.chunks(4) {
+                    let mut tmp = [0u8; 4];
+                    tmp[0..chunk.len()].copy_from_slice(chunk);
+                    r.fifo(index).write_value(regs::Fifo(u32::from_ne_bytes(tmp)));
+                }
+            }
+        });
+
+        poll_fn(|cx| {
+            state.ch_wakers[index].register(cx.waker());
+
+            match state.ch_status[index].load(Ordering::Acquire) {
+                STATUS_PENDING if !is_connected::<T>() => Poll::Ready(Err(Error::Disconnected)),
+                STATUS_PENDING => Poll::Pending,
+                status => Poll::Ready(Ok(status)),
+            }
+        })
+        .await
+    }
+
+    /// Waits for `frames` start of frames.
+    async fn wait_frames(&mut self, frames: u16) -> Result<(), Error> {
+        let r = T::regs();
+        // SAFETY: atomic read with no side effects
+        let start = unsafe { r.hfnum().read().frnum() };
+
+        poll_fn(|cx| {
+            T::host_state().ch_wakers[self.index].register(cx.waker());
+
+            if !is_connected::<T>() {
+                return Poll::Ready(Err(Error::Disconnected));
+            }
+
+            // SAFETY: atomic read with no side effects
+            let elapsed = unsafe { r.hfnum().read().frnum() }.wrapping_sub(start) & 0x3FFF;
+            if elapsed >= frames {
+                Poll::Ready(Ok(()))
+            } else {
+                // SAFETY: GINTMSK is shared with IRQ so critical section is needed for RMW
+                critical_section::with(|_| unsafe { r.gintmsk().modify(|w| w.set_sofm(true)) });
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl<'h, T: Instance> Drop for Channel<'h, T> {
+    fn drop(&mut self) {
+        let r = T::regs();
+        let state = T::host_state();
+
+        // SAFETY: HAINTMSK and HCCHAR are shared so critical section is needed for RMW
+        critical_section::with(|_| unsafe {
+            if r.hcchar(self.index).read().chena() {
+                r.hcchar(self.index).modify(|w| {
+                    w.set_chdis(true);
+                    w.set_chena(true);
+                });
+            }
+            r.haintmsk().modify(|w| w.set_haintm(w.haintm() & !(1 << self.index)));
+
+            let allocated = state.ch_allocated.load(Ordering::Relaxed);
+            state
+                .ch_allocated
+                .store(allocated & !(1 << self.index), Ordering::Relaxed);
+        });
+    }
+}
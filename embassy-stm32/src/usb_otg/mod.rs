@@ -7,20 +7,28 @@ use crate::rcc::RccPeripheral;
 mod usb;
 #[cfg(feature = "nightly")]
 pub use usb::*;
+#[cfg(feature = "nightly")]
+pub mod host;
 
 // Using Instance::ENDPOINT_COUNT requires feature(const_generic_expr) so just define maximum eps
 #[cfg(feature = "nightly")]
 const MAX_EP_COUNT: usize = 9;
+// Host channels beyond 12 are not described in the PAC
+#[cfg(feature = "nightly")]
+const MAX_CHANNEL_COUNT: usize = 12;
 
 pub(crate) mod sealed {
     pub trait Instance {
         const HIGH_SPEED: bool;
         const FIFO_DEPTH_WORDS: u16;
         const ENDPOINT_COUNT: usize;
+        const CHANNEL_COUNT: usize;
 
         fn regs() -> crate::pac::otg::Otg;
         #[cfg(feature = "nightly")]
         fn state() -> &'static super::State<{ super::MAX_EP_COUNT }>;
+        #[cfg(feature = "nightly")]
+        fn host_state() -> &'static super::host::State<{ super::MAX_CHANNEL_COUNT }>;
     }
 }
 
@@ -55,6 +63,7 @@ foreach_interrupt!(
                 if #[cfg(stm32f1)] {
                     const FIFO_DEPTH_WORDS: u16 = 128;
                     const ENDPOINT_COUNT: usize = 8;
+                    const CHANNEL_COUNT: usize = 8;
                 } else if #[cfg(any(
                     stm32f2,
                     stm32f401,
@@ -70,6 +79,7 @@ foreach_interrupt!(
                 ))] {
                     const FIFO_DEPTH_WORDS: u16 = 320;
                     const ENDPOINT_COUNT: usize = 4;
+                    const CHANNEL_COUNT: usize = 8;
                 } else if #[cfg(any(
                     stm32f412,
                     stm32f413,
@@ -83,15 +93,19 @@ foreach_interrupt!(
                 ))] {
                     const FIFO_DEPTH_WORDS: u16 = 320;
                     const ENDPOINT_COUNT: usize = 6;
+                    const CHANNEL_COUNT: usize = 12;
                 } else if #[cfg(stm32g0x1)] {
                     const FIFO_DEPTH_WORDS: u16 = 512;
                     const ENDPOINT_COUNT: usize = 8;
+                    const CHANNEL_COUNT: usize = 8;
                 } else if #[cfg(stm32h7)] {
                     const FIFO_DEPTH_WORDS: u16 = 1024;
                     const ENDPOINT_COUNT: usize = 9;
+                    const CHANNEL_COUNT: usize = 12;
                 } else if #[cfg(stm32u5)] {
                     const FIFO_DEPTH_WORDS: u16 = 320;
                     const ENDPOINT_COUNT: usize = 6;
+                    const CHANNEL_COUNT: usize = 12;
                 } else {
                     compile_error!("USB_OTG_FS peripheral is not supported by this chip.");
                 }
@@ -106,6 +120,12 @@ foreach_interrupt!(
                 static STATE: State<MAX_EP_COUNT> = State::new();
                 &STATE
             }
+
+            #[cfg(feature = "nightly")]
+            fn host_state() -> &'static host::State<MAX_CHANNEL_COUNT> {
+                static STATE: host::State<MAX_CHANNEL_COUNT> = host::State::new();
+                &STATE
+            }
         }
 
         impl Instance for peripherals::USB_OTG_FS {
@@ -131,6 +151,7 @@ foreach_interrupt!(
                 ))] {
                     const FIFO_DEPTH_WORDS: u16 = 1024;
                     const ENDPOINT_COUNT: usize = 6;
+                    const CHANNEL_COUNT: usize = 12;
                 } else if #[cfg(any(
                     stm32f446,
                     stm32f469,
@@ -140,9 +161,11 @@ foreach_interrupt!(
                 ))] {
                     const FIFO_DEPTH_WORDS: u16 = 1024;
                     const ENDPOINT_COUNT: usize = 9;
+                    const CHANNEL_COUNT: usize = 12;
                 } else if #[cfg(stm32u5)] {
                     const FIFO_DEPTH_WORDS: u16 = 1024;
                     const ENDPOINT_COUNT: usize = 9;
+                    const CHANNEL_COUNT: usize = 12;
                 } else {
                     compile_error!("USB_OTG_HS peripheral is not supported by this chip.");
                 }
@@ -158,6 +181,12 @@ foreach_interrupt!(
                 static STATE: State<MAX_EP_COUNT> = State::new();
                 &STATE
             }
+
+            #[cfg(feature = "nightly")]
+            fn host_state() -> &'static host::State<MAX_CHANNEL_COUNT> {
+                static STATE: host::State<MAX_CHANNEL_COUNT> = host::State::new();
+                &STATE
+            }
         }
 
         impl Instance for peripherals::USB_OTG_HS {
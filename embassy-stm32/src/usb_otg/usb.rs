@@ -356,7 +356,7 @@ impl<'d, T: Instance> Driver<'d, T> {
 
     // Returns total amount of words (u32) allocated in dedicated FIFO
     fn allocated_fifo_words(&self) -> u16 {
-        RX_FIFO_EXTRA_SIZE_WORDS + ep_fifo_size(&self.ep_out) + ep_fifo_size(&self.ep_in)
+        rx_fifo_size_words(&self.ep_out) + ep_fifo_size(&self.ep_in)
     }
 
     fn alloc_endpoint<D: Dir>(
@@ -373,8 +373,13 @@ impl<'d, T: Instance> Driver<'d, T> {
             D::dir()
         );
 
+        if max_packet_size > max_packet_size_limit(ep_type, self.phy_type.high_speed()) {
+            error!("Max packet size too large for endpoint type and speed");
+            return Err(EndpointAllocError);
+        }
+
         if D::dir() == Direction::Out {
-            if self.ep_out_buffer_offset + max_packet_size as usize >= self.ep_out_buffer.len() {
+            if self.ep_out_buffer_offset + max_packet_size as usize > self.ep_out_buffer.len() {
                 error!("Not enough endpoint out buffer capacity");
                 return Err(EndpointAllocError);
             }
@@ -386,7 +391,23 @@ impl<'d, T: Instance> Driver<'d, T> {
             Direction::In => u16::max((max_packet_size + 3) / 4, 16),
         };
 
-        if fifo_size_words + self.allocated_fifo_words() > T::FIFO_DEPTH_WORDS {
+        // The RX FIFO is shared by all OUT endpoints, so its size doesn't grow linearly with them.
+        let allocated_fifo_words = match D::dir() {
+            Direction::Out => {
+                let mut ep_out = self.ep_out;
+                if let Some(ep) = ep_out.iter_mut().find(|ep| ep.is_none()) {
+                    *ep = Some(EndpointData {
+                        ep_type,
+                        max_packet_size,
+                        fifo_size_words,
+                    });
+                }
+                rx_fifo_size_words(&ep_out) + ep_fifo_size(&self.ep_in)
+            }
+            Direction::In => fifo_size_words + self.allocated_fifo_words(),
+        };
+
+        if allocated_fifo_words > T::FIFO_DEPTH_WORDS {
             error!("Not enough FIFO capacity");
             return Err(EndpointAllocError);
         }
@@ -530,7 +551,7 @@ impl<'d, T: Instance> Bus<'d, T> {
         let r = T::regs();
 
         // Configure RX fifo size. All endpoints share the same FIFO area.
-        let rx_fifo_size_words = RX_FIFO_EXTRA_SIZE_WORDS + ep_fifo_size(&self.ep_out);
+        let rx_fifo_size_words = rx_fifo_size_words(&self.ep_out);
         trace!("configuring rx fifo size={}", rx_fifo_size_words);
 
         // SAFETY: register is exclusive to `Bus` with `&mut self`
@@ -629,15 +650,7 @@ impl<'d, T: Instance> Bus<'d, T> {
     }
 
     fn disable(&mut self) {
-        unsafe { T::Interrupt::steal() }.disable();
-
-        <T as RccPeripheral>::disable();
-
-        #[cfg(stm32l4)]
-        unsafe {
-            crate::pac::PWR.cr2().modify(|w| w.set_usv(false));
-            // Cannot disable PWR, because other peripherals might be using it
-        }
+        power_down::<T>();
     }
 }
 
@@ -822,82 +835,7 @@ impl<'d, T: Instance> embassy_usb_driver::Bus for Bus<'d, T> {
 
         // SAFETY: registers are only accessed by `Bus` under `&mut self`
         unsafe {
-            #[cfg(stm32l4)]
-            {
-                crate::peripherals::PWR::enable();
-                critical_section::with(|_| crate::pac::PWR.cr2().modify(|w| w.set_usv(true)));
-            }
-
-            #[cfg(stm32h7)]
-            {
-                // If true, VDD33USB is generated by internal regulator from VDD50USB
-                // If false, VDD33USB and VDD50USB must be suplied directly with 3.3V (default on nucleo)
-                // TODO: unhardcode
-                let internal_regulator = false;
-
-                // Enable USB power
-                critical_section::with(|_| {
-                    crate::pac::PWR.cr3().modify(|w| {
-                        w.set_usb33den(true);
-                        w.set_usbregen(internal_regulator);
-                    })
-                });
-
-                // Wait for USB power to stabilize
-                while !crate::pac::PWR.cr3().read().usb33rdy() {}
-
-                // Use internal 48MHz HSI clock. Should be enabled in RCC by default.
-                critical_section::with(|_| {
-                    crate::pac::RCC
-                        .d2ccip2r()
-                        .modify(|w| w.set_usbsel(crate::pac::rcc::vals::Usbsel::HSI48))
-                });
-
-                // Enable ULPI clock if external PHY is used
-                let ulpien = !self.phy_type.internal();
-                critical_section::with(|_| {
-                    crate::pac::RCC.ahb1enr().modify(|w| {
-                        if T::HIGH_SPEED {
-                            w.set_usb_otg_hs_ulpien(ulpien);
-                        } else {
-                            w.set_usb_otg_fs_ulpien(ulpien);
-                        }
-                    });
-                    crate::pac::RCC.ahb1lpenr().modify(|w| {
-                        if T::HIGH_SPEED {
-                            w.set_usb_otg_hs_ulpilpen(ulpien);
-                        } else {
-                            w.set_usb_otg_fs_ulpilpen(ulpien);
-                        }
-                    });
-                });
-            }
-
-            #[cfg(stm32u5)]
-            {
-                // Enable USB power
-                critical_section::with(|_| {
-                    crate::pac::RCC.ahb3enr().modify(|w| {
-                        w.set_pwren(true);
-                    });
-                    cortex_m::asm::delay(2);
-
-                    crate::pac::PWR.svmcr().modify(|w| {
-                        w.set_usv(true);
-                        w.set_uvmen(true);
-                    });
-                });
-
-                // Wait for USB power to stabilize
-                while !crate::pac::PWR.svmsr().read().vddusbrdy() {}
-
-                // Select HSI48 as USB clock source.
-                critical_section::with(|_| {
-                    crate::pac::RCC.ccipr1().modify(|w| {
-                        w.set_iclksel(crate::pac::rcc::vals::Iclksel::HSI48);
-                    })
-                });
-            }
+            power_up::<T>(self.phy_type);
 
             <T as RccPeripheral>::enable();
             <T as RccPeripheral>::reset();
@@ -998,7 +936,18 @@ impl<'d, T: Instance> embassy_usb_driver::Bus for Bus<'d, T> {
     }
 
     async fn remote_wakeup(&mut self) -> Result<(), Unsupported> {
-        Err(Unsupported)
+        let r = T::regs();
+
+        // Send the resume signaling, which must last from 1 to 15 ms.
+        unsafe { r.dctl().modify(|w| w.set_rwusig(true)) };
+
+        #[cfg(feature = "time")]
+        embassy_time::Timer::after(embassy_time::Duration::from_millis(5)).await;
+        #[cfg(not(feature = "time"))]
+        cortex_m::asm::delay(T::frequency().0 / 200);
+
+        unsafe { r.dctl().modify(|w| w.set_rwusig(false)) };
+        Ok(())
     }
 }
 
@@ -1008,6 +957,118 @@ impl<'d, T: Instance> Drop for Bus<'d, T> {
     }
 }
 
+/// Powers up the USB peripheral supply and selects its clocks.
+///
+/// # Safety
+///
+/// Must be called before the peripheral is enabled, with exclusive access to it.
+#[cfg_attr(
+    not(any(stm32h7, stm32f2, stm32f4, stm32f7)),
+    allow(unused_variables, clippy::extra_unused_type_parameters)
+)]
+pub(super) unsafe fn power_up<T: Instance>(phy_type: PhyType) {
+    #[cfg(stm32l4)]
+    {
+        crate::peripherals::PWR::enable();
+        critical_section::with(|_| crate::pac::PWR.cr2().modify(|w| w.set_usv(true)));
+    }
+
+    #[cfg(stm32h7)]
+    {
+        // If true, VDD33USB is generated by internal regulator from VDD50USB
+        // If false, VDD33USB and VDD50USB must be suplied directly with 3.3V (default on nucleo)
+        // TODO: unhardcode
+        let internal_regulator = false;
+
+        // Enable USB power
+        critical_section::with(|_| {
+            crate::pac::PWR.cr3().modify(|w| {
+                w.set_usb33den(true);
+                w.set_usbregen(internal_regulator);
+            })
+        });
+
+        // Wait for USB power to stabilize
+        while !crate::pac::PWR.cr3().read().usb33rdy() {}
+
+        // Use internal 48MHz HSI clock. Should be enabled in RCC by default.
+        critical_section::with(|_| {
+            crate::pac::RCC
+                .d2ccip2r()
+                .modify(|w| w.set_usbsel(crate::pac::rcc::vals::Usbsel::HSI48))
+        });
+
+        // Enable ULPI clock if external PHY is used
+        let ulpien = !phy_type.internal();
+        critical_section::with(|_| {
+            crate::pac::RCC.ahb1enr().modify(|w| {
+                if T::HIGH_SPEED {
+                    w.set_usb_otg_hs_ulpien(ulpien);
+                } else {
+                    w.set_usb_otg_fs_ulpien(ulpien);
+                }
+            });
+            crate::pac::RCC.ahb1lpenr().modify(|w| {
+                if T::HIGH_SPEED {
+                    w.set_usb_otg_hs_ulpilpen(ulpien);
+                } else {
+                    w.set_usb_otg_fs_ulpilpen(ulpien);
+                }
+            });
+        });
+    }
+
+    #[cfg(any(stm32f2, stm32f4, stm32f7))]
+    if T::HIGH_SPEED {
+        // Enable ULPI clock if external PHY is used. It must stay disabled in sleep mode
+        // otherwise, or the peripheral doesn't work with the internal PHY.
+        let ulpien = !phy_type.internal();
+        critical_section::with(|_| {
+            crate::pac::RCC.ahb1enr().modify(|w| w.set_usb_otg_hsulpien(ulpien));
+            crate::pac::RCC.ahb1lpenr().modify(|w| w.set_usb_otg_hsulpilpen(ulpien));
+        });
+    }
+
+    #[cfg(stm32u5)]
+    {
+        // Enable USB power
+        critical_section::with(|_| {
+            crate::pac::RCC.ahb3enr().modify(|w| {
+                w.set_pwren(true);
+            });
+            cortex_m::asm::delay(2);
+
+            crate::pac::PWR.svmcr().modify(|w| {
+                w.set_usv(true);
+                w.set_uvmen(true);
+            });
+        });
+
+        // Wait for USB power to stabilize
+        while !crate::pac::PWR.svmsr().read().vddusbrdy() {}
+
+        // Select HSI48 as USB clock source.
+        critical_section::with(|_| {
+            crate::pac::RCC.ccipr1().modify(|w| {
+                w.set_iclksel(crate::pac::rcc::vals::Iclksel::HSI48);
+            })
+        });
+    }
+}
+
+/// Powers down the USB peripheral.
+pub(super) fn power_down<T: Instance>() {
+    unsafe { T::Interrupt::steal() }.disable();
+
+    <T as RccPeripheral>::disable();
+
+    #[cfg(stm32l4)]
+    unsafe {
+        crate::pac::PWR.cr2().modify(|w| w.set_usv(false));
+        // Cannot disable PWR, because other peripherals might be using it
+    }
+}
+
 trait Dir {
     fn dir() -> Direction;
 }
@@ -1328,6 +1389,32 @@ fn ep_fifo_size(eps: &[Option<EndpointData>]) -> u16 {
     eps.iter().map(|ep| ep.map(|ep| ep.fifo_size_words).unwrap_or(0)).sum()
 }
 
+/// Calculates RX FIFO size in words, shared by all OUT endpoints.
+///
+/// The RX FIFO only needs to hold two of the largest packets at once, in addition to SETUP packets
+/// and status information. Capping it this way leaves room for the TX FIFOs of high-speed devices
+/// with several 512-byte bulk endpoints.
+fn rx_fifo_size_words(eps: &[Option<EndpointData>]) -> u16 {
+    let largest = eps
+        .iter()
+        .map(|ep| ep.map(|ep| ep.fifo_size_words).unwrap_or(0))
+        .max()
+        .unwrap_or(0);
+    RX_FIFO_EXTRA_SIZE_WORDS + u16::min(ep_fifo_size(eps), 2 * (largest + 1))
+}
+
+/// Returns the maximum packet size allowed by the USB specification for an endpoint.
+fn max_packet_size_limit(ep_type: EndpointType, high_speed: bool) -> u16 {
+    match (ep_type, high_speed) {
+        (EndpointType::Control, _) => 64,
+        (EndpointType::Bulk, false) => 64,
+        (EndpointType::Bulk, true) => 512,
+        (EndpointType::Interrupt, false) => 64,
+        (EndpointType::Isochronous, false) => 1023,
+        (EndpointType::Interrupt | EndpointType::Isochronous, true) => 1024,
+    }
+}
+
 /// Generates IRQ mask for enabled endpoints
 fn ep_irq_mask(eps: &[Option<EndpointData>]) -> u16 {
     eps.iter().enumerate().fold(
@@ -1,10 +1,20 @@
+//! Independent (IWDG) and window (WWDG) watchdogs
 use core::marker::PhantomData;
+use core::time::Duration;
 
 use embassy_hal_common::{into_ref, Peripheral};
 use stm32_metapac::iwdg::vals::{Key, Pr};
 
 use crate::rcc::LSI_FREQ;
 
+mod supervisor;
+#[cfg(wwdg)]
+mod wwdg;
+
+pub use supervisor::{Supervisor, TaskId};
+#[cfg(wwdg)]
+pub use wwdg::{WindowConfig, WindowInstance, WindowInterruptHandler, WindowWatchdog};
+
 pub struct IndependentWatchdog<'d, T: Instance> {
     wdg: PhantomData<&'d mut T>,
 }
@@ -22,31 +32,39 @@ const fn reload_value(prescaler: u16, timeout_us: u32) -> u16 {
     (timeout_us / prescaler as u32 * LSI_FREQ.0 / 1_000_000) as u16 - 1
 }
 
+/// Prescaler register value and reload value for a watchdog period of `timeout`, or `None` if
+/// it is out of range. The lowest prescaler able to count `timeout` is used.
+fn iwdg_config(timeout: Duration) -> Option<(u8, u16)> {
+    let timeout_us = u32::try_from(timeout.as_micros()).ok()?;
+
+    // Find lowest prescaler value, which makes watchdog period longer or equal to timeout.
+    // This iterates from 4 (2^2) to 256 (2^8).
+    let psc_power = (2..=8).find(|psc_power| timeout_us <= get_timeout_us(1 << psc_power, MAX_RL))?;
+
+    // Prescaler value
+    let psc = 1 << psc_power;
+    // The reload value would underflow for timeouts shorter than a counter tick
+    if timeout_us / psc as u32 * LSI_FREQ.0 < 1_000_000 {
+        return None;
+    }
+
+    // Convert prescaler power to PR register value
+    Some((psc_power as u8 - 2, reload_value(psc, timeout_us)))
+}
+
 impl<'d, T: Instance> IndependentWatchdog<'d, T> {
-    /// Creates an IWDG (Independent Watchdog) instance with a given timeout value in microseconds.
+    /// Creates an IWDG (Independent Watchdog) instance with a given timeout.
     ///
     /// [Self] has to be started with [Self::unleash()].
     /// Once timer expires, MCU will be reset. To prevent this, timer must be reloaded by repeatedly calling [Self::pet()] within timeout interval.
-    pub fn new(_instance: impl Peripheral<P = T> + 'd, timeout_us: u32) -> Self {
+    ///
+    /// Panics if `timeout` can't be counted with the LSI clock, e.g. longer than 32.8s with a 32 kHz LSI.
+    pub fn new(_instance: impl Peripheral<P = T> + 'd, timeout: Duration) -> Self {
         into_ref!(_instance);
 
-        // Find lowest prescaler value, which makes watchdog period longer or equal to timeout.
-        // This iterates from 4 (2^2) to 256 (2^8).
-        let psc_power = unwrap!((2..=8).find(|psc_power| {
-            let psc = 2u16.pow(*psc_power);
-            timeout_us <= get_timeout_us(psc, MAX_RL)
-        }));
-
-        // Prescaler value
-        let psc = 2u16.pow(psc_power);
-
-        // Convert prescaler power to PR register value
-        let pr = psc_power as u8 - 2;
+        let (pr, rl) = unwrap!(iwdg_config(timeout), "watchdog timeout out of range");
         assert!(pr <= 0b110);
 
-        // Reload value
-        let rl = reload_value(psc, timeout_us);
-
         let wdg = T::regs();
         unsafe {
             wdg.kr().write(|w| w.set_key(Key::ENABLE));
@@ -56,8 +74,8 @@ impl<'d, T: Instance> IndependentWatchdog<'d, T> {
 
         trace!(
             "Watchdog configured with {}us timeout, desired was {}us (PR={}, RL={})",
-            get_timeout_us(psc, rl),
-            timeout_us,
+            get_timeout_us(4 << pr, rl),
+            timeout.as_micros() as u32,
             pr,
             rl
         );
@@ -67,12 +85,14 @@ impl<'d, T: Instance> IndependentWatchdog<'d, T> {
         }
     }
 
-    pub unsafe fn unleash(&mut self) {
-        T::regs().kr().write(|w| w.set_key(Key::START));
+    /// Start the watchdog. It can't be stopped until the next reset.
+    pub fn unleash(&mut self) {
+        unsafe { T::regs().kr().write(|w| w.set_key(Key::START)) }
     }
 
-    pub unsafe fn pet(&mut self) {
-        T::regs().kr().write(|w| w.set_key(Key::RESET));
+    /// Reload the watchdog counter, postponing the reset by the timeout.
+    pub fn pet(&mut self) {
+        unsafe { T::regs().kr().write(|w| w.set_key(Key::RESET)) }
     }
 }
 
@@ -118,4 +138,17 @@ mod tests {
 
         assert_eq!(3999, reload_value(64, 8000_000));
     }
+
+    #[test]
+    fn can_compute_config() {
+        assert_eq!(Some((0, 0xFFF)), iwdg_config(Duration::from_micros(512_000)));
+        assert_eq!(Some((1, 2499)), iwdg_config(Duration::from_millis(625)));
+        assert_eq!(Some((4, 3999)), iwdg_config(Duration::from_secs(8)));
+        assert_eq!(Some((6, 0xFFF)), iwdg_config(Duration::from_micros(32_768_000)));
+
+        assert_eq!(Some((0, 0)), iwdg_config(Duration::from_micros(128)));
+        assert_eq!(None, iwdg_config(Duration::from_micros(125)));
+        assert_eq!(None, iwdg_config(Duration::from_secs(33)));
+        assert_eq!(None, iwdg_config(Duration::from_secs(1 << 40)));
+    }
 }
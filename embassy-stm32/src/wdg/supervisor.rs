@@ -0,0 +1,111 @@
+use atomic_polyfill::{AtomicU32, Ordering};
+
+/// Liveness of up to 32 tasks, for a supervisor task which only feeds a watchdog while all of them
+/// make progress.
+///
+/// Each task calls [`Supervisor::report`] with its [`TaskId`] at least once per supervision
+/// period, and the supervisor calls [`Supervisor::check`] once per period, petting the watchdog
+/// only if it succeeds:
+///
+/// ```ignore
+/// static SUPERVISOR: Supervisor = Supervisor::new(2);
+///
+/// #[embassy_executor::task]
+/// async fn supervisor(mut wdg: IndependentWatchdog<'static, IWDG>) {
+///     loop {
+///         Timer::after(Duration::from_millis(500)).await;
+///         match SUPERVISOR.check() {
+///             Ok(()) => wdg.pet(),
+///             Err(missing) => warn!("tasks {:b} are stuck", missing),
+///         }
+///     }
+/// }
+/// ```
+pub struct Supervisor {
+    /// Bit `n` is set for every supervised task `n`.
+    expected: u32,
+    /// Bit `n` is set when task `n` reported since the last check.
+    alive: AtomicU32,
+}
+
+/// Index of a task supervised by a [`Supervisor`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TaskId(u8);
+
+impl Supervisor {
+    /// Supervise `tasks` tasks, with IDs from 0 to `tasks - 1`.
+    pub const fn new(tasks: u8) -> Self {
+        ::core::assert!(tasks > 0 && tasks <= 32, "a supervisor handles 1 to 32 tasks");
+
+        Self {
+            expected: u32::MAX >> (32 - tasks as u32),
+            alive: AtomicU32::new(0),
+        }
+    }
+
+    /// ID of the `n`th task. Panics if `n` isn't supervised.
+    pub fn task(&self, n: u8) -> TaskId {
+        assert!(n < 32 && self.expected & (1 << n) != 0, "task not supervised");
+        TaskId(n)
+    }
+
+    /// Report that task `id` made progress.
+    pub fn report(&self, id: TaskId) {
+        self.alive.fetch_or(1 << id.0, Ordering::Relaxed);
+    }
+
+    /// Check that every task reported since the last check, and start a new period.
+    ///
+    /// On failure, returns the mask of the tasks which didn't report, bit `n` being set for task `n`.
+    pub fn check(&self) -> Result<(), u32> {
+        let missing = self.expected & !self.alive.swap(0, Ordering::Relaxed);
+        if missing == 0 {
+            Ok(())
+        } else {
+            Err(missing)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_all_tasks() {
+        let supervisor = Supervisor::new(3);
+        let (a, b, c) = (supervisor.task(0), supervisor.task(1), supervisor.task(2));
+
+        assert_eq!(Err(0b111), supervisor.check());
+
+        supervisor.report(a);
+        supervisor.report(c);
+        assert_eq!(Err(0b010), supervisor.check());
+
+        supervisor.report(a);
+        supervisor.report(b);
+        supervisor.report(b);
+        supervisor.report(c);
+        assert_eq!(Ok(()), supervisor.check());
+
+        // A new period starts after each check
+        supervisor.report(b);
+        assert_eq!(Err(0b101), supervisor.check());
+    }
+
+    #[test]
+    fn supports_32_tasks() {
+        let supervisor = Supervisor::new(32);
+        for n in 0..32 {
+            supervisor.report(supervisor.task(n));
+        }
+        assert_eq!(Ok(()), supervisor.check());
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_unknown_task() {
+        Supervisor::new(3).task(3);
+    }
+}
@@ -0,0 +1,224 @@
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+use core::time::Duration;
+
+use atomic_polyfill::{AtomicBool, Ordering};
+use embassy_hal_common::{into_ref, PeripheralRef};
+use embassy_sync::waitqueue::AtomicWaker;
+use stm32_metapac::wwdg::vals::{Wdga, Wdgtb};
+
+use crate::interrupt::{Interrupt, InterruptExt};
+use crate::rcc::RccPeripheral;
+use crate::{interrupt, Peripheral};
+
+static EARLY_WAKEUP_WAKER: AtomicWaker = AtomicWaker::new();
+/// Set when the early wakeup interrupt fired and has not been waited for yet.
+static EARLY_WAKEUP_FIRED: AtomicBool = AtomicBool::new(false);
+
+/// Value of the counter at which the MCU is reset, when bit 6 gets cleared.
+const RESET_COUNTER: u8 = 0x3F;
+/// The counter counts at most 64 ticks before resetting the MCU.
+const MAX_TICKS: u128 = 64;
+
+/// WWDG early wakeup interrupt handler.
+pub struct WindowInterruptHandler<T: WindowInstance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: WindowInstance> interrupt::Handler<T::Interrupt> for WindowInterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let r = T::regs();
+        if r.sr().read().ewif() {
+            r.sr().write(|w| w.set_ewif(false));
+            EARLY_WAKEUP_FIRED.store(true, Ordering::SeqCst);
+            EARLY_WAKEUP_WAKER.wake();
+        }
+    }
+}
+
+/// Window watchdog configuration.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WindowConfig {
+    /// Time after the last refresh at which the MCU is reset.
+    pub timeout: Duration,
+    /// Time after the last refresh before which refreshing the watchdog resets the MCU.
+    /// [`Duration::ZERO`] allows refreshing at any time.
+    pub window: Duration,
+}
+
+/// Timer base, counter reload value and window value for `config`, given the APB clock
+/// frequency `pclk`, or `None` if it is out of range. The finest timer base able to count the
+/// timeout is used.
+fn wwdg_config(pclk: u32, config: &WindowConfig) -> Option<(u8, u8, u8)> {
+    (0..4).find_map(|wdgtb| {
+        let ticks = |duration: Duration| duration.as_nanos() * pclk as u128 / (4096 << wdgtb) / 1_000_000_000;
+
+        let timeout_ticks = ticks(config.timeout);
+        if timeout_ticks > MAX_TICKS {
+            return None;
+        }
+        // Rounding down makes the window less strict, and is thus always safe.
+        let window_ticks = ticks(config.window);
+        if timeout_ticks == 0 || window_ticks >= timeout_ticks {
+            return Some(None);
+        }
+
+        let counter = RESET_COUNTER + timeout_ticks as u8;
+        Some(Some((wdgtb, counter, counter - window_ticks as u8)))
+    })?
+}
+
+/// Window watchdog, clocked by the APB clock.
+///
+/// Once started, it resets the MCU if it is not refreshed with [`WindowWatchdog::pet()`]
+/// within the timeout, or if it is refreshed before the end of the window.
+pub struct WindowWatchdog<'d, T: WindowInstance> {
+    _inner: PeripheralRef<'d, T>,
+    counter: u8,
+}
+
+impl<'d, T: WindowInstance> WindowWatchdog<'d, T> {
+    /// Create a WWDG (Window Watchdog) instance. [Self] has to be started with [Self::unleash()].
+    ///
+    /// Panics if `config` can't be counted with the APB clock, e.g. from 98us to 50ms with a 42 MHz clock.
+    pub fn new(instance: impl Peripheral<P = T> + 'd, config: WindowConfig) -> Self {
+        Self::new_inner(instance, config, false)
+    }
+
+    /// Create a WWDG instance with the early wakeup interrupt enabled, which fires one counter
+    /// tick before the MCU is reset. See [`WindowWatchdog::wait_early_wakeup`].
+    pub fn new_with_early_wakeup(
+        instance: impl Peripheral<P = T> + 'd,
+        _irq: impl interrupt::Binding<T::Interrupt, WindowInterruptHandler<T>> + 'd,
+        config: WindowConfig,
+    ) -> Self {
+        let this = Self::new_inner(instance, config, true);
+
+        EARLY_WAKEUP_FIRED.store(false, Ordering::SeqCst);
+        unsafe { T::Interrupt::steal() }.unpend();
+        unsafe { T::Interrupt::steal() }.enable();
+
+        this
+    }
+
+    fn new_inner(instance: impl Peripheral<P = T> + 'd, config: WindowConfig, early_wakeup: bool) -> Self {
+        into_ref!(instance);
+
+        T::enable();
+        T::reset();
+
+        let (wdgtb, counter, window) = unwrap!(
+            wwdg_config(T::frequency().0, &config),
+            "watchdog timeout or window out of range"
+        );
+
+        let r = T::regs();
+        unsafe {
+            r.cfr().write(|w| {
+                w.set_wdgtb(Wdgtb(wdgtb));
+                w.set_w(window);
+                w.set_ewi(early_wakeup);
+            });
+            r.sr().write(|w| w.set_ewif(false));
+        }
+
+        trace!(
+            "Window watchdog configured with WDGTB={}, T={}, W={}",
+            wdgtb,
+            counter,
+            window
+        );
+
+        Self {
+            _inner: instance,
+            counter,
+        }
+    }
+
+    /// Start the watchdog. It can't be stopped until the next reset.
+    pub fn unleash(&mut self) {
+        self.pet()
+    }
+
+    /// Reload the watchdog counter, postponing the reset by the timeout.
+    ///
+    /// The MCU is reset if this is called before the end of the window.
+    pub fn pet(&mut self) {
+        unsafe {
+            T::regs().cr().write(|w| {
+                w.set_wdga(Wdga::ENABLED);
+                w.set_t(self.counter);
+            })
+        }
+    }
+
+    /// Wait until the early wakeup interrupt fires, giving a last chance to refresh the watchdog
+    /// or to save state before the MCU is reset.
+    ///
+    /// Returns immediately if it fired since the last call. Never returns if the watchdog was
+    /// created without the early wakeup interrupt.
+    pub async fn wait_early_wakeup(&mut self) {
+        poll_fn(|cx| {
+            EARLY_WAKEUP_WAKER.register(cx.waker());
+
+            if EARLY_WAKEUP_FIRED.swap(false, Ordering::SeqCst) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+pub(crate) mod sealed {
+    pub trait WindowInstance {
+        fn regs() -> crate::pac::wwdg::Wwdg;
+    }
+}
+
+pub trait WindowInstance: Peripheral<P = Self> + sealed::WindowInstance + RccPeripheral + 'static {
+    type Interrupt: Interrupt;
+}
+
+foreach_interrupt!(
+    ($inst:ident, wwdg, $block:ident, GLOBAL, $irq:ident) => {
+        impl sealed::WindowInstance for crate::peripherals::$inst {
+            fn regs() -> crate::pac::wwdg::Wwdg {
+                crate::pac::$inst
+            }
+        }
+
+        impl WindowInstance for crate::peripherals::$inst {
+            type Interrupt = crate::interrupt::$irq;
+        }
+    };
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(timeout_us: u64, window_us: u64) -> WindowConfig {
+        WindowConfig {
+            timeout: Duration::from_micros(timeout_us),
+            window: Duration::from_micros(window_us),
+        }
+    }
+
+    #[test]
+    fn can_compute_config() {
+        const PCLK: u32 = 42_000_000;
+
+        assert_eq!(Some((0, 0x72, 0x72)), wwdg_config(PCLK, &config(5_000, 0)));
+        assert_eq!(Some((2, 0x72, 0x59)), wwdg_config(PCLK, &config(20_000, 10_000)));
+        assert_eq!(Some((3, 0x7F, 0x7F)), wwdg_config(PCLK, &config(50_000, 0)));
+
+        // Out of range timeouts
+        assert_eq!(None, wwdg_config(PCLK, &config(60_000, 0)));
+        assert_eq!(None, wwdg_config(PCLK, &config(50, 0)));
+        // Window longer than the timeout
+        assert_eq!(None, wwdg_config(PCLK, &config(5_000, 5_000)));
+    }
+}
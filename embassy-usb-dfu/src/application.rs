@@ -0,0 +1,111 @@
+use core::marker::PhantomData;
+
+use embassy_boot::FirmwareUpdater;
+use embassy_usb::control::{InResponse, OutResponse, Recipient, Request, RequestType};
+use embassy_usb::driver::Driver;
+use embassy_usb::types::InterfaceNumber;
+use embassy_usb::{Builder, Handler};
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::consts::*;
+use crate::Reset;
+
+/// Internal state for the DFU runtime interface of the application.
+pub struct Control<'d, STATE: NorFlash, RST: Reset> {
+    updater: FirmwareUpdater,
+    state_flash: STATE,
+    aligned: &'d mut [u8],
+    state: State,
+    if_num: InterfaceNumber,
+    _rst: PhantomData<RST>,
+}
+
+impl<'d, STATE: NorFlash, RST: Reset> Control<'d, STATE, RST> {
+    /// Create a new `Control`.
+    ///
+    /// `aligned` is used to write the bootloader state, it must have a size of `STATE::WRITE_SIZE`
+    /// and follow the alignment rules of `state_flash`.
+    pub fn new(updater: FirmwareUpdater, state_flash: STATE, aligned: &'d mut [u8]) -> Self {
+        assert_eq!(aligned.len(), STATE::WRITE_SIZE);
+        Self {
+            updater,
+            state_flash,
+            aligned,
+            state: State::AppIdle,
+            if_num: InterfaceNumber(0),
+            _rst: PhantomData,
+        }
+    }
+}
+
+impl<'d, STATE: NorFlash, RST: Reset> Handler for Control<'d, STATE, RST> {
+    fn reset(&mut self) {
+        if self.state != State::AppDetach {
+            return;
+        }
+
+        match self.updater.mark_dfu_blocking(&mut self.state_flash, self.aligned) {
+            Ok(()) => RST::sys_reset(),
+            Err(e) => {
+                warn!("dfu: failed to mark the bootloader state: {:?}", e);
+                self.state = State::AppIdle;
+            }
+        }
+    }
+
+    fn control_out(&mut self, req: Request, _data: &[u8]) -> Option<OutResponse> {
+        if (req.request_type, req.recipient, req.index)
+            != (RequestType::Class, Recipient::Interface, self.if_num.0 as u16)
+        {
+            return None;
+        }
+
+        match req.request {
+            REQ_DETACH => {
+                trace!("dfu: detach requested, switching to the bootloader on next bus reset");
+                self.state = State::AppDetach;
+                Some(OutResponse::Accepted)
+            }
+            _ => Some(OutResponse::Rejected),
+        }
+    }
+
+    fn control_in<'a>(&'a mut self, req: Request, buf: &'a mut [u8]) -> Option<InResponse<'a>> {
+        if (req.request_type, req.recipient, req.index)
+            != (RequestType::Class, Recipient::Interface, self.if_num.0 as u16)
+        {
+            return None;
+        }
+
+        match req.request {
+            REQ_GETSTATUS => Some(InResponse::Accepted(status_response(buf, Status::Ok, self.state))),
+            REQ_GETSTATE => {
+                buf[0] = self.state as u8;
+                Some(InResponse::Accepted(&buf[..1]))
+            }
+            _ => Some(InResponse::Rejected),
+        }
+    }
+}
+
+/// Adds the DFU runtime interface to the application's USB device.
+///
+/// When the host requests a detach, the device marks the bootloader state to enter DFU mode, and
+/// resets on the next bus reset. The host waits up to `detach_timeout_ms` for this bus reset.
+pub fn usb_dfu<'d, D: Driver<'d>, STATE: NorFlash, RST: Reset>(
+    builder: &mut Builder<'d, D>,
+    handler: &'d mut Control<'d, STATE, RST>,
+    detach_timeout_ms: u16,
+) {
+    let mut func = builder.function(USB_CLASS_APPN_SPEC, APPN_SPEC_SUBCLASS_DFU, DFU_PROTOCOL_RT);
+    let mut iface = func.interface();
+    let mut alt = iface.alt_setting(USB_CLASS_APPN_SPEC, APPN_SPEC_SUBCLASS_DFU, DFU_PROTOCOL_RT, None);
+    handler.if_num = alt.interface_number();
+    alt.descriptor(
+        DESC_DFU_FUNCTIONAL,
+        &functional_descriptor(ATTR_CAN_DNLOAD, detach_timeout_ms, 0),
+    );
+
+    drop(func);
+    builder.handler(handler);
+}
@@ -0,0 +1,95 @@
+pub(crate) const USB_CLASS_APPN_SPEC: u8 = 0xFE;
+pub(crate) const APPN_SPEC_SUBCLASS_DFU: u8 = 0x01;
+#[cfg(feature = "application")]
+pub(crate) const DFU_PROTOCOL_RT: u8 = 0x01;
+#[cfg(feature = "dfu")]
+pub(crate) const DFU_PROTOCOL_DFU: u8 = 0x02;
+
+pub(crate) const DESC_DFU_FUNCTIONAL: u8 = 0x21;
+
+// bmAttributes of the DFU functional descriptor
+pub(crate) const ATTR_CAN_DNLOAD: u8 = 0x01;
+//pub(crate) const ATTR_CAN_UPLOAD: u8 = 0x02;
+//pub(crate) const ATTR_MANIFESTATION_TOLERANT: u8 = 0x04;
+//pub(crate) const ATTR_WILL_DETACH: u8 = 0x08;
+
+#[cfg(feature = "application")]
+pub(crate) const REQ_DETACH: u8 = 0x00;
+#[cfg(feature = "dfu")]
+pub(crate) const REQ_DNLOAD: u8 = 0x01;
+//pub(crate) const REQ_UPLOAD: u8 = 0x02;
+pub(crate) const REQ_GETSTATUS: u8 = 0x03;
+#[cfg(feature = "dfu")]
+pub(crate) const REQ_CLRSTATUS: u8 = 0x04;
+pub(crate) const REQ_GETSTATE: u8 = 0x05;
+#[cfg(feature = "dfu")]
+pub(crate) const REQ_ABORT: u8 = 0x06;
+
+/// DFU state of the device, as reported to the host.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+#[allow(unused)]
+pub(crate) enum State {
+    AppIdle = 0,
+    AppDetach = 1,
+    DfuIdle = 2,
+    DlSync = 3,
+    DlBusy = 4,
+    DlIdle = 5,
+    ManifestSync = 6,
+    Manifest = 7,
+    ManifestWaitReset = 8,
+    UploadIdle = 9,
+    Error = 10,
+}
+
+/// Status of the last DFU request, as reported to the host.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+#[allow(unused)]
+pub(crate) enum Status {
+    Ok = 0x00,
+    ErrTarget = 0x01,
+    ErrFile = 0x02,
+    ErrWrite = 0x03,
+    ErrErase = 0x04,
+    ErrCheckErased = 0x05,
+    ErrProg = 0x06,
+    ErrVerify = 0x07,
+    ErrAddress = 0x08,
+    ErrNotDone = 0x09,
+    ErrFirmware = 0x0A,
+    ErrVendor = 0x0B,
+    ErrUsbr = 0x0C,
+    ErrPor = 0x0D,
+    ErrUnknown = 0x0E,
+    ErrStalledPkt = 0x0F,
+}
+
+/// Content of the DFU functional descriptor.
+pub(crate) fn functional_descriptor(attributes: u8, detach_timeout_ms: u16, transfer_size: u16) -> [u8; 7] {
+    [
+        attributes,                     // bmAttributes
+        detach_timeout_ms as u8,        // wDetachTimeOut
+        (detach_timeout_ms >> 8) as u8, // |
+        transfer_size as u8,            // wTransferSize
+        (transfer_size >> 8) as u8,     // |
+        0x10,                           // bcdDFUVersion (1.1)
+        0x01,                           // |
+    ]
+}
+
+/// Response to a GETSTATUS request.
+pub(crate) fn status_response(buf: &mut [u8], status: Status, state: State) -> &[u8] {
+    buf[..6].copy_from_slice(&[
+        status as u8,
+        0x00, // bwPollTimeout, flash operations are done synchronously
+        0x00, // |
+        0x00, // |
+        state as u8,
+        0x00, // iString
+    ]);
+    &buf[..6]
+}
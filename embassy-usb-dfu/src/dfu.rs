@@ -0,0 +1,211 @@
+use core::marker::PhantomData;
+
+use embassy_boot::{AlignedBuffer, FirmwareUpdater, FirmwareUpdaterError};
+use embassy_usb::control::{InResponse, OutResponse, Recipient, Request, RequestType};
+use embassy_usb::driver::Driver;
+use embassy_usb::types::InterfaceNumber;
+use embassy_usb::{Builder, Handler};
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::consts::*;
+use crate::Reset;
+
+/// Internal state for the DFU mode interface of the bootloader.
+///
+/// The firmware is downloaded in blocks of `BLOCK_SIZE` bytes, which must be a multiple of
+/// `DFU::WRITE_SIZE`. The control buffer of the USB device must be at least `BLOCK_SIZE` long.
+///
+/// The DFU partition is erased page by page while the blocks are written, so that no request
+/// takes longer than erasing the pages spanned by one block.
+pub struct Control<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> {
+    updater: FirmwareUpdater,
+    dfu_flash: DFU,
+    state_flash: STATE,
+    aligned: &'d mut [u8],
+    buf: AlignedBuffer<BLOCK_SIZE>,
+    offset: usize,
+    /// End of the erased part of the DFU partition.
+    erased: usize,
+    state: State,
+    status: Status,
+    if_num: InterfaceNumber,
+    _rst: PhantomData<RST>,
+}
+
+impl<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> Control<'d, DFU, STATE, RST, BLOCK_SIZE> {
+    /// Create a new `Control`.
+    ///
+    /// `aligned` is used to write the bootloader state, it must have a size of `STATE::WRITE_SIZE`
+    /// and follow the alignment rules of `state_flash`.
+    pub fn new(updater: FirmwareUpdater, dfu_flash: DFU, state_flash: STATE, aligned: &'d mut [u8]) -> Self {
+        assert_eq!(aligned.len(), STATE::WRITE_SIZE);
+        assert!(BLOCK_SIZE % DFU::WRITE_SIZE == 0 && BLOCK_SIZE <= u16::MAX as usize);
+        Self {
+            updater,
+            dfu_flash,
+            state_flash,
+            aligned,
+            buf: AlignedBuffer([0; BLOCK_SIZE]),
+            offset: 0,
+            erased: 0,
+            state: State::DfuIdle,
+            status: Status::Ok,
+            if_num: InterfaceNumber(0),
+            _rst: PhantomData,
+        }
+    }
+
+    fn fail(&mut self, status: Status) -> OutResponse {
+        self.state = State::Error;
+        self.status = status;
+        OutResponse::Rejected
+    }
+
+    fn download(&mut self, data: &[u8]) -> OutResponse {
+        if data.is_empty() {
+            if self.state != State::DlIdle {
+                return self.fail(Status::ErrNotDone);
+            }
+
+            trace!("dfu: download done, {} bytes", self.offset);
+            return match self.updater.mark_updated_blocking(&mut self.state_flash, self.aligned) {
+                Ok(()) => {
+                    self.state = State::ManifestSync;
+                    OutResponse::Accepted
+                }
+                Err(e) => {
+                    warn!("dfu: failed to mark the firmware as updated: {:?}", e);
+                    self.fail(Status::ErrWrite)
+                }
+            };
+        }
+
+        let partition = self.updater.dfu_partition();
+
+        // Only the last block may be shorter than BLOCK_SIZE.
+        if data.len() > BLOCK_SIZE
+            || self.offset % BLOCK_SIZE != 0
+            || self.offset + data.len() > partition.size() as usize
+        {
+            return self.fail(Status::ErrAddress);
+        }
+
+        let len = (data.len() + DFU::WRITE_SIZE - 1) / DFU::WRITE_SIZE * DFU::WRITE_SIZE;
+
+        let end = self.offset + len;
+        if end > self.erased {
+            let erase_end =
+                ((end + DFU::ERASE_SIZE - 1) / DFU::ERASE_SIZE * DFU::ERASE_SIZE).min(partition.size() as usize);
+            if let Err(e) = partition.erase_blocking(&mut self.dfu_flash, self.erased as u32, erase_end as u32) {
+                warn!(
+                    "dfu: failed to erase the DFU partition: {:?}",
+                    FirmwareUpdaterError::from(e)
+                );
+                return self.fail(Status::ErrErase);
+            }
+            self.erased = erase_end;
+        }
+
+        self.buf.0[..data.len()].copy_from_slice(data);
+        self.buf.0[data.len()..len].fill(0xFF);
+
+        match partition.write_blocking(&mut self.dfu_flash, self.offset as u32, &self.buf.0[..len]) {
+            Ok(()) => {
+                self.offset += data.len();
+                self.state = State::DlSync;
+                OutResponse::Accepted
+            }
+            Err(e) => {
+                warn!("dfu: failed to write the firmware: {:?}", FirmwareUpdaterError::from(e));
+                self.fail(Status::ErrWrite)
+            }
+        }
+    }
+}
+
+impl<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> Handler
+    for Control<'d, DFU, STATE, RST, BLOCK_SIZE>
+{
+    fn reset(&mut self) {
+        if matches!(self.state, State::Manifest | State::ManifestWaitReset) {
+            RST::sys_reset()
+        }
+    }
+
+    fn control_out(&mut self, req: Request, data: &[u8]) -> Option<OutResponse> {
+        if (req.request_type, req.recipient, req.index)
+            != (RequestType::Class, Recipient::Interface, self.if_num.0 as u16)
+        {
+            return None;
+        }
+
+        match (req.request, self.state) {
+            (REQ_DNLOAD, State::DfuIdle | State::DlIdle) => Some(self.download(data)),
+            (REQ_ABORT, State::DfuIdle | State::DlIdle | State::DlSync) => {
+                self.state = State::DfuIdle;
+                self.offset = 0;
+                self.erased = 0;
+                Some(OutResponse::Accepted)
+            }
+            (REQ_CLRSTATUS, State::Error) => {
+                self.state = State::DfuIdle;
+                self.status = Status::Ok;
+                self.offset = 0;
+                self.erased = 0;
+                Some(OutResponse::Accepted)
+            }
+            _ => Some(self.fail(Status::ErrStalledPkt)),
+        }
+    }
+
+    fn control_in<'a>(&'a mut self, req: Request, buf: &'a mut [u8]) -> Option<InResponse<'a>> {
+        if (req.request_type, req.recipient, req.index)
+            != (RequestType::Class, Recipient::Interface, self.if_num.0 as u16)
+        {
+            return None;
+        }
+
+        match req.request {
+            REQ_GETSTATUS => {
+                // Blocks are written synchronously, so the pending operation is already done.
+                self.state = match self.state {
+                    State::DlSync => State::DlIdle,
+                    State::ManifestSync => State::Manifest,
+                    State::Manifest => State::ManifestWaitReset,
+                    state => state,
+                };
+                Some(InResponse::Accepted(status_response(buf, self.status, self.state)))
+            }
+            REQ_GETSTATE => {
+                buf[0] = self.state as u8;
+                Some(InResponse::Accepted(&buf[..1]))
+            }
+            _ => {
+                self.state = State::Error;
+                self.status = Status::ErrStalledPkt;
+                Some(InResponse::Rejected)
+            }
+        }
+    }
+}
+
+/// Adds the DFU mode interface to the bootloader's USB device.
+///
+/// The downloaded firmware is written to the DFU partition, and marked as updated once complete.
+/// The device then resets on the next bus reset, so that the bootloader installs the new firmware.
+pub fn usb_dfu<'d, D: Driver<'d>, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize>(
+    builder: &mut Builder<'d, D>,
+    handler: &'d mut Control<'d, DFU, STATE, RST, BLOCK_SIZE>,
+) {
+    let mut func = builder.function(USB_CLASS_APPN_SPEC, APPN_SPEC_SUBCLASS_DFU, DFU_PROTOCOL_DFU);
+    let mut iface = func.interface();
+    let mut alt = iface.alt_setting(USB_CLASS_APPN_SPEC, APPN_SPEC_SUBCLASS_DFU, DFU_PROTOCOL_DFU, None);
+    handler.if_num = alt.interface_number();
+    alt.descriptor(
+        DESC_DFU_FUNCTIONAL,
+        &functional_descriptor(ATTR_CAN_DNLOAD, 0, BLOCK_SIZE as u16),
+    );
+
+    drop(func);
+    builder.handler(handler);
+}
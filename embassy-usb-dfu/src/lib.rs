@@ -0,0 +1,34 @@
+#![no_std]
+#![warn(missing_docs)]
+#![doc = include_str!("../README.md")]
+mod fmt;
+
+mod consts;
+
+#[cfg(feature = "application")]
+mod application;
+#[cfg(feature = "application")]
+pub use application::*;
+
+#[cfg(feature = "dfu")]
+mod dfu;
+#[cfg(feature = "dfu")]
+pub use dfu::*;
+
+#[cfg(all(feature = "application", feature = "dfu"))]
+compile_error!("The `application` and `dfu` features are mutually exclusive");
+
+/// Provides a platform-agnostic interface for resetting the device.
+pub trait Reset {
+    /// Resets the device.
+    fn sys_reset() -> !;
+}
+
+/// Resets the device immediately with the Cortex-M system reset.
+pub struct ResetImmediate;
+
+impl Reset for ResetImmediate {
+    fn sys_reset() -> ! {
+        cortex_m::peripheral::SCB::sys_reset()
+    }
+}
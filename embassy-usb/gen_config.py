@@ -29,6 +29,7 @@ def feature(name, default, min, max, pow2=None):
 
 feature("max_interface_count", default=4, min=1, max=8)
 feature("max_handler_count", default=4, min=1, max=8)
+feature("max_string_count", default=4, min=1, max=16)
 
 # ========= Update Cargo.toml
 
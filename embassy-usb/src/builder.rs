@@ -1,8 +1,8 @@
 use heapless::Vec;
 
 use crate::config::*;
-use crate::descriptor::{BosWriter, DescriptorWriter};
-use crate::driver::{Driver, Endpoint, EndpointType};
+use crate::descriptor::{BosWriter, DescriptorWriter, SynchronizationType, UsageType};
+use crate::driver::{Driver, Endpoint, EndpointInfo, EndpointType};
 #[cfg(feature = "msos-descriptor")]
 use crate::msos::{DeviceLevelDescriptor, FunctionLevelDescriptor, MsOsDescriptorWriter};
 use crate::types::*;
@@ -58,6 +58,8 @@ pub struct Config<'a> {
 
     /// Serial number string descriptor.
     ///
+    /// It can be derived from the unique ID of the chip with [`serial_number_from_uid`].
+    ///
     /// Default: (none)
     pub serial_number: Option<&'a str>,
 
@@ -66,13 +68,17 @@ pub struct Config<'a> {
     /// Default: `false`
     pub supports_remote_wakeup: bool,
 
-    /// Configures the device as a composite device with interface association descriptors.
+    /// Configures the device as a composite device with interface association descriptors for all
+    /// functions.
     ///
     /// If set to `true`, the following fields should have the given values:
     ///
     /// - `device_class` = `0xEF`
     /// - `device_sub_class` = `0x02`
     /// - `device_protocol` = `0x01`
+    ///
+    /// Functions added with [`Builder::function_with_iad`] always have an interface association
+    /// descriptor. If the device class is left to `0x00`, it is then set to the values above.
     pub composite_with_iads: bool,
 
     /// Whether the device has its own power source.
@@ -119,15 +125,33 @@ impl<'a> Config<'a> {
     }
 }
 
+/// Formats the unique ID of the chip as a hexadecimal serial number, for [`Config::serial_number`].
+///
+/// `buf` must be at least twice as long as `uid`.
+pub fn serial_number_from_uid<'a>(uid: &[u8], buf: &'a mut [u8]) -> &'a str {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    let buf = &mut buf[..uid.len() * 2];
+    for (b, chunk) in uid.iter().zip(buf.chunks_mut(2)) {
+        chunk[0] = HEX[(b >> 4) as usize];
+        chunk[1] = HEX[(b & 0xF) as usize];
+    }
+
+    // Safety: only ASCII characters were written.
+    unsafe { core::str::from_utf8_unchecked(buf) }
+}
+
 /// [`UsbDevice`] builder.
 pub struct Builder<'d, D: Driver<'d>> {
     config: Config<'d>,
     handlers: Vec<&'d mut dyn Handler, MAX_HANDLER_COUNT>,
     interfaces: Vec<Interface, MAX_INTERFACE_COUNT>,
+    strings: Vec<(StringIndex, &'d str), MAX_STRING_COUNT>,
     control_buf: &'d mut [u8],
 
     driver: D,
     next_string_index: u8,
+    has_iads: bool,
 
     device_descriptor: DescriptorWriter<'d>,
     config_descriptor: DescriptorWriter<'d>,
@@ -181,8 +205,10 @@ impl<'d, D: Driver<'d>> Builder<'d, D> {
             config,
             interfaces: Vec::new(),
             handlers: Vec::new(),
+            strings: Vec::new(),
             control_buf,
             next_string_index: STRING_INDEX_CUSTOM_START,
+            has_iads: false,
 
             device_descriptor,
             config_descriptor,
@@ -198,6 +224,14 @@ impl<'d, D: Driver<'d>> Builder<'d, D> {
         #[cfg(feature = "msos-descriptor")]
         let msos_descriptor = self.msos_descriptor.build(&mut self.bos_descriptor);
 
+        // Interface association descriptors require the device to use the IAD class codes.
+        if self.has_iads && !self.config.composite_with_iads && self.config.device_class == 0x00 {
+            self.config.device_class = 0xEF;
+            self.config.device_sub_class = 0x02;
+            self.config.device_protocol = 0x01;
+            self.device_descriptor.buf[4..7].copy_from_slice(&[0xEF, 0x02, 0x01]);
+        }
+
         self.config_descriptor.end_configuration();
         self.bos_descriptor.end_bos();
 
@@ -213,6 +247,7 @@ impl<'d, D: Driver<'d>> Builder<'d, D> {
             self.driver,
             self.config,
             self.handlers,
+            self.strings,
             self.device_descriptor.into_buf(),
             self.config_descriptor.into_buf(),
             self.bos_descriptor.writer.into_buf(),
@@ -236,10 +271,38 @@ impl<'d, D: Driver<'d>> Builder<'d, D> {
     ///
     /// If it's not set, no IAD descriptor is added.
     pub fn function(&mut self, class: u8, subclass: u8, protocol: u8) -> FunctionBuilder<'_, 'd, D> {
+        let iad = self.config.composite_with_iads;
+        self.function_inner(class, subclass, protocol, iad, None)
+    }
+
+    /// Add an USB function with an IAD descriptor, associating all the child interfaces.
+    ///
+    /// This should be used by functions made of several interfaces, so that they are correctly
+    /// grouped by the host in composite devices, whether [`Config::composite_with_iads`] is set
+    /// or not. `function_string` optionally names the function.
+    pub fn function_with_iad(
+        &mut self,
+        class: u8,
+        subclass: u8,
+        protocol: u8,
+        function_string: Option<StringIndex>,
+    ) -> FunctionBuilder<'_, 'd, D> {
+        self.function_inner(class, subclass, protocol, true, function_string)
+    }
+
+    fn function_inner(
+        &mut self,
+        class: u8,
+        subclass: u8,
+        protocol: u8,
+        iad: bool,
+        function_string: Option<StringIndex>,
+    ) -> FunctionBuilder<'_, 'd, D> {
         let first_interface = InterfaceNumber::new(self.interfaces.len() as u8);
-        let iface_count_index = if self.config.composite_with_iads {
+        let iface_count_index = if iad {
+            self.has_iads = true;
             self.config_descriptor
-                .iad(first_interface, 0, class, subclass, protocol);
+                .iad(first_interface, 0, class, subclass, protocol, function_string);
 
             Some(self.config_descriptor.position() - 5)
         } else {
@@ -269,12 +332,35 @@ impl<'d, D: Driver<'d>> Builder<'d, D> {
     }
 
     /// Allocates a new string index.
+    ///
+    /// The string is provided by a [`Handler`], see [`Handler::get_string`].
     pub fn string(&mut self) -> StringIndex {
         let index = self.next_string_index;
         self.next_string_index += 1;
         StringIndex::new(index)
     }
 
+    /// Allocates a new string index for the string `s`, which is returned by the device without a
+    /// [`Handler`].
+    pub fn string_descriptor(&mut self, s: &'d str) -> StringIndex {
+        let index = self.string();
+        if self.strings.push((index, s)).is_err() {
+            panic!(
+                "embassy-usb: string list full. Increase the `max_string_count` compile-time setting. Current value: {}",
+                MAX_STRING_COUNT
+            )
+        }
+        index
+    }
+
+    /// Add a device capability descriptor to the BOS descriptor.
+    ///
+    /// This is used by classes which are announced with a platform capability, such as
+    /// [WebUSB](crate::class::web_usb).
+    pub fn bos_capability(&mut self, capability_type: u8, data: &[u8]) {
+        self.bos_descriptor.capability(capability_type, data)
+    }
+
     #[cfg(feature = "msos-descriptor")]
     /// Add an MS OS 2.0 Descriptor Set.
     ///
@@ -380,6 +466,13 @@ impl<'a, 'd, D: Driver<'d>> InterfaceBuilder<'a, 'd, D> {
         self.builder.string()
     }
 
+    /// Allocates a new string index for the string `s`.
+    ///
+    /// See [`Builder::string_descriptor`].
+    pub fn string_descriptor(&mut self, s: &'d str) -> StringIndex {
+        self.builder.string_descriptor(s)
+    }
+
     /// Add an alternate setting to the interface and write its descriptor.
     ///
     /// Alternate setting numbers are guaranteed to be allocated consecutively, starting from 0.
@@ -440,29 +533,64 @@ impl<'a, 'd, D: Driver<'d>> InterfaceAltBuilder<'a, 'd, D> {
     }
 
     fn endpoint_in(&mut self, ep_type: EndpointType, max_packet_size: u16, interval_ms: u8) -> D::EndpointIn {
-        let ep = self
-            .builder
-            .driver
-            .alloc_endpoint_in(ep_type, max_packet_size, interval_ms)
-            .expect("alloc_endpoint_in failed");
-
+        let ep = self.alloc_endpoint_in(ep_type, max_packet_size, interval_ms);
         self.builder.config_descriptor.endpoint(ep.info());
 
         ep
     }
 
     fn endpoint_out(&mut self, ep_type: EndpointType, max_packet_size: u16, interval_ms: u8) -> D::EndpointOut {
-        let ep = self
-            .builder
-            .driver
-            .alloc_endpoint_out(ep_type, max_packet_size, interval_ms)
-            .expect("alloc_endpoint_out failed");
-
+        let ep = self.alloc_endpoint_out(ep_type, max_packet_size, interval_ms);
         self.builder.config_descriptor.endpoint(ep.info());
 
         ep
     }
 
+    /// Allocate an IN endpoint, without writing its descriptor.
+    ///
+    /// This is needed when the descriptor of an endpoint refers to another endpoint, whose
+    /// descriptor comes after it. The descriptor must then be written with
+    /// [`endpoint_descriptor`](Self::endpoint_descriptor).
+    pub fn alloc_endpoint_in(&mut self, ep_type: EndpointType, max_packet_size: u16, interval_ms: u8) -> D::EndpointIn {
+        self.builder
+            .driver
+            .alloc_endpoint_in(ep_type, max_packet_size, interval_ms)
+            .expect("alloc_endpoint_in failed")
+    }
+
+    /// Allocate an OUT endpoint, without writing its descriptor.
+    ///
+    /// See [`alloc_endpoint_in`](Self::alloc_endpoint_in).
+    pub fn alloc_endpoint_out(
+        &mut self,
+        ep_type: EndpointType,
+        max_packet_size: u16,
+        interval_ms: u8,
+    ) -> D::EndpointOut {
+        self.builder
+            .driver
+            .alloc_endpoint_out(ep_type, max_packet_size, interval_ms)
+            .expect("alloc_endpoint_out failed")
+    }
+
+    /// Write the descriptor of an endpoint allocated with [`alloc_endpoint_in`](Self::alloc_endpoint_in)
+    /// or [`alloc_endpoint_out`](Self::alloc_endpoint_out).
+    ///
+    /// `synchronization_type` and `usage_type` only apply to isochronous endpoints. `extra_fields`
+    /// are appended to the standard fields of the descriptor, like `bRefresh` and `bSynchAddress` of
+    /// audio class endpoints.
+    pub fn endpoint_descriptor(
+        &mut self,
+        endpoint: &EndpointInfo,
+        synchronization_type: SynchronizationType,
+        usage_type: UsageType,
+        extra_fields: &[u8],
+    ) {
+        self.builder
+            .config_descriptor
+            .endpoint_ext(endpoint, synchronization_type, usage_type, extra_fields)
+    }
+
     /// Allocate a BULK IN endpoint and write its descriptor.
     ///
     /// Descriptors are written in the order builder functions are called. Note that some
@@ -260,7 +260,7 @@ impl<'d, D: Driver<'d>> CdcNcmClass<'d, D> {
     ) -> Self {
         state.shared.mac_addr = mac_address;
 
-        let mut func = builder.function(USB_CLASS_CDC, CDC_SUBCLASS_NCM, CDC_PROTOCOL_NONE);
+        let mut func = builder.function_with_iad(USB_CLASS_CDC, CDC_SUBCLASS_NCM, CDC_PROTOCOL_NONE, None);
 
         // Control interface
         let mut iface = func.interface();
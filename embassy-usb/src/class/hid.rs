@@ -1,9 +1,14 @@
 //! USB HID (Human Interface Device) class implementation.
 
+use core::cell::RefCell;
 use core::mem::MaybeUninit;
 use core::ops::Range;
 use core::sync::atomic::{AtomicUsize, Ordering};
 
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::channel::Channel;
+use heapless::Vec;
 #[cfg(feature = "usbd-hid")]
 use ssmarshal::serialize;
 #[cfg(feature = "usbd-hid")]
@@ -187,6 +192,13 @@ impl<'d, D: Driver<'d>, const READ_N: usize, const WRITE_N: usize> HidReaderWrit
         self.writer.write(report).await
     }
 
+    /// Writes the input report `id` to its interrupt endpoint.
+    ///
+    /// See [`HidWriter::write_report`].
+    pub async fn write_report(&mut self, id: u8, data: &[u8]) -> Result<(), EndpointError> {
+        self.writer.write_report(id, data).await
+    }
+
     /// Reads an output report from the Interrupt Out pipe.
     ///
     /// See [`HidReader::read`].
@@ -282,6 +294,18 @@ impl<'d, D: Driver<'d>, const N: usize> HidWriter<'d, D, N> {
 
         Ok(())
     }
+
+    /// Writes the input report `id` to its interrupt endpoint, for interfaces with several reports.
+    ///
+    /// `data` is the content of the report, without the report ID, which is prepended to it.
+    pub async fn write_report(&mut self, id: u8, data: &[u8]) -> Result<(), EndpointError> {
+        assert!(id != 0 && data.len() < N);
+
+        let mut buf: [u8; N] = [0; N];
+        buf[0] = id;
+        buf[1..][..data.len()].copy_from_slice(data);
+        self.write(&buf[..1 + data.len()]).await
+    }
 }
 
 impl<'d, D: Driver<'d>, const N: usize> HidReader<'d, D, N> {
@@ -404,6 +428,110 @@ pub trait RequestHandler {
     }
 }
 
+/// A report sent by the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Report<const N: usize> {
+    /// ID and type of the report: [`ReportId::Out`] or [`ReportId::Feature`].
+    pub id: ReportId,
+    /// Content of the report, starting with the report ID if the interface has several reports.
+    pub data: Vec<u8, N>,
+}
+
+/// [`RequestHandler`] queueing the reports sent by the host, and answering its requests for
+/// reports with stored values.
+///
+/// This allows handling the reports in async code: output reports received by
+/// [`HidReader::run`] and output and feature reports set with control requests are queued, and
+/// retrieved with [`receive`](Self::receive). Up to `DEPTH` reports of at most `N` bytes are
+/// queued, further reports are rejected until there is room in the queue.
+///
+/// Requests for input and feature reports are answered with the values set with
+/// [`set_value`](Self::set_value), for up to `IDS` different reports.
+pub struct ReportQueue<const N: usize, const DEPTH: usize, const IDS: usize> {
+    queue: Channel<CriticalSectionRawMutex, Report<N>, DEPTH>,
+    values: Mutex<CriticalSectionRawMutex, RefCell<ReportValues<N, IDS>>>,
+}
+
+type ReportValues<const N: usize, const IDS: usize> = Vec<(ReportId, Vec<u8, N>), IDS>;
+
+impl<const N: usize, const DEPTH: usize, const IDS: usize> ReportQueue<N, DEPTH, IDS> {
+    /// Create a new `ReportQueue`.
+    pub const fn new() -> Self {
+        Self {
+            queue: Channel::new(),
+            values: Mutex::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Waits for the next report sent by the host.
+    pub async fn receive(&self) -> Report<N> {
+        self.queue.recv().await
+    }
+
+    /// Gets the next report sent by the host, if there is one.
+    pub fn try_receive(&self) -> Option<Report<N>> {
+        self.queue.try_recv().ok()
+    }
+
+    /// Sets the value of report `id`, returned when the host requests it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is longer than `N`, or if values are already set for `IDS` other reports.
+    pub fn set_value(&self, id: ReportId, data: &[u8]) {
+        let data = unwrap!(Vec::from_slice(data));
+        self.values.lock(|values| {
+            let mut values = values.borrow_mut();
+            match values.iter_mut().find(|(i, _)| *i == id) {
+                Some((_, value)) => *value = data,
+                None => {
+                    if values.push((id, data)).is_err() {
+                        panic!("Too many report values");
+                    }
+                }
+            }
+        })
+    }
+
+    /// Clears the value of report `id`, so that requests for it are rejected.
+    pub fn clear_value(&self, id: ReportId) {
+        self.values.lock(|values| values.borrow_mut().retain(|(i, _)| *i != id))
+    }
+}
+
+impl<const N: usize, const DEPTH: usize, const IDS: usize> Default for ReportQueue<N, DEPTH, IDS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, const DEPTH: usize, const IDS: usize> RequestHandler for ReportQueue<N, DEPTH, IDS> {
+    fn get_report(&self, id: ReportId, buf: &mut [u8]) -> Option<usize> {
+        self.values.lock(|values| {
+            let values = values.borrow();
+            let (_, value) = values.iter().find(|(i, _)| *i == id)?;
+            let len = value.len().min(buf.len());
+            buf[..len].copy_from_slice(&value[..len]);
+            Some(len)
+        })
+    }
+
+    fn set_report(&self, id: ReportId, data: &[u8]) -> OutResponse {
+        let Ok(data) = Vec::from_slice(data) else {
+            warn!("Report too long for the queue: {}", data.len());
+            return OutResponse::Rejected;
+        };
+        match self.queue.try_send(Report { id, data }) {
+            Ok(()) => OutResponse::Accepted,
+            Err(_) => {
+                warn!("Report queue full, rejecting report");
+                OutResponse::Rejected
+            }
+        }
+    }
+}
+
 struct Control<'d> {
     if_num: InterfaceNumber,
     report_descriptor: &'d [u8],
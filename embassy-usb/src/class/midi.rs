@@ -0,0 +1,302 @@
+//! USB MIDI class implementation.
+//!
+//! The device exposes a number of virtual cables in each direction, which show up as separate
+//! MIDI ports on the host. MIDI messages are transferred as 4 byte [`MidiEvent`]s tagged with the
+//! cable they belong to.
+
+use crate::driver::{Driver, Endpoint, EndpointError, EndpointIn, EndpointOut};
+use crate::Builder;
+
+const USB_CLASS_AUDIO: u8 = 0x01;
+const AUDIO_SUBCLASS_CONTROL: u8 = 0x01;
+const AUDIO_SUBCLASS_MIDISTREAMING: u8 = 0x03;
+const AUDIO_PROTOCOL_NONE: u8 = 0x00;
+
+const CS_INTERFACE: u8 = 0x24;
+const CS_ENDPOINT: u8 = 0x25;
+const AC_TYPE_HEADER: u8 = 0x01;
+const MS_TYPE_HEADER: u8 = 0x01;
+const MS_TYPE_MIDI_IN_JACK: u8 = 0x02;
+const MS_TYPE_MIDI_OUT_JACK: u8 = 0x03;
+const MS_TYPE_GENERAL: u8 = 0x01;
+const JACK_TYPE_EMBEDDED: u8 = 0x01;
+const JACK_TYPE_EXTERNAL: u8 = 0x02;
+
+const MIDI_IN_JACK_LEN: u16 = 6;
+const MIDI_OUT_JACK_LEN: u16 = 9;
+
+/// Maximum number of virtual cables in each direction.
+pub const MAX_CABLES: u8 = 16;
+
+/// Largest max packet size supported by [`MidiClass::recv_event`].
+pub const MAX_PACKET_SIZE: usize = 64;
+
+/// A USB-MIDI event packet: a MIDI message tagged with the virtual cable it is sent on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MidiEvent {
+    bytes: [u8; 4],
+}
+
+impl MidiEvent {
+    /// Creates an event from a complete MIDI message, other than a system exclusive message.
+    ///
+    /// Returns `None` if `message` doesn't start with a status byte, or its length doesn't match
+    /// the status. System exclusive messages are sent with [`MidiClass::send_sysex`].
+    pub fn new(cable: u8, message: &[u8]) -> Option<Self> {
+        assert!(cable < MAX_CABLES);
+        let status = *message.first()?;
+        let code_index = match status {
+            0x80..=0xef => status >> 4,
+            0xf1 | 0xf3 => 0x2,
+            0xf2 => 0x3,
+            0xf6 => 0x5,
+            0xf8..=0xff => 0xf,
+            _ => return None,
+        };
+        if message.len() != event_len(code_index) {
+            return None;
+        }
+
+        let mut bytes = [cable << 4 | code_index, 0, 0, 0];
+        bytes[1..][..message.len()].copy_from_slice(message);
+        Some(Self { bytes })
+    }
+
+    /// Creates an event from its 4 byte USB representation.
+    pub fn from_bytes(bytes: [u8; 4]) -> Self {
+        Self { bytes }
+    }
+
+    /// The 4 byte USB representation of the event.
+    pub fn to_bytes(&self) -> [u8; 4] {
+        self.bytes
+    }
+
+    /// Virtual cable the event is sent on.
+    pub fn cable(&self) -> u8 {
+        self.bytes[0] >> 4
+    }
+
+    /// Code Index Number, classifying the MIDI message of the event.
+    pub fn code_index(&self) -> u8 {
+        self.bytes[0] & 0x0f
+    }
+
+    /// The MIDI message, or part of a system exclusive message, carried by the event.
+    pub fn message(&self) -> &[u8] {
+        &self.bytes[1..][..event_len(self.code_index())]
+    }
+}
+
+/// Length of the MIDI message carried by an event with the given Code Index Number.
+fn event_len(code_index: u8) -> usize {
+    match code_index {
+        0x5 | 0xf => 1,
+        0x2 | 0x6 | 0xc | 0xd => 2,
+        _ => 3,
+    }
+}
+
+/// USB MIDI class.
+pub struct MidiClass<'d, D: Driver<'d>> {
+    read_ep: D::EndpointOut,
+    write_ep: D::EndpointIn,
+    rx_buf: [u8; MAX_PACKET_SIZE],
+    rx_pos: usize,
+    rx_len: usize,
+}
+
+impl<'d, D: Driver<'d>> MidiClass<'d, D> {
+    /// Creates a new MidiClass with `n_in_cables` cables from the host to the device, and
+    /// `n_out_cables` cables from the device to the host.
+    ///
+    /// `max_packet_size` must be a multiple of 4, up to [`MAX_PACKET_SIZE`].
+    pub fn new(builder: &mut Builder<'d, D>, n_in_cables: u8, n_out_cables: u8, max_packet_size: u16) -> Self {
+        assert!(n_in_cables <= MAX_CABLES && n_out_cables <= MAX_CABLES);
+        assert!(max_packet_size as usize <= MAX_PACKET_SIZE && max_packet_size % 4 == 0);
+
+        let mut func = builder.function_with_iad(USB_CLASS_AUDIO, AUDIO_SUBCLASS_CONTROL, AUDIO_PROTOCOL_NONE, None);
+
+        // Audio control interface, only needed to reference the streaming interface.
+        let mut iface = func.interface();
+        let ms_if = u8::from(iface.interface_number()) + 1;
+        let mut alt = iface.alt_setting(USB_CLASS_AUDIO, AUDIO_SUBCLASS_CONTROL, AUDIO_PROTOCOL_NONE, None);
+        alt.descriptor(
+            CS_INTERFACE,
+            &[
+                AC_TYPE_HEADER, // bDescriptorSubtype
+                0x00,           // bcdADC (1.00)
+                0x01,           // |
+                0x09,           // wTotalLength
+                0x00,           // |
+                0x01,           // bInCollection
+                ms_if,          // baInterfaceNr
+            ],
+        );
+
+        // MIDI streaming interface
+        let mut iface = func.interface();
+        let mut alt = iface.alt_setting(USB_CLASS_AUDIO, AUDIO_SUBCLASS_MIDISTREAMING, AUDIO_PROTOCOL_NONE, None);
+
+        let total_len = 7 + (n_in_cables as u16 + n_out_cables as u16) * (MIDI_IN_JACK_LEN + MIDI_OUT_JACK_LEN);
+        alt.descriptor(
+            CS_INTERFACE,
+            &[
+                MS_TYPE_HEADER,         // bDescriptorSubtype
+                0x00,                   // bcdMSC (1.00)
+                0x01,                   // |
+                total_len as u8,        // wTotalLength
+                (total_len >> 8) as u8, // |
+            ],
+        );
+
+        // Jack IDs: the embedded IN jacks come first, so that they are numbered 1..=n_in_cables,
+        // followed by the embedded OUT jacks, then the external jacks connected to them.
+        let emb_in_id = |cable: u8| 1 + cable;
+        let emb_out_id = |cable: u8| 1 + n_in_cables + cable;
+        let ext_out_id = |cable: u8| 1 + n_in_cables + n_out_cables + cable;
+        let ext_in_id = |cable: u8| 1 + 2 * n_in_cables + n_out_cables + cable;
+
+        for cable in 0..n_in_cables {
+            alt.descriptor(
+                CS_INTERFACE,
+                &[MS_TYPE_MIDI_IN_JACK, JACK_TYPE_EMBEDDED, emb_in_id(cable), 0x00],
+            );
+            alt.descriptor(
+                CS_INTERFACE,
+                &[
+                    MS_TYPE_MIDI_OUT_JACK,
+                    JACK_TYPE_EXTERNAL,
+                    ext_out_id(cable),
+                    0x01,             // bNrInputPins
+                    emb_in_id(cable), // baSourceID
+                    0x01,             // baSourcePin
+                    0x00,             // iJack
+                ],
+            );
+        }
+        for cable in 0..n_out_cables {
+            alt.descriptor(
+                CS_INTERFACE,
+                &[MS_TYPE_MIDI_IN_JACK, JACK_TYPE_EXTERNAL, ext_in_id(cable), 0x00],
+            );
+            alt.descriptor(
+                CS_INTERFACE,
+                &[
+                    MS_TYPE_MIDI_OUT_JACK,
+                    JACK_TYPE_EMBEDDED,
+                    emb_out_id(cable),
+                    0x01,             // bNrInputPins
+                    ext_in_id(cable), // baSourceID
+                    0x01,             // baSourcePin
+                    0x00,             // iJack
+                ],
+            );
+        }
+
+        // Each endpoint lists the embedded jacks it carries, in cable order.
+        let mut jacks = [0; 2 + MAX_CABLES as usize];
+
+        let read_ep = alt.endpoint_bulk_out(max_packet_size);
+        jacks[0] = MS_TYPE_GENERAL;
+        jacks[1] = n_in_cables;
+        for cable in 0..n_in_cables {
+            jacks[2 + cable as usize] = emb_in_id(cable);
+        }
+        alt.descriptor(CS_ENDPOINT, &jacks[..2 + n_in_cables as usize]);
+
+        let write_ep = alt.endpoint_bulk_in(max_packet_size);
+        jacks[1] = n_out_cables;
+        for cable in 0..n_out_cables {
+            jacks[2 + cable as usize] = emb_out_id(cable);
+        }
+        alt.descriptor(CS_ENDPOINT, &jacks[..2 + n_out_cables as usize]);
+
+        MidiClass {
+            read_ep,
+            write_ep,
+            rx_buf: [0; MAX_PACKET_SIZE],
+            rx_pos: 0,
+            rx_len: 0,
+        }
+    }
+
+    /// Gets the maximum packet size in bytes.
+    pub fn max_packet_size(&self) -> u16 {
+        // The size is the same for both endpoints.
+        self.read_ep.info().max_packet_size
+    }
+
+    /// Writes a single packet of events into the IN endpoint.
+    pub async fn write_packet(&mut self, data: &[u8]) -> Result<(), EndpointError> {
+        self.write_ep.write(data).await
+    }
+
+    /// Reads a single packet of events from the OUT endpoint.
+    pub async fn read_packet(&mut self, data: &mut [u8]) -> Result<usize, EndpointError> {
+        self.read_ep.read(data).await
+    }
+
+    /// Sends a single event to the host.
+    pub async fn send_event(&mut self, event: MidiEvent) -> Result<(), EndpointError> {
+        self.write_ep.write(&event.to_bytes()).await
+    }
+
+    /// Sends a complete system exclusive message, from the initial `0xF0` to the final `0xF7`.
+    pub async fn send_sysex(&mut self, cable: u8, message: &[u8]) -> Result<(), EndpointError> {
+        assert!(cable < MAX_CABLES);
+        assert!(message.len() >= 2 && message[0] == 0xf0 && message[message.len() - 1] == 0xf7);
+
+        let mut packet = [0; MAX_PACKET_SIZE];
+        let max_packet_size = self.max_packet_size() as usize;
+        let mut len = 0;
+        let mut chunks = message.chunks(3).peekable();
+        while let Some(chunk) = chunks.next() {
+            let code_index = match (chunks.peek(), chunk.len()) {
+                (Some(_), _) => 0x4, // start or continue
+                (None, 1) => 0x5,
+                (None, 2) => 0x6,
+                (None, _) => 0x7,
+            };
+            packet[len] = cable << 4 | code_index;
+            packet[len + 1..][..3].fill(0);
+            packet[len + 1..][..chunk.len()].copy_from_slice(chunk);
+            len += 4;
+
+            if len == max_packet_size {
+                self.write_ep.write(&packet[..len]).await?;
+                len = 0;
+            }
+        }
+        if len > 0 {
+            self.write_ep.write(&packet[..len]).await?;
+        }
+        Ok(())
+    }
+
+    /// Receives a single event from the host.
+    ///
+    /// Several events are usually received in one packet: they are returned by successive calls.
+    pub async fn recv_event(&mut self) -> Result<MidiEvent, EndpointError> {
+        loop {
+            while self.rx_pos + 4 <= self.rx_len {
+                let bytes = self.rx_buf[self.rx_pos..][..4].try_into().unwrap();
+                self.rx_pos += 4;
+                // padding at the end of a packet
+                if bytes != [0; 4] {
+                    return Ok(MidiEvent::from_bytes(bytes));
+                }
+            }
+
+            self.rx_pos = 0;
+            self.rx_len = 0;
+            self.rx_len = self.read_ep.read(&mut self.rx_buf).await?;
+        }
+    }
+
+    /// Waits for the USB host to enable this interface
+    pub async fn wait_connection(&mut self) {
+        self.read_ep.wait_enabled().await
+    }
+}
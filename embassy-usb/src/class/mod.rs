@@ -1,4 +1,9 @@
 //! Implementations of well-known USB classes.
 pub mod cdc_acm;
+pub mod cdc_ecm;
 pub mod cdc_ncm;
 pub mod hid;
+pub mod midi;
+pub mod msc;
+pub mod uac1;
+pub mod web_usb;
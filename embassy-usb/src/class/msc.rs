@@ -0,0 +1,554 @@
+//! USB Mass Storage class implementation, using the Bulk-Only Transport and the SCSI transparent
+//! command set.
+//!
+//! The storage itself is provided by a [`BlockDevice`]. Only the commands needed by common hosts
+//! (Linux, Windows and macOS) are implemented, and a single logical unit is exposed.
+
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::control::{InResponse, OutResponse, Recipient, Request, RequestType};
+use crate::driver::{Driver, Endpoint, EndpointError, EndpointIn, EndpointOut};
+use crate::types::InterfaceNumber;
+use crate::{Builder, Handler};
+
+const USB_CLASS_MSC: u8 = 0x08;
+const MSC_SUBCLASS_SCSI: u8 = 0x06;
+const MSC_PROTOCOL_BULK_ONLY: u8 = 0x50;
+
+const REQ_GET_MAX_LUN: u8 = 0xfe;
+const REQ_BULK_ONLY_RESET: u8 = 0xff;
+
+const CBW_SIGNATURE: u32 = 0x4342_5355;
+const CBW_LEN: usize = 31;
+const CSW_SIGNATURE: u32 = 0x5342_5355;
+const CSW_LEN: usize = 13;
+
+const SCSI_TEST_UNIT_READY: u8 = 0x00;
+const SCSI_REQUEST_SENSE: u8 = 0x03;
+const SCSI_INQUIRY: u8 = 0x12;
+const SCSI_MODE_SENSE_6: u8 = 0x1a;
+const SCSI_START_STOP_UNIT: u8 = 0x1b;
+const SCSI_PREVENT_ALLOW_MEDIUM_REMOVAL: u8 = 0x1e;
+const SCSI_READ_FORMAT_CAPACITIES: u8 = 0x23;
+const SCSI_READ_CAPACITY_10: u8 = 0x25;
+const SCSI_READ_10: u8 = 0x28;
+const SCSI_WRITE_10: u8 = 0x2a;
+const SCSI_VERIFY_10: u8 = 0x2f;
+const SCSI_SYNCHRONIZE_CACHE_10: u8 = 0x35;
+const SCSI_MODE_SENSE_10: u8 = 0x5a;
+
+/// Block storage exposed by the [`MscClass`].
+pub trait BlockDevice {
+    /// Error returned by the device.
+    type Error;
+
+    /// Size of a block in bytes, usually 512.
+    fn block_size(&self) -> usize;
+
+    /// Number of blocks of the device.
+    fn block_count(&self) -> u32;
+
+    /// Whether the host is allowed to write to the device.
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    /// Read the blocks starting at `lba` into `buf`, whose length is a multiple of the block size.
+    async fn read(&mut self, lba: u32, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Write `buf`, whose length is a multiple of the block size, to the blocks starting at `lba`.
+    async fn write(&mut self, lba: u32, buf: &[u8]) -> Result<(), Self::Error>;
+
+    /// Make sure all written blocks are stored, when the host synchronizes its cache.
+    async fn flush(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Configuration for the Mass Storage class.
+pub struct Config<'d> {
+    /// Vendor identification reported to the host, up to 8 ASCII characters.
+    pub vendor: &'d str,
+
+    /// Product identification reported to the host, up to 16 ASCII characters.
+    pub product: &'d str,
+
+    /// Product revision reported to the host, up to 4 ASCII characters.
+    pub revision: &'d str,
+
+    /// Whether the host should treat the medium as removable.
+    pub removable: bool,
+
+    /// Max packet size for both the IN and OUT endpoints.
+    pub max_packet_size: u16,
+}
+
+impl<'d> Default for Config<'d> {
+    fn default() -> Self {
+        Self {
+            vendor: "Embassy",
+            product: "Mass Storage",
+            revision: "1.0",
+            removable: true,
+            max_packet_size: 64,
+        }
+    }
+}
+
+/// Internal state for the Mass Storage class.
+pub struct State<'d> {
+    control: MaybeUninit<Control<'d>>,
+    reset: AtomicBool,
+}
+
+impl<'d> State<'d> {
+    /// Create a new `State`.
+    pub fn new() -> Self {
+        Self {
+            control: MaybeUninit::uninit(),
+            reset: AtomicBool::new(false),
+        }
+    }
+}
+
+impl<'d> Default for State<'d> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Control<'d> {
+    if_num: InterfaceNumber,
+    reset: &'d AtomicBool,
+}
+
+impl<'d> Handler for Control<'d> {
+    fn reset(&mut self) {
+        self.reset.store(true, Ordering::Relaxed);
+    }
+
+    fn control_out(&mut self, req: Request, _data: &[u8]) -> Option<OutResponse> {
+        if (req.request_type, req.recipient, req.index)
+            != (RequestType::Class, Recipient::Interface, self.if_num.0 as u16)
+        {
+            return None;
+        }
+
+        match req.request {
+            REQ_BULK_ONLY_RESET => {
+                debug!("Bulk-Only Mass Storage Reset");
+                self.reset.store(true, Ordering::Relaxed);
+                Some(OutResponse::Accepted)
+            }
+            _ => Some(OutResponse::Rejected),
+        }
+    }
+
+    fn control_in<'a>(&'a mut self, req: Request, buf: &'a mut [u8]) -> Option<InResponse<'a>> {
+        if (req.request_type, req.recipient, req.index)
+            != (RequestType::Class, Recipient::Interface, self.if_num.0 as u16)
+        {
+            return None;
+        }
+
+        match req.request {
+            REQ_GET_MAX_LUN => {
+                // only LUN 0
+                buf[0] = 0;
+                Some(InResponse::Accepted(&buf[..1]))
+            }
+            _ => Some(InResponse::Rejected),
+        }
+    }
+}
+
+/// SCSI sense data, describing why the last command failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+struct Sense {
+    key: u8,
+    asc: u8,
+    ascq: u8,
+}
+
+impl Sense {
+    const NONE: Self = Self::new(0x00, 0x00, 0x00);
+    const UNRECOVERED_READ_ERROR: Self = Self::new(0x03, 0x11, 0x00);
+    const WRITE_ERROR: Self = Self::new(0x03, 0x0c, 0x00);
+    const INVALID_COMMAND: Self = Self::new(0x05, 0x20, 0x00);
+    const LBA_OUT_OF_RANGE: Self = Self::new(0x05, 0x21, 0x00);
+    const INVALID_FIELD_IN_CDB: Self = Self::new(0x05, 0x24, 0x00);
+    const WRITE_PROTECTED: Self = Self::new(0x07, 0x27, 0x00);
+
+    const fn new(key: u8, asc: u8, ascq: u8) -> Self {
+        Self { key, asc, ascq }
+    }
+}
+
+/// Status of a command, sent to the host in the Command Status Wrapper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum Status {
+    Passed = 0x00,
+    Failed = 0x01,
+    PhaseError = 0x02,
+}
+
+/// Command Block Wrapper, sent by the host to start a command.
+struct Cbw {
+    tag: u32,
+    data_len: u32,
+    dir_in: bool,
+    cb: [u8; 16],
+}
+
+impl Cbw {
+    fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() != CBW_LEN || u32::from_le_bytes(buf[0..4].try_into().unwrap()) != CBW_SIGNATURE {
+            return None;
+        }
+        let cb_len = buf[14] as usize;
+        if !(1..=16).contains(&cb_len) {
+            return None;
+        }
+        let mut cb = [0; 16];
+        cb[..cb_len].copy_from_slice(&buf[15..15 + cb_len]);
+
+        Some(Self {
+            tag: u32::from_le_bytes(buf[4..8].try_into().unwrap()),
+            data_len: u32::from_le_bytes(buf[8..12].try_into().unwrap()),
+            dir_in: buf[12] & 0x80 != 0,
+            cb,
+        })
+    }
+}
+
+/// USB Mass Storage class, exposing a [`BlockDevice`] to the host as a drive.
+pub struct MscClass<'d, D: Driver<'d>, B: BlockDevice> {
+    read_ep: D::EndpointOut,
+    write_ep: D::EndpointIn,
+    reset: &'d AtomicBool,
+    device: B,
+    buf: &'d mut [u8],
+    sense: Sense,
+    transferred: u32, // bytes of the data phase of the current command
+    config: Config<'d>,
+}
+
+impl<'d, D: Driver<'d>, B: BlockDevice> MscClass<'d, D, B> {
+    /// Creates a new MscClass.
+    ///
+    /// `buf` holds the blocks being transferred. Its length must be a multiple of the block size of
+    /// `device`, and the block size must be a multiple of the max packet size. Larger buffers allow
+    /// transferring several blocks from or to the device at once.
+    pub fn new(
+        builder: &mut Builder<'d, D>,
+        state: &'d mut State<'d>,
+        device: B,
+        buf: &'d mut [u8],
+        config: Config<'d>,
+    ) -> Self {
+        let block_size = device.block_size();
+        assert!(block_size > 0 && buf.len() >= block_size && buf.len() % block_size == 0);
+        assert!(block_size % config.max_packet_size as usize == 0);
+
+        let mut func = builder.function(USB_CLASS_MSC, MSC_SUBCLASS_SCSI, MSC_PROTOCOL_BULK_ONLY);
+        let mut iface = func.interface();
+        let if_num = iface.interface_number();
+        let mut alt = iface.alt_setting(USB_CLASS_MSC, MSC_SUBCLASS_SCSI, MSC_PROTOCOL_BULK_ONLY, None);
+        let read_ep = alt.endpoint_bulk_out(config.max_packet_size);
+        let write_ep = alt.endpoint_bulk_in(config.max_packet_size);
+        drop(func);
+
+        let control = state.control.write(Control {
+            if_num,
+            reset: &state.reset,
+        });
+        builder.handler(control);
+
+        Self {
+            read_ep,
+            write_ep,
+            reset: &state.reset,
+            device,
+            buf,
+            sense: Sense::NONE,
+            transferred: 0,
+            config,
+        }
+    }
+
+    /// Gets the block device.
+    pub fn device(&mut self) -> &mut B {
+        &mut self.device
+    }
+
+    /// Serves the commands of the host. This needs to be running for the drive to be usable.
+    pub async fn run(&mut self) -> ! {
+        loop {
+            self.read_ep.wait_enabled().await;
+            self.reset.store(false, Ordering::Relaxed);
+            self.sense = Sense::NONE;
+            debug!("Mass storage connected");
+
+            loop {
+                match self.handle_command().await {
+                    Ok(()) => {}
+                    Err(EndpointError::Disabled) => break,
+                    Err(EndpointError::BufferOverflow) => warn!("Mass storage packet too long"),
+                }
+            }
+            debug!("Mass storage disconnected");
+        }
+    }
+
+    async fn handle_command(&mut self) -> Result<(), EndpointError> {
+        let mps = self.config.max_packet_size as usize;
+        let n = self.read_ep.read(&mut self.buf[..mps]).await?;
+        // No `swap`, it's not available on thumbv6m.
+        if self.reset.load(Ordering::Relaxed) {
+            self.reset.store(false, Ordering::Relaxed);
+            self.sense = Sense::NONE;
+        }
+        let Some(cbw) = Cbw::parse(&self.buf[..n]) else {
+            warn!("Invalid command block wrapper");
+            return Ok(());
+        };
+        trace!("SCSI command {:02x}, data_len={}", cbw.cb[0], cbw.data_len);
+
+        self.transferred = 0;
+        let status = match self.execute(&cbw).await {
+            Ok(()) => Status::Passed,
+            Err(CommandError::Failed(sense)) => {
+                debug!("SCSI command {:02x} failed: {:?}", cbw.cb[0], sense);
+                self.sense = sense;
+                Status::Failed
+            }
+            Err(CommandError::Phase) => {
+                debug!("SCSI command {:02x}: phase error", cbw.cb[0]);
+                Status::PhaseError
+            }
+            Err(CommandError::Endpoint(e)) => return Err(e),
+        };
+        self.finish_data(&cbw).await?;
+
+        let mut csw = [0; CSW_LEN];
+        csw[0..4].copy_from_slice(&CSW_SIGNATURE.to_le_bytes());
+        csw[4..8].copy_from_slice(&cbw.tag.to_le_bytes());
+        csw[8..12].copy_from_slice(&(cbw.data_len - self.transferred).to_le_bytes());
+        csw[12] = status as u8;
+        self.write_ep.write(&csw).await
+    }
+
+    async fn execute(&mut self, cbw: &Cbw) -> Result<(), CommandError> {
+        let cb = &cbw.cb;
+        let mut resp = [0u8; 36];
+        let len = match cb[0] {
+            SCSI_TEST_UNIT_READY | SCSI_START_STOP_UNIT | SCSI_PREVENT_ALLOW_MEDIUM_REMOVAL | SCSI_VERIFY_10 => {
+                return no_data(cbw);
+            }
+            SCSI_SYNCHRONIZE_CACHE_10 => {
+                no_data(cbw)?;
+                return self
+                    .device
+                    .flush()
+                    .await
+                    .map_err(|_| CommandError::Failed(Sense::WRITE_ERROR));
+            }
+            SCSI_READ_10 | SCSI_WRITE_10 => {
+                let lba = u32::from_be_bytes(cb[2..6].try_into().unwrap());
+                let blocks = u16::from_be_bytes(cb[7..9].try_into().unwrap()) as u32;
+                return if cb[0] == SCSI_READ_10 {
+                    self.read_blocks(cbw, lba, blocks).await
+                } else {
+                    self.write_blocks(cbw, lba, blocks).await
+                };
+            }
+            SCSI_REQUEST_SENSE => {
+                resp[0] = 0x70; // current error, fixed format
+                resp[2] = self.sense.key;
+                resp[7] = 10; // additional sense length
+                resp[12] = self.sense.asc;
+                resp[13] = self.sense.ascq;
+                self.sense = Sense::NONE;
+                18
+            }
+            SCSI_INQUIRY => {
+                if cb[1] & 0x01 != 0 {
+                    // vital product data pages are not supported
+                    return Err(CommandError::Failed(Sense::INVALID_FIELD_IN_CDB));
+                }
+                resp[0] = 0x00; // direct access block device
+                resp[1] = if self.config.removable { 0x80 } else { 0x00 };
+                resp[2] = 0x04; // SPC-2
+                resp[3] = 0x02; // response data format
+                resp[4] = 36 - 5; // additional length
+                ascii_field(&mut resp[8..16], self.config.vendor);
+                ascii_field(&mut resp[16..32], self.config.product);
+                ascii_field(&mut resp[32..36], self.config.revision);
+                36
+            }
+            SCSI_READ_CAPACITY_10 => {
+                let last_lba = self.device.block_count().saturating_sub(1);
+                resp[0..4].copy_from_slice(&last_lba.to_be_bytes());
+                resp[4..8].copy_from_slice(&(self.device.block_size() as u32).to_be_bytes());
+                8
+            }
+            SCSI_READ_FORMAT_CAPACITIES => {
+                resp[3] = 8; // capacity list length
+                resp[4..8].copy_from_slice(&self.device.block_count().to_be_bytes());
+                resp[8] = 0x02; // formatted media
+                resp[9..12].copy_from_slice(&(self.device.block_size() as u32).to_be_bytes()[1..]);
+                12
+            }
+            SCSI_MODE_SENSE_6 => {
+                resp[0] = 3; // mode data length
+                resp[2] = self.write_protect_flag();
+                4
+            }
+            SCSI_MODE_SENSE_10 => {
+                resp[1] = 6; // mode data length
+                resp[3] = self.write_protect_flag();
+                8
+            }
+            _ => return Err(CommandError::Failed(Sense::INVALID_COMMAND)),
+        };
+
+        if cbw.data_len == 0 {
+            return Ok(());
+        }
+        if !cbw.dir_in {
+            return Err(CommandError::Phase);
+        }
+        // the host may ask for less than the whole response
+        let len = len.min(cbw.data_len as usize);
+        self.write_data(&resp[..len]).await
+    }
+
+    async fn read_blocks(&mut self, cbw: &Cbw, lba: u32, blocks: u32) -> Result<(), CommandError> {
+        self.check_transfer(cbw, true, lba, blocks)?;
+
+        let block_size = self.device.block_size();
+        let chunk_blocks = (self.buf.len() / block_size) as u32;
+        let mut done = 0;
+        while done < blocks {
+            let n = chunk_blocks.min(blocks - done);
+            let len = n as usize * block_size;
+            self.device
+                .read(lba + done, &mut self.buf[..len])
+                .await
+                .map_err(|_| CommandError::Failed(Sense::UNRECOVERED_READ_ERROR))?;
+            for packet in self.buf[..len].chunks(self.config.max_packet_size as usize) {
+                self.write_ep.write(packet).await?;
+                self.transferred += packet.len() as u32;
+            }
+            done += n;
+        }
+        Ok(())
+    }
+
+    async fn write_blocks(&mut self, cbw: &Cbw, lba: u32, blocks: u32) -> Result<(), CommandError> {
+        self.check_transfer(cbw, false, lba, blocks)?;
+        if self.device.is_read_only() {
+            return Err(CommandError::Failed(Sense::WRITE_PROTECTED));
+        }
+
+        let mps = self.config.max_packet_size as usize;
+        let block_size = self.device.block_size();
+        let chunk_blocks = (self.buf.len() / block_size) as u32;
+        let mut done = 0;
+        while done < blocks {
+            let n = chunk_blocks.min(blocks - done);
+            let len = n as usize * block_size;
+            let mut pos = 0;
+            while pos < len {
+                let end = (pos + mps).min(len);
+                let n = self.read_ep.read(&mut self.buf[pos..end]).await?;
+                pos += n;
+                self.transferred += n as u32;
+            }
+            self.device
+                .write(lba + done, &self.buf[..len])
+                .await
+                .map_err(|_| CommandError::Failed(Sense::WRITE_ERROR))?;
+            done += n;
+        }
+        Ok(())
+    }
+
+    /// Checks that a block transfer is in range, and agrees with the data phase expected by the host.
+    fn check_transfer(&self, cbw: &Cbw, dir_in: bool, lba: u32, blocks: u32) -> Result<(), CommandError> {
+        let len = blocks as u64 * self.device.block_size() as u64;
+        if len != cbw.data_len as u64 || (len != 0 && cbw.dir_in != dir_in) {
+            return Err(CommandError::Phase);
+        }
+        if lba as u64 + blocks as u64 > self.device.block_count() as u64 {
+            return Err(CommandError::Failed(Sense::LBA_OUT_OF_RANGE));
+        }
+        Ok(())
+    }
+
+    /// Writes the response of a command to the IN endpoint.
+    async fn write_data(&mut self, data: &[u8]) -> Result<(), CommandError> {
+        for packet in data.chunks(self.config.max_packet_size as usize) {
+            self.write_ep.write(packet).await?;
+            self.transferred += packet.len() as u32;
+        }
+        Ok(())
+    }
+
+    /// Completes the data phase of a command that transferred less than the host expected, by ending
+    /// an IN transfer with a short packet, or discarding the rest of an OUT transfer.
+    async fn finish_data(&mut self, cbw: &Cbw) -> Result<(), EndpointError> {
+        if self.transferred >= cbw.data_len {
+            return Ok(());
+        }
+        let mps = self.config.max_packet_size as usize;
+        if cbw.dir_in {
+            if self.transferred as usize % mps == 0 {
+                self.write_ep.write(&[]).await?;
+            }
+        } else {
+            while self.transferred < cbw.data_len {
+                let len = mps.min((cbw.data_len - self.transferred) as usize);
+                self.transferred += self.read_ep.read(&mut self.buf[..len]).await? as u32;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_protect_flag(&self) -> u8 {
+        if self.device.is_read_only() {
+            0x80
+        } else {
+            0x00
+        }
+    }
+}
+
+enum CommandError {
+    Failed(Sense),
+    Phase,
+    Endpoint(EndpointError),
+}
+
+impl From<EndpointError> for CommandError {
+    fn from(e: EndpointError) -> Self {
+        Self::Endpoint(e)
+    }
+}
+
+fn no_data(cbw: &Cbw) -> Result<(), CommandError> {
+    if cbw.data_len == 0 {
+        Ok(())
+    } else {
+        // the host expects data from a command that doesn't transfer any
+        Err(CommandError::Phase)
+    }
+}
+
+/// Copies `s` into a fixed length ASCII field, padded with spaces.
+fn ascii_field(field: &mut [u8], s: &str) {
+    field.fill(b' ');
+    let len = s.len().min(field.len());
+    field[..len].copy_from_slice(&s.as_bytes()[..len]);
+}
@@ -0,0 +1,377 @@
+//! USB Audio Class 1.0 implementation, for speakers and microphones.
+//!
+//! Each [`Speaker`] and [`Microphone`] is a separate audio function, made of an audio control
+//! interface and an audio streaming interface. Interleaved little endian PCM samples are
+//! transferred in isochronous packets, one every frame (1 ms).
+//!
+//! Both are asynchronous: the device runs its own audio clock. The speaker reports the rate at
+//! which it consumes samples on a feedback endpoint, so that the host sends more or fewer samples
+//! per frame. The microphone sends as many samples as it has captured in each frame.
+//!
+//! This needs a driver with isochronous endpoint support.
+
+use crate::builder::{FunctionBuilder, InterfaceAltBuilder};
+use crate::descriptor::{SynchronizationType, UsageType};
+use crate::driver::{Driver, Endpoint, EndpointError, EndpointIn, EndpointOut, EndpointType};
+use crate::Builder;
+
+const USB_CLASS_AUDIO: u8 = 0x01;
+const AUDIO_SUBCLASS_CONTROL: u8 = 0x01;
+const AUDIO_SUBCLASS_STREAMING: u8 = 0x02;
+const AUDIO_PROTOCOL_NONE: u8 = 0x00;
+
+const CS_INTERFACE: u8 = 0x24;
+const CS_ENDPOINT: u8 = 0x25;
+const AC_TYPE_HEADER: u8 = 0x01;
+const AC_TYPE_INPUT_TERMINAL: u8 = 0x02;
+const AC_TYPE_OUTPUT_TERMINAL: u8 = 0x03;
+const AS_TYPE_GENERAL: u8 = 0x01;
+const AS_TYPE_FORMAT: u8 = 0x02;
+const EP_TYPE_GENERAL: u8 = 0x01;
+
+const TERMINAL_USB_STREAMING: u16 = 0x0101;
+const TERMINAL_MICROPHONE: u16 = 0x0201;
+const TERMINAL_SPEAKER: u16 = 0x0301;
+
+const FORMAT_TYPE_I: u8 = 0x01;
+const FORMAT_TAG_PCM: u16 = 0x0001;
+
+const INPUT_TERMINAL_ID: u8 = 1;
+const OUTPUT_TERMINAL_ID: u8 = 2;
+
+/// Length of the audio control interface descriptors: header, input and output terminals.
+const AC_TOTAL_LEN: u16 = 9 + 12 + 9;
+
+/// The feedback is polled by the host every 2^FEEDBACK_REFRESH frames.
+const FEEDBACK_REFRESH: u8 = 5;
+
+/// Format of the samples of an audio stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Sample rate in Hz.
+    pub sample_rate: u32,
+    /// Number of interleaved channels.
+    pub channels: u8,
+    /// Bytes per sample of a single channel: 2 for 16 bit samples, 3 for 24 bit samples.
+    pub sample_width: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            sample_rate: 48_000,
+            channels: 2,
+            sample_width: 2,
+        }
+    }
+}
+
+impl Config {
+    /// Size in bytes of the samples of all channels at a point in time.
+    pub fn frame_size(&self) -> usize {
+        self.channels as usize * self.sample_width as usize
+    }
+
+    /// Size in bytes of the largest packet, which has one sample more than the nominal rate so that
+    /// the clocks of the host and the device can be kept in sync.
+    pub fn max_packet_size(&self) -> u16 {
+        ((self.sample_rate as usize / 1000 + 1) * self.frame_size()) as u16
+    }
+
+    /// Nominal number of samples per frame, in the 10.14 fixed point format of the feedback.
+    pub fn nominal_feedback(&self) -> u32 {
+        (((self.sample_rate as u64) << 14) / 1000) as u32
+    }
+}
+
+/// Writes the audio control interface of a function, with a single input and output terminal.
+fn audio_control<'d, D: Driver<'d>>(
+    func: &mut FunctionBuilder<'_, 'd, D>,
+    input_terminal_type: u16,
+    output_terminal_type: u16,
+    config: &Config,
+) {
+    let mut iface = func.interface();
+    let as_if = u8::from(iface.interface_number()) + 1;
+    let mut alt = iface.alt_setting(USB_CLASS_AUDIO, AUDIO_SUBCLASS_CONTROL, AUDIO_PROTOCOL_NONE, None);
+
+    let channel_config: u16 = match config.channels {
+        2 => 0x0003, // left and right front
+        _ => 0x0000,
+    };
+
+    alt.descriptor(
+        CS_INTERFACE,
+        &[
+            AC_TYPE_HEADER,            // bDescriptorSubtype
+            0x00,                      // bcdADC (1.00)
+            0x01,                      // |
+            AC_TOTAL_LEN as u8,        // wTotalLength
+            (AC_TOTAL_LEN >> 8) as u8, // |
+            0x01,                      // bInCollection
+            as_if,                     // baInterfaceNr
+        ],
+    );
+    alt.descriptor(
+        CS_INTERFACE,
+        &[
+            AC_TYPE_INPUT_TERMINAL,           // bDescriptorSubtype
+            INPUT_TERMINAL_ID,                // bTerminalID
+            input_terminal_type as u8,        // wTerminalType
+            (input_terminal_type >> 8) as u8, // |
+            0x00,                             // bAssocTerminal
+            config.channels,                  // bNrChannels
+            channel_config as u8,             // wChannelConfig
+            (channel_config >> 8) as u8,      // |
+            0x00,                             // iChannelNames
+            0x00,                             // iTerminal
+        ],
+    );
+    alt.descriptor(
+        CS_INTERFACE,
+        &[
+            AC_TYPE_OUTPUT_TERMINAL,           // bDescriptorSubtype
+            OUTPUT_TERMINAL_ID,                // bTerminalID
+            output_terminal_type as u8,        // wTerminalType
+            (output_terminal_type >> 8) as u8, // |
+            0x00,                              // bAssocTerminal
+            INPUT_TERMINAL_ID,                 // bSourceID
+            0x00,                              // iTerminal
+        ],
+    );
+}
+
+/// Writes the class specific descriptors of the streaming alternate setting.
+fn stream_format<'d, D: Driver<'d>>(alt: &mut InterfaceAltBuilder<'_, 'd, D>, terminal_link: u8, config: &Config) {
+    alt.descriptor(
+        CS_INTERFACE,
+        &[
+            AS_TYPE_GENERAL,             // bDescriptorSubtype
+            terminal_link,               // bTerminalLink
+            0x01,                        // bDelay
+            FORMAT_TAG_PCM as u8,        // wFormatTag
+            (FORMAT_TAG_PCM >> 8) as u8, // |
+        ],
+    );
+    let rate = config.sample_rate.to_le_bytes();
+    alt.descriptor(
+        CS_INTERFACE,
+        &[
+            AS_TYPE_FORMAT,          // bDescriptorSubtype
+            FORMAT_TYPE_I,           // bFormatType
+            config.channels,         // bNrChannels
+            config.sample_width,     // bSubframeSize
+            config.sample_width * 8, // bBitResolution
+            0x01,                    // bSamFreqType: a single sample rate
+            rate[0],                 // tSamFreq
+            rate[1],                 // |
+            rate[2],                 // |
+        ],
+    );
+}
+
+/// Writes the class specific descriptor of an audio data endpoint.
+fn audio_endpoint<'d, D: Driver<'d>>(alt: &mut InterfaceAltBuilder<'_, 'd, D>) {
+    alt.descriptor(
+        CS_ENDPOINT,
+        &[
+            EP_TYPE_GENERAL, // bDescriptorSubtype
+            0x00,            // bmAttributes: no sampling frequency or pitch control
+            0x00,            // bLockDelayUnits
+            0x00,            // wLockDelay
+            0x00,            // |
+        ],
+    );
+}
+
+/// USB Audio Class 1.0 speaker, receiving audio from the host.
+pub struct Speaker<'d, D: Driver<'d>> {
+    read_ep: D::EndpointOut,
+    feedback_ep: D::EndpointIn,
+    config: Config,
+}
+
+impl<'d, D: Driver<'d>> Speaker<'d, D> {
+    /// Creates a new speaker, playing samples of the given format.
+    pub fn new(builder: &mut Builder<'d, D>, config: Config) -> Self {
+        assert!(matches!(config.sample_width, 2 | 3) && config.channels > 0);
+
+        let mut func = builder.function_with_iad(USB_CLASS_AUDIO, AUDIO_SUBCLASS_CONTROL, AUDIO_PROTOCOL_NONE, None);
+        audio_control(&mut func, TERMINAL_USB_STREAMING, TERMINAL_SPEAKER, &config);
+
+        // Streaming interface, with no endpoints in the default alternate setting.
+        let mut iface = func.interface();
+        let _alt = iface.alt_setting(USB_CLASS_AUDIO, AUDIO_SUBCLASS_STREAMING, AUDIO_PROTOCOL_NONE, None);
+        let mut alt = iface.alt_setting(USB_CLASS_AUDIO, AUDIO_SUBCLASS_STREAMING, AUDIO_PROTOCOL_NONE, None);
+        stream_format(&mut alt, INPUT_TERMINAL_ID, &config);
+
+        // The data endpoint refers to the feedback endpoint, which comes after it.
+        let read_ep = alt.alloc_endpoint_out(EndpointType::Isochronous, config.max_packet_size(), 1);
+        let feedback_ep = alt.alloc_endpoint_in(EndpointType::Isochronous, 3, 1);
+        alt.endpoint_descriptor(
+            read_ep.info(),
+            SynchronizationType::Asynchronous,
+            UsageType::DataEndpoint,
+            &[
+                0x00,                           // bRefresh
+                feedback_ep.info().addr.into(), // bSynchAddress
+            ],
+        );
+        audio_endpoint(&mut alt);
+        alt.endpoint_descriptor(
+            feedback_ep.info(),
+            SynchronizationType::NoSynchronization,
+            UsageType::FeedbackEndpoint,
+            &[
+                FEEDBACK_REFRESH, // bRefresh
+                0x00,             // bSynchAddress
+            ],
+        );
+
+        Speaker {
+            read_ep,
+            feedback_ep,
+            config,
+        }
+    }
+
+    /// Gets the format of the samples.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Waits for the host to start streaming audio.
+    pub async fn wait_connection(&mut self) {
+        self.read_ep.wait_enabled().await
+    }
+
+    /// Reads the samples of a frame.
+    ///
+    /// `buf` must be at least [`Config::max_packet_size`] long. Returns
+    /// [`EndpointError::Disabled`] when the host stops streaming.
+    pub async fn read_packet(&mut self, buf: &mut [u8]) -> Result<usize, EndpointError> {
+        self.read_ep.read(buf).await
+    }
+
+    /// Reports the rate at which the device consumes samples, as a number of samples per frame
+    /// in 10.14 fixed point format.
+    ///
+    /// The host reads the feedback every 32 frames, so it should be written regularly.
+    /// [`Config::nominal_feedback`] is the value matching the nominal sample rate.
+    pub async fn write_feedback(&mut self, samples_per_frame: u32) -> Result<(), EndpointError> {
+        self.feedback_ep.write(&samples_per_frame.to_le_bytes()[..3]).await
+    }
+
+    /// Split the speaker into a stream and a feedback writer.
+    ///
+    /// This allows reading samples and writing the feedback from separate tasks.
+    pub fn split(self) -> (SpeakerStream<'d, D>, Feedback<'d, D>) {
+        (
+            SpeakerStream {
+                read_ep: self.read_ep,
+                config: self.config,
+            },
+            Feedback {
+                feedback_ep: self.feedback_ep,
+            },
+        )
+    }
+}
+
+/// Samples received by a [`Speaker`].
+///
+/// You can obtain a `SpeakerStream` with [`Speaker::split`]
+pub struct SpeakerStream<'d, D: Driver<'d>> {
+    read_ep: D::EndpointOut,
+    config: Config,
+}
+
+impl<'d, D: Driver<'d>> SpeakerStream<'d, D> {
+    /// Gets the format of the samples.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Waits for the host to start streaming audio.
+    pub async fn wait_connection(&mut self) {
+        self.read_ep.wait_enabled().await
+    }
+
+    /// Reads the samples of a frame.
+    ///
+    /// See [`Speaker::read_packet`].
+    pub async fn read_packet(&mut self, buf: &mut [u8]) -> Result<usize, EndpointError> {
+        self.read_ep.read(buf).await
+    }
+}
+
+/// Feedback of a [`Speaker`].
+///
+/// You can obtain a `Feedback` with [`Speaker::split`]
+pub struct Feedback<'d, D: Driver<'d>> {
+    feedback_ep: D::EndpointIn,
+}
+
+impl<'d, D: Driver<'d>> Feedback<'d, D> {
+    /// Reports the rate at which the device consumes samples.
+    ///
+    /// See [`Speaker::write_feedback`].
+    pub async fn write_feedback(&mut self, samples_per_frame: u32) -> Result<(), EndpointError> {
+        self.feedback_ep.write(&samples_per_frame.to_le_bytes()[..3]).await
+    }
+}
+
+/// USB Audio Class 1.0 microphone, sending audio to the host.
+pub struct Microphone<'d, D: Driver<'d>> {
+    write_ep: D::EndpointIn,
+    config: Config,
+}
+
+impl<'d, D: Driver<'d>> Microphone<'d, D> {
+    /// Creates a new microphone, capturing samples of the given format.
+    pub fn new(builder: &mut Builder<'d, D>, config: Config) -> Self {
+        assert!(matches!(config.sample_width, 2 | 3) && config.channels > 0);
+
+        let mut func = builder.function_with_iad(USB_CLASS_AUDIO, AUDIO_SUBCLASS_CONTROL, AUDIO_PROTOCOL_NONE, None);
+        audio_control(&mut func, TERMINAL_MICROPHONE, TERMINAL_USB_STREAMING, &config);
+
+        // Streaming interface, with no endpoints in the default alternate setting.
+        let mut iface = func.interface();
+        let _alt = iface.alt_setting(USB_CLASS_AUDIO, AUDIO_SUBCLASS_STREAMING, AUDIO_PROTOCOL_NONE, None);
+        let mut alt = iface.alt_setting(USB_CLASS_AUDIO, AUDIO_SUBCLASS_STREAMING, AUDIO_PROTOCOL_NONE, None);
+        stream_format(&mut alt, OUTPUT_TERMINAL_ID, &config);
+
+        let write_ep = alt.alloc_endpoint_in(EndpointType::Isochronous, config.max_packet_size(), 1);
+        alt.endpoint_descriptor(
+            write_ep.info(),
+            SynchronizationType::Asynchronous,
+            UsageType::DataEndpoint,
+            &[
+                0x00, // bRefresh
+                0x00, // bSynchAddress
+            ],
+        );
+        audio_endpoint(&mut alt);
+
+        Microphone { write_ep, config }
+    }
+
+    /// Gets the format of the samples.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Waits for the host to start streaming audio.
+    pub async fn wait_connection(&mut self) {
+        self.write_ep.wait_enabled().await
+    }
+
+    /// Writes the samples of a frame.
+    ///
+    /// `data` must hold whole samples for all channels, and be at most [`Config::max_packet_size`]
+    /// long. Returns [`EndpointError::Disabled`] when the host stops streaming.
+    pub async fn write_packet(&mut self, data: &[u8]) -> Result<(), EndpointError> {
+        self.write_ep.write(data).await
+    }
+}
@@ -0,0 +1,166 @@
+//! WebUSB API capability implementation.
+//!
+//! The WebUSB platform capability tells browsers that the device can be accessed from web pages,
+//! and optionally gives the URL of a landing page, which browsers show when the device is
+//! plugged in. The device interfaces accessed from the browser are usually vendor-specific
+//! interfaces, added separately.
+//!
+//! See <https://wicg.github.io/webusb>
+
+use core::mem::MaybeUninit;
+
+use crate::control::{InResponse, Recipient, Request, RequestType};
+use crate::descriptor::capability_type;
+use crate::driver::Driver;
+use crate::{Builder, Handler};
+
+/// Request to get a URL descriptor.
+const WEBUSB_REQUEST_GET_URL: u16 = 2;
+const DESC_WEBUSB_URL: u8 = 3;
+
+/// Index of the landing page URL.
+const LANDING_PAGE_INDEX: u8 = 1;
+
+/// A URL, as sent in URL descriptors.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Url<'d> {
+    scheme: u8,
+    url: &'d str,
+}
+
+impl<'d> Url<'d> {
+    /// Create a new URL.
+    ///
+    /// `url` may start with `https://` or `http://`, the scheme is then sent separately from the
+    /// rest of the URL, as required by the URL descriptor. Otherwise, it is sent as is.
+    pub fn new(url: &'d str) -> Self {
+        let (scheme, url) = if let Some(url) = url.strip_prefix("https://") {
+            (1, url)
+        } else if let Some(url) = url.strip_prefix("http://") {
+            (0, url)
+        } else {
+            (255, url)
+        };
+        assert!(url.len() <= 252, "URL too long");
+
+        Self { scheme, url }
+    }
+}
+
+/// Configuration for WebUSB.
+pub struct Config<'d> {
+    /// Vendor code of the vendor requests used by the host to get the WebUSB descriptors.
+    ///
+    /// It must be different from the vendor codes of the other vendor requests of the device,
+    /// like the one of the Microsoft OS 2.0 descriptors.
+    pub vendor_code: u8,
+
+    /// URL of the landing page, shown by browsers when the device is plugged in.
+    pub landing_url: Option<Url<'d>>,
+}
+
+/// Internal state for WebUSB.
+pub struct State<'d> {
+    control: MaybeUninit<Control<'d>>,
+}
+
+impl<'d> State<'d> {
+    /// Create a new `State`.
+    pub fn new() -> Self {
+        State {
+            control: MaybeUninit::uninit(),
+        }
+    }
+}
+
+impl<'d> Default for State<'d> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// WebUSB capability of the device.
+pub struct WebUsb;
+
+impl WebUsb {
+    /// Adds the WebUSB platform capability to the device, and handles the requests for its
+    /// landing page.
+    pub fn configure<'d, D: Driver<'d>>(
+        builder: &mut Builder<'d, D>,
+        state: &'d mut State<'d>,
+        config: &'d Config<'d>,
+    ) {
+        let landing_page = if config.landing_url.is_some() {
+            LANDING_PAGE_INDEX
+        } else {
+            0
+        };
+
+        builder.bos_capability(
+            capability_type::PLATFORM,
+            &[
+                0, // reserved
+                // platform capability UUID, WebUSB platform capability
+                0x38,
+                0xb6,
+                0x08,
+                0x34,
+                0xa9,
+                0x09,
+                0xa0,
+                0x47,
+                0x8b,
+                0xfd,
+                0xa0,
+                0x76,
+                0x88,
+                0x15,
+                0xb6,
+                0x65,
+                0x00, // bcdVersion (1.00)
+                0x01, // |
+                config.vendor_code,
+                landing_page, // iLandingPage
+            ],
+        );
+
+        let control = state.control.write(Control { config });
+        builder.handler(control);
+    }
+}
+
+struct Control<'d> {
+    config: &'d Config<'d>,
+}
+
+impl<'d> Handler for Control<'d> {
+    fn control_in<'a>(&'a mut self, req: Request, buf: &'a mut [u8]) -> Option<InResponse<'a>> {
+        if (req.request_type, req.recipient, req.request)
+            != (RequestType::Vendor, Recipient::Device, self.config.vendor_code)
+        {
+            return None;
+        }
+
+        if req.index != WEBUSB_REQUEST_GET_URL {
+            return Some(InResponse::Rejected);
+        }
+
+        match self.config.landing_url {
+            Some(url) if req.value == LANDING_PAGE_INDEX as u16 => {
+                let len = 3 + url.url.len();
+                if buf.len() < len {
+                    warn!("control buffer too small for the WebUSB landing page URL");
+                    return Some(InResponse::Rejected);
+                }
+
+                buf[0] = len as u8;
+                buf[1] = DESC_WEBUSB_URL;
+                buf[2] = url.scheme;
+                buf[3..len].copy_from_slice(url.url.as_bytes());
+                Some(InResponse::Accepted(&buf[..len]))
+            }
+            _ => Some(InResponse::Rejected),
+        }
+    }
+}
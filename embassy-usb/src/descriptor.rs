@@ -36,6 +36,33 @@ pub mod capability_type {
     pub const PLATFORM: u8 = 5;
 }
 
+/// Synchronization type of an isochronous endpoint.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SynchronizationType {
+    /// No synchronization
+    NoSynchronization = 0b00,
+    /// The endpoint is clocked independently from the USB frames, and the host is told the data
+    /// rate by an explicit or implicit feedback endpoint.
+    Asynchronous = 0b01,
+    /// The endpoint adapts to the data rate of the host.
+    Adaptive = 0b10,
+    /// The endpoint is clocked by the start of frame packets.
+    Synchronous = 0b11,
+}
+
+/// Usage type of an isochronous endpoint.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UsageType {
+    /// Data endpoint
+    DataEndpoint = 0b00,
+    /// Feedback endpoint, reporting the data rate of an asynchronous endpoint.
+    FeedbackEndpoint = 0b01,
+    /// Data endpoint, whose data rate also serves as feedback for another endpoint.
+    ImplicitFeedbackDataEndpoint = 0b10,
+}
+
 /// A writer for USB descriptors.
 pub(crate) struct DescriptorWriter<'a> {
     pub buf: &'a mut [u8],
@@ -155,7 +182,10 @@ impl<'a> DescriptorWriter<'a> {
         function_class: u8,
         function_sub_class: u8,
         function_protocol: u8,
+        function_string: Option<StringIndex>,
     ) {
+        let str_index = function_string.map_or(0, Into::into);
+
         self.write(
             descriptor_type::IAD,
             &[
@@ -164,7 +194,7 @@ impl<'a> DescriptorWriter<'a> {
                 function_class,
                 function_sub_class,
                 function_protocol,
-                0,
+                str_index, // iFunction
             ],
         );
     }
@@ -226,21 +256,43 @@ impl<'a> DescriptorWriter<'a> {
     /// * `endpoint` - Endpoint previously allocated with
     ///   [`UsbDeviceBuilder`](crate::bus::UsbDeviceBuilder).
     pub fn endpoint(&mut self, endpoint: &EndpointInfo) {
+        self.endpoint_ext(
+            endpoint,
+            SynchronizationType::NoSynchronization,
+            UsageType::DataEndpoint,
+            &[],
+        )
+    }
+
+    /// Writes an endpoint descriptor, with the synchronization and usage types of an isochronous
+    /// endpoint, and class specific fields appended to the standard ones.
+    pub fn endpoint_ext(
+        &mut self,
+        endpoint: &EndpointInfo,
+        synchronization_type: SynchronizationType,
+        usage_type: UsageType,
+        extra_fields: &[u8],
+    ) {
         match self.num_endpoints_mark {
             Some(mark) => self.buf[mark] += 1,
             None => panic!("you can only call `endpoint` after `interface/interface_alt`."),
         };
 
-        self.write(
-            descriptor_type::ENDPOINT,
-            &[
-                endpoint.addr.into(),   // bEndpointAddress
-                endpoint.ep_type as u8, // bmAttributes
-                endpoint.max_packet_size as u8,
-                (endpoint.max_packet_size >> 8) as u8, // wMaxPacketSize
-                endpoint.interval_ms,                  // bInterval
-            ],
-        );
+        let mut descriptor = [0; 16];
+        let len = 5 + extra_fields.len();
+        if len > descriptor.len() {
+            panic!("Too many extra endpoint descriptor fields");
+        }
+        descriptor[..5].copy_from_slice(&[
+            endpoint.addr.into(), // bEndpointAddress
+            endpoint.ep_type as u8 | (synchronization_type as u8) << 2 | (usage_type as u8) << 4, // bmAttributes
+            endpoint.max_packet_size as u8,
+            (endpoint.max_packet_size >> 8) as u8, // wMaxPacketSize
+            endpoint.interval_ms,                  // bInterval
+        ]);
+        descriptor[5..len].copy_from_slice(extra_fields);
+
+        self.write(descriptor_type::ENDPOINT, &descriptor[..len]);
     }
 
     /// Writes a string descriptor.
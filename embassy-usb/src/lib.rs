@@ -1,4 +1,6 @@
 #![no_std]
+#![feature(async_fn_in_trait)]
+#![allow(incomplete_features)]
 #![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
 
@@ -23,7 +25,7 @@ mod config {
 use embassy_futures::select::{select, Either};
 use heapless::Vec;
 
-pub use crate::builder::{Builder, Config};
+pub use crate::builder::{serial_number_from_uid, Builder, Config};
 use crate::config::*;
 use crate::control::*;
 use crate::descriptor::*;
@@ -212,6 +214,7 @@ struct Inner<'d, D: Driver<'d>> {
 
     interfaces: Vec<Interface, MAX_INTERFACE_COUNT>,
     handlers: Vec<&'d mut dyn Handler, MAX_HANDLER_COUNT>,
+    strings: Vec<(StringIndex, &'d str), MAX_STRING_COUNT>,
 
     #[cfg(feature = "msos-descriptor")]
     msos_descriptor: crate::msos::MsOsDescriptorSet<'d>,
@@ -222,6 +225,7 @@ impl<'d, D: Driver<'d>> UsbDevice<'d, D> {
         driver: D,
         config: Config<'d>,
         handlers: Vec<&'d mut dyn Handler, MAX_HANDLER_COUNT>,
+        strings: Vec<(StringIndex, &'d str), MAX_STRING_COUNT>,
         device_descriptor: &'d [u8],
         config_descriptor: &'d [u8],
         bos_descriptor: &'d [u8],
@@ -251,6 +255,7 @@ impl<'d, D: Driver<'d>> UsbDevice<'d, D> {
                 set_address_pending: false,
                 interfaces,
                 handlers,
+                strings,
                 #[cfg(feature = "msos-descriptor")]
                 msos_descriptor,
             },
@@ -742,13 +747,19 @@ impl<'d, D: Driver<'d>> Inner<'d, D> {
                         STRING_INDEX_PRODUCT => self.config.product,
                         STRING_INDEX_SERIAL_NUMBER => self.config.serial_number,
                         _ => {
-                            let mut s = None;
-                            for handler in &mut self.handlers {
-                                let index = StringIndex::new(index);
-                                let lang_id = req.index;
-                                if let Some(res) = handler.get_string(index, lang_id) {
-                                    s = Some(res);
-                                    break;
+                            let mut s = self
+                                .strings
+                                .iter()
+                                .find(|(i, _)| u8::from(*i) == index)
+                                .map(|(_, s)| *s);
+                            if s.is_none() {
+                                for handler in &mut self.handlers {
+                                    let index = StringIndex::new(index);
+                                    let lang_id = req.index;
+                                    if let Some(res) = handler.get_string(index, lang_id) {
+                                        s = Some(res);
+                                        break;
+                                    }
                                 }
                             }
                             s
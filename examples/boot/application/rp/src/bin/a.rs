@@ -26,7 +26,7 @@ async fn main(_s: Spawner) {
     let mut watchdog = Watchdog::new(p.WATCHDOG);
     watchdog.start(Duration::from_secs(8));
 
-    let mut flash: Flash<_, FLASH_SIZE> = Flash::new_blocking(p.FLASH);
+    let mut flash: Flash<_, FLASH_SIZE> = Flash::new(p.FLASH);
 
     let mut updater = FirmwareUpdater::default();
 
@@ -36,22 +36,23 @@ async fn main(_s: Spawner) {
     let mut offset = 0;
     let mut buf: AlignedBuffer<4096> = AlignedBuffer([0; 4096]);
     defmt::info!("preparing update");
-    let mut writer = updater
-        .prepare_update_blocking(&mut flash)
+    let dfu = updater
+        .prepare_update(&mut flash)
+        .await
         .map_err(|e| defmt::warn!("E: {:?}", defmt::Debug2Format(&e)))
         .unwrap();
-    defmt::info!("writer created, starting write");
+    defmt::info!("dfu partition erased, starting write");
     for chunk in APP_B.chunks(4096) {
         buf.0[..chunk.len()].copy_from_slice(chunk);
         defmt::info!("writing block at offset {}", offset);
-        writer
-            .write_block_blocking(offset, &buf.0[..], &mut flash, 256)
+        dfu.write(&mut flash, offset as u32, &buf.0[..chunk.len()])
+            .await
             .unwrap();
         offset += chunk.len();
+        watchdog.feed();
     }
-    watchdog.feed();
     defmt::info!("firmware written, marking update");
-    updater.mark_updated_blocking(&mut flash, &mut buf.0[..1]).unwrap();
+    updater.mark_updated(&mut flash, &mut buf.0[..1]).await.unwrap();
     Timer::after(Duration::from_secs(2)).await;
     led.set_low();
     defmt::info!("update marked, resetting");
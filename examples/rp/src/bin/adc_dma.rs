@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_rp::adc::{Adc, Channels, Config, InterruptHandler};
+use embassy_rp::bind_interrupts;
+use embassy_time::{Duration, Timer};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    ADC_IRQ_FIFO => InterruptHandler;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+    let mut adc = Adc::new(p.ADC, Irqs, Config::default());
+    let mut dma = p.DMA_CH0;
+
+    let mut p26 = p.PIN_26;
+    let mut p27 = p.PIN_27;
+
+    loop {
+        // Sample both pins in turn at the full 500 ksps, 250 ksps each
+        let channels = Channels::new().pin(&mut p26).pin(&mut p27);
+        let mut buf = [0u16; 1024];
+        match adc.read_many(channels, &mut buf, 0, &mut dma).await {
+            Ok(()) => {
+                let avg = |ch: usize| buf.iter().skip(ch).step_by(2).map(|&s| s as u32).sum::<u32>() / 512;
+                info!("Pin 26 ADC: {}, Pin 27 ADC: {}", avg(0), avg(1));
+            }
+            Err(e) => warn!("ADC error: {:?}", e),
+        }
+        Timer::after(Duration::from_secs(1)).await;
+    }
+}
@@ -0,0 +1,73 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_rp::dma::{self, Channel, Ring, TransferOptions};
+use embassy_rp::pac::dma::vals::TreqSel;
+use {defmt_rtt as _, panic_probe as _};
+
+/// A ring buffer must be aligned to its size
+#[repr(C, align(16))]
+struct Pattern([u32; 4]);
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+    let mut ch0 = p.DMA_CH0;
+    let mut ch1 = p.DMA_CH1;
+
+    // Copy two blocks back to back: the first channel triggers the second when it completes
+    let first = [1u32, 2, 3, 4];
+    let second = [5u32, 6, 7, 8];
+    let mut dst = [0u32; 8];
+    let (dst_first, dst_second) = dst.split_at_mut(4);
+    let ch1_number = ch1.number();
+    unsafe {
+        let chained = dma::transfer(
+            &mut ch1,
+            second.as_ptr(),
+            dst_second.as_mut_ptr(),
+            second.len(),
+            TreqSel::PERMANENT.0,
+            TransferOptions {
+                start: false,
+                ..Default::default()
+            },
+        );
+        dma::transfer(
+            &mut ch0,
+            first.as_ptr(),
+            dst_first.as_mut_ptr(),
+            first.len(),
+            TreqSel::PERMANENT.0,
+            TransferOptions {
+                chain_to: Some(ch1_number),
+                ..Default::default()
+            },
+        )
+        .await;
+        chained.await;
+    }
+    info!("chained copy: {}", dst);
+
+    // Repeat a pattern over a larger buffer by wrapping the read address
+    let pattern = Pattern([0xa, 0xb, 0xc, 0xd]);
+    let mut filled = [0u32; 16];
+    unsafe {
+        dma::transfer(
+            &mut ch0,
+            pattern.0.as_ptr(),
+            filled.as_mut_ptr(),
+            filled.len(),
+            TreqSel::PERMANENT.0,
+            TransferOptions {
+                ring: Some(Ring::Read(4)),
+                ..Default::default()
+            },
+        )
+        .await;
+    }
+    info!("pattern fill: {}", filled);
+}
@@ -0,0 +1,63 @@
+//! This example makes the Pico an I2C device at address 0x55, exposing a 16 byte register file.
+//!
+//! The master writes the register address followed by the values to store, or writes the register
+//! address then reads the values from there.
+
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_rp::bind_interrupts;
+use embassy_rp::i2c::InterruptHandler;
+use embassy_rp::i2c_slave::{self, Command, I2cSlave, ReadStatus};
+use embassy_rp::peripherals::I2C1;
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    I2C1_IRQ => InterruptHandler<I2C1>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+
+    let mut config = i2c_slave::Config::default();
+    config.addr = 0x55;
+    let mut dev = I2cSlave::new(p.I2C1, p.PIN_15, p.PIN_14, Irqs, config);
+
+    let mut regs = [0u8; 16];
+    let mut reg = 0usize;
+    let mut buf = [0u8; 17];
+
+    loop {
+        match dev.listen(&mut buf).await {
+            Ok(Command::Write(len)) => {
+                reg = buf[0] as usize % regs.len();
+                for &b in &buf[1..len] {
+                    regs[reg] = b;
+                    reg = (reg + 1) % regs.len();
+                }
+                info!("Write of {} bytes", len);
+            }
+            Ok(Command::WriteRead(len)) => {
+                reg = buf[len - 1] as usize % regs.len();
+                respond(&mut dev, &regs[reg..]).await;
+            }
+            Ok(Command::Read) => respond(&mut dev, &regs[reg..]).await,
+            Ok(Command::GeneralCall(len)) => info!("General call of {} bytes", len),
+            Err(e) => warn!("I2C error: {:?}", e),
+        }
+    }
+}
+
+async fn respond(dev: &mut I2cSlave<'_, I2C1>, data: &[u8]) {
+    match dev.respond_to_read(data).await {
+        Ok(ReadStatus::Done) => info!("Read of {} bytes", data.len()),
+        Ok(ReadStatus::LeftoverBytes(n)) => info!("Read of {} bytes", data.len() - n as usize),
+        // Pad with zeros until the master stops reading
+        Ok(ReadStatus::NeedMoreBytes) => while let Ok(ReadStatus::NeedMoreBytes) = dev.respond_to_read(&[0]).await {},
+        Err(e) => warn!("I2C error: {:?}", e),
+    }
+}
@@ -0,0 +1,83 @@
+//! This example runs an interrupt mode executor on core1, next to a thread mode executor on each core.
+//!
+//! The task of the interrupt mode executor is woken from core0 through a channel.
+
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use cortex_m::peripheral::NVIC;
+use defmt::*;
+use embassy_executor::Executor;
+use embassy_executor::_export::StaticCell;
+use embassy_rp::gpio::{Level, Output};
+use embassy_rp::interrupt;
+use embassy_rp::multicore::{spawn_core1, InterruptExecutor, Stack};
+use embassy_rp::pac::Interrupt;
+use embassy_rp::peripherals::PIN_25;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_time::{Duration, Timer};
+use {defmt_rtt as _, panic_probe as _};
+
+static mut CORE1_STACK: Stack<4096> = Stack::new();
+static EXECUTOR0: StaticCell<Executor> = StaticCell::new();
+static EXECUTOR1: StaticCell<Executor> = StaticCell::new();
+static EXECUTOR1_HIGH: InterruptExecutor = InterruptExecutor::new();
+static CHANNEL: Channel<CriticalSectionRawMutex, bool, 1> = Channel::new();
+
+#[interrupt]
+unsafe fn SWI_IRQ_0() {
+    EXECUTOR1_HIGH.on_interrupt()
+}
+
+#[cortex_m_rt::entry]
+fn main() -> ! {
+    let p = embassy_rp::init(Default::default());
+    let led = Output::new(p.PIN_25, Level::Low);
+
+    spawn_core1(p.CORE1, unsafe { &mut CORE1_STACK }, move || {
+        // The interrupt is enabled and pended on the NVIC of core1
+        let mut nvic: NVIC = unsafe { core::mem::transmute(()) };
+        unsafe { nvic.set_priority(Interrupt::SWI_IRQ_0, 2 << 6) };
+        let spawner = EXECUTOR1_HIGH.start(Interrupt::SWI_IRQ_0);
+        unwrap!(spawner.spawn(core1_led_task(led)));
+
+        let executor1 = EXECUTOR1.init(Executor::new());
+        executor1.run(|spawner| unwrap!(spawner.spawn(core1_task())));
+    });
+
+    let executor0 = EXECUTOR0.init(Executor::new());
+    executor0.run(|spawner| unwrap!(spawner.spawn(core0_task())));
+}
+
+#[embassy_executor::task]
+async fn core0_task() {
+    info!("Hello from core 0");
+    loop {
+        CHANNEL.send(true).await;
+        Timer::after(Duration::from_millis(100)).await;
+        CHANNEL.send(false).await;
+        Timer::after(Duration::from_millis(400)).await;
+    }
+}
+
+#[embassy_executor::task]
+async fn core1_task() {
+    info!("Hello from core 1");
+    loop {
+        Timer::after(Duration::from_secs(1)).await;
+        info!("core 1 tick");
+    }
+}
+
+#[embassy_executor::task]
+async fn core1_led_task(mut led: Output<'static, PIN_25>) {
+    info!("Hello from the interrupt executor of core 1");
+    loop {
+        match CHANNEL.recv().await {
+            true => led.set_high(),
+            false => led.set_low(),
+        }
+    }
+}
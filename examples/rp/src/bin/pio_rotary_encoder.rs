@@ -0,0 +1,64 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::info;
+use embassy_executor::Spawner;
+use embassy_rp::gpio::Pull;
+use embassy_rp::peripherals::PIO0;
+use embassy_rp::pio::{Common, Config, Direction, FifoJoin, Pio, PioPin, ShiftDirection, StateMachine};
+use embassy_rp::relocate::RelocatedProgram;
+use fixed::traits::ToFixed;
+use {defmt_rtt as _, panic_probe as _};
+
+fn setup_encoder<'a>(
+    pio: &mut Common<'a, PIO0>,
+    sm: &mut StateMachine<'a, PIO0, 0>,
+    pin_a: impl PioPin,
+    pin_b: impl PioPin,
+) {
+    // Wait for a falling edge of B, then sample both pins: A tells the direction of the step
+    let prg = pio_proc::pio_asm!("wait 1 pin 1", "wait 0 pin 1", "in pins, 2", "push",);
+
+    let mut pin_a = pio.make_pio_pin(pin_a);
+    let mut pin_b = pio.make_pio_pin(pin_b);
+    pin_a.set_pull(Pull::Up);
+    pin_b.set_pull(Pull::Up);
+    sm.set_pin_dirs(Direction::In, &[&pin_a, &pin_b]);
+
+    let relocated = RelocatedProgram::new(&prg.program);
+    let mut cfg = Config::default();
+    cfg.use_program(&pio.load_program(&relocated), &[]);
+    cfg.set_in_pins(&[&pin_a, &pin_b]);
+    cfg.fifo_join = FifoJoin::RxOnly;
+    cfg.shift_in.direction = ShiftDirection::Left;
+    // Slow enough to debounce the contacts of a mechanical encoder
+    cfg.clock_divider = 10_000.to_fixed();
+    sm.set_config(&cfg);
+}
+
+#[embassy_executor::task]
+async fn encoder_task(mut sm: StateMachine<'static, PIO0, 0>) {
+    sm.set_enable(true);
+
+    let mut count = 0i32;
+    loop {
+        // The state machine stalls on its FIFO until the next step, the task sleeps until then
+        match sm.rx().wait_pull().await & 0b11 {
+            0 => count -= 1,
+            _ => count += 1,
+        }
+        info!("Count: {}", count);
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+    let Pio {
+        mut common, mut sm0, ..
+    } = Pio::new(p.PIO0);
+
+    setup_encoder(&mut common, &mut sm0, p.PIN_4, p.PIN_5);
+    spawner.spawn(encoder_task(sm0)).unwrap();
+}
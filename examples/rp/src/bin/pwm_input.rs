@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_rp::pwm::{Config, InputMode, Pwm};
+use embassy_time::Duration;
+use {defmt_rtt as _, panic_probe as _};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+
+    // Count the rising edges on pin 3, e.g. the output of a fan tachometer
+    let cfg: Config = Default::default();
+    let mut pwm = Pwm::new_input(p.PWM_CH1, p.PIN_3, InputMode::RisingEdge, cfg);
+
+    loop {
+        let freq = pwm.measure_frequency(Duration::from_secs(1)).await;
+        info!("Input frequency: {} Hz", freq);
+    }
+}
@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_rp::bind_interrupts;
+use embassy_rp::rtc::{DateTime, DateTimeFilter, DayOfWeek, InterruptHandler, RealTimeClock};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    RTC_IRQ => InterruptHandler;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+    info!("Hello World!");
+
+    let now = DateTime {
+        year: 2023,
+        month: 5,
+        day: 15,
+        day_of_week: DayOfWeek::Monday,
+        hour: 10,
+        minute: 30,
+        second: 50,
+    };
+    let mut rtc = RealTimeClock::new(p.RTC, Irqs, now).unwrap();
+
+    loop {
+        // Wake up at the start of every minute
+        rtc.wait_for_alarm(DateTimeFilter::default().second(0)).await;
+
+        if let Ok(dt) = rtc.now() {
+            info!(
+                "Now: {}-{:02}-{:02} {}:{:02}:{:02}",
+                dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second,
+            );
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! This example makes the Pico an SPI slave on SPI0, answering each 4 byte transfer of the master
+//! with the bytes it received in the previous one.
+
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_rp::spi_slave::{Config, SpiSlave};
+use {defmt_rtt as _, panic_probe as _};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+
+    let (miso, cs, clk, mosi) = (p.PIN_16, p.PIN_17, p.PIN_18, p.PIN_19);
+    let mut spi = SpiSlave::new(p.SPI0, clk, mosi, miso, cs, p.DMA_CH0, p.DMA_CH1, Config::default());
+
+    let mut tx_buf = [0u8; 4];
+    let mut rx_buf = [0u8; 4];
+    loop {
+        unwrap!(spi.transfer(&mut rx_buf, &tx_buf).await);
+        info!("Received {:x}", rx_buf);
+        tx_buf = rx_buf;
+    }
+}
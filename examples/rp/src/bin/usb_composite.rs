@@ -0,0 +1,163 @@
+//! This example combines a USB serial echo and a RAM disk in a composite device.
+//!
+//! The serial number of the device is derived from the unique ID of the flash chip, so that
+//! several boards plugged to the same host are told apart.
+
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+#![feature(async_fn_in_trait)]
+#![allow(incomplete_features)]
+
+use defmt::{info, panic, unwrap};
+use embassy_executor::Spawner;
+use embassy_futures::join::join3;
+use embassy_rp::bind_interrupts;
+use embassy_rp::flash::Flash;
+use embassy_rp::peripherals::USB;
+use embassy_rp::usb::{Driver, Instance, InterruptHandler};
+use embassy_usb::class::cdc_acm::{self, CdcAcmClass};
+use embassy_usb::class::msc::{self, BlockDevice, MscClass};
+use embassy_usb::driver::EndpointError;
+use embassy_usb::{serial_number_from_uid, Builder, Config};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    USBCTRL_IRQ => InterruptHandler<USB>;
+});
+
+const FLASH_SIZE: usize = 2 * 1024 * 1024;
+
+const BLOCK_SIZE: usize = 512;
+const BLOCK_COUNT: usize = 64;
+
+struct RamDisk {
+    data: &'static mut [u8; BLOCK_SIZE * BLOCK_COUNT],
+}
+
+impl BlockDevice for RamDisk {
+    type Error = ();
+
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn block_count(&self) -> u32 {
+        BLOCK_COUNT as u32
+    }
+
+    async fn read(&mut self, lba: u32, buf: &mut [u8]) -> Result<(), ()> {
+        let start = lba as usize * BLOCK_SIZE;
+        buf.copy_from_slice(&self.data[start..start + buf.len()]);
+        Ok(())
+    }
+
+    async fn write(&mut self, lba: u32, buf: &[u8]) -> Result<(), ()> {
+        let start = lba as usize * BLOCK_SIZE;
+        self.data[start..start + buf.len()].copy_from_slice(buf);
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+static mut DISK_DATA: [u8; BLOCK_SIZE * BLOCK_COUNT] = [0; BLOCK_SIZE * BLOCK_COUNT];
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+
+    // Read the unique ID of the flash chip, used as serial number.
+    let mut flash = Flash::<_, FLASH_SIZE>::new(p.FLASH);
+    let mut uid = [0; 8];
+    unwrap!(flash.unique_id(&mut uid));
+    let mut serial_number = [0; 16];
+    let serial_number = serial_number_from_uid(&uid, &mut serial_number);
+    info!("Serial number: {}", serial_number);
+
+    // Create the driver, from the HAL.
+    let driver = Driver::new(p.USB, Irqs);
+
+    // Create embassy-usb Config
+    //
+    // The CDC-ACM function adds an interface association descriptor, so the device class is set
+    // for composite devices when building.
+    let mut config = Config::new(0xc0de, 0xcafe);
+    config.manufacturer = Some("Embassy");
+    config.product = Some("USB composite example");
+    config.serial_number = Some(serial_number);
+    config.max_power = 100;
+    config.max_packet_size_0 = 64;
+
+    // Create embassy-usb DeviceBuilder using the driver and config.
+    // It needs some buffers for building the descriptors.
+    let mut device_descriptor = [0; 256];
+    let mut config_descriptor = [0; 256];
+    let mut bos_descriptor = [0; 256];
+    let mut control_buf = [0; 64];
+
+    let mut acm_state = cdc_acm::State::new();
+    let mut msc_state = msc::State::new();
+    let mut block_buf = [0; BLOCK_SIZE * 2];
+
+    let mut builder = Builder::new(
+        driver,
+        config,
+        &mut device_descriptor,
+        &mut config_descriptor,
+        &mut bos_descriptor,
+        &mut control_buf,
+    );
+
+    // Create classes on the builder.
+    let mut serial = CdcAcmClass::new(&mut builder, &mut acm_state, 64);
+    let disk = RamDisk {
+        data: unsafe { &mut DISK_DATA },
+    };
+    let mut storage = MscClass::new(
+        &mut builder,
+        &mut msc_state,
+        disk,
+        &mut block_buf,
+        msc::Config {
+            product: "RAM disk",
+            ..Default::default()
+        },
+    );
+
+    // Build the builder.
+    let mut usb = builder.build();
+
+    let echo_fut = async {
+        loop {
+            serial.wait_connection().await;
+            info!("Connected");
+            let _ = echo(&mut serial).await;
+            info!("Disconnected");
+        }
+    };
+
+    join3(usb.run(), echo_fut, storage.run()).await;
+}
+
+struct Disconnected {}
+
+impl From<EndpointError> for Disconnected {
+    fn from(val: EndpointError) -> Self {
+        match val {
+            EndpointError::BufferOverflow => panic!("Buffer overflow"),
+            EndpointError::Disabled => Disconnected {},
+        }
+    }
+}
+
+async fn echo<'d, T: Instance + 'd>(class: &mut CdcAcmClass<'d, Driver<'d, T>>) -> Result<(), Disconnected> {
+    let mut buf = [0; 64];
+    loop {
+        let n = class.read_packet(&mut buf).await?;
+        let data = &buf[..n];
+        class.write_packet(data).await?;
+    }
+}
@@ -0,0 +1,90 @@
+//! This example shows up as a USB microphone, recording a 1 kHz sawtooth tone.
+
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::info;
+use embassy_executor::Spawner;
+use embassy_futures::join::join;
+use embassy_rp::bind_interrupts;
+use embassy_rp::peripherals::USB;
+use embassy_rp::usb::{Driver, InterruptHandler};
+use embassy_usb::class::uac1::{self, Microphone};
+use embassy_usb::{Builder, Config};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    USBCTRL_IRQ => InterruptHandler<USB>;
+});
+
+const SAMPLE_RATE: u32 = 48_000;
+const SAMPLES_PER_FRAME: usize = SAMPLE_RATE as usize / 1000;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+
+    // Create the driver, from the HAL.
+    let driver = Driver::new(p.USB, Irqs);
+
+    // Create embassy-usb Config
+    let mut config = Config::new(0xc0de, 0xcafe);
+    config.manufacturer = Some("Embassy");
+    config.product = Some("USB microphone example");
+    config.serial_number = Some("12345678");
+    config.max_power = 100;
+    config.max_packet_size_0 = 64;
+
+    // Create embassy-usb DeviceBuilder using the driver and config.
+    // It needs some buffers for building the descriptors.
+    let mut device_descriptor = [0; 256];
+    let mut config_descriptor = [0; 256];
+    let mut bos_descriptor = [0; 256];
+    let mut control_buf = [0; 64];
+
+    let mut builder = Builder::new(
+        driver,
+        config,
+        &mut device_descriptor,
+        &mut config_descriptor,
+        &mut bos_descriptor,
+        &mut control_buf,
+    );
+
+    // Create classes on the builder.
+    let mut mic = Microphone::new(
+        &mut builder,
+        uac1::Config {
+            sample_rate: SAMPLE_RATE,
+            channels: 1,
+            sample_width: 2,
+        },
+    );
+
+    // Build the builder.
+    let mut usb = builder.build();
+
+    // Run the USB device.
+    let usb_fut = usb.run();
+
+    // One period of the tone per frame
+    let mut packet = [0; SAMPLES_PER_FRAME * 2];
+    for (i, sample) in packet.chunks_mut(2).enumerate() {
+        let value = (i as i32 * u16::MAX as i32 / SAMPLES_PER_FRAME as i32 + i16::MIN as i32) as i16;
+        sample.copy_from_slice(&value.to_le_bytes());
+    }
+
+    // Send one millisecond of samples every frame.
+    let mic_fut = async {
+        loop {
+            mic.wait_connection().await;
+            info!("Recording started");
+
+            while mic.write_packet(&packet).await.is_ok() {}
+            info!("Recording stopped");
+        }
+    };
+
+    join(usb_fut, mic_fut).await;
+}
@@ -0,0 +1,116 @@
+//! This example exposes a RAM disk to the host as a USB drive.
+//!
+//! The disk is not formatted: the host will offer to format it when it is plugged in.
+
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+#![feature(async_fn_in_trait)]
+#![allow(incomplete_features)]
+
+use defmt::info;
+use embassy_executor::Spawner;
+use embassy_futures::join::join;
+use embassy_rp::bind_interrupts;
+use embassy_rp::peripherals::USB;
+use embassy_rp::usb::{Driver, InterruptHandler};
+use embassy_usb::class::msc::{self, BlockDevice, MscClass, State};
+use embassy_usb::{Builder, Config};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    USBCTRL_IRQ => InterruptHandler<USB>;
+});
+
+const BLOCK_SIZE: usize = 512;
+const BLOCK_COUNT: usize = 128;
+
+struct RamDisk {
+    data: &'static mut [u8; BLOCK_SIZE * BLOCK_COUNT],
+}
+
+impl BlockDevice for RamDisk {
+    type Error = ();
+
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn block_count(&self) -> u32 {
+        BLOCK_COUNT as u32
+    }
+
+    async fn read(&mut self, lba: u32, buf: &mut [u8]) -> Result<(), ()> {
+        let start = lba as usize * BLOCK_SIZE;
+        buf.copy_from_slice(&self.data[start..start + buf.len()]);
+        Ok(())
+    }
+
+    async fn write(&mut self, lba: u32, buf: &[u8]) -> Result<(), ()> {
+        let start = lba as usize * BLOCK_SIZE;
+        self.data[start..start + buf.len()].copy_from_slice(buf);
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+static mut DISK_DATA: [u8; BLOCK_SIZE * BLOCK_COUNT] = [0; BLOCK_SIZE * BLOCK_COUNT];
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+
+    // Create the driver, from the HAL.
+    let driver = Driver::new(p.USB, Irqs);
+
+    // Create embassy-usb Config
+    let mut config = Config::new(0xc0de, 0xcafe);
+    config.manufacturer = Some("Embassy");
+    config.product = Some("USB mass storage example");
+    config.serial_number = Some("12345678");
+    config.max_power = 100;
+    config.max_packet_size_0 = 64;
+
+    // Create embassy-usb DeviceBuilder using the driver and config.
+    // It needs some buffers for building the descriptors.
+    let mut device_descriptor = [0; 256];
+    let mut config_descriptor = [0; 256];
+    let mut bos_descriptor = [0; 256];
+    let mut control_buf = [0; 64];
+
+    let mut state = State::new();
+    let mut block_buf = [0; BLOCK_SIZE * 2];
+
+    let mut builder = Builder::new(
+        driver,
+        config,
+        &mut device_descriptor,
+        &mut config_descriptor,
+        &mut bos_descriptor,
+        &mut control_buf,
+    );
+
+    // Create classes on the builder.
+    let disk = RamDisk {
+        data: unsafe { &mut DISK_DATA },
+    };
+    let mut class = MscClass::new(
+        &mut builder,
+        &mut state,
+        disk,
+        &mut block_buf,
+        msc::Config {
+            product: "RAM disk",
+            ..Default::default()
+        },
+    );
+
+    // Build the builder.
+    let mut usb = builder.build();
+
+    info!("Serving a {} KiB RAM disk", BLOCK_SIZE * BLOCK_COUNT / 1024);
+    join(usb.run(), class.run()).await;
+}
@@ -18,6 +18,17 @@ async fn main(_spawner: Spawner) {
     let mut watchdog = Watchdog::new(p.WATCHDOG);
     let mut led = Output::new(p.PIN_25, Level::Low);
 
+    // Count the resets by the watchdog in a scratch register, which keeps its value through them
+    let resets = match watchdog.reset_reason() {
+        Some(reason) => {
+            info!("Reset by the watchdog: {}", reason);
+            watchdog.get_scratch(0) + 1
+        }
+        None => 0,
+    };
+    watchdog.set_scratch(0, resets);
+    info!("Resets by the watchdog since power-up: {}", resets);
+
     // Set the LED high for 2 seconds so we know when we're about to start the watchdog
     led.set_high();
     Timer::after(Duration::from_secs(2)).await;
@@ -0,0 +1,148 @@
+//! This example gives WiFi to the RP2040 with an ESP32-C3 running the esp-hosted firmware, and
+//! implements a TCP echo server on port 1234.
+
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_embedded_hal::shared_bus::asynch::spi::SpiDevice;
+use embassy_executor::Spawner;
+use embassy_net::tcp::TcpSocket;
+use embassy_net::{Stack, StackResources};
+use embassy_net_esp_hosted::{NetDriver, Runner, State};
+use embassy_rp::gpio::{AnyPin, Input, Level, Output, Pin, Pull};
+use embassy_rp::peripherals::{PIN_13, SPI1};
+use embassy_rp::spi::{Async, Config as SpiConfig, Phase, Polarity, Spi};
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_sync::mutex::Mutex;
+use embassy_time::{Duration, Timer};
+use embedded_io::asynch::Write;
+use static_cell::StaticCell;
+use {defmt_rtt as _, panic_probe as _};
+
+const WIFI_NETWORK: &str = "EmbassyTest";
+const WIFI_PASSWORD: &str = "V8YxhKt5CdIAJFud";
+
+macro_rules! singleton {
+    ($val:expr) => {{
+        type T = impl Sized;
+        static STATIC_CELL: StaticCell<T> = StaticCell::new();
+        let (x,) = STATIC_CELL.init(($val,));
+        x
+    }};
+}
+
+type EspSpi = SpiDevice<'static, NoopRawMutex, Spi<'static, SPI1, Async>, Output<'static, PIN_13>>;
+
+#[embassy_executor::task]
+async fn wifi_task(runner: Runner<'static, EspSpi, Input<'static, AnyPin>>) -> ! {
+    runner.run().await
+}
+
+#[embassy_executor::task]
+async fn net_task(stack: &'static Stack<NetDriver<'static>>) -> ! {
+    stack.run().await
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+
+    // The ESP32-C3 expects SPI mode 3.
+    let mut spi_cfg = SpiConfig::default();
+    spi_cfg.frequency = 10_000_000;
+    spi_cfg.phase = Phase::CaptureOnSecondTransition;
+    spi_cfg.polarity = Polarity::IdleHigh;
+    let (miso, mosi, clk) = (p.PIN_12, p.PIN_11, p.PIN_10);
+    let spi = Spi::new(p.SPI1, clk, mosi, miso, p.DMA_CH0, p.DMA_CH1, spi_cfg);
+    let spi_bus = singleton!(Mutex::<NoopRawMutex, _>::new(spi));
+    let cs = Output::new(p.PIN_13, Level::High);
+    let handshake = Input::new(p.PIN_14.degrade(), Pull::None);
+    let ready = Input::new(p.PIN_15.degrade(), Pull::None);
+    let reset = Output::new(p.PIN_16, Level::Low);
+
+    let (device, mut control, runner) = embassy_net_esp_hosted::new(
+        singleton!(State::new()),
+        SpiDevice::new(spi_bus, cs),
+        handshake,
+        ready,
+        reset,
+    )
+    .await;
+    unwrap!(spawner.spawn(wifi_task(runner)));
+
+    unwrap!(control.init().await);
+    unwrap!(
+        control
+            .scan(|ap| info!(
+                "found {=[u8]:a} ({:02x}), channel {}, {} dBm",
+                ap.ssid, ap.bssid, ap.channel, ap.rssi
+            ))
+            .await
+    );
+    unwrap!(control.join(WIFI_NETWORK, WIFI_PASSWORD).await);
+
+    // Generate random seed
+    let seed = 1234; // guaranteed random, chosen by a fair dice roll
+
+    // Init network stack
+    let stack = &*singleton!(Stack::new(
+        device,
+        embassy_net::Config::dhcpv4(Default::default()),
+        singleton!(StackResources::<2>::new()),
+        seed
+    ));
+
+    unwrap!(spawner.spawn(net_task(stack)));
+
+    info!("Waiting for DHCP...");
+    let cfg = loop {
+        if let Some(cfg) = stack.config_v4() {
+            break cfg;
+        }
+        Timer::after(Duration::from_millis(100)).await;
+    };
+    info!("IP address: {:?}", cfg.address.address());
+
+    let mut rx_buffer = [0; 4096];
+    let mut tx_buffer = [0; 4096];
+    let mut buf = [0; 4096];
+
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+        socket.set_timeout(Some(Duration::from_secs(10)));
+
+        info!("Listening on TCP:1234...");
+        if let Err(e) = socket.accept(1234).await {
+            warn!("accept error: {:?}", e);
+            continue;
+        }
+
+        info!("Received connection from {:?}", socket.remote_endpoint());
+
+        loop {
+            let n = match socket.read(&mut buf).await {
+                Ok(0) => {
+                    warn!("read EOF");
+                    break;
+                }
+                Ok(n) => n,
+                Err(e) => {
+                    warn!("read error: {:?}", e);
+                    break;
+                }
+            };
+
+            info!("rxd {:02x}", &buf[..n]);
+
+            match socket.write_all(&buf[..n]).await {
+                Ok(()) => {}
+                Err(e) => {
+                    warn!("write error: {:?}", e);
+                    break;
+                }
+            };
+        }
+    }
+}
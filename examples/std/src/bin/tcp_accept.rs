@@ -62,13 +62,13 @@ async fn main_task(spawner: Spawner) {
 
     // Choose between dhcp or static ip
     let config = if opts.static_ip {
-        Config::Static(embassy_net::StaticConfig {
+        Config::ipv4_static(embassy_net::StaticConfigV4 {
             address: Ipv4Cidr::new(Ipv4Address::new(192, 168, 69, 2), 24),
             dns_servers: Vec::new(),
             gateway: Some(Ipv4Address::new(192, 168, 69, 1)),
         })
     } else {
-        Config::Dhcp(Default::default())
+        Config::dhcpv4(Default::default())
     };
 
     // Generate random seed
@@ -110,9 +110,7 @@ async fn main_task(spawner: Spawner) {
             Timer::after(Duration::from_millis(500)).await;
         }
         info!("Closing the connection");
-        socket.abort();
-        info!("Flushing the RST out...");
-        socket.flush().await;
+        socket.abort().await;
         info!("Finished with the socket");
     }
 }
@@ -17,7 +17,7 @@ async fn main(_spawner: Spawner) {
     adc.set_sample_time(SampleTime::Cycles71_5);
     let mut pin = p.PA1;
 
-    let mut vrefint = adc.enable_vref(&mut Delay);
+    let mut vrefint = adc.enable_vrefint(&mut Delay);
     let vrefint_sample = adc.read_internal(&mut vrefint);
     let convert_to_millivolts = |sample| {
         // From https://www.st.com/resource/en/datasheet/stm32f031c6.pdf
@@ -13,13 +13,13 @@ async fn main(_spawner: Spawner) {
     // Initialize and create handle for devicer peripherals
     let p = embassy_stm32::init(Default::default());
     // Configure the independent watchdog  timer
-    let mut wdg = IndependentWatchdog::new(p.IWDG, 20_000_00);
+    let mut wdg = IndependentWatchdog::new(p.IWDG, Duration::from_secs(2).into());
 
     info!("Watchdog start");
-    unsafe { wdg.unleash() };
+    wdg.unleash();
 
     loop {
         Timer::after(Duration::from_secs(1)).await;
-        unsafe { wdg.pet() };
+        wdg.pet();
     }
 }
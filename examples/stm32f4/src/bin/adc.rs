@@ -5,10 +5,12 @@
 use cortex_m::prelude::_embedded_hal_blocking_delay_DelayUs;
 use defmt::*;
 use embassy_executor::Spawner;
-use embassy_stm32::adc::{Adc, Temperature, VrefInt};
+use embassy_stm32::adc::{Adc, Temperature, VrefInt, VREF_CALIB_MV};
 use embassy_time::{Delay, Duration, Timer};
 use {defmt_rtt as _, panic_probe as _};
 
+const MAX_ADC_SAMPLE: u16 = (1 << 12) - 1;
+
 #[embassy_executor::main]
 async fn main(_spawner: Spawner) {
     let p = embassy_stm32::init(Default::default());
@@ -26,27 +28,11 @@ async fn main(_spawner: Spawner) {
 
     let vrefint_sample = adc.read_internal(&mut vrefint);
 
-    let convert_to_millivolts = |sample| {
-        // From http://www.st.com/resource/en/datasheet/DM00071990.pdf
-        // 6.3.24 Reference voltage
-        const VREFINT_MV: u32 = 1210; // mV
-
-        (u32::from(sample) * VREFINT_MV / u32::from(vrefint_sample)) as u16
-    };
-
-    let convert_to_celcius = |sample| {
-        // From http://www.st.com/resource/en/datasheet/DM00071990.pdf
-        // 6.3.22 Temperature sensor characteristics
-        const V25: i32 = 760; // mV
-        const AVG_SLOPE: f32 = 2.5; // mV/C
-
-        let sample_mv = convert_to_millivolts(sample) as i32;
-
-        (sample_mv - V25) as f32 / AVG_SLOPE + 25.0
-    };
+    // VrefInt converts to its factory calibration value at VDDA = VREF_CALIB_MV
+    let vdda_mv = VREF_CALIB_MV * u32::from(VrefInt::calibration_value()) / u32::from(vrefint_sample);
+    let convert_to_millivolts = |sample| (u32::from(sample) * vdda_mv / u32::from(MAX_ADC_SAMPLE)) as u16;
 
     info!("VrefInt: {}", vrefint_sample);
-    const MAX_ADC_SAMPLE: u16 = (1 << 12) - 1;
     info!("VCCA: {} mV", convert_to_millivolts(MAX_ADC_SAMPLE));
 
     loop {
@@ -56,8 +42,8 @@ async fn main(_spawner: Spawner) {
 
         // Read internal temperature
         let v = adc.read_internal(&mut temp);
-        let celcius = convert_to_celcius(v);
-        info!("Internal temp: {} ({} C)", v, celcius);
+        let celsius = Temperature::convert_to_celsius(v);
+        info!("Internal temp: {} ({} C)", v, celsius);
 
         // Read internal voltage reference
         let v = adc.read_internal(&mut vrefint);
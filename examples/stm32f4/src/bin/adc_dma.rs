@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::adc::{Adc, SampleTime};
+use embassy_time::Delay;
+use {defmt_rtt as _, panic_probe as _};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Hello World!");
+
+    let mut adc = Adc::new(p.ADC1, &mut Delay);
+    adc.set_sample_time(SampleTime::Cycles480);
+    let mut pin = p.PC1;
+
+    let mut dma_buf = [0u16; 512];
+    let mut stream = adc.read_continuous(&mut pin, p.DMA2_CH0, &mut dma_buf);
+
+    // Read the half-buffers as the DMA fills them
+    let mut samples = [0u16; 256];
+    loop {
+        match stream.read(&mut samples).await {
+            Ok(()) => {
+                let avg = samples.iter().map(|&s| s as u32).sum::<u32>() / samples.len() as u32;
+                info!("PC1: average of {} samples: {}", samples.len(), avg);
+            }
+            Err(e) => warn!("ADC error: {:?}", e),
+        }
+    }
+}
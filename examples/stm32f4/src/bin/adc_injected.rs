@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::adc::{self, Adc, InjectedChannel, InjectedTrigger};
+use embassy_stm32::{bind_interrupts, peripherals};
+use embassy_time::{Delay, Duration, Timer};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    ADC => adc::InterruptHandler<peripherals::ADC1>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Hello World!");
+
+    let mut adc = Adc::new(p.ADC1, &mut Delay);
+    let mut pin_a = p.PC1;
+    let mut pin_b = p.PC2;
+
+    // Convert both pins in sequence, with PC2 centered around mid scale
+    let channels = [
+        InjectedChannel::new(&mut pin_a, 0),
+        InjectedChannel::new(&mut pin_b, 2048),
+    ];
+    let mut injected = adc.injected(Irqs, &channels, InjectedTrigger::Software);
+
+    let mut results = [0; 2];
+    loop {
+        injected.start();
+        injected.read(&mut results).await;
+        info!("PC1: {}, PC2: {}", results[0], results[1]);
+
+        Timer::after(Duration::from_millis(100)).await;
+    }
+}
@@ -2,16 +2,24 @@
 #![no_main]
 #![feature(type_alias_impl_trait)]
 
-use cortex_m_rt::entry;
 use defmt::*;
+use embassy_executor::Spawner;
 use embassy_stm32::can::bxcan::filter::Mask32;
 use embassy_stm32::can::bxcan::{Fifo, Frame, StandardId};
-use embassy_stm32::can::Can;
+use embassy_stm32::can::{Can, Rx0InterruptHandler, Rx1InterruptHandler, SceInterruptHandler, TxInterruptHandler};
 use embassy_stm32::gpio::{Input, Pull};
+use embassy_stm32::{bind_interrupts, peripherals};
 use {defmt_rtt as _, panic_probe as _};
 
-#[entry]
-fn main() -> ! {
+bind_interrupts!(struct Irqs {
+    CAN1_TX => TxInterruptHandler<peripherals::CAN1>;
+    CAN1_RX0 => Rx0InterruptHandler<peripherals::CAN1>;
+    CAN1_RX1 => Rx1InterruptHandler<peripherals::CAN1>;
+    CAN1_SCE => SceInterruptHandler<peripherals::CAN1>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
     info!("Hello World!");
 
     let mut p = embassy_stm32::init(Default::default());
@@ -23,7 +31,7 @@ fn main() -> ! {
     let rx_pin = Input::new(&mut p.PA11, Pull::Up);
     core::mem::forget(rx_pin);
 
-    let mut can = Can::new(p.CAN1, p.PA11, p.PA12);
+    let mut can = Can::new(p.CAN1, p.PA11, p.PA12, Irqs);
 
     can.modify_filters().enable_bank(0, Fifo::Fifo0, Mask32::accept_all());
 
@@ -33,13 +41,27 @@ fn main() -> ! {
         .set_silent(true)
         .enable();
 
+    can.set_automatic_bus_off_recovery(true);
+
     let mut i: u8 = 0;
     loop {
         let tx_frame = Frame::new_data(unwrap!(StandardId::new(i as _)), [i]);
-        unwrap!(nb::block!(can.transmit(&tx_frame)));
-        while !can.is_transmitter_idle() {}
-        let rx_frame = unwrap!(nb::block!(can.receive()));
-        info!("loopback frame {=u8}", unwrap!(rx_frame.data())[0]);
-        i += 1;
+        let status = can.write(&tx_frame).await;
+        can.flush(status.mailbox()).await;
+
+        match can.read().await {
+            Ok(rx_frame) => info!("loopback frame {=u8}", unwrap!(rx_frame.data())[0]),
+            Err(e) => {
+                let counters = can.error_counters();
+                warn!(
+                    "read error {:?}, last bus error {:?}, tec {=u8}, rec {=u8}",
+                    e,
+                    can.last_error(),
+                    counters.transmit,
+                    counters.receive
+                );
+            }
+        }
+        i = i.wrapping_add(1);
     }
 }
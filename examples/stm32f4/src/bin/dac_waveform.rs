@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::dac::{CallbackResult, Dac};
+use embassy_stm32::time::Hertz;
+use {defmt_rtt as _, panic_probe as _};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Hello World!");
+
+    let mut dac = Dac::new_1ch(p.DAC, p.PA4);
+    let mut waveform = unwrap!(dac.waveform_ch1(p.DMA1_CH5, p.TIM6));
+
+    // A single ramp from 0 to full scale
+    let mut ramp = [0u16; 64];
+    for (i, s) in ramp.iter_mut().enumerate() {
+        *s = (i * 4095 / (ramp.len() - 1)) as u16;
+    }
+    waveform.write_waveform(&ramp, Hertz(64_000)).await;
+    info!("Ramp done");
+
+    // Then a continuous sawtooth, generated in blocks of 128 samples
+    let mut bufs = [[0u16; 128]; 2];
+    let mut level = 0u16;
+    waveform
+        .write_continuous(&mut bufs, Hertz(48_000), |buf| {
+            for s in buf.iter_mut() {
+                *s = level;
+                level = (level + 16) % 4096;
+            }
+            CallbackResult::Continue
+        })
+        .await;
+}
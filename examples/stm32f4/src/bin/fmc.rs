@@ -0,0 +1,87 @@
+//! This example initializes the IS42S16400J SDRAM of the STM32F429I-DISCO, then writes and reads
+//! it back.
+
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::fmc::Fmc;
+use embassy_stm32::time::mhz;
+use embassy_stm32::Config;
+use embassy_time::Delay;
+use {defmt_rtt as _, panic_probe as _};
+
+/// 64 Mbit
+const SDRAM_SIZE: usize = 8 * 1024 * 1024;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let mut config = Config::default();
+    // The SDRAM clock is HCLK / 2
+    config.rcc.sys_ck = Some(mhz(168));
+    let p = embassy_stm32::init(config);
+    info!("Hello World!");
+
+    let mut sdram = Fmc::sdram_a12bits_d16bits_4banks_bank2(
+        p.FMC,
+        // A0-A11
+        p.PF0,
+        p.PF1,
+        p.PF2,
+        p.PF3,
+        p.PF4,
+        p.PF5,
+        p.PF12,
+        p.PF13,
+        p.PF14,
+        p.PF15,
+        p.PG0,
+        p.PG1,
+        // BA0-BA1
+        p.PG4,
+        p.PG5,
+        // D0-D15
+        p.PD14,
+        p.PD15,
+        p.PD0,
+        p.PD1,
+        p.PE7,
+        p.PE8,
+        p.PE9,
+        p.PE10,
+        p.PE11,
+        p.PE12,
+        p.PE13,
+        p.PE14,
+        p.PE15,
+        p.PD8,
+        p.PD9,
+        p.PD10,
+        // NBL0-NBL1
+        p.PE0,
+        p.PE1,
+        p.PB5,  // SDCKE1
+        p.PG8,  // SDCLK
+        p.PG15, // SDNCAS
+        p.PB6,  // SDNE1 (!CS)
+        p.PF11, // SDNRAS
+        p.PC0,  // SDNWE
+        stm32_fmc::devices::is42s16400j_7::Is42s16400j {},
+    );
+
+    let ram = unsafe {
+        let ptr = sdram.init(&mut Delay) as *mut u32;
+        core::slice::from_raw_parts_mut(ptr, SDRAM_SIZE / core::mem::size_of::<u32>())
+    };
+
+    for (i, word) in ram.iter_mut().enumerate() {
+        *word = i as u32;
+    }
+    for (i, word) in ram.iter().enumerate() {
+        defmt::assert_eq!(*word, i as u32);
+    }
+
+    info!("Test OK");
+}
@@ -0,0 +1,63 @@
+//! This example makes the board an I2C device at address 0x55, exposing a 16 byte register file.
+//!
+//! The controller writes the register address followed by the values to store, or writes the register
+//! address then reads the values from there.
+
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::i2c::{CommandKind, I2cSlave, SlaveConfig};
+use embassy_stm32::time::Hertz;
+use embassy_stm32::{bind_interrupts, i2c, peripherals};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    I2C2_EV => i2c::InterruptHandler<peripherals::I2C2>;
+    I2C2_ER => i2c::ErrorInterruptHandler<peripherals::I2C2>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    info!("Hello world!");
+    let p = embassy_stm32::init(Default::default());
+
+    let mut config = SlaveConfig::default();
+    config.address = 0x55;
+    let mut dev = I2cSlave::new(p.I2C2, p.PB10, p.PB11, Irqs, Hertz(100_000), config);
+
+    let mut regs = [0u8; 16];
+    let mut reg = 0usize;
+    let mut buf = [0u8; 17];
+
+    loop {
+        let command = match dev.listen().await {
+            Ok(command) => command,
+            Err(e) => {
+                warn!("I2C error: {:?}", e);
+                continue;
+            }
+        };
+
+        match command.kind {
+            CommandKind::Write => match dev.respond_to_write(&mut buf).await {
+                Ok(0) => {}
+                Ok(len) => {
+                    reg = buf[0] as usize % regs.len();
+                    for &b in &buf[1..len] {
+                        regs[reg] = b;
+                        reg = (reg + 1) % regs.len();
+                    }
+                    info!("Write of {} bytes", len);
+                }
+                Err(e) => warn!("I2C error: {:?}", e),
+            },
+            CommandKind::Read => match dev.respond_to_read(&regs[reg..]).await {
+                Ok(len) => info!("Read of {} bytes", len),
+                Err(e) => warn!("I2C error: {:?}", e),
+            },
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! This example captures a 24 bit I2S MEMS microphone, like the INMP441, and prints the peak
+//! level of each block of samples.
+
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::i2s::{Config, Format, Function, I2S};
+use embassy_stm32::time::Hertz;
+use futures::StreamExt;
+use {defmt_rtt as _, panic_probe as _};
+
+// Each sample takes two half-words, for 32 stereo samples per block
+const BLOCK_LEN: usize = 128;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let mut config = embassy_stm32::Config::default();
+    config.rcc.plli2s = Some(Hertz(96_000_000));
+    let p = embassy_stm32::init(config);
+    info!("Hello World!");
+
+    let mut i2s_config = Config::default();
+    i2s_config.function = Function::Receive;
+    i2s_config.format = Format::Data24Channel32;
+    i2s_config.master_clock = false;
+
+    let mut i2s = I2S::new(
+        p.SPI2,
+        p.PC3,  // sd
+        p.PB12, // ws
+        p.PB10, // ck
+        p.PC6,  // mck
+        p.DMA1_CH4,
+        p.DMA1_CH3,
+        Hertz(16_000),
+        i2s_config,
+    );
+
+    let mut dma_buf = [0u16; 4 * BLOCK_LEN];
+    let mut stream = i2s.stream::<BLOCK_LEN>(&mut dma_buf);
+
+    while let Some(block) = stream.next().await {
+        let block = match block {
+            Ok(block) => block,
+            Err(_) => {
+                warn!("overrun");
+                continue;
+            }
+        };
+
+        // Rebuild the 24 bit samples from the high and low half-words
+        let peak = block
+            .chunks_exact(2)
+            .map(|s| ((((s[0] as u32) << 16) | s[1] as u32) as i32 >> 8).unsigned_abs())
+            .max()
+            .unwrap_or(0);
+        info!("peak: {}", peak);
+    }
+}
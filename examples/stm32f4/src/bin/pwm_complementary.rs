@@ -4,9 +4,10 @@
 
 use defmt::*;
 use embassy_executor::Spawner;
-use embassy_stm32::pwm::complementary_pwm::{ComplementaryPwm, ComplementaryPwmPin};
+use embassy_stm32::gpio::Pull;
+use embassy_stm32::pwm::complementary_pwm::{BreakPin, ComplementaryPwm, ComplementaryPwmPin};
 use embassy_stm32::pwm::simple_pwm::PwmPin;
-use embassy_stm32::pwm::Channel;
+use embassy_stm32::pwm::{BreakConfig, Channel, CountingMode};
 use embassy_stm32::time::khz;
 use embassy_time::{Duration, Timer};
 use {defmt_rtt as _, panic_probe as _};
@@ -30,16 +31,26 @@ async fn main(_spawner: Spawner) {
         None,
         khz(10),
     );
+    pwm.set_counting_mode(CountingMode::CenterAlignedBothInterrupts);
 
     let max = pwm.get_max_duty();
     pwm.set_dead_time(max / 1024);
 
+    // Pulling PE15 low disables the outputs
+    let bkin = BreakPin::new(p.PE15, Pull::Up);
+    pwm.enable_break(bkin, BreakConfig::default());
+
     pwm.enable(Channel::Ch1);
 
     info!("PWM initialized");
     info!("PWM max duty {}", max);
 
     loop {
+        if pwm.break_occurred() {
+            warn!("break");
+            pwm.clear_break();
+        }
+
         pwm.set_duty(Channel::Ch1, 0);
         Timer::after(Duration::from_millis(300)).await;
         pwm.set_duty(Channel::Ch1, max / 4);
@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::gpio::Pull;
+use embassy_stm32::time::mhz;
+use embassy_stm32::timer::pwm_input::PwmInput;
+use embassy_time::{Duration, Timer};
+use {defmt_rtt as _, panic_probe as _};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Hello World!");
+
+    let tick = mhz(1);
+    let mut pwm_input = PwmInput::new(p.TIM4, p.PB6, Pull::None, tick);
+    pwm_input.enable();
+
+    loop {
+        let period = pwm_input.get_period_ticks();
+        if period != 0 {
+            info!(
+                "frequency: {} Hz, duty cycle: {}%",
+                tick.0 / period as u32,
+                pwm_input.get_duty_cycle() * 100.
+            );
+        }
+        Timer::after(Duration::from_millis(500)).await;
+    }
+}
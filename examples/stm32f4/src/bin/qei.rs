@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::timer::qei::{self, Qei, QeiPin};
+use embassy_stm32::{bind_interrupts, peripherals};
+use embassy_time::{Duration, Timer};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    TIM3 => qei::InterruptHandler<peripherals::TIM3>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Hello World!");
+
+    let ch1 = QeiPin::new_ch1(p.PA6);
+    let ch2 = QeiPin::new_ch2(p.PA7);
+    let encoder = Qei::new(p.TIM3, ch1, ch2, Irqs, Default::default());
+
+    loop {
+        info!(
+            "position: {}, direction: {}",
+            encoder.position(),
+            encoder.read_direction()
+        );
+        Timer::after(Duration::from_millis(300)).await;
+    }
+}
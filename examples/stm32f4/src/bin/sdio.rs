@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::sdmmc::Sdmmc;
+use embassy_stm32::time::mhz;
+use embassy_stm32::{bind_interrupts, peripherals, sdmmc, Config};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    SDIO => sdmmc::InterruptHandler<peripherals::SDIO>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let mut config = Config::default();
+    config.rcc.sys_ck = Some(mhz(48));
+    config.rcc.pll48 = true;
+    let p = embassy_stm32::init(config);
+    info!("Hello World!");
+
+    let mut sdmmc = Sdmmc::new_4bit(
+        p.SDIO,
+        Irqs,
+        p.DMA2_CH3,
+        p.PC12,
+        p.PD2,
+        p.PC8,
+        p.PC9,
+        p.PC10,
+        p.PC11,
+        Default::default(),
+    );
+
+    unwrap!(sdmmc.init_sdio_card(mhz(24)).await);
+
+    let card = unwrap!(sdmmc.sdio_card());
+    info!(
+        "SDIO card {:04x}:{:04x}, {} functions",
+        card.manufacturer, card.card_id, card.num_functions
+    );
+    let num_functions = card.num_functions;
+    for func in 1..=num_functions {
+        info!(
+            "function {}: interface code {:x}",
+            func,
+            card.interface_codes[func as usize - 1]
+        );
+    }
+    info!("Clock: {}", sdmmc.clock());
+
+    // Enable function 1, and read its first registers.
+    unwrap!(sdmmc.enable_function(1));
+    unwrap!(sdmmc.set_block_size(1, 64));
+
+    let mut buf = [0u32; 16];
+    unwrap!(sdmmc.read_extended(1, 0, true, &mut buf).await);
+    info!("Function 1: {:08x}", buf);
+}
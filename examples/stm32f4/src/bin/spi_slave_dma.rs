@@ -0,0 +1,62 @@
+//! This example connects SPI1 as master to SPI2 as slave on the same board.
+//!
+//! Wire PA5 to PB13 (SCK), PA7 to PB15 (MOSI), PA6 to PB14 (MISO) and PA4 to PB12 (NSS).
+
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::spi::{Config, Spi};
+use embassy_stm32::time::Hertz;
+use embassy_time::{Duration, Timer};
+use futures::future::join;
+use {defmt_rtt as _, panic_probe as _};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Hello World!");
+
+    let mut master = Spi::new_with_nss(
+        p.SPI1,
+        p.PA5,
+        p.PA7,
+        p.PA6,
+        p.PA4,
+        p.DMA2_CH3,
+        p.DMA2_CH2,
+        Hertz(1_000_000),
+        Config::default(),
+    );
+    let mut slave = Spi::new_slave(
+        p.SPI2,
+        p.PB13,
+        p.PB15,
+        p.PB14,
+        p.PB12,
+        p.DMA1_CH4,
+        p.DMA1_CH3,
+        Config::default(),
+    );
+
+    for n in 0u8.. {
+        let master_tx = [n, 0x11, 0x22, 0x33];
+        let slave_tx = [n, 0xaa, 0xbb, 0xcc];
+        let mut master_rx = [0; 4];
+        let mut slave_rx = [0; 4];
+
+        // The slave is polled first, so it is ready before the master starts clocking.
+        let (slave_res, master_res) = join(
+            slave.transfer(&mut slave_rx, &slave_tx),
+            master.transfer(&mut master_rx, &master_tx),
+        )
+        .await;
+        unwrap!(slave_res);
+        unwrap!(master_res);
+
+        info!("master received {:x}, slave received {:x}", master_rx, slave_rx);
+        Timer::after(Duration::from_millis(500)).await;
+    }
+}
@@ -0,0 +1,114 @@
+//! This example uses the USB OTG peripheral as a host, and prints the reports of a keyboard (or any
+//! other HID device with an interrupt IN endpoint) plugged in the board.
+
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::gpio::{Level, Output, Speed};
+use embassy_stm32::time::mhz;
+use embassy_stm32::usb_otg::host::{
+    Channel, EndpointAddress, EndpointType, Error, Host, InterruptHandler, DESCRIPTOR_TYPE_CONFIGURATION,
+};
+use embassy_stm32::usb_otg::Instance;
+use embassy_stm32::{bind_interrupts, peripherals, Config};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    OTG_FS => InterruptHandler<peripherals::USB_OTG_FS>;
+});
+
+const DEVICE_ADDRESS: u8 = 1;
+const DESCRIPTOR_TYPE_ENDPOINT: u8 = 0x05;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    info!("Hello World!");
+
+    let mut config = Config::default();
+    config.rcc.pll48 = true;
+    config.rcc.sys_ck = Some(mhz(48));
+
+    let p = embassy_stm32::init(config);
+
+    // Switch on the power of the USB port (Nucleo-144 boards)
+    let _vbus = Output::new(p.PG6, Level::High, Speed::Low);
+
+    let host = Host::new_fs(p.USB_OTG_FS, Irqs, p.PA12, p.PA11);
+
+    loop {
+        let speed = host.wait_for_device().await;
+        info!("Device connected, speed: {:?}", speed);
+
+        match run(&host).await {
+            Ok(()) => {}
+            Err(e) => warn!("Error: {:?}", e),
+        }
+
+        host.wait_for_disconnect().await;
+        info!("Device disconnected");
+    }
+}
+
+async fn run<T: Instance>(host: &Host<'_, T>) -> Result<(), Error> {
+    let (descriptor, mut control) = host.enumerate(DEVICE_ADDRESS).await?;
+    info!(
+        "Device {:04x}:{:04x}, class {:02x}",
+        descriptor.vendor_id, descriptor.product_id, descriptor.device_class
+    );
+
+    let mut buf = [0; 256];
+    let len = control
+        .get_descriptor(DESCRIPTOR_TYPE_CONFIGURATION, 0, &mut buf)
+        .await?;
+    let config = &buf[..len];
+    if len < 6 {
+        return Err(Error::InvalidDescriptor);
+    }
+
+    let (endpoint, max_packet_size, interval_ms) = find_interrupt_in(config).ok_or(Error::InvalidDescriptor)?;
+    info!("Found interrupt IN endpoint {:?}", endpoint);
+
+    // Select the configuration given by bConfigurationValue
+    control.set_configuration(config[5]).await?;
+
+    let mut channel: Channel<'_, T> = host.alloc_channel(
+        DEVICE_ADDRESS,
+        endpoint,
+        EndpointType::Interrupt,
+        max_packet_size,
+        interval_ms,
+    )?;
+
+    let mut report = [0; 64];
+    loop {
+        let n = channel.read(&mut report[..max_packet_size as usize]).await?;
+        info!("Report: {:x}", &report[..n]);
+    }
+}
+
+/// Finds the first interrupt IN endpoint in a configuration descriptor.
+fn find_interrupt_in(config: &[u8]) -> Option<(EndpointAddress, u16, u8)> {
+    let mut descriptors = config;
+    while descriptors.len() >= 2 {
+        let len = descriptors[0] as usize;
+        if len < 2 || len > descriptors.len() {
+            return None;
+        }
+
+        let descriptor = &descriptors[..len];
+        if descriptor[1] == DESCRIPTOR_TYPE_ENDPOINT && len >= 7 {
+            let endpoint = EndpointAddress::from(descriptor[2]);
+            let interrupt = descriptor[3] & 0x03 == 0x03;
+            if endpoint.is_in() && interrupt {
+                let max_packet_size = u16::from_le_bytes([descriptor[4], descriptor[5]]).min(64);
+                return Some((endpoint, max_packet_size, descriptor[6]));
+            }
+        }
+
+        descriptors = &descriptors[len..];
+    }
+    None
+}
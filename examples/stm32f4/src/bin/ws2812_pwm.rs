@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::pwm::simple_pwm::{PwmPin, SimplePwm};
+use embassy_stm32::pwm::Channel;
+use embassy_stm32::time::khz;
+use embassy_time::{Duration, Timer};
+use {defmt_rtt as _, panic_probe as _};
+
+const LED_COUNT: usize = 8;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Hello World!");
+
+    // WS2812 LEDs take one 800 kHz pulse per bit: a short one for 0, a long one for 1.
+    let ch1 = PwmPin::new_ch1(p.PA8);
+    let mut pwm = SimplePwm::new(p.TIM1, Some(ch1), None, None, None, khz(800));
+    let max = pwm.get_max_duty();
+    let zero = max / 3;
+    let one = max * 2 / 3;
+    pwm.set_duty(Channel::Ch1, 0);
+    pwm.enable(Channel::Ch1);
+
+    let mut dma = p.DMA2_CH5;
+
+    // 24 bits per LED, in GRB order, then a 0 to keep the line low after the last bit.
+    let mut duty = [0u16; LED_COUNT * 24 + 1];
+    let mut hue = 0u8;
+
+    loop {
+        for led in 0..LED_COUNT {
+            let value = hue.wrapping_add(led as u8 * 32);
+            let grb = [value, 255 - value, 0];
+            for (i, byte) in grb.iter().enumerate() {
+                for bit in 0..8 {
+                    duty[led * 24 + i * 8 + bit] = match byte & (0x80 >> bit) {
+                        0 => zero,
+                        _ => one,
+                    };
+                }
+            }
+        }
+
+        pwm.waveform_up(&mut dma, Channel::Ch1, &duty).await;
+
+        hue = hue.wrapping_add(4);
+        Timer::after(Duration::from_millis(20)).await;
+    }
+}
@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::bind_interrupts;
+use embassy_stm32::peripherals::WWDG;
+use embassy_stm32::wdg::{Supervisor, TaskId, WindowConfig, WindowInterruptHandler, WindowWatchdog};
+use embassy_time::{Duration, Timer};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    WWDG => WindowInterruptHandler<WWDG>;
+});
+
+static SUPERVISOR: Supervisor = Supervisor::new(2);
+
+#[embassy_executor::task(pool_size = 2)]
+async fn worker(id: TaskId, period: Duration, iterations: u32) {
+    // Stop reporting after some iterations, as if the task got stuck
+    for _ in 0..iterations {
+        Timer::after(period).await;
+        SUPERVISOR.report(id);
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Hello World!");
+
+    // Clocked by the 16 MHz APB clock, the watchdog must be refreshed between 10 and 30 ms
+    // after the previous refresh.
+    let config = WindowConfig {
+        timeout: Duration::from_millis(30).into(),
+        window: Duration::from_millis(10).into(),
+    };
+    let mut wdg = WindowWatchdog::new_with_early_wakeup(p.WWDG, Irqs, config);
+
+    unwrap!(spawner.spawn(worker(SUPERVISOR.task(0), Duration::from_millis(5), u32::MAX)));
+    unwrap!(spawner.spawn(worker(SUPERVISOR.task(1), Duration::from_millis(10), 100)));
+
+    wdg.unleash();
+
+    loop {
+        // Each worker reports at least once in 20 ms
+        Timer::after(Duration::from_millis(20)).await;
+        match SUPERVISOR.check() {
+            Ok(()) => wdg.pet(),
+            Err(missing) => {
+                warn!("tasks {:b} are stuck, waiting for the reset", missing);
+                wdg.wait_early_wakeup().await;
+                error!("resetting");
+                loop {}
+            }
+        }
+    }
+}
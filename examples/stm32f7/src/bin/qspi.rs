@@ -0,0 +1,141 @@
+//! This example programs a page of a QSPI NOR flash, like the one of the STM32F746G-DISCO, then
+//! reads it back through the memory-mapped mode.
+
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::qspi::enums::{AddressSize, DummyCycles, MemorySize, QspiWidth};
+use embassy_stm32::qspi::{AutoPollConfig, Config as QspiConfig, Qspi, TransferConfig};
+use {defmt_rtt as _, panic_probe as _};
+
+const CMD_READ_ID: u8 = 0x9F;
+const CMD_WRITE_ENABLE: u8 = 0x06;
+const CMD_READ_STATUS: u8 = 0x05;
+const CMD_SECTOR_ERASE: u8 = 0x20;
+const CMD_PAGE_PROGRAM: u8 = 0x02;
+const CMD_FAST_READ_QUAD_OUT: u8 = 0x6B;
+
+const STATUS_WIP: u32 = 1 << 0;
+
+/// Start of the QUADSPI region of the memory map
+const MEMORY_MAPPED_BASE: usize = 0x9000_0000;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Hello World!");
+
+    let mut config = QspiConfig::default();
+    config.memory_size = MemorySize::_16MiB;
+    config.address_size = AddressSize::_24bit;
+    config.prescaler = 4;
+
+    let mut qspi = Qspi::new(
+        p.QUADSPI, p.PD11, p.PD12, p.PE2, p.PD13, p.PB2, p.PB6, p.DMA2_CH7, config,
+    );
+
+    let mut id = [0; 3];
+    qspi.blocking_read(
+        &mut id,
+        TransferConfig {
+            iwidth: QspiWidth::SING,
+            dwidth: QspiWidth::SING,
+            instruction: CMD_READ_ID,
+            data_len: Some(3),
+            ..Default::default()
+        },
+    );
+    info!("JEDEC ID: {:02x}", id);
+
+    write_enable(&mut qspi);
+    qspi.command(TransferConfig {
+        iwidth: QspiWidth::SING,
+        awidth: QspiWidth::SING,
+        instruction: CMD_SECTOR_ERASE,
+        address: Some(0),
+        ..Default::default()
+    });
+    wait_ready(&mut qspi);
+
+    let mut page = [0; 256];
+    for (i, b) in page.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    write_enable(&mut qspi);
+    qspi.write_dma(
+        &page,
+        TransferConfig {
+            iwidth: QspiWidth::SING,
+            awidth: QspiWidth::SING,
+            dwidth: QspiWidth::SING,
+            instruction: CMD_PAGE_PROGRAM,
+            address: Some(0),
+            data_len: Some(page.len()),
+            ..Default::default()
+        },
+    )
+    .await;
+    wait_ready(&mut qspi);
+
+    let mut read = [0; 256];
+    qspi.read_dma(
+        &mut read,
+        TransferConfig {
+            iwidth: QspiWidth::SING,
+            awidth: QspiWidth::SING,
+            dwidth: QspiWidth::QUAD,
+            instruction: CMD_FAST_READ_QUAD_OUT,
+            address: Some(0),
+            dummy: DummyCycles::_8,
+            data_len: Some(256),
+        },
+    )
+    .await;
+    info!("read: {:02x}", read[..16]);
+
+    qspi.enable_memory_mapped(
+        TransferConfig {
+            iwidth: QspiWidth::SING,
+            awidth: QspiWidth::SING,
+            dwidth: QspiWidth::QUAD,
+            instruction: CMD_FAST_READ_QUAD_OUT,
+            dummy: DummyCycles::_8,
+            ..Default::default()
+        },
+        None,
+    );
+    let mapped = unsafe { core::slice::from_raw_parts(MEMORY_MAPPED_BASE as *const u8, page.len()) };
+    info!("memory-mapped: {:02x}", mapped[..16]);
+    defmt::assert_eq!(mapped, &page[..]);
+
+    info!("Test OK");
+}
+
+fn write_enable<T: embassy_stm32::qspi::Instance, Dma>(qspi: &mut Qspi<'_, T, Dma>) {
+    qspi.command(TransferConfig {
+        iwidth: QspiWidth::SING,
+        instruction: CMD_WRITE_ENABLE,
+        ..Default::default()
+    });
+}
+
+/// Wait for the end of an erase or program operation.
+fn wait_ready<T: embassy_stm32::qspi::Instance, Dma>(qspi: &mut Qspi<'_, T, Dma>) {
+    qspi.blocking_auto_poll(
+        TransferConfig {
+            iwidth: QspiWidth::SING,
+            dwidth: QspiWidth::SING,
+            instruction: CMD_READ_STATUS,
+            data_len: Some(1),
+            ..Default::default()
+        },
+        AutoPollConfig {
+            mask: STATUS_WIP,
+            match_value: 0,
+            ..Default::default()
+        },
+    );
+}
@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::gpio::Pull;
+use embassy_stm32::lptim::counter::{Edge, PulseCounter};
+use embassy_stm32::lptim::pwm::LptimPwm;
+use embassy_stm32::lptim::ClockSource;
+use embassy_stm32::time::hz;
+use embassy_time::{Duration, Timer};
+use {defmt_rtt as _, panic_probe as _};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Hello World!");
+
+    // 1 kHz PWM clocked by the LSI, which keeps running in STOP modes
+    let mut pwm = LptimPwm::new(p.LPTIM1, p.PB2, ClockSource::Lsi, hz(1000));
+    let max = pwm.get_max_duty();
+    pwm.set_duty(max / 4);
+
+    // Connect PB2 to PB1 to count the PWM pulses
+    let counter = PulseCounter::new(p.LPTIM2, p.PB1, Pull::None, Edge::Rising);
+
+    loop {
+        Timer::after(Duration::from_millis(100)).await;
+        info!("pulses: {}", counter.count());
+    }
+}
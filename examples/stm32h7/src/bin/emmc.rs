@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::sdmmc::{DataBlock, Sdmmc};
+use embassy_stm32::time::mhz;
+use embassy_stm32::{bind_interrupts, peripherals, sdmmc, Config};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    SDMMC1 => sdmmc::InterruptHandler<peripherals::SDMMC1>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) -> ! {
+    let mut config = Config::default();
+    config.rcc.sys_ck = Some(mhz(200));
+    let p = embassy_stm32::init(config);
+    info!("Hello World!");
+
+    let mut sdmmc = Sdmmc::new_8bit(
+        p.SDMMC1,
+        Irqs,
+        p.PC12,
+        p.PD2,
+        p.PC8,
+        p.PC9,
+        p.PC10,
+        p.PC11,
+        p.PB8,
+        p.PB9,
+        p.PC6,
+        p.PC7,
+        Default::default(),
+    );
+
+    // Should print 400kHz for initialization
+    info!("Configured clock: {}", sdmmc.clock().0);
+
+    unwrap!(sdmmc.init_emmc(mhz(50)).await);
+
+    let emmc = unwrap!(sdmmc.emmc());
+    info!(
+        "eMMC: {} sectors, {:?} bus, {:?} timing, at {} Hz",
+        emmc.sector_count,
+        Debug2Format(&emmc.bus_width),
+        emmc.timing,
+        sdmmc.clock().0
+    );
+
+    let mut block = DataBlock([0; 512]);
+    unwrap!(sdmmc.read_block(0, &mut block).await);
+    info!("Block 0: {:02x}", block.0[..32]);
+
+    loop {}
+}
@@ -13,12 +13,12 @@ async fn main(_spawner: Spawner) {
     let p = embassy_stm32::init(Default::default());
     info!("Hello World!");
 
-    let mut wdg = IndependentWatchdog::new(p.IWDG1, 20_000_000);
+    let mut wdg = IndependentWatchdog::new(p.IWDG1, Duration::from_secs(20).into());
 
-    unsafe { wdg.unleash() };
+    wdg.unleash();
 
     loop {
         Timer::after(Duration::from_secs(1)).await;
-        unsafe { wdg.pet() };
+        wdg.pet();
     }
 }
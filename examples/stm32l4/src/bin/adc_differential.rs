@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use defmt::*;
+use embassy_stm32::adc::{Adc, OversamplingRatio};
+use embassy_stm32::pac;
+use embassy_time::Delay;
+use {defmt_rtt as _, panic_probe as _};
+
+#[cortex_m_rt::entry]
+fn main() -> ! {
+    info!("Hello World!");
+
+    unsafe {
+        pac::RCC.ccipr().modify(|w| {
+            w.set_adcsel(0b11);
+        });
+        pac::RCC.ahb2enr().modify(|w| w.set_adcen(true));
+    }
+
+    let p = embassy_stm32::init(Default::default());
+
+    let mut adc = Adc::new(p.ADC1, &mut Delay);
+    // Accumulate 16 conversions into 14 bit results
+    adc.set_oversampling(OversamplingRatio::Mul16, 2);
+
+    // Channel 1 on PC0, with PC1 as negative input
+    let mut pos = p.PC0;
+    let mut neg = p.PC1;
+
+    loop {
+        let v = adc.read_differential(&mut pos, &mut neg);
+        info!("--> {}", v as i32 - (1 << 13));
+    }
+}
@@ -57,13 +57,14 @@ async fn main(_spawner: Spawner) {
                 let version_major = fw_info.version_major();
                 let version_minor = fw_info.version_minor();
                 let subversion = fw_info.subversion();
+                let stack_type = fw_info.stack_type();
 
                 let sram2a_size = fw_info.sram2a_size();
                 let sram2b_size = fw_info.sram2b_size();
 
                 info!(
-                    "version {}.{}.{} - SRAM2a {} - SRAM2b {}",
-                    version_major, version_minor, subversion, sram2a_size, sram2b_size
+                    "version {}.{}.{} - stack type {:x} - SRAM2a {} - SRAM2b {}",
+                    version_major, version_minor, subversion, stack_type, sram2a_size, sram2b_size
                 );
 
                 break;
@@ -53,24 +53,16 @@ async fn main(_spawner: Spawner) {
 
     info!("waiting for coprocessor to boot");
     let event_box = mbox.read().await;
-
-    let mut payload = [0u8; 6];
-    event_box.copy_into_slice(&mut payload).unwrap();
-
-    let event_packet = event_box.evt();
-    let kind = event_packet.evt_serial.kind;
+    let evt = event_box.evt_view().unwrap();
 
     // means recieved SYS event, which indicates in this case that the coprocessor is ready
-    if kind == 0x12 {
-        let code = event_packet.evt_serial.evt.evt_code;
-        let payload_len = event_packet.evt_serial.evt.payload_len;
-
+    if evt.packet_type() == 0x12 {
         info!(
             "==> kind: {:#04x}, code: {:#04x}, payload_length: {}, payload: {:#04x}",
-            kind,
-            code,
-            payload_len,
-            payload[3..]
+            evt.packet_type(),
+            evt.evt_code(),
+            evt.payload().len(),
+            evt.payload()
         );
     }
 
@@ -80,19 +72,14 @@ async fn main(_spawner: Spawner) {
     mbox.send_ble_cmd(&mut ipcc, &[0x01, 0x03, 0x0c, 0x00, 0x00]);
 
     let event_box = mbox.read().await;
-
-    let mut payload = [0u8; 7];
-    event_box.copy_into_slice(&mut payload).unwrap();
-
-    let event_packet = event_box.evt();
-    let kind = event_packet.evt_serial.kind;
-
-    let code = event_packet.evt_serial.evt.evt_code;
-    let payload_len = event_packet.evt_serial.evt.payload_len;
+    let evt = event_box.evt_view().unwrap();
 
     info!(
         "==> kind: {:#04x}, code: {:#04x}, payload_length: {}, payload: {:#04x}",
-        kind, code, payload_len, payload
+        evt.packet_type(),
+        evt.evt_code(),
+        evt.payload().len(),
+        evt.payload()
     );
 
     loop {}
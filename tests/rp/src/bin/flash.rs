@@ -58,6 +58,27 @@ async fn main(_spawner: Spawner) {
         defmt::panic!("unexpected");
     }
 
+    // Write 3 bytes straddling the boundary between the first and the second sector,
+    // the bytes around them in both sectors must be preserved.
+    defmt::unwrap!(flash.erase(ADDR_OFFSET, ADDR_OFFSET + 2 * ERASE_SIZE as u32));
+    defmt::unwrap!(flash.write(ADDR_OFFSET, &mut buf));
+    defmt::unwrap!(flash.write(ADDR_OFFSET + ERASE_SIZE as u32, &mut buf));
+
+    let boundary = ADDR_OFFSET + ERASE_SIZE as u32;
+    defmt::unwrap!(flash.write_unaligned(boundary - 2, &[0x01, 0x02, 0x03]));
+
+    defmt::unwrap!(flash.read(ADDR_OFFSET, &mut buf));
+    info!("First sector after straddling write ends with {=[u8]}", buf[ERASE_SIZE - 4..]);
+    if buf[..ERASE_SIZE - 2].iter().any(|x| *x != 0xDA) || buf[ERASE_SIZE - 2..] != [0x01, 0x02] {
+        defmt::panic!("unexpected");
+    }
+
+    defmt::unwrap!(flash.read(boundary, &mut buf));
+    info!("Second sector after straddling write starts with {=[u8]}", buf[0..4]);
+    if buf[0] != 0x03 || buf[1..].iter().any(|x| *x != 0xDA) {
+        defmt::panic!("unexpected");
+    }
+
     info!("Test OK");
     cortex_m::asm::bkpt();
 }